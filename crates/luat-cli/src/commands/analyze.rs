@@ -0,0 +1,532 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Template usage analysis (`luat analyze`).
+//!
+//! Builds the import graph of the project, starting from every file in the
+//! routes directory, and reports:
+//!
+//! - **Unreachable modules**: files in the lib directory that no route reaches
+//!   through `require()`.
+//! - **Unused imports**: `local X = require(...)` bindings and
+//!   `{#use X from "..."}` imports in templates that are never referenced.
+//! - **Unread props**: props passed to a component (`<Card title=...>`) that
+//!   the component never reads through `props.<name>`.
+//! - **Invalid HTML**: markup browsers would restructure, such as `<div>`
+//!   inside `<p>` (see [`luat::lint_html`]).
+//!
+//! Components that use `props` as a whole (e.g. `{...props}`) are assumed to
+//! read every prop.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use console::style;
+use luat::{
+    lint_html, parse_template, referenced_props, transform_ast, Attribute, ComponentDirs, FileSystemResolver, Node,
+};
+use regex::Regex;
+
+use crate::config::Config;
+
+/// A `require()` binding that is never used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnusedImport {
+    /// Template containing the binding.
+    pub file: String,
+    /// Local name of the binding.
+    pub name: String,
+    /// Required module name.
+    pub module: String,
+}
+
+/// A prop passed to a component that the component never reads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnreadProp {
+    /// Template passing the prop.
+    pub file: String,
+    /// Component file receiving the prop.
+    pub component: String,
+    /// Prop name.
+    pub prop: String,
+}
+
+/// An HTML validity problem in a template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidHtml {
+    /// Template containing the markup.
+    pub file: String,
+    /// What is wrong.
+    pub message: String,
+}
+
+/// Result of [`analyze`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnalysisReport {
+    /// Lib modules not reachable from any route, relative to the project root.
+    pub unreachable: Vec<String>,
+    /// Imported but unused components and modules.
+    pub unused_imports: Vec<UnusedImport>,
+    /// Props that are passed but never read.
+    pub unread_props: Vec<UnreadProp>,
+    /// Markup that is not valid HTML.
+    pub invalid_html: Vec<InvalidHtml>,
+}
+
+impl AnalysisReport {
+    /// Returns true if nothing was found.
+    pub fn is_empty(&self) -> bool {
+        self.unreachable.is_empty()
+            && self.unused_imports.is_empty()
+            && self.unread_props.is_empty()
+            && self.invalid_html.is_empty()
+    }
+}
+
+impl fmt::Display for AnalysisReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No issues found.");
+        }
+
+        if !self.unreachable.is_empty() {
+            writeln!(f, "Unreachable modules ({}):", self.unreachable.len())?;
+            for file in &self.unreachable {
+                writeln!(f, "  {}", file)?;
+            }
+        }
+        if !self.unused_imports.is_empty() {
+            writeln!(f, "Unused imports ({}):", self.unused_imports.len())?;
+            for import in &self.unused_imports {
+                writeln!(f, "  {}: {} = require(\"{}\")", import.file, import.name, import.module)?;
+            }
+        }
+        if !self.unread_props.is_empty() {
+            writeln!(f, "Unread props ({}):", self.unread_props.len())?;
+            for prop in &self.unread_props {
+                writeln!(f, "  {}: `{}` passed to {} but never read", prop.file, prop.prop, prop.component)?;
+            }
+        }
+        if !self.invalid_html.is_empty() {
+            writeln!(f, "Invalid HTML ({}):", self.invalid_html.len())?;
+            for invalid in &self.invalid_html {
+                writeln!(f, "  {}: {}", invalid.file, invalid.message)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A parsed project file.
+struct SourceFile {
+    source: String,
+    /// Local bindings from `local X = require("...")` and `{#use X from "..."}`:
+    /// (name, module, resolved file).
+    bindings: Vec<(String, String, Option<PathBuf>)>,
+    /// Resolved files of every `require()` call.
+    imports: Vec<PathBuf>,
+}
+
+/// Analyzes the project in `project_dir`.
+///
+/// Component tags found in `component_dirs` count as imports, as they do
+/// when the templates are compiled (see [`luat::auto_import`]).
+pub fn analyze(
+    project_dir: &Path,
+    routes_dir: &str,
+    lib_dir: &str,
+    component_dirs: &[String],
+) -> anyhow::Result<AnalysisReport> {
+    let routes_root = project_dir.join(routes_dir);
+    let lib_root = project_dir.join(lib_dir);
+
+    let mut routes = Vec::new();
+    collect_sources(&routes_root, &mut routes)?;
+    let mut libs = Vec::new();
+    collect_sources(&lib_root, &mut libs)?;
+
+    let require_re = Regex::new(r#"require\s*\(?\s*["']([^"']+)["']"#).expect("valid regex");
+    let binding_re =
+        Regex::new(r#"local\s+([A-Za-z_][A-Za-z0-9_]*)\s*=\s*require\s*\(?\s*["']([^"']+)["']"#).expect("valid regex");
+    let use_re = Regex::new(r#"\{#use\s+([A-Za-z_][A-Za-z0-9_]*)\s+from\s+["']([^"']+)["']\s*\}"#).expect("valid regex");
+
+    let dirs = ComponentDirs::new();
+    for dir in component_dirs {
+        dirs.add(dir);
+    }
+    let resolver = FileSystemResolver::new(&routes_root).with_lib_dir(&lib_root);
+
+    let mut files: BTreeMap<PathBuf, SourceFile> = BTreeMap::new();
+    for path in routes.iter().chain(libs.iter()) {
+        let source = fs::read_to_string(path)?;
+        let resolve = |module: &str| resolve_module(module, path, &routes_root, &lib_root);
+        let mut imports: Vec<PathBuf> = require_re
+            .captures_iter(&source)
+            .map(|c| c[1].to_string())
+            .chain(use_re.captures_iter(&source).map(|c| c[2].to_string()))
+            .filter_map(|module| resolve(&module))
+            .collect();
+        let mut bindings: Vec<(String, String, Option<PathBuf>)> = binding_re
+            .captures_iter(&source)
+            .chain(use_re.captures_iter(&source))
+            .map(|c| (c[1].to_string(), c[2].to_string(), resolve(&c[2])))
+            .collect();
+        if !component_dirs.is_empty() && path.extension().and_then(|e| e.to_str()) == Some("luat") {
+            let auto = auto_imports(&source, path, &dirs, &resolver, resolve);
+            imports.extend(auto.iter().filter_map(|(_, _, resolved)| resolved.clone()));
+            bindings.extend(auto);
+        }
+        files.insert(
+            path.clone(),
+            SourceFile {
+                source,
+                bindings,
+                imports,
+            },
+        );
+    }
+
+    let rel = |path: &Path| {
+        path.strip_prefix(project_dir)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    };
+
+    // Reachability from the route files
+    let mut reached: BTreeSet<PathBuf> = routes.iter().cloned().collect();
+    let mut queue: VecDeque<PathBuf> = routes.iter().cloned().collect();
+    while let Some(path) = queue.pop_front() {
+        if let Some(file) = files.get(&path) {
+            for import in &file.imports {
+                if reached.insert(import.clone()) {
+                    queue.push_back(import.clone());
+                }
+            }
+        }
+    }
+
+    let mut report = AnalysisReport {
+        unreachable: libs.iter().filter(|p| !reached.contains(*p)).map(|p| rel(p)).collect(),
+        ..AnalysisReport::default()
+    };
+
+    for (path, file) in &files {
+        if path.extension().and_then(|e| e.to_str()) != Some("luat") {
+            continue;
+        }
+        let mut ast = match parse_template(&file.source) {
+            Ok(ast) => ast,
+            Err(e) => {
+                tracing::warn!("Skipping {}: {}", rel(path), e);
+                continue;
+            }
+        };
+
+        let mut usages = Vec::new();
+        collect_component_usages(&ast.body, &mut usages);
+
+        ast.path = Some(path.to_string_lossy().into_owned());
+        if let Ok(ir) = transform_ast(ast) {
+            for issue in lint_html(&ir) {
+                report.invalid_html.push(InvalidHtml {
+                    file: rel(path),
+                    message: issue.message,
+                });
+            }
+        }
+
+        // Source without the bindings themselves, so module paths like
+        // `lib/components/Card` don't count as a use of `Card`
+        let without_uses = use_re.replace_all(&file.source, "");
+        let without_bindings = binding_re.replace_all(&without_uses, "");
+        let without_requires = require_re.replace_all(&without_bindings, "");
+
+        for (name, module, resolved) in &file.bindings {
+            let word = Regex::new(&format!(r"\b{}\b", regex::escape(name))).expect("valid regex");
+            if !word.is_match(&without_requires) {
+                report.unused_imports.push(UnusedImport {
+                    file: rel(path),
+                    name: name.clone(),
+                    module: module.clone(),
+                });
+                continue;
+            }
+
+            let Some(component) = resolved.as_ref().and_then(|r| files.get(r).map(|f| (r, f))) else {
+                continue;
+            };
+            let (component_path, component_file) = component;
+            if component_path.extension().and_then(|e| e.to_str()) != Some("luat")
+                || reads_all_props(&component_file.source)
+            {
+                continue;
+            }
+            let read: BTreeSet<String> = referenced_props(&component_file.source).into_iter().collect();
+
+            let mut unread = BTreeSet::new();
+            for (_, props) in usages.iter().filter(|(n, _)| n == name) {
+                for prop in props {
+                    if prop != "children" && !read.contains(prop) {
+                        unread.insert(prop.clone());
+                    }
+                }
+            }
+            for prop in unread {
+                report.unread_props.push(UnreadProp {
+                    file: rel(path),
+                    component: rel(component_path),
+                    prop,
+                });
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Runs `luat analyze` in the current directory.
+pub fn run() -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let project_dir = std::env::current_dir()?;
+    let report = analyze(
+        &project_dir,
+        &config.routing.routes_dir,
+        &config.routing.lib_dir,
+        &config.routing.component_dirs,
+    )?;
+
+    if report.is_empty() {
+        println!("{}", style("No issues found.").green());
+    } else {
+        print!("{}", report);
+    }
+    Ok(())
+}
+
+fn collect_sources(dir: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        if path.is_dir() {
+            if !file_name.starts_with('.') {
+                collect_sources(&path, files)?;
+            }
+        } else if matches!(path.extension().and_then(|e| e.to_str()), Some("luat") | Some("lua")) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(())
+}
+
+/// Resolves a `require()` name to a project file, mirroring the resolver's
+/// lookup order: `$lib/`/`lib/` aliases, relative paths, then the routes and
+/// lib directories.
+fn resolve_module(module: &str, importer: &Path, routes_root: &Path, lib_root: &Path) -> Option<PathBuf> {
+    let candidates: Vec<PathBuf> = if let Some(rest) = module.strip_prefix("$lib/").or_else(|| module.strip_prefix("lib/")) {
+        vec![lib_root.join(rest)]
+    } else if module.starts_with("./") || module.starts_with("../") {
+        vec![importer.parent()?.join(module)]
+    } else {
+        vec![routes_root.join(module), lib_root.join(module)]
+    };
+
+    candidates.into_iter().find_map(|base| {
+        let base = normalize(&base);
+        ["luat", "lua"]
+            .iter()
+            .map(|ext| PathBuf::from(format!("{}.{}", base.display(), ext)))
+            .chain(std::iter::once(base.clone()))
+            .chain(["index.luat", "index.lua"].iter().map(|index| base.join(index)))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+/// Returns the bindings `dirs` give the template at `path`, as the compiler
+/// imports them. A template with an ambiguous component gets none; the
+/// compiler reports it.
+fn auto_imports(
+    source: &str,
+    path: &Path,
+    dirs: &ComponentDirs,
+    resolver: &FileSystemResolver,
+    resolve: impl Fn(&str) -> Option<PathBuf>,
+) -> Vec<(String, String, Option<PathBuf>)> {
+    let Ok(ir) = parse_template(source).and_then(transform_ast) else {
+        return Vec::new();
+    };
+    let importer = path.to_string_lossy();
+    dirs.resolve(&ir, &importer, resolver)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|import| {
+            let resolved = resolve(&import.path);
+            (import.name, import.path, resolved)
+        })
+        .collect()
+}
+
+/// Removes `.` and `..` components without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// True if a component uses `props` other than through `props.<name>`.
+fn reads_all_props(source: &str) -> bool {
+    let re = Regex::new(r"\bprops\b\s*([.\[]?)").expect("valid regex");
+    re.captures_iter(source).any(|c| &c[1] != ".")
+}
+
+/// Collects `(component name, passed prop names)` for every component usage.
+///
+/// Usages with a spread attribute are skipped, since the passed props are
+/// not known statically.
+fn collect_component_usages(nodes: &[Node], usages: &mut Vec<(String, Vec<String>)>) {
+    for node in nodes {
+        match node {
+            Node::ComponentNode {
+                name,
+                attributes,
+                children,
+                ..
+            } => {
+                if !attributes.iter().any(|a| matches!(a, Attribute::Spread(_))) {
+                    let props = attributes
+                        .iter()
+                        .filter_map(|a| match a {
+                            Attribute::Named { name, .. } => Some(name.clone()),
+                            Attribute::Spread(_) => None,
+                        })
+                        .collect();
+                    usages.push((name.clone(), props));
+                }
+                collect_component_usages(children, usages);
+            }
+            Node::ElementNode { children, .. }
+            | Node::HtmlComment { children }
+            | Node::CustomBlock { children, .. }
+            | Node::Snippet { body: children, .. } => {
+                collect_component_usages(children, usages)
+            }
+            Node::IfBlock {
+                then_branch,
+                else_branch,
+                ..
+            }
+            | Node::SensitiveIfBlock {
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                collect_component_usages(then_branch, usages);
+                if let Some(branch) = else_branch {
+                    collect_component_usages(branch, usages);
+                }
+            }
+            Node::EachBlock { body, empty, .. } | Node::SensitiveEachBlock { body, empty, .. } => {
+                collect_component_usages(body, usages);
+                if let Some(empty) = empty {
+                    collect_component_usages(empty, usages);
+                }
+            }
+            Node::AwaitBlock {
+                pending,
+                then_branch,
+                catch_branch,
+                ..
+            } => {
+                collect_component_usages(pending, usages);
+                for branch in [then_branch, catch_branch].into_iter().flatten() {
+                    collect_component_usages(branch, usages);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, path: &str, content: &str) {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_analyze_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(
+            root,
+            "src/routes/+page.luat",
+            r#"{#use Badge from "lib/components/Badge"}
+<script>
+local Card = require("lib/components/Card")
+</script>
+<Card title="Hi" subtitle="unused">text</Card>
+<Icon name="star" />
+"#,
+        );
+        write(root, "src/lib/components/Card.luat", "<h2>{props.title}</h2>{@render children?()}");
+        write(root, "src/lib/components/Badge.luat", "<span>{props.label}</span>");
+        write(root, "src/lib/components/Old.luat", "<p>old <div>block</div></p>");
+        write(root, "src/lib/util.lua", "return {}");
+        write(root, "src/lib/icons/Icon.luat", "<i class={props.name}></i>");
+
+        let report = analyze(root, "src/routes", "src/lib", &["$lib/icons".to_string()]).unwrap();
+        assert_eq!(
+            report.unreachable,
+            vec!["src/lib/components/Old.luat", "src/lib/util.lua"]
+        );
+        assert_eq!(
+            report.unused_imports,
+            vec![UnusedImport {
+                file: "src/routes/+page.luat".to_string(),
+                name: "Badge".to_string(),
+                module: "lib/components/Badge".to_string(),
+            }]
+        );
+        assert_eq!(
+            report.unread_props,
+            vec![UnreadProp {
+                file: "src/routes/+page.luat".to_string(),
+                component: "src/lib/components/Card.luat".to_string(),
+                prop: "subtitle".to_string(),
+            }]
+        );
+        assert_eq!(
+            report.invalid_html,
+            vec![InvalidHtml {
+                file: "src/lib/components/Old.luat".to_string(),
+                message: "<div> cannot be inside <p>".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_reads_all_props() {
+        assert!(!reads_all_props("{props.title}"));
+        assert!(reads_all_props("<div {...props}>"));
+        assert!(reads_all_props("{props[key]}"));
+    }
+}
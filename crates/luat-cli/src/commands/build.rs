@@ -5,22 +5,40 @@
 //! Build command for compiling LUAT templates into a production bundle.
 
 use crate::config::Config;
+use crate::encryption::{self, BundleKey};
 use crate::router::Router as LuatRouter;
 use crate::toolchain::{build::BuildOrchestrator, prepare_build_tools};
 use console::style;
 use indicatif::{ProgressBar, ProgressStyle};
-use luat::{Engine, FileSystemResolver, ResourceResolver, parse_template};
+use luat::extensions::assets::{AssetManifest, ASSET_MANIFEST_FILE};
+use luat::module_state::PER_REQUEST_MODULES;
+use luat::{BundleInfo, Engine, FileSystemResolver, ModuleState, ResourceResolver, parse_template};
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
-use std::time::Instant;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// File under `dist/public` holding the scoped CSS of all templates.
+const COMPONENT_STYLESHEET: &str = "luat.css";
+
+/// Directory under `dist` holding the content collections.
+pub(crate) const CONTENT_DIR: &str = "content";
+
+/// Directory under `dist` holding the translation catalogs.
+pub(crate) const LOCALES_DIR: &str = "locales";
+
+/// Directory under `dist` holding the module chunks of a chunked bundle.
+const CHUNKS_DIR: &str = "chunks";
 
 /// Runs the build command to compile templates into a production bundle.
-pub async fn run(source: bool, output: &str) -> anyhow::Result<()> {
+pub async fn run(source: bool, output: &str, defines: &[String]) -> anyhow::Result<()> {
     let config = Config::load()?;
     let templates_dir = &config.dev.templates_dir;
     let working_dir = std::env::current_dir()?;
 
+    // Vendor git dependencies into the modules directory
+    crate::commands::package::sync_dependencies(&config)?;
+
     // Run frontend build if any tools are enabled
     let enabled_tools = config.frontend.get_enabled_tools();
     if !enabled_tools.is_empty() {
@@ -43,12 +61,7 @@ pub async fn run(source: bool, output: &str) -> anyhow::Result<()> {
         println!();
     }
 
-    // Use routes_dir for SvelteKit-style routing, templates_dir for simplified mode
-    let source_dir = if config.routing.simplified {
-        templates_dir.clone()
-    } else {
-        config.routing.routes_dir.clone()
-    };
+    let source_dir = project_source_dir(&config);
 
     println!(
         "{} {}",
@@ -56,16 +69,34 @@ pub async fn run(source: bool, output: &str) -> anyhow::Result<()> {
         source_dir
     );
 
+    // Resolve the encryption key before doing any work
+    let bundle_key = if config.build.encrypt {
+        if source {
+            anyhow::bail!("[build] encrypt applies to binary bundles; drop --source to build an encrypted bundle");
+        }
+        if config.build.chunked {
+            anyhow::bail!("[build] encrypt and chunked can't be combined; chunks are written as plain Lua");
+        }
+        Some(BundleKey::from_env()?.ok_or_else(|| {
+            anyhow::anyhow!(
+                "[build] encrypt is enabled but no key is set; set {} or {} (generate one with `openssl rand -hex 32`)",
+                encryption::KEY_ENV,
+                encryption::KEY_FILE_ENV
+            )
+        })?)
+    } else {
+        None
+    };
+
     // Create output directory
     fs::create_dir_all(output)?;
 
     // Create engine
-    let routes_root = working_dir.join(&source_dir);
-    let lib_root = working_dir.join(&config.routing.lib_dir);
-    let resolver = FileSystemResolver::new(&routes_root).with_lib_dir(&lib_root);
-    let mut engine = Engine::with_memory_cache(resolver, 100)?;
-    // Set root path for readable error messages (show relative paths)
-    engine.set_root_path(&working_dir);
+    let engine = project_engine(&config, &working_dir, &source_dir)?;
+    for define in defines {
+        engine.defines().parse(define)?;
+        println!("{} {}", style("Define:").cyan(), define);
+    }
 
     // Discover routes for SvelteKit-style routing
     let routes_dir = working_dir.join(&source_dir);
@@ -75,65 +106,8 @@ pub async fn run(source: bool, output: &str) -> anyhow::Result<()> {
         None
     };
 
-    // Collect template files (.luat) - will be compiled
-    let mut sources = Vec::new();
-    // Collect server files (.lua) - will be stored as raw source
-    let mut server_sources: Vec<(String, String)> = Vec::new();
-    let mut source_paths: Vec<(String, String, bool)> = Vec::new(); // (module_key, abs_path, is_template)
-    let mut path_map: HashMap<String, String> = HashMap::new(); // canonical path -> module_key
-
-    // Collect all .luat template files
-    let pattern = format!("{}/**/*.luat", source_dir);
-    for path in (glob::glob(&pattern)?).flatten() {
-        let relative = path.strip_prefix(&source_dir)?;
-        let content = fs::read_to_string(&path)?;
-        let key = relative.to_string_lossy().to_string();
-        let abs = fs::canonicalize(&path)?;
-        path_map.insert(abs.to_string_lossy().to_string(), key.clone());
-        source_paths.push((key.clone(), abs.to_string_lossy().to_string(), true));
-        sources.push((key, content));
-    }
-
-    // Collect all .lua files - server files go to server_sources, lib files to sources
-    let lua_pattern = format!("{}/**/*.lua", source_dir);
-    for path in glob::glob(&lua_pattern)?.flatten() {
-        let relative = path.strip_prefix(&source_dir)?;
-        let content = fs::read_to_string(&path)?;
-        let rel_str = relative.to_string_lossy().to_string();
-        // Server files are stored as raw source (not compiled)
-        let abs = fs::canonicalize(&path)?;
-        path_map.insert(abs.to_string_lossy().to_string(), rel_str.clone());
-        source_paths.push((rel_str.clone(), abs.to_string_lossy().to_string(), false));
-        server_sources.push((rel_str, content));
-    }
-
-    // Also collect lib directory files
-    let lib_dir = Path::new(&config.routing.lib_dir);
-    if lib_dir.exists() {
-        // Collect .lua files from lib - these go to server_sources (raw Lua, not templates)
-        let lib_lua_pattern = format!("{}/**/*.lua", lib_dir.display());
-        for path in glob::glob(&lib_lua_pattern)?.flatten() {
-            let relative = path.strip_prefix(lib_dir)?;
-            let content = fs::read_to_string(&path)?;
-            // Store lib .lua files as server sources (executed as raw Lua)
-            let key = format!("lib/{}", relative.to_string_lossy());
-            let abs = fs::canonicalize(&path)?;
-            path_map.insert(abs.to_string_lossy().to_string(), key.clone());
-            source_paths.push((key.clone(), abs.to_string_lossy().to_string(), false));
-            server_sources.push((key, content));
-        }
-        // Collect .luat files from lib - these are templates
-        let lib_luat_pattern = format!("{}/**/*.luat", lib_dir.display());
-        for path in (glob::glob(&lib_luat_pattern)?).flatten() {
-            let relative = path.strip_prefix(lib_dir)?;
-            let content = fs::read_to_string(&path)?;
-            let key = format!("lib/{}", relative.to_string_lossy());
-            let abs = fs::canonicalize(&path)?;
-            path_map.insert(abs.to_string_lossy().to_string(), key.clone());
-            source_paths.push((key.clone(), abs.to_string_lossy().to_string(), true));
-            sources.push((key, content));
-        }
-    }
+    let ProjectSources { sources, server_sources, source_paths, path_map } =
+        collect_project_sources(&config, &source_dir)?;
 
     if sources.is_empty() {
         println!("No templates found in {}", templates_dir);
@@ -159,11 +133,19 @@ pub async fn run(source: bool, output: &str) -> anyhow::Result<()> {
     );
     pb.enable_steady_tick(std::time::Duration::from_millis(100));
 
+    // Scoped CSS of the templates' <style> blocks
+    let stylesheet = engine.stylesheet(&sources)?;
+
     let start_compile = Instant::now();
     let pb_clone = pb.clone();
-    let (mut bundle, source_map) = engine.bundle_sources(sources, move |current, _total| {
-        pb_clone.set_position(current as u64);
-    })?;
+    let progress = move |current: usize, _total: usize| pb_clone.set_position(current as u64);
+    let (mut bundle, source_map, chunks) = if config.build.chunked {
+        let chunked = engine.bundle_sources_chunked(sources, CHUNKS_DIR, progress)?;
+        (chunked.bundle, luat::sourcemap::BundleSourceMap::new(), chunked.chunks)
+    } else {
+        let (bundle, source_map) = engine.bundle_sources(sources, progress)?;
+        (bundle, source_map, Vec::new())
+    };
     pb.finish_and_clear();
     let compile_time = start_compile.elapsed();
 
@@ -212,6 +194,16 @@ pub async fn run(source: bool, output: &str) -> anyhow::Result<()> {
         );
     }
 
+    // Stamp engine version, content hash and build time for `luat serve`
+    let info = BundleInfo::new(&bundle, build_timestamp());
+    bundle = info.stamp(&bundle);
+    println!(
+        "{} luat {}, {}",
+        style("Stamped bundle:").cyan(),
+        info.engine_version,
+        info.content_hash
+    );
+
     // Write output
     let output_path = Path::new(output);
     if source {
@@ -233,11 +225,39 @@ pub async fn run(source: bool, output: &str) -> anyhow::Result<()> {
             }
         };
         let output_file = output_path.join("bundle.bin");
-        fs::write(&output_file, &binary_bundle)?;
+        match bundle_key {
+            Some(ref key) => {
+                fs::write(&output_file, encryption::encrypt(&binary_bundle, key)?)?;
+                println!(
+                    "{} {}",
+                    style("Written encrypted binary bundle to:").cyan(),
+                    output_file.display()
+                );
+            }
+            None => {
+                fs::write(&output_file, &binary_bundle)?;
+                println!(
+                    "{} {}",
+                    style("Written binary bundle to:").cyan(),
+                    output_file.display()
+                );
+            }
+        }
+    }
+
+    // Module chunks, replacing those of the previous build
+    let chunks_dir = output_path.join(CHUNKS_DIR);
+    let _ = fs::remove_dir_all(&chunks_dir);
+    if !chunks.is_empty() {
+        fs::create_dir_all(&chunks_dir)?;
+        for (path, code) in &chunks {
+            fs::write(output_path.join(path), code)?;
+        }
         println!(
-            "{} {}",
-            style("Written binary bundle to:").cyan(),
-            output_file.display()
+            "{} {} module chunk(s) to {}",
+            style("Written").green(),
+            chunks.len(),
+            chunks_dir.display()
         );
     }
 
@@ -255,6 +275,22 @@ pub async fn run(source: bool, output: &str) -> anyhow::Result<()> {
         );
     }
 
+    // Write the component stylesheet
+    let stylesheet_file = output_path.join("public").join(COMPONENT_STYLESHEET);
+    if stylesheet.is_empty() {
+        // Don't serve styles of templates that no longer have them
+        let _ = fs::remove_file(&stylesheet_file);
+    } else {
+        fs::create_dir_all(output_path.join("public"))?;
+        fs::write(&stylesheet_file, &stylesheet)?;
+        println!(
+            "{} {} (link /public/{})",
+            style("Written component styles to:").cyan(),
+            stylesheet_file.display(),
+            COMPONENT_STYLESHEET
+        );
+    }
+
     // Copy static directory
     let static_dir = Path::new(&config.routing.static_dir);
     if static_dir.exists() {
@@ -268,6 +304,53 @@ pub async fn run(source: bool, output: &str) -> anyhow::Result<()> {
         );
     }
 
+    // Compute Subresource Integrity hashes for built assets
+    if config.build.sri {
+        let mut manifest = AssetManifest::default();
+        hash_assets(&mut manifest, &output_path.join("public"), "/public")?;
+        hash_assets(&mut manifest, &output_path.join("static"), "/static")?;
+        fs::write(
+            output_path.join(ASSET_MANIFEST_FILE),
+            serde_json::to_string_pretty(&manifest)?,
+        )?;
+        println!(
+            "{} {} asset(s) with SRI hashes",
+            style("Hashed").green(),
+            manifest.assets.len()
+        );
+    } else {
+        // Don't leave hashes from an earlier build pointing at changed files
+        let _ = fs::remove_file(output_path.join(ASSET_MANIFEST_FILE));
+    }
+
+    // Copy content collections, which the production server reads from dist
+    let content_dir = Path::new(&config.routing.content_dir);
+    let dest_content = output_path.join(CONTENT_DIR);
+    let _ = fs::remove_dir_all(&dest_content);
+    if content_dir.exists() {
+        copy_dir_recursive(content_dir, &dest_content)?;
+        println!(
+            "{} {} -> {}",
+            style("Copied").green(),
+            content_dir.display(),
+            dest_content.display()
+        );
+    }
+
+    // Copy translation catalogs, which the production server reads from dist
+    let locales_dir = Path::new(&config.i18n.dir);
+    let dest_locales = output_path.join(LOCALES_DIR);
+    let _ = fs::remove_dir_all(&dest_locales);
+    if locales_dir.exists() {
+        copy_dir_recursive(locales_dir, &dest_locales)?;
+        println!(
+            "{} {} -> {}",
+            style("Copied").green(),
+            locales_dir.display(),
+            dest_locales.display()
+        );
+    }
+
     // Copy app.html if it exists
     let app_html_path = Path::new(&config.routing.app_html);
     if app_html_path.exists() {
@@ -291,8 +374,211 @@ pub async fn run(source: bool, output: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Sources of a project, collected for bundling.
+pub(crate) struct ProjectSources {
+    /// Templates (`.luat`), compiled into the bundle.
+    pub sources: Vec<(String, String)>,
+    /// Lua files, stored as raw source for execution at runtime.
+    pub server_sources: Vec<(String, String)>,
+    /// (module_key, abs_path, is_template) of every source.
+    pub source_paths: Vec<(String, String, bool)>,
+    /// Canonical path -> module_key.
+    pub path_map: HashMap<String, String>,
+}
+
+/// Returns the directory templates are collected from: the routes dir for
+/// SvelteKit-style routing, the templates dir in simplified mode.
+pub(crate) fn project_source_dir(config: &Config) -> String {
+    if config.routing.simplified {
+        config.dev.templates_dir.clone()
+    } else {
+        config.routing.routes_dir.clone()
+    }
+}
+
+/// Creates the engine that compiles the templates under `source_dir`.
+pub(crate) fn project_engine(
+    config: &Config,
+    working_dir: &Path,
+    source_dir: &str,
+) -> anyhow::Result<Engine<FileSystemResolver>> {
+    let routes_root = working_dir.join(source_dir);
+    let lib_root = working_dir.join(&config.routing.lib_dir);
+    let resolver = config.routing.source_roots.iter().fold(
+        FileSystemResolver::new(&routes_root)
+            .with_lib_dir(&lib_root)
+            .with_modules_dir(working_dir.join(&config.routing.modules_dir))
+            .with_content_dir(working_dir.join(&config.routing.content_dir)),
+        |resolver, root| resolver.with_source_root(working_dir.join(root)),
+    );
+    let mut engine = Engine::with_memory_cache(resolver, 100)?;
+    // Set root path for readable error messages (show relative paths)
+    engine.set_root_path(working_dir);
+    engine.set_collapse_whitespace(config.build.collapse_whitespace);
+    config.apply_component_dirs(&engine);
+    config.apply_defines(&engine)?;
+    Ok(engine)
+}
+
+/// Collects the templates and Lua files of the project: `source_dir`, the
+/// lib dir, shared source roots and installed component packages.
+pub(crate) fn collect_project_sources(config: &Config, source_dir: &str) -> anyhow::Result<ProjectSources> {
+    // Collect template files (.luat) - will be compiled
+    let mut sources = Vec::new();
+    // Collect server files (.lua) - will be stored as raw source
+    let mut server_sources: Vec<(String, String)> = Vec::new();
+    let mut source_paths: Vec<(String, String, bool)> = Vec::new(); // (module_key, abs_path, is_template)
+    let mut path_map: HashMap<String, String> = HashMap::new(); // canonical path -> module_key
+
+    // Collect all .luat template files
+    let pattern = format!("{}/**/*.luat", source_dir);
+    for path in (glob::glob(&pattern)?).flatten() {
+        let relative = path.strip_prefix(source_dir)?;
+        let content = fs::read_to_string(&path)?;
+        let key = relative.to_string_lossy().to_string();
+        let abs = fs::canonicalize(&path)?;
+        path_map.insert(abs.to_string_lossy().to_string(), key.clone());
+        source_paths.push((key.clone(), abs.to_string_lossy().to_string(), true));
+        sources.push((key, content));
+    }
+
+    // Collect all .lua files - server files go to server_sources, lib files to sources
+    let lua_pattern = format!("{}/**/*.lua", source_dir);
+    for path in glob::glob(&lua_pattern)?.flatten() {
+        let relative = path.strip_prefix(source_dir)?;
+        let content = fs::read_to_string(&path)?;
+        let rel_str = relative.to_string_lossy().to_string();
+        // Server files are stored as raw source (not compiled)
+        let abs = fs::canonicalize(&path)?;
+        path_map.insert(abs.to_string_lossy().to_string(), rel_str.clone());
+        source_paths.push((rel_str.clone(), abs.to_string_lossy().to_string(), false));
+        server_sources.push((rel_str, content));
+    }
+
+    // Also collect lib directory files
+    let lib_dir = Path::new(&config.routing.lib_dir);
+    if lib_dir.exists() {
+        // Collect .lua files from lib - these go to server_sources (raw Lua, not templates)
+        let lib_lua_pattern = format!("{}/**/*.lua", lib_dir.display());
+        for path in glob::glob(&lib_lua_pattern)?.flatten() {
+            let relative = path.strip_prefix(lib_dir)?;
+            let content = fs::read_to_string(&path)?;
+            // Store lib .lua files as server sources (executed as raw Lua)
+            let key = format!("lib/{}", relative.to_string_lossy());
+            let abs = fs::canonicalize(&path)?;
+            path_map.insert(abs.to_string_lossy().to_string(), key.clone());
+            source_paths.push((key.clone(), abs.to_string_lossy().to_string(), false));
+            server_sources.push((key, content));
+        }
+        // Collect .luat files from lib - these are templates
+        let lib_luat_pattern = format!("{}/**/*.luat", lib_dir.display());
+        for path in (glob::glob(&lib_luat_pattern)?).flatten() {
+            let relative = path.strip_prefix(lib_dir)?;
+            let content = fs::read_to_string(&path)?;
+            let key = format!("lib/{}", relative.to_string_lossy());
+            let abs = fs::canonicalize(&path)?;
+            path_map.insert(abs.to_string_lossy().to_string(), key.clone());
+            source_paths.push((key.clone(), abs.to_string_lossy().to_string(), true));
+            sources.push((key, content));
+        }
+    }
+
+    // Collect shared source roots, keyed by the root as configured
+    // (`@../shared/components/...`), so roots with the same directory name
+    // don't collide
+    for root in &config.routing.source_roots {
+        let root_dir = Path::new(root);
+        if !root_dir.exists() {
+            eprintln!(
+                "{} Source root '{}' does not exist",
+                style("Warning:").yellow(),
+                root
+            );
+            continue;
+        }
+        let root_key = root.replace('\\', "/");
+        let root_key = root_key.trim_start_matches("./").trim_end_matches('/');
+        collect_extra_sources(
+            root_dir,
+            &format!("@{}", root_key),
+            &mut sources,
+            &mut server_sources,
+            &mut source_paths,
+            &mut path_map,
+        )?;
+    }
+
+    // Collect installed component packages (luat add), keyed by the
+    // configured modules directory
+    let modules_dir = Path::new(&config.routing.modules_dir);
+    if modules_dir.exists() {
+        let modules_key = config.routing.modules_dir.replace('\\', "/");
+        collect_extra_sources(
+            modules_dir,
+            modules_key.trim_start_matches("./").trim_end_matches('/'),
+            &mut sources,
+            &mut server_sources,
+            &mut source_paths,
+            &mut path_map,
+        )?;
+    }
+
+    Ok(ProjectSources { sources, server_sources, source_paths, path_map })
+}
+
+/// Collect `.luat` templates and `.lua` sources from a directory outside the
+/// routes and lib dirs, keyed as `<key_prefix>/<relative path>`.
+///
+/// Hidden directories (e.g. staging dirs left by `luat add`) are skipped.
+fn collect_extra_sources(
+    dir: &Path,
+    key_prefix: &str,
+    sources: &mut Vec<(String, String)>,
+    server_sources: &mut Vec<(String, String)>,
+    source_paths: &mut Vec<(String, String, bool)>,
+    path_map: &mut HashMap<String, String>,
+) -> anyhow::Result<()> {
+    for ext in ["luat", "lua"] {
+        let pattern = format!("{}/**/*.{}", dir.display(), ext);
+        for path in glob::glob(&pattern)?.flatten() {
+            let relative = path.strip_prefix(dir)?;
+            if relative
+                .components()
+                .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+            {
+                continue;
+            }
+            let content = fs::read_to_string(&path)?;
+            let key = format!("{}/{}", key_prefix, relative.to_string_lossy());
+            let abs = fs::canonicalize(&path)?;
+            let is_template = ext == "luat";
+            path_map.insert(abs.to_string_lossy().to_string(), key.clone());
+            source_paths.push((key.clone(), abs.to_string_lossy().to_string(), is_template));
+            if is_template {
+                sources.push((key, content));
+            } else {
+                server_sources.push((key, content));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Build time for the bundle stamp; honors `SOURCE_DATE_EPOCH` for reproducible builds.
+fn build_timestamp() -> u64 {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default()
+        })
+}
+
 /// Recursively copy a directory
-fn copy_dir_recursive(src: &Path, dst: &Path) -> anyhow::Result<()> {
+pub(crate) fn copy_dir_recursive(src: &Path, dst: &Path) -> anyhow::Result<()> {
     if !dst.exists() {
         fs::create_dir_all(dst)?;
     }
@@ -312,6 +598,24 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Add SRI hashes for every CSS and JS file under `dir`, served at `url_prefix`
+fn hash_assets(manifest: &mut AssetManifest, dir: &Path, url_prefix: &str) -> anyhow::Result<()> {
+    if !dir.exists() {
+        return Ok(());
+    }
+
+    for ext in ["css", "js"] {
+        let pattern = format!("{}/**/*.{}", dir.display(), ext);
+        for path in glob::glob(&pattern)?.flatten() {
+            let relative = path.strip_prefix(dir)?;
+            let url = format!("{}/{}", url_prefix, relative.to_string_lossy().replace('\\', "/"));
+            manifest.insert(url, &fs::read(&path)?);
+        }
+    }
+
+    Ok(())
+}
+
 /// Generate Lua table with server file sources (stored as strings for runtime execution)
 fn generate_server_sources_lua(server_sources: &[(String, String)]) -> String {
     let mut lua = String::new();
@@ -328,6 +632,15 @@ fn generate_server_sources_lua(server_sources: &[(String, String)]) -> String {
 
     lua.push_str("}\n\n");
 
+    // Server modules that declare per-request state are unloaded before each
+    // request; the bundle has the table the engine reads them from as a local
+    for (name, source) in server_sources {
+        if ModuleState::of_source(source) == ModuleState::PerRequest {
+            lua.push_str(&format!("{}[\"{}\"] = true\n", PER_REQUEST_MODULES, name.replace('\\', "/")));
+        }
+    }
+    lua.push('\n');
+
     lua
 }
 
@@ -395,6 +708,21 @@ fn build_require_map(
                     continue;
                 }
             };
+            // Components imported from `[routing] component_dirs` have no
+            // require in the source
+            let auto_imports: Vec<String> = if engine.component_dirs().is_empty() {
+                Vec::new()
+            } else {
+                match luat::transform_ast(ast.clone())
+                    .and_then(|ir| engine.component_dirs().resolve(&ir, abs_path, engine.resolver()))
+                {
+                    Ok(imports) => imports.into_iter().map(|import| import.path).collect(),
+                    Err(e) => {
+                        eprintln!("{} {}: {}", style("Warning:").yellow(), module_key, e);
+                        Vec::new()
+                    }
+                }
+            };
             let mut script_content = String::new();
             if let Some(script) = ast.module_script {
                 script_content.push_str(&script.content);
@@ -406,7 +734,7 @@ fn build_require_map(
             if !script_content.is_empty() {
                 warn_non_literal_requires(&script_content, module_key);
             }
-            ast.imports
+            ast.imports.into_iter().chain(auto_imports).collect()
         } else {
             warn_non_literal_requires(&source, module_key);
             extract_requires(&source)
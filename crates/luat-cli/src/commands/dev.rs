@@ -13,14 +13,54 @@ use tokio::sync::broadcast;
 
 use crate::config::Config;
 use crate::server::http::create_server;
+use crate::server::listen::UnixSocketOptions;
 use crate::toolchain::{build::BuildOrchestrator, prepare_build_tools, Tool};
 use crate::watcher::FileWatcher;
 
+/// Options for `luat dev`.
+#[derive(Debug, Clone, Default)]
+pub struct DevOptions {
+    /// Host to bind to.
+    pub host: String,
+    /// Port to bind to.
+    pub port: u16,
+    /// Listen on a Unix domain socket instead of `host`/`port`.
+    pub uds: Option<UnixSocketOptions>,
+    /// Show build tool output.
+    pub verbose: bool,
+    /// Suppress status output.
+    pub quiet: bool,
+    /// Page to announce at startup (e.g. a component preview URL path).
+    pub open_path: Option<String>,
+    /// Extra directories to watch for changes, besides `src/` and source roots.
+    pub watch: Vec<PathBuf>,
+    /// Render deterministically regardless of `[dev] deterministic`.
+    pub deterministic: bool,
+    /// Build-time defines (`NAME=VALUE`), added to `[build] defines`.
+    pub defines: Vec<String>,
+}
+
 /// Runs the development server with hot reload.
-pub async fn run(host: &str, port: u16, verbose: bool, quiet: bool) -> anyhow::Result<()> {
-    let config = Config::load()?;
+pub async fn run(options: DevOptions) -> anyhow::Result<()> {
+    let DevOptions {
+        host,
+        port,
+        uds,
+        verbose,
+        quiet,
+        open_path,
+        watch,
+        deterministic,
+        defines,
+    } = options;
+    let mut config = Config::load()?;
+    config.dev.deterministic |= deterministic;
+    config.build.defines.extend(defines);
     let working_dir = std::env::current_dir()?;
 
+    // Vendor git dependencies into the modules directory
+    crate::commands::package::sync_dependencies(&config)?;
+
     // Prepare frontend build tools if any are enabled
     let enabled_tools = config.frontend.get_enabled_tools();
     if !enabled_tools.is_empty() {
@@ -147,16 +187,39 @@ pub async fn run(host: &str, port: u16, verbose: bool, quiet: bool) -> anyhow::R
         }
     })?;
 
+    // Shared source roots live outside src/ and need their own watch
+    for root in &config.routing.source_roots {
+        let root_dir = working_dir.join(root);
+        if root_dir.exists() {
+            watcher.watch_path(&root_dir)?;
+        }
+    }
+
+    for dir in &watch {
+        watcher.watch_path(dir)?;
+    }
+
     watcher.start()?;
 
     // Start HTTP server
     let addr = format!("{}:{}", host, port);
     if !quiet {
+        let location = match uds {
+            Some(ref uds) => format!("unix:{}", uds.path.display()),
+            None => format!("http://{}", addr),
+        };
         println!(
             "{} {}",
             style("Server:").cyan(),
-            style(format!("http://{}", addr)).green().bold()
+            style(&location).green().bold()
         );
+        if let Some(ref path) = open_path {
+            let page = match uds {
+                Some(_) => path.clone(),
+                None => format!("{}{}", location, path),
+            };
+            println!("{} {}", style("Open:").cyan(), style(page).green().bold());
+        }
         println!(
             "{} {}",
             style("Status:").cyan(),
@@ -165,7 +228,7 @@ pub async fn run(host: &str, port: u16, verbose: bool, quiet: bool) -> anyhow::R
         println!();
     }
 
-    create_server(&addr, &config, reload_tx).await?;
+    create_server(&addr, uds.as_ref(), &config, reload_tx).await?;
 
     Ok(())
 }
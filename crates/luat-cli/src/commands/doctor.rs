@@ -0,0 +1,583 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Project and environment diagnostics (`luat doctor`).
+//!
+//! Checks the project in the current directory and prints an actionable fix
+//! for every problem found:
+//!
+//! - **Config**: `luat.toml` parses, has no unknown sections, and points at
+//!   existing directories.
+//! - **Routes**: no two route directories map to conflicting URL patterns.
+//! - **Layouts**: every `+layout.luat` wraps at least one `+page.luat`.
+//! - **Toolchain**: enabled frontend tools are cached, executable and intact.
+//! - **KV**: the SQLite store of `luat dev` and `luat serve` can be opened.
+//! - **Port**: the dev server port is free.
+//! - **Lua**: sources don't use Lua 5.1/LuaJIT APIs missing from Lua 5.4.
+//!
+//! The command exits with an error when any check fails, so it can run in CI.
+
+use std::fmt;
+use std::fs;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+
+use console::style;
+use regex::Regex;
+
+use crate::config::{Config, KvBackend};
+use crate::router::Router;
+use crate::toolchain::{self, Tool};
+
+/// Outcome of a single check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// Nothing to do.
+    Ok,
+    /// Works, but probably not as intended.
+    Warning,
+    /// Will fail at runtime.
+    Error,
+}
+
+/// A single diagnostic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    /// Category, e.g. `config` or `routes`.
+    pub category: &'static str,
+    /// Outcome.
+    pub status: Status,
+    /// What was found.
+    pub message: String,
+    /// How to fix it.
+    pub fix: Option<String>,
+}
+
+/// Result of [`diagnose`].
+#[derive(Debug, Clone, Default)]
+pub struct DoctorReport {
+    /// All checks in the order they ran.
+    pub checks: Vec<Check>,
+}
+
+impl DoctorReport {
+    fn push(&mut self, category: &'static str, status: Status, message: impl Into<String>, fix: Option<String>) {
+        self.checks.push(Check {
+            category,
+            status,
+            message: message.into(),
+            fix,
+        });
+    }
+
+    fn ok(&mut self, category: &'static str, message: impl Into<String>) {
+        self.push(category, Status::Ok, message, None);
+    }
+
+    fn warn(&mut self, category: &'static str, message: impl Into<String>, fix: impl Into<String>) {
+        self.push(category, Status::Warning, message, Some(fix.into()));
+    }
+
+    fn error(&mut self, category: &'static str, message: impl Into<String>, fix: impl Into<String>) {
+        self.push(category, Status::Error, message, Some(fix.into()));
+    }
+
+    /// Number of failed checks.
+    pub fn errors(&self) -> usize {
+        self.checks.iter().filter(|c| c.status == Status::Error).count()
+    }
+
+    /// Number of warnings.
+    pub fn warnings(&self) -> usize {
+        self.checks.iter().filter(|c| c.status == Status::Warning).count()
+    }
+}
+
+impl fmt::Display for DoctorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let mark = match check.status {
+                Status::Ok => style("✓").green(),
+                Status::Warning => style("!").yellow(),
+                Status::Error => style("✗").red(),
+            };
+            writeln!(f, "{} {:<10} {}", mark, style(check.category).dim(), check.message)?;
+            if let Some(fix) = &check.fix {
+                writeln!(f, "  {:<10} {} {}", "", style("fix:").cyan(), fix)?;
+            }
+        }
+        writeln!(f)?;
+        write!(f, "{} error(s), {} warning(s)", self.errors(), self.warnings())
+    }
+}
+
+/// Lua 5.1/LuaJIT APIs that don't exist in Lua 5.4, with their replacement.
+const LUA51_APIS: [(&str, &str, &str); 12] = [
+    ("setfenv", r"\bsetfenv\s*\(", "pass an environment table to `load` instead"),
+    ("getfenv", r"\bgetfenv\s*\(", "use `_ENV` instead"),
+    ("loadstring", r"\bloadstring\s*\(", "use `load` instead"),
+    ("table.getn", r"\btable\.getn\s*\(", "use the `#` operator instead"),
+    ("table.setn", r"\btable\.setn\s*\(", "remove the call; tables have no explicit size"),
+    ("math.pow", r"\bmath\.pow\s*\(", "use the `^` operator instead"),
+    ("math.mod", r"\bmath\.mod\s*\(", "use `math.fmod` or `%` instead"),
+    ("string.gfind", r"\bstring\.gfind\s*\(", "use `string.gmatch` instead"),
+    ("module", r"(?m)^\s*module\s*\(", "return a table from the module instead"),
+    ("unpack", r"(?:^|[^.\w])unpack\s*\(", "use `table.unpack` instead"),
+    ("bit32/bit", r"\b(?:bit32|bit)\.\w+\s*\(", "use the native bitwise operators (`&`, `|`, `~`, `<<`, `>>`)"),
+    ("jit/ffi", r#"\bjit\.\w+|require\s*\(?\s*["']ffi["']"#, "remove LuaJIT-specific code; luat runs Lua 5.4"),
+];
+
+/// Runs the file-based checks for the project in `project_dir`.
+///
+/// Toolchain and port checks depend on the machine rather than the project
+/// and are run separately by [`run`].
+pub fn diagnose(project_dir: &Path) -> DoctorReport {
+    let mut report = DoctorReport::default();
+    let Some(config) = check_config(project_dir, &mut report) else {
+        return report;
+    };
+
+    let routes_dir = project_dir.join(&config.routing.routes_dir);
+    if routes_dir.is_dir() {
+        check_routes(&routes_dir, &mut report);
+        check_layouts(project_dir, &routes_dir, &mut report);
+    }
+    check_kv(project_dir, &config, &mut report);
+    check_lua_version(project_dir, &config, &mut report);
+    report
+}
+
+/// Parses `luat.toml` and checks the paths it refers to.
+fn check_config(project_dir: &Path, report: &mut DoctorReport) -> Option<Config> {
+    const CATEGORY: &str = "config";
+    let config_path = project_dir.join("luat.toml");
+
+    if !config_path.exists() {
+        report.warn(CATEGORY, "No luat.toml found, using defaults", "run `luat init` to create a project");
+    } else {
+        let content = match fs::read_to_string(&config_path) {
+            Ok(content) => content,
+            Err(e) => {
+                report.error(CATEGORY, format!("Cannot read luat.toml: {}", e), "check the file permissions");
+                return None;
+            }
+        };
+        let table: toml::Table = match toml::from_str(&content) {
+            Ok(table) => table,
+            Err(e) => {
+                report.error(
+                    CATEGORY,
+                    format!("luat.toml is not valid TOML: {}", e.message()),
+                    "fix the syntax error in luat.toml",
+                );
+                return None;
+            }
+        };
+        let known_sections = Config::sections();
+        for section in table.keys().filter(|k| !known_sections.contains(&k.as_str())) {
+            report.warn(
+                CATEGORY,
+                format!("Unknown section `[{}]` in luat.toml is ignored", section),
+                format!("remove it or rename it to one of: {}", known_sections.join(", ")),
+            );
+        }
+    }
+
+    let config = match Config::load_from(project_dir) {
+        Ok(config) => config,
+        Err(e) => {
+            report.error(CATEGORY, format!("Invalid luat.toml: {}", e), "fix the value reported above");
+            return None;
+        }
+    };
+    if config_path.exists() {
+        report.ok(CATEGORY, "luat.toml is valid");
+    }
+
+    let routes_dir = project_dir.join(&config.routing.routes_dir);
+    if !routes_dir.is_dir() {
+        report.error(
+            CATEGORY,
+            format!("Routes directory `{}` does not exist", config.routing.routes_dir),
+            "create it or set `routing.routes_dir` in luat.toml",
+        );
+    }
+    if !project_dir.join(&config.routing.app_html).is_file() {
+        report.warn(
+            CATEGORY,
+            format!("`{}` not found, the built-in page shell is used", config.routing.app_html),
+            "create it or set `routing.app_html` in luat.toml",
+        );
+    }
+    for tool in &config.frontend.enabled {
+        if tool.parse::<Tool>().is_err() {
+            report.warn(
+                CATEGORY,
+                format!("Unknown frontend tool `{}` is ignored", tool),
+                "use one of: sass, tailwind, typescript",
+            );
+        }
+    }
+
+    Some(config)
+}
+
+/// Reports route directories whose URL patterns conflict.
+fn check_routes(routes_dir: &Path, report: &mut DoctorReport) {
+    const CATEGORY: &str = "routes";
+    let router = match Router::discover(routes_dir) {
+        Ok(router) => router,
+        Err(e) => {
+            report.error(CATEGORY, format!("Route discovery failed: {}", e), "check the route directory names");
+            return;
+        }
+    };
+
+    let mut matcher = matchit::Router::new();
+    let mut conflicts = 0;
+    for route in router.routes() {
+        if let Err(e) = matcher.insert(route.pattern.as_str(), route.fs_path.clone()) {
+            conflicts += 1;
+            let detail = match &e {
+                matchit::InsertError::Conflict { with } => {
+                    let other = router
+                        .routes()
+                        .iter()
+                        .find(|r| &r.pattern == with)
+                        .map(|r| display_dir(&r.fs_path))
+                        .unwrap_or_else(|| with.clone());
+                    format!("conflicts with `{}`", other)
+                }
+                other => other.to_string(),
+            };
+            report.error(
+                CATEGORY,
+                format!("Route `{}` ({}) {}", display_dir(&route.fs_path), route.pattern, detail),
+                "rename or merge one of the route directories",
+            );
+        }
+    }
+    if conflicts == 0 {
+        report.ok(CATEGORY, format!("{} route(s), no conflicts", router.routes().len()));
+    }
+}
+
+/// Reports layouts that don't wrap any page.
+fn check_layouts(project_dir: &Path, routes_dir: &Path, report: &mut DoctorReport) {
+    const CATEGORY: &str = "layouts";
+    let mut layouts = Vec::new();
+    find_files(routes_dir, &|name| name == "+layout.luat", &mut layouts);
+
+    let mut orphans = 0;
+    for layout in &layouts {
+        let dir = layout.parent().unwrap_or(routes_dir);
+        let mut pages = Vec::new();
+        find_files(dir, &|name| name == "+page.luat", &mut pages);
+        if pages.is_empty() {
+            orphans += 1;
+            report.warn(
+                CATEGORY,
+                format!("{} has no +page.luat in or below its directory", relative(project_dir, layout)),
+                format!("add {}/+page.luat or remove the layout", relative(project_dir, dir)),
+            );
+        }
+    }
+    if orphans == 0 && !layouts.is_empty() {
+        report.ok(CATEGORY, format!("{} layout(s) in use", layouts.len()));
+    }
+}
+
+/// Opens the SQLite stores used by `luat dev` and `luat serve` without
+/// creating them.
+fn check_kv(project_dir: &Path, config: &Config, report: &mut DoctorReport) {
+    const CATEGORY: &str = "kv";
+    if config.kv.backend == KvBackend::Memory {
+        report.ok(CATEGORY, "In-memory backend, nothing to check");
+        return;
+    }
+
+    let dev_dir = project_dir.join(&config.routing.data_dir);
+    let serve_dir = project_dir.join(".luat").join("kv");
+    for (command, dir) in [("dev", dev_dir), ("serve", serve_dir)] {
+        let db = dir.join("kv.db");
+        if db.exists() {
+            let result = rusqlite::Connection::open_with_flags(&db, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .and_then(|conn| conn.query_row("SELECT COUNT(*) FROM kv", [], |row| row.get::<_, i64>(0)));
+            match result {
+                Ok(count) => report.ok(CATEGORY, format!("{} ({} key(s), luat {})", relative(project_dir, &db), count, command)),
+                Err(e) => report.error(
+                    CATEGORY,
+                    format!("Cannot read {}: {}", relative(project_dir, &db), e),
+                    format!("delete {} to start with an empty store", relative(project_dir, &db)),
+                ),
+            }
+        } else {
+            let existing = dir.ancestors().find(|p| p.exists()).unwrap_or(project_dir);
+            let writable = fs::metadata(existing)
+                .map(|m| m.is_dir() && !m.permissions().readonly())
+                .unwrap_or(false);
+            if !writable {
+                report.error(
+                    CATEGORY,
+                    format!("{} is not writable, luat {} cannot create its store", relative(project_dir, existing), command),
+                    "fix the directory permissions",
+                );
+            }
+        }
+    }
+}
+
+/// Scans Lua sources for APIs removed in Lua 5.4.
+fn check_lua_version(project_dir: &Path, config: &Config, report: &mut DoctorReport) {
+    const CATEGORY: &str = "lua";
+    let runtime = mlua::Lua::new()
+        .globals()
+        .get::<String>("_VERSION")
+        .unwrap_or_else(|_| "Lua".to_string());
+
+    let patterns: Vec<(&str, Regex, &str)> = LUA51_APIS
+        .iter()
+        .map(|(name, re, fix)| (*name, Regex::new(re).expect("valid regex"), *fix))
+        .collect();
+
+    let mut files = Vec::new();
+    let mut roots = vec![&config.routing.routes_dir, &config.routing.lib_dir];
+    roots.extend(config.routing.source_roots.iter());
+    for root in roots {
+        find_files(
+            &project_dir.join(root),
+            &|name| name.ends_with(".lua") || name.ends_with(".luat"),
+            &mut files,
+        );
+    }
+    files.sort();
+    files.dedup();
+
+    let mut found = 0;
+    for file in &files {
+        let Ok(source) = fs::read_to_string(file) else {
+            continue;
+        };
+        for (line_no, line) in source.lines().enumerate() {
+            let code = line.split("--").next().unwrap_or_default();
+            for (name, re, fix) in &patterns {
+                if re.is_match(code) {
+                    found += 1;
+                    report.warn(
+                        CATEGORY,
+                        format!(
+                            "{}:{} uses `{}`, which is not available in {}",
+                            relative(project_dir, file),
+                            line_no + 1,
+                            name,
+                            runtime
+                        ),
+                        *fix,
+                    );
+                }
+            }
+        }
+    }
+    if found == 0 {
+        report.ok(CATEGORY, format!("{} source file(s) compatible with {}", files.len(), runtime));
+    }
+}
+
+/// Checks that the enabled frontend tools are cached and intact.
+fn check_toolchain(config: &Config, report: &mut DoctorReport) {
+    const CATEGORY: &str = "toolchain";
+    let tools = config.frontend.get_enabled_tools();
+    if tools.is_empty() {
+        return;
+    }
+    let cache_dir = toolchain::cache_dir().map(|d| d.display().to_string()).unwrap_or_default();
+
+    let mut tools: Vec<Tool> = tools.into_iter().collect();
+    tools.sort_by_key(|t| t.as_str());
+    for tool in tools {
+        let version = match tool {
+            Tool::Sass => &config.frontend.sass_version,
+            Tool::Tailwind => &config.frontend.tailwind_version,
+            Tool::TypeScript => &config.frontend.esbuild_version,
+        };
+        let tool_dir = Path::new(&cache_dir).join(tool.as_str());
+        let latest = tool_dir.join("latest");
+        if version == "latest" && latest.is_symlink() && !latest.exists() {
+            report.error(
+                CATEGORY,
+                format!("{}: `latest` points to a missing version", tool.as_str()),
+                format!("delete {} and run `luat build` to download it again", tool_dir.display()),
+            );
+            continue;
+        }
+
+        match toolchain::cached_tool(tool, version) {
+            Ok(Some(cached)) => {
+                let version_dir = cached.path.parent().map(Path::to_path_buf).unwrap_or_default();
+                if !is_executable(&cached.path) {
+                    report.error(
+                        CATEGORY,
+                        format!("{} {} is not executable", tool.as_str(), cached.version),
+                        format!("delete {} to download it again", version_dir.display()),
+                    );
+                } else if !tool_dir.join(&cached.version).join(format!("{}.sha256", tool.as_str())).exists() {
+                    report.warn(
+                        CATEGORY,
+                        format!("{} {} has no checksum file", tool.as_str(), cached.version),
+                        format!("delete {} to download it again", tool_dir.join(&cached.version).display()),
+                    );
+                } else {
+                    report.ok(CATEGORY, format!("{} {} cached", tool.as_str(), cached.version));
+                }
+            }
+            Ok(None) => report.warn(
+                CATEGORY,
+                format!("{} {} is not cached", tool.as_str(), version),
+                "it is downloaded on the next `luat dev` or `luat build` (needs network access)",
+            ),
+            Err(e) => report.error(
+                CATEGORY,
+                format!("{}: {}", tool.as_str(), e),
+                "remove it from `frontend.enabled` on unsupported platforms",
+            ),
+        }
+    }
+}
+
+/// Checks that the dev server can bind its address.
+fn check_port(config: &Config, report: &mut DoctorReport) {
+    const CATEGORY: &str = "port";
+    let address = format!("{}:{}", config.dev.host, config.dev.port);
+    match TcpListener::bind(address.as_str()) {
+        Ok(_) => report.ok(CATEGORY, format!("{} is available", address)),
+        Err(e) => report.error(
+            CATEGORY,
+            format!("Cannot bind {}: {}", address, e),
+            "stop the other process or change `dev.port` in luat.toml",
+        ),
+    }
+}
+
+/// Runs `luat doctor` in the current directory.
+pub fn run() -> anyhow::Result<()> {
+    let project_dir = std::env::current_dir()?;
+    let mut report = diagnose(&project_dir);
+    if let Ok(config) = Config::load_from(&project_dir) {
+        check_toolchain(&config, &mut report);
+        check_port(&config, &mut report);
+    }
+
+    println!("{}", report);
+    if report.errors() > 0 {
+        anyhow::bail!("{} check(s) failed", report.errors());
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).map(|m| m.permissions().mode() & 0o111 != 0).unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+fn find_files(dir: &Path, matches: &dyn Fn(&str) -> bool, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+    paths.sort();
+    for path in paths {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "node_modules" {
+                find_files(&path, matches, files);
+            }
+        } else if matches(name) {
+            files.push(path);
+        }
+    }
+}
+
+fn relative(project_dir: &Path, path: &Path) -> String {
+    path.strip_prefix(project_dir)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+fn display_dir(path: &Path) -> String {
+    let dir = path.to_string_lossy().replace('\\', "/");
+    if dir.is_empty() {
+        "/".to_string()
+    } else {
+        dir
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, path: &str, content: &str) {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn messages(report: &DoctorReport, status: Status) -> Vec<String> {
+        report
+            .checks
+            .iter()
+            .filter(|c| c.status == status)
+            .map(|c| format!("{}: {}", c.category, c.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_diagnose_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(root, "luat.toml", "[project]\nname = \"demo\"\n\n[devv]\nport = 1\n\n[cors]\norigins = [\"*\"]\n");
+        write(root, "src/app.html", "<html></html>");
+        write(root, "src/routes/+page.luat", "<h1>Home</h1>");
+        write(root, "src/routes/blog/[slug]/+page.luat", "<h1>Post</h1>");
+        write(root, "src/routes/blog/[id]/+page.luat", "<h1>Post</h1>");
+        write(root, "src/routes/admin/+layout.luat", "{@render children()}");
+        write(
+            root,
+            "src/lib/util.lua",
+            "local t = {}\nlocal n = table.getn(t) -- old\nlocal a, b = table.unpack(t)\nreturn loadstring('x')\n",
+        );
+
+        let report = diagnose(root);
+        let warnings = messages(&report, Status::Warning);
+        let errors = messages(&report, Status::Error);
+
+        assert!(warnings.iter().any(|w| w.contains("Unknown section `[devv]`")), "{:?}", warnings);
+        assert!(!warnings.iter().any(|w| w.contains("`[cors]`")), "{:?}", warnings);
+        assert!(warnings.iter().any(|w| w.starts_with("layouts: src/routes/admin/+layout.luat")));
+        assert!(warnings.iter().any(|w| w.contains("src/lib/util.lua:2 uses `table.getn`")));
+        assert!(warnings.iter().any(|w| w.contains("src/lib/util.lua:4 uses `loadstring`")));
+        assert!(!warnings.iter().any(|w| w.contains("`unpack`")));
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].starts_with("routes: Route `blog/["), "{:?}", errors);
+        assert!(errors[0].contains("conflicts with `blog/["));
+    }
+
+    #[test]
+    fn test_invalid_config_stops_early() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "luat.toml", "[project\nname = 1");
+
+        let report = diagnose(dir.path());
+        assert_eq!(report.errors(), 1);
+        assert_eq!(report.checks.len(), 1);
+        assert!(report.checks[0].message.contains("not valid TOML"));
+    }
+}
@@ -0,0 +1,398 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Static site export (`luat export`).
+//!
+//! Prerenders the page routes of the production build (`dist/bundle.bin`)
+//! into static HTML files, one `index.html` per URL, and copies the built
+//! `public/` and `static/` directories next to them.
+//!
+//! Routes without parameters are always exported. With `--crawl`, every
+//! exported page is scanned for internal `<a href>` links, which are
+//! rendered in turn, so parameterized routes such as `/blog/{slug}` are
+//! exported for every URL linked from another page. The crawl respects
+//! `static/robots.txt` (`Disallow` rules for `*`), `rel="nofollow"` links and
+//! pages whose `seo` robots value contains `nofollow`.
+//!
+//! At the end, parameterized routes that no link led to are reported as
+//! unreachable, along with links that failed to render.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use console::style;
+use luat::{LuatRequest, LuatResponse};
+use regex::Regex;
+
+use crate::commands::build::copy_dir_recursive;
+use crate::commands::serve::{
+    bundle_route_to_engine_route, collect_production_head_assets, html_escape, load_bundle,
+    wrap_with_app_html, DEFAULT_APP_HTML,
+};
+use crate::config::Config;
+use crate::kv::KVManager;
+use crate::server::stream::split_at_body_end;
+
+/// Options for `luat export`.
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    /// Directory the site is written to.
+    pub output: String,
+    /// Follow internal links from rendered pages.
+    pub crawl: bool,
+}
+
+/// `Disallow`/`Allow` rules from `robots.txt` that apply to all user agents.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RobotsRules {
+    allow: Vec<String>,
+    disallow: Vec<String>,
+}
+
+impl RobotsRules {
+    /// Parses the `User-agent: *` groups of a `robots.txt` file.
+    pub fn parse(content: &str) -> Self {
+        let mut rules = Self::default();
+        let mut agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => {
+                    // A user-agent line after rules starts a new group
+                    if in_rules {
+                        agents.clear();
+                        in_rules = false;
+                    }
+                    agents.push(value.to_string());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    if value.is_empty() || !agents.iter().any(|agent| agent == "*") {
+                        continue;
+                    }
+                    if key.trim().eq_ignore_ascii_case("allow") {
+                        rules.allow.push(value.to_string());
+                    } else {
+                        rules.disallow.push(value.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+        rules
+    }
+
+    /// Returns true if `path` may be crawled; the longest matching rule wins.
+    pub fn allows(&self, path: &str) -> bool {
+        let longest = |rules: &[String]| {
+            rules
+                .iter()
+                .filter(|rule| path.starts_with(rule.as_str()))
+                .map(String::len)
+                .max()
+        };
+        match (longest(&self.allow), longest(&self.disallow)) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(allow), Some(disallow)) => allow >= disallow,
+        }
+    }
+}
+
+/// Returns the internal links of `html`, resolved against the page at `base`.
+///
+/// Fragments and query strings are dropped, as are links to other origins,
+/// to `/public/` and `/static/` assets and links marked `rel="nofollow"`.
+pub fn extract_links(html: &str, base: &str) -> Vec<String> {
+    let anchor = Regex::new(r"(?is)<a\s([^>]*)>").expect("valid anchor regex");
+    let href = Regex::new(r#"(?is)\bhref\s*=\s*(?:"([^"]*)"|'([^']*)')"#).expect("valid href regex");
+    let rel = Regex::new(r#"(?is)\brel\s*=\s*(?:"([^"]*)"|'([^']*)')"#).expect("valid rel regex");
+    let attr = |re: &Regex, attrs: &str| {
+        re.captures(attrs)
+            .and_then(|caps| caps.get(1).or_else(|| caps.get(2)))
+            .map(|value| value.as_str().replace("&amp;", "&"))
+    };
+
+    let mut links = Vec::new();
+    for caps in anchor.captures_iter(html) {
+        let attrs = &caps[1];
+        if attr(&rel, attrs).is_some_and(|rel| rel.split_whitespace().any(|v| v.eq_ignore_ascii_case("nofollow"))) {
+            continue;
+        }
+        let Some(target) = attr(&href, attrs) else {
+            continue;
+        };
+        if let Some(path) = resolve_link(&target, base) {
+            if !links.contains(&path) {
+                links.push(path);
+            }
+        }
+    }
+    links
+}
+
+/// Resolves a link target to a site path, or `None` for external and asset links.
+fn resolve_link(target: &str, base: &str) -> Option<String> {
+    let target = target.trim();
+    let target = target.split(['#', '?']).next().unwrap_or_default();
+    if target.is_empty() || target.starts_with("//") || target.contains(':') {
+        return None;
+    }
+
+    let joined = if target.starts_with('/') {
+        target.to_string()
+    } else {
+        let dir = match base.rfind('/') {
+            Some(pos) => &base[..=pos],
+            None => "/",
+        };
+        format!("{}{}", dir, target)
+    };
+
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in joined.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    let path = format!("/{}", segments.join("/"));
+    if path.starts_with("/public/") || path.starts_with("/static/") {
+        return None;
+    }
+    Some(path)
+}
+
+/// Returns the file a URL path is exported to.
+pub fn output_file(output: &Path, path: &str) -> PathBuf {
+    let relative = path.trim_matches('/');
+    if relative.is_empty() {
+        output.join("index.html")
+    } else {
+        output.join(relative).join("index.html")
+    }
+}
+
+/// Returns true if a route pattern has parameters (`{slug}`, `{*rest}`).
+fn is_parameterized(pattern: &str) -> bool {
+    pattern.contains('{')
+}
+
+/// Exports the production build as a static site.
+pub async fn run(options: ExportOptions) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let working_dir = std::env::current_dir()?;
+    let dist_dir = working_dir.join("dist");
+    if !dist_dir.join("bundle.bin").exists() {
+        anyhow::bail!("No build found at dist/bundle.bin. Run `luat build` first.");
+    }
+
+    // The same store `luat serve` runs the build against
+    let kv_dir = working_dir.join(".luat").join("kv");
+    let kv_manager = Arc::new(KVManager::for_backend(config.kv.backend, &kv_dir)?);
+    let bundle = load_bundle(&config, &dist_dir, &kv_manager, 1, false)?;
+    let Some(router) = bundle.router.as_ref() else {
+        anyhow::bail!("No routes found in the build. `luat export` needs SvelteKit-style routing.");
+    };
+    let robots = fs::read_to_string(dist_dir.join("static").join("robots.txt"))
+        .map(|content| RobotsRules::parse(&content))
+        .unwrap_or_default();
+
+    let output = working_dir.join(&options.output);
+    fs::create_dir_all(&output)?;
+
+    let mut queue: VecDeque<String> = router
+        .routes()
+        .iter()
+        .filter(|route| route.is_page_route() && !is_parameterized(&route.pattern))
+        .map(|route| route.pattern.clone())
+        .collect();
+    let mut seen: BTreeSet<String> = queue.iter().cloned().collect();
+    let mut reached: BTreeSet<String> = BTreeSet::new();
+    let mut failed: BTreeMap<String, String> = BTreeMap::new();
+    let mut exported = 0usize;
+
+    let engine = bundle.engines.clone().checkout_owned().await;
+
+    while let Some(path) = queue.pop_front() {
+        let Some((route, params)) = router.match_url(&path) else {
+            failed.insert(path, "no matching route".to_string());
+            continue;
+        };
+        if !route.is_page_route() {
+            continue;
+        }
+        let engine_route = bundle_route_to_engine_route(route, &params);
+        let request = LuatRequest::new(&path, "GET");
+
+        let response = engine.respond(&engine_route, &request);
+        let head = response.as_ref().ok().and_then(LuatResponse::head).cloned().unwrap_or_default();
+        let (status, body, deferred) = match response {
+            Ok(LuatResponse::Html { status, body, .. }) => (status, body, Vec::new()),
+            Ok(LuatResponse::Stream { status, body, deferred, .. }) => (status, body, deferred),
+            Ok(LuatResponse::Redirect { location, .. }) => {
+                if let Some(target) = resolve_link(&location, &path) {
+                    if options.crawl && robots.allows(&target) && seen.insert(target.clone()) {
+                        queue.push_back(target);
+                    }
+                }
+                continue;
+            }
+            Ok(LuatResponse::Error { status, message }) => {
+                failed.insert(path, format!("{} {}", status, message));
+                continue;
+            }
+            Ok(_) => {
+                failed.insert(path, "not an HTML page".to_string());
+                continue;
+            }
+            Err(e) => {
+                failed.insert(path, e.to_string());
+                continue;
+            }
+        };
+        if status >= 400 {
+            failed.insert(path, format!("status {}", status));
+            continue;
+        }
+
+        let title = html_escape(head.title.as_deref().unwrap_or("Luat App"));
+        let head_assets = format!(
+            "{}{}",
+            head.to_html(),
+            collect_production_head_assets(&config, &bundle.assets, None)
+        );
+        let app_html = bundle.app_html_template.as_deref().unwrap_or(DEFAULT_APP_HTML);
+        let mut html = wrap_with_app_html(app_html, &body, &title, &head_assets);
+        if !deferred.is_empty() {
+            let chunks: luat::Result<String> = deferred.iter().map(|id| engine.resolve_deferred(id, None)).collect();
+            let chunks = match chunks {
+                Ok(chunks) => chunks,
+                Err(e) => {
+                    // Values after the failing one are never resolved
+                    let _ = engine.discard_deferred(&deferred);
+                    failed.insert(path, format!("deferred value: {}", e));
+                    continue;
+                }
+            };
+            let (start, tail) = split_at_body_end(&html);
+            html = format!("{}{}{}", start, chunks, tail);
+        }
+
+        let file = output_file(&output, &path);
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&file, &html)?;
+        reached.insert(route.pattern.clone());
+        exported += 1;
+
+        let nofollow = head
+            .robots
+            .as_deref()
+            .is_some_and(|robots| robots.to_ascii_lowercase().contains("nofollow"));
+        if options.crawl && !nofollow {
+            for link in extract_links(&body, &path) {
+                if robots.allows(&link) && seen.insert(link.clone()) {
+                    queue.push_back(link);
+                }
+            }
+        }
+    }
+    drop(engine);
+
+    for dir in ["public", "static"] {
+        let source = dist_dir.join(dir);
+        if source.exists() {
+            copy_dir_recursive(&source, &output.join(dir))?;
+        }
+    }
+
+    println!(
+        "{} {} pages to {}",
+        style("Exported").green(),
+        exported,
+        output.display()
+    );
+
+    let unreachable: Vec<&str> = router
+        .routes()
+        .iter()
+        .filter(|route| route.is_page_route() && !reached.contains(&route.pattern))
+        .map(|route| route.pattern.as_str())
+        .collect();
+    if !unreachable.is_empty() {
+        let hint = if options.crawl {
+            "no exported page links to these routes"
+        } else {
+            "use --crawl to discover their URLs from links"
+        };
+        println!("\n{} ({})", style("Unreachable routes").yellow().bold(), hint);
+        for pattern in unreachable {
+            println!("  {}", pattern);
+        }
+    }
+    if !failed.is_empty() {
+        println!("\n{}", style("Failed pages").red().bold());
+        for (path, reason) in &failed {
+            println!("  {}  {}", path, style(reason).dim());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_links() {
+        let html = r#"
+            <a href="/blog/first">First</a>
+            <a class="x" href='second?page=2#top'>Second</a>
+            <a href="../about">About</a>
+            <a href="https://example.com/">External</a>
+            <a href="//cdn.example.com/x">Protocol-relative</a>
+            <a href="mailto:hi@example.com">Mail</a>
+            <a href="/public/app.css">Asset</a>
+            <a rel="nofollow noopener" href="/private">Private</a>
+            <a href="/blog/first#comments">Again</a>
+            <a name="anchor">No href</a>
+        "#;
+        assert_eq!(
+            extract_links(html, "/blog/index"),
+            vec!["/blog/first", "/blog/second", "/about"]
+        );
+    }
+
+    #[test]
+    fn test_robots_rules() {
+        let robots = RobotsRules::parse(
+            "User-agent: Googlebot\nDisallow: /\n\nUser-agent: *\nDisallow: /admin\nAllow: /admin/help # public\n",
+        );
+        assert!(robots.allows("/blog"));
+        assert!(!robots.allows("/admin/users"));
+        assert!(robots.allows("/admin/help/faq"));
+        assert!(RobotsRules::default().allows("/anything"));
+    }
+
+    #[test]
+    fn test_output_file() {
+        let out = Path::new("site");
+        assert_eq!(output_file(out, "/"), Path::new("site/index.html"));
+        assert_eq!(output_file(out, "/blog/hello"), Path::new("site/blog/hello/index.html"));
+    }
+}
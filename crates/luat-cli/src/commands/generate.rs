@@ -0,0 +1,399 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Scaffolding generator (`luat new route|component|action`).
+//!
+//! Files are rendered from templates with `{{name}}`, `{{route}}`, `{{url}}`,
+//! `{{server_path}}` and `{{test_path}}` placeholders. A project can override any built-in template by
+//! placing a file with the same name in `[generators] templates_dir`
+//! (default: `.luat/generators`):
+//!
+//! ```text
+//! .luat/generators/
+//!   route/+page.luat
+//!   route/+page.server.lua
+//!   route/actions.lua
+//!   route/page_test.lua
+//!   component/Component.luat
+//!   action/fragment.luat
+//!   action/action.lua
+//! ```
+//!
+//! Existing files are never overwritten.
+
+use crate::config::Config;
+use console::style;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+const ROUTE_PAGE: &str = r#"{/*
+{{route}} ({{url}})
+*/}
+
+<script>
+local title = props.title or "{{name}}"
+</script>
+
+<h1>{title}</h1>
+"#;
+
+const ROUTE_SERVER: &str = r#"-- Server logic for {{url}}
+
+function load(ctx)
+    return {
+        title = "{{name}}"
+    }
+end
+"#;
+
+const ROUTE_ACTIONS: &str = r#"
+-- Form actions, called via POST {{url}}?/<name>
+actions = {
+    default = function(ctx)
+        local form = ctx.form or {}
+        return { success = true }
+    end
+}
+"#;
+
+const ROUTE_TEST: &str = r#"-- Smoke test for {{url}}
+-- Run from the project root: lua {{test_path}}
+
+dofile("{{server_path}}")
+
+local result = load({ params = {}, query = {}, url = "{{url}}", method = "GET" })
+assert(type(result) == "table", "load() should return a table")
+
+print("ok - {{url}}")
+"#;
+
+const COMPONENT: &str = r#"{/*
+{{name}} component
+
+USAGE:
+  <{{name}} />
+*/}
+
+<script>
+local class = props.class or ""
+</script>
+
+<div class="{class}">
+    {@render props.children?.()}
+</div>
+"#;
+
+const ACTION_FRAGMENT: &str = r#"{/* Fragment returned by the "{{name}}" action on {{url}} */}
+{#if props.error}
+    <p class="error">{props.error}</p>
+{/if}
+"#;
+
+const ACTION_STUB: &str = r#"    {{name}} = function(ctx)
+        local form = ctx.form or {}
+        return { success = true }
+    end,
+"#;
+
+/// Generates files for a project rooted at `root`.
+pub struct Generator {
+    root: PathBuf,
+    routes_dir: PathBuf,
+    lib_dir: PathBuf,
+    templates_dir: PathBuf,
+}
+
+impl Generator {
+    /// Creates a generator using the directories from `config`.
+    pub fn new(root: &Path, config: &Config) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            routes_dir: root.join(&config.routing.routes_dir),
+            lib_dir: root.join(&config.routing.lib_dir),
+            templates_dir: root.join(&config.generators.templates_dir),
+        }
+    }
+
+    /// Creates a route directory with `+page.luat` and, unless `server` is
+    /// false, `+page.server.lua`. `actions` adds a form actions table and
+    /// `test` writes a smoke test under `tests/routes/`.
+    pub fn route(&self, route: &str, server: bool, actions: bool, test: bool) -> anyhow::Result<Vec<PathBuf>> {
+        let route = clean_path(route)?;
+        let dir = self.routes_dir.join(&route);
+        let vars = self.route_vars(&route);
+        let mut created = Vec::new();
+
+        self.write(&dir.join("+page.luat"), "route/+page.luat", ROUTE_PAGE, &vars, &mut created)?;
+        if server || actions || test {
+            let mut server_source = self.template("route/+page.server.lua", ROUTE_SERVER)?;
+            if actions {
+                server_source.push_str(&self.template("route/actions.lua", ROUTE_ACTIONS)?);
+            }
+            write_new(&dir.join("+page.server.lua"), &render(&server_source, &vars), &mut created)?;
+        }
+        if test {
+            let test_path = self.root.join("tests/routes").join(&route).join("page_test.lua");
+            self.write(&test_path, "route/page_test.lua", ROUTE_TEST, &vars, &mut created)?;
+        }
+
+        Ok(created)
+    }
+
+    /// Creates `<lib_dir>/components/<name>.luat`.
+    pub fn component(&self, name: &str) -> anyhow::Result<Vec<PathBuf>> {
+        let name = clean_path(name)?;
+        let path = self.lib_dir.join("components").join(&name).with_extension("luat");
+        let vars = vec![("name", file_stem(&name))];
+        let mut created = Vec::new();
+        self.write(&path, "component/Component.luat", COMPONENT, &vars, &mut created)?;
+        Ok(created)
+    }
+
+    /// Adds an action to an existing route: a `(fragments)/<name>.luat`
+    /// template and an entry in the `actions` table of `+page.server.lua`
+    /// (created if missing). Returns the newly created files.
+    pub fn action(&self, route: &str, name: &str) -> anyhow::Result<Vec<PathBuf>> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            anyhow::bail!("Invalid action name '{}'", name);
+        }
+        let route = clean_path(route)?;
+        let dir = self.routes_dir.join(&route);
+        if !dir.join("+page.luat").exists() {
+            anyhow::bail!("Route '{}' has no +page.luat", route.display());
+        }
+
+        let mut vars = self.route_vars(&route);
+        vars[0].1 = name.to_string();
+        let mut created = Vec::new();
+        self.write(
+            &dir.join("(fragments)").join(format!("{}.luat", name)),
+            "action/fragment.luat",
+            ACTION_FRAGMENT,
+            &vars,
+            &mut created,
+        )?;
+
+        let stub = render(&self.template("action/action.lua", ACTION_STUB)?, &vars);
+        let server_path = dir.join("+page.server.lua");
+        let source = fs::read_to_string(&server_path).unwrap_or_default();
+        let updated = add_action(&source, name, &stub)?;
+        fs::write(&server_path, updated)?;
+        println!("  {} {}", style("update").cyan(), server_path.display());
+
+        Ok(created)
+    }
+
+    fn route_vars(&self, route: &Path) -> Vec<(&'static str, String)> {
+        let route_str = route.to_string_lossy().replace('\\', "/");
+        let url = route
+            .components()
+            .filter_map(|c| c.as_os_str().to_str())
+            // Route groups like (admin) don't appear in the URL
+            .filter(|segment| !(segment.starts_with('(') && segment.ends_with(')')))
+            .collect::<Vec<_>>()
+            .join("/");
+        let routes_dir = self
+            .routes_dir
+            .strip_prefix(&self.root)
+            .unwrap_or(&self.routes_dir)
+            .to_string_lossy()
+            .replace('\\', "/");
+        vec![
+            ("name", file_stem(route)),
+            ("route", format!("routes/{}/+page.luat", route_str)),
+            ("url", format!("/{}", url)),
+            ("server_path", format!("{}/{}/+page.server.lua", routes_dir, route_str)),
+            ("test_path", format!("tests/routes/{}/page_test.lua", route_str)),
+        ]
+    }
+
+    /// Returns the project override for `name`, or the built-in template.
+    fn template(&self, name: &str, default: &str) -> anyhow::Result<String> {
+        let custom = self.templates_dir.join(name);
+        if custom.is_file() {
+            Ok(fs::read_to_string(custom)?)
+        } else {
+            Ok(default.to_string())
+        }
+    }
+
+    fn write(
+        &self,
+        path: &Path,
+        template: &str,
+        default: &str,
+        vars: &[(&str, String)],
+        created: &mut Vec<PathBuf>,
+    ) -> anyhow::Result<()> {
+        write_new(path, &render(&self.template(template, default)?, vars), created)
+    }
+}
+
+/// Runs `luat new route <path>`.
+pub fn run_route(path: &str, no_server: bool, actions: bool, test: bool) -> anyhow::Result<()> {
+    let generator = project_generator()?;
+    report(generator.route(path, !no_server, actions, test)?);
+    Ok(())
+}
+
+/// Runs `luat new component <name>`.
+pub fn run_component(name: &str) -> anyhow::Result<()> {
+    let generator = project_generator()?;
+    report(generator.component(name)?);
+    Ok(())
+}
+
+/// Runs `luat new action <route> <name>`.
+pub fn run_action(route: &str, name: &str) -> anyhow::Result<()> {
+    let generator = project_generator()?;
+    report(generator.action(route, name)?);
+    Ok(())
+}
+
+fn project_generator() -> anyhow::Result<Generator> {
+    let config = Config::load()?;
+    Ok(Generator::new(&std::env::current_dir()?, &config))
+}
+
+fn report(created: Vec<PathBuf>) {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    for path in created {
+        println!(
+            "  {} {}",
+            style("create").green(),
+            path.strip_prefix(&cwd).unwrap_or(&path).display()
+        );
+    }
+}
+
+/// Writes a file unless it already exists.
+fn write_new(path: &Path, content: &str, created: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    if path.exists() {
+        println!("  {} {} (exists)", style("skip").yellow(), path.display());
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)?;
+    created.push(path.to_path_buf());
+    Ok(())
+}
+
+/// Inserts an action stub into the `actions` table, creating it if needed.
+fn add_action(source: &str, name: &str, stub: &str) -> anyhow::Result<String> {
+    let actions_re = regex::Regex::new(r"(?m)^\s*actions\s*=\s*\{[ \t]*\n").unwrap();
+    let existing_re = regex::Regex::new(&format!(r"(?m)^\s*{}\s*=\s*function", regex::escape(name))).unwrap();
+    if existing_re.is_match(source) {
+        anyhow::bail!("Action '{}' already exists", name);
+    }
+
+    match actions_re.find(source) {
+        Some(m) => Ok(format!("{}{}{}", &source[..m.end()], stub, &source[m.end()..])),
+        None => {
+            let mut updated = source.to_string();
+            if !updated.is_empty() && !updated.ends_with('\n') {
+                updated.push('\n');
+            }
+            updated.push_str(&format!("\nactions = {{\n{}}}\n", stub));
+            Ok(updated)
+        }
+    }
+}
+
+fn render(template: &str, vars: &[(&str, String)]) -> String {
+    vars.iter().fold(template.to_string(), |out, (key, value)| {
+        out.replace(&format!("{{{{{}}}}}", key), value)
+    })
+}
+
+/// Validates a user-supplied relative path (no `..`, no absolute paths).
+fn clean_path(path: &str) -> anyhow::Result<PathBuf> {
+    let path = Path::new(path.trim_matches('/'));
+    if path.as_os_str().is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        anyhow::bail!("Invalid path '{}'", path.display());
+    }
+    Ok(path.to_path_buf())
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn generator(root: &Path) -> Generator {
+        Generator {
+            root: root.to_path_buf(),
+            routes_dir: root.join("src/routes"),
+            lib_dir: root.join("src/lib"),
+            templates_dir: root.join(".luat/generators"),
+        }
+    }
+
+    #[test]
+    fn test_route_with_actions_and_test() {
+        let temp = TempDir::new().unwrap();
+        let generator = generator(temp.path());
+
+        let created = generator.route("blog/[slug]", true, true, true).unwrap();
+        assert_eq!(created.len(), 3);
+
+        let server = fs::read_to_string(temp.path().join("src/routes/blog/[slug]/+page.server.lua")).unwrap();
+        assert!(server.contains("function load(ctx)"));
+        assert!(server.contains("POST /blog/[slug]?/<name>"));
+        assert!(server.contains("actions = {"));
+
+        let test = fs::read_to_string(temp.path().join("tests/routes/blog/[slug]/page_test.lua")).unwrap();
+        assert!(test.contains(r#"dofile("src/routes/blog/[slug]/+page.server.lua")"#));
+
+        // Running again doesn't overwrite anything
+        assert!(generator.route("blog/[slug]", true, true, true).unwrap().is_empty());
+        assert!(generator.route("../outside", true, false, false).is_err());
+    }
+
+    #[test]
+    fn test_custom_templates() {
+        let temp = TempDir::new().unwrap();
+        let generator = generator(temp.path());
+        fs::create_dir_all(temp.path().join(".luat/generators/component")).unwrap();
+        fs::write(
+            temp.path().join(".luat/generators/component/Component.luat"),
+            "<div class=\"{{name}}\"></div>",
+        )
+        .unwrap();
+
+        generator.component("forms/TextInput").unwrap();
+        let component = fs::read_to_string(temp.path().join("src/lib/components/forms/TextInput.luat")).unwrap();
+        assert_eq!(component, "<div class=\"TextInput\"></div>");
+    }
+
+    #[test]
+    fn test_add_action() {
+        let temp = TempDir::new().unwrap();
+        let generator = generator(temp.path());
+        generator.route("(admin)/todos", true, true, false).unwrap();
+
+        generator.action("(admin)/todos", "archive").unwrap();
+        let server = fs::read_to_string(temp.path().join("src/routes/(admin)/todos/+page.server.lua")).unwrap();
+        assert!(server.contains("actions = {\n    archive = function(ctx)"));
+        assert!(temp.path().join("src/routes/(admin)/todos/(fragments)/archive.luat").exists());
+        let fragment = fs::read_to_string(temp.path().join("src/routes/(admin)/todos/(fragments)/archive.luat")).unwrap();
+        assert!(fragment.contains("on /todos"));
+
+        assert!(generator.action("(admin)/todos", "archive").is_err());
+        assert!(generator.action("missing", "archive").is_err());
+    }
+
+    #[test]
+    fn test_add_action_creates_table() {
+        let updated = add_action("function load(ctx)\nend", "save", "    save = function(ctx) end,\n").unwrap();
+        assert!(updated.ends_with("\nactions = {\n    save = function(ctx) end,\n}\n"));
+    }
+}
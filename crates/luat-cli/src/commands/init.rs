@@ -3,21 +3,94 @@
 // SPDX-License-Identifier: MIT
 
 //! Project initialization command for creating new LUAT projects.
+//!
+//! `luat init` asks for the template, CSS tool, KV backend, whether to run
+//! `git init` and whether to include the example routes, then writes a
+//! commented `luat.toml` matching those choices. `--yes` accepts the defaults
+//! without prompting.
 
 use include_dir::{include_dir, Dir, DirEntry};
 use std::fs;
-use std::io::{self, Write};
+use std::io::{self, BufRead, Write};
 use std::path::Path;
+use std::process::Command;
+
+use crate::config::KvBackend;
 
 static DEFAULT_TEMPLATE: Dir = include_dir!("$CARGO_MANIFEST_DIR/templates/default");
 static MINIMAL_TEMPLATE: Dir = include_dir!("$CARGO_MANIFEST_DIR/templates/minimal");
 
+/// Example route directories in the default template, with their library modules.
+const EXAMPLE_ROUTES: &[(&str, &[&str])] = &[
+    ("about", &[]),
+    ("api", &[]),
+    ("blog", &["src/lib/blog.lua"]),
+    ("todos", &["src/lib/todos.lua"]),
+];
+
+/// CSS tool selected for the project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CssTool {
+    /// Tailwind CSS (scans templates for utility classes).
+    Tailwind,
+    /// Sass, compiled from `assets/css/app.css`.
+    Sass,
+    /// Plain CSS copied into `public/css/`.
+    None,
+}
+
+impl CssTool {
+    fn name(self) -> &'static str {
+        match self {
+            CssTool::Tailwind => "tailwind",
+            CssTool::Sass => "sass",
+            CssTool::None => "none",
+        }
+    }
+}
+
+/// Choices made in the init wizard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitOptions {
+    /// Template name: "default" or "minimal".
+    pub template: String,
+    /// CSS tool.
+    pub css: CssTool,
+    /// Backend for the `kv` module.
+    pub kv: KvBackend,
+    /// Run `git init` in the new project.
+    pub git: bool,
+    /// Include the example routes (default template only).
+    pub examples: bool,
+}
+
+impl Default for InitOptions {
+    fn default() -> Self {
+        Self {
+            template: "default".to_string(),
+            css: CssTool::Tailwind,
+            kv: KvBackend::Sqlite,
+            git: true,
+            examples: true,
+        }
+    }
+}
+
 /// Initializes a new LUAT project from a template.
-pub async fn run(name: Option<String>, template: Option<String>) -> anyhow::Result<()> {
-    // If no template specified, show selection menu
-    let template_name = match template {
-        Some(t) => t,
-        None => select_template()?,
+///
+/// Prompts for each option unless `yes` is set, in which case the defaults
+/// (and the template given on the command line, if any) are used.
+pub async fn run(name: Option<String>, template: Option<String>, yes: bool) -> anyhow::Result<()> {
+    let options = if yes {
+        let template = template.unwrap_or_else(|| "default".to_string());
+        InitOptions {
+            examples: template == "default",
+            template,
+            ..InitOptions::default()
+        }
+    } else {
+        let stdin = io::stdin();
+        prompt_options(&mut stdin.lock(), template)?
     };
 
     // Handle "." or no argument to init in current directory
@@ -34,7 +107,7 @@ pub async fn run(name: Option<String>, template: Option<String>) -> anyhow::Resu
         tracing::info!("Created project directory: {}", project_name);
     }
 
-    let template_dir = match template_name.as_str() {
+    let template_dir = match options.template.as_str() {
         "minimal" => &MINIMAL_TEMPLATE,
         _ => &DEFAULT_TEMPLATE,
     };
@@ -45,39 +118,326 @@ pub async fn run(name: Option<String>, template: Option<String>) -> anyhow::Resu
     fs::create_dir_all(project_dir.join("public/css"))?;
     fs::create_dir_all(project_dir.join("public/js"))?;
 
-    print_success(&project_name, &template_name, is_current_dir);
+    apply_options(&project_dir, &project_name, &options)?;
+
+    print_success(&project_name, &options, is_current_dir);
 
     Ok(())
 }
 
-fn select_template() -> anyhow::Result<String> {
-    println!();
-    println!("Select a template:");
-    println!();
-    println!("  1. default (recommended)");
-    println!("     Full-featured starter with HTMX, Idiomorph, TypeScript,");
-    println!("     Tailwind CSS, and a todo example with form actions & fragments");
+/// Asks for each init option on the given input.
+///
+/// An empty answer (or end of input) selects the default. A template passed
+/// on the command line skips the template question.
+pub fn prompt_options(input: &mut impl BufRead, template: Option<String>) -> anyhow::Result<InitOptions> {
+    let template = match template {
+        Some(t) => t,
+        None => {
+            let choice = prompt_choice(
+                input,
+                "Select a template:",
+                &[
+                    (
+                        "default (recommended)",
+                        "Full-featured starter with HTMX, Idiomorph, TypeScript,\n     \
+                         Tailwind CSS, and a todo example with form actions & fragments",
+                    ),
+                    ("minimal", "Simple starter with TypeScript and Tailwind CSS"),
+                ],
+            )?;
+            ["default", "minimal"][choice].to_string()
+        }
+    };
+
+    let css = prompt_choice(
+        input,
+        "CSS tool:",
+        &[
+            ("tailwind", "Utility classes generated from your templates"),
+            ("sass", "Compile assets/css/app.css with Sass"),
+            ("none", "Plain CSS copied to public/css/"),
+        ],
+    )?;
+    let css = [CssTool::Tailwind, CssTool::Sass, CssTool::None][css];
+
+    let kv = prompt_choice(
+        input,
+        "KV store backend:",
+        &[
+            ("sqlite", "Persistent storage in .luat/data"),
+            ("memory", "In-memory storage, cleared on restart"),
+        ],
+    )?;
+    let kv = [KvBackend::Sqlite, KvBackend::Memory][kv];
+
+    let examples = template == "default" && prompt_yes_no(input, "Include example routes (todos, blog, about, api)?", true)?;
+    let git = prompt_yes_no(input, "Initialize a git repository?", true)?;
+
+    Ok(InitOptions {
+        template,
+        css,
+        kv,
+        git,
+        examples,
+    })
+}
+
+/// Prints a numbered menu and returns the index of the chosen option.
+fn prompt_choice(input: &mut impl BufRead, question: &str, options: &[(&str, &str)]) -> anyhow::Result<usize> {
     println!();
-    println!("  2. minimal");
-    println!("     Simple starter with TypeScript and Tailwind CSS");
+    println!("{}", question);
     println!();
+    for (i, (label, description)) in options.iter().enumerate() {
+        println!("  {}. {}", i + 1, label);
+        println!("     {}", description);
+        println!();
+    }
     print!("Enter choice [1]: ");
     io::stdout().flush()?;
 
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    let input = input.trim();
+    let answer = read_answer(input)?;
+    if answer.is_empty() {
+        return Ok(0);
+    }
+
+    let by_number = answer.parse::<usize>().ok().filter(|n| (1..=options.len()).contains(n)).map(|n| n - 1);
+    let by_name = options.iter().position(|(label, _)| label.split_whitespace().next() == Some(answer.as_str()));
+
+    match by_number.or(by_name) {
+        Some(index) => Ok(index),
+        None => {
+            println!("Invalid choice, using {}", options[0].0);
+            Ok(0)
+        }
+    }
+}
+
+fn prompt_yes_no(input: &mut impl BufRead, question: &str, default: bool) -> anyhow::Result<bool> {
+    print!("{} [{}]: ", question, if default { "Y/n" } else { "y/N" });
+    io::stdout().flush()?;
+
+    match read_answer(input)?.to_ascii_lowercase().as_str() {
+        "y" | "yes" => Ok(true),
+        "n" | "no" => Ok(false),
+        _ => Ok(default),
+    }
+}
+
+fn read_answer(input: &mut impl BufRead) -> anyhow::Result<String> {
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+/// Applies the wizard choices to an extracted template.
+fn apply_options(project_dir: &Path, project_name: &str, options: &InitOptions) -> anyhow::Result<()> {
+    fs::write(project_dir.join("luat.toml"), render_config(project_name, options))?;
+
+    if !options.examples {
+        remove_examples(project_dir)?;
+    }
+
+    if options.css != CssTool::Tailwind {
+        // The templates import Tailwind from their stylesheet
+        let stylesheet = project_dir.join("assets/css/app.css");
+        if stylesheet.exists() {
+            let css = fs::read_to_string(&stylesheet)?
+                .lines()
+                .filter(|line| line.trim() != "@import \"tailwindcss\";")
+                .collect::<Vec<_>>()
+                .join("\n");
+            fs::write(&stylesheet, css + "\n")?;
+            if options.css == CssTool::None {
+                fs::copy(&stylesheet, project_dir.join("public/css/app.css"))?;
+            }
+        }
+    }
 
-    match input {
-        "" | "1" | "default" => Ok("default".to_string()),
-        "2" | "minimal" => Ok("minimal".to_string()),
-        _ => {
-            println!("Invalid choice, using default template");
-            Ok("default".to_string())
+    if options.git && !project_dir.join(".git").exists() {
+        let status = Command::new("git").args(["init", "-q"]).current_dir(project_dir).status();
+        if !matches!(status, Ok(s) if s.success()) {
+            eprintln!("Warning: git init failed; skipping repository setup");
         }
     }
+
+    Ok(())
+}
+
+/// Removes the default template's example routes, their library modules and
+/// their navigation links.
+fn remove_examples(project_dir: &Path) -> anyhow::Result<()> {
+    let routes_dir = project_dir.join("src/routes");
+    for (route, modules) in EXAMPLE_ROUTES {
+        let dir = routes_dir.join(route);
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+        for module in *modules {
+            let path = project_dir.join(module);
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+    }
+
+    let layout = routes_dir.join("+layout.luat");
+    if layout.exists() {
+        let content = fs::read_to_string(&layout)?;
+        let kept: Vec<&str> = content
+            .lines()
+            .filter(|line| {
+                !EXAMPLE_ROUTES
+                    .iter()
+                    .any(|(route, _)| line.contains(&format!("<NavItem href=\"/{}\"", route)))
+            })
+            .collect();
+        fs::write(&layout, kept.join("\n") + "\n")?;
+    }
+
+    Ok(())
+}
+
+/// Renders a commented `luat.toml` for the given choices.
+pub fn render_config(project_name: &str, options: &InitOptions) -> String {
+    let kv_backend = match options.kv {
+        KvBackend::Sqlite => "sqlite",
+        KvBackend::Memory => "memory",
+    };
+    let enabled = match options.css {
+        CssTool::Tailwind => r#"["tailwind", "esbuild"]"#,
+        CssTool::Sass => r#"["sass", "esbuild"]"#,
+        CssTool::None => r#"["esbuild"]"#,
+    };
+    let (css_comment, css_settings) = match options.css {
+        CssTool::Tailwind => (
+            "\
+# TAILWIND CSS:
+#   Utility-first CSS framework. Luat scans your templates for classes
+#   and generates only the CSS you actually use.
+#   tailwind_content tells Tailwind which files to scan for classes.
+",
+            "\
+tailwind_version = \"4.0.5\"
+tailwind_content = [\"src/**/*.luat\", \"src/**/*.lua\"]
+",
+        ),
+        CssTool::Sass => (
+            "\
+# SASS:
+#   Compiles sass_entrypoint to sass_output.
+",
+            "\
+sass_version = \"1.77.8\"
+sass_entrypoint = \"assets/css/app.css\"
+sass_output = \"public/css/app.css\"
+",
+        ),
+        CssTool::None => (
+            "\
+# PLAIN CSS:
+#   No CSS tool is enabled; edit public/css/app.css directly.
+#   Add \"tailwind\" or \"sass\" to enabled to compile assets/css/app.css.
+",
+            "",
+        ),
+    };
+
+    CONFIG_TEMPLATE
+        .replace("{{project_name}}", project_name)
+        .replace("{{kv_backend}}", kv_backend)
+        .replace("{{enabled}}", enabled)
+        .replace("{{css_comment}}", css_comment)
+        .replace("{{css_settings}}", css_settings)
 }
 
+const CONFIG_TEMPLATE: &str = r#"# ==============================================================================
+# LUAT PROJECT CONFIGURATION
+# ==============================================================================
+# This is the main configuration file for your Luat application.
+# Luat uses TOML format for configuration - simple key-value pairs and sections.
+#
+# When you run `luat dev`, this file tells Luat:
+#   - Where to find your templates and routes
+#   - What port to run the dev server on
+#   - What frontend tools to use (Tailwind, TypeScript, etc.)
+#   - How to build for production
+# ==============================================================================
+
+# ------------------------------------------------------------------------------
+# PROJECT METADATA
+# ------------------------------------------------------------------------------
+# Basic information about your project. The name is used for logging and
+# the version helps track releases.
+[project]
+name = "{{project_name}}"
+version = "0.1.0"
+
+# ------------------------------------------------------------------------------
+# DEVELOPMENT SERVER
+# ------------------------------------------------------------------------------
+# Settings for `luat dev` command.
+# - port: The HTTP port your dev server runs on (default: 3000)
+# - host: The network interface to bind to (127.0.0.1 = localhost only)
+# - templates_dir: Where Luat looks for your route templates
+# - public_dir: Where compiled frontend assets go (CSS, JS)
+[dev]
+port = 3000
+host = "127.0.0.1"
+templates_dir = "src/routes"
+public_dir = "public"
+
+# ------------------------------------------------------------------------------
+# BUILD CONFIGURATION
+# ------------------------------------------------------------------------------
+# Settings for `luat build` command (production builds).
+# - output_dir: Where the compiled production bundle goes
+# - bundle_format: "source" keeps files readable, "binary" for deployment
+[build]
+output_dir = "dist"
+bundle_format = "source"
+
+# ------------------------------------------------------------------------------
+# ROUTING CONFIGURATION
+# ------------------------------------------------------------------------------
+# SvelteKit-style routing with +page.luat, +layout.luat and +server.lua:
+#   src/routes/+page.luat             -> /
+#   src/routes/about/+page.luat       -> /about
+#   src/routes/blog/[slug]/+page.luat -> /blog/:slug (dynamic route)
+#
+# Set simplified = true for direct file-to-route mapping instead:
+#   templates/about.luat -> /about
+[routing]
+simplified = false
+routes_dir = "src/routes"
+lib_dir = "src/lib"
+static_dir = "static"
+app_html = "src/app.html"
+
+# ------------------------------------------------------------------------------
+# KEY-VALUE STORE
+# ------------------------------------------------------------------------------
+# Backend for the `kv` module available in server code.
+# - "sqlite": persistent, one database per namespace
+# - "memory": in-process, cleared on every restart
+[kv]
+backend = "{{kv_backend}}"
+
+# ------------------------------------------------------------------------------
+# FRONTEND TOOLS
+# ------------------------------------------------------------------------------
+# Luat can automatically compile your frontend assets.
+#
+# ESBUILD:
+#   Ultra-fast bundler for TypeScript/JavaScript. Compiles your client-side
+#   code from assets/js/ to public/js/.
+#
+{{css_comment}}[frontend]
+enabled = {{enabled}}
+{{css_settings}}esbuild_version = "0.24.0"
+typescript_entrypoint = "assets/js/app.ts"
+typescript_output = "public/js/app.js"
+"#;
+
 fn resolve_project_path(name: Option<String>) -> anyhow::Result<(std::path::PathBuf, String)> {
     match name.as_deref() {
         Some(".") | None => {
@@ -157,8 +517,17 @@ fn extract_entry(entry: &DirEntry, target: &Path, project_name: &str) -> anyhow:
     Ok(())
 }
 
-fn print_success(project_name: &str, template_name: &str, is_current_dir: bool) {
-    println!("Created luat project: {} ({} template)", project_name, template_name);
+fn print_success(project_name: &str, options: &InitOptions, is_current_dir: bool) {
+    println!(
+        "Created luat project: {} ({} template, {} CSS, {} KV)",
+        project_name,
+        options.template,
+        options.css.name(),
+        match options.kv {
+            KvBackend::Sqlite => "sqlite",
+            KvBackend::Memory => "memory",
+        }
+    );
     println!();
     println!("Next steps:");
     if !is_current_dir {
@@ -167,8 +536,79 @@ fn print_success(project_name: &str, template_name: &str, is_current_dir: bool)
     println!("  npm install");
     println!("  luat dev");
 
-    if template_name == "default" {
+    if options.examples {
         println!();
         println!("Visit http://localhost:3000/todos to see the HTMX example.");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_prompt_options_defaults() {
+        let options = prompt_options(&mut Cursor::new(""), None).unwrap();
+        assert_eq!(options, InitOptions::default());
+    }
+
+    #[test]
+    fn test_prompt_options_answers() {
+        let mut input = Cursor::new("sass\n2\nn\nno\n");
+        let options = prompt_options(&mut input, Some("default".to_string())).unwrap();
+        assert_eq!(
+            options,
+            InitOptions {
+                template: "default".to_string(),
+                css: CssTool::Sass,
+                kv: KvBackend::Memory,
+                git: false,
+                examples: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_render_config_matches_options() {
+        for css in [CssTool::Tailwind, CssTool::Sass, CssTool::None] {
+            let options = InitOptions {
+                css,
+                kv: KvBackend::Memory,
+                ..InitOptions::default()
+            };
+            let config: Config = toml::from_str(&render_config("demo", &options)).unwrap();
+            assert_eq!(config.project.name, "demo");
+            assert_eq!(config.kv.backend, KvBackend::Memory);
+            assert_eq!(
+                config.frontend.enabled.contains(&"tailwind".to_string()),
+                css == CssTool::Tailwind
+            );
+            assert_eq!(
+                config.frontend.enabled.contains(&"sass".to_string()),
+                css == CssTool::Sass
+            );
+        }
+    }
+
+    #[test]
+    fn test_apply_options_without_examples() {
+        let dir = tempfile::tempdir().unwrap();
+        extract_template(&DEFAULT_TEMPLATE, dir.path(), "demo").unwrap();
+        let options = InitOptions {
+            git: false,
+            examples: false,
+            ..InitOptions::default()
+        };
+        apply_options(dir.path(), "demo", &options).unwrap();
+
+        assert!(dir.path().join("luat.toml").exists());
+        assert!(dir.path().join("src/routes/+page.luat").exists());
+        assert!(!dir.path().join("src/routes/todos").exists());
+        assert!(!dir.path().join("src/lib/todos.lua").exists());
+        let layout = fs::read_to_string(dir.path().join("src/routes/+layout.luat")).unwrap();
+        assert!(!layout.contains("href=\"/todos\""));
+        assert!(!dir.path().join(".git").exists());
+    }
+}
@@ -0,0 +1,249 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Compilation stage inspector (`luat inspect`).
+//!
+//! Prints what the compiler makes of a single `.luat` file: the parsed AST,
+//! the IR handed to code generation, the generated Lua and the modules it
+//! requires. Attach the output to compiler bug reports.
+//!
+//! Templates under the routes directory are resolved like `luat dev` does;
+//! other files are resolved from the project root.
+
+use std::io::Write;
+use std::path::Path;
+
+use console::style;
+use luat::{CompileDebug, Engine, FileSystemResolver};
+use regex::Regex;
+
+use crate::config::Config;
+
+/// Stages to print. With none selected, everything is printed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InspectOptions {
+    /// Print the AST.
+    pub ast: bool,
+    /// Print the IR.
+    pub ir: bool,
+    /// Print the generated Lua.
+    pub lua: bool,
+    /// Print the required modules.
+    pub deps: bool,
+}
+
+impl InspectOptions {
+    fn all_if_none(self) -> Self {
+        if self.ast || self.ir || self.lua || self.deps {
+            self
+        } else {
+            Self {
+                ast: true,
+                ir: true,
+                lua: true,
+                deps: true,
+            }
+        }
+    }
+}
+
+/// Lua keywords highlighted in generated code.
+const LUA_KEYWORDS: [&str; 22] = [
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+/// Kind of a highlighted span of Lua source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// A reserved word.
+    Keyword,
+    /// A string literal, including long brackets.
+    String,
+    /// A number literal.
+    Number,
+    /// A line or block comment.
+    Comment,
+    /// Anything else.
+    Plain,
+}
+
+/// Splits Lua source into highlightable spans. Concatenating the spans
+/// yields the input again.
+pub fn lua_tokens(code: &str) -> Vec<(TokenKind, &str)> {
+    // Long brackets with up to one `=` cover what the code generator emits
+    let re = Regex::new(
+        r#"(?s)(?P<comment>--\[\[.*?\]\]|--\[=\[.*?\]=\]|--[^\n]*)|(?P<string>"(?:[^"\\\n]|\\.)*"|'(?:[^'\\\n]|\\.)*'|\[\[.*?\]\]|\[=\[.*?\]=\])|(?P<number>\b0[xX][0-9a-fA-F]+\b|\b\d+(?:\.\d+)?(?:[eE][+-]?\d+)?\b)|(?P<word>\b[A-Za-z_][A-Za-z0-9_]*\b)"#,
+    )
+    .expect("valid regex");
+
+    let mut tokens = Vec::new();
+    let mut last = 0;
+    for caps in re.captures_iter(code) {
+        let whole = caps.get(0).expect("match");
+        let kind = if caps.name("comment").is_some() {
+            TokenKind::Comment
+        } else if caps.name("string").is_some() {
+            TokenKind::String
+        } else if caps.name("number").is_some() {
+            TokenKind::Number
+        } else if LUA_KEYWORDS.contains(&whole.as_str()) {
+            TokenKind::Keyword
+        } else {
+            TokenKind::Plain
+        };
+        if kind == TokenKind::Plain {
+            continue;
+        }
+        if whole.start() > last {
+            tokens.push((TokenKind::Plain, &code[last..whole.start()]));
+        }
+        tokens.push((kind, whole.as_str()));
+        last = whole.end();
+    }
+    if last < code.len() {
+        tokens.push((TokenKind::Plain, &code[last..]));
+    }
+    tokens
+}
+
+/// Highlights Lua source for the terminal, with line numbers.
+pub fn highlight_lua(code: &str) -> String {
+    let mut highlighted = String::new();
+    for (kind, text) in lua_tokens(code) {
+        // Style each line separately so line numbers can be inserted between them
+        let styled: Vec<String> = text
+            .split('\n')
+            .map(|part| match kind {
+                TokenKind::Keyword => style(part).magenta().bold().to_string(),
+                TokenKind::String => style(part).green().to_string(),
+                TokenKind::Number => style(part).yellow().to_string(),
+                TokenKind::Comment => style(part).dim().to_string(),
+                TokenKind::Plain => part.to_string(),
+            })
+            .collect();
+        highlighted.push_str(&styled.join("\n"));
+    }
+
+    let width = code.lines().count().to_string().len();
+    highlighted
+        .lines()
+        .enumerate()
+        .map(|(i, line)| format!("{} {}\n", style(format!("{:>width$} │", i + 1, width = width)).dim(), line))
+        .collect()
+}
+
+/// Highlights a `{:#?}` dump: type names in cyan, strings in green.
+fn highlight_dump(dump: &str) -> String {
+    let re = Regex::new(r#"(?P<string>"(?:[^"\\]|\\.)*")|(?P<name>\b[A-Z][A-Za-z0-9_]*\b)"#).expect("valid regex");
+    re.replace_all(dump, |caps: &regex::Captures| {
+        if let Some(string) = caps.name("string") {
+            style(string.as_str()).green().to_string()
+        } else {
+            style(&caps[0]).cyan().to_string()
+        }
+    })
+    .into_owned()
+}
+
+fn heading(out: &mut String, title: &str) {
+    out.push('\n');
+    out.push_str(&format!("{}\n", style(format!("── {} ──", title)).bold()));
+}
+
+/// Renders the selected stages of `debug`.
+fn render_stages(debug: &CompileDebug, options: InspectOptions) -> String {
+    let mut out = format!("{} {}\n", style("Template:").dim(), debug.path);
+    if options.ast {
+        heading(&mut out, "AST");
+        out.push_str(&highlight_dump(&debug.ast_dump()));
+        out.push('\n');
+    }
+    if options.ir {
+        heading(&mut out, "IR");
+        out.push_str(&highlight_dump(&debug.ir_dump()));
+        out.push('\n');
+    }
+    if options.lua {
+        heading(&mut out, "Lua");
+        out.push_str(&highlight_lua(&debug.lua_code));
+    }
+    if options.deps {
+        heading(&mut out, "Dependencies");
+        if debug.dependencies.is_empty() {
+            out.push_str(&format!("{}\n", style("(none)").dim()));
+        }
+        for dependency in &debug.dependencies {
+            let target = match &dependency.resolved {
+                Some(path) => path.clone(),
+                None => style("not found").red().to_string(),
+            };
+            out.push_str(&format!("  {} {} {}\n", dependency.module, style("→").dim(), target));
+        }
+    }
+    out
+}
+
+/// Runs `luat inspect` for `template`, a path relative to the current directory.
+pub fn run(template: &str, options: InspectOptions) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let working_dir = std::env::current_dir()?;
+    let file = working_dir.join(template);
+    if !file.is_file() {
+        anyhow::bail!("Template not found: {}", template);
+    }
+
+    let routes_root = working_dir.join(&config.routing.routes_dir);
+    let (root, entry) = match file.strip_prefix(&routes_root) {
+        Ok(relative) => (routes_root.clone(), relative.to_path_buf()),
+        Err(_) => (working_dir.clone(), Path::new(template).to_path_buf()),
+    };
+
+    let resolver = config.routing.source_roots.iter().fold(
+        FileSystemResolver::new(&root)
+            .with_lib_dir(working_dir.join(&config.routing.lib_dir))
+            .with_modules_dir(working_dir.join(&config.routing.modules_dir)),
+        |resolver, source_root| resolver.with_source_root(working_dir.join(source_root)),
+    );
+    let mut engine = Engine::with_memory_cache(resolver, 10)?;
+    engine.set_root_path(&working_dir);
+    config.apply_component_dirs(&engine);
+
+    let debug = engine.compile_debug(&entry.to_string_lossy().replace('\\', "/"))?;
+    let output = render_stages(&debug, options.all_if_none());
+
+    // Output is often piped into `less` or `head`; a closed pipe is not an error
+    match std::io::stdout().write_all(output.as_bytes()) {
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lua_tokens() {
+        let code = "local s = \"end\" -- done\nif n == 0x1F then return [[x]] end";
+        let tokens = lua_tokens(code);
+
+        assert_eq!(tokens.iter().map(|(_, t)| *t).collect::<String>(), code);
+        let kinds: Vec<(TokenKind, &str)> = tokens.into_iter().filter(|(k, _)| *k != TokenKind::Plain).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (TokenKind::Keyword, "local"),
+                (TokenKind::String, "\"end\""),
+                (TokenKind::Comment, "-- done"),
+                (TokenKind::Keyword, "if"),
+                (TokenKind::Number, "0x1F"),
+                (TokenKind::Keyword, "then"),
+                (TokenKind::Keyword, "return"),
+                (TokenKind::String, "[[x]]"),
+                (TokenKind::Keyword, "end"),
+            ]
+        );
+    }
+}
@@ -6,19 +6,52 @@
 //!
 //! This module contains the implementations for all LUAT CLI commands:
 //!
+//! - `analyze`: Report dead templates, unused imports and unread props
 //! - `build`: Compile templates for production
 //! - `dev`: Start development server with hot reload
+//! - `doctor`: Check the project setup and environment
+//! - `export`: Prerender the production build into a static site
 //! - `init`: Initialize a new LUAT project
+//! - `inspect`: Print the AST, IR and Lua generated for a template
+//! - `new`: Scaffold routes, components and actions
+//! - `openapi`: Generate an OpenAPI document for the API routes
+//! - `pack` / `add`: Package and install component libraries
+//! - `preview`: Render a single component with fixture props
+//! - `render`: Render a template or route URL once, outside a server
+//! - `seed`: Run seed and migration scripts against the KV store
 //! - `serve`: Serve a production build
+//! - `shell`: Evaluate Lua interactively in the project environment
 //! - `watch`: Watch files and rebuild on changes
 
+/// Template usage analysis command.
+pub mod analyze;
 /// Production build command.
 pub mod build;
 /// Development server command.
 pub mod dev;
+/// Project diagnostics command.
+pub mod doctor;
+/// Static site export command.
+pub mod export;
+/// Scaffolding generator (`luat new`).
+pub mod generate;
 /// Project initialization command.
 pub mod init;
+/// Compilation stage inspector.
+pub mod inspect;
+/// OpenAPI document command.
+pub mod openapi;
+/// Component package commands (pack, add).
+pub mod package;
+/// Component preview command.
+pub mod preview;
+/// One-off render command.
+pub mod render;
+/// Seed script command.
+pub mod seed;
 /// Production server command.
 pub mod serve;
+/// Interactive Lua shell command.
+pub mod shell;
 /// File watch command.
 pub mod watch;
@@ -0,0 +1,222 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! OpenAPI documentation for API routes (`luat openapi`).
+//!
+//! Every route with a `+server.lua` becomes a path of an OpenAPI 3 document,
+//! with an operation for each method handler the module exports and the
+//! parameters of its route pattern:
+//!
+//! ```text
+//! luat openapi --out dist/openapi.json
+//! ```
+//!
+//! A module describes itself further by exporting a `spec` table. Keys named
+//! after a method hold that operation's fields (`summary`, `parameters`,
+//! `requestBody`, `responses`, ...); `tags` applies to every operation; the
+//! other keys (`summary`, `description`, `parameters`) describe the path:
+//!
+//! ```lua
+//! spec = {
+//!     tags = { "posts" },
+//!     GET = {
+//!         summary = "Get a post",
+//!         responses = { ["200"] = { description = "The post" } },
+//!     },
+//! }
+//!
+//! function GET(ctx) ... end
+//! ```
+//!
+//! Operations without `responses` get a plain `200` response.
+
+use std::fs;
+
+use console::style;
+use luat::ApiSpec;
+use serde_json::{json, Map, Value};
+
+use crate::commands::build::project_source_dir;
+use crate::commands::render::{project_kv_manager, render_engine};
+use crate::config::Config;
+use crate::router::Router as LuatRouter;
+use crate::server::http::cli_route_to_engine_route;
+
+/// Options for `luat openapi`.
+#[derive(Debug, Clone, Default)]
+pub struct OpenApiOptions {
+    /// File the document is written to, instead of stdout.
+    pub out: Option<String>,
+}
+
+/// Writes the OpenAPI document of the project's API routes.
+pub fn run(options: OpenApiOptions) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let working_dir = std::env::current_dir()?;
+    let source_dir = project_source_dir(&config);
+    let routes_dir = working_dir.join(&source_dir);
+    if config.routing.simplified || !routes_dir.exists() {
+        anyhow::bail!("API routes need SvelteKit-style routing");
+    }
+
+    let engine = render_engine(&config, &working_dir, &source_dir, &project_kv_manager(&config, &working_dir)?)?;
+    let router = LuatRouter::discover(&routes_dir)?;
+    let mut paths = Map::new();
+    for route in router.routes().iter().filter(|route| route.api.is_some()) {
+        let engine_route = cli_route_to_engine_route(route, &[], &routes_dir);
+        let Some(api) = engine.api_spec(&engine_route)? else {
+            continue;
+        };
+        let (path, params) = openapi_path(&route.pattern);
+        for warning in spec_warnings(&api) {
+            eprintln!("{} {}: {}", style("warning:").yellow(), path, warning);
+        }
+        paths.insert(path, path_item(&params, &api));
+    }
+
+    let count = paths.len();
+    let document = json!({
+        "openapi": "3.0.3",
+        "info": { "title": config.project.name, "version": config.project.version },
+        "paths": paths,
+    });
+    let output = serde_json::to_string_pretty(&document)?;
+    match &options.out {
+        Some(out) => {
+            let path = working_dir.join(out);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, output + "\n")?;
+            eprintln!("{} {} ({} paths)", style("Wrote").green(), out, count);
+        }
+        None => println!("{}", output),
+    }
+    Ok(())
+}
+
+/// Converts a route pattern to an OpenAPI path and its parameter names:
+/// `/files/{*rest}` becomes `/files/{rest}`.
+fn openapi_path(pattern: &str) -> (String, Vec<String>) {
+    let mut params = Vec::new();
+    let segments: Vec<String> = pattern
+        .split('/')
+        .map(|segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            Some(name) => {
+                let name = name.trim_start_matches('*');
+                params.push(name.to_string());
+                format!("{{{}}}", name)
+            }
+            None => segment.to_string(),
+        })
+        .collect();
+    (segments.join("/"), params)
+}
+
+/// Builds the path item of a route from its parameters and module spec.
+fn path_item(params: &[String], api: &ApiSpec) -> Value {
+    let spec = api.spec.as_object().cloned().unwrap_or_default();
+    let tags = spec.get("tags").cloned();
+    let mut item = Map::new();
+
+    for (key, value) in &spec {
+        if !is_method(key) && key != "tags" {
+            item.insert(key.clone(), value.clone());
+        }
+    }
+
+    // Path parameters must be declared; keep the ones the spec declares
+    let mut parameters = item.get("parameters").and_then(Value::as_array).cloned().unwrap_or_default();
+    for name in params {
+        let declared = parameters.iter().any(|p| p["name"] == name.as_str() && p["in"] == "path");
+        if !declared {
+            parameters.push(json!({ "name": name, "in": "path", "required": true, "schema": { "type": "string" } }));
+        }
+    }
+    if !parameters.is_empty() {
+        item.insert("parameters".to_string(), Value::Array(parameters));
+    }
+
+    for method in &api.methods {
+        let mut operation = spec.get(method.as_str()).and_then(Value::as_object).cloned().unwrap_or_default();
+        if let Some(tags) = &tags {
+            operation.entry("tags").or_insert_with(|| tags.clone());
+        }
+        operation
+            .entry("responses")
+            .or_insert_with(|| json!({ "200": { "description": "OK" } }));
+        item.insert(method.to_lowercase(), Value::Object(operation));
+    }
+    Value::Object(item)
+}
+
+/// Returns problems with a module's spec: operations for methods it has no
+/// handler for, and a `spec` that isn't a table of fields.
+fn spec_warnings(api: &ApiSpec) -> Vec<String> {
+    let Some(spec) = api.spec.as_object() else {
+        return match api.spec {
+            Value::Null => Vec::new(),
+            _ => vec!["spec is not a table of fields".to_string()],
+        };
+    };
+    spec.keys()
+        .filter(|key| is_method(key) && !api.methods.contains(key))
+        .map(|key| format!("spec documents {} but there is no {} handler", key, key))
+        .collect()
+}
+
+fn is_method(key: &str) -> bool {
+    matches!(key, "GET" | "HEAD" | "POST" | "PUT" | "PATCH" | "DELETE" | "OPTIONS")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi_path() {
+        assert_eq!(openapi_path("/api/hello"), ("/api/hello".to_string(), vec![]));
+        assert_eq!(
+            openapi_path("/api/{user}/files/{*rest}"),
+            ("/api/{user}/files/{rest}".to_string(), vec!["user".to_string(), "rest".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_path_item() {
+        let api = ApiSpec {
+            methods: vec!["GET".to_string(), "DELETE".to_string()],
+            spec: json!({
+                "tags": ["posts"],
+                "description": "A post",
+                "GET": { "summary": "Get a post", "responses": { "200": { "description": "The post" } } },
+                "POST": { "summary": "Not handled" },
+            }),
+        };
+        let item = path_item(&["slug".to_string()], &api);
+
+        assert_eq!(item["description"], "A post");
+        assert_eq!(item["parameters"][0]["name"], "slug");
+        assert_eq!(item["parameters"][0]["in"], "path");
+        assert_eq!(item["get"]["summary"], "Get a post");
+        assert_eq!(item["get"]["tags"], json!(["posts"]));
+        assert_eq!(item["get"]["responses"]["200"]["description"], "The post");
+        assert_eq!(item["delete"]["responses"]["200"]["description"], "OK");
+        assert!(item.get("post").is_none() && item.get("tags").is_none());
+        assert_eq!(spec_warnings(&api), ["spec documents POST but there is no POST handler"]);
+    }
+
+    #[test]
+    fn test_path_item_keeps_declared_parameters() {
+        let api = ApiSpec {
+            methods: vec!["GET".to_string()],
+            spec: json!({
+                "parameters": [{ "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }],
+            }),
+        };
+        let item = path_item(&["id".to_string()], &api);
+        assert_eq!(item["parameters"].as_array().unwrap().len(), 1);
+        assert_eq!(item["parameters"][0]["schema"]["type"], "integer");
+    }
+}
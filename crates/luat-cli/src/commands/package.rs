@@ -0,0 +1,483 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Component library packaging (`luat pack`) and installation (`luat add`).
+//!
+//! A package is a directory of `.luat`/`.lua` components with a
+//! `luat-package.toml` manifest:
+//!
+//! ```toml
+//! [package]
+//! name = "ui"
+//! version = "0.1.0"
+//! description = "Shared UI components"
+//! ```
+//!
+//! `luat pack` writes `<name>-<version>.tar.gz`; `luat add` unpacks an
+//! archive (or copies a package directory) into `luat_modules/<name>/`, where
+//! `require("ui/Button")` and `$lib/ui/Button` can find it.
+//!
+//! Packages can also be declared as git dependencies in `luat.toml`; they are
+//! fetched into `.luat/git/` and vendored the same way:
+//!
+//! ```toml
+//! [dependencies]
+//! ui = { git = "https://github.com/acme/ui-components", rev = "v1.2.0" }
+//! ```
+
+use crate::config::{Config, GitDependency};
+use console::style;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// File name of the package manifest.
+pub const PACKAGE_MANIFEST: &str = "luat-package.toml";
+
+/// Records the repository and commit a git dependency was vendored from.
+const GIT_STAMP_FILE: &str = ".luat-git";
+
+/// Parsed `luat-package.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackageManifest {
+    /// Package metadata.
+    pub package: PackageInfo,
+}
+
+/// The `[package]` table of a package manifest.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackageInfo {
+    /// Package name, used as the directory under `luat_modules/`.
+    pub name: String,
+    /// Package version.
+    pub version: String,
+    /// Optional one-line description.
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl PackageManifest {
+    /// Parses and validates a manifest.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the TOML is invalid or the name is not a single
+    /// path segment of letters, digits, `-` and `_`.
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        let manifest: PackageManifest = toml::from_str(content)?;
+        if !is_valid_package_name(&manifest.package.name) {
+            anyhow::bail!("Invalid package name '{}'", manifest.package.name);
+        }
+        Ok(manifest)
+    }
+
+    /// Reads the manifest from a package directory.
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let path = dir.join(PACKAGE_MANIFEST);
+        let content = fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?;
+        Self::parse(&content)
+    }
+}
+
+/// Runs `luat pack`: packages `dir` into an archive in `output`.
+pub fn run_pack(dir: &str, output: &str) -> anyhow::Result<()> {
+    let archive = pack(Path::new(dir), Path::new(output))?;
+    println!(
+        "{} {}",
+        style("Packed").green().bold(),
+        archive.display()
+    );
+    Ok(())
+}
+
+/// Runs `luat add`: installs a package archive or directory into the project.
+pub fn run_add(package: &str) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let manifest = install(Path::new(package), Path::new(&config.routing.modules_dir))?;
+    println!(
+        "{} {}@{} -> {}/{}",
+        style("Installed").green().bold(),
+        manifest.package.name,
+        manifest.package.version,
+        config.routing.modules_dir,
+        manifest.package.name
+    );
+    Ok(())
+}
+
+/// Fetches the project's `[dependencies]` and vendors them into the modules directory.
+///
+/// Called by `luat build` and `luat dev`. Dependencies whose vendored copy
+/// already matches the requested revision are not fetched again.
+pub fn sync_dependencies(config: &Config) -> anyhow::Result<()> {
+    if config.dependencies.is_empty() {
+        return Ok(());
+    }
+
+    let modules_dir = Path::new(&config.routing.modules_dir);
+    let checkouts_dir = Path::new(".luat").join("git");
+    for (name, dep) in &config.dependencies {
+        if let Some(commit) = sync_git_dependency(name, dep, modules_dir, &checkouts_dir)? {
+            println!(
+                "{} {} ({} @ {})",
+                style("Fetched").green(),
+                name,
+                dep.git,
+                &commit[..commit.len().min(12)]
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Vendors a single git dependency into `modules_dir/<name>/`.
+///
+/// Returns the checked-out commit, or `None` if the vendored copy was
+/// already up to date.
+pub fn sync_git_dependency(
+    name: &str,
+    dep: &GitDependency,
+    modules_dir: &Path,
+    checkouts_dir: &Path,
+) -> anyhow::Result<Option<String>> {
+    if !is_valid_package_name(name) {
+        anyhow::bail!("Invalid dependency name '{}'", name);
+    }
+    // Values starting with `-` would be read by git as options
+    if dep.git.starts_with('-') {
+        anyhow::bail!("Invalid git URL '{}' for dependency '{}'", dep.git, name);
+    }
+    if let Some(rev) = dep.rev.as_deref().filter(|rev| rev.starts_with('-')) {
+        anyhow::bail!("Invalid rev '{}' for dependency '{}'", rev, name);
+    }
+
+    // Pinned revisions are only fetched again when the pin changes
+    let dest = modules_dir.join(name);
+    let source = format!(
+        "git={}\nrev={}\n",
+        dep.git,
+        dep.rev.as_deref().unwrap_or_default()
+    );
+    if dep.rev.is_some() {
+        if let Ok(stamp) = fs::read_to_string(dest.join(GIT_STAMP_FILE)) {
+            if stamp.starts_with(&source) {
+                return Ok(None);
+            }
+        }
+    }
+
+    let checkout = checkouts_dir.join(name);
+    let same_remote = git(&checkout, &["remote", "get-url", "origin"])
+        .map(|url| url == dep.git)
+        .unwrap_or(false);
+    if same_remote {
+        git(&checkout, &["fetch", "--quiet", "--tags", "origin"])?;
+    } else {
+        if checkout.exists() {
+            fs::remove_dir_all(&checkout)?;
+        }
+        fs::create_dir_all(checkouts_dir)?;
+        git(
+            checkouts_dir,
+            &["clone", "--quiet", "--", &dep.git, name],
+        )?;
+    }
+
+    let target = match &dep.rev {
+        Some(rev) => rev.clone(),
+        None => "HEAD".to_string(),
+    };
+    // Branch names resolve against the fetched remote, tags and commits directly
+    let remote_branch = format!("origin/{}", target);
+    let checkout_target = if git(&checkout, &["rev-parse", "--verify", "--quiet", &remote_branch])
+        .is_ok()
+    {
+        remote_branch
+    } else {
+        target
+    };
+    git(&checkout, &["checkout", "--quiet", "--detach", &checkout_target, "--"])?;
+    let commit = git(&checkout, &["rev-parse", "HEAD"])?;
+
+    let staging = staging_dir(modules_dir)?;
+    let result = copy_component_files(&checkout, &staging)
+        .and_then(|_| {
+            fs::write(
+                staging.join(GIT_STAMP_FILE),
+                format!("{}commit={}\n", source, commit),
+            )?;
+            replace_dir(&staging, &dest)
+        });
+    if staging.exists() {
+        let _ = fs::remove_dir_all(&staging);
+    }
+    result?;
+
+    Ok(Some(commit))
+}
+
+/// Runs a git command in `dir` and returns its trimmed stdout.
+fn git(dir: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Packages the components in `dir` into `<output>/<name>-<version>.tar.gz`.
+///
+/// The archive contains the manifest and every `.luat`/`.lua` file, under a
+/// top-level `<name>/` directory. Hidden directories and the package's own
+/// modules directory (`[routing] modules_dir`) are skipped.
+pub fn pack(dir: &Path, output: &Path) -> anyhow::Result<PathBuf> {
+    let manifest = PackageManifest::load(dir)?;
+    let name = &manifest.package.name;
+
+    let mut files = vec![PathBuf::from(PACKAGE_MANIFEST)];
+    collect_component_files(dir, &mut files)?;
+    files.sort();
+
+    fs::create_dir_all(output)?;
+    let archive_path = output.join(format!("{}-{}.tar.gz", name, manifest.package.version));
+    let file = fs::File::create(&archive_path)?;
+    let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+    for relative in &files {
+        builder.append_path_with_name(dir.join(relative), Path::new(name).join(relative))?;
+    }
+    builder.into_inner()?.finish()?;
+
+    Ok(archive_path)
+}
+
+/// Installs a package into `modules_dir/<name>/`, replacing any previous version.
+///
+/// `source` is either a `.tar.gz` produced by [`pack`] or a package directory.
+pub fn install(source: &Path, modules_dir: &Path) -> anyhow::Result<PackageManifest> {
+    let staging = staging_dir(modules_dir)?;
+    let result = stage_package(source, &staging).and_then(|manifest| {
+        replace_dir(&staging, &modules_dir.join(&manifest.package.name))?;
+        Ok(manifest)
+    });
+
+    if staging.exists() {
+        let _ = fs::remove_dir_all(&staging);
+    }
+    result
+}
+
+/// Unpacks or copies a package into `staging` and returns its manifest.
+fn stage_package(source: &Path, staging: &Path) -> anyhow::Result<PackageManifest> {
+    if source.is_dir() {
+        let manifest = PackageManifest::load(source)?;
+        copy_component_files(source, staging)?;
+        return Ok(manifest);
+    }
+
+    let file = fs::File::open(source)
+        .map_err(|e| anyhow::anyhow!("Cannot open package {}: {}", source.display(), e))?;
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.into_owned();
+        // Strip the top-level `<name>/` directory and reject anything unsafe
+        let relative: PathBuf = path.components().skip(1).collect();
+        if relative.as_os_str().is_empty()
+            || !relative.components().all(|c| matches!(c, Component::Normal(_)))
+            || !is_package_file(&relative)
+        {
+            anyhow::bail!("Unexpected file in package: {}", path.display());
+        }
+        let dest = staging.join(&relative);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        entry.unpack(&dest)?;
+    }
+
+    PackageManifest::load(staging)
+}
+
+/// Copies the manifest (if any) and all `.luat`/`.lua` files from `source` to `dest`.
+fn copy_component_files(source: &Path, dest: &Path) -> anyhow::Result<()> {
+    let mut files = Vec::new();
+    if source.join(PACKAGE_MANIFEST).is_file() {
+        files.push(PathBuf::from(PACKAGE_MANIFEST));
+    }
+    collect_component_files(source, &mut files)?;
+    for relative in files {
+        let target = dest.join(&relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(source.join(&relative), target)?;
+    }
+    Ok(())
+}
+
+/// Moves a staged package into place, replacing any previous contents.
+fn replace_dir(staging: &Path, dest: &Path) -> anyhow::Result<()> {
+    if dest.exists() {
+        fs::remove_dir_all(dest)?;
+    }
+    fs::rename(staging, dest)?;
+    Ok(())
+}
+
+/// Creates an empty staging directory inside `modules_dir`.
+fn staging_dir(modules_dir: &Path) -> anyhow::Result<PathBuf> {
+    fs::create_dir_all(modules_dir)?;
+    let staging = modules_dir.join(format!(".staging-{}", std::process::id()));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    fs::create_dir_all(&staging)?;
+    Ok(staging)
+}
+
+fn is_valid_package_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn is_package_file(path: &Path) -> bool {
+    path == Path::new(PACKAGE_MANIFEST)
+        || matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("luat") | Some("lua")
+        )
+}
+
+/// Recursively collects `.luat`/`.lua` files relative to `root`, skipping
+/// hidden directories and the modules directory `root`'s `luat.toml` sets.
+fn collect_component_files(root: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    let config = Config::load_from(root).unwrap_or_else(|_| Config::defaults());
+    let modules_dir = root.join(&config.routing.modules_dir);
+    collect_files_in(root, root, &modules_dir, files)
+}
+
+fn collect_files_in(root: &Path, dir: &Path, modules_dir: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        if path.is_dir() {
+            if !file_name.starts_with('.') && path != modules_dir {
+                collect_files_in(root, &path, modules_dir, files)?;
+            }
+        } else if is_package_file(&path) && file_name != PACKAGE_MANIFEST {
+            files.push(path.strip_prefix(root)?.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_package(dir: &Path) {
+        fs::create_dir_all(dir.join("forms")).unwrap();
+        fs::write(
+            dir.join(PACKAGE_MANIFEST),
+            "[package]\nname = \"ui\"\nversion = \"1.2.0\"\n",
+        )
+        .unwrap();
+        fs::write(dir.join("Button.luat"), "<button>{props.label}</button>").unwrap();
+        fs::write(dir.join("forms/Input.luat"), "<input>").unwrap();
+        fs::write(dir.join("README.md"), "not packaged").unwrap();
+    }
+
+    #[test]
+    fn test_pack_and_install() {
+        let temp = TempDir::new().unwrap();
+        let src = temp.path().join("ui-src");
+        write_package(&src);
+
+        // The package's own modules directory is not packed
+        fs::write(src.join("luat.toml"), "[project]\nname = \"ui\"\nversion = \"1.2.0\"\n\n[routing]\nmodules_dir = \"vendor\"\n").unwrap();
+        fs::create_dir_all(src.join("vendor/icons")).unwrap();
+        fs::write(src.join("vendor/icons/Icon.luat"), "<i></i>").unwrap();
+
+        let archive = pack(&src, &temp.path().join("out")).unwrap();
+        assert!(archive.ends_with("ui-1.2.0.tar.gz"));
+
+        let modules = temp.path().join("luat_modules");
+        let manifest = install(&archive, &modules).unwrap();
+        assert_eq!(manifest.package.name, "ui");
+        assert!(modules.join("ui/Button.luat").exists());
+        assert!(modules.join("ui/forms/Input.luat").exists());
+        assert!(modules.join("ui").join(PACKAGE_MANIFEST).exists());
+        assert!(!modules.join("ui/README.md").exists());
+        assert!(!modules.join("ui/vendor").exists());
+
+        // Reinstalling from a directory replaces the previous version
+        fs::remove_file(src.join("forms/Input.luat")).unwrap();
+        install(&src, &modules).unwrap();
+        assert!(!modules.join("ui/forms/Input.luat").exists());
+    }
+
+    #[test]
+    fn test_sync_git_dependency() {
+        let temp = TempDir::new().unwrap();
+        let repo = temp.path().join("ui-repo");
+        fs::create_dir_all(repo.join("forms")).unwrap();
+        fs::write(repo.join("Button.luat"), "<button>{props.label}</button>").unwrap();
+        fs::write(repo.join("forms/Input.luat"), "<input>").unwrap();
+        for args in [
+            vec!["init", "--quiet"],
+            vec!["add", "."],
+            vec!["-c", "user.name=test", "-c", "user.email=test@example.com", "commit", "--quiet", "-m", "init"],
+            vec!["tag", "v1"],
+        ] {
+            git(&repo, &args).unwrap();
+        }
+
+        let dep = GitDependency {
+            git: repo.to_string_lossy().to_string(),
+            rev: Some("v1".to_string()),
+        };
+        let modules = temp.path().join("luat_modules");
+        let checkouts = temp.path().join(".luat/git");
+
+        let commit = sync_git_dependency("ui", &dep, &modules, &checkouts).unwrap();
+        assert_eq!(commit.unwrap().len(), 40);
+        assert!(modules.join("ui/Button.luat").exists());
+        assert!(modules.join("ui/forms/Input.luat").exists());
+        assert!(!modules.join("ui/.git").exists());
+
+        // A pinned revision that is already vendored is not fetched again
+        assert!(sync_git_dependency("ui", &dep, &modules, &checkouts).unwrap().is_none());
+
+        assert!(sync_git_dependency("../ui", &dep, &modules, &checkouts).is_err());
+
+        // Option-like values are refused before git sees them
+        let option_url = GitDependency { git: "--upload-pack=touch pwned".to_string(), rev: None };
+        assert!(sync_git_dependency("x", &option_url, &modules, &checkouts).is_err());
+        let option_rev = GitDependency { rev: Some("--orphan=x".to_string()), ..dep };
+        assert!(sync_git_dependency("x", &option_rev, &modules, &checkouts).is_err());
+    }
+
+    #[test]
+    fn test_invalid_package_name() {
+        assert!(PackageManifest::parse("[package]\nname = \"../x\"\nversion = \"1\"\n").is_err());
+        assert!(PackageManifest::parse("[package]\nname = \"ui-kit\"\nversion = \"1\"\n").is_ok());
+    }
+}
@@ -0,0 +1,44 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Component preview command (`luat preview`).
+//!
+//! Starts the development server and points at the preview route for one
+//! component, rendered with props from a JSON fixture. Changes to the
+//! component or the fixture reload the page.
+
+use crate::commands::dev::{self, DevOptions};
+use crate::server::preview::{load_fixture, preview_url};
+
+/// Previews `component` with props from the `props` fixture.
+///
+/// The fixture is validated before the server starts. A fixture outside
+/// `src/` gets its directory added to the watch list.
+pub async fn run(component: &str, props: Option<String>, options: DevOptions) -> anyhow::Result<()> {
+    let project_dir = std::env::current_dir()?;
+
+    let mut watch = options.watch.clone();
+    if let Some(ref fixture) = props {
+        load_fixture(&project_dir, fixture)?;
+
+        let fixture_dir = project_dir
+            .join(fixture)
+            .canonicalize()?
+            .parent()
+            .map(|dir| dir.to_path_buf());
+        let src_dir = project_dir.join("src").canonicalize().ok();
+        if let Some(dir) = fixture_dir {
+            if src_dir.map_or(true, |src| !dir.starts_with(src)) {
+                watch.push(dir);
+            }
+        }
+    }
+
+    dev::run(DevOptions {
+        open_path: Some(preview_url(component, props.as_deref())),
+        watch,
+        ..options
+    })
+    .await
+}
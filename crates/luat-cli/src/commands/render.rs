@@ -0,0 +1,207 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! One-off rendering (`luat render`).
+//!
+//! Renders a template or a route URL with the project engine, outside any
+//! server, and writes the result to a file or stdout:
+//!
+//! ```text
+//! luat render src/lib/components/Card.luat --props card.json --out card.html
+//! luat render Card --props card.json
+//! luat render /blog/hello?lang=de --out hello.html
+//! ```
+//!
+//! A template is rendered on its own, with the props of the `--props` JSON
+//! file. It is given as a path, or as a component name like `luat preview`
+//! takes. A URL (starting with `/`) is matched against the routes and
+//! rendered like `luat dev` serves it: load functions run, layouts apply and
+//! pages are wrapped in `app.html`.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use console::style;
+use luat::router::Route;
+use luat::{Engine, FileSystemResolver, LuatRequest, LuatResponse};
+
+use crate::commands::build::{project_engine, project_source_dir};
+use crate::commands::serve::{html_escape, wrap_with_app_html, DEFAULT_APP_HTML};
+use crate::config::Config;
+use crate::kv::KVManager;
+use crate::router::Router as LuatRouter;
+use crate::server::http::{cli_route_to_engine_route, collect_head_assets};
+use crate::server::preview::{component_module_path, load_fixture};
+use crate::server::stream::split_at_body_end;
+
+/// Options for `luat render`.
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// JSON file with the template's props.
+    pub props: Option<String>,
+    /// File the output is written to, instead of stdout.
+    pub out: Option<String>,
+}
+
+/// Renders `target`, a template or a URL, and writes the output.
+pub fn run(target: &str, options: RenderOptions) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let working_dir = std::env::current_dir()?;
+    let source_dir = project_source_dir(&config);
+    let engine = render_engine(&config, &working_dir, &source_dir, &project_kv_manager(&config, &working_dir)?)?;
+
+    let output = if target.starts_with('/') {
+        if options.props.is_some() {
+            anyhow::bail!("--props applies to templates; a URL gets its data from the route's load functions");
+        }
+        render_url(&config, &engine, &working_dir.join(&source_dir), target)?
+    } else {
+        let props = match &options.props {
+            Some(fixture) => load_fixture(&working_dir, fixture)?,
+            None => serde_json::json!({}),
+        };
+        let entry = template_entry(&config, &working_dir, &source_dir, target);
+        let module = engine.compile_entry(&entry)?;
+        engine.render(&module, &engine.to_value(props)?)?.into_bytes()
+    };
+
+    match &options.out {
+        Some(out) => {
+            let path = working_dir.join(out);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, &output)?;
+            eprintln!("{} {} ({} bytes)", style("Rendered to").green(), out, output.len());
+            Ok(())
+        }
+        // Output is often piped into other tools; a closed pipe is not an error
+        None => match std::io::stdout().write_all(&output) {
+            Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(e.into()),
+            _ => Ok(()),
+        },
+    }
+}
+
+/// Creates the KV manager of the configured backend and data directory.
+pub(crate) fn project_kv_manager(config: &Config, working_dir: &Path) -> anyhow::Result<Arc<KVManager>> {
+    Ok(Arc::new(KVManager::for_backend(
+        config.kv.backend,
+        working_dir.join(&config.routing.data_dir),
+    )?))
+}
+
+/// Creates the project engine with the modules server code uses in `luat dev`,
+/// its `KV` namespaces backed by `kv_manager`.
+pub(crate) fn render_engine(
+    config: &Config,
+    working_dir: &Path,
+    source_dir: &str,
+    kv_manager: &Arc<KVManager>,
+) -> anyhow::Result<Engine<FileSystemResolver>> {
+    let mut engine = project_engine(config, working_dir, source_dir)?;
+    config.apply_route_headers(&mut engine);
+    engine.set_deterministic(config.dev.deterministic)?;
+
+    let factory = kv_manager.clone().factory();
+    config.flags.apply(&engine, &factory)?;
+    config.auth.apply(&engine, &factory)?;
+    config.i18n.apply(&mut engine, &working_dir.join(&config.i18n.dir))?;
+    config.images.apply(&engine)?;
+    luat::kv::register_kv_module(engine.lua(), factory)?;
+    crate::extensions::register_http_module(engine.lua())?;
+    crate::extensions::register_graphql_module(engine.lua())?;
+    crate::extensions::register_crypto_module(engine.lua())?;
+    crate::extensions::register_jwt_module(engine.lua())?;
+    #[cfg(feature = "grpc")]
+    crate::extensions::register_grpc_module(engine.lua())?;
+    config.storage.apply(&engine)?;
+    #[cfg(feature = "webauthn")]
+    crate::extensions::register_webauthn_module(engine.lua(), kv_manager.clone().factory())?;
+    Ok(engine)
+}
+
+/// Returns the module path of the template `target`: relative to the routes
+/// directory, `lib/...` for the lib directory, or a component name.
+fn template_entry(config: &Config, working_dir: &Path, source_dir: &str, target: &str) -> String {
+    let file = working_dir.join(target);
+    if let Ok(relative) = file.strip_prefix(working_dir.join(source_dir)) {
+        return relative.to_string_lossy().replace('\\', "/");
+    }
+    if let Ok(relative) = file.strip_prefix(working_dir.join(&config.routing.lib_dir)) {
+        return format!("lib/{}", relative.to_string_lossy().replace('\\', "/"));
+    }
+    component_module_path(target)
+}
+
+/// Responds to a GET request for `url` and returns the body, wrapped in
+/// `app.html` for pages.
+fn render_url(
+    config: &Config,
+    engine: &Engine<FileSystemResolver>,
+    routes_dir: &Path,
+    url: &str,
+) -> anyhow::Result<Vec<u8>> {
+    if config.routing.simplified || !routes_dir.exists() {
+        anyhow::bail!("Rendering a URL needs SvelteKit-style routing; pass a template path instead");
+    }
+    let (route, request) = url_request(routes_dir, "GET", url)?;
+
+    let response = engine.respond(&route, &request)?;
+    let head = response.head().cloned().unwrap_or_default();
+    let (status, body, deferred) = match response {
+        LuatResponse::Html { status, body, .. } => (status, body, Vec::new()),
+        LuatResponse::Stream { status, body, deferred, .. } => (status, body, deferred),
+        LuatResponse::Json { status, headers, body } => {
+            ensure_success(url, status)?;
+            return Ok(luat::encode_json_body(&body, &headers).into_bytes());
+        }
+        LuatResponse::Bytes { status, body, .. } => {
+            ensure_success(url, status)?;
+            return Ok(body);
+        }
+        LuatResponse::Redirect { status, location } => {
+            anyhow::bail!("{} redirects to {} (status {})", url, location, status)
+        }
+        LuatResponse::Error { status, message } => anyhow::bail!("{} failed with status {}: {}", url, status, message),
+        _ => anyhow::bail!("{} returned an unsupported response", url),
+    };
+    ensure_success(url, status)?;
+
+    let app_html = fs::read_to_string(&config.routing.app_html).unwrap_or_else(|_| DEFAULT_APP_HTML.to_string());
+    let title = html_escape(head.title.as_deref().unwrap_or("Luat App"));
+    let head_assets = format!("{}{}", head.to_html(), collect_head_assets(config));
+    let mut html = wrap_with_app_html(&app_html, &body, &title, &head_assets);
+    if !deferred.is_empty() {
+        let (start, tail) = split_at_body_end(&html);
+        let mut chunks = String::new();
+        for id in &deferred {
+            chunks.push_str(&engine.resolve_deferred(id, None)?);
+        }
+        html = format!("{}{}{}", start, chunks, tail);
+    }
+    Ok(html.into_bytes())
+}
+
+/// Matches `url` (with an optional query string) against the routes and
+/// returns the route with a `method` request for it.
+pub(crate) fn url_request(routes_dir: &Path, method: &str, url: &str) -> anyhow::Result<(Route, LuatRequest)> {
+    let router = LuatRouter::discover(routes_dir)?;
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let Some(route_match) = router.match_url(path) else {
+        anyhow::bail!("No route matches {}", path);
+    };
+    let route = cli_route_to_engine_route(route_match.route, &route_match.params, &routes_dir.to_path_buf());
+    let query = form_urlencoded::parse(query.as_bytes()).into_owned().collect();
+    Ok((route, LuatRequest::new(path, method).with_query(query)))
+}
+
+fn ensure_success(url: &str, status: u16) -> anyhow::Result<()> {
+    if status >= 400 {
+        anyhow::bail!("{} responded with status {}", url, status);
+    }
+    Ok(())
+}
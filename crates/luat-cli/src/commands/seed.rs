@@ -0,0 +1,181 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Seed and migration scripts (`luat seed`).
+//!
+//! Lua scripts in the seeds directory (`routing.seeds_dir`, default
+//! `src/seeds`) run in file name order, with the modules server code uses in
+//! `luat dev` loaded, against the configured KV backend:
+//!
+//! ```text
+//! src/seeds/001_users.lua
+//! src/seeds/002_posts.lua
+//! ```
+//!
+//! ```lua
+//! local users = KV.namespace("users")
+//! users:put("alice", json.encode({ name = "Alice", role = "admin" }))
+//! ```
+//!
+//! Each script runs once: the ones applied are recorded in the
+//! [`META_NAMESPACE`] namespace, and later runs only apply new scripts, so
+//! seeds double as ordered migrations. The first script that fails stops the
+//! run and is not recorded. `--force` runs every script again; `--status`
+//! lists the scripts without running any.
+
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use console::style;
+use luat::kv::{KVStore, PutOptions};
+
+use crate::commands::build::project_source_dir;
+use crate::commands::render::{project_kv_manager, render_engine};
+use crate::config::{Config, KvBackend};
+
+/// KV namespace the applied scripts are recorded in.
+pub const META_NAMESPACE: &str = "__luat_meta";
+
+/// Key prefix of an applied script's record.
+const APPLIED_PREFIX: &str = "seeds:";
+
+/// Options for `luat seed`.
+#[derive(Debug, Clone, Default)]
+pub struct SeedOptions {
+    /// Run scripts that were applied before again.
+    pub force: bool,
+    /// List applied and pending scripts without running any.
+    pub status: bool,
+}
+
+/// Runs the pending seed scripts.
+pub fn run(options: SeedOptions) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let working_dir = std::env::current_dir()?;
+    let seeds_dir = working_dir.join(&config.routing.seeds_dir);
+    let scripts = discover_seeds(&seeds_dir)?;
+    if scripts.is_empty() {
+        println!("No seed scripts in {}", config.routing.seeds_dir);
+        return Ok(());
+    }
+
+    let kv_manager = project_kv_manager(&config, &working_dir)?;
+    let meta = kv_manager.get_store(META_NAMESPACE);
+
+    if options.status {
+        for script in &scripts {
+            let state = if is_applied(meta.as_ref(), script)? {
+                style("applied").green()
+            } else {
+                style("pending").yellow()
+            };
+            println!("  {:<8} {}", state, script);
+        }
+        return Ok(());
+    }
+
+    if config.kv.backend == KvBackend::Memory {
+        println!(
+            "{} The KV backend is in memory; seeded data is dropped when this command exits",
+            style("warning:").yellow()
+        );
+    }
+
+    let pending = pending_seeds(&scripts, meta.as_ref(), options.force)?;
+    if pending.is_empty() {
+        println!("All {} seed script(s) already applied", scripts.len());
+        return Ok(());
+    }
+
+    let source_dir = project_source_dir(&config);
+    let engine = render_engine(&config, &working_dir, &source_dir, &kv_manager)?;
+    for script in &pending {
+        let source = fs::read_to_string(seeds_dir.join(script))?;
+        engine
+            .lua()
+            .load(source.as_str())
+            .set_name(format!("@{}/{}", config.routing.seeds_dir, script))
+            .exec()
+            .map_err(|e| anyhow::anyhow!("Seed {} failed: {}", script, e))?;
+        mark_applied(meta.as_ref(), script)?;
+        println!("  {} {}", style("✓").green(), script);
+    }
+    println!("Applied {} seed script(s)", pending.len());
+    Ok(())
+}
+
+/// Returns the `.lua` file names in `dir`, sorted; none if it doesn't exist.
+fn discover_seeds(dir: &Path) -> anyhow::Result<Vec<String>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut scripts = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if path.is_file() && name.ends_with(".lua") && !name.starts_with('.') {
+            scripts.push(name.to_string());
+        }
+    }
+    scripts.sort();
+    Ok(scripts)
+}
+
+/// Returns the scripts to run: those not applied yet, or all with `force`.
+fn pending_seeds(scripts: &[String], meta: &dyn KVStore, force: bool) -> anyhow::Result<Vec<String>> {
+    let mut pending = Vec::new();
+    for script in scripts {
+        if force || !is_applied(meta, script)? {
+            pending.push(script.clone());
+        }
+    }
+    Ok(pending)
+}
+
+fn is_applied(meta: &dyn KVStore, script: &str) -> anyhow::Result<bool> {
+    Ok(meta.get(&format!("{}{}", APPLIED_PREFIX, script))?.is_some())
+}
+
+/// Records `script` as applied, with the time it ran.
+fn mark_applied(meta: &dyn KVStore, script: &str) -> anyhow::Result<()> {
+    let applied_at = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    let record = serde_json::json!({ "applied_at": applied_at });
+    meta.put(
+        &format!("{}{}", APPLIED_PREFIX, script),
+        record.to_string().as_bytes(),
+        PutOptions::default(),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use luat::kv::MemoryKVStore;
+
+    #[test]
+    fn test_discover_seeds() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("002_posts.lua"), "").unwrap();
+        fs::write(dir.path().join("001_users.lua"), "").unwrap();
+        fs::write(dir.path().join("README.md"), "").unwrap();
+        fs::write(dir.path().join(".003_draft.lua"), "").unwrap();
+        assert_eq!(discover_seeds(dir.path()).unwrap(), ["001_users.lua", "002_posts.lua"]);
+        assert!(discover_seeds(&dir.path().join("missing")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_pending_seeds() {
+        let meta = MemoryKVStore::new();
+        let scripts = vec!["001_users.lua".to_string(), "002_posts.lua".to_string()];
+        assert_eq!(pending_seeds(&scripts, &meta, false).unwrap(), scripts);
+
+        mark_applied(&meta, "001_users.lua").unwrap();
+        assert_eq!(pending_seeds(&scripts, &meta, false).unwrap(), ["002_posts.lua"]);
+        assert_eq!(pending_seeds(&scripts, &meta, true).unwrap(), scripts);
+    }
+}
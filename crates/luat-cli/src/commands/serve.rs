@@ -6,26 +6,45 @@
 //!
 //! Serves the application from the pre-built bundle (dist/bundle.bin).
 //! No live reload, optimized for production.
+//!
+//! A new bundle can be deployed without restarting the process: on `SIGHUP`
+//! (or, with `--reload`, when `dist/bundle.bin` changes) the bundle is loaded
+//! into a fresh engine and swapped in once it is ready. In-flight requests
+//! finish on the previous engine.
 
 use std::sync::Arc;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
 use axum::{
     body::Body,
     extract::{Request, State},
     http::{Method, StatusCode},
+    middleware,
     response::{Html, IntoResponse, Response},
     Router,
 };
 use console::style;
-use luat::{Engine, LuatRequest, LuatResponse, MemoryCache, MemoryResourceResolver, kv::register_kv_module};
+use luat::extensions::assets::{AssetManifest, ASSET_MANIFEST_FILE};
+use luat::extensions::flags::VARIANTS_HEADER;
+use luat::extensions::Catalogs;
+use luat::{BundleInfo, Engine, EnginePool, LuatRequest, LuatResponse, MemoryResourceResolver, OwnedPooledEngine, kv::register_kv_module};
 use mlua::{Lua, Table};
-use tokio::sync::RwLock;
 use tower_http::services::ServeDir;
 
-use crate::config::Config;
+use crate::commands::build::{CONTENT_DIR, LOCALES_DIR};
+use crate::config::{Config, PageCacheRule};
 use crate::kv::KVManager;
+use crate::server::cors;
+use crate::server::crash::{self, CrashReport, RequestInfo};
+use crate::server::http::body_response;
+use crate::server::images::ImageService;
+use crate::server::listen::{bind_tcp, inherited_listener, serve_unix, shutdown_signal, UnixSocketOptions};
+use crate::server::page_cache::{CachedPage, PageCache, PAGE_CACHE_NAMESPACE};
+use crate::server::security::{generate_nonce, security_headers, CspNonce};
+use crate::server::stream::{deferred_body, early_flush_head, flush_early, split_at_body_end, EarlyFlush};
+use crate::server::workers::worker_count;
 
 /// Route information parsed from __routes in the bundle.
 #[derive(Debug, Clone)]
@@ -76,6 +95,11 @@ impl BundleRouter {
         Ok(Self { routes, matcher })
     }
 
+    /// Returns all routes in bundle order.
+    pub fn routes(&self) -> &[BundleRoute] {
+        &self.routes
+    }
+
     /// Matches a URL path and returns the route with extracted parameters.
     pub fn match_url(&self, path: &str) -> Option<(&BundleRoute, Vec<(String, String)>)> {
         match self.matcher.at(path) {
@@ -93,25 +117,94 @@ impl BundleRouter {
     }
 }
 
-/// Shared application state for the production server.
-pub struct AppState {
-    /// Template engine with memory resolver.
-    pub engine: RwLock<Engine<MemoryResourceResolver>>,
-    /// Application configuration.
-    pub config: Config,
+/// Everything loaded from `dist/`; replaced as a unit when the bundle is reloaded.
+pub struct LoadedBundle {
+    /// Render workers: one engine each, holding the bundle.
+    pub engines: Arc<EnginePool<MemoryResourceResolver>>,
     /// URL router for matching requests.
     pub router: Option<BundleRouter>,
     /// HTML template for wrapping rendered pages.
     pub app_html_template: Option<String>,
+    /// SRI hashes for built assets (empty unless built with `[build] sri`).
+    pub assets: AssetManifest,
+    /// Build stamp of the bundle.
+    pub info: BundleInfo,
+}
+
+/// Shared application state for the production server.
+pub struct AppState {
+    /// The bundle currently serving requests.
+    pub bundle: std::sync::RwLock<Arc<LoadedBundle>>,
+    /// Application configuration.
+    pub config: Config,
+    /// Response cache for routes matching `[[page_cache.rules]]`.
+    pub page_cache: Option<PageCache>,
+    /// KV stores shared by every loaded bundle.
+    pub kv_manager: Arc<KVManager>,
+    /// Directory holding the bundle and its assets.
+    pub dist_dir: PathBuf,
+    /// Number of render workers per loaded bundle.
+    pub worker_count: usize,
+    /// Whether engines render in deterministic mode.
+    pub deterministic: bool,
+}
+
+impl AppState {
+    /// Returns the bundle currently serving requests.
+    pub fn bundle(&self) -> Arc<LoadedBundle> {
+        self.bundle.read().unwrap().clone()
+    }
 }
 
 const MAX_BODY_SIZE: usize = 1024 * 1024;
 
+/// How often `--reload` checks the bundle file for changes.
+const BUNDLE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Options for `luat serve`.
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    /// Host to bind to.
+    pub host: String,
+    /// Port to bind to.
+    pub port: u16,
+    /// Number of render workers (`0` means one per CPU core).
+    pub workers: usize,
+    /// Reload when `dist/bundle.bin` changes (`SIGHUP` always reloads on Unix).
+    pub reload: bool,
+    /// Set `SO_REUSEPORT` so several processes can share the port.
+    pub reuse_port: bool,
+    /// Listen on a Unix domain socket instead of `host`/`port`.
+    pub uds: Option<UnixSocketOptions>,
+    /// Freeze time, seed randomness and sort table iteration in templates.
+    pub deterministic: bool,
+    /// Build-time defines (`NAME=VALUE`) for templates compiled by the
+    /// server, added to `[build] defines`; the bundle keeps those it was
+    /// built with.
+    pub defines: Vec<String>,
+}
+
 /// Runs the production server using the pre-built bundle.
-pub async fn run(host: &str, port: u16) -> anyhow::Result<()> {
-    let config = Config::load()?;
+///
+/// A Unix socket (`uds`) or a socket passed by systemd (`LISTEN_FDS`) takes
+/// precedence over `host`/`port`. On Ctrl+C or `SIGTERM` the server stops accepting
+/// connections and finishes in-flight requests before exiting.
+pub async fn run(options: ServeOptions) -> anyhow::Result<()> {
+    let ServeOptions {
+        host,
+        port,
+        workers,
+        reload,
+        reuse_port,
+        uds,
+        deterministic,
+        defines,
+    } = options;
+    let mut config = Config::load()?;
+    config.build.defines.extend(defines);
     let working_dir = std::env::current_dir()?;
     let dist_dir = working_dir.join("dist");
+    crash::install_panic_hook(&working_dir);
 
     // Check if bundle exists
     let bundle_path = dist_dir.join("bundle.bin");
@@ -133,79 +226,330 @@ pub async fn run(host: &str, port: u16) -> anyhow::Result<()> {
         bundle_path.display()
     );
 
-    // Load the bundle
-    let bundle_bytes = std::fs::read(&bundle_path)?;
-
-    // Create engine with memory resolver (templates are in bundle, not filesystem)
-    let resolver = MemoryResourceResolver::new();
-    let cache = MemoryCache::new(1000);
-    let engine = Engine::new(resolver, Box::new(cache))?;
-
-    // Preload bundle into engine
-    engine.preload_bundle_code_from_binary(&bundle_bytes)?;
-
-    // Register KV module with SQLite backend on engine Lua
+    // Register KV module with the configured backend on engine Lua
     let kv_dir = working_dir.join(".luat").join("kv");
-    let kv_manager = Arc::new(KVManager::new(&kv_dir)?);
-    register_kv_module(engine.lua(), kv_manager.clone().factory())?;
-
-    // Register HTTP module for making HTTP requests from Lua
-    crate::extensions::register_http_module(engine.lua())?;
+    let kv_manager = Arc::new(KVManager::for_backend(config.kv.backend, &kv_dir)?);
 
-    // Extract routes from __routes
-    let routes = extract_routes_from_lua(engine.lua())?;
-    let router = if !routes.is_empty() {
-        println!(
+    let worker_count = worker_count(workers);
+    let bundle = load_bundle(&config, &dist_dir, &kv_manager, worker_count, deterministic)?;
+    println!(
+        "{} luat {}, {}",
+        style("Bundle built by").dim(),
+        bundle.info.engine_version,
+        bundle.info.content_hash
+    );
+    match bundle.router {
+        Some(ref router) => println!(
             "{} {} route(s) from bundle",
             style("Loaded").green(),
-            routes.len()
-        );
-        Some(BundleRouter::new(routes)?)
-    } else {
-        println!("{}", style("No routes found in bundle").yellow());
-        None
-    };
+            router.routes.len()
+        ),
+        None => println!("{}", style("No routes found in bundle").yellow()),
+    }
+    println!("{} {} render worker(s)", style("Started").green(), worker_count);
 
-    // Load app.html from dist or use default
-    let app_html_path = dist_dir.join("app.html");
-    let app_html_template = if app_html_path.exists() {
-        std::fs::read_to_string(&app_html_path).ok()
-    } else {
+    let page_cache = if config.page_cache.rules.is_empty() {
         None
+    } else {
+        println!(
+            "{} {} page cache rule(s)",
+            style("Loaded").green(),
+            config.page_cache.rules.len()
+        );
+        Some(PageCache::new(
+            &config.page_cache.rules,
+            kv_manager.get_store(PAGE_CACHE_NAMESPACE),
+        )?)
     };
 
     let state = Arc::new(AppState {
-        engine: RwLock::new(engine),
+        bundle: std::sync::RwLock::new(Arc::new(bundle)),
         config: config.clone(),
-        router,
-        app_html_template,
+        page_cache,
+        kv_manager,
+        dist_dir: dist_dir.clone(),
+        worker_count,
+        deterministic,
     });
 
+    spawn_reload_tasks(state.clone(), reload);
+
     // Serve static files from dist/
     let public_dir = dist_dir.join("public");
     let static_dir = dist_dir.join("static");
+    let images = ImageService::new(
+        &config.images,
+        vec![
+            ("/static".to_string(), static_dir.clone()),
+            ("/public".to_string(), public_dir.clone()),
+        ],
+        working_dir.join(&config.images.cache_dir),
+    );
 
     let app = Router::new()
         .nest_service("/public", ServeDir::new(&public_dir))
         .nest_service("/static", ServeDir::new(&static_dir))
+        .merge(images.router())
         .fallback(fallback_handler)
-        .with_state(state);
+        .with_state(state)
+        .layer(middleware::from_fn_with_state(
+            Arc::new(config.security.clone()),
+            security_headers,
+        ));
+
+    if let Some(ref uds) = uds {
+        println!();
+        println!(
+            "{} {}",
+            style("Production server listening on").green().bold(),
+            style(uds.path.display()).cyan().underlined()
+        );
+        if reload {
+            println!("{}", style("Reloading when dist/bundle.bin changes").dim());
+        }
+        println!("{}", style("Press Ctrl+C to stop").dim());
+        return serve_unix(app, uds).await;
+    }
+
+    let (listener, activated) = match inherited_listener()? {
+        Some(listener) => (listener, true),
+        None => (bind_tcp(&format!("{}:{}", host, port), reuse_port)?, false),
+    };
+    let listener = tokio::net::TcpListener::from_std(listener)?;
+    let addr = listener.local_addr()?;
 
-    let addr = format!("{}:{}", host, port);
     println!();
     println!(
         "{} {}",
         style("Production server running at").green().bold(),
         style(format!("http://{}", addr)).cyan().underlined()
     );
+    if activated {
+        println!("{}", style("Using socket passed by systemd").dim());
+    } else if reuse_port {
+        println!("{}", style("Port shared with SO_REUSEPORT").dim());
+    }
+    if reload {
+        println!("{}", style("Reloading when dist/bundle.bin changes").dim());
+    }
     println!("{}", style("Press Ctrl+C to stop").dim());
 
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
 
     Ok(())
 }
 
+/// Loads the bundle, assets and app shell from `dist_dir` into `workers` fresh engines.
+pub(crate) fn load_bundle(
+    config: &Config,
+    dist_dir: &Path,
+    kv_manager: &Arc<KVManager>,
+    workers: usize,
+    deterministic: bool,
+) -> anyhow::Result<LoadedBundle> {
+    let bundle_bytes = crate::encryption::read_bundle(&dist_dir.join("bundle.bin"))?;
+
+    // Load SRI hashes for built assets, if present
+    let manifest_path = dist_dir.join(ASSET_MANIFEST_FILE);
+    let assets: AssetManifest = if manifest_path.exists() {
+        serde_json::from_slice(&std::fs::read(&manifest_path)?)?
+    } else {
+        AssetManifest::default()
+    };
+
+    let content = read_content(&dist_dir.join(CONTENT_DIR))?;
+    let catalogs = config.i18n.load_catalogs(&dist_dir.join(LOCALES_DIR))?;
+
+    let engines = (0..workers.max(1))
+        .map(|_| load_engine(config, dist_dir, &bundle_bytes, &assets, &content, &catalogs, kv_manager, deterministic))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let info = engines[0]
+        .bundle_info()?
+        .ok_or_else(|| anyhow::anyhow!("dist/bundle.bin has no build stamp"))?;
+
+    // Extract routes from __routes
+    let routes = extract_routes_from_lua(engines[0].lua())?;
+    let router = if routes.is_empty() {
+        None
+    } else {
+        Some(BundleRouter::new(routes)?)
+    };
+
+    // Load app.html from dist or use default
+    let app_html_template = std::fs::read_to_string(dist_dir.join("app.html")).ok();
+
+    Ok(LoadedBundle {
+        engines: Arc::new(EnginePool::from_engines(engines)),
+        router,
+        app_html_template,
+        assets,
+        info,
+    })
+}
+
+/// Reads the content collection files under `dir`, keyed by their
+/// `$content/...` path; none if the build has no content.
+fn read_content(dir: &Path) -> anyhow::Result<Vec<(String, String)>> {
+    fn visit(root: &Path, dir: &Path, files: &mut Vec<(String, String)>) -> anyhow::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            // Symlinked directories aren't followed, as they may loop
+            if entry.file_type()?.is_dir() {
+                visit(root, &path, files)?;
+            } else if path.is_file() && matches!(
+                path.extension().and_then(|e| e.to_str()),
+                Some("md" | "json" | "yaml" | "yml")
+            ) {
+                let relative = path.strip_prefix(root)?.to_string_lossy().replace('\\', "/");
+                files.push((format!("$content/{}", relative), std::fs::read_to_string(&path)?));
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    if dir.is_dir() {
+        visit(dir, dir, &mut files)?;
+    }
+    Ok(files)
+}
+
+/// Creates one engine with the bundle preloaded and the server modules registered.
+#[allow(clippy::too_many_arguments)]
+fn load_engine(
+    config: &Config,
+    dist_dir: &Path,
+    bundle_bytes: &[u8],
+    assets: &AssetManifest,
+    content: &[(String, String)],
+    catalogs: &Catalogs,
+    kv_manager: &Arc<KVManager>,
+    deterministic: bool,
+) -> anyhow::Result<Engine<MemoryResourceResolver>> {
+    // Create engine with memory resolver (templates are in bundle, not
+    // filesystem); it holds only the content collections
+    let mut resolver = MemoryResourceResolver::new();
+    for (path, source) in content {
+        resolver.add_resource(path, source);
+    }
+    let mut engine = Engine::builder(resolver)
+        .memory_cache(1000)?
+        .request_isolation(config.security.isolate_requests)
+        .build()?;
+    config.limits.apply(&mut engine)?;
+    config.apply_route_headers(&mut engine);
+    engine.set_deterministic(deterministic)?;
+    config.apply_defines(&engine)?;
+
+    // Preload bundle into engine, refusing bundles this engine cannot run;
+    // chunked bundles load their modules from dist/chunks on first use
+    engine.set_chunk_root(dist_dir)?;
+    engine.preload_bundle_code_from_binary(bundle_bytes)?;
+    match engine.bundle_info()? {
+        Some(info) => info.check_compatible()?,
+        None => anyhow::bail!("dist/bundle.bin has no build stamp; rebuild it with this version of `luat build`"),
+    }
+    engine.set_asset_manifest(assets.clone())?;
+    engine.set_catalogs(catalogs.clone())?;
+    config.images.apply(&engine)?;
+
+    let factory = kv_manager.clone().factory();
+    config.flags.apply(&engine, &factory)?;
+    config.auth.apply(&engine, &factory)?;
+    register_kv_module(engine.lua(), factory)?;
+
+    // Register HTTP module for making HTTP requests from Lua
+    crate::extensions::register_http_module(engine.lua())?;
+    crate::extensions::register_graphql_module(engine.lua())?;
+    crate::extensions::register_crypto_module(engine.lua())?;
+    crate::extensions::register_jwt_module(engine.lua())?;
+    #[cfg(feature = "grpc")]
+    crate::extensions::register_grpc_module(engine.lua())?;
+    config.storage.apply(&engine)?;
+    #[cfg(feature = "webauthn")]
+    crate::extensions::register_webauthn_module(engine.lua(), kv_manager.clone().factory())?;
+
+    Ok(engine)
+}
+
+/// Loads the current bundle from disk and swaps it in.
+///
+/// On failure the running bundle keeps serving. Cached pages are dropped
+/// after a successful swap since they were rendered by the old bundle.
+fn reload_bundle(state: &AppState) {
+    match load_bundle(
+        &state.config,
+        &state.dist_dir,
+        &state.kv_manager,
+        state.worker_count,
+        state.deterministic,
+    ) {
+        Ok(bundle) => {
+            *state.bundle.write().unwrap() = Arc::new(bundle);
+            if let Some(ref cache) = state.page_cache {
+                cache.clear();
+            }
+            println!("{}", style("Bundle reloaded").green());
+        }
+        Err(e) => eprintln!(
+            "{} {}",
+            style("Bundle reload failed, keeping the current bundle:").red(),
+            e
+        ),
+    }
+}
+
+/// Starts the `SIGHUP` listener and, with `watch`, the bundle file poller.
+fn spawn_reload_tasks(state: Arc<AppState>, watch: bool) {
+    #[cfg(unix)]
+    {
+        let state = state.clone();
+        tokio::spawn(async move {
+            use tokio::signal::unix::{signal, SignalKind};
+            let mut hangup = match signal(SignalKind::hangup()) {
+                Ok(hangup) => hangup,
+                Err(e) => {
+                    tracing::warn!("Failed to listen for SIGHUP: {}", e);
+                    return;
+                }
+            };
+            while hangup.recv().await.is_some() {
+                println!("{}", style("SIGHUP received, reloading bundle...").cyan());
+                let state = state.clone();
+                let _ = tokio::task::spawn_blocking(move || reload_bundle(&state)).await;
+            }
+        });
+    }
+
+    if watch {
+        tokio::spawn(async move {
+            let bundle_path = state.dist_dir.join("bundle.bin");
+            let mut last = bundle_stamp(&bundle_path);
+            let mut interval = tokio::time::interval(BUNDLE_POLL_INTERVAL);
+            loop {
+                interval.tick().await;
+                let stamp = bundle_stamp(&bundle_path);
+                if stamp.is_some() && stamp != last {
+                    last = stamp;
+                    println!("{}", style("Bundle changed, reloading...").cyan());
+                    let state = state.clone();
+                    let _ = tokio::task::spawn_blocking(move || reload_bundle(&state)).await;
+                }
+            }
+        });
+    }
+}
+
+/// Modification time and size of the bundle, used to detect a new deploy.
+fn bundle_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
 /// Extract routes from __routes global in Lua state
 fn extract_routes_from_lua(lua: &Lua) -> anyhow::Result<Vec<BundleRoute>> {
     let globals = lua.globals();
@@ -279,6 +623,7 @@ async fn fallback_handler(
     let headers = parts.headers.clone();
     let path = uri.path().to_string();
     let query_string = uri.query().unwrap_or_default().to_string();
+    let nonce = parts.extensions.get::<CspNonce>().map(|n| n.0.clone());
 
     let query: HashMap<String, String> = query_string
         .split('&')
@@ -294,7 +639,14 @@ async fn fallback_handler(
         })
         .collect();
 
-    if let Some(ref router) = state.router {
+    let headers_map: HashMap<String, String> = headers
+        .iter()
+        .filter_map(|(k, v)| v.to_str().ok().map(|v| (k.to_string(), v.to_string())))
+        .collect();
+    let request_id = request_id(&headers_map);
+
+    let bundle = state.bundle();
+    if let Some(ref router) = bundle.router {
         if let Some((route, params)) = router.match_url(&path) {
             let body_bytes = if method != Method::GET && method != Method::HEAD {
                 match axum::body::to_bytes(body, MAX_BODY_SIZE).await {
@@ -311,23 +663,160 @@ async fn fallback_handler(
                 None
             };
 
-            let headers_map: HashMap<String, String> = headers
-                .iter()
-                .filter_map(|(k, v)| v.to_str().ok().map(|v| (k.to_string(), v.to_string())))
-                .collect();
+            // Serve from the page cache before touching the engine
+            let cache_entry = state.page_cache.as_ref().and_then(|cache| {
+                cache
+                    .rule_for(method.as_str(), &path, &headers_map)
+                    .map(|rule| {
+                        let key = PageCache::cache_key(rule, &path, &query_string, &headers_map);
+                        (cache, rule, key)
+                    })
+            });
+            if let Some((cache, _, ref key)) = cache_entry {
+                if let Some(page) = cache.get(key, nonce.as_deref()) {
+                    return with_request_id(cached_page_response(page), &request_id);
+                }
+            }
 
-            let luat_request = to_luat_request(&path, &method, query, body_bytes, headers_map);
+            let mut headers_map = headers_map;
+            headers_map.insert("x-request-id".to_string(), request_id.clone());
+            let mut luat_request = to_luat_request(&path, &method, query, body_bytes, headers_map);
+            if let Some(ref nonce) = nonce {
+                luat_request = luat_request.with_csp_nonce(nonce.clone());
+            }
             let engine_route = bundle_route_to_engine_route(route, &params);
+            let cors = engine_route.is_api_route() && state.config.cors.is_enabled();
+            if cors && cors::is_preflight(method.as_str(), &luat_request.headers) {
+                return with_request_id(cors::preflight(&state.config.cors, &luat_request.headers), &request_id);
+            }
 
-            let engine = state.engine.read().await;
-            return match engine.respond_async(&engine_route, &luat_request).await {
-                Ok(response) => luat_response_to_http(response, &state),
-                Err(e) => error_page(&format!("Error: {}", e)),
+            let request_info = RequestInfo {
+                method: method.to_string(),
+                path: path.clone(),
+                route: Some(route.pattern.clone()),
+            };
+            let app_html = bundle.app_html_template.as_deref().unwrap_or(DEFAULT_APP_HTML);
+            let mut early_flush =
+                early_flush_head(state.config.routing.early_flush_ms, app_html, &engine_route, &luat_request);
+            if let Some(early) = early_flush.as_mut() {
+                early.headers = bundle.engines.clone().checkout_owned().await.route_headers(&path);
+            }
+            let request_headers = luat_request.headers.clone();
+            let render = respond_route(
+                state.clone(),
+                bundle.clone(),
+                engine_route,
+                luat_request,
+                request_info,
+                nonce.clone(),
+            );
+            let (mut http_response, cacheable) = match flush_early(render, early_flush, |(response, _)| response).await {
+                EarlyFlush::Ready(rendered) => rendered,
+                EarlyFlush::Flushed(response) => return with_request_id(response, &request_id),
+            };
+            if cors {
+                cors::apply(&state.config.cors, &request_headers, &mut http_response);
+            }
+            let http_response = with_request_id(http_response, &request_id);
+            return match cache_entry {
+                Some((cache, rule, key)) if cacheable => {
+                    store_cached_page(cache, rule, &key, http_response, nonce.as_deref())
+                        .await
+                }
+                _ => http_response,
             };
         }
     }
 
-    error_page("Page not found")
+    let mut luat_request = to_luat_request(&path, &method, query, None, headers_map);
+    luat_request.headers.insert("x-request-id".to_string(), request_id.clone());
+    if let Some(ref nonce) = nonce {
+        luat_request = luat_request.with_csp_nonce(nonce.clone());
+    }
+    let engine = bundle.engines.clone().checkout_owned().await;
+    let response = engine.respond_not_found_async(&luat_request).await;
+    with_request_id(
+        luat_response_to_http(response, &state.config, &bundle, engine, nonce.as_deref()),
+        &request_id,
+    )
+}
+
+/// Responds to a request for a matched route with a pooled engine, and
+/// tells whether the page cache may store the response.
+async fn respond_route(
+    state: Arc<AppState>,
+    bundle: Arc<LoadedBundle>,
+    engine_route: luat::router::Route,
+    luat_request: LuatRequest,
+    request_info: RequestInfo,
+    nonce: Option<String>,
+) -> (Response, bool) {
+    let engine = bundle.engines.clone().checkout_owned().await;
+    let result = crash::with_request(request_info.clone(), engine.respond_async(&engine_route, &luat_request)).await;
+    let response = match result {
+        Ok(LuatResponse::Error { status, message }) => {
+            engine.respond_error_async(Some(&engine_route), &luat_request, status, &message).await
+        }
+        Ok(response) => response,
+        Err(e) => {
+            let request_id = luat_request.headers.get("x-request-id").map(String::as_str).unwrap_or_default();
+            eprintln!(
+                "{} {} {} [{}]: {}",
+                style("Error").red(),
+                request_info.method,
+                request_info.path,
+                request_id,
+                e
+            );
+            if crash::is_crash(&e) {
+                crash::save(&CrashReport::render_error(&e, Some(request_info), &|_, _| None));
+            }
+            // Error details stay in the log; the page only shows the request id
+            let response = engine
+                .respond_error_async(Some(&engine_route), &luat_request, 500, luat::reason_phrase(500))
+                .await;
+            let http_response = luat_response_to_http(response, &state.config, &bundle, engine, nonce.as_deref());
+            return (http_response, false);
+        }
+    };
+    let cacheable = is_cacheable(&response);
+    let http_response = luat_response_to_http(
+        response,
+        &state.config,
+        &bundle,
+        engine,
+        nonce.as_deref(),
+    );
+    (http_response, cacheable)
+}
+
+/// Returns the client's `X-Request-Id` when it looks sane, or a new random id.
+fn request_id(headers: &HashMap<String, String>) -> String {
+    let valid = |id: &&String| {
+        !id.is_empty() && id.len() <= 128 && id.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+    };
+    match headers.get("x-request-id").filter(valid) {
+        Some(id) => id.clone(),
+        None => match generate_nonce() {
+            Ok(nonce) => nonce[..16].to_string(),
+            // Request ids only correlate logs, so a clock-based id will do.
+            Err(_) => format!(
+                "{:016x}",
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or_default()
+            ),
+        },
+    }
+}
+
+/// Adds the `X-Request-Id` header so logs and error pages can be matched up.
+fn with_request_id(mut response: Response, request_id: &str) -> Response {
+    if let Ok(value) = axum::http::HeaderValue::from_str(request_id) {
+        response.headers_mut().insert("x-request-id", value);
+    }
+    response
 }
 
 fn to_luat_request(
@@ -348,7 +837,7 @@ fn to_luat_request(
     request
 }
 
-fn bundle_route_to_engine_route(
+pub(crate) fn bundle_route_to_engine_route(
     route: &BundleRoute,
     params: &[(String, String)],
 ) -> luat::router::Route {
@@ -376,72 +865,50 @@ fn bundle_route_to_engine_route(
     engine_route
 }
 
-fn luat_response_to_http(response: LuatResponse, state: &AppState) -> Response {
+fn luat_response_to_http(
+    response: LuatResponse,
+    config: &Config,
+    bundle: &LoadedBundle,
+    engine: OwnedPooledEngine<MemoryResourceResolver>,
+    nonce: Option<&str>,
+) -> Response {
+    let head = response.head().cloned().unwrap_or_default();
     match response {
         LuatResponse::Html {
             status,
-            mut headers,
+            headers,
             body,
-        } => {
-            let is_fragment = headers.remove("x-luat-fragment").is_some()
-                || headers.remove("X-Luat-Fragment").is_some();
-            let status_code = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
-            let has_content_type = has_content_type_header(&headers);
-
-            let mut builder = axum::http::Response::builder().status(status_code);
-            for (key, value) in headers {
-                builder = builder.header(key, value);
-            }
-
-            if !has_content_type {
-                builder = builder.header("content-type", "text/html; charset=utf-8");
-            }
-
-            if is_fragment {
-                return builder.body(Body::from(body)).unwrap_or_else(|_| {
-                    (StatusCode::INTERNAL_SERVER_ERROR, "Failed to build response")
-                        .into_response()
-                });
-            }
-
-            let title = "Luat App";
-            let head_assets = collect_production_head_assets(&state.config);
-            let app_html = state
-                .app_html_template
-                .as_deref()
-                .unwrap_or(DEFAULT_APP_HTML);
-            let full_html = wrap_with_app_html(app_html, &body, title, &head_assets);
-
-            builder.body(Body::from(full_html)).unwrap_or_else(|_| {
-                (StatusCode::INTERNAL_SERVER_ERROR, "Failed to build response").into_response()
-            })
-        }
+            ..
+        } => html_response(status, headers, body, head, None, config, bundle, nonce),
+        LuatResponse::Stream {
+            status,
+            headers,
+            body,
+            deferred,
+            ..
+        } => html_response(
+            status,
+            headers,
+            body,
+            head,
+            Some((deferred, engine)),
+            config,
+            bundle,
+            nonce,
+        ),
         LuatResponse::Json {
             status,
             headers,
             body,
         } => {
-            let status_code = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
-            let has_content_type = has_content_type_header(&headers);
-            let mut builder = axum::http::Response::builder().status(status_code);
-
-            for (key, value) in headers {
-                builder = builder.header(key, value);
-            }
-
-            if !has_content_type {
-                builder = builder.header("content-type", "application/json");
-            }
-
-            builder
-                .body(Body::from(
-                    serde_json::to_string(&body).unwrap_or_default(),
-                ))
-                .unwrap_or_else(|_| {
-                    (StatusCode::INTERNAL_SERVER_ERROR, "Failed to build response")
-                        .into_response()
-                })
+            let body = luat::encode_json_body(&body, &headers);
+            body_response(status, headers, Body::from(body), "application/json")
         }
+        LuatResponse::Bytes {
+            status,
+            headers,
+            body,
+        } => body_response(status, headers, Body::from(body), "application/octet-stream"),
         LuatResponse::Redirect { status, location } => {
             let status_code = StatusCode::from_u16(status).unwrap_or(StatusCode::FOUND);
             Response::builder()
@@ -457,7 +924,172 @@ fn luat_response_to_http(response: LuatResponse, state: &AppState) -> Response {
             let _ = status;
             error_page(&message)
         }
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, "Unsupported response").into_response(),
+    }
+}
+
+/// Builds an HTML response, streaming deferred values after the shell when present.
+///
+/// Deferred values live in the Lua state that rendered the page, so they come
+/// paired with the engine that must resolve them, which stays checked out
+/// until they are sent.
+#[allow(clippy::too_many_arguments)]
+fn html_response(
+    status: u16,
+    mut headers: HashMap<String, String>,
+    body: String,
+    head: luat::PageHead,
+    deferred: Option<(Vec<String>, OwnedPooledEngine<MemoryResourceResolver>)>,
+    config: &Config,
+    bundle: &LoadedBundle,
+    nonce: Option<&str>,
+) -> Response {
+    let deferred = deferred.filter(|(ids, _)| !ids.is_empty());
+    let is_fragment = headers.remove("x-luat-fragment").is_some()
+        || headers.remove("X-Luat-Fragment").is_some();
+    let status_code = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
+    let has_content_type = has_content_type_header(&headers);
+
+    let mut builder = axum::http::Response::builder().status(status_code);
+    for (key, value) in headers {
+        builder = builder.header(key, value);
+    }
+
+    if !has_content_type {
+        builder = builder.header("content-type", "text/html; charset=utf-8");
+    }
+
+    if is_fragment {
+        let body = match deferred {
+            None => Body::from(body),
+            Some((ids, engine)) => deferred_body(
+                std::future::ready(engine),
+                body,
+                ids,
+                String::new(),
+                nonce.map(str::to_string),
+            ),
+        };
+        return builder.body(body).unwrap_or_else(|_| {
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to build response")
+                .into_response()
+        });
     }
+
+    let title = html_escape(head.title.as_deref().unwrap_or("Luat App"));
+    let head_assets = format!(
+        "{}{}",
+        head.to_html(),
+        collect_production_head_assets(config, &bundle.assets, nonce)
+    );
+    let app_html = bundle
+        .app_html_template
+        .as_deref()
+        .unwrap_or(DEFAULT_APP_HTML);
+    let full_html = wrap_with_app_html(app_html, &body, &title, &head_assets);
+
+    let body = match deferred {
+        None => Body::from(full_html),
+        Some((ids, engine)) => {
+            let (head, tail) = split_at_body_end(&full_html);
+            deferred_body(
+                std::future::ready(engine),
+                head,
+                ids,
+                tail,
+                nonce.map(str::to_string),
+            )
+        }
+    };
+
+    builder.body(body).unwrap_or_else(|_| {
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to build response").into_response()
+    })
+}
+
+/// Only complete (non-fragment), successful, cookie-free HTML pages are stored.
+/// Pages that evaluated a feature flag experiment differ per user, so they are
+/// never stored under the shared key.
+fn is_cacheable(response: &LuatResponse) -> bool {
+    match response {
+        LuatResponse::Html {
+            status: 200,
+            headers,
+            ..
+        } => !headers.keys().any(|key| {
+            key.eq_ignore_ascii_case("set-cookie")
+                || key.eq_ignore_ascii_case("x-luat-fragment")
+                || key.eq_ignore_ascii_case(VARIANTS_HEADER)
+        }),
+        _ => false,
+    }
+}
+
+/// Stores a rendered response in the page cache and returns it to the client.
+async fn store_cached_page(
+    cache: &PageCache,
+    rule: &PageCacheRule,
+    key: &str,
+    response: Response,
+    nonce: Option<&str>,
+) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to read response body")
+                .into_response()
+        }
+    };
+
+    let content_type = parts
+        .headers
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("text/html; charset=utf-8")
+        .to_string();
+    // Route headers are replayed on a hit; per-request ones are not
+    let headers = parts
+        .headers
+        .iter()
+        .filter(|(name, _)| {
+            !matches!(name.as_str(), "content-type" | "content-length" | "x-request-id")
+                && !name.as_str().starts_with("access-control-")
+        })
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+
+    cache.put(
+        key,
+        rule,
+        &CachedPage {
+            status: parts.status.as_u16(),
+            content_type,
+            headers,
+            body: bytes.to_vec(),
+        },
+        nonce,
+    );
+
+    parts
+        .headers
+        .insert("x-luat-cache", axum::http::HeaderValue::from_static("MISS"));
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+fn cached_page_response(page: CachedPage) -> Response {
+    let status_code = StatusCode::from_u16(page.status).unwrap_or(StatusCode::OK);
+    let mut builder = Response::builder().status(status_code);
+    for (name, value) in page.headers {
+        builder = builder.header(name, value);
+    }
+    builder
+        .header("content-type", page.content_type)
+        .header("x-luat-cache", "HIT")
+        .body(Body::from(page.body))
+        .unwrap_or_else(|_| {
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to build response").into_response()
+        })
 }
 
 fn has_content_type_header(headers: &HashMap<String, String>) -> bool {
@@ -490,7 +1122,7 @@ fn error_page(message: &str) -> Response {
     .into_response()
 }
 
-fn html_escape(s: &str) -> String {
+pub(crate) fn html_escape(s: &str) -> String {
     s.replace('&', "&amp;")
         .replace('<', "&lt;")
         .replace('>', "&gt;")
@@ -499,47 +1131,70 @@ fn html_escape(s: &str) -> String {
 }
 
 /// Collect head assets for production
-fn collect_production_head_assets(config: &Config) -> String {
+pub(crate) fn collect_production_head_assets(
+    config: &Config,
+    assets: &AssetManifest,
+    nonce: Option<&str>,
+) -> String {
     let mut head = String::new();
+    let nonce_attr = nonce
+        .map(|n| format!(" nonce=\"{}\"", n))
+        .unwrap_or_default();
+    let integrity_attr = |url: &str| {
+        assets
+            .integrity(url)
+            .map(|hash| format!(" integrity=\"{}\" crossorigin=\"anonymous\"", hash))
+            .unwrap_or_default()
+    };
 
     use crate::toolchain::Tool;
     let enabled_tools = config.frontend.get_enabled_tools();
 
     if enabled_tools.contains(&Tool::Sass) {
-        let path = config.frontend.sass_output.trim_start_matches("public/");
+        let url = format!("/public/{}", config.frontend.sass_output.trim_start_matches("public/"));
         head.push_str(&format!(
-            "    <link rel=\"stylesheet\" href=\"/public/{}\">\n",
-            path
+            "    <link rel=\"stylesheet\" href=\"{}\"{}>\n",
+            url,
+            integrity_attr(&url)
         ));
     }
 
     if enabled_tools.contains(&Tool::Tailwind) {
-        let path = config.frontend.tailwind_output.trim_start_matches("public/");
+        let url = format!(
+            "/public/{}",
+            config.frontend.tailwind_output.trim_start_matches("public/")
+        );
         head.push_str(&format!(
-            "    <link rel=\"stylesheet\" href=\"/public/{}\">\n",
-            path
+            "    <link rel=\"stylesheet\" href=\"{}\"{}>\n",
+            url,
+            integrity_attr(&url)
         ));
     }
 
     if enabled_tools.contains(&Tool::TypeScript) {
-        let path = config.frontend.typescript_output.trim_start_matches("public/");
+        let url = format!(
+            "/public/{}",
+            config.frontend.typescript_output.trim_start_matches("public/")
+        );
         head.push_str(&format!(
-            "    <script src=\"/public/{}\" defer></script>\n",
-            path
+            "    <script src=\"{}\"{}{} defer></script>\n",
+            url,
+            integrity_attr(&url),
+            nonce_attr
         ));
     }
 
     head
 }
 
-fn wrap_with_app_html(app_html: &str, body: &str, title: &str, head_assets: &str) -> String {
+pub(crate) fn wrap_with_app_html(app_html: &str, body: &str, title: &str, head_assets: &str) -> String {
     app_html
         .replace("%luat.title%", title)
         .replace("%luat.head%", head_assets)
         .replace("%luat.body%", body)
 }
 
-const DEFAULT_APP_HTML: &str = r#"<!DOCTYPE html>
+pub(crate) const DEFAULT_APP_HTML: &str = r#"<!DOCTYPE html>
 <html lang="en">
 <head>
     <meta charset="UTF-8">
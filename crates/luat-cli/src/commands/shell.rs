@@ -0,0 +1,267 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Interactive Lua shell (`luat shell`).
+//!
+//! Boots the project engine with the modules server code uses in `luat dev`
+//! (`kv`, `http`, `json`, `flags`, ...) and evaluates Lua read from stdin.
+//! Components and server modules can be `require`d like in a template, and
+//! routes answered with fake requests to debug their load functions:
+//!
+//! ```text
+//! luat> posts = KV.namespace("posts")
+//! luat> posts:get("hello", "json")
+//! { title = "Hello", views = 3 }
+//! luat> :request GET /blog/hello?lang=de
+//! 200 html (1532 bytes)
+//! <article>...
+//! luat> :render lib/components/Card {"title": "Hi"}
+//! <div class="card"><h2>Hi</h2></div>
+//! ```
+//!
+//! A line is first evaluated as an expression and its values printed; if it
+//! doesn't parse as one, it runs as a statement. Input that ends inside a
+//! block or string continues on the next line. Each input runs as its own
+//! chunk, so use globals to keep values between lines; the first value of
+//! the last input is kept in `_`.
+
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use console::style;
+use luat::{Engine, FileSystemResolver, LuatResponse};
+use mlua::{MultiValue, Value};
+
+use crate::commands::build::project_source_dir;
+use crate::commands::render::{project_kv_manager, render_engine, url_request};
+use crate::config::Config;
+
+/// Tables nested deeper than this are printed as `{...}`.
+const MAX_DEPTH: usize = 4;
+
+const HELP: &str = "\
+Lua is evaluated as typed; the values of expressions are printed.
+
+  :render <template> [json]        Render a template with props
+  :request <METHOD> <url> [json]   Respond to a request, running load functions
+  :help                            Show this help
+  :quit                            Leave the shell (or Ctrl+D)";
+
+/// Runs the shell until `:quit` or end of input.
+pub fn run() -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let working_dir = std::env::current_dir()?;
+    let source_dir = project_source_dir(&config);
+    let engine = render_engine(&config, &working_dir, &source_dir, &project_kv_manager(&config, &working_dir)?)?;
+    let routes_dir = working_dir.join(&source_dir);
+
+    eprintln!("{} Lua shell, {} for help", style("luat").cyan().bold(), style(":help").bold());
+
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut chunk = String::new();
+    loop {
+        eprint!("{} ", if chunk.is_empty() { "luat>" } else { "  ..>" });
+        std::io::stderr().flush()?;
+        let Some(line) = lines.next().transpose()? else {
+            eprintln!();
+            break;
+        };
+
+        if chunk.is_empty() {
+            let command = line.trim();
+            if command.is_empty() {
+                continue;
+            }
+            if let Some(command) = command.strip_prefix(':') {
+                match run_command(&config, &engine, &routes_dir, command) {
+                    Ok(true) => continue,
+                    Ok(false) => break,
+                    Err(e) => {
+                        eprintln!("{} {}", style("error:").red(), e);
+                        continue;
+                    }
+                }
+            }
+        }
+
+        chunk.push_str(&line);
+        chunk.push('\n');
+        match eval(&engine, &chunk) {
+            Err(mlua::Error::SyntaxError { incomplete_input: true, .. }) => continue,
+            Ok(values) => {
+                if !values.is_empty() {
+                    let printed: Vec<String> = values.iter().map(format_value).collect();
+                    println!("{}", printed.join("\t"));
+                }
+                engine.lua().globals().raw_set("_", values.into_iter().next().unwrap_or(Value::Nil))?;
+            }
+            Err(e) => eprintln!("{} {}", style("error:").red(), e),
+        }
+        chunk.clear();
+    }
+    Ok(())
+}
+
+/// Evaluates `source` as an expression, or as a statement if it isn't one.
+fn eval(engine: &Engine<FileSystemResolver>, source: &str) -> mlua::Result<MultiValue> {
+    let lua = engine.lua();
+    match lua.load(format!("return {}", source)).set_name("=shell").eval() {
+        Err(mlua::Error::SyntaxError { .. }) => lua.load(source).set_name("=shell").eval(),
+        result => result,
+    }
+}
+
+/// Runs a `:command`; returns `false` to leave the shell.
+fn run_command(
+    config: &Config,
+    engine: &Engine<FileSystemResolver>,
+    routes_dir: &Path,
+    command: &str,
+) -> anyhow::Result<bool> {
+    let (name, args) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+    let args = args.trim();
+    match name {
+        "q" | "quit" | "exit" => return Ok(false),
+        "h" | "help" => println!("{}", HELP),
+        "render" => {
+            let (template, props) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+            if template.is_empty() {
+                anyhow::bail!("usage: :render <template> [json]");
+            }
+            let props = parse_json(props)?;
+            let module = engine.compile_entry(template)?;
+            println!("{}", engine.render(&module, &engine.to_value(props)?)?);
+        }
+        "request" => {
+            let mut parts = args.splitn(3, char::is_whitespace);
+            let (Some(method), Some(url)) = (parts.next().filter(|m| !m.is_empty()), parts.next()) else {
+                anyhow::bail!("usage: :request <METHOD> <url> [json]");
+            };
+            if config.routing.simplified || !routes_dir.exists() {
+                anyhow::bail!("Requests need SvelteKit-style routing");
+            }
+            let body = parts.next().map(str::trim).unwrap_or_default();
+            let (route, mut request) = url_request(routes_dir, &method.to_uppercase(), url)?;
+            if !body.is_empty() {
+                parse_json(body)?;
+                request = request.with_body(body.as_bytes().to_vec()).with_headers(
+                    [("content-type".to_string(), "application/json".to_string())].into_iter().collect(),
+                );
+            }
+            print_response(engine, engine.respond(&route, &request)?)?;
+        }
+        other => anyhow::bail!("unknown command :{} (:help lists the commands)", other),
+    }
+    Ok(true)
+}
+
+fn parse_json(source: &str) -> anyhow::Result<serde_json::Value> {
+    if source.is_empty() {
+        return Ok(serde_json::json!({}));
+    }
+    serde_json::from_str(source).map_err(|e| anyhow::anyhow!("invalid JSON: {}", e))
+}
+
+fn print_response(engine: &Engine<FileSystemResolver>, response: LuatResponse) -> anyhow::Result<()> {
+    match response {
+        LuatResponse::Html { status, body, .. } => {
+            println!("{} html ({} bytes)\n{}", status, body.len(), body);
+        }
+        LuatResponse::Stream { status, body, deferred, .. } => {
+            println!("{} stream ({} bytes, {} deferred)\n{}", status, body.len(), deferred.len(), body);
+            for id in &deferred {
+                println!("{}", engine.resolve_deferred(id, None)?);
+            }
+        }
+        LuatResponse::Json { status, body, .. } => {
+            println!("{} json\n{}", status, serde_json::to_string_pretty(&body)?);
+        }
+        LuatResponse::Bytes { status, body, .. } => println!("{} bytes ({} bytes)", status, body.len()),
+        LuatResponse::Redirect { status, location } => println!("{} redirect -> {}", status, location),
+        LuatResponse::Error { status, message } => println!("{} error: {}", status, message),
+        _ => println!("unsupported response"),
+    }
+    Ok(())
+}
+
+/// Formats a Lua value for display, tables as `{ 1, 2, key = value }`.
+fn format_value(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(value, 0, &mut out);
+    out
+}
+
+fn write_value(value: &Value, depth: usize, out: &mut String) {
+    match value {
+        Value::String(s) => out.push_str(&format!("{:?}", s.to_string_lossy())),
+        Value::Table(table) => {
+            if depth >= MAX_DEPTH {
+                out.push_str("{...}");
+                return;
+            }
+            let len = table.raw_len();
+            let mut entries: Vec<String> = (1..=len)
+                .map(|i| {
+                    let mut item = String::new();
+                    write_value(&table.raw_get(i).unwrap_or(Value::Nil), depth + 1, &mut item);
+                    item
+                })
+                .collect();
+            let mut fields: Vec<(String, String)> = table
+                .clone()
+                .pairs::<Value, Value>()
+                .filter_map(Result::ok)
+                .filter(|(key, _)| !matches!(key, Value::Integer(i) if *i >= 1 && (*i as usize) <= len))
+                .map(|(key, value)| {
+                    let key = match &key {
+                        Value::String(s) if is_identifier(&s.to_string_lossy()) => s.to_string_lossy(),
+                        _ => format!("[{}]", format_value(&key)),
+                    };
+                    let mut item = String::new();
+                    write_value(&value, depth + 1, &mut item);
+                    (key, item)
+                })
+                .collect();
+            fields.sort();
+            entries.extend(fields.into_iter().map(|(key, value)| format!("{} = {}", key, value)));
+            if entries.is_empty() {
+                out.push_str("{}");
+            } else {
+                out.push_str(&format!("{{ {} }}", entries.join(", ")));
+            }
+        }
+        Value::Nil => out.push_str("nil"),
+        Value::Boolean(b) => out.push_str(&b.to_string()),
+        Value::Integer(i) => out.push_str(&i.to_string()),
+        Value::Number(n) => out.push_str(&n.to_string()),
+        other => out.push_str(&format!("<{}>", other.type_name())),
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_value() {
+        let lua = mlua::Lua::new();
+        let value: Value = lua
+            .load(r#"return { 1, "two", title = "Hi", ["a-b"] = true, nested = { x = 1.5, f = print } }"#)
+            .eval()
+            .unwrap();
+        assert_eq!(
+            format_value(&value),
+            r#"{ 1, "two", ["a-b"] = true, nested = { f = <function>, x = 1.5 }, title = "Hi" }"#
+        );
+        let deep: Value = lua.load("return {{{{{}}}}}").eval().unwrap();
+        assert_eq!(format_value(&deep), "{ { { { {...} } } } }");
+    }
+}
@@ -2,37 +2,312 @@
 // SPDX-License-Identifier: Apache-2.0
 // SPDX-License-Identifier: MIT
 
-//! File watcher command for monitoring template changes.
+//! File watcher command for rebuilding templates on change.
+//!
+//! Each rebuild compiles every template and reports the compile time per
+//! file, the size of the bundle, the modules added, changed or removed since
+//! the previous rebuild, and the compile warnings. With `--json` every
+//! rebuild is printed as one JSON object per line, for editors and CI:
+//!
+//! ```text
+//! {"event":"rebuild","trigger":["src/routes/+page.luat"],"duration_ms":42,"bundle_bytes":18234,
+//!  "files":[{"file":"+page.luat","compile_ms":3.1}],"modules":{"added":[],"changed":["+page.luat"],"removed":[]},
+//!  "warnings":[]}
+//! {"event":"error","trigger":["src/routes/+page.luat"],"message":"Parse error ..."}
+//! ```
 
+use crate::commands::build::{collect_project_sources, project_engine, project_source_dir, ProjectSources};
 use crate::config::Config;
 use crate::watcher::FileWatcher;
-use std::path::PathBuf;
+use console::style;
+use luat::CompileWarning;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 use tokio::signal;
+use tokio::sync::mpsc;
 
-/// Runs the file watcher to monitor template changes.
-pub async fn run() -> anyhow::Result<()> {
-    let config = Config::load()?;
-    let templates_dir = config.dev.templates_dir.clone();
+/// Source hash of every module, keyed by module key.
+type ModuleHashes = BTreeMap<String, u64>;
+
+/// Outcome of one rebuild.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+enum RebuildEvent {
+    /// The templates compiled.
+    Rebuild {
+        /// Files whose change triggered the rebuild; empty for the first build.
+        trigger: Vec<String>,
+        /// Time the whole rebuild took.
+        duration_ms: u128,
+        /// Size of the bundle including server sources.
+        bundle_bytes: usize,
+        /// Compile time of each template.
+        files: Vec<FileMetrics>,
+        /// Modules that differ from the previous rebuild.
+        modules: ModuleDiff,
+        /// Compile warnings of all templates.
+        warnings: Vec<CompileWarning>,
+    },
+    /// A template failed to compile.
+    Error {
+        /// Files whose change triggered the rebuild.
+        trigger: Vec<String>,
+        /// The compile error.
+        message: String,
+    },
+}
+
+/// Compile time of one template.
+#[derive(Debug, Serialize)]
+struct FileMetrics {
+    /// Module key of the template.
+    file: String,
+    /// Compile time in milliseconds.
+    compile_ms: f64,
+}
+
+/// Modules added, changed or removed between two rebuilds.
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct ModuleDiff {
+    added: Vec<String>,
+    changed: Vec<String>,
+    removed: Vec<String>,
+}
+
+impl ModuleDiff {
+    fn between(previous: &ModuleHashes, current: &ModuleHashes) -> Self {
+        let mut diff = ModuleDiff::default();
+        for (key, hash) in current {
+            match previous.get(key) {
+                None => diff.added.push(key.clone()),
+                Some(previous_hash) if previous_hash != hash => diff.changed.push(key.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = previous.keys().filter(|key| !current.contains_key(*key)).cloned().collect();
+        diff
+    }
+
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Runs the file watcher, rebuilding the templates on every change.
+/// `defines` are added to `[build] defines`.
+pub async fn run(json: bool, defines: Vec<String>) -> anyhow::Result<()> {
+    let mut config = Config::load()?;
+    config.build.defines.extend(defines);
     let working_dir = std::env::current_dir()?;
+    let source_dir = project_source_dir(&config);
 
-    println!("Watching for changes in: {}", templates_dir);
-    println!("Press Ctrl+C to stop...");
-    println!();
+    let (tx, mut rx) = mpsc::unbounded_channel::<Vec<PathBuf>>();
+    let mut watcher = FileWatcher::new(source_dir.clone(), working_dir.clone(), move |paths: Vec<PathBuf>| {
+        let _ = tx.send(paths);
+    })?;
+    let lib_dir = working_dir.join(&config.routing.lib_dir);
+    if lib_dir.exists() && !lib_dir.starts_with(working_dir.join(&source_dir)) {
+        watcher.watch_path(&lib_dir)?;
+    }
+    for root in &config.routing.source_roots {
+        let root_dir = working_dir.join(root);
+        if root_dir.exists() {
+            watcher.watch_path(&root_dir)?;
+        }
+    }
+    watcher.start()?;
+
+    if !json {
+        println!("Watching for changes in: {}", source_dir);
+        println!("Press Ctrl+C to stop...");
+        println!();
+    }
+
+    let mut modules = ModuleHashes::new();
+    let event = rebuild(&config, &working_dir, &source_dir, Vec::new(), &mut modules);
+    print_event(&event, json)?;
+
+    loop {
+        tokio::select! {
+            paths = rx.recv() => {
+                let Some(paths) = paths else { break };
+                let trigger = paths.iter().map(|p| p.display().to_string()).collect();
+                let event = rebuild(&config, &working_dir, &source_dir, trigger, &mut modules);
+                print_event(&event, json)?;
+            }
+            _ = signal::ctrl_c() => break,
+        }
+    }
 
-    let mut watcher = FileWatcher::new(templates_dir, working_dir, |paths: Vec<PathBuf>| {
-        let files = paths
-            .iter()
-            .map(|p| p.display().to_string())
-            .collect::<Vec<_>>()
-            .join(", ");
-        println!("  File changed: {} - rebuild triggered", files);
+    if !json {
+        println!("\nStopping file watcher...");
+    }
+    Ok(())
+}
+
+/// Compiles and bundles the project, updating `modules` to its module hashes.
+fn rebuild(
+    config: &Config,
+    working_dir: &Path,
+    source_dir: &str,
+    trigger: Vec<String>,
+    modules: &mut ModuleHashes,
+) -> RebuildEvent {
+    let start = Instant::now();
+    match compile_project(config, working_dir, source_dir) {
+        Ok((files, bundle_bytes, warnings, current)) => {
+            let diff = ModuleDiff::between(modules, &current);
+            *modules = current;
+            RebuildEvent::Rebuild {
+                trigger,
+                duration_ms: start.elapsed().as_millis(),
+                bundle_bytes,
+                files,
+                modules: diff,
+                warnings,
+            }
+        }
+        Err(e) => RebuildEvent::Error { trigger, message: e.to_string() },
+    }
+}
+
+/// Compiles and bundles the templates, returning the compile times, the
+/// bundle size, the warnings and the module hashes.
+fn compile_project(
+    config: &Config,
+    working_dir: &Path,
+    source_dir: &str,
+) -> anyhow::Result<(Vec<FileMetrics>, usize, Vec<CompileWarning>, ModuleHashes)> {
+    // A fresh engine, so nothing is served from the cache of the last rebuild
+    let engine = project_engine(config, working_dir, source_dir)?;
+    let ProjectSources { sources, server_sources, .. } = collect_project_sources(config, source_dir)?;
+
+    let hashes: ModuleHashes = sources
+        .iter()
+        .chain(&server_sources)
+        .map(|(name, source)| {
+            let mut hasher = DefaultHasher::new();
+            source.hash(&mut hasher);
+            (name.clone(), hasher.finish())
+        })
+        .collect();
+
+    // Each template is compiled once, by the bundler: compiling reports two
+    // steps per template, before and after, and bundling one
+    let names: Vec<String> = sources.iter().map(|(name, _)| name.clone()).collect();
+    let compile_steps = names.len() * 2;
+    let mut files = Vec::with_capacity(names.len());
+    let mut start = Instant::now();
+    let (bundle, _) = engine.bundle_sources(sources, |step, steps| {
+        if steps != compile_steps {
+            return;
+        }
+        if step % 2 == 0 {
+            start = Instant::now();
+        } else {
+            files.push(FileMetrics {
+                file: names[step / 2].clone(),
+                compile_ms: start.elapsed().as_secs_f64() * 1000.0,
+            });
+        }
     })?;
+    let warnings = engine.take_warnings();
+    let bundle_bytes = bundle.len() + server_sources.iter().map(|(_, source)| source.len()).sum::<usize>();
 
-    watcher.start()?;
+    Ok((files, bundle_bytes, warnings, hashes))
+}
 
-    // Wait for Ctrl+C
-    signal::ctrl_c().await?;
+fn print_event(event: &RebuildEvent, json: bool) -> anyhow::Result<()> {
+    if json {
+        println!("{}", serde_json::to_string(event)?);
+        return Ok(());
+    }
 
-    println!("\nStopping file watcher...");
+    match event {
+        RebuildEvent::Rebuild { trigger, duration_ms, bundle_bytes, files, modules, warnings } => {
+            if !trigger.is_empty() {
+                println!("  {} {}", style("Changed:").cyan(), trigger.join(", "));
+            }
+            println!(
+                "  {} {} template(s) in {}ms, bundle {}",
+                style("✓ Rebuilt").green(),
+                files.len(),
+                duration_ms,
+                format_bytes(*bundle_bytes)
+            );
+            let mut slowest: Vec<&FileMetrics> = files.iter().collect();
+            slowest.sort_by(|a, b| b.compile_ms.total_cmp(&a.compile_ms));
+            for file in slowest {
+                println!("    {:>8.1}ms  {}", file.compile_ms, file.file);
+            }
+            if !trigger.is_empty() && !modules.is_empty() {
+                for (label, keys) in [("+", &modules.added), ("~", &modules.changed), ("-", &modules.removed)] {
+                    for key in keys {
+                        println!("    {} {}", style(label).dim(), key);
+                    }
+                }
+            }
+            for warning in warnings {
+                println!("  {} {}", style("warning:").yellow(), warning);
+            }
+        }
+        RebuildEvent::Error { trigger, message } => {
+            if !trigger.is_empty() {
+                println!("  {} {}", style("Changed:").cyan(), trigger.join(", "));
+            }
+            println!("  {} {}", style("✗").red(), style(message).red());
+        }
+    }
+    println!();
     Ok(())
 }
+
+/// Formats a byte count as B, KB or MB.
+fn format_bytes(bytes: usize) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_diff() {
+        let previous: ModuleHashes = [("a.luat", 1), ("b.luat", 2), ("c.lua", 3)]
+            .into_iter()
+            .map(|(key, hash)| (key.to_string(), hash))
+            .collect();
+        let current: ModuleHashes = [("a.luat", 1), ("b.luat", 5), ("d.luat", 4)]
+            .into_iter()
+            .map(|(key, hash)| (key.to_string(), hash))
+            .collect();
+        assert_eq!(
+            ModuleDiff::between(&previous, &current),
+            ModuleDiff {
+                added: vec!["d.luat".to_string()],
+                changed: vec!["b.luat".to_string()],
+                removed: vec!["c.lua".to_string()],
+            }
+        );
+        assert!(ModuleDiff::between(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn test_rebuild_event_json() {
+        let event = RebuildEvent::Error { trigger: vec!["a.luat".to_string()], message: "boom".to_string() };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"error","trigger":["a.luat"],"message":"boom"}"#
+        );
+    }
+}
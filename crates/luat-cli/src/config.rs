@@ -20,19 +20,79 @@
 //! [build]
 //! output_dir = "dist"
 //! minify = true
+//! encrypt = true
+//! # chunked = true
 //!
 //! [routing]
 //! routes_dir = "src/routes"
 //! lib_dir = "src/lib"
 //! static_dir = "static"
+//! source_roots = ["../shared/components"]
 //!
 //! [frontend]
 //! enabled = true
 //! port = 5173
+//!
+//! [security]
+//! csp = "default-src 'self'; script-src 'self' 'nonce-{nonce}'"
+//! hsts = "max-age=63072000; includeSubDomains"
+//! frame_options = "DENY"
+//! isolate_requests = true
+//!
+//! [limits]
+//! max_output_bytes = 5242880
+//! memory_bytes = 268435456
+//! render_timeout_ms = 2000
+//! max_instructions = 50000000
+//!
+//! [cors]
+//! origins = ["https://app.example.com"]
+//! credentials = true
+//!
+//! [routes."/docs/**"]
+//! headers = { "Cache-Control" = "public, max-age=300" }
+//!
+//! [[page_cache.rules]]
+//! path = "/blog/**"
+//! ttl = 300
+//! vary = ["accept-language"]
+//! bypass_cookie = "session"
+//!
+//! [flags]
+//! user_cookie = "session_id"
+//! overrides = "flags"
+//!
+//! [flags.new-checkout]
+//! rollout = 25
+//! cookie = "beta"
+//!
+//! [i18n]
+//! dir = "src/locales"
+//! default_locale = "en"
+//!
+//! [storage]
+//! bucket = "uploads"
+//! region = "eu-central-1"
+//! # endpoint = "https://<account>.r2.cloudflarestorage.com"
+//!
+//! [images]
+//! widths = [480, 960, 1920]
+//! quality = 75
+//!
+//! [auth]
+//! callback_url = "https://example.com/auth/callback"
+//!
+//! [auth.providers.github]
+//! client_id = "Iv1.0123456789abcdef"
+//! client_secret_env = "GITHUB_CLIENT_SECRET"
+//!
+//! [dependencies]
+//! ui = { git = "https://github.com/acme/ui-components", rev = "v1.2.0" }
 //! ```
 
 use crate::toolchain::ToolchainConfig;
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
@@ -53,6 +113,594 @@ pub struct Config {
     /// Routing configuration.
     #[serde(default)]
     pub routing: RoutingConfig,
+    /// Response caching rules for the production server.
+    #[serde(default)]
+    pub page_cache: PageCacheConfig,
+    /// Per-request resource limits.
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    /// Security response headers for the production server.
+    #[serde(default)]
+    pub security: SecurityConfig,
+    /// Cross-origin access to API routes.
+    #[serde(default)]
+    pub cors: CorsConfig,
+    /// Per-route settings, keyed by URL pattern (e.g. "/docs/**").
+    #[serde(default)]
+    pub routes: BTreeMap<String, RouteConfig>,
+    /// Component packages fetched from git, keyed by package name.
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, GitDependency>,
+    /// Scaffolding templates for `luat new`.
+    #[serde(default)]
+    pub generators: GeneratorsConfig,
+    /// Key-value store settings.
+    #[serde(default)]
+    pub kv: KvConfig,
+    /// Feature flags for `flags.enabled()` and `{#feature}`.
+    #[serde(default)]
+    pub flags: FlagsConfig,
+    /// Translation catalogs for `t()` and the `i18n` module.
+    #[serde(default)]
+    pub i18n: I18nConfig,
+    /// S3-compatible object storage for the `storage` module.
+    #[serde(default)]
+    pub storage: StorageConfig,
+    /// Image resizing endpoint and `img()` helper.
+    #[serde(default)]
+    pub images: ImagesConfig,
+    /// OAuth2/OpenID Connect login for the `auth` module.
+    #[serde(default)]
+    pub auth: AuthConfig,
+}
+
+/// Backend used for the Lua `kv` module.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum KvBackend {
+    /// Persistent SQLite databases, one per namespace.
+    #[default]
+    Sqlite,
+    /// In-process store; data is lost on restart.
+    Memory,
+}
+
+/// Settings for the key-value store.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct KvConfig {
+    /// Storage backend: "sqlite" (default) or "memory".
+    #[serde(default)]
+    pub backend: KvBackend,
+}
+
+/// Feature flag definitions and their runtime overrides.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct FlagsConfig {
+    /// KV namespace whose values override flag definitions at runtime.
+    #[serde(default)]
+    pub overrides: Option<String>,
+    /// User cookie and flag definitions.
+    #[serde(flatten)]
+    pub flags: luat::extensions::FeatureFlags,
+}
+
+impl FlagsConfig {
+    /// Sets the flags on `engine`, reading overrides through `factory`.
+    pub fn apply<R: luat::ResourceResolver>(
+        &self,
+        engine: &luat::Engine<R>,
+        factory: &luat::kv::KVStoreFactory,
+    ) -> luat::Result<()> {
+        let overrides = self.overrides.as_deref().map(|namespace| factory(namespace));
+        engine.set_feature_flags(self.flags.clone(), overrides)
+    }
+}
+
+/// Translation catalogs, one file per locale (`en.json`, `de-CH.toml`).
+#[derive(Debug, Deserialize, Clone)]
+pub struct I18nConfig {
+    /// Directory holding the catalogs (default: "src/locales").
+    #[serde(default = "default_locales_dir")]
+    pub dir: String,
+    /// Locale used for keys and requests no catalog covers (default: "en").
+    #[serde(default = "default_locale")]
+    pub default_locale: String,
+}
+
+fn default_locales_dir() -> String {
+    "src/locales".to_string()
+}
+
+fn default_locale() -> String {
+    "en".to_string()
+}
+
+impl Default for I18nConfig {
+    fn default() -> Self {
+        Self {
+            dir: default_locales_dir(),
+            default_locale: default_locale(),
+        }
+    }
+}
+
+impl I18nConfig {
+    /// Reads the `<locale>.json` and `<locale>.toml` catalogs in `dir`; none
+    /// if it doesn't exist.
+    pub fn load_catalogs(&self, dir: &Path) -> anyhow::Result<luat::extensions::Catalogs> {
+        let mut catalogs = luat::extensions::Catalogs::new(&self.default_locale);
+        if !dir.is_dir() {
+            return Ok(catalogs);
+        }
+        let mut paths = fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        paths.sort();
+        for path in paths {
+            let Some(locale) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let messages: serde_json::Value = match path.extension().and_then(|ext| ext.to_str()) {
+                Some("json") => serde_json::from_str(&fs::read_to_string(&path)?)
+                    .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?,
+                Some("toml") => toml::from_str(&fs::read_to_string(&path)?)
+                    .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?,
+                _ => continue,
+            };
+            catalogs.insert(locale, &messages);
+        }
+        Ok(catalogs)
+    }
+
+    /// Loads the catalogs in `dir` into `engine`.
+    pub fn apply<R: luat::ResourceResolver>(&self, engine: &mut luat::Engine<R>, dir: &Path) -> anyhow::Result<()> {
+        engine.set_catalogs(self.load_catalogs(dir)?)?;
+        Ok(())
+    }
+}
+
+/// Settings for the `/__img` resizing endpoint and the `img()` helper.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ImagesConfig {
+    /// Widths `img()` generates and the endpoint serves
+    /// (default: 320, 640, 960, 1280, 1920).
+    #[serde(default = "default_image_widths")]
+    pub widths: Vec<u32>,
+    /// JPEG and WebP quality when a URL sets none (default: 80).
+    #[serde(default = "default_image_quality")]
+    pub quality: u8,
+    /// Directory for resized variants (default: ".luat/cache/images").
+    #[serde(default = "default_image_cache_dir")]
+    pub cache_dir: String,
+}
+
+fn default_image_widths() -> Vec<u32> {
+    luat::extensions::img::DEFAULT_IMAGE_WIDTHS.to_vec()
+}
+
+fn default_image_quality() -> u8 {
+    80
+}
+
+fn default_image_cache_dir() -> String {
+    ".luat/cache/images".to_string()
+}
+
+impl Default for ImagesConfig {
+    fn default() -> Self {
+        Self {
+            widths: default_image_widths(),
+            quality: default_image_quality(),
+            cache_dir: default_image_cache_dir(),
+        }
+    }
+}
+
+impl ImagesConfig {
+    /// Makes `img()` in `engine` generate the configured widths.
+    pub fn apply<R: luat::ResourceResolver>(&self, engine: &luat::Engine<R>) -> anyhow::Result<()> {
+        engine.set_image_widths(self.widths.clone())?;
+        Ok(())
+    }
+}
+
+/// OAuth2/OpenID Connect providers and the sessions created by logging in.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AuthConfig {
+    /// URL providers redirect back to, unless a provider sets its own
+    /// `redirect_uri`; its route calls `auth.callback`.
+    #[serde(default)]
+    pub callback_url: Option<String>,
+    /// Name of the session cookie (default: "luat_session").
+    #[serde(default = "default_session_cookie")]
+    pub cookie: String,
+    /// Seconds a session lasts (default: 7 days).
+    #[serde(default = "default_session_ttl")]
+    pub session_ttl: u64,
+    /// Leaves `Secure` off the cookies, for testing on plain HTTP hosts
+    /// other than localhost.
+    #[serde(default)]
+    pub insecure_cookies: bool,
+    /// Login providers by name, e.g. `github`.
+    #[serde(default)]
+    pub providers: BTreeMap<String, AuthProvider>,
+}
+
+/// An OAuth2 or OpenID Connect provider.
+///
+/// `github`, `gitlab`, `google` and `microsoft` are preset by name (or with
+/// `preset`); other OpenID Connect providers need an `issuer`, and plain
+/// OAuth2 providers their endpoint URLs.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct AuthProvider {
+    /// Preset the endpoints come from (default: the provider name, if known).
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// OAuth client id.
+    pub client_id: String,
+    /// OAuth client secret; prefer `client_secret_env`.
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    /// Environment variable holding the client secret
+    /// (default: `<NAME>_CLIENT_SECRET`).
+    #[serde(default)]
+    pub client_secret_env: Option<String>,
+    /// OpenID Connect issuer whose discovery document lists the endpoints.
+    #[serde(default)]
+    pub issuer: Option<String>,
+    /// Authorization endpoint, overriding the preset or discovery.
+    #[serde(default)]
+    pub authorize_url: Option<String>,
+    /// Token endpoint, overriding the preset or discovery.
+    #[serde(default)]
+    pub token_url: Option<String>,
+    /// User info endpoint, overriding the preset or discovery.
+    #[serde(default)]
+    pub userinfo_url: Option<String>,
+    /// Scopes requested, overriding the preset's.
+    #[serde(default)]
+    pub scopes: Option<Vec<String>>,
+    /// Redirect URI registered with the provider (default: `callback_url`).
+    #[serde(default)]
+    pub redirect_uri: Option<String>,
+}
+
+fn default_session_cookie() -> String {
+    "luat_session".to_string()
+}
+
+fn default_session_ttl() -> u64 {
+    7 * 24 * 60 * 60
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            callback_url: None,
+            cookie: default_session_cookie(),
+            session_ttl: default_session_ttl(),
+            insecure_cookies: false,
+            providers: BTreeMap::new(),
+        }
+    }
+}
+
+impl AuthConfig {
+    /// Registers the `auth` module on `engine`, keeping login state and
+    /// sessions in the KV stores of `factory`.
+    pub fn apply<R: luat::ResourceResolver>(
+        &self,
+        engine: &luat::Engine<R>,
+        factory: &luat::kv::KVStoreFactory,
+    ) -> luat::Result<()> {
+        crate::extensions::register_auth_module(engine.lua(), self.clone(), factory.clone())?;
+        Ok(())
+    }
+}
+
+/// S3-compatible bucket used by the Lua `storage` module.
+#[derive(Debug, Deserialize, Clone)]
+pub struct StorageConfig {
+    /// Service endpoint (default: AWS S3 in `region`).
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// Region used in request signatures (default: "us-east-1").
+    #[serde(default = "default_storage_region")]
+    pub region: String,
+    /// Bucket name; the module errors when unset.
+    #[serde(default)]
+    pub bucket: Option<String>,
+    /// Environment variable holding the access key id
+    /// (default: "AWS_ACCESS_KEY_ID").
+    #[serde(default = "default_access_key_env")]
+    pub access_key_env: String,
+    /// Environment variable holding the secret access key
+    /// (default: "AWS_SECRET_ACCESS_KEY").
+    #[serde(default = "default_secret_key_env")]
+    pub secret_key_env: String,
+    /// Addresses objects as `endpoint/bucket/key`, as MinIO needs (default: false).
+    #[serde(default)]
+    pub path_style: bool,
+    /// Lifetime of signed URLs in seconds (default: 3600).
+    #[serde(default = "default_signed_url_ttl")]
+    pub signed_url_ttl: u64,
+}
+
+fn default_storage_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_access_key_env() -> String {
+    "AWS_ACCESS_KEY_ID".to_string()
+}
+
+fn default_secret_key_env() -> String {
+    "AWS_SECRET_ACCESS_KEY".to_string()
+}
+
+fn default_signed_url_ttl() -> u64 {
+    3600
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            region: default_storage_region(),
+            bucket: None,
+            access_key_env: default_access_key_env(),
+            secret_key_env: default_secret_key_env(),
+            path_style: false,
+            signed_url_ttl: default_signed_url_ttl(),
+        }
+    }
+}
+
+impl StorageConfig {
+    /// Returns the configured bucket with credentials from the environment;
+    /// none without `bucket`.
+    pub fn bucket(&self) -> anyhow::Result<Option<crate::extensions::storage::S3Bucket>> {
+        let Some(bucket) = &self.bucket else {
+            return Ok(None);
+        };
+        let endpoint = match &self.endpoint {
+            Some(endpoint) => endpoint.clone(),
+            None => format!("https://s3.{}.amazonaws.com", self.region),
+        };
+        let credential = |name: &str| {
+            std::env::var(name)
+                .map_err(|_| anyhow::anyhow!("[storage] needs the {} environment variable", name))
+        };
+        Ok(Some(crate::extensions::storage::S3Bucket {
+            endpoint: reqwest::Url::parse(&endpoint)
+                .map_err(|e| anyhow::anyhow!("invalid [storage] endpoint '{}': {}", endpoint, e))?,
+            region: self.region.clone(),
+            bucket: bucket.clone(),
+            access_key: credential(&self.access_key_env)?,
+            secret_key: credential(&self.secret_key_env)?,
+            path_style: self.path_style,
+            signed_url_ttl: self.signed_url_ttl,
+        }))
+    }
+
+    /// Registers the `storage` module on `engine`.
+    pub fn apply<R: luat::ResourceResolver>(&self, engine: &luat::Engine<R>) -> anyhow::Result<()> {
+        crate::extensions::register_storage_module(engine.lua(), self.bucket()?)?;
+        Ok(())
+    }
+}
+
+/// Settings for the `luat new` scaffolding generator.
+#[derive(Debug, Deserialize, Clone)]
+pub struct GeneratorsConfig {
+    /// Directory with project-specific templates overriding the built-in ones
+    /// (default: ".luat/generators").
+    #[serde(default = "default_generators_dir")]
+    pub templates_dir: String,
+}
+
+fn default_generators_dir() -> String {
+    ".luat/generators".to_string()
+}
+
+impl Default for GeneratorsConfig {
+    fn default() -> Self {
+        Self {
+            templates_dir: default_generators_dir(),
+        }
+    }
+}
+
+/// A component package vendored from a git repository.
+///
+/// The repository is fetched at build/dev time and its `.luat`/`.lua` files
+/// are copied into `luat_modules/<name>/`, so `require("<name>/Button")`
+/// resolves to them.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct GitDependency {
+    /// Repository URL (anything `git clone` accepts).
+    pub git: String,
+    /// Commit, tag or branch to check out (default: the remote's HEAD).
+    #[serde(default)]
+    pub rev: Option<String>,
+}
+
+/// Security headers added by `luat serve` to every response, and request
+/// isolation for `luat dev` and `luat serve`.
+///
+/// All headers are opt-in. A `{nonce}` placeholder in `csp` is replaced with a
+/// fresh per-request nonce, which templates can read with `nonce()` and which
+/// is added automatically to emitted `<script>` and `<style>` tags.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct SecurityConfig {
+    /// `Content-Security-Policy` value, may contain `{nonce}`.
+    #[serde(default)]
+    pub csp: Option<String>,
+    /// `Strict-Transport-Security` value (e.g. "max-age=63072000").
+    #[serde(default)]
+    pub hsts: Option<String>,
+    /// `X-Frame-Options` value ("DENY" or "SAMEORIGIN").
+    #[serde(default)]
+    pub frame_options: Option<String>,
+    /// `Referrer-Policy` value.
+    #[serde(default)]
+    pub referrer_policy: Option<String>,
+    /// Send `X-Content-Type-Options: nosniff`.
+    #[serde(default)]
+    pub content_type_options: bool,
+    /// Undo the Lua globals each request sets, so requests rendered by the
+    /// same engine can't observe each other's.
+    #[serde(default)]
+    pub isolate_requests: bool,
+}
+
+impl SecurityConfig {
+    /// Returns true if the CSP uses a per-request nonce.
+    pub fn uses_nonce(&self) -> bool {
+        self.csp.as_deref().is_some_and(|csp| csp.contains("{nonce}"))
+    }
+}
+
+/// CORS settings for API routes (`+server.lua`), used by `luat dev` and
+/// `luat serve`.
+///
+/// Disabled unless `origins` is set. The server then answers preflight
+/// requests itself and adds `Access-Control-*` headers to API responses for
+/// allowed origins, so handlers don't have to.
+#[derive(Debug, Deserialize, Clone)]
+pub struct CorsConfig {
+    /// Allowed origins (e.g. "https://app.example.com"), or "*" for any.
+    #[serde(default)]
+    pub origins: Vec<String>,
+    /// Allowed methods (default: GET, HEAD, POST, PUT, PATCH, DELETE).
+    #[serde(default = "default_cors_methods")]
+    pub methods: Vec<String>,
+    /// Allowed request headers; when empty, those the browser asks for are allowed.
+    #[serde(default)]
+    pub headers: Vec<String>,
+    /// Response headers readable by scripts.
+    #[serde(default)]
+    pub expose_headers: Vec<String>,
+    /// Allow cookies and other credentials. Origins must then be listed;
+    /// `*` is refused, as it would let any site act for signed-in users.
+    #[serde(default)]
+    pub credentials: bool,
+    /// How long browsers may cache a preflight response, in seconds.
+    #[serde(default)]
+    pub max_age: Option<u64>,
+}
+
+fn default_cors_methods() -> Vec<String> {
+    ["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE"]
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            origins: Vec::new(),
+            methods: default_cors_methods(),
+            headers: Vec::new(),
+            expose_headers: Vec::new(),
+            credentials: false,
+            max_age: None,
+        }
+    }
+}
+
+impl CorsConfig {
+    /// Returns true if any origin is allowed.
+    pub fn is_enabled(&self) -> bool {
+        !self.origins.is_empty()
+    }
+
+    /// Refuses `credentials` with a `*` origin.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.credentials && self.origins.iter().any(|origin| origin == "*") {
+            anyhow::bail!("[cors] credentials = true needs explicit origins, not \"*\"");
+        }
+        Ok(())
+    }
+}
+
+/// Settings for the routes matching a `[routes."<pattern>"]` key.
+///
+/// In the pattern, `*` matches within a path segment and `**` across
+/// segments. When several patterns match, later ones (in sorted order) win.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct RouteConfig {
+    /// Response headers added to page and API responses, unless the route's
+    /// server modules or handlers set them.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Per-request resource limits applied by `luat dev` and `luat serve`.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct LimitsConfig {
+    /// Maximum rendered HTML size per template render, in bytes (default: unlimited).
+    #[serde(default)]
+    pub max_output_bytes: Option<usize>,
+    /// Maximum memory of an engine's Lua state, in bytes (default: unlimited).
+    /// Renders that go over it fail with a resource limit error.
+    #[serde(default)]
+    pub memory_bytes: Option<usize>,
+    /// Longest a template render may run, in milliseconds (default: unlimited).
+    #[serde(default)]
+    pub render_timeout_ms: Option<u64>,
+    /// Most Lua instructions a template render may run (default: unlimited).
+    #[serde(default)]
+    pub max_instructions: Option<u64>,
+}
+
+impl LimitsConfig {
+    /// Applies the limits to `engine`.
+    pub fn apply<R: luat::ResourceResolver>(&self, engine: &mut luat::Engine<R>) -> anyhow::Result<()> {
+        engine.set_max_output_bytes(self.max_output_bytes);
+        engine.set_memory_limit_bytes(self.memory_bytes)?;
+        engine.set_render_timeout(self.render_timeout_ms.map(std::time::Duration::from_millis));
+        engine.set_instruction_limit(self.max_instructions);
+        Ok(())
+    }
+}
+
+/// Page cache configuration used by `luat serve`.
+///
+/// Rendered HTML for matching `GET` and `HEAD` requests is stored in the KV
+/// layer and served without running load functions or templates until it
+/// expires.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct PageCacheConfig {
+    /// Cache rules, checked in order; the first matching rule wins.
+    #[serde(default)]
+    pub rules: Vec<PageCacheRule>,
+}
+
+/// A single page cache rule.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PageCacheRule {
+    /// Glob pattern matched against the request path (e.g. "/blog/**").
+    ///
+    /// `*` matches within a path segment, `**` matches across segments.
+    pub path: String,
+
+    /// Time-to-live for cached responses, in seconds (default: 60).
+    #[serde(default = "default_cache_ttl")]
+    pub ttl: u64,
+
+    /// Request headers whose values are part of the cache key.
+    #[serde(default)]
+    pub vary: Vec<String>,
+
+    /// Cookie name that bypasses the cache when present (e.g. a session cookie).
+    #[serde(default)]
+    pub bypass_cookie: Option<String>,
+}
+
+fn default_cache_ttl() -> u64 {
+    60
 }
 
 /// Routing configuration for file-based routing.
@@ -72,6 +720,22 @@ pub struct RoutingConfig {
     #[serde(default = "default_lib_dir")]
     pub lib_dir: String,
 
+    /// Directory for installed component packages (default: "luat_modules").
+    #[serde(default = "default_modules_dir")]
+    pub modules_dir: String,
+
+    /// Additional source roots shared between apps (e.g. `["../shared/components"]`).
+    ///
+    /// Modules in these directories can be required by bare name, are
+    /// watched by `luat dev` and are included in `luat build`.
+    #[serde(default)]
+    pub source_roots: Vec<String>,
+
+    /// Directories component tags are imported from without a `require`
+    /// or `{#use}` (e.g. `["$lib/components"]`; default: none).
+    #[serde(default)]
+    pub component_dirs: Vec<String>,
+
     /// Directory for static files (default: "static").
     #[serde(default = "default_static_dir")]
     pub static_dir: String,
@@ -83,6 +747,24 @@ pub struct RoutingConfig {
     /// Directory for persistent data storage like KV store (default: ".luat/data").
     #[serde(default = "default_data_dir")]
     pub data_dir: String,
+
+    /// Directory of the seed scripts `luat seed` runs (default: "src/seeds").
+    #[serde(default = "default_seeds_dir")]
+    pub seeds_dir: String,
+
+    /// Directory of the content collections `content.query` reads
+    /// (default: "src/content").
+    #[serde(default = "default_content_dir")]
+    pub content_dir: String,
+    /// Milliseconds a page may take before the static start of the app
+    /// shell is flushed ahead of it (default: never).
+    ///
+    /// Once flushed, the response is `200 OK` with the `[routes]` headers of
+    /// the path: a later redirect becomes a `<meta http-equiv="refresh">`,
+    /// an error page is marked `noindex`, and headers and cookies the page
+    /// sets are dropped. Leave it unset for pages that rely on those.
+    #[serde(default)]
+    pub early_flush_ms: Option<u64>,
 }
 
 fn default_routes_dir() -> String {
@@ -93,6 +775,10 @@ fn default_lib_dir() -> String {
     "src/lib".to_string()
 }
 
+fn default_modules_dir() -> String {
+    "luat_modules".to_string()
+}
+
 fn default_static_dir() -> String {
     "static".to_string()
 }
@@ -105,15 +791,29 @@ fn default_data_dir() -> String {
     ".luat/data".to_string()
 }
 
+fn default_seeds_dir() -> String {
+    "src/seeds".to_string()
+}
+
+fn default_content_dir() -> String {
+    "src/content".to_string()
+}
+
 impl Default for RoutingConfig {
     fn default() -> Self {
         Self {
             simplified: false,
             routes_dir: default_routes_dir(),
             lib_dir: default_lib_dir(),
+            modules_dir: default_modules_dir(),
+            source_roots: Vec::new(),
+            component_dirs: Vec::new(),
             static_dir: default_static_dir(),
             app_html: default_app_html(),
             data_dir: default_data_dir(),
+            seeds_dir: default_seeds_dir(),
+            content_dir: default_content_dir(),
+            early_flush_ms: None,
         }
     }
 }
@@ -143,6 +843,14 @@ pub struct DevConfig {
     /// Public assets directory (default: "public").
     #[serde(default = "default_public_dir")]
     pub public_dir: String,
+    /// Log every unescaped output site (`{@html}`, raw attributes, script
+    /// interpolation) reached while rendering (default: false).
+    #[serde(default)]
+    pub escape_audit: bool,
+    /// Freeze time, seed randomness and sort table iteration so renders are
+    /// byte-stable across runs, e.g. for snapshot tests (default: false).
+    #[serde(default)]
+    pub deterministic: bool,
 }
 
 /// Production build configuration.
@@ -154,6 +862,30 @@ pub struct BuildConfig {
     /// Bundle format: "lua" or "binary" (default: "lua").
     #[serde(default = "default_bundle_format")]
     pub bundle_format: String,
+    /// Compute Subresource Integrity hashes for built CSS/JS assets (default: false).
+    #[serde(default)]
+    pub sri: bool,
+    /// Encrypt `bundle.bin` with the key from `LUAT_BUNDLE_KEY` (default: false).
+    ///
+    /// See [`crate::encryption`].
+    #[serde(default)]
+    pub encrypt: bool,
+    /// Collapse runs of whitespace in HTML templates to a single space (default: false).
+    ///
+    /// Also applies to the dev server, so pages look the same as when built.
+    #[serde(default)]
+    pub collapse_whitespace: bool,
+    /// Write each module to its own file under `dist/chunks`, loaded on its
+    /// first `require`, instead of into `bundle.bin` (default: false).
+    #[serde(default)]
+    pub chunked: bool,
+    /// Build-time defines for `defines.NAME` in templates, as `NAME=VALUE`
+    /// (default: none). `--define` adds to them.
+    ///
+    /// Also apply to the dev server, `luat watch` and `luat serve`, so
+    /// templates compile the same everywhere.
+    #[serde(default)]
+    pub defines: Vec<String>,
 }
 
 fn default_version() -> String {
@@ -191,6 +923,8 @@ impl Default for DevConfig {
             host: default_host(),
             templates_dir: default_templates_dir(),
             public_dir: default_public_dir(),
+            escape_audit: false,
+            deterministic: false,
         }
     }
 }
@@ -200,6 +934,11 @@ impl Default for BuildConfig {
         Self {
             output_dir: default_output_dir(),
             bundle_format: default_bundle_format(),
+            sri: false,
+            encrypt: false,
+            collapse_whitespace: false,
+            chunked: false,
+            defines: Vec::new(),
         }
     }
 }
@@ -213,24 +952,108 @@ impl Config {
     ///
     /// Returns an error if the configuration file exists but cannot be parsed.
     pub fn load() -> anyhow::Result<Self> {
-        let config_path = Path::new("luat.toml");
+        Self::load_from(Path::new("."))
+    }
+
+    /// Loads configuration from `luat.toml` in `dir`, or the defaults if the
+    /// file does not exist.
+    pub fn load_from(dir: &Path) -> anyhow::Result<Self> {
+        let config_path = dir.join("luat.toml");
 
         if !config_path.exists() {
             // Return default config if no config file exists
-            return Ok(Config {
-                project: ProjectConfig {
-                    name: "unnamed".to_string(),
-                    version: default_version(),
-                },
-                dev: DevConfig::default(),
-                build: BuildConfig::default(),
-                frontend: ToolchainConfig::default(),
-                routing: RoutingConfig::default(),
-            });
+            return Ok(Self::defaults());
         }
 
         let content = fs::read_to_string(config_path)?;
         let config: Config = toml::from_str(&content)?;
+        config.cors.validate()?;
         Ok(config)
     }
+
+    /// Returns the configuration used when there is no `luat.toml`.
+    pub fn defaults() -> Self {
+        Config {
+            project: ProjectConfig {
+                name: "unnamed".to_string(),
+                version: default_version(),
+            },
+            dev: DevConfig::default(),
+            build: BuildConfig::default(),
+            frontend: ToolchainConfig::default(),
+            routing: RoutingConfig::default(),
+            page_cache: PageCacheConfig::default(),
+            limits: LimitsConfig::default(),
+            security: SecurityConfig::default(),
+            cors: CorsConfig::default(),
+            routes: BTreeMap::new(),
+            dependencies: BTreeMap::new(),
+            generators: GeneratorsConfig::default(),
+            kv: KvConfig::default(),
+            flags: FlagsConfig::default(),
+            i18n: I18nConfig::default(),
+            storage: StorageConfig::default(),
+            images: ImagesConfig::default(),
+            auth: AuthConfig::default(),
+        }
+    }
+
+    /// Returns the top-level sections of `luat.toml`: the fields [`Config`]
+    /// deserializes, so the list follows the struct.
+    pub fn sections() -> &'static [&'static str] {
+        let mut fields: &'static [&'static str] = &[];
+        // Fails once the field names are read; nothing is deserialized
+        let _ = Config::deserialize(FieldNames(&mut fields));
+        fields
+    }
+
+    /// Adds the `[routing] component_dirs` to `engine`.
+    pub fn apply_component_dirs<R: luat::ResourceResolver>(&self, engine: &luat::Engine<R>) {
+        for dir in &self.routing.component_dirs {
+            engine.component_dirs().add(dir);
+        }
+    }
+
+    /// Sets the `[build] defines` on `engine`.
+    pub fn apply_defines<R: luat::ResourceResolver>(&self, engine: &luat::Engine<R>) -> luat::Result<()> {
+        for define in &self.build.defines {
+            engine.defines().parse(define)?;
+        }
+        Ok(())
+    }
+
+    /// Adds the `[routes]` response headers to `engine`.
+    pub fn apply_route_headers<R: luat::ResourceResolver>(&self, engine: &mut luat::Engine<R>) {
+        for (pattern, route) in &self.routes {
+            engine.add_route_headers(pattern, route.headers.clone().into_iter().collect());
+        }
+    }
+}
+
+/// A deserializer that records the field names of the struct deserialized
+/// from it, then fails; see [`Config::sections`].
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+impl<'de> serde::Deserializer<'de> for FieldNames<'_> {
+    type Error = serde::de::value::Error;
+
+    fn deserialize_any<V: serde::de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(serde::de::Error::custom("expected a struct"))
+    }
+
+    fn deserialize_struct<V: serde::de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        Err(serde::de::Error::custom("field names recorded"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
 }
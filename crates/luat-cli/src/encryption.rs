@@ -0,0 +1,162 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Bundle encryption for closed-source template distribution.
+//!
+//! With `[build] encrypt = true`, `luat build` encrypts `dist/bundle.bin`
+//! (compiled templates and server sources) with AES-256-GCM. The key is a
+//! 32-byte key written as 64 hex characters, taken from `LUAT_BUNDLE_KEY` or
+//! from the file named by `LUAT_BUNDLE_KEY_FILE`. `luat serve` and
+//! `luat export` decrypt the bundle at load with the key from the same
+//! variables; a wrong or missing key fails before anything is served.
+//!
+//! Generate a key with `openssl rand -hex 32`.
+
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::path::Path;
+
+/// Environment variable holding the bundle key as hex.
+pub const KEY_ENV: &str = "LUAT_BUNDLE_KEY";
+
+/// Environment variable naming a file that holds the bundle key as hex.
+pub const KEY_FILE_ENV: &str = "LUAT_BUNDLE_KEY_FILE";
+
+/// Marks an encrypted bundle; followed by the nonce and the sealed bundle.
+const MAGIC: &[u8; 8] = b"LUATENC1";
+
+/// AES-256-GCM key for bundle encryption.
+#[derive(Clone)]
+pub struct BundleKey([u8; 32]);
+
+impl std::fmt::Debug for BundleKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BundleKey(..)")
+    }
+}
+
+impl BundleKey {
+    /// Parses a key written as 64 hex characters.
+    pub fn from_hex(hex: &str) -> anyhow::Result<Self> {
+        let hex = hex.trim();
+        if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            anyhow::bail!("Bundle key must be 64 hex characters (32 bytes)");
+        }
+        let mut key = [0u8; 32];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)?;
+        }
+        Ok(Self(key))
+    }
+
+    /// Reads the key from `LUAT_BUNDLE_KEY` or `LUAT_BUNDLE_KEY_FILE`.
+    ///
+    /// Returns `None` when neither is set.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        if let Ok(hex) = std::env::var(KEY_ENV) {
+            return Self::from_hex(&hex).map(Some);
+        }
+        match std::env::var(KEY_FILE_ENV) {
+            Ok(path) => {
+                let hex = std::fs::read_to_string(&path)
+                    .map_err(|e| anyhow::anyhow!("Failed to read {} ({}): {}", KEY_FILE_ENV, path, e))?;
+                Self::from_hex(&hex).map(Some)
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn aead_key(&self) -> LessSafeKey {
+        let key = UnboundKey::new(&AES_256_GCM, &self.0).expect("AES-256 keys are 32 bytes");
+        LessSafeKey::new(key)
+    }
+}
+
+/// Returns true if `data` is an encrypted bundle.
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Encrypts a bundle under `key` with a fresh random nonce.
+pub fn encrypt(bundle: &[u8], key: &BundleKey) -> anyhow::Result<Vec<u8>> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| anyhow::anyhow!("Failed to generate a nonce for bundle encryption"))?;
+
+    let mut sealed = bundle.to_vec();
+    key.aead_key()
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(MAGIC), &mut sealed)
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt the bundle"))?;
+
+    let mut out = Vec::with_capacity(MAGIC.len() + NONCE_LEN + sealed.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&sealed);
+    Ok(out)
+}
+
+/// Decrypts a bundle produced by [`encrypt`].
+pub fn decrypt(data: &[u8], key: &BundleKey) -> anyhow::Result<Vec<u8>> {
+    if !is_encrypted(data) || data.len() < MAGIC.len() + NONCE_LEN {
+        anyhow::bail!("Not an encrypted bundle");
+    }
+    let (nonce, sealed) = data[MAGIC.len()..].split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce)
+        .map_err(|_| anyhow::anyhow!("Encrypted bundle has an invalid nonce"))?;
+
+    let mut plain = sealed.to_vec();
+    let len = key
+        .aead_key()
+        .open_in_place(nonce, Aad::from(MAGIC), &mut plain)
+        .map_err(|_| anyhow::anyhow!("Failed to decrypt the bundle: wrong key or corrupted bundle"))?
+        .len();
+    plain.truncate(len);
+    Ok(plain)
+}
+
+/// Reads a bundle file, decrypting it with the key from the environment if
+/// it is encrypted.
+pub fn read_bundle(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let data = std::fs::read(path)?;
+    if !is_encrypted(&data) {
+        return Ok(data);
+    }
+    let key = BundleKey::from_env()?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "{} is encrypted; set {} or {} to the key it was built with",
+            path.display(),
+            KEY_ENV,
+            KEY_FILE_ENV
+        )
+    })?;
+    decrypt(&data, &key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    #[test]
+    fn test_round_trip() {
+        let key = BundleKey::from_hex(KEY).unwrap();
+        let encrypted = encrypt(b"return __modules", &key).unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.windows(7).any(|w| w == b"modules"));
+        assert_eq!(decrypt(&encrypted, &key).unwrap(), b"return __modules");
+
+        let other = BundleKey::from_hex(&KEY.replace("1f", "ff")).unwrap();
+        let err = decrypt(&encrypted, &other).unwrap_err();
+        assert!(err.to_string().contains("wrong key"), "{}", err);
+    }
+
+    #[test]
+    fn test_key_parsing() {
+        assert!(BundleKey::from_hex(&format!("{}\n", KEY)).is_ok());
+        assert!(BundleKey::from_hex("abcd").is_err());
+        assert!(BundleKey::from_hex(&KEY.replace('0', "g")).is_err());
+    }
+}
@@ -0,0 +1,796 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! OAuth2/OpenID Connect login module for Lua.
+//!
+//! Implements the authorization-code flow with PKCE for the providers in
+//! `[auth.providers]` and keeps the signed-in user in a cookie session. The
+//! state of a login in progress and the sessions live in the KV store.
+//!
+//! # Example
+//!
+//! ```lua
+//! -- src/routes/auth/login/[provider]/+server.lua
+//! local auth = require("auth")
+//! function GET(ctx)
+//!     return auth.login(ctx.params.provider, { redirect_to = ctx.query.next })
+//! end
+//!
+//! -- src/routes/auth/callback/+server.lua
+//! function GET(ctx)
+//!     local response, err = auth.callback(ctx)
+//!     return response or { status = 400, body = { error = err } }
+//! end
+//!
+//! -- +page.server.lua, actions and API routes
+//! local user = auth.user(ctx)
+//! if not user then
+//!     return { redirect = "/auth/login/github?next=" .. ctx.url }
+//! end
+//! ```
+//!
+//! `auth.login(provider, options)` returns a response redirecting to the
+//! provider, with `redirect_to` (a path on this site, default `/`), and
+//! `scopes` and `params` (extra authorization parameters, e.g. `prompt`)
+//! as options. `auth.callback(ctx)` checks the state, exchanges the code,
+//! fetches the user and returns a response that sets the session cookie
+//! and redirects to `redirect_to`, and the session; or `nil` and an error.
+//!
+//! `auth.user(ctx)` returns the signed-in user (`id`, `email`, `name`,
+//! `avatar`, `provider` and the provider's `raw` user info) or `nil`;
+//! `auth.session(ctx)` the whole session, with the provider's `tokens`.
+//! `auth.logout(ctx, options)` ends the session and returns a response
+//! redirecting to `redirect_to`. `auth.providers()` lists the providers.
+//!
+//! The user comes from the provider's user info endpoint, called with the
+//! access token received directly from the token endpoint; ID tokens are
+//! kept in `tokens` but not verified. `email` is only set once the provider
+//! reports it verified (`email_verified`, or GitHub's verified primary
+//! address).
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use luat::kv::{KVStore, KVStoreFactory, PutOptions};
+use mlua::{Lua, LuaSerdeExt, Result as LuaResult, Table, Value};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use sha2::{Digest, Sha256};
+
+use crate::config::{AuthConfig, AuthProvider};
+
+/// KV namespace of logins waiting for their callback, keyed by state.
+const PENDING_NAMESPACE: &str = "__auth_pending";
+
+/// KV namespace of sessions, keyed by session id.
+const SESSION_NAMESPACE: &str = "__auth_sessions";
+
+/// Cookie binding a login to the browser that started it.
+const STATE_COOKIE: &str = "luat_auth_state";
+
+/// Seconds a login may take from `auth.login` to `auth.callback`.
+const LOGIN_TIMEOUT_SECS: u64 = 600;
+
+/// Timeout of requests to the provider.
+const HTTP_TIMEOUT_SECS: u64 = 10;
+
+/// Endpoints and default scopes of a provider.
+#[derive(Debug, Clone, PartialEq)]
+struct Endpoints {
+    authorize: String,
+    token: String,
+    userinfo: String,
+    scopes: Vec<String>,
+}
+
+/// Returns the endpoints of a well-known provider.
+fn preset(name: &str) -> Option<Endpoints> {
+    let (authorize, token, userinfo, scopes): (&str, &str, &str, &[&str]) = match name {
+        "github" => (
+            "https://github.com/login/oauth/authorize",
+            "https://github.com/login/oauth/access_token",
+            "https://api.github.com/user",
+            &["read:user", "user:email"],
+        ),
+        "gitlab" => (
+            "https://gitlab.com/oauth/authorize",
+            "https://gitlab.com/oauth/token",
+            "https://gitlab.com/oauth/userinfo",
+            &["openid", "email", "profile"],
+        ),
+        "google" => (
+            "https://accounts.google.com/o/oauth2/v2/auth",
+            "https://oauth2.googleapis.com/token",
+            "https://openidconnect.googleapis.com/v1/userinfo",
+            &["openid", "email", "profile"],
+        ),
+        "microsoft" => (
+            "https://login.microsoftonline.com/common/oauth2/v2.0/authorize",
+            "https://login.microsoftonline.com/common/oauth2/v2.0/token",
+            "https://graph.microsoft.com/oidc/userinfo",
+            &["openid", "email", "profile"],
+        ),
+        _ => return None,
+    };
+    Some(Endpoints {
+        authorize: authorize.to_string(),
+        token: token.to_string(),
+        userinfo: userinfo.to_string(),
+        scopes: scopes.iter().map(|s| s.to_string()).collect(),
+    })
+}
+
+/// A login waiting for its callback.
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingLogin {
+    provider: String,
+    verifier: String,
+    redirect_to: String,
+}
+
+/// A signed-in user's session.
+#[derive(Debug, Serialize, Deserialize)]
+struct Session {
+    provider: String,
+    user: JsonValue,
+    tokens: JsonValue,
+    created_at: u64,
+}
+
+/// State shared by the `auth` functions.
+struct Auth {
+    config: AuthConfig,
+    pending: Arc<dyn KVStore>,
+    sessions: Arc<dyn KVStore>,
+    /// Endpoints from OpenID Connect discovery, by issuer.
+    discovered: Mutex<HashMap<String, Endpoints>>,
+}
+
+/// Register the auth module on the given Lua instance.
+///
+/// This makes `auth.login()`, `auth.callback()`, `auth.user()`,
+/// `auth.session()`, `auth.logout()` and `auth.providers()` available in
+/// Lua code, also as `require("auth")`.
+pub fn register_auth_module(lua: &Lua, config: AuthConfig, factory: KVStoreFactory) -> LuaResult<()> {
+    let auth = Arc::new(Auth {
+        config,
+        pending: factory(PENDING_NAMESPACE),
+        sessions: factory(SESSION_NAMESPACE),
+        discovered: Mutex::new(HashMap::new()),
+    });
+    let module = lua.create_table()?;
+
+    let state = auth.clone();
+    module.set(
+        "login",
+        lua.create_function(move |lua, (provider, options): (String, Option<Table>)| {
+            state.login(lua, &provider, options)
+        })?,
+    )?;
+
+    let state = auth.clone();
+    module.set(
+        "callback",
+        lua.create_function(move |lua, ctx: Table| match state.callback(lua, &ctx)? {
+            Ok((response, session)) => Ok((Value::Table(response), session)),
+            Err(message) => Ok((Value::Nil, Value::String(lua.create_string(&message)?))),
+        })?,
+    )?;
+
+    let state = auth.clone();
+    module.set(
+        "session",
+        lua.create_function(move |lua, ctx: Table| match state.current_session(&ctx)? {
+            Some((id, session)) => session_to_lua(lua, &id, &session),
+            None => Ok(Value::Nil),
+        })?,
+    )?;
+
+    let state = auth.clone();
+    module.set(
+        "user",
+        lua.create_function(move |lua, ctx: Table| match state.current_session(&ctx)? {
+            Some((_, session)) => lua.to_value(&session.user),
+            None => Ok(Value::Nil),
+        })?,
+    )?;
+
+    let state = auth.clone();
+    module.set(
+        "logout",
+        lua.create_function(move |lua, (ctx, options): (Table, Option<Table>)| state.logout(lua, &ctx, options))?,
+    )?;
+
+    let state = auth;
+    module.set(
+        "providers",
+        lua.create_function(move |_, ()| Ok(state.config.providers.keys().cloned().collect::<Vec<_>>()))?,
+    )?;
+
+    let globals = lua.globals();
+    globals.set("auth", module)?;
+
+    let package: Table = globals.get("package")?;
+    let preload: Table = package.get("preload")?;
+    preload.set(
+        "auth",
+        lua.create_function(|lua, _: ()| lua.globals().get::<Table>("auth"))?,
+    )?;
+
+    Ok(())
+}
+
+impl Auth {
+    fn provider(&self, name: &str) -> LuaResult<&AuthProvider> {
+        self.config
+            .providers
+            .get(name)
+            .ok_or_else(|| mlua::Error::runtime(format!("auth: unknown provider '{}'", name)))
+    }
+
+    /// Resolves the endpoints of a provider from its preset or issuer and
+    /// its own settings.
+    fn endpoints(&self, name: &str, provider: &AuthProvider) -> Result<Endpoints, String> {
+        let base = match (&provider.issuer, &provider.preset) {
+            (Some(issuer), _) => Some(self.discover(issuer)?),
+            (None, Some(preset_name)) => {
+                Some(preset(preset_name).ok_or_else(|| format!("unknown preset '{}'", preset_name))?)
+            }
+            (None, None) => preset(name),
+        };
+        let url = |own: &Option<String>, base: Option<&String>, field: &str| {
+            own.clone()
+                .or_else(|| base.cloned())
+                .ok_or_else(|| format!("provider '{}' needs an issuer, a preset or a {}", name, field))
+        };
+        Ok(Endpoints {
+            authorize: url(&provider.authorize_url, base.as_ref().map(|e| &e.authorize), "authorize_url")?,
+            token: url(&provider.token_url, base.as_ref().map(|e| &e.token), "token_url")?,
+            userinfo: url(&provider.userinfo_url, base.as_ref().map(|e| &e.userinfo), "userinfo_url")?,
+            scopes: provider
+                .scopes
+                .clone()
+                .or_else(|| base.map(|e| e.scopes))
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Reads the OpenID Connect discovery document of `issuer`, once.
+    fn discover(&self, issuer: &str) -> Result<Endpoints, String> {
+        if let Some(endpoints) = self.discovered.lock().unwrap_or_else(PoisonError::into_inner).get(issuer) {
+            return Ok(endpoints.clone());
+        }
+        let url = format!("{}/.well-known/openid-configuration", issuer.trim_end_matches('/'));
+        let document = get_json(&url, None)?;
+        let field = |key: &str| {
+            document
+                .get(key)
+                .and_then(JsonValue::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| format!("{} has no {}", url, key))
+        };
+        let endpoints = Endpoints {
+            authorize: field("authorization_endpoint")?,
+            token: field("token_endpoint")?,
+            userinfo: field("userinfo_endpoint")?,
+            scopes: vec!["openid".to_string(), "email".to_string(), "profile".to_string()],
+        };
+        self.discovered
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(issuer.to_string(), endpoints.clone());
+        Ok(endpoints)
+    }
+
+    fn redirect_uri<'a>(&'a self, provider: &'a AuthProvider) -> Option<&'a str> {
+        provider.redirect_uri.as_deref().or(self.config.callback_url.as_deref())
+    }
+
+    fn login(&self, lua: &Lua, name: &str, options: Option<Table>) -> LuaResult<Table> {
+        let provider = self.provider(name)?;
+        let endpoints = self
+            .endpoints(name, provider)
+            .map_err(|e| mlua::Error::runtime(format!("auth: {}", e)))?;
+        let redirect_uri = self
+            .redirect_uri(provider)
+            .ok_or_else(|| mlua::Error::runtime("auth: set [auth] callback_url or the provider's redirect_uri"))?;
+
+        let mut redirect_to = None;
+        let mut scopes = endpoints.scopes;
+        let mut extra = Vec::new();
+        if let Some(options) = &options {
+            redirect_to = options.get::<Option<String>>("redirect_to")?;
+            if let Some(custom) = options.get::<Option<Vec<String>>>("scopes")? {
+                scopes = custom;
+            }
+            if let Some(params) = options.get::<Option<Table>>("params")? {
+                for pair in params.pairs::<String, String>() {
+                    extra.push(pair?);
+                }
+            }
+        }
+
+        let state = random_token()?;
+        let verifier = random_token()?;
+        let pending = PendingLogin {
+            provider: name.to_string(),
+            verifier: verifier.clone(),
+            redirect_to: safe_redirect(redirect_to.as_deref()),
+        };
+        put_json(&*self.pending, &state, &pending, LOGIN_TIMEOUT_SECS)?;
+
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+        let scope = scopes.join(" ");
+        let mut params = vec![
+            ("response_type", "code"),
+            ("client_id", provider.client_id.as_str()),
+            ("redirect_uri", redirect_uri),
+            ("state", state.as_str()),
+            ("code_challenge", challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ];
+        if !scope.is_empty() {
+            params.push(("scope", scope.as_str()));
+        }
+        params.extend(extra.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        let location = reqwest::Url::parse_with_params(&endpoints.authorize, &params)
+            .map_err(|e| mlua::Error::runtime(format!("auth: invalid authorize_url: {}", e)))?;
+
+        redirect(lua, location.as_str(), &self.cookie(STATE_COOKIE, &state, LOGIN_TIMEOUT_SECS))
+    }
+
+    /// Finishes a login; the outer error is for Lua failures, the inner one
+    /// for logins that failed.
+    fn callback(&self, lua: &Lua, ctx: &Table) -> LuaResult<Result<(Table, Value), String>> {
+        let query: Table = ctx.get("query")?;
+        if let Some(error) = query.get::<Option<String>>("error")? {
+            let description = query.get::<Option<String>>("error_description")?;
+            return Ok(Err(match description {
+                Some(description) => format!("{}: {}", error, description),
+                None => error,
+            }));
+        }
+        let (Some(state), Some(code)) = (query.get::<Option<String>>("state")?, query.get::<Option<String>>("code")?)
+        else {
+            return Ok(Err("missing code or state".to_string()));
+        };
+        if cookie(ctx, STATE_COOKIE)?.as_deref() != Some(state.as_str()) {
+            return Ok(Err("login state does not match this browser".to_string()));
+        }
+        let Some(pending) = take_json::<PendingLogin>(&*self.pending, &state)? else {
+            return Ok(Err("unknown or expired login".to_string()));
+        };
+
+        let name = pending.provider.as_str();
+        let provider = self.provider(name)?;
+        let session = match self.exchange(name, provider, &code, &pending.verifier) {
+            Ok(session) => session,
+            Err(message) => return Ok(Err(message)),
+        };
+
+        let id = random_token()?;
+        put_json(&*self.sessions, &id, &session, self.config.session_ttl)?;
+        // Set-Cookie values are separated by newlines; the state cookie is done
+        let cookies = format!(
+            "{}\n{}",
+            self.cookie(&self.config.cookie, &id, self.config.session_ttl),
+            self.cookie(STATE_COOKIE, "", 0)
+        );
+        let response = redirect(lua, &pending.redirect_to, &cookies)?;
+        Ok(Ok((response, session_to_lua(lua, &id, &session)?)))
+    }
+
+    /// Exchanges `code` for tokens and fetches the user they belong to.
+    fn exchange(&self, name: &str, provider: &AuthProvider, code: &str, verifier: &str) -> Result<Session, String> {
+        let endpoints = self.endpoints(name, provider)?;
+        let redirect_uri = self.redirect_uri(provider).unwrap_or_default();
+        let secret = client_secret(name, provider);
+
+        let mut form = form_urlencoded::Serializer::new(String::new());
+        form.append_pair("grant_type", "authorization_code")
+            .append_pair("code", code)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("client_id", &provider.client_id)
+            .append_pair("code_verifier", verifier);
+        if let Some(secret) = &secret {
+            form.append_pair("client_secret", secret);
+        }
+        let response = client()?
+            .post(&endpoints.token)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Accept", "application/json")
+            .body(form.finish())
+            .send()
+            .map_err(|e| format!("token request failed: {}", e))?;
+        let tokens: JsonValue = response
+            .json()
+            .map_err(|e| format!("invalid token response: {}", e))?;
+        let Some(access_token) = tokens.get("access_token").and_then(JsonValue::as_str) else {
+            let error = tokens.get("error").and_then(JsonValue::as_str).unwrap_or("no access token");
+            return Err(match tokens.get("error_description").and_then(JsonValue::as_str) {
+                Some(description) => format!("{}: {}", error, description),
+                None => error.to_string(),
+            });
+        };
+
+        let mut info = get_json(&endpoints.userinfo, Some(access_token))?;
+        // GitHub only publishes verified addresses and has no `email_verified`
+        let github = endpoints.userinfo == "https://api.github.com/user";
+        if matches!(info.get("email"), None | Some(JsonValue::Null)) && github {
+            // GitHub leaves out private addresses; the primary one is listed
+            // separately, and only counts once the user has verified it
+            if let Ok(JsonValue::Array(emails)) = get_json("https://api.github.com/user/emails", Some(access_token)) {
+                let primary = emails.iter().find(|e| {
+                    e.get("primary") == Some(&JsonValue::Bool(true)) && e.get("verified") == Some(&JsonValue::Bool(true))
+                });
+                if let (Some(email), JsonValue::Object(info)) = (primary.and_then(|e| e.get("email")), &mut info) {
+                    info.insert("email".to_string(), email.clone());
+                }
+            }
+        }
+
+        Ok(Session {
+            provider: name.to_string(),
+            user: normalize_user(name, info, github),
+            tokens,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        })
+    }
+
+    fn current_session(&self, ctx: &Table) -> LuaResult<Option<(String, Session)>> {
+        let Some(id) = cookie(ctx, &self.config.cookie)? else {
+            return Ok(None);
+        };
+        let session = self
+            .sessions
+            .get(&id)
+            .map_err(mlua::Error::external)?
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+        Ok(session.map(|session| (id, session)))
+    }
+
+    fn logout(&self, lua: &Lua, ctx: &Table, options: Option<Table>) -> LuaResult<Table> {
+        if let Some(id) = cookie(ctx, &self.config.cookie)? {
+            self.sessions.delete(&id).map_err(mlua::Error::external)?;
+        }
+        let redirect_to = match &options {
+            Some(options) => options.get::<Option<String>>("redirect_to")?,
+            None => None,
+        };
+        redirect(lua, &safe_redirect(redirect_to.as_deref()), &self.cookie(&self.config.cookie, "", 0))
+    }
+
+    /// Returns a `Set-Cookie` value for an HTTP-only cookie.
+    fn cookie(&self, name: &str, value: &str, max_age: u64) -> String {
+        let secure = if self.config.insecure_cookies { "" } else { "; Secure" };
+        format!("{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax{}", name, value, max_age, secure)
+    }
+}
+
+fn client() -> Result<reqwest::blocking::Client, String> {
+    reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(HTTP_TIMEOUT_SECS))
+        .user_agent(concat!("luat/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| format!("failed to create HTTP client: {}", e))
+}
+
+/// Fetches JSON from `url`, with `token` as bearer token.
+fn get_json(url: &str, token: Option<&str>) -> Result<JsonValue, String> {
+    let mut request = client()?.get(url).header("Accept", "application/json");
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().map_err(|e| format!("request to {} failed: {}", url, e))?;
+    if !response.status().is_success() {
+        return Err(format!("request to {} failed with status {}", url, response.status()));
+    }
+    response.json().map_err(|e| format!("invalid response from {}: {}", url, e))
+}
+
+/// Returns the secret of a provider, from its settings or the environment.
+fn client_secret(name: &str, provider: &AuthProvider) -> Option<String> {
+    provider.client_secret.clone().or_else(|| {
+        let var = provider
+            .client_secret_env
+            .clone()
+            .unwrap_or_else(|| format!("{}_CLIENT_SECRET", name.to_uppercase().replace('-', "_")));
+        std::env::var(var).ok()
+    })
+}
+
+/// Maps provider user info to `id`, `email`, `name` and `avatar`, keeping
+/// the original as `raw`. The email is left out unless `email_verified` is
+/// true or `email_trusted` says the provider only reports verified ones.
+fn normalize_user(provider: &str, info: JsonValue, email_trusted: bool) -> JsonValue {
+    let email_verified = email_trusted
+        || matches!(info.get("email_verified"), Some(JsonValue::Bool(true)))
+        || matches!(info.get("email_verified"), Some(JsonValue::String(s)) if s == "true");
+    let text = |keys: &[&str]| {
+        keys.iter().find_map(|key| match info.get(key)? {
+            JsonValue::String(s) if !s.is_empty() => Some(JsonValue::String(s.clone())),
+            JsonValue::Number(n) => Some(JsonValue::String(n.to_string())),
+            _ => None,
+        })
+    };
+    let mut user = Map::new();
+    for (field, keys) in [
+        ("id", &["sub", "id"][..]),
+        ("email", &["email", "mail"]),
+        ("name", &["name", "login", "username", "preferred_username"]),
+        ("avatar", &["picture", "avatar_url"]),
+    ] {
+        if field == "email" && !email_verified {
+            continue;
+        }
+        if let Some(value) = text(keys) {
+            user.insert(field.to_string(), value);
+        }
+    }
+    user.insert("provider".to_string(), JsonValue::String(provider.to_string()));
+    user.insert("raw".to_string(), info);
+    JsonValue::Object(user)
+}
+
+/// Keeps only paths on this site, so a login can't redirect elsewhere.
+///
+/// Browsers drop tabs and newlines from URLs, so `/\t/evil.com` would
+/// become `//evil.com`; control characters and whitespace are refused.
+fn safe_redirect(target: Option<&str>) -> String {
+    match target {
+        Some(target)
+            if target.starts_with('/')
+                && !target.starts_with("//")
+                && !target.contains('\\')
+                && !target.chars().any(|c| c.is_control() || c.is_whitespace()) =>
+        {
+            target.to_string()
+        }
+        _ => "/".to_string(),
+    }
+}
+
+/// Returns 32 random bytes as base64url text.
+fn random_token() -> LuaResult<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| mlua::Error::runtime(format!("auth: {}", e)))?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+fn cookie(ctx: &Table, name: &str) -> LuaResult<Option<String>> {
+    match ctx.get::<Option<Table>>("cookies")? {
+        Some(cookies) => cookies.get(name),
+        None => Ok(None),
+    }
+}
+
+fn put_json<T: Serialize>(store: &dyn KVStore, key: &str, value: &T, ttl: u64) -> LuaResult<()> {
+    let bytes = serde_json::to_vec(value).map_err(mlua::Error::external)?;
+    let options = PutOptions {
+        expiration_ttl: Some(ttl),
+        ..Default::default()
+    };
+    store.put(key, &bytes, options).map_err(mlua::Error::external)
+}
+
+/// Reads and deletes a value, so it can be used once.
+fn take_json<T: serde::de::DeserializeOwned>(store: &dyn KVStore, key: &str) -> LuaResult<Option<T>> {
+    let Some(bytes) = store.get(key).map_err(mlua::Error::external)? else {
+        return Ok(None);
+    };
+    store.delete(key).map_err(mlua::Error::external)?;
+    Ok(serde_json::from_slice(&bytes).ok())
+}
+
+/// Returns a `302` response to `location` that sets `cookie`.
+fn redirect(lua: &Lua, location: &str, cookie: &str) -> LuaResult<Table> {
+    let headers = lua.create_table()?;
+    headers.set("Location", location)?;
+    headers.set("Set-Cookie", cookie)?;
+    let response = lua.create_table()?;
+    response.set("status", 302)?;
+    response.set("headers", headers)?;
+    Ok(response)
+}
+
+fn session_to_lua(lua: &Lua, id: &str, session: &Session) -> LuaResult<Value> {
+    let table = lua.create_table()?;
+    table.set("id", id)?;
+    table.set("provider", session.provider.as_str())?;
+    table.set("user", lua.to_value(&session.user)?)?;
+    table.set("tokens", lua.to_value(&session.tokens)?)?;
+    table.set("created_at", session.created_at)?;
+    Ok(Value::Table(table))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use luat::kv::MemoryKVStore;
+    use std::collections::BTreeMap;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Serves `responses` in order, one connection each, and returns the
+    /// base URL and a handle yielding the request lines and bodies received.
+    fn serve(responses: Vec<&'static str>) -> (String, std::thread::JoinHandle<Vec<(String, String)>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                requests.push((request_line.trim().to_string(), String::from_utf8(body).unwrap()));
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response.len(),
+                    response
+                )
+                .unwrap();
+            }
+            requests
+        });
+        (base, handle)
+    }
+
+    fn lua(base: &str) -> Lua {
+        let provider = AuthProvider {
+            client_id: "client".to_string(),
+            client_secret: Some("secret".to_string()),
+            authorize_url: Some(format!("{}/authorize", base)),
+            token_url: Some(format!("{}/token", base)),
+            userinfo_url: Some(format!("{}/userinfo", base)),
+            scopes: Some(vec!["openid".to_string(), "email".to_string()]),
+            ..Default::default()
+        };
+        let config = AuthConfig {
+            callback_url: Some("http://localhost:3000/auth/callback".to_string()),
+            providers: BTreeMap::from([("acme".to_string(), provider)]),
+            ..Default::default()
+        };
+        let store: Arc<dyn KVStore> = Arc::new(MemoryKVStore::new());
+        let factory: KVStoreFactory = Arc::new(move |_| store.clone());
+        let lua = Lua::new();
+        register_auth_module(&lua, config, factory).unwrap();
+        lua
+    }
+
+    #[test]
+    fn test_login_and_callback() {
+        let (base, handle) = serve(vec![
+            r#"{"access_token":"at-1","token_type":"Bearer"}"#,
+            r#"{"sub":"u-42","email":"ada@example.com","email_verified":true,"name":"Ada"}"#,
+        ]);
+        let lua = lua(&base);
+        let (location, state_cookie): (String, String) = lua
+            .load(
+                r#"
+                local response = auth.login("acme", { redirect_to = "/dashboard" })
+                return response.headers.Location, response.headers["Set-Cookie"]
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert!(location.starts_with(&format!("{}/authorize?response_type=code&client_id=client", base)));
+        assert!(location.contains("code_challenge_method=S256"));
+        assert!(location.contains("scope=openid+email"));
+        let state = state_cookie.strip_prefix("luat_auth_state=").unwrap().split(';').next().unwrap();
+        assert!(location.contains(&format!("state={}", state)));
+
+        lua.globals().set("state", state).unwrap();
+        let (status, location, cookie, cookies, email, name): (u16, String, String, String, String, String) = lua
+            .load(
+                r#"
+                local ctx = { query = { code = "c-1", state = state }, cookies = { luat_auth_state = state } }
+                local response, session = auth.callback(ctx)
+                local cookies = response.headers["Set-Cookie"]
+                local id = cookies:match("^luat_session=([^;]+)")
+                local user = auth.user({ cookies = { luat_session = id } })
+                return response.status, response.headers.Location, id, cookies, user.email, session.user.name
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!((status, location.as_str()), (302, "/dashboard"));
+        assert!(!cookie.is_empty());
+        assert!(cookies.contains("\nluat_auth_state=; Path=/; Max-Age=0"), "{}", cookies);
+        assert_eq!((email.as_str(), name.as_str()), ("ada@example.com", "Ada"));
+
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[0].0, "POST /token HTTP/1.1");
+        assert!(requests[0].1.contains("code=c-1"));
+        assert!(requests[0].1.contains("code_verifier="));
+        assert_eq!(requests[1].0, "GET /userinfo HTTP/1.1");
+
+        // The state was used up
+        let err: String = lua
+            .load(r#"local _, err = auth.callback({ query = { code = "c-1", state = state }, cookies = { luat_auth_state = state } }) return err"#)
+            .eval()
+            .unwrap();
+        assert_eq!(err, "unknown or expired login");
+    }
+
+    #[test]
+    fn test_callback_rejects_foreign_state() {
+        let lua = lua("http://127.0.0.1:9");
+        let err: String = lua
+            .load(
+                r#"
+                local response = auth.login("acme")
+                local _, err = auth.callback({ query = { code = "c", state = "forged" }, cookies = {} })
+                return err
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!(err, "login state does not match this browser");
+    }
+
+    #[test]
+    fn test_logout_clears_session() {
+        let lua = lua("http://127.0.0.1:9");
+        let (cookie, location): (String, String) = lua
+            .load(
+                r#"
+                local response = auth.logout({ cookies = { luat_session = "abc" } }, { redirect_to = "https://evil.example" })
+                return response.headers["Set-Cookie"], response.headers.Location
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert!(cookie.starts_with("luat_session=; Path=/; Max-Age=0"));
+        assert_eq!(location, "/");
+        let user: Value = lua.load(r#"return auth.user({ cookies = {} })"#).eval().unwrap();
+        assert!(user.is_nil());
+    }
+
+    #[test]
+    fn test_presets_and_redirects() {
+        assert_eq!(preset("github").unwrap().token, "https://github.com/login/oauth/access_token");
+        assert!(preset("unknown").is_none());
+        assert_eq!(safe_redirect(Some("/a?b=1")), "/a?b=1");
+        assert_eq!(safe_redirect(Some("//evil.example")), "/");
+        assert_eq!(safe_redirect(Some("/\t/evil.example")), "/");
+        assert_eq!(safe_redirect(Some("/\n/evil.example")), "/");
+        assert_eq!(safe_redirect(Some("/ /evil.example")), "/");
+        assert_eq!(safe_redirect(None), "/");
+
+        let user = normalize_user("github", serde_json::json!({ "id": 7, "login": "ada", "avatar_url": "a.png" }), true);
+        assert_eq!(user["id"], "7");
+        assert_eq!(user["name"], "ada");
+        assert_eq!(user["avatar"], "a.png");
+        assert!(user.get("email").is_none());
+
+        // Unverified addresses can't identify a user
+        let info = serde_json::json!({ "sub": "1", "email": "ada@example.com", "email_verified": false });
+        assert!(normalize_user("google", info, false).get("email").is_none());
+        let info = serde_json::json!({ "sub": "1", "email": "ada@example.com" });
+        assert!(normalize_user("oidc", info, false).get("email").is_none());
+        let info = serde_json::json!({ "sub": "1", "email": "ada@example.com", "email_verified": true });
+        assert_eq!(normalize_user("google", info, false)["email"], "ada@example.com");
+    }
+}
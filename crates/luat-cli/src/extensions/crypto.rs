@@ -0,0 +1,137 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Password hashing for Lua.
+//!
+//! Provides `crypto.password_hash` and `crypto.password_verify`, so sign-up
+//! and sign-in flows written in server Lua store passwords with Argon2id
+//! instead of rolling their own hashing.
+//!
+//! # Example
+//!
+//! ```lua
+//! local crypto = require("crypto")
+//!
+//! -- Sign-up: store the hash, never the password
+//! users:put(email, crypto.password_hash(ctx.form.password))
+//!
+//! -- Sign-in
+//! local hash = users:get(email)
+//! if hash and crypto.password_verify(ctx.form.password, hash) then
+//!     -- signed in
+//! end
+//! ```
+//!
+//! Hashes are PHC strings (`$argon2id$v=19$m=19456,t=2,p=1$...`) with a
+//! random salt and the parameters they were made with, so hashes stay
+//! verifiable when the default parameters change. `password_verify` raises
+//! an error for strings that are not Argon2 hashes.
+
+use argon2::password_hash::{Error as HashError, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use mlua::{Error as LuaError, Lua, Result as LuaResult, String as LuaString, Table};
+
+/// Length of the random salt of a new hash, in bytes.
+const SALT_LEN: usize = 16;
+
+/// Register the crypto module on the given Lua instance.
+///
+/// This makes `crypto.password_hash()` and `crypto.password_verify()`
+/// available in Lua code, also as `require("crypto")`.
+pub fn register_crypto_module(lua: &Lua) -> LuaResult<()> {
+    let crypto = lua.create_table()?;
+
+    crypto.set(
+        "password_hash",
+        lua.create_function(|_, password: LuaString| password_hash(&password.as_bytes()))?,
+    )?;
+
+    crypto.set(
+        "password_verify",
+        lua.create_function(|_, (password, hash): (LuaString, String)| {
+            password_verify(&password.as_bytes(), &hash)
+        })?,
+    )?;
+
+    let globals = lua.globals();
+    globals.set("crypto", crypto)?;
+
+    let package: Table = globals.get("package")?;
+    let preload: Table = package.get("preload")?;
+    preload.set(
+        "crypto",
+        lua.create_function(|lua, _: ()| lua.globals().get::<Table>("crypto"))?,
+    )?;
+
+    Ok(())
+}
+
+/// Hashes `password` with Argon2id and a random salt.
+fn password_hash(password: &[u8]) -> LuaResult<String> {
+    let mut salt = [0u8; SALT_LEN];
+    getrandom::getrandom(&mut salt)
+        .map_err(|e| LuaError::RuntimeError(format!("crypto.password_hash: no randomness: {}", e)))?;
+    let salt = SaltString::encode_b64(&salt).map_err(hash_error("password_hash"))?;
+    let hash = Argon2::default()
+        .hash_password(password, &salt)
+        .map_err(hash_error("password_hash"))?;
+    Ok(hash.to_string())
+}
+
+/// Checks `password` against a hash made by [`password_hash`], using the
+/// parameters stored in the hash.
+fn password_verify(password: &[u8], hash: &str) -> LuaResult<bool> {
+    let hash = PasswordHash::new(hash).map_err(hash_error("password_verify"))?;
+    match Argon2::default().verify_password(password, &hash) {
+        Ok(()) => Ok(true),
+        Err(HashError::Password) => Ok(false),
+        Err(e) => Err(hash_error("password_verify")(e)),
+    }
+}
+
+fn hash_error(function: &'static str) -> impl Fn(HashError) -> LuaError {
+    move |e| LuaError::RuntimeError(format!("crypto.{}: {}", function, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_and_verify() {
+        let lua = Lua::new();
+        register_crypto_module(&lua).unwrap();
+
+        let (first, second, ok, wrong): (String, String, bool, bool) = lua
+            .load(
+                r#"
+                local crypto = require("crypto")
+                local first = crypto.password_hash("correct horse")
+                local second = crypto.password_hash("correct horse")
+                return first, second,
+                    crypto.password_verify("correct horse", first),
+                    crypto.password_verify("battery staple", first)
+                "#,
+            )
+            .eval()
+            .unwrap();
+
+        assert!(first.starts_with("$argon2id$"), "{}", first);
+        assert_ne!(first, second, "salts differ");
+        assert!(ok);
+        assert!(!wrong);
+    }
+
+    #[test]
+    fn test_verify_rejects_malformed_hash() {
+        let lua = Lua::new();
+        register_crypto_module(&lua).unwrap();
+
+        let err = lua
+            .load(r#"return crypto.password_verify("pw", "not-a-hash")"#)
+            .eval::<bool>()
+            .unwrap_err();
+        assert!(err.to_string().contains("crypto.password_verify"), "{}", err);
+    }
+}
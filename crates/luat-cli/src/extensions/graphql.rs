@@ -0,0 +1,494 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! GraphQL client module for Lua.
+//!
+//! Provides `graphql.query` for single operations and `graphql.batch` for
+//! sending several in one request, for load functions whose data comes from
+//! GraphQL APIs.
+//!
+//! # Example
+//!
+//! ```lua
+//! local graphql = require("graphql")
+//!
+//! local data, errors = graphql.query("https://api.example.com/graphql", [[
+//!     query Post($slug: String!) { post(slug: $slug) { title body } }
+//! ]], { slug = ctx.params.slug }, { persisted = true, ttl = 60 })
+//!
+//! -- One request, one result per operation, in order
+//! local results = graphql.batch("https://api.example.com/graphql", {
+//!     { query = "{ posts { title } }" },
+//!     { query = "query($id: ID!) { author(id: $id) { name } }", variables = { id = "1" } },
+//! })
+//! local posts = results[1].data.posts
+//! ```
+//!
+//! Both take `headers`, `timeout` (seconds, default 30), `persisted` and
+//! `ttl` options. A query returns its `data` and `errors` (`nil` when there
+//! are none); a batch returns `{ data, errors }` tables. The server has to
+//! support array batching for `graphql.batch`.
+//!
+//! With `persisted = true`, operations are sent as automatic persisted
+//! queries: only the SHA-256 hash of the query goes over the wire, and the
+//! full query is sent once if the server doesn't know the hash yet.
+//!
+//! With `ttl`, results without errors are cached through the hooks set as
+//! `graphql.cache`, e.g. on a KV namespace. The request headers are part of
+//! the cache key, so results fetched with one user's `Authorization` are
+//! never served to another:
+//!
+//! ```lua
+//! local store = KV.namespace("graphql")
+//! graphql.cache = {
+//!     get = function(key) return store:get(key) end,
+//!     set = function(key, value, ttl) store:put(key, value, { expirationTtl = ttl }) end,
+//! }
+//! ```
+
+use mlua::{Function, Lua, LuaSerdeExt, Result as LuaResult, Table, Value};
+use serde_json::{json, Value as JsonValue};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// Request timeout when the call doesn't set one.
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// Prefix of the keys results are cached under.
+const CACHE_PREFIX: &str = "graphql:";
+
+/// Register the graphql module on the given Lua instance.
+///
+/// This makes `graphql.query()` and `graphql.batch()` available in Lua code,
+/// also as `require("graphql")`.
+pub fn register_graphql_module(lua: &Lua) -> LuaResult<()> {
+    let graphql = lua.create_table()?;
+
+    graphql.set(
+        "query",
+        lua.create_function(
+            |lua, (endpoint, query, variables, options): (String, String, Option<Value>, Option<Table>)| {
+                let options = Options::from_lua(options.as_ref())?;
+                let operation = Operation {
+                    query,
+                    variables: variables_to_json(lua, variables)?,
+                    operation_name: options.operation_name.clone(),
+                };
+                let result = execute(lua, &endpoint, &[operation], &options, false)?.remove(0);
+                Ok((json_to_lua(lua, &result["data"])?, errors_to_lua(lua, &result)?))
+            },
+        )?,
+    )?;
+
+    graphql.set(
+        "batch",
+        lua.create_function(|lua, (endpoint, operations, options): (String, Table, Option<Table>)| {
+            let options = Options::from_lua(options.as_ref())?;
+            let operations = operations
+                .sequence_values::<Table>()
+                .map(|operation| {
+                    let operation = operation?;
+                    Ok(Operation {
+                        query: operation.get("query")?,
+                        variables: variables_to_json(lua, operation.get("variables")?)?,
+                        operation_name: operation.get("operation_name")?,
+                    })
+                })
+                .collect::<LuaResult<Vec<_>>>()?;
+
+            let results = lua.create_table()?;
+            if operations.is_empty() {
+                return Ok(results);
+            }
+            for result in execute(lua, &endpoint, &operations, &options, true)? {
+                let entry = lua.create_table()?;
+                entry.set("data", json_to_lua(lua, &result["data"])?)?;
+                entry.set("errors", errors_to_lua(lua, &result)?)?;
+                results.raw_push(entry)?;
+            }
+            Ok(results)
+        })?,
+    )?;
+
+    let globals = lua.globals();
+    globals.set("graphql", graphql)?;
+
+    let package: Table = globals.get("package")?;
+    let preload: Table = package.get("preload")?;
+    preload.set(
+        "graphql",
+        lua.create_function(|lua, _: ()| lua.globals().get::<Table>("graphql"))?,
+    )?;
+
+    Ok(())
+}
+
+/// A GraphQL operation to send.
+struct Operation {
+    query: String,
+    variables: JsonValue,
+    operation_name: Option<String>,
+}
+
+/// Options of `graphql.query` and `graphql.batch`.
+#[derive(Default)]
+struct Options {
+    headers: HashMap<String, String>,
+    timeout: Option<u64>,
+    persisted: bool,
+    ttl: Option<u64>,
+    operation_name: Option<String>,
+}
+
+impl Options {
+    fn from_lua(table: Option<&Table>) -> LuaResult<Self> {
+        let Some(table) = table else {
+            return Ok(Self::default());
+        };
+        let headers = match table.get::<Option<Table>>("headers")? {
+            Some(headers) => headers.pairs::<String, String>().collect::<LuaResult<_>>()?,
+            None => HashMap::new(),
+        };
+        Ok(Self {
+            headers,
+            timeout: table.get("timeout")?,
+            persisted: table.get::<Option<bool>>("persisted")?.unwrap_or(false),
+            ttl: table.get("ttl")?,
+            operation_name: table.get("operation_name")?,
+        })
+    }
+}
+
+/// Returns the results of `operations`, from the cache where possible and
+/// otherwise from `endpoint`, sent as an array when `batched`.
+fn execute(
+    lua: &Lua,
+    endpoint: &str,
+    operations: &[Operation],
+    options: &Options,
+    batched: bool,
+) -> LuaResult<Vec<JsonValue>> {
+    let cache = match options.ttl {
+        Some(_) => cache_hooks(lua)?,
+        None => None,
+    };
+
+    let mut results: Vec<Option<JsonValue>> = Vec::with_capacity(operations.len());
+    for operation in operations {
+        let cached = match &cache {
+            Some((get, _)) => get
+                .call::<Option<String>>(cache_key(endpoint, operation, &options.headers))?
+                .and_then(|value| serde_json::from_str(&value).ok()),
+            None => None,
+        };
+        results.push(cached);
+    }
+
+    let pending: Vec<usize> = (0..operations.len()).filter(|&i| results[i].is_none()).collect();
+    if !pending.is_empty() {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(std::time::Duration::from_secs(options.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS)))
+            .build()
+            .map_err(|e| mlua::Error::external(format!("Failed to create HTTP client: {}", e)))?;
+        let send = |indexes: &[usize], include_query: bool| {
+            let bodies = indexes
+                .iter()
+                .map(|&i| request_body(&operations[i], options.persisted, include_query))
+                .collect();
+            post(&client, endpoint, bodies, batched, &options.headers)
+        };
+
+        let mut responses = send(&pending, !options.persisted)?;
+        if options.persisted {
+            // Operations the server has no hash for are sent again in full
+            let unknown: Vec<usize> = (0..pending.len()).filter(|&n| is_persisted_query_not_found(&responses[n])).collect();
+            if !unknown.is_empty() {
+                let indexes: Vec<usize> = unknown.iter().map(|&n| pending[n]).collect();
+                for (n, response) in unknown.into_iter().zip(send(&indexes, true)?) {
+                    responses[n] = response;
+                }
+            }
+        }
+
+        for (i, response) in pending.into_iter().zip(responses) {
+            if let (Some((_, set)), Some(ttl)) = (&cache, options.ttl) {
+                if has_errors(&response).is_none() && response.get("data").is_some_and(|data| !data.is_null()) {
+                    let key = cache_key(endpoint, &operations[i], &options.headers);
+                    set.call::<()>((key, response.to_string(), ttl))?;
+                }
+            }
+            results[i] = Some(response);
+        }
+    }
+
+    Ok(results.into_iter().map(Option::unwrap_or_default).collect())
+}
+
+/// Returns the `get` and `set` functions of `graphql.cache`, if set.
+fn cache_hooks(lua: &Lua) -> LuaResult<Option<(Function, Function)>> {
+    let Some(cache) = lua.globals().get::<Table>("graphql")?.get::<Option<Table>>("cache")? else {
+        return Ok(None);
+    };
+    Ok(Some((cache.get("get")?, cache.get("set")?)))
+}
+
+/// Key an operation's result is cached under, specific to the request
+/// headers.
+fn cache_key(endpoint: &str, operation: &Operation, headers: &HashMap<String, String>) -> String {
+    let mut headers: Vec<(String, &str)> = headers
+        .iter()
+        .map(|(name, value)| (name.to_ascii_lowercase(), value.as_str()))
+        .collect();
+    headers.sort();
+
+    let mut hasher = Sha256::new();
+    hasher.update(endpoint.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(operation.query.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(operation.operation_name.as_deref().unwrap_or_default().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(operation.variables.to_string().as_bytes());
+    for (name, value) in headers {
+        hasher.update(b"\n");
+        hasher.update(name.as_bytes());
+        hasher.update(b":");
+        hasher.update(value.as_bytes());
+    }
+    format!("{}{:x}", CACHE_PREFIX, hasher.finalize())
+}
+
+/// Builds the request body of an operation; persisted operations carry the
+/// query's hash and, when `include_query`, the query itself.
+fn request_body(operation: &Operation, persisted: bool, include_query: bool) -> JsonValue {
+    let mut body = json!({ "variables": operation.variables });
+    if include_query {
+        body["query"] = json!(operation.query);
+    }
+    if let Some(name) = &operation.operation_name {
+        body["operationName"] = json!(name);
+    }
+    if persisted {
+        let hash = format!("{:x}", Sha256::digest(operation.query.as_bytes()));
+        body["extensions"] = json!({ "persistedQuery": { "version": 1, "sha256Hash": hash } });
+    }
+    body
+}
+
+/// Posts `bodies` to `endpoint` and returns one response per body.
+fn post(
+    client: &reqwest::blocking::Client,
+    endpoint: &str,
+    bodies: Vec<JsonValue>,
+    batched: bool,
+    headers: &HashMap<String, String>,
+) -> LuaResult<Vec<JsonValue>> {
+    let count = bodies.len();
+    let payload = if batched {
+        JsonValue::Array(bodies)
+    } else {
+        bodies.into_iter().next().unwrap_or_default()
+    };
+
+    let mut request = client
+        .post(endpoint)
+        .header("Content-Type", "application/json")
+        .header("Accept", "application/json");
+    for (key, value) in headers {
+        request = request.header(key, value);
+    }
+    let response = request
+        .body(payload.to_string())
+        .send()
+        .map_err(|e| mlua::Error::external(format!("GraphQL request failed: {}", e)))?;
+    let status = response.status();
+    let text = response
+        .text()
+        .map_err(|e| mlua::Error::external(format!("Failed to read GraphQL response: {}", e)))?;
+
+    let failed = || mlua::Error::external(format!("GraphQL request to {} failed with status {}", endpoint, status));
+    match serde_json::from_str::<JsonValue>(&text).map_err(|_| failed())? {
+        JsonValue::Array(results) if batched && results.len() == count => Ok(results),
+        // An error for the whole request applies to each operation
+        result if is_result(&result) => Ok(vec![result; count]),
+        _ => Err(failed()),
+    }
+}
+
+/// Whether `value` is a GraphQL result, with `data` or `errors`.
+fn is_result(value: &JsonValue) -> bool {
+    value.get("data").is_some() || value.get("errors").is_some()
+}
+
+/// The non-empty `errors` of a result.
+fn has_errors(result: &JsonValue) -> Option<&JsonValue> {
+    result.get("errors").filter(|errors| errors.as_array().map_or(!errors.is_null(), |e| !e.is_empty()))
+}
+
+/// Whether the server asks for the full query of a persisted operation.
+fn is_persisted_query_not_found(result: &JsonValue) -> bool {
+    let Some(errors) = has_errors(result).and_then(JsonValue::as_array) else {
+        return false;
+    };
+    errors.iter().any(|error| {
+        error["message"] == "PersistedQueryNotFound" || error["extensions"]["code"] == "PERSISTED_QUERY_NOT_FOUND"
+    })
+}
+
+fn variables_to_json(lua: &Lua, variables: Option<Value>) -> LuaResult<JsonValue> {
+    match variables {
+        None | Some(Value::Nil) => Ok(json!({})),
+        // An empty table would otherwise become an array
+        Some(Value::Table(table)) if table.is_empty() => Ok(json!({})),
+        Some(value) => lua.from_value(value),
+    }
+}
+
+/// Converts JSON to Lua, with `null` as `nil`.
+fn json_to_lua(lua: &Lua, value: &JsonValue) -> LuaResult<Value> {
+    match value {
+        JsonValue::Null => Ok(Value::Nil),
+        value => lua.to_value(value),
+    }
+}
+
+fn errors_to_lua(lua: &Lua, result: &JsonValue) -> LuaResult<Value> {
+    match has_errors(result) {
+        Some(errors) => lua.to_value(errors),
+        None => Ok(Value::Nil),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Serves `responses` in order, one connection each, and returns the
+    /// endpoint and a handle yielding the request bodies received.
+    fn serve(responses: Vec<&'static str>) -> (String, std::thread::JoinHandle<Vec<JsonValue>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/graphql", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut bodies = Vec::new();
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                bodies.push(serde_json::from_slice(&body).unwrap());
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response.len(),
+                    response
+                )
+                .unwrap();
+            }
+            bodies
+        });
+        (endpoint, handle)
+    }
+
+    #[test]
+    fn test_request_body_persisted() {
+        let operation = Operation {
+            query: "{ posts { title } }".to_string(),
+            variables: json!({}),
+            operation_name: None,
+        };
+        let body = request_body(&operation, true, false);
+        assert!(body.get("query").is_none());
+        assert_eq!(
+            body["extensions"]["persistedQuery"]["sha256Hash"],
+            "80be7652e8410bc1c8424f070a6a5e62f53f81e910dfc4a5ab00898b23ba0b27"
+        );
+        assert_eq!(request_body(&operation, false, true), json!({ "query": "{ posts { title } }", "variables": {} }));
+
+        let not_found = json!({ "errors": [{ "message": "PersistedQueryNotFound" }] });
+        assert!(is_persisted_query_not_found(&not_found));
+        assert!(!is_persisted_query_not_found(&json!({ "data": {}, "errors": [] })));
+    }
+
+    #[test]
+    fn test_cache_key_depends_on_headers() {
+        let operation = Operation {
+            query: "{ me { name } }".to_string(),
+            variables: json!({}),
+            operation_name: None,
+        };
+        let headers = |token: &str| HashMap::from([("Authorization".to_string(), format!("Bearer {}", token))]);
+        let anonymous = cache_key("https://api.example.com", &operation, &HashMap::new());
+        let alice = cache_key("https://api.example.com", &operation, &headers("alice"));
+        assert_ne!(anonymous, alice);
+        assert_ne!(alice, cache_key("https://api.example.com", &operation, &headers("bob")));
+        assert_eq!(alice, cache_key("https://api.example.com", &operation, &headers("alice")));
+    }
+
+    #[test]
+    fn test_query_retries_unknown_persisted_query() {
+        let (endpoint, server) = serve(vec![
+            r#"{"errors":[{"message":"PersistedQueryNotFound"}]}"#,
+            r#"{"data":{"post":{"title":"Hello"}}}"#,
+        ]);
+        let lua = Lua::new();
+        register_graphql_module(&lua).unwrap();
+        lua.globals().set("endpoint", endpoint).unwrap();
+        let (title, errors): (String, Value) = lua
+            .load(
+                r#"
+                local data, errors = graphql.query(endpoint, "query($slug: String!) { post(slug: $slug) { title } }",
+                    { slug = "hello" }, { persisted = true })
+                return data.post.title, errors
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!(title, "Hello");
+        assert!(errors.is_nil());
+
+        let bodies = server.join().unwrap();
+        assert!(bodies[0].get("query").is_none());
+        assert_eq!(bodies[1]["query"], "query($slug: String!) { post(slug: $slug) { title } }");
+        assert_eq!(bodies[1]["variables"], json!({ "slug": "hello" }));
+    }
+
+    #[test]
+    fn test_batch_uses_cache_hooks() {
+        let (endpoint, server) = serve(vec![r#"[{"data":{"a":1}},{"data":null,"errors":[{"message":"boom"}]}]"#]);
+        let lua = Lua::new();
+        register_graphql_module(&lua).unwrap();
+        lua.globals().set("endpoint", endpoint).unwrap();
+        let (a, error, cached, sets): (i64, String, i64, i64) = lua
+            .load(
+                r#"
+                local store, sets = {}, 0
+                graphql.cache = {
+                    get = function(key) return store[key] end,
+                    set = function(key, value, ttl) store[key] = value; sets = sets + 1 end,
+                }
+                local operations = { { query = "{ a }" }, { query = "{ b }" } }
+                local results = graphql.batch(endpoint, operations, { ttl = 60 })
+                local again = graphql.batch(endpoint, { operations[1] }, { ttl = 60 })
+                return results[1].data.a, results[2].errors[1].message, again[1].data.a, sets
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!((a, error.as_str(), cached, sets), (1, "boom", 1, 1));
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,469 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! gRPC client module for Lua (`grpc` feature).
+//!
+//! Lets server code call unary methods of upstream gRPC services, with
+//! messages as Lua tables in the protobuf JSON mapping. A service is
+//! described by a descriptor set compiled ahead of time
+//! (`protoc --include_imports --descriptor_set_out=users.pb users.proto` or
+//! `buf build -o users.pb`), or fetched from the server through gRPC
+//! reflection when no descriptor set is given.
+//!
+//! # Example
+//!
+//! ```lua
+//! local grpc = require("grpc")
+//! local users = grpc.connect("http://users:50051", { descriptors = "protos/users.pb" })
+//!
+//! local user, err = users:call("users.v1.Users/GetUser", { id = "42" }, {
+//!     metadata = { authorization = "Bearer " .. token },
+//!     timeout = 2,
+//! })
+//! if not user then
+//!     return { status = 502, body = { error = err.message } }
+//! end
+//! ```
+//!
+//! `grpc.connect(url, options)` takes `descriptors` (path of a descriptor
+//! set), `reflection` (also ask the server for services missing from the
+//! descriptor set), `metadata` (sent with every call) and `timeout` (seconds,
+//! default 30); `client:call` takes `metadata` and `timeout` per call.
+//!
+//! Fields use their JSON names (`userId` for `user_id`; requests accept
+//! both), 64-bit integers are returned as strings and enums by name. A call
+//! that fails returns `nil` and a `{ code, status, message }` table, e.g.
+//! `{ code = "NOT_FOUND", status = 5, message = "no such user" }`; unknown
+//! methods and messages that don't match their type raise errors.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::Duration;
+
+use mlua::{Lua, LuaSerdeExt, Result as LuaResult, Table, UserData, UserDataMethods, Value};
+use prost_reflect::prost::Message;
+use prost_reflect::prost_types::FileDescriptorProto;
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, MethodDescriptor};
+use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::metadata::{MetadataKey, MetadataValue};
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+use tonic::{Code, Status};
+use tonic_reflection::pb::v1alpha::server_reflection_client::ServerReflectionClient;
+use tonic_reflection::pb::v1alpha::server_reflection_request::MessageRequest;
+use tonic_reflection::pb::v1alpha::server_reflection_response::MessageResponse;
+use tonic_reflection::pb::v1alpha::ServerReflectionRequest;
+
+/// Call timeout when neither the client nor the call sets one.
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// Register the grpc module on the given Lua instance.
+///
+/// This makes `grpc.connect()` available in Lua code, also as
+/// `require("grpc")`.
+pub fn register_grpc_module(lua: &Lua) -> LuaResult<()> {
+    let grpc = lua.create_table()?;
+    grpc.set(
+        "connect",
+        lua.create_function(|_, (url, options): (String, Option<Table>)| connect(&url, options))?,
+    )?;
+
+    let globals = lua.globals();
+    globals.set("grpc", grpc)?;
+
+    let package: Table = globals.get("package")?;
+    let preload: Table = package.get("preload")?;
+    preload.set(
+        "grpc",
+        lua.create_function(|lua, _: ()| lua.globals().get::<Table>("grpc"))?,
+    )?;
+
+    Ok(())
+}
+
+/// A connection to a gRPC server, returned by `grpc.connect`.
+struct GrpcClient {
+    channel: Channel,
+    /// Services known so far; reflection adds to it
+    pool: Mutex<DescriptorPool>,
+    reflection: bool,
+    metadata: HashMap<String, String>,
+    timeout: Duration,
+}
+
+impl UserData for GrpcClient {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method(
+            "call",
+            |lua, client, (name, message, options): (String, Option<Value>, Option<Table>)| {
+                let method = client.method(&name)?;
+                let request = to_message(lua, method.input(), message)?;
+
+                let mut metadata = client.metadata.clone();
+                let mut timeout = client.timeout;
+                if let Some(options) = options {
+                    metadata.extend(read_metadata(options.get("metadata")?)?);
+                    if let Some(secs) = options.get::<Option<u64>>("timeout")? {
+                        timeout = Duration::from_secs(secs);
+                    }
+                }
+
+                match block_on(unary(client.channel.clone(), &method, request, metadata, timeout)) {
+                    Ok(reply) => {
+                        let json = serde_json::to_value(&reply)
+                            .map_err(|e| mlua::Error::external(format!("Failed to convert gRPC reply: {}", e)))?;
+                        Ok((lua.to_value(&json)?, Value::Nil))
+                    }
+                    Err(status) => Ok((Value::Nil, Value::Table(status_table(lua, &status)?))),
+                }
+            },
+        );
+    }
+}
+
+impl GrpcClient {
+    /// Looks up `package.Service/Method`, asking the server through
+    /// reflection if the service isn't known yet.
+    fn method(&self, name: &str) -> LuaResult<MethodDescriptor> {
+        let Some((service, method)) = name.trim_start_matches('/').split_once('/') else {
+            return Err(mlua::Error::external(format!(
+                "gRPC method '{}' should be given as 'package.Service/Method'",
+                name
+            )));
+        };
+
+        let mut pool = self.pool.lock().unwrap_or_else(PoisonError::into_inner);
+        if pool.get_service_by_name(service).is_none() && self.reflection {
+            let files = block_on(reflect(self.channel.clone(), service.to_string())).map_err(|status| {
+                mlua::Error::external(format!("gRPC reflection for {} failed: {}", service, status.message()))
+            })?;
+            let files: Vec<FileDescriptorProto> =
+                files.into_iter().filter(|file| pool.get_file_by_name(file.name()).is_none()).collect();
+            pool.add_file_descriptor_protos(files)
+                .map_err(|e| mlua::Error::external(format!("Invalid descriptors for {}: {}", service, e)))?;
+        }
+
+        let descriptor = pool
+            .get_service_by_name(service)
+            .ok_or_else(|| mlua::Error::external(format!("Unknown gRPC service {}", service)))?
+            .methods()
+            .find(|m| m.name() == method)
+            .ok_or_else(|| mlua::Error::external(format!("gRPC service {} has no method {}", service, method)))?;
+        if descriptor.is_client_streaming() || descriptor.is_server_streaming() {
+            return Err(mlua::Error::external(format!("{} is a streaming method; only unary calls are supported", name)));
+        }
+        Ok(descriptor)
+    }
+}
+
+/// Creates a client for `url`; the connection is made on the first call.
+fn connect(url: &str, options: Option<Table>) -> LuaResult<GrpcClient> {
+    let mut pool = DescriptorPool::new();
+    let mut reflection = true;
+    let mut metadata = HashMap::new();
+    let mut timeout = Duration::from_secs(DEFAULT_TIMEOUT_SECS);
+
+    if let Some(options) = options {
+        if let Some(path) = options.get::<Option<String>>("descriptors")? {
+            let bytes = std::fs::read(&path)
+                .map_err(|e| mlua::Error::external(format!("Failed to read descriptors {}: {}", path, e)))?;
+            pool = DescriptorPool::decode(bytes.as_slice())
+                .map_err(|e| mlua::Error::external(format!("Invalid descriptor set {}: {}", path, e)))?;
+            reflection = false;
+        }
+        if let Some(enabled) = options.get::<Option<bool>>("reflection")? {
+            reflection = enabled;
+        }
+        metadata = read_metadata(options.get("metadata")?)?;
+        if let Some(secs) = options.get::<Option<u64>>("timeout")? {
+            timeout = Duration::from_secs(secs);
+        }
+    }
+
+    let mut endpoint = Endpoint::from_shared(url.to_string())
+        .map_err(|e| mlua::Error::external(format!("Invalid gRPC URL {}: {}", url, e)))?;
+    if url.starts_with("https://") {
+        endpoint = endpoint
+            .tls_config(ClientTlsConfig::new().with_native_roots())
+            .map_err(|e| mlua::Error::external(format!("Failed to set up TLS for {}: {}", url, e)))?;
+    }
+    let channel = {
+        let _guard = runtime().enter();
+        endpoint.connect_lazy()
+    };
+
+    Ok(GrpcClient {
+        channel,
+        pool: Mutex::new(pool),
+        reflection,
+        metadata,
+        timeout,
+    })
+}
+
+fn read_metadata(table: Option<Table>) -> LuaResult<HashMap<String, String>> {
+    match table {
+        Some(table) => table.pairs::<String, String>().collect(),
+        None => Ok(HashMap::new()),
+    }
+}
+
+/// Converts a Lua value to a message of type `descriptor`.
+fn to_message(lua: &Lua, descriptor: MessageDescriptor, value: Option<Value>) -> LuaResult<DynamicMessage> {
+    let json = match value {
+        None | Some(Value::Nil) => serde_json::json!({}),
+        // An empty table would otherwise become an array
+        Some(Value::Table(table)) if table.is_empty() => serde_json::json!({}),
+        Some(value) => lua.from_value::<serde_json::Value>(value)?,
+    };
+    let name = descriptor.full_name().to_string();
+    DynamicMessage::deserialize(descriptor, json)
+        .map_err(|e| mlua::Error::external(format!("Invalid {} message: {}", name, e)))
+}
+
+/// Returns the `{ code, status, message }` table of a failed call.
+fn status_table(lua: &Lua, status: &Status) -> LuaResult<Table> {
+    let table = lua.create_table()?;
+    table.set("code", code_name(status.code()))?;
+    table.set("status", status.code() as i32)?;
+    table.set("message", status.message())?;
+    Ok(table)
+}
+
+/// The canonical name of a status code, e.g. `NOT_FOUND`.
+fn code_name(code: Code) -> &'static str {
+    match code {
+        Code::Ok => "OK",
+        Code::Cancelled => "CANCELLED",
+        Code::Unknown => "UNKNOWN",
+        Code::InvalidArgument => "INVALID_ARGUMENT",
+        Code::DeadlineExceeded => "DEADLINE_EXCEEDED",
+        Code::NotFound => "NOT_FOUND",
+        Code::AlreadyExists => "ALREADY_EXISTS",
+        Code::PermissionDenied => "PERMISSION_DENIED",
+        Code::ResourceExhausted => "RESOURCE_EXHAUSTED",
+        Code::FailedPrecondition => "FAILED_PRECONDITION",
+        Code::Aborted => "ABORTED",
+        Code::OutOfRange => "OUT_OF_RANGE",
+        Code::Unimplemented => "UNIMPLEMENTED",
+        Code::Internal => "INTERNAL",
+        Code::Unavailable => "UNAVAILABLE",
+        Code::DataLoss => "DATA_LOSS",
+        Code::Unauthenticated => "UNAUTHENTICATED",
+    }
+}
+
+/// The runtime the channels and calls run on, shared by all clients since a
+/// channel belongs to the runtime it was created on.
+fn runtime() -> &'static tokio::runtime::Runtime {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("luat-grpc")
+            .enable_all()
+            .build()
+            .expect("Failed to start the gRPC runtime")
+    })
+}
+
+/// Runs `future` to completion on the gRPC runtime. Lua runs inside the
+/// server's runtime, where blocking on another one isn't allowed, so the
+/// wait happens on a separate thread there.
+fn block_on<F>(future: F) -> F::Output
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    if tokio::runtime::Handle::try_current().is_err() {
+        return runtime().block_on(future);
+    }
+    std::thread::scope(|scope| {
+        scope
+            .spawn(|| runtime().block_on(future))
+            .join()
+            .expect("gRPC call panicked")
+    })
+}
+
+/// Makes a unary call of `method`.
+async fn unary(
+    channel: Channel,
+    method: &MethodDescriptor,
+    message: DynamicMessage,
+    metadata: HashMap<String, String>,
+    timeout: Duration,
+) -> Result<DynamicMessage, Status> {
+    let path = PathAndQuery::try_from(format!("/{}/{}", method.parent_service().full_name(), method.name()))
+        .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+    let mut request = tonic::Request::new(message);
+    for (key, value) in metadata {
+        let name = MetadataKey::from_bytes(key.to_ascii_lowercase().as_bytes())
+            .map_err(|_| Status::invalid_argument(format!("Invalid metadata key {}", key)))?;
+        let value = MetadataValue::try_from(value.as_str())
+            .map_err(|_| Status::invalid_argument(format!("Invalid metadata value for {}", key)))?;
+        request.metadata_mut().insert(name, value);
+    }
+    request.set_timeout(timeout);
+
+    let mut grpc = tonic::client::Grpc::new(channel);
+    grpc.ready().await.map_err(|e| Status::unavailable(e.to_string()))?;
+    let response = grpc.unary(request, path, DynamicCodec(method.output())).await?;
+    Ok(response.into_inner())
+}
+
+/// Fetches the file declaring `symbol`, with its dependencies, through the
+/// server reflection service.
+async fn reflect(channel: Channel, symbol: String) -> Result<Vec<FileDescriptorProto>, Status> {
+    let request = ServerReflectionRequest {
+        host: String::new(),
+        message_request: Some(MessageRequest::FileContainingSymbol(symbol)),
+    };
+    let mut responses = ServerReflectionClient::new(channel)
+        .server_reflection_info(futures_util::stream::iter([request]))
+        .await?
+        .into_inner();
+
+    match responses.message().await?.and_then(|response| response.message_response) {
+        Some(MessageResponse::FileDescriptorResponse(response)) => response
+            .file_descriptor_proto
+            .iter()
+            .map(|bytes| FileDescriptorProto::decode(bytes.as_slice()).map_err(|e| Status::internal(e.to_string())))
+            .collect(),
+        Some(MessageResponse::ErrorResponse(error)) => {
+            Err(Status::new(Code::from(error.error_code), error.error_message))
+        }
+        _ => Err(Status::internal("Unexpected reflection response")),
+    }
+}
+
+/// Encodes and decodes messages whose types are only known at runtime.
+struct DynamicCodec(MessageDescriptor);
+
+impl Codec for DynamicCodec {
+    type Encode = DynamicMessage;
+    type Decode = DynamicMessage;
+    type Encoder = DynamicEncoder;
+    type Decoder = DynamicDecoder;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        DynamicEncoder
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        DynamicDecoder(self.0.clone())
+    }
+}
+
+struct DynamicEncoder;
+
+impl Encoder for DynamicEncoder {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        item.encode(dst).map_err(|e| Status::internal(e.to_string()))
+    }
+}
+
+struct DynamicDecoder(MessageDescriptor);
+
+impl Decoder for DynamicDecoder {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        let mut message = DynamicMessage::new(self.0.clone());
+        message.merge(src).map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Some(message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost_reflect::prost_types::field_descriptor_proto::{Label, Type};
+    use prost_reflect::prost_types::{
+        DescriptorProto, FieldDescriptorProto, FileDescriptorSet, MethodDescriptorProto, ServiceDescriptorProto,
+    };
+
+    /// `greet.Greeter/SayHello(Hello) returns (Hello)`, `Hello { string user_name = 1; }`.
+    fn greeter_descriptors() -> FileDescriptorSet {
+        let field = FieldDescriptorProto {
+            name: Some("user_name".to_string()),
+            json_name: Some("userName".to_string()),
+            number: Some(1),
+            label: Some(Label::Optional as i32),
+            r#type: Some(Type::String as i32),
+            ..Default::default()
+        };
+        let method = MethodDescriptorProto {
+            name: Some("SayHello".to_string()),
+            input_type: Some(".greet.Hello".to_string()),
+            output_type: Some(".greet.Hello".to_string()),
+            ..Default::default()
+        };
+        FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("greet.proto".to_string()),
+                package: Some("greet".to_string()),
+                syntax: Some("proto3".to_string()),
+                message_type: vec![DescriptorProto {
+                    name: Some("Hello".to_string()),
+                    field: vec![field],
+                    ..Default::default()
+                }],
+                service: vec![ServiceDescriptorProto {
+                    name: Some("Greeter".to_string()),
+                    method: vec![method],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        }
+    }
+
+    #[test]
+    fn test_to_message() {
+        let lua = Lua::new();
+        let pool = DescriptorPool::from_file_descriptor_set(greeter_descriptors()).unwrap();
+        let hello = pool.get_message_by_name("greet.Hello").unwrap();
+
+        let value: Value = lua.load(r#"return { user_name = "Ada" }"#).eval().unwrap();
+        let message = to_message(&lua, hello.clone(), Some(value)).unwrap();
+        assert_eq!(serde_json::to_value(&message).unwrap(), serde_json::json!({ "userName": "Ada" }));
+
+        let empty: Value = lua.load("return {}").eval().unwrap();
+        assert!(to_message(&lua, hello.clone(), Some(empty)).is_ok());
+        let wrong: Value = lua.load("return { user_name = { 1 } }").eval().unwrap();
+        assert!(to_message(&lua, hello, Some(wrong)).is_err());
+    }
+
+    #[test]
+    fn test_call_unreachable_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("greet.pb");
+        std::fs::write(&path, greeter_descriptors().encode_to_vec()).unwrap();
+
+        let lua = Lua::new();
+        register_grpc_module(&lua).unwrap();
+        lua.globals().set("descriptors", path.to_string_lossy().to_string()).unwrap();
+        let (failed, code, missing): (bool, String, String) = lua
+            .load(
+                r#"
+                local client = require("grpc").connect("http://127.0.0.1:1", { descriptors = descriptors, timeout = 2 })
+                local reply, err = client:call("greet.Greeter/SayHello", { userName = "Ada" })
+                local ok, missing = pcall(client.call, client, "greet.Greeter/Wave", {})
+                return reply == nil, err.code, tostring(missing)
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert!(failed);
+        assert_ne!(code, "OK");
+        assert!(missing.contains("has no method Wave"), "{}", missing);
+    }
+}
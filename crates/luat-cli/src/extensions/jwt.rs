@@ -0,0 +1,419 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! JSON Web Token module for Lua.
+//!
+//! Provides `jwt.sign` and `jwt.verify` for API routes that issue their own
+//! tokens or accept tokens from an external identity provider.
+//!
+//! # Example
+//!
+//! ```lua
+//! local jwt = require("jwt")
+//!
+//! -- Issue a token signed with a shared secret
+//! local token = jwt.sign({ sub = user.id, exp = os.time() + 3600 }, secret)
+//!
+//! -- Verify a provider's token against its published keys
+//! local claims, err = jwt.verify(bearer, { jwks = "https://login.example.com/.well-known/jwks.json" }, {
+//!     issuer = "https://login.example.com/",
+//!     audience = "my-api",
+//! })
+//! if not claims then
+//!     return { status = 401, body = { error = err } }
+//! end
+//! ```
+//!
+//! `jwt.sign(claims, key, alg, options)` returns the signed token. `alg` is
+//! one of `HS256` (default), `HS384`, `HS512`, `RS256`, `RS384`, `RS512`,
+//! `PS256`, `PS384`, `PS512`, `ES256`, `ES384` or `EdDSA`; HMAC algorithms
+//! take a secret as key, the others a PEM private key. `options.kid` sets
+//! the key id in the header.
+//!
+//! `jwt.verify(token, key, options)` returns the claims of a valid token, or
+//! `nil` and an error. The key is a secret, a PEM public key, or a table
+//! with `jwks`, the URL of a key set, or `keys`, a key set itself; keys are
+//! picked by the token's `kid`. Secrets only verify HMAC tokens and public
+//! keys only the others. Key sets fetched from a URL are cached for 10
+//! minutes and fetched again when a token names an unknown key.
+//!
+//! The signature, `exp` (required) and `nbf` are always checked. Options:
+//! `algorithms` (allowed algorithms, default: any the key fits), `issuer`,
+//! `audience` (a string or list), `leeway` (seconds of clock skew, default
+//! 60) and `require` (claims that must be present, default `{ "exp" }`).
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use mlua::{Lua, LuaSerdeExt, Result as LuaResult, Table, Value};
+use serde_json::Value as JsonValue;
+
+/// How long a fetched key set is used before it is fetched again.
+const JWKS_TTL: Duration = Duration::from_secs(600);
+
+/// Shortest time between two fetches of a key set, so tokens naming unknown
+/// keys can't make every request fetch it.
+const JWKS_MIN_REFETCH: Duration = Duration::from_secs(30);
+
+/// Timeout of key set requests.
+const JWKS_TIMEOUT_SECS: u64 = 10;
+
+/// Key sets fetched from a URL, with when they were fetched.
+type JwksCache = Mutex<HashMap<String, (Instant, JwkSet)>>;
+
+fn jwks_cache() -> &'static JwksCache {
+    static CACHE: OnceLock<JwksCache> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// Register the jwt module on the given Lua instance.
+///
+/// This makes `jwt.sign()` and `jwt.verify()` available in Lua code, also
+/// as `require("jwt")`.
+pub fn register_jwt_module(lua: &Lua) -> LuaResult<()> {
+    let jwt = lua.create_table()?;
+
+    jwt.set(
+        "sign",
+        lua.create_function(
+            |lua, (claims, key, alg, options): (Value, mlua::String, Option<String>, Option<Table>)| {
+                let claims: JsonValue = lua.from_value(claims)?;
+                let alg = parse_algorithm(alg.as_deref().unwrap_or("HS256")).map_err(mlua::Error::RuntimeError)?;
+                let mut header = Header::new(alg);
+                if let Some(options) = options {
+                    header.kid = options.get("kid")?;
+                }
+                let key = encoding_key(alg, &key.as_bytes())
+                    .map_err(|e| mlua::Error::RuntimeError(format!("jwt.sign: {}", e)))?;
+                jsonwebtoken::encode(&header, &claims, &key)
+                    .map_err(|e| mlua::Error::RuntimeError(format!("jwt.sign: {}", e)))
+            },
+        )?,
+    )?;
+
+    jwt.set(
+        "verify",
+        lua.create_function(|lua, (token, key, options): (String, Value, Option<Table>)| {
+            let key = VerifyKey::from_lua(lua, key)?;
+            let options = VerifyOptions::from_lua(options.as_ref())?;
+            match verify(&token, &key, &options) {
+                Ok(claims) => Ok((lua.to_value(&claims)?, Value::Nil)),
+                Err(e) => Ok((Value::Nil, Value::String(lua.create_string(e)?))),
+            }
+        })?,
+    )?;
+
+    let globals = lua.globals();
+    globals.set("jwt", jwt)?;
+
+    let package: Table = globals.get("package")?;
+    let preload: Table = package.get("preload")?;
+    preload.set(
+        "jwt",
+        lua.create_function(|lua, _: ()| lua.globals().get::<Table>("jwt"))?,
+    )?;
+
+    Ok(())
+}
+
+/// Key given to `jwt.verify`.
+enum VerifyKey {
+    /// HMAC secret.
+    Secret(Vec<u8>),
+    /// PEM public key.
+    Pem(Vec<u8>),
+    /// URL of a key set.
+    JwksUrl(String),
+    /// Key set given inline.
+    Jwks(JwkSet),
+}
+
+impl VerifyKey {
+    fn from_lua(lua: &Lua, key: Value) -> LuaResult<Self> {
+        match key {
+            Value::String(key) => {
+                let key = key.as_bytes().to_vec();
+                if String::from_utf8_lossy(&key).trim_start().starts_with("-----BEGIN") {
+                    Ok(Self::Pem(key))
+                } else {
+                    Ok(Self::Secret(key))
+                }
+            }
+            Value::Table(table) => {
+                if let Some(url) = table.get::<Option<String>>("jwks")? {
+                    Ok(Self::JwksUrl(url))
+                } else if table.contains_key("keys")? {
+                    let set: JwkSet = lua.from_value(Value::Table(table))?;
+                    Ok(Self::Jwks(set))
+                } else {
+                    Err(mlua::Error::RuntimeError(
+                        "jwt.verify: key table needs `jwks` or `keys`".to_string(),
+                    ))
+                }
+            }
+            _ => Err(mlua::Error::RuntimeError(
+                "jwt.verify: key must be a string or a table".to_string(),
+            )),
+        }
+    }
+}
+
+/// Options of `jwt.verify`.
+#[derive(Default)]
+struct VerifyOptions {
+    algorithms: Option<Vec<Algorithm>>,
+    issuer: Option<String>,
+    audience: Option<Vec<String>>,
+    leeway: Option<u64>,
+    require: Option<Vec<String>>,
+}
+
+impl VerifyOptions {
+    fn from_lua(options: Option<&Table>) -> LuaResult<Self> {
+        let Some(options) = options else {
+            return Ok(Self::default());
+        };
+        let algorithms = options
+            .get::<Option<Vec<String>>>("algorithms")?
+            .map(|names| {
+                names
+                    .iter()
+                    .map(|name| parse_algorithm(name))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()
+            .map_err(mlua::Error::RuntimeError)?;
+        let audience = match options.get::<Value>("audience")? {
+            Value::Nil => None,
+            Value::String(audience) => Some(vec![audience.to_str()?.to_string()]),
+            Value::Table(audience) => Some(audience.sequence_values::<String>().collect::<LuaResult<_>>()?),
+            _ => {
+                return Err(mlua::Error::RuntimeError(
+                    "jwt.verify: audience must be a string or a list".to_string(),
+                ))
+            }
+        };
+        Ok(Self {
+            algorithms,
+            issuer: options.get("issuer")?,
+            audience,
+            leeway: options.get("leeway")?,
+            require: options.get("require")?,
+        })
+    }
+}
+
+fn parse_algorithm(name: &str) -> Result<Algorithm, String> {
+    name.parse().map_err(|_| format!("unsupported JWT algorithm '{}'", name))
+}
+
+fn is_hmac(alg: Algorithm) -> bool {
+    matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512)
+}
+
+fn encoding_key(alg: Algorithm, key: &[u8]) -> jsonwebtoken::errors::Result<EncodingKey> {
+    match alg {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => Ok(EncodingKey::from_secret(key)),
+        Algorithm::ES256 | Algorithm::ES384 => EncodingKey::from_ec_pem(key),
+        Algorithm::EdDSA => EncodingKey::from_ed_pem(key),
+        _ => EncodingKey::from_rsa_pem(key),
+    }
+}
+
+fn pem_decoding_key(alg: Algorithm, key: &[u8]) -> jsonwebtoken::errors::Result<DecodingKey> {
+    match alg {
+        Algorithm::ES256 | Algorithm::ES384 => DecodingKey::from_ec_pem(key),
+        Algorithm::EdDSA => DecodingKey::from_ed_pem(key),
+        _ => DecodingKey::from_rsa_pem(key),
+    }
+}
+
+/// Checks `token` and returns its claims.
+fn verify(token: &str, key: &VerifyKey, options: &VerifyOptions) -> Result<JsonValue, String> {
+    let header = jsonwebtoken::decode_header(token).map_err(|e| format!("invalid token: {}", e))?;
+    let alg = header.alg;
+    if let Some(allowed) = &options.algorithms {
+        if !allowed.contains(&alg) {
+            return Err(format!("algorithm {:?} is not allowed", alg));
+        }
+    }
+    // A public key must never be used as an HMAC secret, or anyone could
+    // sign tokens with it
+    let secret_key = matches!(key, VerifyKey::Secret(_));
+    if is_hmac(alg) != secret_key {
+        return Err(format!("algorithm {:?} does not fit the key", alg));
+    }
+
+    let decoding_key = match key {
+        VerifyKey::Secret(secret) => DecodingKey::from_secret(secret),
+        VerifyKey::Pem(pem) => pem_decoding_key(alg, pem).map_err(|e| format!("invalid key: {}", e))?,
+        VerifyKey::JwksUrl(url) => jwk_key(&fetched_jwk(url, header.kid.as_deref())?, alg)?,
+        VerifyKey::Jwks(set) => jwk_key(&find_jwk(set, header.kid.as_deref())?, alg)?,
+    };
+
+    let mut validation = Validation::new(alg);
+    validation.validate_nbf = true;
+    if let Some(leeway) = options.leeway {
+        validation.leeway = leeway;
+    }
+    if let Some(require) = &options.require {
+        validation.set_required_spec_claims(require.as_slice());
+    }
+    if let Some(issuer) = &options.issuer {
+        validation.set_issuer(&[issuer]);
+    }
+    match &options.audience {
+        Some(audience) => validation.set_audience(audience.as_slice()),
+        None => validation.validate_aud = false,
+    }
+
+    jsonwebtoken::decode::<JsonValue>(token, &decoding_key, &validation)
+        .map(|data| data.claims)
+        .map_err(|e| format!("invalid token: {}", e))
+}
+
+/// Returns the key of `set` with id `kid`, or its only key for tokens
+/// without one.
+fn find_jwk(set: &JwkSet, kid: Option<&str>) -> Result<Jwk, String> {
+    let jwk = match kid {
+        Some(kid) => set.find(kid),
+        None if set.keys.len() == 1 => set.keys.first(),
+        None => None,
+    };
+    jwk.cloned().ok_or_else(|| match kid {
+        Some(kid) => format!("no key with id '{}' in the key set", kid),
+        None => "token has no key id and the key set has several keys".to_string(),
+    })
+}
+
+fn jwk_key(jwk: &Jwk, alg: Algorithm) -> Result<DecodingKey, String> {
+    if let Some(key_alg) = &jwk.common.key_algorithm {
+        if format!("{:?}", key_alg) != format!("{:?}", alg) {
+            return Err(format!("key is for {:?}, token uses {:?}", key_alg, alg));
+        }
+    }
+    DecodingKey::from_jwk(jwk).map_err(|e| format!("invalid key: {}", e))
+}
+
+/// Returns the key `kid` of the key set at `url`, fetching the set when it
+/// isn't cached, is stale, or lacks the key.
+fn fetched_jwk(url: &str, kid: Option<&str>) -> Result<Jwk, String> {
+    let cached = jwks_cache()
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(url)
+        .map(|(fetched, set)| (fetched.elapsed(), find_jwk(set, kid)));
+    match cached {
+        Some((age, Ok(jwk))) if age < JWKS_TTL => return Ok(jwk),
+        Some((age, Err(e))) if age < JWKS_MIN_REFETCH => return Err(e),
+        _ => {}
+    }
+
+    let set = fetch_jwks(url)?;
+    let jwk = find_jwk(&set, kid);
+    jwks_cache()
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(url.to_string(), (Instant::now(), set));
+    jwk
+}
+
+fn fetch_jwks(url: &str) -> Result<JwkSet, String> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(JWKS_TIMEOUT_SECS))
+        .user_agent(concat!("luat/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| format!("failed to create HTTP client: {}", e))?;
+    let response = client
+        .get(url)
+        .header("Accept", "application/json")
+        .send()
+        .map_err(|e| format!("failed to fetch key set: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("failed to fetch key set: HTTP {}", response.status()));
+    }
+    response.json().map_err(|e| format!("invalid key set: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lua() -> Lua {
+        let lua = Lua::new();
+        register_jwt_module(&lua).unwrap();
+        lua
+    }
+
+    #[test]
+    fn test_sign_and_verify_with_secret() {
+        let lua = lua();
+        let (sub, err): (Option<String>, Option<String>) = lua
+            .load(
+                r#"
+                local jwt = require("jwt")
+                local token = jwt.sign({ sub = "42", exp = os.time() + 60, aud = "api" }, "secret")
+                local claims, err = jwt.verify(token, "secret", { audience = "api" })
+                return claims and claims.sub, err
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!(sub.as_deref(), Some("42"));
+        assert_eq!(err, None);
+    }
+
+    #[test]
+    fn test_verify_rejects_bad_tokens() {
+        let lua = lua();
+        let errors: Vec<String> = lua
+            .load(
+                r#"
+                local exp = os.time() + 60
+                local errors = {}
+                local function check(token, key, options)
+                    local claims, err = jwt.verify(token, key, options)
+                    assert(claims == nil)
+                    table.insert(errors, err)
+                end
+                check(jwt.sign({ exp = exp }, "other"), "secret")
+                check(jwt.sign({ exp = os.time() - 3600 }, "secret"), "secret")
+                check(jwt.sign({ exp = exp, nbf = os.time() + 3600 }, "secret"), "secret")
+                check(jwt.sign({ sub = "no expiry" }, "secret"), "secret")
+                check(jwt.sign({ exp = exp, iss = "evil" }, "secret"), "secret", { issuer = "good" })
+                check(jwt.sign({ exp = exp }, "secret", "HS512"), "secret", { algorithms = { "HS256" } })
+                check(jwt.sign({ exp = exp }, "-----BEGIN PUBLIC KEY-----"), "-----BEGIN PUBLIC KEY-----")
+                return errors
+                "#,
+            )
+            .eval()
+            .unwrap();
+
+        assert_eq!(errors.len(), 7);
+        assert!(errors[0].contains("InvalidSignature"), "{}", errors[0]);
+        assert!(errors[1].contains("ExpiredSignature"), "{}", errors[1]);
+        assert!(errors[2].contains("ImmatureSignature"), "{}", errors[2]);
+        assert!(errors[3].contains("exp"), "{}", errors[3]);
+        assert!(errors[4].contains("InvalidIssuer"), "{}", errors[4]);
+        assert!(errors[5].contains("not allowed"), "{}", errors[5]);
+        assert!(errors[6].contains("does not fit the key"), "{}", errors[6]);
+    }
+
+    #[test]
+    fn test_find_jwk_by_kid() {
+        let set: JwkSet = serde_json::from_value(serde_json::json!({
+            "keys": [
+                { "kty": "oct", "kid": "a", "k": "c2VjcmV0" },
+                { "kty": "oct", "kid": "b", "k": "b3RoZXI" }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(find_jwk(&set, Some("b")).unwrap().common.key_id.as_deref(), Some("b"));
+        assert!(find_jwk(&set, Some("c")).unwrap_err().contains("'c'"));
+        assert!(find_jwk(&set, None).is_err());
+    }
+}
@@ -4,6 +4,24 @@
 
 //! CLI-specific Lua extensions that require async/network capabilities.
 
+pub mod auth;
+pub mod crypto;
+pub mod graphql;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 pub mod http;
+pub mod jwt;
+pub mod storage;
+#[cfg(feature = "webauthn")]
+pub mod webauthn;
 
+pub use auth::register_auth_module;
+pub use crypto::register_crypto_module;
+pub use graphql::register_graphql_module;
+#[cfg(feature = "grpc")]
+pub use grpc::register_grpc_module;
 pub use http::register_http_module;
+pub use jwt::register_jwt_module;
+pub use storage::register_storage_module;
+#[cfg(feature = "webauthn")]
+pub use webauthn::register_webauthn_module;
@@ -0,0 +1,325 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! WebAuthn (passkey) module for Lua (`webauthn` feature).
+//!
+//! Runs the registration and authentication ceremonies in Rust, so server
+//! code can offer passwordless sign-in without doing any crypto in Lua. The
+//! state of a ceremony in progress is kept in the KV store, under a random
+//! challenge id that the server hands to the browser, typically in a cookie.
+//!
+//! # Example
+//!
+//! ```lua
+//! local webauthn = require("webauthn")
+//! local rp = webauthn.relying_party({ id = "example.com", origin = "https://example.com", name = "Example" })
+//!
+//! -- Registration: send `options` to navigator.credentials.create()
+//! local options, challenge = rp:start_registration({ user_id = user.id, name = user.email, display_name = user.name })
+//! -- ...then verify the credential the browser returns
+//! local passkey, err = rp:finish_registration(challenge, request.body)
+//! if passkey then
+//!     passkeys:put(user.id .. ":" .. passkey.id, json.encode(passkey.credential))
+//! end
+//!
+//! -- Authentication: send `options` to navigator.credentials.get()
+//! local options, challenge = rp:start_authentication(user_passkeys)
+//! local result, err = rp:finish_authentication(challenge, request.body)
+//! ```
+//!
+//! `webauthn.relying_party(options)` takes `id` (the domain), `origin` (the
+//! URL pages are served from), `name` (shown by the authenticator, default:
+//! `id`) and `timeout` (seconds a ceremony may take, default 300).
+//!
+//! `rp:start_registration(user)` takes `user_id` (any stable string),
+//! `name`, `display_name` (default: `name`) and `exclude` (the user's
+//! stored credentials, so an authenticator isn't registered twice). It
+//! returns the `PublicKeyCredentialCreationOptions` (as a `{ publicKey }`
+//! table) and the challenge id. `rp:finish_registration(challenge,
+//! credential)` returns `{ id, credential }`: the credential id and the
+//! credential to store with the user.
+//!
+//! `rp:start_authentication(credentials)` takes the stored credentials of
+//! the user signing in and returns the request options and the challenge id.
+//! `rp:finish_authentication(challenge, credential)` returns `{ id,
+//! user_verified, counter, credential }`, where `credential` is the updated
+//! credential to store, or `nil` if it didn't change.
+//!
+//! Browser credentials are the JSON encoding of `PublicKeyCredential`
+//! (`credential.toJSON()`). A challenge can be finished once; a ceremony
+//! that fails verification, expired or was already finished returns `nil`
+//! and an error message.
+
+use mlua::{Lua, LuaSerdeExt, Result as LuaResult, Table, UserData, UserDataMethods, Value};
+use luat::kv::{KVStoreFactory, PutOptions};
+use sha2::{Digest, Sha256};
+use webauthn_rs::prelude::*;
+
+/// KV namespace ceremony state is stored in.
+const CHALLENGE_NAMESPACE: &str = "__webauthn";
+
+/// Seconds a ceremony may take when the relying party sets no timeout.
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
+
+/// Register the webauthn module on the given Lua instance.
+///
+/// This makes `webauthn.relying_party()` available in Lua code, also as
+/// `require("webauthn")`. Ceremony state is stored in the KV namespace
+/// `__webauthn` of `factory`.
+pub fn register_webauthn_module(lua: &Lua, factory: KVStoreFactory) -> LuaResult<()> {
+    let webauthn = lua.create_table()?;
+    webauthn.set(
+        "relying_party",
+        lua.create_function(move |_, options: Table| RelyingParty::new(&options, factory.clone()))?,
+    )?;
+
+    let globals = lua.globals();
+    globals.set("webauthn", webauthn)?;
+
+    let package: Table = globals.get("package")?;
+    let preload: Table = package.get("preload")?;
+    preload.set(
+        "webauthn",
+        lua.create_function(|lua, _: ()| lua.globals().get::<Table>("webauthn"))?,
+    )?;
+
+    Ok(())
+}
+
+/// A relying party: the site passkeys are registered with.
+struct RelyingParty {
+    webauthn: Webauthn,
+    factory: KVStoreFactory,
+    timeout: u64,
+}
+
+impl RelyingParty {
+    fn new(options: &Table, factory: KVStoreFactory) -> LuaResult<Self> {
+        let id: String = options.get("id")?;
+        let origin: String = options.get("origin")?;
+        let name: Option<String> = options.get("name")?;
+        let timeout: Option<u64> = options.get("timeout")?;
+
+        let origin = Url::parse(&origin)
+            .map_err(|e| mlua::Error::runtime(format!("webauthn: invalid origin '{}': {}", origin, e)))?;
+        let webauthn = WebauthnBuilder::new(&id, &origin)
+            .and_then(|builder| builder.rp_name(name.as_deref().unwrap_or(&id)).build())
+            .map_err(|e| mlua::Error::runtime(format!("webauthn: invalid relying party '{}': {}", id, e)))?;
+
+        Ok(Self {
+            webauthn,
+            factory,
+            timeout: timeout.unwrap_or(DEFAULT_TIMEOUT_SECS),
+        })
+    }
+
+    /// Stores the state of a ceremony under a new challenge id.
+    fn save_state<T: serde::Serialize>(&self, state: &T) -> LuaResult<String> {
+        let mut bytes = [0u8; 16];
+        getrandom::getrandom(&mut bytes).map_err(|e| mlua::Error::runtime(format!("webauthn: {}", e)))?;
+        let challenge: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+        let state = serde_json::to_vec(state).map_err(mlua::Error::external)?;
+        let options = PutOptions {
+            expiration_ttl: Some(self.timeout),
+            ..Default::default()
+        };
+        (self.factory)(CHALLENGE_NAMESPACE)
+            .put(&challenge, &state, options)
+            .map_err(mlua::Error::external)?;
+        Ok(challenge)
+    }
+
+    /// Takes the state stored under `challenge`; a challenge is single-use.
+    fn take_state<T: serde::de::DeserializeOwned>(&self, challenge: &str) -> LuaResult<Option<T>> {
+        let store = (self.factory)(CHALLENGE_NAMESPACE);
+        let Some(state) = store.get(challenge).map_err(mlua::Error::external)? else {
+            return Ok(None);
+        };
+        store.delete(challenge).map_err(mlua::Error::external)?;
+        Ok(serde_json::from_slice(&state).ok())
+    }
+}
+
+/// Maps a user id to the UUID WebAuthn identifies users by; UUIDs are kept.
+fn user_uuid(user_id: &str) -> Uuid {
+    Uuid::parse_str(user_id).unwrap_or_else(|_| {
+        let digest = Sha256::digest(user_id.as_bytes());
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&digest[..16]);
+        Uuid::from_bytes(bytes)
+    })
+}
+
+/// Returns the base64url text of a credential id.
+fn credential_id(id: &CredentialID) -> String {
+    match serde_json::to_value(id) {
+        Ok(serde_json::Value::String(id)) => id,
+        _ => String::new(),
+    }
+}
+
+/// Reads stored credentials, as returned by the `finish_*` methods.
+fn passkeys(lua: &Lua, credentials: Option<Table>) -> LuaResult<Vec<Passkey>> {
+    let Some(credentials) = credentials else {
+        return Ok(Vec::new());
+    };
+    credentials
+        .sequence_values::<Value>()
+        .map(|credential| lua.from_value(credential?))
+        .collect()
+}
+
+impl UserData for RelyingParty {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("start_registration", |lua, rp, user: Table| {
+            let user_id: String = user.get("user_id")?;
+            let name: String = user.get("name")?;
+            let display_name: Option<String> = user.get("display_name")?;
+            let exclude = passkeys(lua, user.get("exclude")?)?;
+            let exclude = (!exclude.is_empty())
+                .then(|| exclude.iter().map(|passkey| passkey.cred_id().clone()).collect());
+
+            let (options, state) = rp
+                .webauthn
+                .start_passkey_registration(
+                    user_uuid(&user_id),
+                    &name,
+                    display_name.as_deref().unwrap_or(&name),
+                    exclude,
+                )
+                .map_err(|e| mlua::Error::runtime(format!("webauthn: {}", e)))?;
+            let challenge = rp.save_state(&state)?;
+            Ok((lua.to_value(&options)?, challenge))
+        });
+
+        methods.add_method("finish_registration", |lua, rp, (challenge, credential): (String, Value)| {
+            let Some(state) = rp.take_state::<PasskeyRegistration>(&challenge)? else {
+                return Ok((Value::Nil, Some("unknown or expired challenge".to_string())));
+            };
+            let credential: RegisterPublicKeyCredential = match lua.from_value(credential) {
+                Ok(credential) => credential,
+                Err(e) => return Ok((Value::Nil, Some(format!("invalid credential: {}", e)))),
+            };
+            match rp.webauthn.finish_passkey_registration(&credential, &state) {
+                Ok(passkey) => {
+                    let result = lua.create_table()?;
+                    result.set("id", credential_id(passkey.cred_id()))?;
+                    result.set("credential", lua.to_value(&passkey)?)?;
+                    Ok((Value::Table(result), None))
+                }
+                Err(e) => Ok((Value::Nil, Some(e.to_string()))),
+            }
+        });
+
+        methods.add_method("start_authentication", |lua, rp, credentials: Option<Table>| {
+            let credentials = passkeys(lua, credentials)?;
+            let (options, state) = rp
+                .webauthn
+                .start_passkey_authentication(&credentials)
+                .map_err(|e| mlua::Error::runtime(format!("webauthn: {}", e)))?;
+            // The credentials are stored with the state so the finished
+            // ceremony can return the one used, with its counter updated
+            let challenge = rp.save_state(&(state, credentials))?;
+            Ok((lua.to_value(&options)?, challenge))
+        });
+
+        methods.add_method("finish_authentication", |lua, rp, (challenge, credential): (String, Value)| {
+            let Some((state, mut credentials)) =
+                rp.take_state::<(PasskeyAuthentication, Vec<Passkey>)>(&challenge)?
+            else {
+                return Ok((Value::Nil, Some("unknown or expired challenge".to_string())));
+            };
+            let credential: PublicKeyCredential = match lua.from_value(credential) {
+                Ok(credential) => credential,
+                Err(e) => return Ok((Value::Nil, Some(format!("invalid credential: {}", e)))),
+            };
+            let auth = match rp.webauthn.finish_passkey_authentication(&credential, &state) {
+                Ok(auth) => auth,
+                Err(e) => return Ok((Value::Nil, Some(e.to_string()))),
+            };
+
+            let result = lua.create_table()?;
+            result.set("id", credential_id(auth.cred_id()))?;
+            result.set("user_verified", auth.user_verified())?;
+            result.set("counter", auth.counter())?;
+            let updated = credentials
+                .iter_mut()
+                .find(|passkey| passkey.update_credential(&auth) == Some(true));
+            if let Some(passkey) = updated {
+                result.set("credential", lua.to_value(&*passkey)?)?;
+            }
+            Ok((Value::Table(result), None))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use luat::kv::MemoryKVStore;
+    use std::sync::Arc;
+
+    fn lua() -> Lua {
+        let lua = Lua::new();
+        let store: Arc<dyn luat::kv::KVStore> = Arc::new(MemoryKVStore::new());
+        let factory: KVStoreFactory = Arc::new(move |_| store.clone());
+        register_webauthn_module(&lua, factory).unwrap();
+        lua
+    }
+
+    #[test]
+    fn test_start_registration_stores_challenge() {
+        let lua = lua();
+        let (challenge, user_id): (String, String) = lua
+            .load(
+                r#"
+                local rp = require("webauthn").relying_party({ id = "example.com", origin = "https://example.com" })
+                local options, challenge = rp:start_registration({ user_id = "42", name = "ada@example.com" })
+                return challenge, options.publicKey.user.name
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!(challenge.len(), 32);
+        assert_eq!(user_id, "ada@example.com");
+    }
+
+    #[test]
+    fn test_finish_rejects_unknown_or_reused_challenge() {
+        let lua = lua();
+        let (first, second): (String, String) = lua
+            .load(
+                r#"
+                local rp = webauthn.relying_party({ id = "example.com", origin = "https://example.com" })
+                local _, challenge = rp:start_registration({ user_id = "42", name = "ada" })
+                local _, first = rp:finish_registration(challenge, { id = "x" })
+                local _, second = rp:finish_registration(challenge, { id = "x" })
+                return first, second
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert!(first.starts_with("invalid credential"), "{}", first);
+        assert_eq!(second, "unknown or expired challenge");
+    }
+
+    #[test]
+    fn test_relying_party_requires_valid_origin() {
+        let lua = lua();
+        let err = lua
+            .load(r#"webauthn.relying_party({ id = "example.com", origin = "not a url" })"#)
+            .exec()
+            .unwrap_err();
+        assert!(err.to_string().contains("invalid origin"), "{}", err);
+    }
+
+    #[test]
+    fn test_user_uuid_is_stable() {
+        assert_eq!(user_uuid("42"), user_uuid("42"));
+        assert_ne!(user_uuid("42"), user_uuid("43"));
+        let id = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        assert_eq!(user_uuid(id).to_string(), id);
+    }
+}
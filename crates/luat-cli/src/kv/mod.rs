@@ -2,24 +2,27 @@
 // SPDX-License-Identifier: Apache-2.0
 // SPDX-License-Identifier: MIT
 
-//! CLI KV store implementation using SQLite.
+//! CLI KV store implementation using SQLite, with an in-memory alternative.
 
 mod sqlite;
 
 pub use sqlite::SqliteKVStore;
 
-use luat::kv::{KVStore, KVStoreFactory};
+use luat::kv::{KVStore, KVStoreFactory, MemoryKVStore};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 
+use crate::config::KvBackend;
+
 /// Manager for creating and caching KV store instances.
 ///
 /// Each namespace gets its own KV store, and stores are cached
 /// across requests for the lifetime of the server.
 pub struct KVManager {
-    data_dir: PathBuf,
-    stores: RwLock<HashMap<String, Arc<SqliteKVStore>>>,
+    /// SQLite data directory, or `None` for in-memory stores.
+    data_dir: Option<PathBuf>,
+    stores: RwLock<HashMap<String, Arc<dyn KVStore>>>,
 }
 
 impl KVManager {
@@ -33,13 +36,31 @@ impl KVManager {
         std::fs::create_dir_all(&data_dir)?;
 
         Ok(Self {
-            data_dir,
+            data_dir: Some(data_dir),
             stores: RwLock::new(HashMap::new()),
         })
     }
 
+    /// Creates a KV manager whose stores live in memory only.
+    pub fn in_memory() -> Self {
+        Self {
+            data_dir: None,
+            stores: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Creates a KV manager for the configured backend.
+    ///
+    /// `data_dir` is only used by the SQLite backend.
+    pub fn for_backend(backend: KvBackend, data_dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        match backend {
+            KvBackend::Sqlite => Self::new(data_dir),
+            KvBackend::Memory => Ok(Self::in_memory()),
+        }
+    }
+
     /// Gets or creates a KV store for the given namespace.
-    pub fn get_store(&self, namespace: &str) -> Arc<SqliteKVStore> {
+    pub fn get_store(&self, namespace: &str) -> Arc<dyn KVStore> {
         // Check if we already have a store for this namespace
         {
             let stores = self.stores.read().unwrap();
@@ -49,10 +70,13 @@ impl KVManager {
         }
 
         // Create a new store
-        let store = Arc::new(
-            SqliteKVStore::new(&self.data_dir, namespace)
-                .expect("Failed to create KV store"),
-        );
+        let store: Arc<dyn KVStore> = match self.data_dir {
+            Some(ref data_dir) => Arc::new(
+                SqliteKVStore::new(data_dir, namespace)
+                    .expect("Failed to create KV store"),
+            ),
+            None => Arc::new(MemoryKVStore::new()),
+        };
 
         // Cache it
         {
@@ -36,6 +36,8 @@
 pub mod commands;
 /// Project configuration from `luat.toml`.
 pub mod config;
+/// Bundle encryption for closed-source template distribution.
+pub mod encryption;
 /// CLI-specific Lua extensions (http client).
 pub mod extensions;
 /// Key-Value store with SQLite backend.
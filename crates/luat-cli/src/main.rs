@@ -2,8 +2,12 @@
 // SPDX-License-Identifier: Apache-2.0
 // SPDX-License-Identifier: MIT
 
+use std::path::PathBuf;
+
 use clap::{Parser, Subcommand};
 use luat_cli::commands;
+use luat_cli::commands::dev::DevOptions;
+use luat_cli::server::listen::{claim_activation, parse_socket_mode, UnixSocketOptions};
 use tracing_subscriber::EnvFilter;
 
 #[derive(Parser)]
@@ -34,9 +38,12 @@ enum Commands {
     Init {
         /// Project name (defaults to current directory name)
         name: Option<String>,
-        /// Template to use: default, htmx
-        #[arg(short, long, default_value = "default")]
-        template: String,
+        /// Template to use: default, minimal (prompted for when omitted)
+        #[arg(short, long)]
+        template: Option<String>,
+        /// Skip the prompts and use the default options
+        #[arg(short, long)]
+        yes: bool,
     },
     /// Start development server with live reload
     Dev {
@@ -46,6 +53,18 @@ enum Commands {
         /// Host to bind to
         #[arg(long, default_value = "127.0.0.1")]
         host: String,
+        /// Listen on a Unix domain socket instead of host/port
+        #[arg(long, value_name = "PATH")]
+        uds: Option<PathBuf>,
+        /// File mode for the Unix socket, in octal (e.g. 660)
+        #[arg(long, value_name = "MODE", requires = "uds", value_parser = parse_socket_mode)]
+        uds_mode: Option<u32>,
+        /// Freeze time, seed randomness and sort table iteration for byte-stable output
+        #[arg(long)]
+        deterministic: bool,
+        /// Build-time define for `defines.NAME` in templates (repeatable)
+        #[arg(long = "define", value_name = "NAME=VALUE")]
+        defines: Vec<String>,
     },
     /// Build templates for production
     Build {
@@ -55,6 +74,9 @@ enum Commands {
         /// Output directory
         #[arg(short, long, default_value = "dist")]
         output: String,
+        /// Build-time define for `defines.NAME` in templates (repeatable)
+        #[arg(long = "define", value_name = "NAME=VALUE")]
+        defines: Vec<String>,
     },
     /// Serve production build (no live reload, optimized)
     Serve {
@@ -64,13 +86,170 @@ enum Commands {
         /// Host to bind to
         #[arg(long, default_value = "0.0.0.0")]
         host: String,
+        /// Number of render workers (0 = one per CPU core)
+        #[arg(short, long, default_value = "1")]
+        workers: usize,
+        /// Reload when dist/bundle.bin changes (SIGHUP always reloads)
+        #[arg(long)]
+        reload: bool,
+        /// Set SO_REUSEPORT so several processes can share the port
+        #[arg(long)]
+        reuse_port: bool,
+        /// Listen on a Unix domain socket instead of host/port
+        #[arg(long, value_name = "PATH", conflicts_with = "reuse_port")]
+        uds: Option<PathBuf>,
+        /// File mode for the Unix socket, in octal (e.g. 660)
+        #[arg(long, value_name = "MODE", requires = "uds", value_parser = parse_socket_mode)]
+        uds_mode: Option<u32>,
+        /// Freeze time, seed randomness and sort table iteration for byte-stable output
+        #[arg(long)]
+        deterministic: bool,
+        /// Build-time define for `defines.NAME` in templates (repeatable)
+        #[arg(long = "define", value_name = "NAME=VALUE")]
+        defines: Vec<String>,
+    },
+    /// Prerender the production build into a static site
+    Export {
+        /// Output directory
+        #[arg(short, long, default_value = "dist/site")]
+        output: String,
+        /// Follow internal links to export parameterized routes
+        #[arg(long)]
+        crawl: bool,
+    },
+    /// Generate an OpenAPI document for the +server.lua API routes
+    Openapi {
+        /// Write the document to a file instead of stdout
+        #[arg(long, value_name = "FILE")]
+        out: Option<String>,
+    },
+    /// Preview a component with props from a JSON fixture
+    Preview {
+        /// Component name (e.g. Card) or path (e.g. lib/forms/Input)
+        component: String,
+        /// JSON fixture with the component's props
+        #[arg(long, value_name = "FILE")]
+        props: Option<String>,
+        /// Port to run the dev server on
+        #[arg(short, long, default_value = "3000")]
+        port: u16,
+        /// Host to bind to
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+    },
+    /// Render a template or route URL once, outside a server
+    Render {
+        /// Template path, component name or URL (starting with /)
+        target: String,
+        /// JSON file with the template's props
+        #[arg(long, value_name = "FILE")]
+        props: Option<String>,
+        /// Write the output to a file instead of stdout
+        #[arg(long, value_name = "FILE")]
+        out: Option<String>,
+    },
+    /// Run the seed scripts in src/seeds that haven't been applied yet
+    Seed {
+        /// Run every script again, including applied ones
+        #[arg(long)]
+        force: bool,
+        /// List applied and pending scripts without running any
+        #[arg(long)]
+        status: bool,
     },
+    /// Evaluate Lua interactively with the project's modules
+    Shell,
     /// Watch files and rebuild on change (no server)
-    Watch,
+    Watch {
+        /// Print each rebuild as a JSON line
+        #[arg(long)]
+        json: bool,
+        /// Build-time define for `defines.NAME` in templates (repeatable)
+        #[arg(long = "define", value_name = "NAME=VALUE")]
+        defines: Vec<String>,
+    },
+    /// Report unreachable templates, unused imports, unread props and invalid HTML
+    Analyze,
+    /// Check the project and environment for common problems
+    Doctor,
+    /// Print the compilation stages of a template (all stages when no flag is given)
+    Inspect {
+        /// Path to the .luat file
+        template: String,
+        /// Print the parsed AST
+        #[arg(long)]
+        ast: bool,
+        /// Print the intermediate representation
+        #[arg(long)]
+        ir: bool,
+        /// Print the generated Lua code
+        #[arg(long)]
+        lua: bool,
+        /// Print the required modules and where they resolve to
+        #[arg(long)]
+        deps: bool,
+    },
+    /// Package a component library into a distributable archive
+    Pack {
+        /// Package directory containing luat-package.toml
+        #[arg(default_value = ".")]
+        dir: String,
+        /// Output directory for the archive
+        #[arg(short, long, default_value = ".")]
+        output: String,
+    },
+    /// Scaffold a route, component or action
+    New {
+        #[command(subcommand)]
+        kind: NewKind,
+    },
+    /// Install a component package into the modules directory (default: luat_modules/)
+    Add {
+        /// Path to a package archive (.tar.gz) or package directory
+        package: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum NewKind {
+    /// Create a route directory with +page.luat and +page.server.lua
+    Route {
+        /// Route path relative to the routes directory (e.g. blog/[slug])
+        path: String,
+        /// Add a form actions table to +page.server.lua
+        #[arg(long)]
+        actions: bool,
+        /// Add a smoke test under tests/routes/
+        #[arg(long)]
+        test: bool,
+        /// Only create +page.luat
+        #[arg(long)]
+        no_server: bool,
+    },
+    /// Create a component in the lib components directory
+    Component {
+        /// Component name, optionally nested (e.g. forms/TextInput)
+        name: String,
+    },
+    /// Add a form action and its fragment template to a route
+    Action {
+        /// Route path relative to the routes directory
+        route: String,
+        /// Action name
+        name: String,
+    },
+}
+
+fn main() -> anyhow::Result<()> {
+    // Edits the environment, so it runs before the runtime starts threads
+    claim_activation();
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(run())
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn run() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     // Initialize tracing with the specified log level
@@ -82,20 +261,88 @@ async fn main() -> anyhow::Result<()> {
         .init();
 
     match cli.command {
-        Commands::Init { name, template } => {
-            commands::init::run(name, Some(template)).await
+        Commands::Init { name, template, yes } => {
+            commands::init::run(name, template, yes).await
+        }
+        Commands::Dev { port, host, uds, uds_mode, deterministic, defines } => {
+            let uds = uds.map(|path| UnixSocketOptions { path, mode: uds_mode });
+            commands::dev::run(DevOptions {
+                host,
+                port,
+                uds,
+                deterministic,
+                defines,
+                verbose: cli.verbose,
+                quiet: cli.quiet,
+                ..DevOptions::default()
+            })
+            .await
+        }
+        Commands::Preview { component, props, port, host } => {
+            let options = DevOptions {
+                host,
+                port,
+                verbose: cli.verbose,
+                quiet: cli.quiet,
+                ..DevOptions::default()
+            };
+            commands::preview::run(&component, props, options).await
+        }
+        Commands::Build { source, output, defines } => {
+            commands::build::run(source, &output, &defines).await
+        }
+        Commands::Serve { port, host, workers, reload, reuse_port, uds, uds_mode, deterministic, defines } => {
+            commands::serve::run(commands::serve::ServeOptions {
+                host,
+                port,
+                workers,
+                reload,
+                reuse_port,
+                uds: uds.map(|path| UnixSocketOptions { path, mode: uds_mode }),
+                deterministic,
+                defines,
+            })
+            .await
+        }
+        Commands::Export { output, crawl } => {
+            commands::export::run(commands::export::ExportOptions { output, crawl }).await
+        }
+        Commands::Openapi { out } => {
+            commands::openapi::run(commands::openapi::OpenApiOptions { out })
+        }
+        Commands::Render { target, props, out } => {
+            commands::render::run(&target, commands::render::RenderOptions { props, out })
+        }
+        Commands::Seed { force, status } => {
+            commands::seed::run(commands::seed::SeedOptions { force, status })
+        }
+        Commands::Shell => {
+            commands::shell::run()
+        }
+        Commands::Watch { json, defines } => {
+            commands::watch::run(json, defines).await
+        }
+        Commands::Analyze => {
+            commands::analyze::run()
         }
-        Commands::Dev { port, host } => {
-            commands::dev::run(&host, port, cli.verbose, cli.quiet).await
+        Commands::Doctor => {
+            commands::doctor::run()
         }
-        Commands::Build { source, output } => {
-            commands::build::run(source, &output).await
+        Commands::Inspect { template, ast, ir, lua, deps } => {
+            commands::inspect::run(&template, commands::inspect::InspectOptions { ast, ir, lua, deps })
         }
-        Commands::Serve { port, host } => {
-            commands::serve::run(&host, port).await
+        Commands::Pack { dir, output } => {
+            commands::package::run_pack(&dir, &output)
         }
-        Commands::Watch => {
-            commands::watch::run().await
+        Commands::New { kind } => match kind {
+            NewKind::Route { path, actions, test, no_server } => {
+                commands::generate::run_route(&path, no_server, actions, test)
+            }
+            NewKind::Component { name } => commands::generate::run_component(&name),
+            NewKind::Action { route, name } => commands::generate::run_action(&route, &name),
+        },
+        Commands::Add { package } => {
+            commands::package::run_add(&package)
         }
     }
 }
@@ -0,0 +1,250 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Component catalog for the development server.
+//!
+//! `GET /__luat/components` lists every `.luat` component under the lib
+//! directory with the props it references, and renders an example for each
+//! co-located fixture:
+//!
+//! ```text
+//! src/lib/components/Card.luat
+//! src/lib/components/Card.fixture.json          -> "default" example
+//! src/lib/components/Card.with-title.fixture.json -> "with-title" example
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use luat::referenced_props;
+
+use super::http::html_escape;
+use super::preview::preview_url;
+
+/// URL path of the catalog page.
+pub const CATALOG_PATH: &str = "/__luat/components";
+
+/// Suffix of fixture files placed next to a component.
+const FIXTURE_SUFFIX: &str = ".fixture.json";
+
+/// A component found in the lib directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentEntry {
+    /// Module path passed to the engine (e.g. `lib/components/Card`).
+    pub module_path: String,
+    /// Props the template reads or declares (see [`referenced_props`]), sorted.
+    pub props: Vec<String>,
+    /// Co-located fixtures, sorted by label.
+    pub fixtures: Vec<ComponentFixture>,
+}
+
+/// A fixture file with example props for a component.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentFixture {
+    /// Example label (`default` for `Name.fixture.json`).
+    pub label: String,
+    /// Fixture path relative to the project root.
+    pub path: String,
+}
+
+/// Finds all components under `lib_dir` (relative to `project_dir`).
+///
+/// Route files (`+page.luat` etc.) and hidden directories are skipped.
+pub fn discover_components(project_dir: &Path, lib_dir: &str) -> anyhow::Result<Vec<ComponentEntry>> {
+    let lib_root = project_dir.join(lib_dir);
+    let mut files = Vec::new();
+    if lib_root.is_dir() {
+        collect_templates(&lib_root, &mut files)?;
+    }
+    files.sort();
+
+    let mut components = Vec::new();
+    for file in files {
+        let rel = file.strip_prefix(&lib_root)?.with_extension("");
+        let module_path = format!("lib/{}", rel.to_string_lossy().replace('\\', "/"));
+        let source = fs::read_to_string(&file)?;
+        components.push(ComponentEntry {
+            module_path,
+            props: referenced_props(&source),
+            fixtures: find_fixtures(project_dir, &file)?,
+        });
+    }
+    Ok(components)
+}
+
+fn collect_templates(dir: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        if path.is_dir() {
+            if !file_name.starts_with('.') {
+                collect_templates(&path, files)?;
+            }
+        } else if file_name.ends_with(".luat") && !file_name.starts_with('+') {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn find_fixtures(project_dir: &Path, component: &Path) -> anyhow::Result<Vec<ComponentFixture>> {
+    let stem = component
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let Some(dir) = component.parent() else {
+        return Ok(Vec::new());
+    };
+
+    let mut fixtures = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let Some(rest) = name.strip_prefix(stem).and_then(|r| r.strip_suffix(FIXTURE_SUFFIX)) else {
+            continue;
+        };
+        let label = match rest {
+            "" => "default".to_string(),
+            variant => match variant.strip_prefix('.') {
+                Some(variant) if !variant.is_empty() => variant.to_string(),
+                _ => continue,
+            },
+        };
+        let rel = path.strip_prefix(project_dir).unwrap_or(&path);
+        fixtures.push(ComponentFixture {
+            label,
+            path: rel.to_string_lossy().replace('\\', "/"),
+        });
+    }
+    fixtures.sort_by(|a, b| a.label.cmp(&b.label));
+    Ok(fixtures)
+}
+
+/// Renders the catalog body.
+///
+/// `render` produces the HTML for one fixture (or an error message).
+pub fn render_catalog(
+    components: &[ComponentEntry],
+    mut render: impl FnMut(&ComponentEntry, &ComponentFixture) -> Result<String, String>,
+) -> String {
+    let mut html = String::from(
+        "<main style=\"max-width:64rem;margin:0 auto;padding:2rem;font-family:system-ui,sans-serif\">\n\
+         <h1>Components</h1>\n",
+    );
+
+    if components.is_empty() {
+        html.push_str("<p>No components found.</p>\n");
+    }
+
+    for component in components {
+        let name = html_escape(&component.module_path);
+        html.push_str(&format!(
+            "<section id=\"{}\" style=\"margin:2rem 0;border-top:1px solid #ddd;padding-top:1rem\">\n<h2>{}</h2>\n",
+            name, name
+        ));
+
+        if component.props.is_empty() {
+            html.push_str("<p><em>No props referenced.</em></p>\n");
+        } else {
+            let props: Vec<String> = component
+                .props
+                .iter()
+                .map(|p| format!("<code>{}</code>", html_escape(p)))
+                .collect();
+            html.push_str(&format!("<p>Props: {}</p>\n", props.join(", ")));
+        }
+
+        if component.fixtures.is_empty() {
+            html.push_str(&format!(
+                "<p><em>No fixtures. Add <code>{}{}</code> next to the component.</em> \
+                 <a href=\"{}\">Preview</a></p>\n",
+                html_escape(component.module_path.rsplit('/').next().unwrap_or_default()),
+                FIXTURE_SUFFIX,
+                html_escape(&preview_url(&component.module_path, None))
+            ));
+        }
+
+        for fixture in &component.fixtures {
+            html.push_str(&format!(
+                "<h3>{} <small><a href=\"{}\">Preview</a></small></h3>\n",
+                html_escape(&fixture.label),
+                html_escape(&preview_url(&component.module_path, Some(&fixture.path)))
+            ));
+            match render(component, fixture) {
+                Ok(example) => html.push_str(&format!(
+                    "<div style=\"border:1px dashed #ccc;padding:1rem\">{}</div>\n",
+                    example
+                )),
+                Err(e) => html.push_str(&format!(
+                    "<pre style=\"color:#b00\">{}</pre>\n",
+                    html_escape(&e)
+                )),
+            }
+        }
+
+        html.push_str("</section>\n");
+    }
+
+    html.push_str("</main>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discover_components() {
+        let dir = tempfile::tempdir().unwrap();
+        let components = dir.path().join("src/lib/components");
+        fs::create_dir_all(&components).unwrap();
+        fs::write(components.join("Card.luat"), "<h2>{props.title}</h2>").unwrap();
+        fs::write(components.join("Card.fixture.json"), "{}").unwrap();
+        fs::write(components.join("Card.long.fixture.json"), "{}").unwrap();
+        fs::write(components.join("CardList.luat"), "<ul></ul>").unwrap();
+        fs::write(components.join("+page.luat"), "").unwrap();
+
+        let found = discover_components(dir.path(), "src/lib").unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].module_path, "lib/components/Card");
+        assert_eq!(found[0].props, vec!["title"]);
+        assert_eq!(
+            found[0].fixtures,
+            vec![
+                ComponentFixture {
+                    label: "default".to_string(),
+                    path: "src/lib/components/Card.fixture.json".to_string(),
+                },
+                ComponentFixture {
+                    label: "long".to_string(),
+                    path: "src/lib/components/Card.long.fixture.json".to_string(),
+                },
+            ]
+        );
+        assert_eq!(found[1].module_path, "lib/components/CardList");
+        assert!(found[1].fixtures.is_empty());
+    }
+
+    #[test]
+    fn test_render_catalog() {
+        let components = vec![ComponentEntry {
+            module_path: "lib/components/Card".to_string(),
+            props: vec!["title".to_string()],
+            fixtures: vec![ComponentFixture {
+                label: "default".to_string(),
+                path: "src/lib/components/Card.fixture.json".to_string(),
+            }],
+        }];
+
+        let html = render_catalog(&components, |_, _| Ok("<h2>Hello</h2>".to_string()));
+        assert!(html.contains("<h2>lib/components/Card</h2>"));
+        assert!(html.contains("<code>title</code>"));
+        assert!(html.contains("<h2>Hello</h2>"));
+
+        let html = render_catalog(&components, |_, _| Err("boom <x>".to_string()));
+        assert!(html.contains("boom &lt;x&gt;"));
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! CORS handling for API routes.
+//!
+//! Applies the `[cors]` settings to `+server.lua` routes: [`preflight`]
+//! answers preflight requests before the handler runs, and [`apply`] adds the
+//! `Access-Control-*` headers to the handler's response. Requests from
+//! origins that aren't allowed get no CORS headers, so the browser blocks
+//! them.
+
+use std::collections::HashMap;
+
+use axum::{
+    http::{header, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+use crate::config::CorsConfig;
+
+/// Returns true for a CORS preflight: an `OPTIONS` request carrying
+/// `Origin` and `Access-Control-Request-Method`.
+///
+/// `headers` are the request headers with lowercase names.
+pub fn is_preflight(method: &str, headers: &HashMap<String, String>) -> bool {
+    method.eq_ignore_ascii_case("OPTIONS")
+        && headers.contains_key("origin")
+        && headers.contains_key("access-control-request-method")
+}
+
+/// Answers a preflight request.
+pub fn preflight(config: &CorsConfig, headers: &HashMap<String, String>) -> Response {
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    let Some(origin) = allowed_origin(config, headers) else {
+        return response;
+    };
+
+    let allow_headers = if config.headers.is_empty() {
+        headers.get("access-control-request-headers").cloned()
+    } else {
+        Some(config.headers.join(", "))
+    };
+    set_headers(
+        &mut response,
+        [
+            ("access-control-allow-methods", Some(config.methods.join(", "))),
+            ("access-control-allow-headers", allow_headers),
+            ("access-control-max-age", config.max_age.map(|seconds| seconds.to_string())),
+        ],
+    );
+    set_origin(config, &mut response, origin);
+    response
+}
+
+/// Adds CORS headers to an API response if the request's origin is allowed.
+pub fn apply(config: &CorsConfig, headers: &HashMap<String, String>, response: &mut Response) {
+    let Some(origin) = allowed_origin(config, headers) else {
+        return;
+    };
+
+    let expose_headers = (!config.expose_headers.is_empty()).then(|| config.expose_headers.join(", "));
+    set_headers(response, [("access-control-expose-headers", expose_headers)]);
+    set_origin(config, response, origin);
+}
+
+/// Returns the `Access-Control-Allow-Origin` value for the request, if any.
+fn allowed_origin(config: &CorsConfig, headers: &HashMap<String, String>) -> Option<String> {
+    let origin = headers.get("origin")?;
+    // `*` with credentials is refused by `CorsConfig::validate`
+    if config.origins.iter().any(|allowed| allowed == "*") {
+        return Some("*".to_string());
+    }
+    config
+        .origins
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(origin))
+        .then(|| origin.clone())
+}
+
+fn set_origin(config: &CorsConfig, response: &mut Response, origin: String) {
+    if origin != "*" {
+        // The response differs per origin, so shared caches must key on it
+        response
+            .headers_mut()
+            .append(header::VARY, HeaderValue::from_static("Origin"));
+    }
+    set_headers(
+        response,
+        [
+            ("access-control-allow-origin", Some(origin)),
+            ("access-control-allow-credentials", config.credentials.then(|| "true".to_string())),
+        ],
+    );
+}
+
+fn set_headers<const N: usize>(response: &mut Response, headers: [(&'static str, Option<String>); N]) {
+    for (name, value) in headers {
+        let Some(value) = value else { continue };
+        match HeaderValue::from_str(&value) {
+            Ok(value) => {
+                response
+                    .headers_mut()
+                    .insert(HeaderName::from_static(name), value);
+            }
+            Err(_) => tracing::warn!("Invalid value for CORS header {}", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(origins: &[&str], credentials: bool) -> CorsConfig {
+        CorsConfig {
+            origins: origins.iter().map(|o| o.to_string()).collect(),
+            credentials,
+            max_age: Some(600),
+            ..CorsConfig::default()
+        }
+    }
+
+    fn request(origin: &str) -> HashMap<String, String> {
+        HashMap::from([
+            ("origin".to_string(), origin.to_string()),
+            ("access-control-request-method".to_string(), "POST".to_string()),
+            ("access-control-request-headers".to_string(), "content-type".to_string()),
+        ])
+    }
+
+    fn header<'a>(response: &'a Response, name: &str) -> Option<&'a str> {
+        response.headers().get(name).and_then(|v| v.to_str().ok())
+    }
+
+    #[test]
+    fn test_preflight_for_allowed_origin() {
+        let headers = request("https://app.example.com");
+        assert!(is_preflight("OPTIONS", &headers));
+        assert!(!is_preflight("GET", &headers));
+
+        let response = preflight(&config(&["https://app.example.com"], true), &headers);
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(header(&response, "access-control-allow-origin"), Some("https://app.example.com"));
+        assert_eq!(header(&response, "access-control-allow-headers"), Some("content-type"));
+        assert_eq!(header(&response, "access-control-allow-credentials"), Some("true"));
+        assert_eq!(header(&response, "access-control-max-age"), Some("600"));
+        assert_eq!(header(&response, "vary"), Some("Origin"));
+
+        let response = preflight(&config(&["https://app.example.com"], true), &request("https://evil.test"));
+        assert_eq!(header(&response, "access-control-allow-origin"), None);
+    }
+
+    #[test]
+    fn test_apply_wildcard_origin() {
+        let headers = request("https://app.example.com");
+
+        let mut response = StatusCode::OK.into_response();
+        apply(&config(&["*"], false), &headers, &mut response);
+        assert_eq!(header(&response, "access-control-allow-origin"), Some("*"));
+        assert_eq!(header(&response, "vary"), None);
+
+        // Any origin with credentials would expose every user's data
+        assert!(config(&["*"], true).validate().is_err());
+        assert!(config(&["*"], false).validate().is_ok());
+        assert!(config(&["https://app.example.com"], true).validate().is_ok());
+    }
+}
@@ -0,0 +1,304 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Local crash reports for `luat dev` and `luat serve`.
+//!
+//! When a request handler panics or a render fails at runtime, a JSON report
+//! with the request, the matched route, the Lua traceback and the versions in
+//! use is written to `.luat/crash/` and its path is printed. Reports never
+//! leave the machine; attach them to bug reports by hand.
+//!
+//! Request details are attached through [`with_request`], which scopes them
+//! to the handling task so the panic hook can pick them up.
+
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use luat::LuatError;
+use regex::Regex;
+use serde::Serialize;
+
+tokio::task_local! {
+    static CURRENT_REQUEST: RequestInfo;
+}
+
+/// Directory for crash reports, relative to the project root.
+pub const CRASH_DIR: &str = ".luat/crash";
+
+static PROJECT_DIR: OnceLock<PathBuf> = OnceLock::new();
+static SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+
+/// The request being handled when the crash happened.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct RequestInfo {
+    /// HTTP method.
+    pub method: String,
+    /// Request path, without the query string.
+    pub path: String,
+    /// Matched route pattern.
+    pub route: Option<String>,
+}
+
+/// Versions of the components involved.
+#[derive(Debug, Clone, Serialize)]
+pub struct Versions {
+    /// luat version.
+    pub luat: &'static str,
+    /// Lua runtime version, e.g. `Lua 5.4`.
+    pub lua: String,
+    /// Operating system.
+    pub os: &'static str,
+    /// CPU architecture.
+    pub arch: &'static str,
+}
+
+impl Versions {
+    /// Versions of the running process.
+    pub fn current() -> Self {
+        static LUA_VERSION: OnceLock<String> = OnceLock::new();
+        let lua = LUA_VERSION.get_or_init(|| {
+            mlua::Lua::new()
+                .globals()
+                .get::<String>("_VERSION")
+                .unwrap_or_else(|_| "unknown".to_string())
+        });
+        Self {
+            luat: env!("CARGO_PKG_VERSION"),
+            lua: lua.clone(),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+        }
+    }
+}
+
+/// A crash report as written to disk.
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashReport {
+    /// `panic` or `render_error`.
+    pub kind: &'static str,
+    /// Unix time in seconds.
+    pub timestamp: u64,
+    /// Panic or error message.
+    pub message: String,
+    /// Rust source location of a panic.
+    pub location: Option<String>,
+    /// Request being handled, if known.
+    pub request: Option<RequestInfo>,
+    /// Lua stack traceback; in `luat dev` template frames point at `.luat` lines.
+    pub traceback: Option<String>,
+    /// Rust backtrace of a panic.
+    pub backtrace: Option<String>,
+    /// Versions in use.
+    pub versions: Versions,
+}
+
+impl CrashReport {
+    fn new(kind: &'static str, message: String, request: Option<RequestInfo>) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Self {
+            kind,
+            timestamp,
+            message,
+            location: None,
+            request,
+            traceback: None,
+            backtrace: None,
+            versions: Versions::current(),
+        }
+    }
+
+    /// Creates a report for a failed render.
+    ///
+    /// `map_line` maps a line of the Lua code generated for a `.luat` file
+    /// to the template line; it is applied to traceback frames the engine
+    /// hasn't mapped already.
+    pub fn render_error(
+        error: &LuatError,
+        request: Option<RequestInfo>,
+        map_line: &dyn Fn(&str, usize) -> Option<usize>,
+    ) -> Self {
+        let mut report = Self::new("render_error", error.to_string(), request);
+        report.traceback = match error {
+            LuatError::TemplateRuntimeError {
+                message,
+                lua_traceback,
+                ..
+            } => lua_traceback
+                .clone()
+                .or_else(|| split_traceback(message).map(str::to_string)),
+            other => lua_error(other)
+                .and_then(lua_traceback)
+                .map(|traceback| map_traceback(&traceback, map_line)),
+        };
+        report
+    }
+
+    /// Writes the report to `dir` and returns its path.
+    pub fn write(&self, dir: &Path) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+        let path = dir.join(format!(
+            "crash-{}-{}-{}.json",
+            self.timestamp,
+            std::process::id(),
+            sequence
+        ));
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(&path, json)?;
+        Ok(path)
+    }
+}
+
+/// True for errors raised while running Lua code, as opposed to template
+/// compile errors, which the error page already explains.
+pub fn is_crash(error: &LuatError) -> bool {
+    matches!(
+        error,
+        LuatError::LuaError(_)
+            | LuatError::ResourceLimit(_)
+            | LuatError::TemplateRuntimeError { .. }
+            | LuatError::BundleModuleError { .. }
+            | LuatError::IoError(_)
+            | LuatError::CacheError(_)
+    )
+}
+
+/// Runs `future` with `request` attached to any crash report it causes.
+pub async fn with_request<F: Future>(request: RequestInfo, future: F) -> F::Output {
+    CURRENT_REQUEST.scope(request, future).await
+}
+
+/// Returns the request attached by [`with_request`] on the current task.
+pub fn current_request() -> Option<RequestInfo> {
+    CURRENT_REQUEST.try_with(Clone::clone).ok()
+}
+
+/// Writes `report` to the crash directory of the project and prints its path.
+pub fn save(report: &CrashReport) -> Option<PathBuf> {
+    let project_dir = PROJECT_DIR.get().cloned().unwrap_or_else(|| PathBuf::from("."));
+    match report.write(&project_dir.join(CRASH_DIR)) {
+        Ok(path) => {
+            eprintln!("Crash report written to {}", path.display());
+            Some(path)
+        }
+        Err(e) => {
+            eprintln!("Warning: Failed to write crash report: {}", e);
+            None
+        }
+    }
+}
+
+/// Installs a panic hook that writes a crash report for every panic.
+///
+/// The previous hook still runs, so the panic message is printed as usual.
+pub fn install_panic_hook(project_dir: &Path) {
+    let _ = PROJECT_DIR.set(project_dir.to_path_buf());
+    // Resolve the Lua version now rather than inside the hook
+    let _ = Versions::current();
+
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        previous(info);
+
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Box<dyn Any>".to_string());
+        let mut report = CrashReport::new("panic", message, current_request());
+        report.location = info.location().map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+        report.backtrace = Some(std::backtrace::Backtrace::force_capture().to_string());
+        save(&report);
+    }));
+}
+
+fn lua_error(error: &LuatError) -> Option<&mlua::Error> {
+    match error {
+        LuatError::LuaError(e) => Some(e),
+        LuatError::BundleModuleError { original_error, .. } => lua_error(original_error),
+        _ => None,
+    }
+}
+
+/// Returns the innermost traceback of a Lua error.
+fn lua_traceback(error: &mlua::Error) -> Option<String> {
+    match error {
+        mlua::Error::CallbackError { traceback, cause } => {
+            lua_traceback(cause).or_else(|| Some(traceback.clone()))
+        }
+        mlua::Error::WithContext { cause, .. } => lua_traceback(cause),
+        other => split_traceback(&other.to_string()).map(str::to_string),
+    }
+}
+
+fn split_traceback(message: &str) -> Option<&str> {
+    message.find("stack traceback:").map(|start| &message[start..])
+}
+
+/// Rewrites `file.luat:LINE:` frames with `map_line`.
+fn map_traceback(traceback: &str, map_line: &dyn Fn(&str, usize) -> Option<usize>) -> String {
+    let re = Regex::new(r"([^\s:\[\]]+\.luat):(\d+):").expect("valid regex");
+    re.replace_all(traceback, |caps: &regex::Captures| {
+        let line = caps[2].parse().ok().and_then(|line| map_line(&caps[1], line));
+        match line {
+            Some(line) => format!("{}:{}:", &caps[1], line),
+            None => caps[0].to_string(),
+        }
+    })
+    .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_render_error_report() {
+        let error = LuatError::LuaError(mlua::Error::CallbackError {
+            traceback: "stack traceback:\n\t[C]: in ?\n\tsrc/routes/+page.luat:40: in function 'render'".to_string(),
+            cause: Arc::new(mlua::Error::runtime("boom")),
+        });
+        let request = RequestInfo {
+            method: "GET".to_string(),
+            path: "/".to_string(),
+            route: Some("/".to_string()),
+        };
+        let report = CrashReport::render_error(&error, Some(request.clone()), &|file, line| {
+            (file == "src/routes/+page.luat" && line == 40).then_some(7)
+        });
+
+        assert!(is_crash(&error));
+        assert_eq!(report.kind, "render_error");
+        assert_eq!(report.request, Some(request));
+        let traceback = report.traceback.as_deref().unwrap();
+        assert!(traceback.contains("src/routes/+page.luat:7: in function 'render'"), "{}", traceback);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = report.write(dir.path()).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(json["request"]["method"], "GET");
+        assert_eq!(json["versions"]["lua"], "Lua 5.4");
+        assert!(!is_crash(&LuatError::ModuleNotFound("x".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_with_request_scope() {
+        let request = RequestInfo {
+            method: "POST".to_string(),
+            path: "/login".to_string(),
+            route: None,
+        };
+        assert_eq!(current_request(), None);
+        let inner = with_request(request.clone(), async { current_request() }).await;
+        assert_eq!(inner, Some(request));
+    }
+}
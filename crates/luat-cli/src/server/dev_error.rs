@@ -0,0 +1,453 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Error pages for `luat dev`.
+//!
+//! Shows the error code, the message, an excerpt of the template around the
+//! failing line, the request and the versions in use, with a button that
+//! copies all of it as plain text. The page follows the browser's light or
+//! dark preference.
+//!
+//! These pages expose source code and paths, so they are only used by the
+//! development server; `luat serve` has its own error handling.
+
+use std::path::{Path, PathBuf};
+
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Response};
+use luat::{LuatError, SourceContext};
+use regex::Regex;
+
+use super::crash::{RequestInfo, Versions};
+use super::http::html_escape;
+
+/// Lines shown around the failing line.
+const EXCERPT_RADIUS: usize = 3;
+
+/// An excerpt of the template that failed.
+#[derive(Debug, Clone)]
+pub struct SourceExcerpt {
+    /// Template path as reported by the engine.
+    pub file: String,
+    /// Failing line (1-indexed).
+    pub line: usize,
+    /// Failing column (1-indexed), if known.
+    pub column: Option<usize>,
+    /// Numbered lines around the failing line.
+    pub lines: Vec<(usize, String)>,
+}
+
+impl SourceExcerpt {
+    fn from_context(file: String, context: &SourceContext) -> Self {
+        let lines = (context.snippet_start..=context.snippet_end)
+            .filter_map(|n| context.lines.get(n - 1).map(|l| (n, l.clone())))
+            .collect();
+        Self {
+            file,
+            line: context.error_line,
+            column: Some(context.error_column).filter(|c| *c > 0),
+            lines,
+        }
+    }
+
+    fn from_file(file: &str, line: usize) -> Option<Self> {
+        let source = std::fs::read_to_string(file).ok()?;
+        let total = source.lines().count();
+        if line == 0 || line > total {
+            return None;
+        }
+        let start = line.saturating_sub(EXCERPT_RADIUS).max(1);
+        let end = (line + EXCERPT_RADIUS).min(total);
+        let lines = source
+            .lines()
+            .enumerate()
+            .map(|(i, l)| (i + 1, l.to_string()))
+            .filter(|(n, _)| (start..=end).contains(n))
+            .collect();
+        Some(Self {
+            file: file.to_string(),
+            line,
+            column: None,
+            lines,
+        })
+    }
+}
+
+/// A development error page.
+#[derive(Debug, Clone)]
+pub struct DevErrorPage {
+    /// HTTP status of the response.
+    pub status: StatusCode,
+    /// Stable error code, e.g. `LUAT_PARSE`.
+    pub code: &'static str,
+    /// Short title, e.g. `Parse error`.
+    pub title: &'static str,
+    /// Full error message.
+    pub message: String,
+    /// Template excerpt around the failing line.
+    pub excerpt: Option<SourceExcerpt>,
+    /// Request being handled.
+    pub request: Option<RequestInfo>,
+    /// Crash report written for the error.
+    pub crash_report: Option<PathBuf>,
+}
+
+impl DevErrorPage {
+    /// Creates a page for an error that isn't a [`LuatError`].
+    pub fn from_message(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            code: "LUAT_SERVER",
+            title: "Server error",
+            message: message.into(),
+            excerpt: None,
+            request: None,
+            crash_report: None,
+        }
+    }
+
+    /// Creates a page for `error`.
+    ///
+    /// `map_line` maps a line of the Lua code generated for a `.luat` file
+    /// to the template line; it is used for Lua errors the engine hasn't
+    /// mapped already.
+    pub fn from_error(error: &LuatError, map_line: &dyn Fn(&str, usize) -> Option<usize>) -> Self {
+        let (code, title) = error_code(error);
+        Self {
+            code,
+            title,
+            message: error.to_string(),
+            excerpt: excerpt(error, map_line),
+            ..Self::from_message(String::new())
+        }
+    }
+
+    /// Attaches the request being handled.
+    pub fn with_request(mut self, request: RequestInfo) -> Self {
+        self.request = Some(request);
+        self
+    }
+
+    /// Attaches the path of the crash report written for the error.
+    pub fn with_crash_report(mut self, path: Option<PathBuf>) -> Self {
+        self.crash_report = path;
+        self
+    }
+
+    /// Sets the HTTP status; invalid codes keep 500.
+    pub fn with_status(mut self, status: u16) -> Self {
+        if let Ok(status) = StatusCode::from_u16(status) {
+            self.status = status;
+        }
+        self
+    }
+
+    /// Plain-text report, as copied by the page's "Copy report" button.
+    pub fn report(&self) -> String {
+        let mut report = format!("{} [{}]\n\n{}\n", self.title, self.code, self.message.trim_end());
+        if let Some(excerpt) = &self.excerpt {
+            report.push_str(&format!("\n{}:{}\n", excerpt.file, excerpt.line));
+            for (n, line) in &excerpt.lines {
+                let marker = if *n == excerpt.line { '>' } else { ' ' };
+                report.push_str(&format!("{} {:4} | {}\n", marker, n, line));
+            }
+        }
+        report.push('\n');
+        if let Some(request) = &self.request {
+            report.push_str(&format!("Request: {} {}\n", request.method, request.path));
+            if let Some(route) = &request.route {
+                report.push_str(&format!("Route: {}\n", route));
+            }
+        }
+        for (name, value) in environment() {
+            report.push_str(&format!("{}: {}\n", name, value));
+        }
+        if let Some(path) = &self.crash_report {
+            report.push_str(&format!("Crash report: {}\n", path.display()));
+        }
+        report
+    }
+
+    /// Renders the page as HTML.
+    pub fn to_html(&self) -> String {
+        let excerpt = self.excerpt.as_ref().map(render_excerpt).unwrap_or_default();
+
+        let mut details = String::new();
+        if let Some(request) = &self.request {
+            details.push_str(&detail_row("Request", &format!("{} {}", request.method, request.path)));
+            if let Some(route) = &request.route {
+                details.push_str(&detail_row("Route", route));
+            }
+        }
+        for (name, value) in environment() {
+            details.push_str(&detail_row(name, &value));
+        }
+        if let Some(path) = &self.crash_report {
+            details.push_str(&detail_row("Crash report", &path.display().to_string()));
+        }
+
+        format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>{title} - Luat</title>
+    <style>{style}</style>
+</head>
+<body>
+    <main>
+        <header>
+            <span class="brand">luat dev</span>
+            <span class="code">{status} · {code}</span>
+        </header>
+        <h1>{title}</h1>
+        <pre class="message">{message}</pre>
+        {excerpt}
+        <dl>{details}</dl>
+        <button id="luat-copy" type="button">Copy report</button>
+        <textarea id="luat-report" hidden readonly>{report}</textarea>
+    </main>
+    <script>{script}</script>
+</body>
+</html>"#,
+            title = self.title,
+            style = STYLE,
+            status = self.status.as_u16(),
+            code = self.code,
+            message = html_escape(self.message.trim_end()),
+            excerpt = excerpt,
+            details = details,
+            report = html_escape(&self.report()),
+            script = COPY_SCRIPT,
+        )
+    }
+}
+
+impl IntoResponse for DevErrorPage {
+    fn into_response(self) -> Response {
+        (self.status, Html(self.to_html())).into_response()
+    }
+}
+
+/// Stable code and title for each kind of error.
+pub fn error_code(error: &LuatError) -> (&'static str, &'static str) {
+    match error {
+        LuatError::ParseError { .. } => ("LUAT_PARSE", "Parse error"),
+        LuatError::TransformError(_) => ("LUAT_TRANSFORM", "Transform error"),
+        LuatError::CodegenError(_) => ("LUAT_CODEGEN", "Code generation error"),
+        LuatError::LuaError(_) => ("LUAT_LUA", "Lua error"),
+        LuatError::ResourceLimit(_) => ("LUAT_LIMIT", "Resource limit exceeded"),
+        LuatError::IoError(_) => ("LUAT_IO", "IO error"),
+        LuatError::ResolutionError(_) | LuatError::ModuleNotFound(_) => ("LUAT_RESOLVE", "Template not found"),
+        LuatError::CacheError(_) => ("LUAT_CACHE", "Cache error"),
+        LuatError::InvalidTemplate(_)
+        | LuatError::MultipleModuleScripts
+        | LuatError::MultipleRegularScripts
+        | LuatError::ModuleScriptNotFirst => ("LUAT_INVALID_TEMPLATE", "Invalid template"),
+        LuatError::TemplateRuntimeError { .. } => ("LUAT_RUNTIME", "Template runtime error"),
+        LuatError::BundleModuleError { original_error, .. } => error_code(original_error),
+    }
+}
+
+/// Finds the template excerpt for `error`.
+fn excerpt(error: &LuatError, map_line: &dyn Fn(&str, usize) -> Option<usize>) -> Option<SourceExcerpt> {
+    match error {
+        LuatError::ParseError {
+            file,
+            source_context: Some(context),
+            ..
+        } => Some(SourceExcerpt::from_context(
+            file.clone().unwrap_or_else(|| "template".to_string()),
+            context,
+        )),
+        LuatError::TemplateRuntimeError {
+            template,
+            source_context: Some(context),
+            ..
+        } => Some(SourceExcerpt::from_context(template.clone(), context)),
+        LuatError::BundleModuleError { original_error, .. } => excerpt(original_error, map_line),
+        other => {
+            // Lua errors name the failing line of the generated code: `file.luat:LINE:`
+            let re = Regex::new(r"([^\s:\[\]]+\.luat):(\d+):").expect("valid regex");
+            let message = other.to_string();
+            let caps = re.captures(&message)?;
+            let lua_line: usize = caps[2].parse().ok()?;
+            let line = map_line(&caps[1], lua_line).unwrap_or(lua_line);
+            SourceExcerpt::from_file(&caps[1], line)
+        }
+    }
+}
+
+fn render_excerpt(excerpt: &SourceExcerpt) -> String {
+    let mut rows = String::new();
+    for (n, line) in &excerpt.lines {
+        let class = if *n == excerpt.line { "line error" } else { "line" };
+        rows.push_str(&format!(
+            r#"<span class="{}"><span class="gutter">{}</span>{}</span>"#,
+            class,
+            n,
+            highlight_template(line)
+        ));
+        if let (true, Some(column)) = (*n == excerpt.line, excerpt.column) {
+            rows.push_str(&format!(
+                r#"<span class="line caret"><span class="gutter"></span>{}^</span>"#,
+                " ".repeat(column.saturating_sub(1))
+            ));
+        }
+    }
+    let location = match excerpt.column {
+        Some(column) => format!("{}:{}:{}", excerpt.file, excerpt.line, column),
+        None => format!("{}:{}", excerpt.file, excerpt.line),
+    };
+    format!(
+        r#"<section class="excerpt"><div class="file">{}</div><pre>{}</pre></section>"#,
+        html_escape(&location),
+        rows
+    )
+}
+
+/// Escapes a template line, colouring tags, `{expressions}` and strings.
+fn highlight_template(line: &str) -> String {
+    let re = Regex::new(r#"(?P<expr>\{[^{}]*\})|(?P<tag></?[A-Za-z][\w.:-]*|/?>)|(?P<string>"[^"]*")"#)
+        .expect("valid regex");
+    let mut out = String::new();
+    let mut last = 0;
+    for caps in re.captures_iter(line) {
+        let whole = caps.get(0).expect("match");
+        out.push_str(&html_escape(&line[last..whole.start()]));
+        let class = if caps.name("expr").is_some() {
+            "tok-expr"
+        } else if caps.name("tag").is_some() {
+            "tok-tag"
+        } else {
+            "tok-string"
+        };
+        out.push_str(&format!(r#"<span class="{}">{}</span>"#, class, html_escape(whole.as_str())));
+        last = whole.end();
+    }
+    out.push_str(&html_escape(&line[last..]));
+    out
+}
+
+fn detail_row(name: &str, value: &str) -> String {
+    format!("<dt>{}</dt><dd>{}</dd>", html_escape(name), html_escape(value))
+}
+
+fn environment() -> Vec<(&'static str, String)> {
+    let versions = Versions::current();
+    let project = std::env::current_dir()
+        .map(|dir| project_name(&dir))
+        .unwrap_or_default();
+    vec![
+        ("luat", versions.luat.to_string()),
+        ("Lua", versions.lua),
+        ("OS", format!("{} ({})", versions.os, versions.arch)),
+        ("Project", project),
+    ]
+}
+
+fn project_name(dir: &Path) -> String {
+    dir.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| dir.display().to_string())
+}
+
+const STYLE: &str = r#"
+:root { color-scheme: light dark; --bg: #f6f7fb; --panel: #fff; --text: #1f2330; --muted: #6b7185; --accent: #e94560; --line: #fde8ec; --border: #e3e6ef; --expr: #8a3ffc; --tag: #0f62fe; --string: #198038; }
+@media (prefers-color-scheme: dark) {
+    :root { --bg: #1a1a2e; --panel: #16213e; --text: #eceef4; --muted: #9aa0b5; --accent: #ff5c7a; --line: #3a1f33; --border: #27304d; --expr: #be95ff; --tag: #78a9ff; --string: #6fdc8c; }
+}
+* { box-sizing: border-box; }
+body { margin: 0; font-family: system-ui, sans-serif; background: var(--bg); color: var(--text); }
+main { max-width: 960px; margin: 0 auto; padding: 2rem 1.5rem; }
+header { display: flex; justify-content: space-between; align-items: center; font-size: .85rem; color: var(--muted); }
+.brand { font-weight: 700; letter-spacing: .04em; color: var(--accent); }
+.code { font-family: ui-monospace, monospace; }
+h1 { margin: .5rem 0 1rem; font-size: 1.6rem; }
+pre { margin: 0; font-family: ui-monospace, SFMono-Regular, Menlo, monospace; font-size: .85rem; overflow-x: auto; }
+.message { background: var(--panel); border: 1px solid var(--border); border-left: 4px solid var(--accent); padding: 1rem; border-radius: 6px; white-space: pre-wrap; }
+.excerpt { margin-top: 1.5rem; background: var(--panel); border: 1px solid var(--border); border-radius: 6px; overflow: hidden; }
+.excerpt .file { padding: .5rem 1rem; font-size: .8rem; color: var(--muted); border-bottom: 1px solid var(--border); font-family: ui-monospace, monospace; }
+.excerpt pre { padding: .5rem 0; }
+.line { display: block; padding: 0 1rem; white-space: pre; }
+.line.error { background: var(--line); }
+.line.caret { color: var(--accent); font-weight: 700; }
+.gutter { display: inline-block; width: 3.5em; margin-right: 1em; text-align: right; color: var(--muted); user-select: none; }
+.tok-expr { color: var(--expr); }
+.tok-tag { color: var(--tag); }
+.tok-string { color: var(--string); }
+dl { display: grid; grid-template-columns: max-content 1fr; gap: .35rem 1rem; margin: 1.5rem 0; font-size: .85rem; }
+dt { color: var(--muted); }
+dd { margin: 0; font-family: ui-monospace, monospace; word-break: break-all; }
+button { font: inherit; font-size: .85rem; padding: .5rem 1rem; border-radius: 6px; border: 1px solid var(--border); background: var(--panel); color: var(--text); cursor: pointer; }
+button:hover { border-color: var(--accent); }
+"#;
+
+const COPY_SCRIPT: &str = r#"
+document.getElementById('luat-copy').addEventListener('click', function () {
+    var button = this;
+    var report = document.getElementById('luat-report').value;
+    var done = function () { button.textContent = 'Copied'; setTimeout(function () { button.textContent = 'Copy report'; }, 1500); };
+    if (navigator.clipboard && window.isSecureContext) {
+        navigator.clipboard.writeText(report).then(done);
+    } else {
+        var area = document.getElementById('luat-report');
+        area.hidden = false; area.select(); document.execCommand('copy'); area.hidden = true; done();
+    }
+});
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dev_error_page() {
+        let source = "<div>\n  <p>{props.name</p>\n</div>";
+        let error = LuatError::ParseError {
+            message: "unclosed expression".to_string(),
+            line: 2,
+            column: 6,
+            file: Some("src/routes/+page.luat".to_string()),
+            source_context: Some(SourceContext::from_source(source, 2, 6)),
+        };
+        let page = DevErrorPage::from_error(&error, &|_, _| None).with_request(RequestInfo {
+            method: "GET".to_string(),
+            path: "/".to_string(),
+            route: Some("/".to_string()),
+        });
+        let html = page.to_html();
+
+        assert_eq!(page.status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(html.contains("500 · LUAT_PARSE"));
+        assert!(html.contains("src/routes/+page.luat:2:6"));
+        assert!(html.contains(r#"<span class="line error"><span class="gutter">2</span>"#));
+        assert!(html.contains("prefers-color-scheme: dark"));
+        assert!(html.contains("Copy report"));
+        // Source is escaped, never injected
+        assert!(!html.contains("<p>{props.name</p>"));
+
+        let report = page.report();
+        assert!(report.starts_with("Parse error [LUAT_PARSE]"));
+        assert!(report.contains(">    2 |   <p>{props.name</p>"));
+        assert!(report.contains("Request: GET /"));
+    }
+
+    #[test]
+    fn test_lua_error_excerpt_is_mapped() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("page.luat");
+        std::fs::write(&file, "one\ntwo\nthree\nfour\n").unwrap();
+        let file = file.to_string_lossy().into_owned();
+        let error = LuatError::LuaError(mlua::Error::runtime(format!("{}:40: attempt to index a nil value", file)));
+
+        let page = DevErrorPage::from_error(&error, &|f, line| (f == file && line == 40).then_some(3));
+        let excerpt = page.excerpt.unwrap();
+        assert_eq!(page.code, "LUAT_LUA");
+        assert_eq!(excerpt.line, 3);
+        assert_eq!(excerpt.lines.first().map(|(n, _)| *n), Some(1));
+        assert_eq!(excerpt.lines.last().map(|(n, _)| *n), Some(4));
+    }
+}
@@ -13,7 +13,7 @@ use std::sync::Arc;
 
 use axum::{
     body::Body,
-    extract::{Request, State, WebSocketUpgrade},
+    extract::{Query, Request, State, WebSocketUpgrade},
     http::{Method, StatusCode},
     response::{Html, IntoResponse, Response},
     routing::get,
@@ -24,8 +24,16 @@ use serde_json::json;
 use tokio::sync::{broadcast, RwLock};
 use tower_http::services::ServeDir;
 
+use super::catalog::{discover_components, render_catalog, CATALOG_PATH};
+use super::cors;
+use super::crash::{self, CrashReport, RequestInfo};
+use super::dev_error::DevErrorPage;
+use super::images::ImageService;
 use super::livereload::handle_websocket;
-use crate::config::Config;
+use super::preview::{component_module_path, load_fixture, PREVIEW_PATH};
+use super::stream::{deferred_body, early_flush_head, flush_early, split_at_body_end, EarlyFlush};
+use crate::config::{Config, KvBackend};
+use crate::server::listen::{serve_unix, UnixSocketOptions};
 use crate::kv::KVManager;
 use crate::router::{Route, Router as LuatRouter};
 
@@ -34,7 +42,7 @@ const MAX_BODY_SIZE: usize = 1024 * 1024;
 /// Shared application state for the development server.
 pub struct AppState {
     /// Template engine with filesystem resolver.
-    pub engine: RwLock<Engine<FileSystemResolver>>,
+    pub engine: Arc<RwLock<Engine<FileSystemResolver>>>,
     /// Channel for sending reload notifications.
     pub reload_tx: Arc<broadcast::Sender<()>>,
     /// Application configuration.
@@ -50,12 +58,16 @@ pub struct AppState {
 }
 
 /// Creates and starts the development HTTP server.
+///
+/// Listens on `uds` when given, otherwise on the TCP address `addr`.
 pub async fn create_server(
     addr: &str,
+    uds: Option<&UnixSocketOptions>,
     config: &Config,
     reload_tx: Arc<broadcast::Sender<()>>,
 ) -> anyhow::Result<()> {
     let working_dir = std::env::current_dir()?;
+    crash::install_panic_hook(&working_dir);
 
     // Determine which directory to use for templates
     let (templates_dir, router) = if config.routing.simplified {
@@ -87,26 +99,56 @@ pub async fn create_server(
 
     // Create resolver with lib_dir for $lib alias support
     let lib_dir = working_dir.join(&config.routing.lib_dir);
-    let resolver = FileSystemResolver::new(&templates_dir).with_lib_dir(&lib_dir);
-    // Dev mode: no caching for fresh reloads on file changes
-    let cache = NoOpCache::new();
-    let mut engine = Engine::new(resolver, Box::new(cache))?;
-    // Set root path for readable error messages (show relative paths)
-    engine.set_root_path(&working_dir);
-
-    // Dev mode: setup non-caching require() so modules always load fresh
-    engine.setup_dev_mode()?;
+    let resolver = config.routing.source_roots.iter().fold(
+        FileSystemResolver::new(&templates_dir)
+            .with_lib_dir(&lib_dir)
+            .with_modules_dir(working_dir.join(&config.routing.modules_dir))
+            .with_content_dir(working_dir.join(&config.routing.content_dir)),
+        |resolver, root| resolver.with_source_root(working_dir.join(root)),
+    );
+    // Dev mode: no caching and a non-caching require() so files always load
+    // fresh; paths in error messages are relative to the project
+    let mut engine = Engine::builder(resolver)
+        .cache(Box::new(NoOpCache::new()))
+        .root_path(&working_dir)
+        .dev_mode(true)
+        .request_isolation(config.security.isolate_requests)
+        .build()?;
+    config.limits.apply(&mut engine)?;
+    config.apply_route_headers(&mut engine);
+    engine.set_escape_audit(config.dev.escape_audit);
+    engine.set_collapse_whitespace(config.build.collapse_whitespace);
+    config.apply_component_dirs(&engine);
+    config.apply_defines(&engine)?;
+    engine.set_deterministic(config.dev.deterministic)?;
 
     // Create KV manager for server-side persistence
     let data_dir = working_dir.join(&config.routing.data_dir);
     let kv_manager = Arc::new(
-        KVManager::new(&data_dir).expect("Failed to create KV manager")
+        KVManager::for_backend(config.kv.backend, &data_dir)
+            .expect("Failed to create KV manager")
     );
-    println!("KV store initialized at {}", data_dir.display());
+    match config.kv.backend {
+        KvBackend::Sqlite => println!("KV store initialized at {}", data_dir.display()),
+        KvBackend::Memory => println!("KV store initialized in memory"),
+    }
 
     // Register KV module on the engine's Lua instance
     // This ensures json AND kv modules are available in all Lua execution
     let factory = kv_manager.clone().factory();
+    if let Err(e) = config.flags.apply(&engine, &factory) {
+        eprintln!("Warning: Failed to set feature flags: {}", e);
+    }
+    let locales_dir = working_dir.join(&config.i18n.dir);
+    if let Err(e) = config.i18n.apply(&mut engine, &locales_dir) {
+        eprintln!("Warning: Failed to load translations: {}", e);
+    }
+    if let Err(e) = config.images.apply(&engine) {
+        eprintln!("Warning: Failed to configure image widths: {}", e);
+    }
+    if let Err(e) = config.auth.apply(&engine, &factory) {
+        eprintln!("Warning: Failed to register auth module: {}", e);
+    }
     if let Err(e) = luat::kv::register_kv_module(engine.lua(), factory) {
         eprintln!("Warning: Failed to register KV module: {}", e);
     }
@@ -115,6 +157,26 @@ pub async fn create_server(
     if let Err(e) = crate::extensions::register_http_module(engine.lua()) {
         eprintln!("Warning: Failed to register HTTP module: {}", e);
     }
+    if let Err(e) = crate::extensions::register_graphql_module(engine.lua()) {
+        eprintln!("Warning: Failed to register GraphQL module: {}", e);
+    }
+    if let Err(e) = crate::extensions::register_crypto_module(engine.lua()) {
+        eprintln!("Warning: Failed to register crypto module: {}", e);
+    }
+    if let Err(e) = crate::extensions::register_jwt_module(engine.lua()) {
+        eprintln!("Warning: Failed to register JWT module: {}", e);
+    }
+    #[cfg(feature = "grpc")]
+    if let Err(e) = crate::extensions::register_grpc_module(engine.lua()) {
+        eprintln!("Warning: Failed to register gRPC module: {}", e);
+    }
+    if let Err(e) = config.storage.apply(&engine) {
+        eprintln!("Warning: Failed to register storage module: {}", e);
+    }
+    #[cfg(feature = "webauthn")]
+    if let Err(e) = crate::extensions::register_webauthn_module(engine.lua(), kv_manager.clone().factory()) {
+        eprintln!("Warning: Failed to register WebAuthn module: {}", e);
+    }
 
     // Load app.html if it exists
     let app_html_path = working_dir.join(&config.routing.app_html);
@@ -135,7 +197,7 @@ pub async fn create_server(
     };
 
     let state = Arc::new(AppState {
-        engine: RwLock::new(engine),
+        engine: Arc::new(RwLock::new(engine)),
         reload_tx,
         config: config.clone(),
         router,
@@ -144,14 +206,42 @@ pub async fn create_server(
         kv_manager,
     });
 
+    // Catalogs are loaded into the engine once; reload them with every change
+    let i18n_state = state.clone();
+    let mut reloads = i18n_state.reload_tx.subscribe();
+    tokio::spawn(async move {
+        while !matches!(reloads.recv().await, Err(broadcast::error::RecvError::Closed)) {
+            let mut engine = i18n_state.engine.write().await;
+            if let Err(e) = i18n_state.config.i18n.apply(&mut *engine, &locales_dir) {
+                eprintln!("Warning: Failed to load translations: {}", e);
+            }
+        }
+    });
+
+    let images = ImageService::new(
+        &config.images,
+        vec![
+            ("/static".to_string(), working_dir.join(&config.routing.static_dir)),
+            ("/public".to_string(), working_dir.join(&config.dev.public_dir)),
+        ],
+        working_dir.join(&config.images.cache_dir),
+    );
+
     // Build the app with appropriate routes
     let app = Router::new()
         .route("/__livereload", get(livereload_handler))
+        .route(PREVIEW_PATH, get(preview_handler))
+        .route(CATALOG_PATH, get(catalog_handler))
         .nest_service("/public", ServeDir::new(&config.dev.public_dir))
         .nest_service("/static", ServeDir::new(&config.routing.static_dir))
+        .merge(images.router())
         .fallback(fallback_handler)
         .with_state(state);
 
+    if let Some(uds) = uds {
+        return serve_unix(app, uds).await;
+    }
+
     let listener = tokio::net::TcpListener::bind(addr).await?;
     axum::serve(listener, app).await?;
 
@@ -166,6 +256,87 @@ async fn livereload_handler(
     ws.on_upgrade(move |socket| handle_websocket(socket, rx))
 }
 
+/// Renders a single component with props from a JSON fixture.
+///
+/// Query parameters: `component` (required) and `props` (fixture path
+/// relative to the project root).
+async fn preview_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let Some(component) = params.get("component") else {
+        return error_page("Missing ?component= parameter");
+    };
+
+    let props = match params.get("props") {
+        Some(fixture) => {
+            let project_dir = match std::env::current_dir() {
+                Ok(dir) => dir,
+                Err(e) => return error_page(&format!("Fixture error: {}", e)),
+            };
+            match load_fixture(&project_dir, fixture) {
+                Ok(props) => props,
+                Err(e) => return error_page(&format!("Fixture error: {}", e)),
+            }
+        }
+        None => json!({}),
+    };
+
+    let engine = state.engine.read().await;
+    let context = match engine.to_value(props) {
+        Ok(ctx) => ctx,
+        Err(e) => return error_page(&format!("Context error: {}", e)),
+    };
+
+    let module_path = component_module_path(component);
+    let body_html = match engine.compile_entry(&module_path) {
+        Ok(module) => match engine.render(&module, &context) {
+            Ok(html) => html,
+            Err(e) => return engine_error_page(&engine, &e),
+        },
+        Err(e) => return engine_error_page(&engine, &e),
+    };
+
+    let head_assets = collect_head_assets(&state.config);
+    let app_html = state
+        .app_html_template
+        .as_deref()
+        .unwrap_or(DEFAULT_APP_HTML);
+    let title = format!("Preview: {}", component);
+    let full_html = wrap_with_app_html(app_html, &body_html, &title, &head_assets);
+    Html(inject_livereload_script(&full_html)).into_response()
+}
+
+/// Lists all components with live-rendered examples from their fixtures.
+async fn catalog_handler(State(state): State<Arc<AppState>>) -> Response {
+    let project_dir = match std::env::current_dir() {
+        Ok(dir) => dir,
+        Err(e) => return error_page(&format!("Catalog error: {}", e)),
+    };
+    let components = match discover_components(&project_dir, &state.config.routing.lib_dir) {
+        Ok(components) => components,
+        Err(e) => return error_page(&format!("Catalog error: {}", e)),
+    };
+
+    let engine = state.engine.read().await;
+    let body_html = render_catalog(&components, |component, fixture| {
+        let props = load_fixture(&project_dir, &fixture.path).map_err(|e| e.to_string())?;
+        let context = engine.to_value(props).map_err(|e| e.to_string())?;
+        let module = engine
+            .compile_entry(&component_module_path(&component.module_path))
+            .map_err(|e| e.to_string())?;
+        engine.render(&module, &context).map_err(|e| e.to_string())
+    });
+
+    let head_assets = collect_head_assets(&state.config);
+    let app_html = state
+        .app_html_template
+        .as_deref()
+        .unwrap_or(DEFAULT_APP_HTML);
+    let full_html = wrap_with_app_html(app_html, &body_html, "Components", &head_assets);
+    Html(inject_livereload_script(&full_html)).into_response()
+}
+
 /// Main fallback handler that routes requests
 async fn fallback_handler(
     State(state): State<Arc<AppState>>,
@@ -225,6 +396,17 @@ async fn fallback_handler(
             // Handle route using unified engine.respond_async()
             return handle_route(&state, route_match.route, route_match.params.clone(), luat_request).await;
         }
+
+        // No route matched: render the app's 404 page
+        let headers_map: HashMap<String, String> = headers
+            .iter()
+            .filter_map(|(k, v)| v.to_str().ok().map(|v| (k.to_string(), v.to_string())))
+            .collect();
+        let luat_request = to_luat_request(&path, &method, query, None, headers_map.clone());
+        let engine = state.engine.read().await;
+        let response = engine.respond_not_found_async(&luat_request).await;
+        drop(engine);
+        return luat_response_to_axum(response, &state, &headers_map);
     }
 
     // Fall back to simplified routing
@@ -232,7 +414,7 @@ async fn fallback_handler(
 }
 
 /// Convert CLI Route to Engine Route for use with engine.respond()
-fn cli_route_to_engine_route(
+pub(crate) fn cli_route_to_engine_route(
     cli_route: &Route,
     params: &[(String, String)],
     routes_dir: &PathBuf,
@@ -306,89 +488,23 @@ fn to_luat_request(
 /// Convert LuatResponse to axum Response
 fn luat_response_to_axum(
     response: LuatResponse,
-    state: &AppState,
+    state: &Arc<AppState>,
     request_headers: &HashMap<String, String>,
 ) -> Response {
+    let head = response.head().cloned().unwrap_or_default();
     match response {
-        LuatResponse::Html { status, mut headers, body } => {
-            let is_fragment = headers.remove("x-luat-fragment").is_some()
-                || headers.remove("X-Luat-Fragment").is_some();
-
-            // Check for HTMX boosted navigation (hx-boost="true")
-            let is_htmx_boosted = request_headers
-                .get("hx-boosted")
-                .map(|v| v == "true")
-                .unwrap_or(false);
-
-            // Extract title from response headers (set by setContext("view_title", ...))
-            let title = headers
-                .remove("x-luat-title")
-                .unwrap_or_else(|| "Luat App".to_string());
-
-            // Collect head assets
-            let head_assets = collect_head_assets(&state.config);
-
-            // Wrap with app.html shell
-            let app_html = state
-                .app_html_template
-                .as_deref()
-                .unwrap_or(DEFAULT_APP_HTML);
-
-            // Decide how to render based on request type
-            let (full_html, include_livereload, extra_headers) = if is_fragment {
-                // Fragment: return body only, include title header if set
-                let mut extra = Vec::new();
-                if title != "Luat App" {
-                    extra.push(("x-luat-title".to_string(), title.clone()));
-                }
-                (body, false, extra)
-            } else if is_htmx_boosted {
-                // HTMX Boosted: return body only, add HX-Title header for document.title update
-                (body, false, vec![("HX-Title".to_string(), title)])
-            } else {
-                // Full page: wrap with app.html shell, title goes in <title> tag
-                (wrap_with_app_html(app_html, &body, &title, &head_assets), true, vec![])
-            };
-
-            let html_with_livereload = if include_livereload {
-                inject_livereload_script(&full_html)
-            } else {
-                full_html
-            };
-
-            let status_code = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
-            let mut builder = axum::http::Response::builder().status(status_code);
-
-            // Add remaining response headers
-            for (key, value) in headers {
-                builder = builder.header(key, value);
-            }
-            // Add extra headers (HX-Title, x-luat-title for fragments)
-            for (key, value) in extra_headers {
-                builder = builder.header(key, value);
-            }
-            builder = builder.header("content-type", "text/html; charset=utf-8");
-
-            builder
-                .body(Body::from(html_with_livereload))
-                .unwrap_or_else(|_| {
-                    (StatusCode::INTERNAL_SERVER_ERROR, "Failed to build response").into_response()
-                })
+        LuatResponse::Html { status, headers, body, .. } => {
+            html_response(status, headers, body, head, Vec::new(), state, request_headers)
+        }
+        LuatResponse::Stream { status, headers, body, deferred, .. } => {
+            html_response(status, headers, body, head, deferred, state, request_headers)
         }
         LuatResponse::Json { status, headers, body } => {
-            let status_code = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
-            let mut builder = axum::http::Response::builder().status(status_code);
-
-            for (key, value) in headers {
-                builder = builder.header(key, value);
-            }
-            builder = builder.header("content-type", "application/json");
-
-            builder
-                .body(Body::from(serde_json::to_string(&body).unwrap_or_default()))
-                .unwrap_or_else(|_| {
-                    (StatusCode::INTERNAL_SERVER_ERROR, "Failed to build response").into_response()
-                })
+            let body = luat::encode_json_body(&body, &headers);
+            body_response(status, headers, Body::from(body), "application/json")
+        }
+        LuatResponse::Bytes { status, headers, body } => {
+            body_response(status, headers, Body::from(body), "application/octet-stream")
         }
         LuatResponse::Redirect { status, location } => {
             let status_code = StatusCode::from_u16(status).unwrap_or(StatusCode::FOUND);
@@ -398,15 +514,128 @@ fn luat_response_to_axum(
                 .body(Body::empty())
                 .unwrap()
         }
-        LuatResponse::Error { status: _, message } => {
-            error_page(&message)
+        LuatResponse::Error { status, message } => {
+            DevErrorPage::from_message(message).with_status(status).into_response()
         }
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, "Unsupported response").into_response(),
+    }
+}
+
+/// Build an API response, using `default_content_type` unless `headers` sets one;
+/// `luat serve` builds its API responses with it too
+pub(crate) fn body_response(
+    status: u16,
+    headers: HashMap<String, String>,
+    body: Body,
+    default_content_type: &str,
+) -> Response {
+    let status_code = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
+    let has_content_type = headers
+        .keys()
+        .any(|key| key.eq_ignore_ascii_case("content-type"));
+    let mut builder = axum::http::Response::builder().status(status_code);
+
+    for (key, value) in headers {
+        // Header values can't hold newlines, so they separate several cookies
+        if key.eq_ignore_ascii_case("set-cookie") {
+            for cookie in value.split('\n') {
+                builder = builder.header(&key, cookie);
+            }
+        } else {
+            builder = builder.header(key, value);
+        }
+    }
+    // A 204 carries no body to describe
+    if !has_content_type && status_code != StatusCode::NO_CONTENT {
+        builder = builder.header("content-type", default_content_type);
+    }
+
+    builder
+        .body(body)
+        .unwrap_or_else(|_| {
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to build response").into_response()
+        })
+}
+
+/// Build an HTML response, streaming deferred values after the shell when present
+fn html_response(
+    status: u16,
+    mut headers: HashMap<String, String>,
+    body: String,
+    head: luat::PageHead,
+    deferred: Vec<String>,
+    state: &Arc<AppState>,
+    request_headers: &HashMap<String, String>,
+) -> Response {
+    let is_fragment = headers.remove("x-luat-fragment").is_some()
+        || headers.remove("X-Luat-Fragment").is_some();
+
+    // Check for HTMX boosted navigation (hx-boost="true")
+    let is_htmx_boosted = request_headers
+        .get("hx-boosted")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+
+    // Title and meta tags from seo.set() (or setPageContext("view_title", ...))
+    let title = head.title.clone().unwrap_or_else(|| "Luat App".to_string());
+
+    // Collect head assets
+    let head_assets = format!("{}{}", head.to_html(), collect_head_assets(&state.config));
+
+    // Wrap with app.html shell
+    let app_html = state
+        .app_html_template
+        .as_deref()
+        .unwrap_or(DEFAULT_APP_HTML);
+
+    // Decide how to render based on request type
+    let (full_html, include_livereload, extra_headers) = if is_fragment {
+        // Fragment: return body only
+        (body, false, vec![])
+    } else if is_htmx_boosted {
+        // HTMX Boosted: return body only, add HX-Title header for document.title update
+        (body, false, vec![("HX-Title".to_string(), title)])
+    } else {
+        // Full page: wrap with app.html shell, title goes in <title> tag
+        (wrap_with_app_html(app_html, &body, &html_escape(&title), &head_assets), true, vec![])
+    };
+
+    let html_with_livereload = if include_livereload {
+        inject_livereload_script(&full_html)
+    } else {
+        full_html
+    };
+
+    let status_code = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
+    let mut builder = axum::http::Response::builder().status(status_code);
+
+    // Add remaining response headers
+    for (key, value) in headers {
+        builder = builder.header(key, value);
+    }
+    // Add extra headers (HX-Title for boosted navigation)
+    for (key, value) in extra_headers {
+        builder = builder.header(key, value);
     }
+    builder = builder.header("content-type", "text/html; charset=utf-8");
+
+    let body = if deferred.is_empty() {
+        Body::from(html_with_livereload)
+    } else {
+        let (head, tail) = split_at_body_end(&html_with_livereload);
+        deferred_body(state.engine.clone().read_owned(), head, deferred, tail, None)
+    };
+
+    builder
+        .body(body)
+        .unwrap_or_else(|_| {
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to build response").into_response()
+        })
 }
 
 /// Handle any route (API or page) using engine.respond()
 async fn handle_route(
-    state: &AppState,
+    state: &Arc<AppState>,
     route: &Route,
     params: Vec<(String, String)>,
     request: LuatRequest,
@@ -414,16 +643,76 @@ async fn handle_route(
     // Convert CLI route to engine route
     let engine_route = cli_route_to_engine_route(route, &params, &state.routes_dir);
 
+    let cors = engine_route.is_api_route() && state.config.cors.is_enabled();
+    if cors && cors::is_preflight(&request.method, &request.headers) {
+        return cors::preflight(&state.config.cors, &request.headers);
+    }
+
+    let request_info = RequestInfo {
+        method: request.method.clone(),
+        path: request.path.clone(),
+        route: Some(route.pattern.clone()),
+    };
+
+    let app_html = state.app_html_template.as_deref().unwrap_or(DEFAULT_APP_HTML);
+    let mut early_flush = early_flush_head(state.config.routing.early_flush_ms, app_html, &engine_route, &request);
+    if let Some(early) = early_flush.as_mut() {
+        early.headers = state.engine.read().await.route_headers(&request.path);
+    }
+    let request_headers = request.headers.clone();
+    let render = respond_route(state.clone(), engine_route, request, request_info);
+    let mut response = match flush_early(render, early_flush, |response| response).await {
+        EarlyFlush::Ready(response) | EarlyFlush::Flushed(response) => response,
+    };
+    // Let cross-origin callers read the response, error details included
+    if cors {
+        cors::apply(&state.config.cors, &request_headers, &mut response);
+    }
+    response
+}
+
+/// Responds to a request for `route` with the engine, or with the error page.
+async fn respond_route(
+    state: Arc<AppState>,
+    engine_route: luat::router::Route,
+    request: LuatRequest,
+    request_info: RequestInfo,
+) -> Response {
     // Keep a reference to request headers for response handling
     let request_headers = request.headers.clone();
 
     // Use engine.respond() for unified handling - it handles both API and page routes
     let engine = state.engine.read().await;
+    let result = crash::with_request(request_info.clone(), engine.respond_async_audited(&engine_route, &request))
+        .await
+        .map(|(response, report)| {
+            if !report.is_empty() {
+                tracing::info!("Escape audit for {} {}\n{}", request.method, request.path, report);
+            }
+            response
+        });
 
-    match engine.respond_async(&engine_route, &request).await {
-        Ok(response) => luat_response_to_axum(response, state, &request_headers),
-        Err(e) => error_page(&format!("Error: {}", e)),
-    }
+    let e = match result {
+        Ok(response) => return luat_response_to_axum(response, &state, &request_headers),
+        Err(e) => e,
+    };
+    let map_line = |file: &str, line: usize| template_line(&engine, file, line);
+    let crash_report = if crash::is_crash(&e) {
+        crash::save(&CrashReport::render_error(&e, Some(request_info.clone()), &map_line))
+    } else {
+        None
+    };
+    DevErrorPage::from_error(&e, &map_line)
+        .with_request(request_info)
+        .with_crash_report(crash_report)
+        .into_response()
+}
+
+/// Maps a line of the Lua code generated for `file` back to the template.
+fn template_line(engine: &Engine<FileSystemResolver>, file: &str, line: usize) -> Option<usize> {
+    let source = std::fs::read_to_string(file).ok()?;
+    let module = engine.compile_template_string(file, &source).ok()?;
+    module.source_map.as_ref()?.lookup(line)
 }
 
 /// Handle simplified routing (direct file-to-URL mapping)
@@ -468,34 +757,18 @@ async fn handle_simplified_route(state: &AppState, path: &str) -> Response {
                 let html_with_livereload = inject_livereload_script(&full_html);
                 Html(html_with_livereload).into_response()
             }
-            Err(e) => error_page(&format!("Render error: {}", e)),
+            Err(e) => engine_error_page(&engine, &e),
         },
-        Err(e) => error_page(&format!("Compile error: {}", e)),
+        Err(e) => engine_error_page(&engine, &e),
     }
 }
 
 fn error_page(message: &str) -> Response {
-    Html(format!(
-        r#"<!DOCTYPE html>
-<html>
-<head>
-    <title>Error - Luat</title>
-    <style>
-        body {{ font-family: system-ui, sans-serif; padding: 2rem; background: #1a1a2e; color: #eee; }}
-        .error {{ background: #16213e; border-left: 4px solid #e94560; padding: 1rem; border-radius: 4px; }}
-        pre {{ background: #0f0f1a; padding: 1rem; overflow-x: auto; border-radius: 4px; }}
-    </style>
-</head>
-<body>
-    <h1>Error</h1>
-    <div class="error">
-        <pre>{}</pre>
-    </div>
-</body>
-</html>"#,
-        html_escape(message)
-    ))
-    .into_response()
+    DevErrorPage::from_message(message).into_response()
+}
+
+fn engine_error_page(engine: &Engine<FileSystemResolver>, error: &luat::LuatError) -> Response {
+    DevErrorPage::from_error(error, &|file, line| template_line(engine, file, line)).into_response()
 }
 
 /// Creates a redirect response (reserved for future use).
@@ -508,7 +781,7 @@ fn redirect_response(url: &str) -> Response {
         .unwrap()
 }
 
-fn html_escape(s: &str) -> String {
+pub(crate) fn html_escape(s: &str) -> String {
     s.replace('&', "&amp;")
         .replace('<', "&lt;")
         .replace('>', "&gt;")
@@ -517,7 +790,7 @@ fn html_escape(s: &str) -> String {
 }
 
 /// Collect head assets (CSS and JS files from public directory)
-fn collect_head_assets(config: &Config) -> String {
+pub(crate) fn collect_head_assets(config: &Config) -> String {
     let mut head = String::new();
     let public_dir = std::path::Path::new(&config.dev.public_dir);
 
@@ -630,13 +903,32 @@ impl Clone for Config {
                 host: self.dev.host.clone(),
                 templates_dir: self.dev.templates_dir.clone(),
                 public_dir: self.dev.public_dir.clone(),
+                escape_audit: self.dev.escape_audit,
+                deterministic: self.dev.deterministic,
             },
             build: crate::config::BuildConfig {
                 output_dir: self.build.output_dir.clone(),
                 bundle_format: self.build.bundle_format.clone(),
+                sri: self.build.sri,
+                encrypt: self.build.encrypt,
+                collapse_whitespace: self.build.collapse_whitespace,
+                chunked: self.build.chunked,
             },
             frontend: self.frontend.clone(),
             routing: self.routing.clone(),
+            page_cache: self.page_cache.clone(),
+            limits: self.limits.clone(),
+            security: self.security.clone(),
+            cors: self.cors.clone(),
+            routes: self.routes.clone(),
+            dependencies: self.dependencies.clone(),
+            generators: self.generators.clone(),
+            kv: self.kv.clone(),
+            flags: self.flags.clone(),
+            i18n: self.i18n.clone(),
+            storage: self.storage.clone(),
+            images: self.images.clone(),
+            auth: self.auth.clone(),
         }
     }
 }
@@ -0,0 +1,386 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! On-the-fly image resizing for the `img()` helper.
+//!
+//! `GET /__img/static/hero.jpg?w=800&format=webp&q=70` serves
+//! `static/hero.jpg` scaled down to 800 pixels wide. Only the `[images]
+//! widths` are accepted, so the variants of an image stay bounded; images
+//! narrower than the width are not scaled up. `format` is `webp`, `jpeg` or
+//! `png`; without it, browsers that accept WebP get WebP and the others the
+//! source format. `q` sets the JPEG and WebP quality (default: `[images]
+//! quality`); WebP at quality 100 is lossless. Sources larger than
+//! [`MAX_SOURCE_DIMENSION`] pixels on a side are refused before decoding.
+//!
+//! Variants are cached on disk under `[images] cache_dir`, in a directory per
+//! source and keyed by the source's size and modification time. Writing a
+//! variant for a changed source removes the variants of its older versions.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use axum::{
+    extract::{Path as UrlPath, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use image::codecs::{jpeg::JpegEncoder, png::PngEncoder};
+use image::{imageops::FilterType, DynamicImage, ImageFormat, ImageReader, Limits};
+use luat::extensions::IMAGE_ENDPOINT;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::config::ImagesConfig;
+
+/// Largest width or height of a source image that is decoded.
+pub const MAX_SOURCE_DIMENSION: u32 = 12_000;
+
+/// Largest allocation decoding a source image may make.
+const MAX_DECODE_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Counter that keeps concurrent cache writes apart.
+static CACHE_WRITES: AtomicU64 = AtomicU64::new(0);
+
+/// Format of a resized image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Jpeg,
+    Png,
+    Webp,
+}
+
+impl OutputFormat {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "jpeg" | "jpg" => Some(Self::Jpeg),
+            "png" => Some(Self::Png),
+            "webp" => Some(Self::Webp),
+            _ => None,
+        }
+    }
+
+    /// Picks WebP when the browser accepts it, otherwise the source format
+    /// (PNG for sources other than JPEG).
+    fn negotiate(accept: Option<&str>, source: ImageFormat) -> Self {
+        if accept.is_some_and(|accept| accept.contains("image/webp")) {
+            Self::Webp
+        } else if source == ImageFormat::Jpeg {
+            Self::Jpeg
+        } else {
+            Self::Png
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::Webp => "image/webp",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+            Self::Webp => "webp",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ImageQuery {
+    w: u32,
+    format: Option<String>,
+    q: Option<u8>,
+}
+
+/// Serves resized variants of the images under a set of URL prefixes.
+#[derive(Debug, Clone)]
+pub struct ImageService {
+    /// URL prefixes (e.g. `/static`) and the directories they map to.
+    roots: Vec<(String, PathBuf)>,
+    widths: Vec<u32>,
+    quality: u8,
+    cache_dir: PathBuf,
+}
+
+impl ImageService {
+    /// Creates the service for `roots`, caching variants in `cache_dir`.
+    pub fn new(config: &ImagesConfig, roots: Vec<(String, PathBuf)>, cache_dir: PathBuf) -> Self {
+        Self {
+            roots,
+            widths: config.widths.clone(),
+            quality: config.quality.clamp(1, 100),
+            cache_dir,
+        }
+    }
+
+    /// Returns a router serving `/__img/*path`.
+    pub fn router<S: Clone + Send + Sync + 'static>(self) -> Router<S> {
+        Router::new()
+            .route(&format!("{}/*path", IMAGE_ENDPOINT), get(image_handler))
+            .with_state(Arc::new(self))
+    }
+
+    /// Maps a URL path below the endpoint to a source file, refusing paths
+    /// that leave their root.
+    fn source(&self, path: &str) -> Option<PathBuf> {
+        let path = format!("/{}", path.trim_start_matches('/'));
+        self.roots.iter().find_map(|(prefix, dir)| {
+            let relative = path.strip_prefix(prefix.as_str())?.strip_prefix('/')?;
+            if relative.split('/').any(|part| part.is_empty() || part == ".." || part.contains('\\')) {
+                return None;
+            }
+            let file = dir.join(relative);
+            file.is_file().then_some(file)
+        })
+    }
+
+    /// Returns the variant of `source`, from the cache when present.
+    fn variant(&self, source: &Path, width: u32, format: OutputFormat, quality: u8) -> anyhow::Result<Vec<u8>> {
+        let metadata = fs::metadata(source)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let dir = self.cache_dir.join(hex_prefix(&Sha256::digest(source.display().to_string())));
+        let version = hex_prefix(&Sha256::digest(format!("{}|{}", metadata.len(), modified)));
+        let cached = dir.join(format!("{}-{}-{:?}-{}.{}", version, width, format, quality, format.extension()));
+        if let Ok(bytes) = fs::read(&cached) {
+            return Ok(bytes);
+        }
+
+        let image = decode(source)?;
+        let image = if width < image.width() {
+            image.resize(width, u32::MAX, FilterType::Lanczos3)
+        } else {
+            image
+        };
+        let bytes = encode(&image, format, quality)?;
+        match write_cache(&cached, &bytes) {
+            Ok(()) => prune_cache(&dir, &version),
+            Err(e) => tracing::warn!("Failed to cache {}: {}", cached.display(), e),
+        }
+        Ok(bytes)
+    }
+}
+
+/// Returns the first 16 hex digits of a digest.
+fn hex_prefix(digest: &[u8]) -> String {
+    digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decodes `source`, refusing images beyond the decoding limits.
+fn decode(source: &Path) -> anyhow::Result<DynamicImage> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_BYTES);
+
+    let mut reader = ImageReader::open(source)?.with_guessed_format()?;
+    reader.limits(limits);
+    Ok(reader.decode()?)
+}
+
+/// Removes the variants in a source's cache directory that belong to
+/// another version of the source.
+fn prune_cache(dir: &Path, version: &str) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        // Leave other writers' temporary files alone
+        if name.starts_with(version) || name.ends_with(".tmp") {
+            continue;
+        }
+        if let Err(e) = fs::remove_file(entry.path()) {
+            tracing::warn!("Failed to remove stale variant {}: {}", entry.path().display(), e);
+        }
+    }
+}
+
+/// Encodes `image` in `format`; `quality` applies to JPEG and WebP.
+fn encode(image: &DynamicImage, format: OutputFormat, quality: u8) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    match format {
+        OutputFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, quality))?,
+        OutputFormat::Png => image.write_with_encoder(PngEncoder::new(&mut bytes))?,
+        OutputFormat::Webp => {
+            let (width, height) = (image.width(), image.height());
+            let alpha = image.color().has_alpha();
+            let pixels = if alpha { image.to_rgba8().into_raw() } else { image.to_rgb8().into_raw() };
+            let encoder = if alpha {
+                webp::Encoder::from_rgba(&pixels, width, height)
+            } else {
+                webp::Encoder::from_rgb(&pixels, width, height)
+            };
+            let encoded = if quality >= 100 {
+                encoder.encode_lossless()
+            } else {
+                encoder.encode(f32::from(quality))
+            };
+            bytes.extend_from_slice(&encoded);
+        }
+    }
+    Ok(bytes)
+}
+
+/// Writes a cache entry through a temporary file, so readers never see a
+/// partial one.
+fn write_cache(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp = path.with_extension(format!(
+        "{}-{}.tmp",
+        std::process::id(),
+        CACHE_WRITES.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&temp, bytes)?;
+    fs::rename(&temp, path)
+}
+
+async fn image_handler(
+    State(service): State<Arc<ImageService>>,
+    UrlPath(path): UrlPath<String>,
+    Query(query): Query<ImageQuery>,
+    headers: HeaderMap,
+) -> Response {
+    if !service.widths.contains(&query.w) {
+        return (StatusCode::BAD_REQUEST, format!("Width {} is not in [images] widths", query.w)).into_response();
+    }
+    let Some(source) = service.source(&path) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let source_format = match ImageFormat::from_path(&source) {
+        Ok(format @ (ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP | ImageFormat::Gif)) => format,
+        _ => return StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response(),
+    };
+
+    let negotiated = matches!(query.format.as_deref(), None | Some("auto"));
+    let format = if negotiated {
+        let accept = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok());
+        OutputFormat::negotiate(accept, source_format)
+    } else {
+        match query.format.as_deref().and_then(OutputFormat::parse) {
+            Some(format) => format,
+            None => return (StatusCode::BAD_REQUEST, "Unknown image format").into_response(),
+        }
+    };
+    let quality = query.q.unwrap_or(service.quality).clamp(1, 100);
+
+    let width = query.w;
+    let result = tokio::task::spawn_blocking(move || service.variant(&source, width, format, quality)).await;
+    let bytes = match result {
+        Ok(Ok(bytes)) => bytes,
+        Ok(Err(e)) => {
+            return (StatusCode::UNPROCESSABLE_ENTITY, format!("Cannot resize image: {}", e)).into_response()
+        }
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    let mut response = (
+        [
+            (header::CONTENT_TYPE, format.content_type()),
+            (header::CACHE_CONTROL, "public, max-age=3600"),
+        ],
+        bytes,
+    )
+        .into_response();
+    if negotiated {
+        response
+            .headers_mut()
+            .insert(header::VARY, header::HeaderValue::from_static("Accept"));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn service(dir: &Path) -> ImageService {
+        ImageService::new(
+            &ImagesConfig::default(),
+            vec![("/static".to_string(), dir.join("static"))],
+            dir.join("cache"),
+        )
+    }
+
+    #[test]
+    fn test_source_stays_in_root() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("static/img")).unwrap();
+        fs::write(dir.path().join("static/img/a.png"), b"").unwrap();
+        fs::write(dir.path().join("secret.png"), b"").unwrap();
+        let service = service(dir.path());
+
+        assert_eq!(service.source("static/img/a.png"), Some(dir.path().join("static/img/a.png")));
+        assert_eq!(service.source("static/../secret.png"), None);
+        assert_eq!(service.source("static//img/a.png"), None);
+        assert_eq!(service.source("public/img/a.png"), None);
+        assert_eq!(service.source("static/img/missing.png"), None);
+    }
+
+    #[test]
+    fn test_variant_resizes_and_caches() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("static")).unwrap();
+        let source = dir.path().join("static/wide.png");
+        image::RgbImage::new(40, 20).save(&source).unwrap();
+        let service = service(dir.path());
+
+        let bytes = service.variant(&source, 10, OutputFormat::Png, 80).unwrap();
+        let resized = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((resized.width(), resized.height()), (10, 5));
+        let variants = || {
+            fs::read_dir(dir.path().join("cache"))
+                .unwrap()
+                .flatten()
+                .map(|source| fs::read_dir(source.path()).unwrap().count())
+                .sum::<usize>()
+        };
+        assert_eq!(variants(), 1);
+
+        // Never scaled up
+        let bytes = service.variant(&source, 640, OutputFormat::Webp, 80).unwrap();
+        assert_eq!(image::load_from_memory(&bytes).unwrap().width(), 40);
+        assert_eq!(service.variant(&source, 640, OutputFormat::Webp, 80).unwrap(), bytes);
+        assert_eq!(variants(), 2);
+
+        // A changed source replaces the variants of the old one
+        image::RgbImage::new(30, 30).save(&source).unwrap();
+        let bytes = service.variant(&source, 10, OutputFormat::Png, 80).unwrap();
+        assert_eq!(image::load_from_memory(&bytes).unwrap().height(), 10);
+        assert_eq!(variants(), 1);
+    }
+
+    #[test]
+    fn test_decode_refuses_oversized_sources() {
+        let dir = TempDir::new().unwrap();
+        let source = dir.path().join("tall.png");
+        image::GrayImage::new(1, MAX_SOURCE_DIMENSION + 1).save(&source).unwrap();
+        assert!(decode(&source).is_err());
+    }
+
+    #[test]
+    fn test_negotiate_format() {
+        assert_eq!(OutputFormat::negotiate(Some("image/avif,image/webp,*/*"), ImageFormat::Jpeg), OutputFormat::Webp);
+        assert_eq!(OutputFormat::negotiate(Some("*/*"), ImageFormat::Jpeg), OutputFormat::Jpeg);
+        assert_eq!(OutputFormat::negotiate(None, ImageFormat::Gif), OutputFormat::Png);
+        assert_eq!(OutputFormat::parse("JPG"), Some(OutputFormat::Jpeg));
+        assert_eq!(OutputFormat::parse("avif"), None);
+    }
+}
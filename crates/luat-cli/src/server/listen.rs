@@ -0,0 +1,343 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Listening socket setup for `luat serve` and `luat dev`.
+//!
+//! - **Socket activation**: when started by systemd with `LISTEN_FDS`, the
+//!   first inherited socket is used instead of binding a new one. It must be
+//!   a TCP stream socket.
+//! - **`SO_REUSEPORT`**: several `luat serve` processes can bind the same
+//!   port; the kernel spreads connections across them. Start the new release,
+//!   then stop the old one — it finishes in-flight requests before exiting.
+//! - **Unix domain sockets**: `--uds /run/luat.sock` listens on a socket file
+//!   for deployments behind nginx or caddy. A stale socket file is replaced,
+//!   the file mode can be set with `--uds-mode` (applied before the socket
+//!   appears at its path), and the file is removed on shutdown.
+
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use axum::Router;
+use socket2::{Domain, Protocol, Socket, Type};
+
+/// First file descriptor passed by systemd socket activation.
+#[cfg(unix)]
+const SD_LISTEN_FDS_START: i32 = 3;
+
+/// Backlog for sockets bound by `luat serve`.
+const LISTEN_BACKLOG: i32 = 1024;
+
+/// Number of sockets systemd passed, as read by [`claim_activation`].
+static ACTIVATION_FDS: OnceLock<Option<usize>> = OnceLock::new();
+
+/// Parses the systemd `LISTEN_PID` / `LISTEN_FDS` values.
+///
+/// Returns the number of inherited sockets, or `None` if they were not meant
+/// for the process with id `pid`.
+pub fn listen_fds(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> Option<usize> {
+    let listen_pid: u32 = listen_pid?.trim().parse().ok()?;
+    if listen_pid != pid {
+        return None;
+    }
+    listen_fds?.trim().parse().ok().filter(|&n| n > 0)
+}
+
+/// Reads and removes the systemd socket activation variables.
+///
+/// Must run before any other thread starts (i.e. before the Tokio runtime),
+/// as changing the environment is not thread-safe. The variables are removed
+/// so child processes (e.g. build tools) do not try to claim the same socket.
+pub fn claim_activation() {
+    let count = listen_fds(
+        std::env::var("LISTEN_PID").ok().as_deref(),
+        std::env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    );
+    std::env::remove_var("LISTEN_PID");
+    std::env::remove_var("LISTEN_FDS");
+    std::env::remove_var("LISTEN_FDNAMES");
+    let _ = ACTIVATION_FDS.set(count);
+}
+
+/// Takes the listening socket passed by systemd, if any.
+///
+/// Uses the variables read by [`claim_activation`]. Fails if the inherited
+/// socket is not a TCP stream socket, leaving it open.
+#[cfg(unix)]
+pub fn inherited_listener() -> anyhow::Result<Option<TcpListener>> {
+    use std::mem::ManuallyDrop;
+    use std::os::unix::io::FromRawFd;
+
+    let Some(count) = ACTIVATION_FDS.get().copied().flatten() else {
+        return Ok(None);
+    };
+    if count > 1 {
+        tracing::warn!("systemd passed {} sockets; using the first one", count);
+    }
+
+    // SAFETY: systemd guarantees that fds 3..3+LISTEN_FDS are open sockets
+    // owned by this process, and the variables were checked above. The
+    // socket is only closed once it is known to be ours to serve on.
+    let socket = ManuallyDrop::new(unsafe { Socket::from_raw_fd(SD_LISTEN_FDS_START) });
+    let is_tcp = socket.r#type()? == Type::STREAM && socket.local_addr()?.as_socket().is_some();
+    if !is_tcp {
+        anyhow::bail!("The socket passed by systemd is not a TCP stream socket");
+    }
+    let listener: TcpListener = ManuallyDrop::into_inner(socket).into();
+    listener.set_nonblocking(true)?;
+    Ok(Some(listener))
+}
+
+/// Socket activation is only supported on Unix.
+#[cfg(not(unix))]
+pub fn inherited_listener() -> anyhow::Result<Option<TcpListener>> {
+    Ok(None)
+}
+
+/// Binds a non-blocking TCP listener on `addr` (e.g. `0.0.0.0:3000`).
+///
+/// With `reuse_port`, `SO_REUSEPORT` is set so other processes can bind the
+/// same address.
+pub fn bind_tcp(addr: &str, reuse_port: bool) -> anyhow::Result<TcpListener> {
+    let addr: SocketAddr = addr
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Could not resolve address: {}", addr))?;
+
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+    if reuse_port {
+        #[cfg(unix)]
+        socket.set_reuse_port(true)?;
+        #[cfg(not(unix))]
+        anyhow::bail!("--reuse-port is only supported on Unix");
+    }
+    socket.bind(&addr.into())?;
+    socket.listen(LISTEN_BACKLOG)?;
+    socket.set_nonblocking(true)?;
+
+    Ok(socket.into())
+}
+
+/// A Unix domain socket to listen on instead of TCP.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnixSocketOptions {
+    /// Socket file path.
+    pub path: PathBuf,
+    /// File mode applied after binding (e.g. `0o660`); the umask applies otherwise.
+    pub mode: Option<u32>,
+}
+
+/// Parses an octal file mode such as `660` or `0o660`.
+pub fn parse_socket_mode(mode: &str) -> Result<u32, String> {
+    let digits = mode.trim_start_matches("0o");
+    u32::from_str_radix(digits, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o777)
+        .ok_or_else(|| format!("invalid octal file mode: {}", mode))
+}
+
+/// Removes the socket file when dropped.
+#[cfg(unix)]
+struct SocketFileGuard(PathBuf);
+
+#[cfg(unix)]
+impl Drop for SocketFileGuard {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Binds a Unix domain socket, replacing a stale socket file left by a
+/// previous run.
+///
+/// With a mode, the socket is bound in a private directory next to the path,
+/// given its mode there and then moved into place, so no one can connect
+/// before the mode applies. Fails if another process is still accepting on
+/// the socket, or if the path exists but is not a socket.
+#[cfg(unix)]
+pub fn bind_unix(options: &UnixSocketOptions) -> anyhow::Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+
+    let path = &options.path;
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            anyhow::bail!("{} exists and is not a socket", path.display());
+        }
+        // Only a refused connection shows nobody listens; e.g. EACCES doesn't
+        match std::os::unix::net::UnixStream::connect(path) {
+            Ok(_) => anyhow::bail!("{} is in use by another process", path.display()),
+            Err(err) if err.kind() == std::io::ErrorKind::ConnectionRefused => std::fs::remove_file(path)?,
+            Err(err) => anyhow::bail!("can't tell whether {} is in use: {}", path.display(), err),
+        }
+    }
+
+    let Some(mode) = options.mode else {
+        return Ok(tokio::net::UnixListener::bind(path)?);
+    };
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => std::path::Path::new("."),
+    };
+    let staging = parent.join(format!(".luat-sock-{}", std::process::id()));
+    std::fs::DirBuilder::new().mode(0o700).create(&staging)?;
+    let staged = staging.join("sock");
+    let bound = tokio::net::UnixListener::bind(&staged).and_then(|listener| {
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(mode))?;
+        std::fs::rename(&staged, path)?;
+        Ok(listener)
+    });
+    let _ = std::fs::remove_file(&staged);
+    let _ = std::fs::remove_dir(&staging);
+    Ok(bound?)
+}
+
+/// Serves `app` on a Unix domain socket until Ctrl+C or `SIGTERM`.
+///
+/// In-flight connections are drained before returning, and the socket file
+/// is removed.
+#[cfg(unix)]
+pub async fn serve_unix(app: Router, options: &UnixSocketOptions) -> anyhow::Result<()> {
+    use hyper_util::rt::{TokioExecutor, TokioIo};
+    use hyper_util::server::conn::auto::Builder;
+    use hyper_util::server::graceful::GracefulShutdown;
+    use hyper_util::service::TowerToHyperService;
+
+    let listener = bind_unix(options)?;
+    let _guard = SocketFileGuard(options.path.clone());
+    let graceful = GracefulShutdown::new();
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = match accepted {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        tracing::warn!("Failed to accept connection: {}", e);
+                        continue;
+                    }
+                };
+                let service = TowerToHyperService::new(app.clone());
+                let builder = Builder::new(TokioExecutor::new());
+                let conn = builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+                let conn = graceful.watch(conn.into_owned());
+                tokio::spawn(async move {
+                    if let Err(e) = conn.await {
+                        tracing::debug!("Connection error: {}", e);
+                    }
+                });
+            }
+            _ = &mut shutdown => break,
+        }
+    }
+
+    drop(listener);
+    graceful.shutdown().await;
+    Ok(())
+}
+
+/// Unix domain sockets are only supported on Unix.
+#[cfg(not(unix))]
+pub async fn serve_unix(_app: Router, _options: &UnixSocketOptions) -> anyhow::Result<()> {
+    anyhow::bail!("--uds is only supported on Unix")
+}
+
+/// Resolves when the process is asked to stop (Ctrl+C or `SIGTERM`).
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                term.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listen_fds() {
+        assert_eq!(listen_fds(Some("42"), Some("1"), 42), Some(1));
+        assert_eq!(listen_fds(Some("42"), Some("2"), 42), Some(2));
+        assert_eq!(listen_fds(Some("41"), Some("1"), 42), None);
+        assert_eq!(listen_fds(Some("42"), Some("0"), 42), None);
+        assert_eq!(listen_fds(None, Some("1"), 42), None);
+        assert_eq!(listen_fds(Some("42"), None, 42), None);
+    }
+
+    #[test]
+    fn test_parse_socket_mode() {
+        assert_eq!(parse_socket_mode("660"), Ok(0o660));
+        assert_eq!(parse_socket_mode("0o600"), Ok(0o600));
+        assert!(parse_socket_mode("999").is_err());
+        assert!(parse_socket_mode("1777").is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_bind_unix_replaces_stale_socket() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let options = UnixSocketOptions {
+            path: dir.path().join("luat.sock"),
+            mode: Some(0o660),
+        };
+
+        let listener = bind_unix(&options).unwrap();
+        let mode = std::fs::metadata(&options.path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o660);
+        // The staging directory is gone
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // Still accepting: refuse to take over
+        assert!(bind_unix(&options).is_err());
+
+        // Stale file from a process that went away: replaced
+        drop(listener);
+        assert!(bind_unix(&options).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bind_unix_refuses_regular_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("not-a-socket");
+        std::fs::write(&path, "data").unwrap();
+
+        let options = UnixSocketOptions { path: path.clone(), mode: None };
+        assert!(bind_unix(&options).is_err());
+        assert!(path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reuse_port_allows_shared_port() {
+        let first = bind_tcp("127.0.0.1:0", true).unwrap();
+        let addr = first.local_addr().unwrap().to_string();
+
+        assert!(bind_tcp(&addr, true).is_ok());
+        assert!(bind_tcp(&addr, false).is_err());
+    }
+}
@@ -9,15 +9,48 @@
 //!
 //! # Components
 //!
+//! - `catalog`: Component catalog page for `luat dev`
+//! - `cors`: CORS headers and preflight responses for API routes
+//! - `crash`: Crash reports written to `.luat/crash/`
+//! - `dev_error`: Error pages for `luat dev`
 //! - `http`: HTTP server using Axum
+//! - `images`: Resized image variants for `img()`
 //! - `livereload`: WebSocket-based hot reload
+//! - `listen`: Listening sockets (socket activation, `SO_REUSEPORT`, Unix sockets)
 //! - `loader`: Template loading and caching
+//! - `page_cache`: Rule-based response caching for `luat serve`
+//! - `preview`: Single-component previews with JSON fixtures
+//! - `security`: Security headers and CSP nonces for `luat serve`
+//! - `stream`: Streaming of deferred load values
+//! - `workers`: Per-core render workers for `luat serve`
 
 /// Request body parsing for form data and JSON.
 pub mod body_parser;
+/// Component catalog with fixture-rendered examples.
+pub mod catalog;
+/// CORS for `+server.lua` routes.
+pub mod cors;
+/// Local crash reports for panics and failed renders.
+pub mod crash;
+/// Styled error pages with source excerpts for the development server.
+pub mod dev_error;
 /// HTTP server implementation using Axum.
 pub mod http;
+/// On-the-fly image resizing with a disk cache.
+pub mod images;
 /// Live reload WebSocket server.
 pub mod livereload;
+/// Listening sockets: socket activation, `SO_REUSEPORT` and Unix sockets.
+pub mod listen;
 /// Template loading and resolution.
 pub mod loader;
+/// KV-backed response cache for the production server.
+pub mod page_cache;
+/// Component previews rendered from JSON fixtures.
+pub mod preview;
+/// Security headers middleware with per-request CSP nonces.
+pub mod security;
+/// Out-of-order streaming of deferred load values.
+pub mod stream;
+/// Render worker count for `luat serve`.
+pub mod workers;
@@ -0,0 +1,319 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Rule-based response caching for the production server.
+//!
+//! Rules come from `[[page_cache.rules]]` in `luat.toml`. A `GET` request
+//! whose path matches a rule is looked up in the KV store before the engine
+//! runs; on a miss, a successful HTML response is stored with the rule's TTL.
+//! Requests carrying the rule's bypass cookie always skip the cache.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use globset::{GlobBuilder, GlobMatcher};
+use luat::kv::{KVStore, ListOptions, PutOptions};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::config::PageCacheRule;
+
+/// KV namespace used for cached pages.
+pub const PAGE_CACHE_NAMESPACE: &str = "__page_cache";
+
+/// Stands in for the per-request CSP nonce in stored pages.
+const NONCE_PLACEHOLDER: &str = "%luat.nonce%";
+
+/// A cached response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedPage {
+    /// HTTP status code.
+    pub status: u16,
+    /// Content-Type header value.
+    pub content_type: String,
+    /// Other headers of the response, such as those set by the route.
+    pub headers: Vec<(String, String)>,
+    /// Response body.
+    pub body: Vec<u8>,
+}
+
+/// Page cache backed by a KV store.
+pub struct PageCache {
+    rules: Vec<(GlobMatcher, PageCacheRule)>,
+    store: Arc<dyn KVStore>,
+}
+
+impl PageCache {
+    /// Creates a page cache from config rules.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a rule's path is not a valid glob pattern.
+    pub fn new(rules: &[PageCacheRule], store: Arc<dyn KVStore>) -> anyhow::Result<Self> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                let matcher = GlobBuilder::new(&rule.path)
+                    .literal_separator(true)
+                    .build()?
+                    .compile_matcher();
+                Ok((matcher, rule.clone()))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self { rules, store })
+    }
+
+    /// Returns the rule that applies to a request, if any.
+    ///
+    /// Only `GET` and `HEAD` requests are cacheable (a `HEAD` request shares
+    /// the `GET` entry), and a request carrying the rule's bypass cookie is
+    /// never served from cache.
+    pub fn rule_for(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &HashMap<String, String>,
+    ) -> Option<&PageCacheRule> {
+        if method != "GET" && method != "HEAD" {
+            return None;
+        }
+
+        let (_, rule) = self.rules.iter().find(|(matcher, _)| matcher.is_match(path))?;
+
+        if let Some(ref cookie_name) = rule.bypass_cookie {
+            if has_cookie(headers, cookie_name) {
+                return None;
+            }
+        }
+
+        Some(rule)
+    }
+
+    /// Builds the cache key from the path, query string and `vary` headers.
+    pub fn cache_key(
+        rule: &PageCacheRule,
+        path: &str,
+        query: &str,
+        headers: &HashMap<String, String>,
+    ) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(path.as_bytes());
+        hasher.update(b"?");
+        hasher.update(query.as_bytes());
+        for name in &rule.vary {
+            let value = header_value(headers, name).unwrap_or_default();
+            hasher.update(b"\n");
+            hasher.update(name.to_ascii_lowercase().as_bytes());
+            hasher.update(b":");
+            hasher.update(value.as_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Looks up a cached page. Storage errors are treated as a miss.
+    ///
+    /// `nonce` is the current request's CSP nonce; it replaces the nonce the
+    /// page was rendered with so inline scripts keep working.
+    pub fn get(&self, key: &str, nonce: Option<&str>) -> Option<CachedPage> {
+        let entry = self.store.get_with_metadata(key).ok()??;
+        let metadata = entry.metadata.unwrap_or_default();
+        let nonce_aware = metadata["nonce"].as_bool().unwrap_or(false);
+        let body = match (nonce_aware, String::from_utf8(entry.value)) {
+            (true, Ok(html)) => html.replace(NONCE_PLACEHOLDER, nonce.unwrap_or_default()).into_bytes(),
+            (_, Ok(html)) => html.into_bytes(),
+            (_, Err(e)) => e.into_bytes(),
+        };
+
+        Some(CachedPage {
+            status: metadata["status"].as_u64().unwrap_or(200) as u16,
+            content_type: metadata["content_type"]
+                .as_str()
+                .unwrap_or("text/html; charset=utf-8")
+                .to_string(),
+            headers: serde_json::from_value(metadata["headers"].clone()).unwrap_or_default(),
+            body,
+        })
+    }
+
+    /// Stores a page for the rule's TTL.
+    ///
+    /// The request's CSP nonce, if any, is swapped for a placeholder so a
+    /// later hit can substitute its own nonce.
+    pub fn put(&self, key: &str, rule: &PageCacheRule, page: &CachedPage, nonce: Option<&str>) {
+        let body = match (nonce, std::str::from_utf8(&page.body)) {
+            (Some(nonce), Ok(html)) => html.replace(nonce, NONCE_PLACEHOLDER).into_bytes(),
+            _ => page.body.clone(),
+        };
+
+        let options = PutOptions {
+            expiration: None,
+            expiration_ttl: Some(rule.ttl.max(1)),
+            metadata: Some(json!({
+                "status": page.status,
+                "content_type": page.content_type,
+                "headers": page.headers,
+                "nonce": nonce.is_some(),
+            })),
+        };
+
+        if let Err(e) = self.store.put(key, &body, options) {
+            tracing::warn!("Failed to store cached page: {}", e);
+        }
+    }
+
+    /// Removes every cached page, e.g. after a new bundle is deployed.
+    ///
+    /// Deleting shifts the keys a cursor points past, so each round lists
+    /// from the start again until no keys are left (or none can be deleted).
+    pub fn clear(&self) {
+        loop {
+            let page = match self.store.list(ListOptions::default()) {
+                Ok(page) => page,
+                Err(e) => {
+                    tracing::warn!("Failed to list cached pages: {}", e);
+                    return;
+                }
+            };
+            let mut deleted = false;
+            for key in &page.keys {
+                match self.store.delete(&key.name) {
+                    Ok(()) => deleted = true,
+                    Err(e) => tracing::warn!("Failed to remove cached page: {}", e),
+                }
+            }
+            if page.keys.is_empty() || !deleted {
+                return;
+            }
+        }
+    }
+}
+
+fn header_value<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+fn has_cookie(headers: &HashMap<String, String>, name: &str) -> bool {
+    header_value(headers, "cookie")
+        .map(|cookies| {
+            cookies
+                .split(';')
+                .filter_map(|pair| pair.split('=').next())
+                .any(|key| key.trim() == name)
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use luat::kv::MemoryKVStore;
+
+    fn rule(path: &str) -> PageCacheRule {
+        PageCacheRule {
+            path: path.to_string(),
+            ttl: 60,
+            vary: vec!["Accept-Language".to_string()],
+            bypass_cookie: Some("session".to_string()),
+        }
+    }
+
+    fn cache() -> PageCache {
+        PageCache::new(&[rule("/blog/**")], Arc::new(MemoryKVStore::new())).unwrap()
+    }
+
+    #[test]
+    fn test_rule_matching() {
+        let cache = cache();
+        let headers = HashMap::new();
+
+        assert!(cache.rule_for("GET", "/blog/hello", &headers).is_some());
+        assert!(cache.rule_for("GET", "/about", &headers).is_none());
+        assert!(cache.rule_for("HEAD", "/blog/hello", &headers).is_some());
+        assert!(cache.rule_for("POST", "/blog/hello", &headers).is_none());
+    }
+
+    #[test]
+    fn test_bypass_cookie() {
+        let cache = cache();
+        let mut headers = HashMap::new();
+        headers.insert("cookie".to_string(), "theme=dark; session=abc".to_string());
+
+        assert!(cache.rule_for("GET", "/blog/hello", &headers).is_none());
+    }
+
+    #[test]
+    fn test_cache_key_varies_by_header() {
+        let rule = rule("/blog/**");
+        let mut en = HashMap::new();
+        en.insert("accept-language".to_string(), "en".to_string());
+        let mut de = HashMap::new();
+        de.insert("accept-language".to_string(), "de".to_string());
+
+        assert_ne!(
+            PageCache::cache_key(&rule, "/blog/a", "", &en),
+            PageCache::cache_key(&rule, "/blog/a", "", &de)
+        );
+        assert_eq!(
+            PageCache::cache_key(&rule, "/blog/a", "", &en),
+            PageCache::cache_key(&rule, "/blog/a", "", &en)
+        );
+    }
+
+    #[test]
+    fn test_put_and_get() {
+        let cache = cache();
+        let rule = rule("/blog/**");
+        let page = CachedPage {
+            status: 200,
+            content_type: "text/html; charset=utf-8".to_string(),
+            headers: vec![("cache-control".to_string(), "public, max-age=60".to_string())],
+            body: b"<h1>Hello</h1>".to_vec(),
+        };
+
+        assert!(cache.get("key", None).is_none());
+        cache.put("key", &rule, &page, None);
+        assert_eq!(cache.get("key", None), Some(page));
+    }
+
+    #[test]
+    fn test_clear() {
+        let cache = cache();
+        let rule = rule("/blog/**");
+        let page = CachedPage {
+            status: 200,
+            content_type: "text/html; charset=utf-8".to_string(),
+            headers: Vec::new(),
+            body: b"<h1>Hello</h1>".to_vec(),
+        };
+
+        // More pages than one list call returns
+        for i in 0..1500 {
+            cache.put(&format!("page-{}", i), &rule, &page, None);
+        }
+        cache.clear();
+        assert!(cache.get("page-0", None).is_none());
+        assert!(cache.get("page-1499", None).is_none());
+        assert!(cache.store.list(ListOptions::default()).unwrap().keys.is_empty());
+    }
+
+    #[test]
+    fn test_nonce_is_replaced_on_hit() {
+        let cache = cache();
+        let rule = rule("/blog/**");
+        let page = CachedPage {
+            status: 200,
+            content_type: "text/html; charset=utf-8".to_string(),
+            headers: Vec::new(),
+            body: br#"<script nonce="aaaa">go()</script>"#.to_vec(),
+        };
+
+        cache.put("key", &rule, &page, Some("aaaa"));
+        let hit = cache.get("key", Some("bbbb")).unwrap();
+        assert_eq!(hit.body, br#"<script nonce="bbbb">go()</script>"#.to_vec());
+    }
+}
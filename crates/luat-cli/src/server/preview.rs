@@ -0,0 +1,104 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Component previews for the development server.
+//!
+//! `GET /__luat/preview?component=Card&props=fixtures/card.json` renders a
+//! single component with props read from a JSON fixture, wrapped in the app
+//! shell with live reload. Editing the component or the fixture reloads the
+//! page.
+//!
+//! Components can be given as a bare name (`Card`, looked up in
+//! `lib/components/`), a lib path (`lib/forms/Input`) or a project path
+//! (`src/lib/components/Card.luat`).
+
+use std::path::Path;
+
+use serde_json::Value;
+
+/// URL path of the preview route.
+pub const PREVIEW_PATH: &str = "/__luat/preview";
+
+/// Maps a component argument to the module path passed to the engine.
+pub fn component_module_path(component: &str) -> String {
+    let path = component.trim_start_matches("./");
+    let path = path.strip_prefix("src/").unwrap_or(path);
+    let path = if path.contains('/') {
+        path.to_string()
+    } else {
+        format!("lib/components/{}", path)
+    };
+
+    if path.ends_with(".luat") || path.ends_with(".lua") {
+        path
+    } else {
+        format!("{}.luat", path)
+    }
+}
+
+/// Reads a JSON fixture with the component's props.
+///
+/// The fixture must be a JSON object inside `project_dir`.
+pub fn load_fixture(project_dir: &Path, fixture: &str) -> anyhow::Result<Value> {
+    let path = project_dir.join(fixture).canonicalize()?;
+    if !path.starts_with(project_dir.canonicalize()?) {
+        anyhow::bail!("Fixture must be inside the project: {}", fixture);
+    }
+
+    let props: Value = serde_json::from_str(&std::fs::read_to_string(&path)?)
+        .map_err(|e| anyhow::anyhow!("Invalid fixture {}: {}", fixture, e))?;
+    if !props.is_object() {
+        anyhow::bail!("Fixture must contain a JSON object: {}", fixture);
+    }
+    Ok(props)
+}
+
+/// Builds the preview URL path for a component and optional fixture.
+pub fn preview_url(component: &str, fixture: Option<&str>) -> String {
+    let mut query = form_urlencoded::Serializer::new(String::new());
+    query.append_pair("component", component);
+    if let Some(fixture) = fixture {
+        query.append_pair("props", fixture);
+    }
+    format!("{}?{}", PREVIEW_PATH, query.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_component_module_path() {
+        assert_eq!(component_module_path("Card"), "lib/components/Card.luat");
+        assert_eq!(component_module_path("lib/forms/Input"), "lib/forms/Input.luat");
+        assert_eq!(
+            component_module_path("src/lib/components/Card.luat"),
+            "lib/components/Card.luat"
+        );
+    }
+
+    #[test]
+    fn test_load_fixture() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("card.json"), r#"{"title": "Hello"}"#).unwrap();
+        std::fs::write(dir.path().join("list.json"), "[1, 2]").unwrap();
+
+        assert_eq!(
+            load_fixture(dir.path(), "card.json").unwrap(),
+            json!({"title": "Hello"})
+        );
+        assert!(load_fixture(dir.path(), "list.json").is_err());
+        assert!(load_fixture(dir.path(), "missing.json").is_err());
+    }
+
+    #[test]
+    fn test_preview_url() {
+        assert_eq!(
+            preview_url("Card", Some("fixtures/card one.json")),
+            "/__luat/preview?component=Card&props=fixtures%2Fcard+one.json"
+        );
+        assert_eq!(preview_url("Card", None), "/__luat/preview?component=Card");
+    }
+}
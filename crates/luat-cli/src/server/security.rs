@@ -0,0 +1,106 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Security headers middleware for the production server.
+//!
+//! Adds the headers configured in `[security]` to every response. When the
+//! CSP contains a `{nonce}` placeholder, a fresh nonce is generated per
+//! request and stored in the request extensions as [`CspNonce`] so handlers
+//! can pass the same value to the engine.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::config::SecurityConfig;
+
+/// Per-request CSP nonce, available in request extensions.
+#[derive(Debug, Clone)]
+pub struct CspNonce(pub String);
+
+/// Generates a random 128-bit nonce, hex encoded.
+///
+/// Fails when the OS has no randomness to offer; a predictable nonce would
+/// defeat the CSP, so callers must not fall back to a fixed value.
+pub fn generate_nonce() -> Result<String, getrandom::Error> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes)?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Axum middleware that applies the configured security headers.
+pub async fn security_headers(
+    State(config): State<Arc<SecurityConfig>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let nonce = match config.uses_nonce().then(generate_nonce).transpose() {
+        Ok(nonce) => nonce,
+        Err(e) => {
+            tracing::error!("Failed to read OS randomness for CSP nonce: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    if let Some(ref nonce) = nonce {
+        request.extensions_mut().insert(CspNonce(nonce.clone()));
+    }
+
+    let mut response = next.run(request).await;
+
+    let csp = config
+        .csp
+        .as_ref()
+        .map(|csp| csp.replace("{nonce}", nonce.as_deref().unwrap_or_default()));
+
+    let headers = [
+        ("content-security-policy", csp),
+        ("strict-transport-security", config.hsts.clone()),
+        ("x-frame-options", config.frame_options.clone()),
+        ("referrer-policy", config.referrer_policy.clone()),
+        (
+            "x-content-type-options",
+            config.content_type_options.then(|| "nosniff".to_string()),
+        ),
+    ];
+
+    for (name, value) in headers {
+        let Some(value) = value else { continue };
+        match HeaderValue::from_str(&value) {
+            Ok(value) => {
+                response
+                    .headers_mut()
+                    .insert(HeaderName::from_static(name), value);
+            }
+            Err(_) => tracing::warn!("Invalid value for security header {}", name),
+        }
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_nonce() {
+        let a = generate_nonce().unwrap();
+        let b = generate_nonce().unwrap();
+        assert_eq!(a.len(), 32);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_uses_nonce() {
+        let mut config = SecurityConfig::default();
+        assert!(!config.uses_nonce());
+        config.csp = Some("script-src 'nonce-{nonce}'".to_string());
+        assert!(config.uses_nonce());
+    }
+}
@@ -0,0 +1,363 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Streaming of page responses.
+//!
+//! Pages with deferred load values are streamed out of order: the page shell
+//! (everything up to `</body>`) is flushed immediately. Each deferred value is
+//! then resolved on the engine and appended as its own chunk, followed by the
+//! remainder of the document.
+//!
+//! With `[routing] early_flush_ms` set, a page whose loads take longer than
+//! that gets the static start of the app shell (see [`shell_head`]) flushed
+//! ahead of it, so the browser can fetch the stylesheets and scripts linked
+//! there while the server is still loading. See [`flush_early`].
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::ops::Deref;
+use std::time::Duration;
+
+use axum::body::{Body, Bytes};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use futures_util::StreamExt;
+use luat::router::Route;
+use luat::{Engine, LuatRequest, ResourceResolver};
+use tokio::sync::mpsc;
+
+use super::http::html_escape;
+
+/// Splits a full HTML document before its closing `</body>` tag.
+///
+/// Returns the document unchanged with an empty tail when there is no `</body>`.
+pub fn split_at_body_end(html: &str) -> (String, String) {
+    let end = html
+        .as_bytes()
+        .windows(b"</body>".len())
+        .rposition(|window| window.eq_ignore_ascii_case(b"</body>"));
+    match end {
+        Some(pos) => (html[..pos].to_string(), html[pos..].to_string()),
+        None => (html.to_string(), String::new()),
+    }
+}
+
+/// Builds a streaming body: `head`, one chunk per resolved deferred value, then `tail`.
+///
+/// Deferred values are resolved in order on a background task, with the
+/// engine `engine` resolves to; it must be the engine that rendered the page.
+/// If the client goes away, the remaining values are discarded so they do
+/// not linger in the Lua registry. `nonce` is the request's CSP nonce, if
+/// any, applied to the inline chunk scripts.
+pub fn deferred_body<F, E, R>(
+    engine: F,
+    head: String,
+    deferred: Vec<String>,
+    tail: String,
+    nonce: Option<String>,
+) -> Body
+where
+    F: Future<Output = E> + Send + 'static,
+    E: Deref<Target = Engine<R>> + Send + 'static,
+    R: ResourceResolver,
+{
+    let (tx, rx) = mpsc::channel::<String>(8);
+
+    tokio::spawn(async move {
+        let engine = engine.await;
+
+        if tx.send(head).await.is_err() {
+            let _ = engine.discard_deferred(&deferred);
+            return;
+        }
+
+        for (i, id) in deferred.iter().enumerate() {
+            let chunk = match engine.resolve_deferred(id, nonce.as_deref()) {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    tracing::warn!("Failed to resolve deferred value {}: {}", id, e);
+                    continue;
+                }
+            };
+
+            if tx.send(chunk).await.is_err() {
+                let _ = engine.discard_deferred(&deferred[i + 1..]);
+                return;
+            }
+        }
+
+        let _ = tx.send(tail).await;
+    });
+
+    Body::from_stream(futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (Ok::<_, Infallible>(chunk), rx))
+    }))
+}
+
+/// Returns the part of an app shell before its first `%luat.*%`
+/// placeholder, which is the same for every page.
+pub fn shell_head(app_html: &str) -> &str {
+    match app_html.find("%luat.") {
+        Some(pos) => &app_html[..pos],
+        None => app_html,
+    }
+}
+
+/// What [`flush_early`] sends ahead of a slow page.
+pub struct EarlyFlushHead {
+    /// The static start of the app shell, see [`shell_head`].
+    pub head: String,
+    /// Response headers known before the page renders, such as the
+    /// `luat.toml [routes]` headers of the request path.
+    pub headers: HashMap<String, String>,
+    /// How long to wait for the page before flushing `head`.
+    pub after: Duration,
+}
+
+/// Returns what to flush ahead of a slow page when `[routing] early_flush_ms`
+/// is set and the request gets a full page rendered from `app_html`.
+///
+/// The returned `headers` are empty; callers add the ones they know.
+pub fn early_flush_head(
+    early_flush_ms: Option<u64>,
+    app_html: &str,
+    route: &Route,
+    request: &LuatRequest,
+) -> Option<EarlyFlushHead> {
+    let after = early_flush_ms?;
+    if request.method != "GET" || route.is_api_route() || request.headers.contains_key("hx-request") {
+        return None;
+    }
+    let head = shell_head(app_html);
+    (!head.is_empty()).then(|| EarlyFlushHead {
+        head: head.to_string(),
+        headers: HashMap::new(),
+        after: Duration::from_millis(after),
+    })
+}
+
+/// Outcome of [`flush_early`].
+pub enum EarlyFlush<T> {
+    /// The page finished in time and is sent as usual.
+    Ready(T),
+    /// The shell head was sent; the response streams the rest of the page.
+    Flushed(Response),
+}
+
+/// Runs `render` on a background task and waits up to `early.after` for it.
+/// Without `early`, the page is simply awaited.
+///
+/// When the page takes longer, the response is committed to `200 OK` with
+/// `early.headers`: `early.head` is sent right away, then the rest of the
+/// page once `render` finishes, converted with `into_response` and without
+/// the head it starts with. A redirect at that point is followed with a
+/// `<meta http-equiv="refresh">`, and an error page is marked `noindex`;
+/// other headers the page sets, such as cookies, can no longer be sent.
+pub async fn flush_early<T, F>(
+    render: F,
+    early: Option<EarlyFlushHead>,
+    into_response: fn(T) -> Response,
+) -> EarlyFlush<T>
+where
+    T: Send + 'static,
+    F: Future<Output = T> + Send + 'static,
+{
+    let Some(EarlyFlushHead { head, headers, after }) = early else {
+        return EarlyFlush::Ready(render.await);
+    };
+    let mut task = tokio::spawn(render);
+    match tokio::time::timeout(after, &mut task).await {
+        Ok(Ok(result)) => return EarlyFlush::Ready(result),
+        Ok(Err(_)) => return EarlyFlush::Flushed(render_failed()),
+        Err(_) => {}
+    }
+
+    let (tx, rx) = mpsc::channel::<Bytes>(8);
+    tokio::spawn(async move {
+        // The page keeps rendering if the client goes away, so its load
+        // functions aren't cut off halfway
+        let _ = tx.send(Bytes::from(head.clone())).await;
+        let response = match task.await {
+            Ok(result) => into_response(result),
+            Err(_) => render_failed(),
+        };
+        send_rest(response, head.as_bytes(), &tx).await;
+    });
+
+    let body = Body::from_stream(futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (Ok::<_, Infallible>(chunk), rx))
+    }));
+    let mut builder = Response::builder();
+    for (name, value) in &headers {
+        builder = builder.header(name, value);
+    }
+    EarlyFlush::Flushed(
+        builder
+            .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+            .body(body)
+            .unwrap_or_else(|_| render_failed()),
+    )
+}
+
+fn render_failed() -> Response {
+    (StatusCode::INTERNAL_SERVER_ERROR, "Failed to render page").into_response()
+}
+
+/// Returns the position of the first ASCII case-insensitive match of `needle`.
+fn position_ignore_ascii_case(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window.eq_ignore_ascii_case(needle))
+}
+
+/// Sends what `response` adds to the already flushed `head`.
+async fn send_rest(response: Response, head: &[u8], tx: &mpsc::Sender<Bytes>) {
+    if response.status().is_redirection() {
+        if let Some(location) = response.headers().get(header::LOCATION).and_then(|l| l.to_str().ok()) {
+            let refresh = format!(
+                "<meta http-equiv=\"refresh\" content=\"0;url={}\">",
+                html_escape(location)
+            );
+            let _ = tx.send(Bytes::from(refresh)).await;
+        }
+        return;
+    }
+    if response.headers().contains_key(header::SET_COOKIE) {
+        tracing::warn!("Page set cookies after its shell was flushed early; they were not sent");
+    }
+    let status = response.status();
+    if !status.is_success() {
+        tracing::warn!("Page responded {} after its shell was flushed early with 200", status);
+        // Keep crawlers from indexing the error page under the 200
+        if tx.send(Bytes::from_static(b"<meta name=\"robots\" content=\"noindex\">")).await.is_err() {
+            return;
+        }
+    }
+
+    let mut body = response.into_body().into_data_stream();
+    let mut start = Vec::new();
+    while start.len() < head.len() {
+        match body.next().await {
+            Some(Ok(chunk)) => start.extend_from_slice(&chunk),
+            _ => break,
+        }
+    }
+    let rest = match start.strip_prefix(head) {
+        Some(rest) => rest,
+        // A page with a shell of its own, such as the dev error page: skip
+        // its head rather than sending a second one
+        None => match position_ignore_ascii_case(&start, b"<body") {
+            Some(pos) => &start[pos..],
+            None => &start,
+        },
+    };
+    if !rest.is_empty() && tx.send(Bytes::copy_from_slice(rest)).await.is_err() {
+        return;
+    }
+    while let Some(Ok(chunk)) = body.next().await {
+        if tx.send(chunk).await.is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_at_body_end() {
+        let (head, tail) = split_at_body_end("<html><BODY>hi</BODY></html>");
+        assert_eq!(head, "<html><BODY>hi");
+        assert_eq!(tail, "</BODY></html>");
+
+        // Lowercasing changes the length of some non-ASCII text
+        let (head, tail) = split_at_body_end("<body>İİİ</body>");
+        assert_eq!(head, "<body>İİİ");
+        assert_eq!(tail, "</body>");
+
+        let (head, tail) = split_at_body_end("<p>fragment</p>");
+        assert_eq!(head, "<p>fragment</p>");
+        assert!(tail.is_empty());
+    }
+
+    fn page(html: &'static str) -> Response {
+        Response::new(Body::from(html))
+    }
+
+    async fn body_text(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_shell_head() {
+        assert_eq!(shell_head("<head><link rel=\"stylesheet\"><title>%luat.title%</title>"), "<head><link rel=\"stylesheet\"><title>");
+        assert_eq!(shell_head("%luat.body%"), "");
+    }
+
+    fn early(head: &str, after: Duration) -> Option<EarlyFlushHead> {
+        Some(EarlyFlushHead { head: head.to_string(), headers: HashMap::new(), after })
+    }
+
+    #[tokio::test]
+    async fn test_flush_early_waits_for_fast_pages() {
+        let render = async { page("<head><title>Fast</title>") };
+        match flush_early(render, early("<head><title>", Duration::from_secs(5)), |r| r).await {
+            EarlyFlush::Ready(response) => assert_eq!(body_text(response).await, "<head><title>Fast</title>"),
+            EarlyFlush::Flushed(_) => panic!("fast page was flushed early"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flush_early_streams_slow_pages() {
+        let render = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            page("<head><title>Slow</title></head><body>done</body>")
+        };
+        let mut head = early("<head><title>", Duration::from_millis(10));
+        if let Some(head) = head.as_mut() {
+            head.headers.insert("cache-control".to_string(), "no-store".to_string());
+        }
+        let response = match flush_early(render, head, |r| r).await {
+            EarlyFlush::Flushed(response) => response,
+            EarlyFlush::Ready(_) => panic!("slow page was not flushed early"),
+        };
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["cache-control"], "no-store");
+        assert_eq!(body_text(response).await, "<head><title>Slow</title></head><body>done</body>");
+
+        let render = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Response::builder()
+                .status(StatusCode::SEE_OTHER)
+                .header(header::LOCATION, "/login?next=a&b")
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = match flush_early(render, early("<head>", Duration::from_millis(10)), |r| r).await {
+            EarlyFlush::Flushed(response) => response,
+            EarlyFlush::Ready(_) => panic!("slow redirect was not flushed early"),
+        };
+        assert_eq!(
+            body_text(response).await,
+            "<head><meta http-equiv=\"refresh\" content=\"0;url=/login?next=a&amp;b\">"
+        );
+
+        let render = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::from("<!DOCTYPE html><html><head><title>Error</title></head><BODY>oops</BODY></html>"))
+                .unwrap()
+        };
+        let response = match flush_early(render, early("<!DOCTYPE html><html><head><link>", Duration::from_millis(10)), |r| r).await {
+            EarlyFlush::Flushed(response) => response,
+            EarlyFlush::Ready(_) => panic!("slow error page was not flushed early"),
+        };
+        assert_eq!(
+            body_text(response).await,
+            "<!DOCTYPE html><html><head><link><meta name=\"robots\" content=\"noindex\"><BODY>oops</BODY></html>"
+        );
+    }
+}
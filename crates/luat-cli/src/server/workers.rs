@@ -0,0 +1,32 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Render workers for the production server.
+//!
+//! Each worker is an [`Engine`](luat::Engine) (and so its own Lua state) in
+//! a [`luat::EnginePool`], letting requests render in parallel across CPU
+//! cores. A request checks out an idle engine, waiting for one while all
+//! are busy.
+
+/// Resolves the `--workers` flag: `0` means one worker per CPU core.
+pub fn worker_count(requested: usize) -> usize {
+    if requested > 0 {
+        requested
+    } else {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_worker_count() {
+        assert_eq!(worker_count(3), 3);
+        assert!(worker_count(0) >= 1);
+    }
+}
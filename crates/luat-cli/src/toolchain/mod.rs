@@ -160,6 +160,17 @@ pub async fn is_tool_cached(tool: Tool, version: &str) -> ToolchainResult<bool>
     Ok(cached_tool.is_some())
 }
 
+/// Returns the cached executable for a tool version without downloading it.
+pub fn cached_tool(tool: Tool, version: &str) -> ToolchainResult<Option<ToolPath>> {
+    let manager = ToolchainManager::new()?;
+    manager.find_cached_tool(tool, version)
+}
+
+/// Returns the directory holding downloaded tools.
+pub fn cache_dir() -> ToolchainResult<PathBuf> {
+    ToolchainManager::get_cache_dir()
+}
+
 /// Returns the path to a tool executable, downloading it if necessary
 pub async fn ensure_tool(tool: Tool, version: &str) -> ToolchainResult<PathBuf> {
     let manager = ToolchainManager::new()?;
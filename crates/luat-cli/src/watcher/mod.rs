@@ -10,7 +10,7 @@
 //! # Features
 //!
 //! - Debounced file change events (750ms)
-//! - Filters for relevant file types (.luat, .lua)
+//! - Filters for relevant file types (.luat, .lua, .json and content files)
 //! - Recursive directory watching
 
 use notify::{RecommendedWatcher, RecursiveMode};
@@ -24,7 +24,6 @@ use std::time::Duration;
 /// Uses debouncing to prevent multiple rapid rebuilds and filters
 /// events to only trigger on relevant file types.
 pub struct FileWatcher {
-    #[allow(dead_code)]
     debouncer: Debouncer<RecommendedWatcher, RecommendedCache>,
     #[allow(dead_code)]
     rx: mpsc::Receiver<DebounceEventResult>,
@@ -41,7 +40,8 @@ impl FileWatcher {
     ///
     /// # File Types
     ///
-    /// Only `.luat` and `.lua` files trigger the callback.
+    /// Only `.luat`, `.lua` and `.json` files, and the `.md`, `.yaml` and
+    /// `.yml` files of content collections, trigger the callback.
     pub fn new<F>(path: String, base_path: PathBuf, on_change: F) -> anyhow::Result<Self>
     where
         F: Fn(Vec<PathBuf>) + Send + 'static,
@@ -53,13 +53,15 @@ impl FileWatcher {
             None,
             move |result: DebounceEventResult| {
                 if let Ok(events) = &result {
-                    // Collect changed paths with relevant extensions
+                    // Collect changed paths with relevant extensions (JSON for preview
+                    // fixtures and catalogs, Markdown and YAML for content collections,
+                    // TOML for catalogs)
                     let changed_paths: Vec<PathBuf> = events
                         .iter()
                         .flat_map(|e| e.paths.iter())
                         .filter(|p| {
                             let ext = p.extension().and_then(|e| e.to_str());
-                            matches!(ext, Some("luat") | Some("lua"))
+                            matches!(ext, Some("luat" | "lua" | "json" | "md" | "yaml" | "yml" | "toml"))
                         })
                         .map(|p| p.strip_prefix(&base_path).unwrap_or(p).to_path_buf())
                         .collect();
@@ -78,6 +80,12 @@ impl FileWatcher {
         Ok(Self { debouncer, rx })
     }
 
+    /// Watches an additional directory recursively with the same callback.
+    pub fn watch_path(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.debouncer.watch(path, RecursiveMode::Recursive)?;
+        Ok(())
+    }
+
     /// Starts the file watcher (no-op as watcher runs after construction).
     pub fn start(&mut self) -> anyhow::Result<()> {
         // The watcher is already running after new()
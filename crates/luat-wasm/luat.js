@@ -92,6 +92,7 @@ function createAPI() {
     removeTemplate,
     clearTemplates,
     render,
+    renderBytes,
     renderWithError,
     version,
     isInitialized: () => initialized,
@@ -170,6 +171,39 @@ function render(entry, context = {}) {
   return result;
 }
 
+/**
+ * Render a template and return the raw output as a Uint8Array.
+ * Use this for binary output or output that isn't valid UTF-8.
+ */
+function renderBytes(entry, context = {}) {
+  if (!initialized) {
+    throw new Error('Luat not initialized');
+  }
+
+  const contextJson = JSON.stringify(context);
+  const stack = Module.stackSave();
+  try {
+    const lenPtr = Module.stackAlloc(4);
+    const resultPtr = Module.ccall(
+      'luat_render_bytes',
+      'number',
+      ['string', 'string', 'number'],
+      [entry, contextJson, lenPtr]
+    );
+
+    if (resultPtr === 0) {
+      throw new Error(`Failed to render template: ${entry}`);
+    }
+
+    const len = Module.getValue(lenPtr, 'i32');
+    const result = Module.HEAPU8.slice(resultPtr, resultPtr + len);
+    Module._luat_free_bytes(resultPtr, len);
+    return result;
+  } finally {
+    Module.stackRestore(stack);
+  }
+}
+
 /**
  * Render a template with detailed error information.
  */
@@ -219,6 +253,7 @@ export default {
   removeTemplate,
   clearTemplates,
   render,
+  renderBytes,
   renderWithError,
   version,
 };
@@ -213,6 +213,57 @@ pub unsafe extern "C" fn luat_render(entry: *const c_char, context_json: *const
     })
 }
 
+/// Render a template and return the raw output bytes.
+/// Unlike luat_render, output containing NUL bytes or invalid UTF-8 is
+/// returned unchanged. The length is written to `out_len`; returns null on error.
+/// The caller must free the returned buffer with luat_free_bytes.
+///
+/// # Safety
+///
+/// - `entry` must be a valid pointer to a null-terminated UTF-8 string, or null.
+/// - `context_json` must be a valid pointer to a null-terminated UTF-8 JSON string, or null.
+/// - `out_len` must be a valid pointer to writable `usize` storage, or null.
+/// - The returned pointer must be freed by calling `luat_free_bytes` with the
+///   length written to `out_len`.
+#[no_mangle]
+pub unsafe extern "C" fn luat_render_bytes(
+    entry: *const c_char,
+    context_json: *const c_char,
+    out_len: *mut usize,
+) -> *mut u8 {
+    if entry.is_null() || context_json.is_null() || out_len.is_null() {
+        return std::ptr::null_mut();
+    }
+
+    let Ok(entry_str) = unsafe { CStr::from_ptr(entry) }.to_str() else {
+        return std::ptr::null_mut();
+    };
+    let Ok(context_str) = unsafe { CStr::from_ptr(context_json) }.to_str() else {
+        return std::ptr::null_mut();
+    };
+
+    let Ok(context_value) = serde_json::from_str::<serde_json::Value>(context_str) else {
+        return std::ptr::null_mut();
+    };
+
+    let bytes = ENGINE.with(|e| {
+        let engine_ref = e.borrow();
+        let engine = engine_ref.as_ref()?;
+        let module = engine.compile_entry(entry_str).ok()?;
+        let context = engine.to_value(context_value).ok()?;
+        engine.render_bytes(&module, &context).ok()
+    });
+
+    match bytes {
+        Some(bytes) => {
+            let bytes = bytes.into_boxed_slice();
+            unsafe { *out_len = bytes.len() };
+            Box::into_raw(bytes) as *mut u8
+        }
+        None => std::ptr::null_mut(),
+    }
+}
+
 /// Render a template and return detailed result as JSON.
 /// Returns JSON with { "success": bool, "html": string|null, "error": string|null }
 /// The caller must free the returned string with luat_free_string.
@@ -380,6 +431,21 @@ pub unsafe extern "C" fn luat_free_string(ptr: *mut c_char) {
     }
 }
 
+/// Free a buffer allocated by luat_render_bytes.
+///
+/// # Safety
+///
+/// - `ptr` must be either null or a valid pointer previously returned by
+///   `luat_render_bytes`, and `len` the length it wrote to `out_len`.
+/// - `ptr` must not have been freed before.
+/// - After calling this function, `ptr` is invalid and must not be used.
+#[no_mangle]
+pub unsafe extern "C" fn luat_free_bytes(ptr: *mut u8, len: usize) {
+    if !ptr.is_null() {
+        let _ = Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len));
+    }
+}
+
 /// Get the version of the Luat library.
 /// Returns a pointer to the version string. The caller must NOT free this string.
 #[no_mangle]
@@ -0,0 +1,231 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Accessibility lint rules.
+//!
+//! Every HTML template is checked when it is compiled, and problems are
+//! reported as [compile warnings](crate::warning):
+//!
+//! | Code | Problem |
+//! |------|---------|
+//! | `a11y-missing-alt` | `<img>`, `<area>` or `<input type="image">` without `alt` |
+//! | `a11y-click-events` | Click handler (`onclick`, `on:click`, `@click`, ...) on a non-interactive element without a `role` |
+//! | `a11y-missing-label` | `<input>`, `<select>` or `<textarea>` outside a `<label>` without `id`, `aria-label`, `aria-labelledby` or `title` |
+//! | `a11y-unknown-aria-attribute` | `aria-*` attribute not defined by WAI-ARIA 1.2 |
+//! | `a11y-unknown-role` | `role` value that is not a WAI-ARIA role |
+//!
+//! Elements with a spread attribute (`{...attrs}`) may get any attribute, so
+//! the rules about missing attributes skip them.
+
+use crate::ast::{Attribute, AttributeValue, Node, Span};
+use crate::warning::CompileWarning;
+
+/// Attributes defined by WAI-ARIA 1.2, without the `aria-` prefix.
+const ARIA_ATTRIBUTES: &[&str] = &[
+    "activedescendant", "atomic", "autocomplete", "braillelabel", "brailleroledescription", "busy",
+    "checked", "colcount", "colindex", "colindextext", "colspan", "controls", "current",
+    "describedby", "description", "details", "disabled", "dropeffect", "errormessage", "expanded",
+    "flowto", "grabbed", "haspopup", "hidden", "invalid", "keyshortcuts", "label", "labelledby",
+    "level", "live", "modal", "multiline", "multiselectable", "orientation", "owns", "placeholder",
+    "posinset", "pressed", "readonly", "relevant", "required", "roledescription", "rowcount",
+    "rowindex", "rowindextext", "rowspan", "selected", "setsize", "sort", "valuemax", "valuemin",
+    "valuenow", "valuetext",
+];
+
+/// Non-abstract WAI-ARIA 1.2 roles. `doc-*` and `graphics-*` roles are
+/// accepted as well.
+const ARIA_ROLES: &[&str] = &[
+    "alert", "alertdialog", "application", "article", "banner", "blockquote", "button", "caption",
+    "cell", "checkbox", "code", "columnheader", "combobox", "complementary", "contentinfo",
+    "definition", "deletion", "dialog", "directory", "document", "emphasis", "feed", "figure",
+    "form", "generic", "grid", "gridcell", "group", "heading", "img", "insertion", "link", "list",
+    "listbox", "listitem", "log", "main", "marquee", "math", "menu", "menubar", "menuitem",
+    "menuitemcheckbox", "menuitemradio", "meter", "navigation", "none", "note", "option",
+    "paragraph", "presentation", "progressbar", "radio", "radiogroup", "region", "row", "rowgroup",
+    "rowheader", "scrollbar", "search", "searchbox", "separator", "slider", "spinbutton", "status",
+    "strong", "subscript", "superscript", "switch", "tab", "table", "tablist", "tabpanel", "term",
+    "textbox", "time", "timer", "toolbar", "tooltip", "tree", "treegrid", "treeitem",
+];
+
+/// Elements users can already interact with.
+const INTERACTIVE_ELEMENTS: &[&str] = &[
+    "a", "audio", "button", "details", "embed", "iframe", "input", "label", "option", "select",
+    "summary", "textarea", "video",
+];
+
+/// `<input>` types that need no label.
+const UNLABELED_INPUT_TYPES: &[&str] = &["hidden", "submit", "reset", "button", "image"];
+
+/// Checks the elements of a template body against the accessibility rules.
+pub fn check(body: &[Node]) -> Vec<CompileWarning> {
+    let mut warnings = Vec::new();
+    check_nodes(body, false, &mut warnings);
+    warnings
+}
+
+fn check_nodes(nodes: &[Node], in_label: bool, warnings: &mut Vec<CompileWarning>) {
+    for node in nodes {
+        match node {
+            Node::ElementNode { tag, attributes, children, span } => {
+                check_element(tag, attributes, span, in_label, warnings);
+                check_nodes(children, in_label || tag == "label", warnings);
+            }
+            Node::IfBlock { then_branch, else_branch, .. }
+            | Node::SensitiveIfBlock { then_branch, else_branch, .. } => {
+                check_nodes(then_branch, in_label, warnings);
+                if let Some(else_nodes) = else_branch {
+                    check_nodes(else_nodes, in_label, warnings);
+                }
+            }
+            Node::EachBlock { body, empty, .. } | Node::SensitiveEachBlock { body, empty, .. } => {
+                check_nodes(body, in_label, warnings);
+                if let Some(empty_nodes) = empty {
+                    check_nodes(empty_nodes, in_label, warnings);
+                }
+            }
+            Node::AwaitBlock { pending, then_branch, catch_branch, .. } => {
+                check_nodes(pending, in_label, warnings);
+                for branch in [then_branch, catch_branch].into_iter().flatten() {
+                    check_nodes(branch, in_label, warnings);
+                }
+            }
+            Node::CustomBlock { children, .. } | Node::ComponentNode { children, .. } => {
+                check_nodes(children, in_label, warnings);
+            }
+            Node::Snippet { body, .. } => check_nodes(body, in_label, warnings),
+            _ => {}
+        }
+    }
+}
+
+fn check_element(
+    tag: &str,
+    attributes: &[Attribute],
+    span: &Span,
+    in_label: bool,
+    warnings: &mut Vec<CompileWarning>,
+) {
+    let mut warn = |code: &str, message: String| warnings.push(CompileWarning::new(code, message, span.clone()));
+    let has_spread = attributes.iter().any(|attr| matches!(attr, Attribute::Spread(_)));
+    let has = |name: &str| find_attribute(attributes, name).is_some() || has_spread;
+    let input_type = find_attribute(attributes, "type")
+        .and_then(static_value)
+        .map(str::to_ascii_lowercase);
+
+    if (matches!(tag, "img" | "area") || (tag == "input" && input_type.as_deref() == Some("image")))
+        && !has("alt")
+    {
+        warn("a11y-missing-alt", format!("<{}> should have an alt attribute", tag));
+    }
+
+    let has_click_handler = attributes
+        .iter()
+        .any(|attr| matches!(attr, Attribute::Named { name, .. } if is_click_handler(name)));
+    if has_click_handler && !INTERACTIVE_ELEMENTS.contains(&tag) && !has("role") {
+        warn(
+            "a11y-click-events",
+            format!(
+                "<{}> with a click handler is not reachable by keyboard; use <button>, or add a role, tabindex and key handler",
+                tag
+            ),
+        );
+    }
+
+    let needs_label = match tag {
+        "select" | "textarea" => true,
+        "input" => !input_type.as_deref().is_some_and(|t| UNLABELED_INPUT_TYPES.contains(&t)),
+        _ => false,
+    };
+    if needs_label && !in_label && !["id", "aria-label", "aria-labelledby", "title"].into_iter().any(has) {
+        warn(
+            "a11y-missing-label",
+            format!(
+                "<{}> should have a label: wrap it in <label>, give it an id for <label for>, or add aria-label",
+                tag
+            ),
+        );
+    }
+
+    for attr in attributes {
+        let Attribute::Named { name, value } = attr else {
+            continue;
+        };
+        let name = name.to_ascii_lowercase();
+        if let Some(aria) = name.strip_prefix("aria-") {
+            if !ARIA_ATTRIBUTES.contains(&aria) {
+                warn("a11y-unknown-aria-attribute", format!("Unknown ARIA attribute `{}`", name));
+            }
+        } else if name == "role" {
+            let roles = static_value(value).unwrap_or_default();
+            for role in roles.split_whitespace() {
+                let known = ARIA_ROLES.contains(&role) || role.starts_with("doc-") || role.starts_with("graphics-");
+                if !known {
+                    warn("a11y-unknown-role", format!("Unknown ARIA role `{}`", role));
+                }
+            }
+        }
+    }
+}
+
+fn find_attribute<'a>(attributes: &'a [Attribute], name: &str) -> Option<&'a AttributeValue> {
+    attributes.iter().find_map(|attr| match attr {
+        Attribute::Named { name: n, value } if n.eq_ignore_ascii_case(name) => Some(value),
+        _ => None,
+    })
+}
+
+fn static_value(value: &AttributeValue) -> Option<&str> {
+    match value {
+        AttributeValue::Static(text) => Some(text.as_str()),
+        _ => None,
+    }
+}
+
+/// Returns true for `onclick` and the click syntaxes of Svelte, Alpine.js
+/// and htmx (`on:click`, `@click.prevent`, `x-on:click`, `hx-on:click`).
+fn is_click_handler(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    if name == "onclick" {
+        return true;
+    }
+    let event = ["on:", "@", "x-on:", "hx-on:", "hx-on::"]
+        .iter()
+        .filter_map(|prefix| name.strip_prefix(prefix))
+        .next_back();
+    event.is_some_and(|event| event.split('.').next() == Some("click"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_template;
+
+    fn codes(source: &str) -> Vec<String> {
+        let ast = parse_template(source).unwrap();
+        check(&ast.body).into_iter().map(|warning| warning.code).collect()
+    }
+
+    #[test]
+    fn test_a11y_rules() {
+        assert_eq!(codes(r#"<img src="a.png">"#), ["a11y-missing-alt"]);
+        assert_eq!(codes(r#"<div onclick="go()">x</div>"#), ["a11y-click-events"]);
+        assert_eq!(codes(r#"<input type="text" name="q">"#), ["a11y-missing-label"]);
+        assert_eq!(codes(r#"<div aria-lable="x" role="buton">x</div>"#), ["a11y-unknown-aria-attribute", "a11y-unknown-role"]);
+
+        let warnings = check(&parse_template("<p>\n  <img src=\"a.png\">\n</p>").unwrap().body);
+        assert_eq!((warnings[0].span.line, warnings[0].span.column), (2, 3));
+    }
+
+    #[test]
+    fn test_a11y_valid() {
+        let source = r#"<img src="a.png" alt="">
+<button @click.prevent="go">Go</button>
+<div role="button" tabindex="0" on:click={go}>Go</div>
+<label>Name <input type="text"></label>
+<input id="email" type="email"><input type="hidden" name="t">
+<span {...attrs}></span><img {...image}>
+<nav aria-label="Main" role="navigation doc-toc"></nav>"#;
+        assert!(codes(source).is_empty(), "{:?}", codes(source));
+    }
+}
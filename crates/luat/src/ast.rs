@@ -12,6 +12,7 @@
 //! A LUAT template is represented as a [`TemplateAST`] containing:
 //! - Optional module-level script (`<script context="module">`)
 //! - Optional component script (`<script>`)
+//! - `{#use Name from "path"}` imports
 //! - A list of body [`Node`]s representing the template markup
 //! - Discovered component imports
 //!
@@ -20,8 +21,8 @@
 //! The [`Node`] enum represents all possible template constructs:
 //! - HTML elements and components
 //! - Text content and mustache expressions (`{expr}`)
-//! - Control flow blocks (`{#if}`, `{#each}`)
-//! - Special directives (`{@html}`, `{@local}`, `{@render}`)
+//! - Control flow blocks (`{#if}`, `{#each}`, `{#await}`)
+//! - Snippets (`{#snippet}`) and special directives (`{@html}`, `{@local}`, `{@render}`)
 
 use serde::{Deserialize, Serialize};
 
@@ -39,6 +40,8 @@ pub enum Node {
         attributes: Vec<Attribute>,
         /// Child nodes nested within this element.
         children: Vec<Node>,
+        /// Location of the element.
+        span: Span,
     },
     /// Plain text content between elements or expressions.
     TextNode {
@@ -57,7 +60,8 @@ pub enum Node {
         /// Child nodes within the comment (text and expressions).
         children: Vec<Node>,
     },
-    /// LUAT-specific comment `{! comment !}` that is stripped from output.
+    /// LUAT comment `{/* ... */}`, `{-- ... --}` or `{!-- ... --}` that is
+    /// stripped from output.
     LuatComment,
     /// Conditional block `{#if condition}...{/if}`.
     IfBlock {
@@ -81,6 +85,37 @@ pub enum Node {
         /// Optional nodes to render when the list is empty (`{:empty}`).
         empty: Option<Vec<Node>>,
     },
+    /// Async value block `{#await expr}...{:then value}...{:catch err}...{/await}`.
+    ///
+    /// The value may be a function or coroutine (run during the render), a
+    /// placeholder from `ctx.defer()` (still pending), or a plain value
+    /// (already resolved).
+    AwaitBlock {
+        /// The Lua expression producing the awaited value.
+        expression: Expression,
+        /// Nodes to render while the value is pending.
+        pending: Vec<Node>,
+        /// Variable name bound to the resolved value in `{:then}`.
+        then_id: Option<String>,
+        /// Nodes to render once the value is resolved.
+        then_branch: Option<Vec<Node>>,
+        /// Variable name bound to the error in `{:catch}`.
+        catch_id: Option<String>,
+        /// Nodes to render when resolving the value fails.
+        catch_branch: Option<Vec<Node>>,
+    },
+    /// Custom block `{#name args}...{/name}`, expanded by a registered
+    /// [`BlockDirective`](crate::directives::BlockDirective).
+    CustomBlock {
+        /// The block name.
+        name: String,
+        /// Expression text after the name, if any.
+        args: Option<Expression>,
+        /// Nodes inside the block.
+        children: Vec<Node>,
+        /// Location of the opening tag.
+        span: Span,
+    },
     /// Whitespace-sensitive conditional block `{#sif condition}...{/sif}`.
     ///
     /// Like `IfBlock` but preserves exact whitespace in output.
@@ -107,14 +142,18 @@ pub enum Node {
         /// Optional nodes to render when the list is empty.
         empty: Option<Vec<Node>>,
     },
-    /// Local constant declaration `{@local name = expression}`.
+    /// Local constant declaration `{@const name = expression}` (or `{@local}`).
     ///
-    /// Declares a local variable available in subsequent template expressions.
+    /// Declares a local variable available in subsequent template expressions
+    /// of the enclosing block.
     LocalConst {
         /// The variable name to bind.
         name: String,
         /// The Lua expression to evaluate and assign.
         expression: Expression,
+        /// Tag the declaration was written with: `const` or `local`.
+        #[serde(default = "default_local_tag")]
+        tag: String,
     },
     /// Raw HTML output `{@html expression}`.
     ///
@@ -134,6 +173,30 @@ pub enum Node {
         attributes: Vec<Attribute>,
         /// Child nodes passed as the component's children slot.
         children: Vec<Node>,
+        /// Location of the opening tag.
+        span: Span,
+    },
+    /// Snippet declaration `{#snippet name(params)}...{/snippet}`.
+    ///
+    /// Declares a function rendering the body, visible throughout the
+    /// enclosing block. Directly inside a component tag, the snippet is
+    /// passed to the component as the prop `name` instead.
+    Snippet {
+        /// The snippet name.
+        name: String,
+        /// Parameter names.
+        params: Vec<String>,
+        /// Nodes rendered when the snippet is rendered.
+        body: Vec<Node>,
+    },
+    /// Snippet render tag `{@render expr(args)}` or `{@render expr?.(args)}`.
+    RenderSnippet {
+        /// Expression evaluating to the snippet, e.g. `row` or `props.row`.
+        callee: Expression,
+        /// Argument list text, if any.
+        args: Option<Expression>,
+        /// If true, renders nothing when the snippet is nil (uses `?.()` syntax).
+        optional: bool,
     },
     /// Children slot render directive `{@render children()}` or `{@render children?()}`.
     ///
@@ -142,6 +205,17 @@ pub enum Node {
         /// If true, renders nothing when children is nil (uses `?()` syntax).
         optional: bool,
     },
+    /// A `<style>` element, whose CSS is kept as raw text.
+    ///
+    /// At the top level of a template the CSS is scoped to the template (see
+    /// [`style`](crate::style)); elsewhere, or with the `global` attribute,
+    /// it is rendered as a regular element.
+    StyleBlock {
+        /// Attributes of the `<style>` tag.
+        attributes: Vec<Attribute>,
+        /// The CSS.
+        content: String,
+    },
     /// Pass-through script tag that isn't processed by LUAT.
     ///
     /// Used for `<script type="application/json">` or similar non-Lua scripts.
@@ -166,6 +240,7 @@ pub enum Node {
 /// <div {class}>               <!-- Shorthand (name = value) -->
 /// <Button {...props}>         <!-- Spread operator -->
 /// <input disabled>            <!-- Boolean attribute -->
+/// <li class:active={isActive}> <!-- Class directive -->
 /// ```
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Attribute {
@@ -189,7 +264,8 @@ pub enum AttributeValue {
     Static(String),
     /// A dynamic expression value: `foo="{expr}"`.
     Dynamic(Expression),
-    /// Shorthand syntax where name equals value: `{foo}` expands to `foo={foo}`.
+    /// Shorthand syntax where name equals value: `{foo}` expands to `foo={foo}`,
+    /// and `class:foo` to `class:foo={foo}`.
     Shorthand(Expression),
     /// Raw HTML attribute value: `foo={@html expr}`.
     RawHtml(Expression),
@@ -244,6 +320,65 @@ pub struct ScriptBlock {
     pub content: String,
     /// Source location of the script block.
     pub span: Span,
+    /// 1-indexed line of the first line of `content`.
+    #[serde(default)]
+    pub content_line: usize,
+}
+
+/// A `{#use Name from "path"}` import at the top of a template.
+///
+/// Binds `Name` to `require("path")` without a script block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UseImport {
+    /// Local name the module is bound to.
+    pub name: String,
+    /// Module path passed to `require`.
+    pub path: String,
+    /// Location of the directive.
+    pub span: Span,
+}
+
+fn default_local_tag() -> String {
+    "local".to_string()
+}
+
+/// How a template's output is escaped and how empty elements are closed.
+///
+/// Set per template with a leading `<!-- luat:mode xml -->` directive or by
+/// the file extension (`.luat.xml`, `.luat.txt`); defaults to HTML.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputMode {
+    /// HTML escaping; void elements such as `<br>` are self-closed.
+    #[default]
+    Html,
+    /// XML escaping; every element without children is self-closed.
+    Xml,
+    /// No escaping, for plaintext output such as emails.
+    Text,
+}
+
+impl OutputMode {
+    /// Parses a mode name as used in the `luat:mode` directive.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "html" => Some(Self::Html),
+            "xml" => Some(Self::Xml),
+            "text" | "txt" => Some(Self::Text),
+            _ => None,
+        }
+    }
+
+    /// Returns the mode implied by a template path's extension, if any.
+    pub fn from_path(path: &str) -> Option<Self> {
+        if path.ends_with(".luat.xml") {
+            Some(Self::Xml)
+        } else if path.ends_with(".luat.txt") {
+            Some(Self::Text)
+        } else {
+            None
+        }
+    }
 }
 
 /// Complete AST representation of a parsed LUAT template.
@@ -258,10 +393,16 @@ pub struct TemplateAST {
     pub regular_script: Option<ScriptBlock>,
     /// The template body nodes (markup, expressions, control flow).
     pub body: Vec<Node>,
+    /// `{#use}` imports, in source order.
+    #[serde(default)]
+    pub uses: Vec<UseImport>,
     /// Component paths discovered from `require()` calls in scripts.
     pub imports: Vec<String>,
     /// Canonical file path, set by the engine after resolution.
     pub path: Option<String>,
+    /// Output mode from a `<!-- luat:mode ... -->` directive, if present.
+    #[serde(default)]
+    pub mode: Option<OutputMode>,
 }
 
 impl TemplateAST {
@@ -271,8 +412,10 @@ impl TemplateAST {
             module_script: None,
             regular_script: None,
             body: Vec::new(),
+            uses: Vec::new(),
             imports: Vec::new(),
             path: None,
+            mode: None,
         }
     }
 }
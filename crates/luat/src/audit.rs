@@ -0,0 +1,89 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Escape audit mode.
+//!
+//! When enabled with [`Engine::set_escape_audit`](crate::Engine::set_escape_audit),
+//! every unescaped output site reached during rendering is recorded:
+//!
+//! - `{@html expr}` blocks (`html`)
+//! - raw attribute values (`attribute`)
+//! - expressions interpolated into `<script>` tags (`script`)
+//!
+//! Each record carries the template, source line, the expression text and a
+//! sample of the emitted value, so a security review can confirm that every
+//! raw output is intentional.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! engine.set_escape_audit(true);
+//! let html = engine.render(&module, &context)?;
+//! let report = engine.take_escape_audit()?;
+//! println!("{}", report);
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A single unescaped output site reached during rendering.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RawOutputSite {
+    /// Template (module) that produced the output.
+    pub template: String,
+    /// Source line in the template (0 when unknown).
+    pub line: usize,
+    /// The expression whose value was written unescaped.
+    pub expression: String,
+    /// Kind of site: `html`, `attribute` or `script`.
+    pub kind: String,
+    /// First characters of the emitted value.
+    pub value: String,
+}
+
+/// Raw output sites collected while escape auditing was enabled.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EscapeAuditReport {
+    /// Every recorded site, in render order (duplicates included).
+    pub sites: Vec<RawOutputSite>,
+}
+
+impl EscapeAuditReport {
+    /// Returns true if no raw output was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.sites.is_empty()
+    }
+
+    /// Returns the distinct sites (template, line, expression), sorted by location.
+    pub fn unique_sites(&self) -> Vec<&RawOutputSite> {
+        let mut seen = std::collections::HashSet::new();
+        let mut unique: Vec<&RawOutputSite> = self
+            .sites
+            .iter()
+            .filter(|site| seen.insert((&site.template, site.line, &site.expression)))
+            .collect();
+        unique.sort_by(|a, b| (&a.template, a.line).cmp(&(&b.template, b.line)));
+        unique
+    }
+}
+
+impl fmt::Display for EscapeAuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unique = self.unique_sites();
+        writeln!(
+            f,
+            "Escape audit: {} raw output site(s), {} write(s)",
+            unique.len(),
+            self.sites.len()
+        )?;
+        for site in unique {
+            writeln!(
+                f,
+                "  {}:{} [{}] {} => {:?}",
+                site.template, site.line, site.kind, site.expression, site.value
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Automatic component imports.
+//!
+//! With component directories configured, a component tag the template does
+//! not bind itself is looked up in every directory, so
+//!
+//! ```text
+//! <Button label="Save" />
+//! ```
+//!
+//! works without `{#use Button from "$lib/components/Button"}`:
+//!
+//! ```rust,ignore
+//! engine.component_dirs().add("$lib/components");
+//! ```
+//!
+//! A name is bound by the template if a `{#use}` imports it or a script
+//! mentions it, e.g. `local Button = require(...)`. A component found in
+//! more than one directory is an error that lists the `{#use}` lines to
+//! choose from; a component found in none is left to the template, e.g. for
+//! a global.
+
+use crate::ast::{Span, UseImport};
+use crate::error::{LuatError, Result};
+use crate::expression::{tokenize, TokenKind};
+use crate::resolver::ResourceResolver;
+use crate::transform::{IRNode, IR};
+use std::sync::{Arc, RwLock};
+
+/// Directories component tags are imported from.
+///
+/// Clones share the list, so directories added through one handle apply to
+/// templates compiled through any other.
+#[derive(Debug, Clone, Default)]
+pub struct ComponentDirs {
+    /// Module path prefixes, in the order added.
+    dirs: Arc<RwLock<Vec<String>>>,
+}
+
+impl ComponentDirs {
+    /// Creates an empty list, which imports nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a directory, written as a module path prefix such as
+    /// `$lib/components` or `lib/ui`.
+    pub fn add(&self, dir: &str) {
+        let dir = dir.trim_end_matches('/').to_string();
+        let mut dirs = self.dirs.write().unwrap_or_else(std::sync::PoisonError::into_inner);
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+
+    /// Returns true if no directory is configured.
+    pub fn is_empty(&self) -> bool {
+        self.dirs
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .is_empty()
+    }
+
+    /// Returns the imports for the components `ir` uses without binding
+    /// them, resolved relative to the template `importer`. Each import has
+    /// the span of the first tag using the component.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LuatError::ResolutionError`] if a component exists in more
+    /// than one directory.
+    pub fn resolve(&self, ir: &IR, importer: &str, resolver: &dyn ResourceResolver) -> Result<Vec<UseImport>> {
+        let dirs = self.dirs.read().unwrap_or_else(std::sync::PoisonError::into_inner);
+        if dirs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut names: Vec<&String> = ir
+            .components
+            .iter()
+            .filter(|name| is_component_name(name) && !is_bound(ir, name))
+            .collect();
+        names.sort();
+
+        let mut imports = Vec::new();
+        for name in names {
+            let mut found: Vec<(String, String)> = Vec::new();
+            for dir in dirs.iter() {
+                let module = format!("{}/{}", dir, name);
+                if let Ok(path) = resolver.get_resolved_path(importer, &module) {
+                    if !found.iter().any(|(_, p)| *p == path) {
+                        found.push((module, path));
+                    }
+                }
+            }
+            match found.as_slice() {
+                [] => {}
+                [(module, _)] => imports.push(UseImport {
+                    name: name.clone(),
+                    path: module.clone(),
+                    span: first_use(&ir.body, name).unwrap_or(Span::new(0, 0, 1, 1)),
+                }),
+                _ => {
+                    let choices: Vec<String> = found
+                        .iter()
+                        .map(|(module, _)| format!("{{#use {} from \"{}\"}}", name, module))
+                        .collect();
+                    return Err(LuatError::ResolutionError(format!(
+                        "Component <{}> in '{}' exists in {} component directories; import one explicitly: {}",
+                        name,
+                        importer,
+                        found.len(),
+                        choices.join(" or ")
+                    )));
+                }
+            }
+        }
+        Ok(imports)
+    }
+
+    /// Adds the imports [`resolve`](Self::resolve) finds to `ir`.
+    pub(crate) fn apply(&self, ir: &mut IR, importer: &str, resolver: &dyn ResourceResolver) -> Result<()> {
+        let imports = self.resolve(ir, importer, resolver)?;
+        ir.uses.extend(imports);
+        Ok(())
+    }
+}
+
+/// Returns true for capitalized identifiers; `ui.Button` and the like are
+/// expressions the template evaluates itself.
+fn is_component_name(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| c.is_ascii_uppercase())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Returns the span of the first `<name>` tag in `nodes`.
+fn first_use(nodes: &[IRNode], name: &str) -> Option<Span> {
+    nodes.iter().find_map(|node| match node {
+        IRNode::ComponentNode { name: tag, span, .. } if tag == name => Some(span.clone()),
+        IRNode::IfNode { then_branch, else_branch, .. } => {
+            first_use(then_branch, name).or_else(|| else_branch.as_deref().and_then(|nodes| first_use(nodes, name)))
+        }
+        IRNode::EachNode { body, empty, .. } => {
+            first_use(body, name).or_else(|| empty.as_deref().and_then(|nodes| first_use(nodes, name)))
+        }
+        IRNode::AwaitNode { pending, then_branch, catch_branch, .. } => first_use(pending, name).or_else(|| {
+            [then_branch, catch_branch].into_iter().flatten().find_map(|nodes| first_use(nodes, name))
+        }),
+        IRNode::ElementNode { children, .. }
+        | IRNode::CustomBlock { children, .. }
+        | IRNode::HtmlComment { children }
+        | IRNode::Snippet { body: children, .. } => first_use(children, name),
+        IRNode::ComponentNode { children: Some(children), .. }
+        | IRNode::DynamicComponent { children: Some(children), .. } => first_use(children, name),
+        _ => None,
+    })
+}
+
+/// Returns true if a `{#use}` or a script of `ir` binds `name`.
+fn is_bound(ir: &IR, name: &str) -> bool {
+    if ir.uses.iter().any(|import| import.name == name) {
+        return true;
+    }
+    let scripts = [&ir.module_script, &ir.regular_script];
+    scripts.into_iter().flatten().any(|script| match tokenize(&script.content) {
+        Ok(tokens) => tokens
+            .iter()
+            .any(|token| token.kind == TokenKind::Name && token.text == name),
+        Err(_) => script.content.contains(name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::MemoryResourceResolver;
+    use crate::{parse_template, transform_ast};
+
+    fn imports(source: &str, dirs: &ComponentDirs, resolver: &MemoryResourceResolver) -> Result<Vec<(String, String)>> {
+        let ir = transform_ast(parse_template(source).unwrap()).unwrap();
+        let imports = dirs.resolve(&ir, "", resolver)?;
+        Ok(imports.into_iter().map(|import| (import.name, import.path)).collect())
+    }
+
+    #[test]
+    fn test_auto_import() {
+        let mut resolver = MemoryResourceResolver::new();
+        resolver.add_resource("lib/components/Button.luat", "<button></button>");
+        resolver.add_resource("lib/components/Card.luat", "<div></div>");
+        resolver.add_resource("lib/ui/Card.luat", "<div></div>");
+
+        let dirs = ComponentDirs::new();
+        assert!(imports("<Button />", &dirs, &resolver).unwrap().is_empty());
+
+        dirs.add("lib/components/");
+        assert_eq!(
+            imports("<Button /><Missing />", &dirs, &resolver).unwrap(),
+            [("Button".to_string(), "lib/components/Button".to_string())]
+        );
+        let ir = transform_ast(parse_template("<main>\n  {#if x}<Button />{/if}\n</main>").unwrap()).unwrap();
+        let span = &dirs.resolve(&ir, "", &resolver).unwrap()[0].span;
+        assert_eq!((span.line, span.column), (2, 10));
+        let bound = "<script>local Button = require(\"x\")</script><Button />";
+        assert!(imports(bound, &dirs, &resolver).unwrap().is_empty());
+
+        dirs.add("lib/ui");
+        let error = imports("<Card />", &dirs, &resolver).unwrap_err().to_string();
+        assert!(error.contains(r#"{#use Card from "lib/components/Card"} or {#use Card from "lib/ui/Card"}"#), "{}", error);
+        assert!(imports("{#use Card from \"lib/ui/Card\"}<Card />", &dirs, &resolver).unwrap().is_empty());
+    }
+}
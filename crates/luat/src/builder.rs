@@ -0,0 +1,332 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Fluent construction of [`Engine`] instances.
+//!
+//! [`Engine::builder`] covers everything that otherwise takes a constructor
+//! plus post-construction setters: cache, sandbox policy, dev mode, root
+//! path, `require()` aliases, Lua extensions and block directives.
+//!
+//! ```rust,ignore
+//! use luat::{Engine, FileSystemResolver};
+//!
+//! let engine = Engine::builder(FileSystemResolver::new("./templates"))
+//!     .memory_cache(200)?
+//!     .root_path("./templates")
+//!     .alias("ui", "lib/ui.lua")
+//!     .extension(|lua| lua.globals().set("SITE_NAME", "Docs"))
+//!     .block_directive("feature", feature_directive)
+//!     .build()?;
+//! ```
+
+use crate::cache::{Cache, MemoryCache};
+use crate::directives::{BlockDirective, BlockDirectives};
+use crate::engine::Engine;
+use crate::error::Result;
+use crate::resolver::ResourceResolver;
+use crate::sandbox::SandboxProfile;
+
+/// Number of compiled modules cached when no cache is configured.
+const DEFAULT_CACHE_SIZE: usize = 100;
+
+/// Function that installs globals or modules into the engine's Lua state.
+type Extension = Box<dyn FnOnce(&mlua::Lua) -> mlua::Result<()>>;
+
+/// Builder for [`Engine`], created with [`Engine::builder`].
+pub struct EngineBuilder<R: ResourceResolver> {
+    resolver: R,
+    cache: Option<Box<dyn Cache>>,
+    sandbox: SandboxProfile,
+    dev_mode: bool,
+    request_isolation: bool,
+    memory_limit: Option<usize>,
+    render_timeout: Option<std::time::Duration>,
+    instruction_limit: Option<u64>,
+    root_path: Option<std::path::PathBuf>,
+    aliases: Vec<(String, String)>,
+    extensions: Vec<Extension>,
+    directives: BlockDirectives,
+    component_dirs: Vec<String>,
+}
+
+impl<R: ResourceResolver> EngineBuilder<R> {
+    /// Creates a builder with a memory cache and the Lua sandbox enabled.
+    pub fn new(resolver: R) -> Self {
+        Self {
+            resolver,
+            cache: None,
+            sandbox: SandboxProfile::Standard,
+            dev_mode: false,
+            request_isolation: false,
+            memory_limit: None,
+            render_timeout: None,
+            instruction_limit: None,
+            root_path: None,
+            aliases: Vec::new(),
+            extensions: Vec::new(),
+            directives: BlockDirectives::new(),
+            component_dirs: Vec::new(),
+        }
+    }
+
+    /// Sets the cache for compiled modules.
+    ///
+    /// Defaults to a [`MemoryCache`] holding 100 modules.
+    pub fn cache(mut self, cache: Box<dyn Cache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Uses an in-memory LRU cache holding `size` compiled modules.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `size` is zero.
+    pub fn memory_cache(self, size: usize) -> Result<Self> {
+        Ok(self.cache(Box::new(MemoryCache::try_new(size)?)))
+    }
+
+    /// Uses a cache that persists compiled modules in `cache_dir`, with an
+    /// in-memory LRU cache of `memory_size` modules on top.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cache directory cannot be created.
+    #[cfg(all(not(target_arch = "wasm32"), feature = "filesystem"))]
+    pub fn filesystem_cache<P: AsRef<std::path::Path>>(self, cache_dir: P, memory_size: usize) -> Result<Self> {
+        Ok(self.cache(Box::new(crate::cache::FileSystemCache::new(cache_dir, memory_size)?)))
+    }
+
+    /// Enables or disables the Lua sandbox (enabled by default).
+    ///
+    /// The sandbox removes `io`, `debug`, `load`, `loadstring`, `loadfile`,
+    /// `dofile` and most of `os` from templates and server modules. Disable
+    /// it only when every template and Lua module is trusted, e.g. for game
+    /// scripting or internal tools: without it, template code can read and
+    /// write files and run arbitrary Lua. Never disable it for engines that
+    /// render user-supplied templates.
+    ///
+    /// Same as [`SandboxProfile::Standard`] when enabled and
+    /// [`SandboxProfile::Trusted`] when disabled.
+    pub fn sandbox(self, enabled: bool) -> Self {
+        self.sandbox_profile(if enabled { SandboxProfile::Standard } else { SandboxProfile::Trusted })
+    }
+
+    /// Sets the sandbox profile (default: [`SandboxProfile::Standard`]).
+    ///
+    /// Use [`SandboxProfile::Strict`] for user-authored templates; see
+    /// [`crate::sandbox`] for what each profile allows.
+    pub fn sandbox_profile(mut self, profile: SandboxProfile) -> Self {
+        self.sandbox = profile;
+        self
+    }
+
+    /// Makes `require()` reload modules on every call, as
+    /// [`Engine::setup_dev_mode`] does.
+    pub fn dev_mode(mut self, enabled: bool) -> Self {
+        self.dev_mode = enabled;
+        self
+    }
+
+    /// Undoes the globals each request sets, as
+    /// [`Engine::set_request_isolation`] does.
+    pub fn request_isolation(mut self, enabled: bool) -> Self {
+        self.request_isolation = enabled;
+        self
+    }
+
+    /// Limits the memory of the engine's Lua state to `bytes`, as
+    /// [`Engine::set_memory_limit_bytes`] does.
+    pub fn memory_limit_bytes(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    /// Aborts renders that run longer than `timeout`, as
+    /// [`Engine::set_render_timeout`] does.
+    pub fn render_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.render_timeout = Some(timeout);
+        self
+    }
+
+    /// Aborts renders that run more than `limit` Lua instructions, as
+    /// [`Engine::set_instruction_limit`] does.
+    pub fn instruction_limit(mut self, limit: u64) -> Self {
+        self.instruction_limit = Some(limit);
+        self
+    }
+
+    /// Sets the root that file paths in error messages are shown relative to.
+    ///
+    /// See [`Engine::set_root_path`].
+    pub fn root_path<P: AsRef<std::path::Path>>(mut self, root: P) -> Self {
+        self.root_path = Some(root.as_ref().to_path_buf());
+        self
+    }
+
+    /// Makes `require(alias)` load the module `target`.
+    ///
+    /// See [`Engine::add_module_alias`].
+    pub fn alias(mut self, alias: impl Into<String>, target: impl Into<String>) -> Self {
+        self.aliases.push((alias.into(), target.into()));
+        self
+    }
+
+    /// Runs `extension` against the engine's Lua state when the engine is
+    /// built, after the built-in modules are registered.
+    ///
+    /// Use it to register globals, functions or `package.preload` modules.
+    /// Extensions run in the order added.
+    pub fn extension<F>(mut self, extension: F) -> Self
+    where
+        F: FnOnce(&mlua::Lua) -> mlua::Result<()> + 'static,
+    {
+        self.extensions.push(Box::new(extension));
+        self
+    }
+
+    /// Registers a custom block directive for `{#name args}...{/name}`.
+    ///
+    /// See [`Engine::register_block_directive`].
+    pub fn block_directive(self, name: &str, directive: impl BlockDirective + 'static) -> Self {
+        self.directives.register(name, directive);
+        self
+    }
+
+    /// Imports component tags from `dir`, e.g. `$lib/components`.
+    ///
+    /// See [`Engine::component_dirs`].
+    pub fn component_dir(mut self, dir: impl Into<String>) -> Self {
+        self.component_dirs.push(dir.into());
+        self
+    }
+
+    /// Builds the engine.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the Lua runtime fails to initialize or an
+    /// extension fails.
+    pub fn build(self) -> Result<Engine<R>> {
+        let cache = match self.cache {
+            Some(cache) => cache,
+            None => Box::new(MemoryCache::try_new(DEFAULT_CACHE_SIZE)?),
+        };
+        let mut engine = Engine::with_sandbox(self.resolver, cache, &self.sandbox)?;
+
+        if let Some(root) = self.root_path {
+            engine.set_root_path(root);
+        }
+        engine.set_request_isolation(self.request_isolation);
+        engine.set_render_timeout(self.render_timeout);
+        engine.set_instruction_limit(self.instruction_limit);
+        for (alias, target) in &self.aliases {
+            engine.add_module_alias(alias, target)?;
+        }
+        for extension in self.extensions {
+            extension(engine.lua())?;
+        }
+        engine.block_directives().extend(&self.directives);
+        for dir in &self.component_dirs {
+            engine.component_dirs().add(dir);
+        }
+        if self.dev_mode {
+            engine.setup_dev_mode()?;
+        }
+        // Last, so the built-in modules and extensions don't count against it
+        engine.set_memory_limit_bytes(self.memory_limit)?;
+
+        Ok(engine)
+    }
+}
+
+impl<R: ResourceResolver> Engine<R> {
+    /// Returns a builder for configuring a new engine.
+    pub fn builder(resolver: R) -> EngineBuilder<R> {
+        EngineBuilder::new(resolver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Engine, MemoryResourceResolver, SandboxOptions, SandboxProfile};
+
+    fn render_type(engine: &Engine<MemoryResourceResolver>) -> String {
+        let module = engine.compile_entry("io.luat").unwrap();
+        let context = engine.to_value(serde_json::json!({})).unwrap();
+        engine.render(&module, &context).unwrap()
+    }
+
+    #[test]
+    fn test_sandbox_can_be_disabled() {
+        let mut resolver = MemoryResourceResolver::new();
+        resolver.add_resource("io.luat", "<p>{type(io)}</p>");
+
+        let sandboxed = Engine::builder(resolver.clone()).build().unwrap();
+        assert!(render_type(&sandboxed).contains("<p>nil</p>"));
+
+        let trusted = Engine::builder(resolver).sandbox(false).build().unwrap();
+        assert!(render_type(&trusted).contains("<p>table</p>"));
+    }
+
+    #[test]
+    fn test_sandbox_profiles() {
+        let mut resolver = MemoryResourceResolver::new();
+        resolver.add_resource("lib/util.lua", "return { answer = 42 }");
+        resolver.add_resource("Card.luat", "<b>{props.title}</b>");
+        resolver.add_resource(
+            "page.luat",
+            "<script>\n    local Card = require(\"Card.luat\")\n</script><Card title={json.encode({1})} />",
+        );
+        let eval = |engine: &Engine<MemoryResourceResolver>, code: &str| {
+            engine.lua().load(code).eval::<String>().map_err(|e| e.to_string())
+        };
+
+        let standard = Engine::builder(resolver.clone()).build().unwrap();
+        assert_eq!(eval(&standard, "return tostring(require('/lib/util.lua').answer)").unwrap(), "42");
+
+        let strict = Engine::builder(resolver.clone())
+            .sandbox_profile(SandboxProfile::Strict)
+            .build()
+            .unwrap();
+        let err = eval(&strict, "return require('/lib/util.lua')").unwrap_err();
+        assert!(err.contains("not allowed by the sandbox"), "{}", err);
+        let err = eval(&strict, "return string.rep('x', 2 * 1024 * 1024)").unwrap_err();
+        assert!(err.contains("sandbox limit"), "{}", err);
+        assert_eq!(eval(&strict, "return ('ab'):rep(2, '-')").unwrap(), "ab-ab");
+        assert_eq!(eval(&strict, "return type(__luat_internal_load)").unwrap(), "nil");
+        // Components and built-in modules still load
+        let module = strict.compile_entry("page.luat").unwrap();
+        let context = strict.to_value(serde_json::json!({})).unwrap();
+        assert!(strict.render(&module, &context).unwrap().contains("<b>[1]</b>"));
+
+        let custom = Engine::builder(resolver)
+            .sandbox_profile(SandboxProfile::Custom(SandboxOptions { io: true, ..Default::default() }))
+            .build()
+            .unwrap();
+        assert_eq!(eval(&custom, "return type(io) .. ' ' .. type(debug) .. ' ' .. type(os.exit)").unwrap(), "table nil nil");
+    }
+
+    #[test]
+    fn test_aliases_and_extensions() {
+        let mut resolver = MemoryResourceResolver::new();
+        resolver.add_resource("lib/greeting.lua", "return { text = 'Hello' }");
+        resolver.add_resource(
+            "page.luat",
+            "<script>\n    local greeting = require(\"greeting\")\n</script><p>{greeting.text}, {SITE_NAME}</p>",
+        );
+
+        let engine = Engine::builder(resolver)
+            .memory_cache(10)
+            .unwrap()
+            .alias("greeting", "/lib/greeting.lua")
+            .extension(|lua| lua.globals().set("SITE_NAME", "Docs"))
+            .build()
+            .unwrap();
+
+        let module = engine.compile_entry("page.luat").unwrap();
+        let context = engine.to_value(serde_json::json!({})).unwrap();
+        assert!(engine.render(&module, &context).unwrap().contains("<p>Hello, Docs</p>"));
+    }
+}
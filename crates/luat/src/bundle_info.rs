@@ -0,0 +1,169 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Version and content stamp of production bundles.
+//!
+//! `luat build` stamps each bundle with the engine version that built it,
+//! the bundle format, a hash of the bundle code and the build time. The stamp
+//! is a `__bundle_info` table set when the bundle runs, so it is available
+//! from [`Engine::bundle_info`](crate::Engine::bundle_info) once the bundle
+//! is preloaded. `luat serve` refuses bundles whose stamp is missing or
+//! fails [`BundleInfo::check_compatible`].
+
+use crate::error::{LuatError, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Version of the bundle layout (`__modules`, `__routes`, server sources).
+///
+/// Bumped whenever bundles built by an older engine can no longer be loaded.
+/// Format 2 bundles receive `load` and the chunk reader as arguments
+/// rather than reading them from globals.
+pub const BUNDLE_FORMAT: u32 = 2;
+
+/// Version of this engine, as stamped into bundles.
+pub const ENGINE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Global the stamp is stored in when the bundle runs.
+pub(crate) const BUNDLE_INFO_GLOBAL: &str = "__bundle_info";
+
+/// Build stamp of a bundle.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleInfo {
+    /// Version of the engine that built the bundle.
+    pub engine_version: String,
+    /// Bundle layout version, see [`BUNDLE_FORMAT`].
+    pub format: u32,
+    /// `sha256-` hash of the bundle code, before stamping.
+    pub content_hash: String,
+    /// Build time as a Unix timestamp in seconds.
+    pub built_at: u64,
+}
+
+impl BundleInfo {
+    /// Creates the stamp for bundle code built by this engine at `built_at`.
+    pub fn new(lua_code: &str, built_at: u64) -> Self {
+        let digest = Sha256::digest(lua_code.as_bytes());
+        let hash: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+        Self {
+            engine_version: ENGINE_VERSION.to_string(),
+            format: BUNDLE_FORMAT,
+            content_hash: format!("sha256-{}", hash),
+            built_at,
+        }
+    }
+
+    /// Returns `lua_code` with the stamp added.
+    ///
+    /// The stamp goes before the bundle's final `return __modules`, so the
+    /// line numbers of the bundled modules do not move.
+    pub fn stamp(&self, lua_code: &str) -> String {
+        let stamp = format!(
+            "{} = {{ engine_version = {:?}, format = {}, content_hash = {:?}, built_at = {} }}\n\n",
+            BUNDLE_INFO_GLOBAL, self.engine_version, self.format, self.content_hash, self.built_at
+        );
+        let mut stamped = lua_code.to_string();
+        match stamped.rfind("return __modules") {
+            Some(pos) => stamped.insert_str(pos, &stamp),
+            None => {
+                stamped.push_str("\n\n");
+                stamped.push_str(&stamp);
+            }
+        }
+        stamped
+    }
+
+    /// Checks that this engine can run the bundle.
+    ///
+    /// The bundle format must match, and the engine version must be
+    /// semver-compatible: same major version, or for `0.x` versions the same
+    /// minor version.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LuatError::InvalidTemplate`] describing the mismatch.
+    pub fn check_compatible(&self) -> Result<()> {
+        if self.format != BUNDLE_FORMAT {
+            return Err(LuatError::InvalidTemplate(format!(
+                "Bundle format {} is not supported by luat {} (expected format {}); rebuild the bundle",
+                self.format, ENGINE_VERSION, BUNDLE_FORMAT
+            )));
+        }
+        if compatibility_key(&self.engine_version) != compatibility_key(ENGINE_VERSION) {
+            return Err(LuatError::InvalidTemplate(format!(
+                "Bundle was built by luat {}, which is incompatible with luat {}; rebuild the bundle",
+                self.engine_version, ENGINE_VERSION
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the part of a version that must match: `major`, or `0.minor`.
+fn compatibility_key(version: &str) -> (u64, u64) {
+    let mut parts = version
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse::<u64>().unwrap_or(0));
+    let major = parts.next().unwrap_or(0);
+    let minor = parts.next().unwrap_or(0);
+    if major == 0 {
+        (0, minor)
+    } else {
+        (major, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stamp_before_return() {
+        let code = "local __modules = {}\nreturn __modules";
+        let info = BundleInfo::new(code, 1_700_000_000);
+        assert!(info.content_hash.starts_with("sha256-"));
+        assert_eq!(info.content_hash.len(), "sha256-".len() + 64);
+
+        let stamped = info.stamp(code);
+        assert!(stamped.starts_with("local __modules = {}\n__bundle_info = { engine_version = "));
+        assert!(stamped.ends_with("built_at = 1700000000 }\n\nreturn __modules"));
+    }
+
+    #[test]
+    fn test_engine_reads_stamp() {
+        let engine = crate::Engine::with_memory_cache(crate::MemoryResourceResolver::new(), 10).unwrap();
+        assert_eq!(engine.bundle_info().unwrap(), None);
+
+        let code = "local __modules = {}\nreturn __modules";
+        let info = BundleInfo::new(code, 42);
+        let bytecode = engine.compile_bundle(&info.stamp(code)).unwrap();
+        engine.preload_bundle_code_from_binary(&bytecode).unwrap();
+        assert_eq!(engine.bundle_info().unwrap(), Some(info));
+    }
+
+    #[test]
+    fn test_check_compatible() {
+        let info = BundleInfo::new("", 0);
+        assert!(info.check_compatible().is_ok());
+
+        let other_format = BundleInfo { format: BUNDLE_FORMAT + 1, ..info.clone() };
+        assert!(other_format.check_compatible().is_err());
+
+        let (major, minor) = compatibility_key(ENGINE_VERSION);
+        let incompatible = if major == 0 {
+            format!("0.{}.0", minor + 1)
+        } else {
+            format!("{}.0.0", major + 1)
+        };
+        let other_version = BundleInfo { engine_version: incompatible, ..info };
+        let err = other_version.check_compatible().unwrap_err();
+        assert!(err.to_string().contains("rebuild the bundle"), "{}", err);
+
+        assert_eq!(compatibility_key("1.4.2"), compatibility_key("1.0.0-beta.1"));
+        assert_ne!(compatibility_key("0.2.0"), compatibility_key("0.1.9"));
+    }
+}
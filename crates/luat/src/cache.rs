@@ -21,14 +21,17 @@
 //! # Custom Caches
 //!
 //! Implement the [`Cache`] trait to create custom caching strategies
-//! (e.g., Redis-backed, distributed, etc.).
+//! (e.g., Redis-backed, distributed, etc.). Backends with slow IO can
+//! implement [`AsyncCache`] instead. Cache operations report failures as
+//! errors rather than panicking; [`read_through`] and [`read_through_async`]
+//! treat an unavailable cache as a miss.
 
-use crate::error::Result;
-#[cfg(not(target_arch = "wasm32"))]
-use crate::error::LuatError;
+use crate::error::{LuatError, Result};
 use lru::LruCache;
 use std::hash::{Hash, Hasher};
 use std::collections::hash_map::DefaultHasher;
+use std::future::Future;
+use std::pin::Pin;
 
 // Conditional imports for thread primitives
 // Native builds use Arc/Mutex for thread safety
@@ -56,10 +59,21 @@ type SharedMut<T> = Arc<Mutex<T>>;
 #[cfg(target_arch = "wasm32")]
 type SharedMut<T> = Rc<RefCell<T>>;
 
+/// Version of the serialized [`Module`] format.
+///
+/// Bumped whenever the serialized fields or the generated code change in a
+/// way that makes previously stored modules unusable.
+pub const MODULE_FORMAT_VERSION: u32 = 1;
+
 /// A compiled LUAT template module.
 ///
 /// Contains the generated Lua code and metadata about the template,
 /// including its dependencies and a content hash for cache invalidation.
+///
+/// Modules serialize with serde into a stable, versioned format (see
+/// [`MODULE_FORMAT_VERSION`]), so embedders can keep them in their own
+/// storage; [`Module::store`] and [`Module::load`] do this as JSON. The hash
+/// is not serialized but recomputed on load.
 #[derive(Debug, Clone)]
 pub struct Module {
     /// The module name (typically the template filename).
@@ -74,6 +88,8 @@ pub struct Module {
     pub path: Option<String>,
     /// Source map for mapping Lua line numbers to .luat source lines.
     pub source_map: Option<crate::codegen::LuaSourceMap>,
+    /// Props, imports and slots of the template; empty for plain Lua modules.
+    pub metadata: crate::metadata::ComponentMetadata,
 }
 
 impl Module {
@@ -92,6 +108,7 @@ impl Module {
             hash,
             path: None,
             source_map: None,
+            metadata: Default::default(),
         }
     }
 
@@ -121,7 +138,74 @@ impl Module {
             hash,
             path,
             source_map: Some(source_map),
+            metadata: Default::default(),
+        }
+    }
+
+    /// Writes the module as JSON.
+    pub fn store<W: std::io::Write>(&self, writer: W) -> Result<()> {
+        serde_json::to_writer(writer, self)
+            .map_err(|e| crate::error::LuatError::CacheError(format!("Failed to store module: {}", e)))
+    }
+
+    /// Reads a module written by [`store`](Self::store).
+    ///
+    /// Fails for modules stored in a different format version.
+    pub fn load<R: std::io::Read>(reader: R) -> Result<Self> {
+        serde_json::from_reader(reader)
+            .map_err(|e| crate::error::LuatError::CacheError(format!("Failed to load module: {}", e)))
+    }
+}
+
+/// Serialized form of [`Module`].
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ModuleRecord<'a> {
+    format_version: u32,
+    #[serde(borrow)]
+    name: std::borrow::Cow<'a, str>,
+    #[serde(borrow)]
+    lua_code: std::borrow::Cow<'a, str>,
+    dependencies: std::borrow::Cow<'a, [String]>,
+    path: Option<std::borrow::Cow<'a, str>>,
+    source_map: Option<std::borrow::Cow<'a, crate::codegen::LuaSourceMap>>,
+    #[serde(default)]
+    metadata: std::borrow::Cow<'a, crate::metadata::ComponentMetadata>,
+}
+
+impl serde::Serialize for Module {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        ModuleRecord {
+            format_version: MODULE_FORMAT_VERSION,
+            name: self.name.as_str().into(),
+            lua_code: self.lua_code.as_str().into(),
+            dependencies: self.dependencies.as_slice().into(),
+            path: self.path.as_deref().map(Into::into),
+            source_map: self.source_map.as_ref().map(std::borrow::Cow::Borrowed),
+            metadata: std::borrow::Cow::Borrowed(&self.metadata),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Module {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let record = ModuleRecord::deserialize(deserializer)?;
+        if record.format_version != MODULE_FORMAT_VERSION {
+            return Err(serde::de::Error::custom(format!(
+                "unsupported module format version {} (expected {})",
+                record.format_version, MODULE_FORMAT_VERSION
+            )));
         }
+
+        let mut module = Module::new(
+            record.name.into_owned(),
+            record.lua_code.into_owned(),
+            record.dependencies.into_owned(),
+        );
+        module.path = record.path.map(|p| p.into_owned());
+        module.source_map = record.source_map.map(|m| m.into_owned());
+        module.metadata = record.metadata.into_owned();
+        Ok(module)
     }
 }
 
@@ -168,6 +252,130 @@ impl Clone for Box<dyn Cache> {
     }
 }
 
+/// Boxed future returned by [`AsyncCache`] operations.
+#[cfg(not(target_arch = "wasm32"))]
+pub type CacheFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+/// Boxed future returned by [`AsyncCache`] operations.
+#[cfg(target_arch = "wasm32")]
+pub type CacheFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + 'a>>;
+
+/// Asynchronous counterpart of [`Cache`] for backends whose IO should not
+/// block the caller, such as disk or remote stores.
+///
+/// Failures are reported as errors, never panics; use [`read_through_async`]
+/// to fall back to compiling when the backend is unavailable.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait AsyncCache: Send + Sync + std::fmt::Debug {
+    /// Retrieves a module from the cache.
+    fn get_async<'a>(&'a self, key: &'a str) -> CacheFuture<'a, Option<SharedPtr<Module>>>;
+    /// Stores a module in the cache.
+    fn set_async<'a>(&'a self, key: &'a str, module: SharedPtr<Module>) -> CacheFuture<'a, ()>;
+    /// Removes a module from the cache.
+    fn remove_async<'a>(&'a self, key: &'a str) -> CacheFuture<'a, ()>;
+    /// Clears all cached modules.
+    fn clear_async(&self) -> CacheFuture<'_, ()>;
+}
+
+/// Asynchronous counterpart of [`Cache`] (WASM variant).
+#[cfg(target_arch = "wasm32")]
+pub trait AsyncCache: std::fmt::Debug {
+    /// Retrieves a module from the cache.
+    fn get_async<'a>(&'a self, key: &'a str) -> CacheFuture<'a, Option<SharedPtr<Module>>>;
+    /// Stores a module in the cache.
+    fn set_async<'a>(&'a self, key: &'a str, module: SharedPtr<Module>) -> CacheFuture<'a, ()>;
+    /// Removes a module from the cache.
+    fn remove_async<'a>(&'a self, key: &'a str) -> CacheFuture<'a, ()>;
+    /// Clears all cached modules.
+    fn clear_async(&self) -> CacheFuture<'_, ()>;
+}
+
+/// Looks up `key` and compiles on a miss, storing the result.
+///
+/// A failing cache is treated as a miss: lookup and store errors are logged
+/// and the module is compiled anyway. Only errors from `compile` are returned.
+pub fn read_through<F>(cache: &dyn Cache, key: &str, compile: F) -> Result<SharedPtr<Module>>
+where
+    F: FnOnce() -> Result<SharedPtr<Module>>,
+{
+    match cache.get(key) {
+        Ok(Some(module)) => return Ok(module),
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Cache lookup for '{}' failed, compiling instead: {}", key, e),
+    }
+    let module = compile()?;
+    if let Err(e) = cache.set(key, module.clone()) {
+        tracing::warn!("Failed to cache '{}': {}", key, e);
+    }
+    Ok(module)
+}
+
+/// Asynchronous version of [`read_through`].
+pub async fn read_through_async<F>(cache: &dyn AsyncCache, key: &str, compile: F) -> Result<SharedPtr<Module>>
+where
+    F: FnOnce() -> Result<SharedPtr<Module>>,
+{
+    match cache.get_async(key).await {
+        Ok(Some(module)) => return Ok(module),
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Cache lookup for '{}' failed, compiling instead: {}", key, e),
+    }
+    let module = compile()?;
+    if let Err(e) = cache.set_async(key, module.clone()).await {
+        tracing::warn!("Failed to cache '{}': {}", key, e);
+    }
+    Ok(module)
+}
+
+/// Runs `task` on its own thread and resolves once it finishes, so disk IO
+/// doesn't block the executor. Not tied to any async runtime.
+#[cfg(all(not(target_arch = "wasm32"), feature = "filesystem"))]
+fn spawn_blocking<T, F>(task: F) -> CacheFuture<'static, T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    type Slot<T> = Arc<Mutex<(Option<Result<T>>, Option<std::task::Waker>)>>;
+
+    struct BlockingTask<T>(Slot<T>);
+
+    impl<T> Future for BlockingTask<T> {
+        type Output = Result<T>;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<T>> {
+            let mut slot = self.0.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+            match slot.0.take() {
+                Some(result) => std::task::Poll::Ready(result),
+                None => {
+                    slot.1 = Some(cx.waker().clone());
+                    std::task::Poll::Pending
+                }
+            }
+        }
+    }
+
+    let slot: Slot<T> = Arc::new(Mutex::new((None, None)));
+    let thread_slot = Arc::clone(&slot);
+    let spawned = std::thread::Builder::new()
+        .name("luat-cache-io".to_string())
+        .spawn(move || {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(task))
+                .unwrap_or_else(|_| Err(LuatError::CacheError("Cache task panicked".to_string())));
+            let mut slot = thread_slot.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+            slot.0 = Some(result);
+            if let Some(waker) = slot.1.take() {
+                waker.wake();
+            }
+        });
+
+    match spawned {
+        Ok(_) => Box::pin(BlockingTask(slot)),
+        Err(e) => Box::pin(std::future::ready(Err(LuatError::CacheError(format!(
+            "Failed to start cache IO thread: {}",
+            e
+        ))))),
+    }
+}
+
 /// In-memory LRU (Least Recently Used) cache.
 ///
 /// Stores compiled modules in memory with automatic eviction of
@@ -189,11 +397,29 @@ pub struct MemoryCache {
 impl MemoryCache {
     /// Creates a new memory cache with the given capacity.
     ///
+    /// A capacity of zero is treated as one; use [`MemoryCache::try_new`]
+    /// to reject it instead.
+    ///
     /// # Arguments
     ///
     /// * `capacity` - Maximum number of modules to cache
     pub fn new(capacity: usize) -> Self {
-        let lru_cache = LruCache::new(std::num::NonZeroUsize::new(capacity).unwrap());
+        Self::with_capacity(std::num::NonZeroUsize::new(capacity).unwrap_or(std::num::NonZeroUsize::MIN))
+    }
+
+    /// Creates a new memory cache, failing if `capacity` is zero.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LuatError::CacheError`] for a zero capacity.
+    pub fn try_new(capacity: usize) -> Result<Self> {
+        std::num::NonZeroUsize::new(capacity)
+            .map(Self::with_capacity)
+            .ok_or_else(|| LuatError::CacheError("Cache capacity must be at least 1".to_string()))
+    }
+
+    fn with_capacity(capacity: std::num::NonZeroUsize) -> Self {
+        let lru_cache = LruCache::new(capacity);
 
         #[cfg(not(target_arch = "wasm32"))]
         let cache = Arc::new(Mutex::new(lru_cache));
@@ -217,7 +443,9 @@ impl Cache for MemoryCache {
 
         #[cfg(target_arch = "wasm32")]
         {
-            let mut cache = self.cache.borrow_mut();
+            let mut cache = self.cache.try_borrow_mut().map_err(|_| {
+                LuatError::CacheError("Cache is already in use".to_string())
+            })?;
             Ok(cache.get(key).cloned())
         }
     }
@@ -233,7 +461,9 @@ impl Cache for MemoryCache {
 
         #[cfg(target_arch = "wasm32")]
         {
-            let mut cache = self.cache.borrow_mut();
+            let mut cache = self.cache.try_borrow_mut().map_err(|_| {
+                LuatError::CacheError("Cache is already in use".to_string())
+            })?;
             cache.put(key.to_string(), module);
         }
 
@@ -251,7 +481,9 @@ impl Cache for MemoryCache {
 
         #[cfg(target_arch = "wasm32")]
         {
-            let mut cache = self.cache.borrow_mut();
+            let mut cache = self.cache.try_borrow_mut().map_err(|_| {
+                LuatError::CacheError("Cache is already in use".to_string())
+            })?;
             cache.pop(key);
         }
 
@@ -269,7 +501,9 @@ impl Cache for MemoryCache {
 
         #[cfg(target_arch = "wasm32")]
         {
-            let mut cache = self.cache.borrow_mut();
+            let mut cache = self.cache.try_borrow_mut().map_err(|_| {
+                LuatError::CacheError("Cache is already in use".to_string())
+            })?;
             cache.clear();
         }
 
@@ -288,7 +522,7 @@ impl Cache for MemoryCache {
 
         #[cfg(target_arch = "wasm32")]
         {
-            self.cache.borrow().contains(key)
+            self.cache.try_borrow().map(|cache| cache.contains(key)).unwrap_or(false)
         }
     }
 
@@ -309,6 +543,25 @@ impl Cache for MemoryCache {
     }
 }
 
+// Memory operations never wait on IO, so they complete immediately
+impl AsyncCache for MemoryCache {
+    fn get_async<'a>(&'a self, key: &'a str) -> CacheFuture<'a, Option<SharedPtr<Module>>> {
+        Box::pin(std::future::ready(self.get(key)))
+    }
+
+    fn set_async<'a>(&'a self, key: &'a str, module: SharedPtr<Module>) -> CacheFuture<'a, ()> {
+        Box::pin(std::future::ready(self.set(key, module)))
+    }
+
+    fn remove_async<'a>(&'a self, key: &'a str) -> CacheFuture<'a, ()> {
+        Box::pin(std::future::ready(self.remove(key)))
+    }
+
+    fn clear_async(&self) -> CacheFuture<'_, ()> {
+        Box::pin(std::future::ready(self.clear()))
+    }
+}
+
 /// No-op cache that never stores or retrieves anything.
 ///
 /// Useful for development mode where we want to always compile fresh.
@@ -348,6 +601,24 @@ impl Cache for NoOpCache {
     }
 }
 
+impl AsyncCache for NoOpCache {
+    fn get_async<'a>(&'a self, _key: &'a str) -> CacheFuture<'a, Option<SharedPtr<Module>>> {
+        Box::pin(std::future::ready(Ok(None)))
+    }
+
+    fn set_async<'a>(&'a self, _key: &'a str, _module: SharedPtr<Module>) -> CacheFuture<'a, ()> {
+        Box::pin(std::future::ready(Ok(())))
+    }
+
+    fn remove_async<'a>(&'a self, _key: &'a str) -> CacheFuture<'a, ()> {
+        Box::pin(std::future::ready(Ok(())))
+    }
+
+    fn clear_async(&self) -> CacheFuture<'_, ()> {
+        Box::pin(std::future::ready(Ok(())))
+    }
+}
+
 /// Persistent filesystem-backed cache with memory layer.
 ///
 /// Stores compiled modules on disk for persistence across restarts,
@@ -392,6 +663,14 @@ impl FileSystemCache {
         self.cache_dir.join(format!("{}.lua", safe_key))
     }
 
+    /// Clone sharing the memory layer.
+    fn clone_fs(&self) -> Self {
+        Self {
+            cache_dir: self.cache_dir.clone(),
+            memory_cache: self.memory_cache.clone(),
+        }
+    }
+
     fn metadata_file_path(&self, key: &str) -> std::path::PathBuf {
         let safe_key = key.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_");
         self.cache_dir.join(format!("{}.meta.json", safe_key))
@@ -437,7 +716,9 @@ impl Cache for FileSystemCache {
             })
             .unwrap_or_default();
 
-        let module = Arc::new(Module::new(name, lua_code, dependencies));
+        let mut module = Module::new(name, lua_code, dependencies);
+        module.metadata = serde_json::from_value(metadata["metadata"].clone()).unwrap_or_default();
+        let module = Arc::new(module);
 
         // Store in memory cache for faster access
         self.memory_cache.set(key, module.clone())?;
@@ -461,10 +742,11 @@ impl Cache for FileSystemCache {
             "name": module.name,
             "dependencies": module.dependencies,
             "hash": module.hash,
+            "metadata": module.metadata,
             "created_at": std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs()
+                .map(|d| d.as_secs())
+                .unwrap_or_default()
         });
 
         std::fs::write(&metadata_file, metadata.to_string()).map_err(|e| {
@@ -537,10 +819,39 @@ impl Cache for FileSystemCache {
     }
 
     fn clone_box(&self) -> Box<dyn Cache> {
-        Box::new(Self {
-            cache_dir: self.cache_dir.clone(),
-            memory_cache: self.memory_cache.clone(),
-        })
+        Box::new(self.clone_fs())
+    }
+}
+
+// Memory hits are answered directly; disk IO runs off the calling thread
+#[cfg(all(not(target_arch = "wasm32"), feature = "filesystem"))]
+impl AsyncCache for FileSystemCache {
+    fn get_async<'a>(&'a self, key: &'a str) -> CacheFuture<'a, Option<SharedPtr<Module>>> {
+        match self.memory_cache.get(key) {
+            Ok(Some(module)) => Box::pin(std::future::ready(Ok(Some(module)))),
+            _ => {
+                let cache = self.clone_fs();
+                let key = key.to_string();
+                spawn_blocking(move || cache.get(&key))
+            }
+        }
+    }
+
+    fn set_async<'a>(&'a self, key: &'a str, module: SharedPtr<Module>) -> CacheFuture<'a, ()> {
+        let cache = self.clone_fs();
+        let key = key.to_string();
+        spawn_blocking(move || cache.set(&key, module))
+    }
+
+    fn remove_async<'a>(&'a self, key: &'a str) -> CacheFuture<'a, ()> {
+        let cache = self.clone_fs();
+        let key = key.to_string();
+        spawn_blocking(move || cache.remove(&key))
+    }
+
+    fn clear_async(&self) -> CacheFuture<'_, ()> {
+        let cache = self.clone_fs();
+        spawn_blocking(move || cache.clear())
     }
 }
 
@@ -620,6 +931,69 @@ mod tests {
         assert_eq!(retrieved2.name, "test");
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    #[derive(Debug, Clone)]
+    struct UnavailableCache;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    impl Cache for UnavailableCache {
+        fn get(&self, _key: &str) -> Result<Option<SharedPtr<Module>>> {
+            Err(LuatError::CacheError("connection refused".to_string()))
+        }
+        fn set(&self, _key: &str, _module: SharedPtr<Module>) -> Result<()> {
+            Err(LuatError::CacheError("connection refused".to_string()))
+        }
+        fn remove(&self, _key: &str) -> Result<()> {
+            Ok(())
+        }
+        fn clear(&self) -> Result<()> {
+            Ok(())
+        }
+        fn contains_key(&self, _key: &str) -> bool {
+            false
+        }
+        fn clone_box(&self) -> Box<dyn Cache> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_read_through_survives_unavailable_cache() {
+        assert!(MemoryCache::try_new(0).is_err());
+        assert!(!MemoryCache::new(0).contains_key("x"));
+
+        let module = read_through(&UnavailableCache, "module:a.luat", || {
+            Ok(Arc::new(Module::new("a".to_string(), "return {}".to_string(), vec![])))
+        })
+        .unwrap();
+        assert_eq!(module.name, "a");
+
+        let memory = MemoryCache::new(2);
+        read_through(&memory, "module:a.luat", || Ok(module.clone())).unwrap();
+        let cached = read_through(&memory, "module:a.luat", || panic!("should be cached")).unwrap();
+        assert!(Arc::ptr_eq(&cached, &module));
+    }
+
+    #[cfg(all(not(target_arch = "wasm32"), feature = "filesystem"))]
+    #[tokio::test]
+    async fn test_filesystem_cache_async() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = FileSystemCache::new(temp_dir.path(), 10).unwrap();
+        let module = Arc::new(Module::new("test".to_string(), "return {}".to_string(), vec![]));
+
+        cache.set_async("test", module.clone()).await.unwrap();
+        assert!(temp_dir.path().join("test.lua").exists());
+
+        // A fresh cache has an empty memory layer and reads from disk
+        let cold = FileSystemCache::new(temp_dir.path(), 10).unwrap();
+        let loaded = cold.get_async("test").await.unwrap().unwrap();
+        assert_eq!(loaded.lua_code, module.lua_code);
+
+        cold.remove_async("test").await.unwrap();
+        assert!(cold.get_async("test").await.unwrap().is_none());
+    }
+
     #[test]
     fn test_cache_key_generation() {
         let key1 = generate_cache_key("hello", &[]);
@@ -632,4 +1006,41 @@ mod tests {
         let key4 = generate_cache_key("hello", &["dep".to_string()]);
         assert_ne!(key1, key4);
     }
+
+    #[test]
+    fn test_module_store_and_load() {
+        let mut source_map = crate::codegen::LuaSourceMap::new();
+        source_map.record(12, 3);
+        let mut module = Module::with_source_map(
+            "Card.luat".to_string(),
+            "return { render = function() return \"<p>\\\"hi\\\"</p>\" end }".to_string(),
+            vec!["Button.luat".to_string()],
+            Some("/app/Card.luat".to_string()),
+            source_map.clone(),
+        );
+        module.metadata.props = vec!["title".to_string()];
+        module.metadata.slots = vec!["children".to_string()];
+
+        let mut bytes = Vec::new();
+        module.store(&mut bytes).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["format_version"], MODULE_FORMAT_VERSION);
+        assert!(json.get("hash").is_none());
+
+        let loaded = Module::load(bytes.as_slice()).unwrap();
+        assert_eq!(loaded.name, module.name);
+        assert_eq!(loaded.lua_code, module.lua_code);
+        assert_eq!(loaded.dependencies, module.dependencies);
+        assert_eq!(loaded.path, module.path);
+        assert_eq!(loaded.hash, module.hash);
+        assert_eq!(loaded.source_map, Some(source_map));
+        assert_eq!(loaded.metadata, module.metadata);
+    }
+
+    #[test]
+    fn test_module_load_rejects_other_versions() {
+        let json = r#"{"format_version":999,"name":"a","lua_code":"","dependencies":[],"path":null,"source_map":null}"#;
+        let err = Module::load(json.as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("unsupported module format version 999"), "{}", err);
+    }
 }
@@ -38,8 +38,15 @@ use crate::error::Result;
 use crate::transform::*;
 use std::collections::BTreeMap;
 
+/// Output a streaming render buffers before passing it on, in bytes.
+const STREAM_CHUNK_BYTES: usize = 8192;
+
+/// Most times `{#await}` or a deferred load value resumes a coroutine before
+/// reporting it as failed.
+pub(crate) const AWAIT_MAX_RESUMES: usize = 10_000;
+
 /// Source map that maps Lua line numbers to original .luat source lines.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct LuaSourceMap {
     /// Maps Lua output line number -> .luat source line number.
     /// Only significant lines are recorded (those with expressions).
@@ -177,6 +184,13 @@ struct LuaCodeGenerator {
     current_line: usize,
     /// Source map being built.
     source_map: LuaSourceMap,
+    /// Output mode of the template being generated.
+    mode: OutputMode,
+    /// Scope class of the template's component style, added to its elements.
+    scope_class: Option<String>,
+    /// Keys of the tables the template's scripts assign to locals, in the
+    /// order they are written, by local name.
+    table_keys: std::collections::HashMap<String, Vec<String>>,
 }
 
 impl LuaCodeGenerator {
@@ -188,6 +202,9 @@ impl LuaCodeGenerator {
             local_vars: std::collections::HashSet::new(),
             current_line: 1,
             source_map: LuaSourceMap::new(),
+            mode: OutputMode::Html,
+            scope_class: None,
+            table_keys: std::collections::HashMap::new(),
         }
     }
 
@@ -198,6 +215,21 @@ impl LuaCodeGenerator {
         }
     }
 
+    /// Returns the `__ordered_pairs` call iterating `table`, the value of
+    /// `expression`, in the order its keys are written: in `expression`
+    /// itself when it is a table constructor, or in the script `local` it
+    /// names.
+    fn ordered_pairs(&self, table: &str, expression: &str) -> String {
+        let expression = expression.trim();
+        let keys = crate::expression::table_constructor_keys(expression)
+            .or_else(|| self.table_keys.get(expression).cloned())
+            .filter(|keys| !keys.is_empty());
+        match keys {
+            Some(keys) => format!("__ordered_pairs({}, {{{}}})", table, keys.join(", ")),
+            None => format!("__ordered_pairs({})", table),
+        }
+    }
+
     fn parse_local_vars(script: &str) -> std::collections::HashSet<String> {
         // Very simple: look for lines like 'local foo' or 'local foo = ...'
         let mut set = std::collections::HashSet::new();
@@ -218,6 +250,17 @@ impl LuaCodeGenerator {
     }
 
     fn generate(&mut self, ir: IR) -> Result<String> {
+        self.mode = ir.mode;
+        // CSS without scoped selectors, such as a lone @import, leaves the
+        // elements alone
+        self.scope_class = ir
+            .style
+            .as_ref()
+            .filter(|style| style.css.contains(&style.scope))
+            .map(|style| style.scope.clone());
+        for script in ir.module_script.iter().chain(&ir.regular_script) {
+            self.table_keys.extend(crate::expression::local_table_keys(&script.content));
+        }
         self.write_line("-- Generated Lua template module");
         self.write_line(&format!("-- Module: {}", self.module_name));
         self.write_line("");
@@ -225,10 +268,22 @@ impl LuaCodeGenerator {
         // Generate helper functions
         self.generate_helpers()?;
 
+        // Bind `{#use}` imports ahead of the module script, which may use them
+        if !ir.uses.is_empty() {
+            self.write_line("-- Imports from {#use}");
+            for import in &ir.uses {
+                self.write_line_with_source(
+                    &format!("local {} = require(\"{}\")", import.name, escape_lua_string(&import.path)),
+                    import.span.line,
+                );
+            }
+            self.write_line("");
+        }
+
         // Generate module script (hoisted, executed once)
         if let Some(module_script) = ir.module_script {
             self.write_line("-- Module script (hoisted)");
-            self.write_line(&module_script.content);
+            self.write_script(&module_script.content, module_script.content_line);
             self.write_line("");
         }
 
@@ -237,10 +292,61 @@ impl LuaCodeGenerator {
         self.write_line("runtime = runtime or {}");
         self.write_line("props = props or {}");
         self.write_line("local __output = {}");
+        self.write_line("-- Streaming sink of Engine::render_to_writer, taken by the first render");
+        self.write_line("local __stream, __pending = runtime.stream, 0");
+        self.write_line("runtime.stream = nil");
+        self.write_line("local __guard = runtime.output_guard");
+        self.write_line("if __guard then");
+        self.indent();
+        self.write_line(&format!(
+            "table.insert(__guard.path, \"{}\")",
+            escape_lua_string(&self.module_name)
+        ));
+        self.write_line("__guard.current = table.concat(__guard.path, ' > ')");
+        self.dedent();
+        self.write_line("end");
 
-        self.write_line("local function __write(content)");
+        // Component output is already counted by the component itself, so
+        // nested renders pass `true` to skip the guard
+        self.write_line("local function __write(content, __nested)");
+        self.indent();
+        self.write_line("content = tostring(content)");
+        self.write_line("if __guard and not __nested then __track_output(__guard, content) end");
+        self.write_line("table.insert(__output, content)");
+        self.write_line("if __stream then");
+        self.indent();
+        self.write_line("__pending = __pending + #content");
+        self.write_line(&format!("if __pending >= {} then", STREAM_CHUNK_BYTES));
         self.indent();
-        self.write_line("table.insert(__output, tostring(content))");
+        self.write_line("__stream(table.concat(__output))");
+        self.write_line("__output, __pending = {}, 0");
+        self.dedent();
+        self.write_line("end");
+        self.dedent();
+        self.write_line("end");
+        self.dedent();
+        self.write_line("end");
+        self.write_line("");
+        self.write_line("-- CSP nonce for the current request (empty when none is set)");
+        self.write_line("local function nonce()");
+        self.indent();
+        self.write_line("return runtime.csp_nonce or ''");
+        self.dedent();
+        self.write_line("end");
+        self.write_line("");
+        self.write_line("-- Records unescaped output sites when escape auditing is enabled");
+        self.write_line("local function __raw(value, line, source, kind)");
+        self.indent();
+        self.write_line("local audit = runtime.escape_audit");
+        self.write_line("if audit then");
+        self.indent();
+        self.write_line(&format!(
+            "table.insert(audit, {{ template = \"{}\", line = line, expression = source, kind = kind, value = string.sub(tostring(value), 1, 120) }})",
+            escape_lua_string(&self.module_name)
+        ));
+        self.dedent();
+        self.write_line("end");
+        self.write_line("return value");
         self.dedent();
         self.write_line("end");
         self.write_line("");
@@ -253,7 +359,7 @@ impl LuaCodeGenerator {
         // Generate regular script (executed on each render)
         if let Some(regular_script) = ir.regular_script {
             self.write_line("-- Regular script (executed on each render)");
-            self.write_line(&regular_script.content);
+            self.write_script(&regular_script.content, regular_script.content_line);
             // Parse local vars from script
             self.local_vars = Self::parse_local_vars(&regular_script.content);
             self.write_line("");
@@ -261,12 +367,22 @@ impl LuaCodeGenerator {
             self.local_vars.clear();
         }
 
+        if let Some(style) = &ir.style {
+            self.generate_component_style(style);
+        }
+
         // Generate template body
         self.generate_nodes(&ir.body)?;
 
         self.write_line("");
         self.write_line("-- Pop the context scope after rendering");
         self.write_line("table.remove(runtime.context_stack)");
+        self.write_line("if __guard then");
+        self.indent();
+        self.write_line("table.remove(__guard.path)");
+        self.write_line("__guard.current = table.concat(__guard.path, ' > ')");
+        self.dedent();
+        self.write_line("end");
         self.write_line("return table.concat(__output)");
         self.dedent();
         self.write_line("end");
@@ -353,6 +469,11 @@ impl LuaCodeGenerator {
     fn generate_helpers(&mut self) -> Result<()> {
         self.write_line("-- Helper functions");
 
+        // `{@markdown}` renders with the module as loaded, not the global
+        if cfg!(feature = "markdown") {
+            self.write_line("local __markdown = require(\"markdown\")");
+        }
+
         // HTML escaping function
         self.write_line("local function html_escape(str)");
         self.indent();
@@ -372,7 +493,7 @@ impl LuaCodeGenerator {
         self.write_line("local function smart_tostring(val)");
         self.indent();
         self.write_line("if val == nil then return '' end");
-        self.write_line("if type(val) == 'table' then");
+        self.write_line("if type(val) == 'table' and not (getmetatable(val) or {}).__tostring then");
         self.indent();
         self.write_line("local parts = {}");
         self.write_line("for k, v in pairs(val) do");
@@ -390,13 +511,153 @@ impl LuaCodeGenerator {
         self.dedent();
         self.write_line("end");
         self.write_line("");
+        // Iterates a table in the order its keys are written in the template,
+        // given as `order`, then its other keys sorted, so spread attributes
+        // and class tables render as authored and the same way on every run
+        self.write_line("local function __ordered_pairs(t, order)");
+        self.indent();
+        self.write_line("local keys, listed, rest = {}, {}, {}");
+        self.write_line("for _, k in ipairs(order or {}) do");
+        self.indent();
+        self.write_line("if t[k] ~= nil and not listed[k] then listed[k] = true table.insert(keys, k) end");
+        self.dedent();
+        self.write_line("end");
+        self.write_line("for k in pairs(t) do if not listed[k] then table.insert(rest, k) end end");
+        self.write_line("table.sort(rest, function(a, b) return tostring(a) < tostring(b) end)");
+        self.write_line("for _, k in ipairs(rest) do table.insert(keys, k) end");
+        self.write_line("local i = 0");
+        self.write_line("return function()");
+        self.indent();
+        self.write_line("i = i + 1");
+        self.write_line("local k = keys[i]");
+        self.write_line("if k ~= nil then return k, t[k] end");
+        self.dedent();
+        self.write_line("end");
+        self.dedent();
+        self.write_line("end");
+        self.write_line("");
+        // Sets an attribute of an element with spreads. Attributes keep the
+        // position they were first set at and later values override earlier
+        // ones, except `class` and `style`, whose values are joined; false
+        // removes an attribute and true makes it a boolean one
+        self.write_line("local function __set_attribute(attrs, name, value, escape)");
+        self.indent();
+        self.write_line("if value == nil then return end");
+        self.write_line("if name == 'class' and type(value) == 'table' then");
+        self.indent();
+        self.write_line("local classes = {}");
+        self.write_line("for k, v in __ordered_pairs(value) do if v then table.insert(classes, tostring(k)) end end");
+        self.write_line("value = table.concat(classes, ' ')");
+        self.dedent();
+        self.write_line("end");
+        self.write_line("if value ~= true and value ~= false then");
+        self.indent();
+        self.write_line("value = tostring(value)");
+        self.write_line("if escape then value = escape(value) end");
+        self.dedent();
+        self.write_line("end");
+        self.write_line("local old = attrs.values[name]");
+        self.write_line("if old == nil then table.insert(attrs.names, name) end");
+        self.write_line("if (name == 'class' or name == 'style') and type(old) == 'string' and type(value) == 'string' then");
+        self.indent();
+        self.write_line("if value == '' then");
+        self.indent();
+        self.write_line("value = old");
+        self.dedent();
+        self.write_line("elseif old ~= '' and name == 'class' then");
+        self.indent();
+        self.write_line("value = old .. ' ' .. value");
+        self.dedent();
+        self.write_line("elseif old ~= '' then");
+        self.indent();
+        self.write_line("value = (string.gsub(old, ';%s*$', '')) .. '; ' .. value");
+        self.dedent();
+        self.write_line("end");
+        self.dedent();
+        self.write_line("end");
+        self.write_line("attrs.values[name] = value");
+        self.dedent();
+        self.write_line("end");
+        self.write_line("");
+        // Output size guard: counts bytes per component path and aborts once
+        // the limit set by the engine (runtime.output_guard) is exceeded
+        self.write_line("local function __track_output(guard, content)");
+        self.indent();
+        self.write_line("local size = #content");
+        self.write_line("guard.bytes = guard.bytes + size");
+        self.write_line("guard.by_path[guard.current] = (guard.by_path[guard.current] or 0) + size");
+        self.write_line("if guard.bytes > guard.limit then");
+        self.indent();
+        self.write_line("local top, top_bytes = guard.current, 0");
+        self.write_line("for path, bytes in pairs(guard.by_path) do");
+        self.indent();
+        self.write_line("if bytes > top_bytes then top, top_bytes = path, bytes end");
+        self.dedent();
+        self.write_line("end");
+        self.write_line("error(string.format('Rendered output exceeded %d bytes (limit %d); most output came from %s (%d bytes)', guard.bytes, guard.limit, top, top_bytes), 0)");
+        self.dedent();
+        self.write_line("end");
+        self.dedent();
+        self.write_line("end");
+        self.write_line("");
+        // Settles an {#await} value: functions are called and coroutines run
+        // to completion or until they used up their resumes, ctx.defer()
+        // placeholders stay pending
+        self.write_line("local function __await(value)");
+        self.indent();
+        self.write_line("if type(value) == 'table' and value.__luat_deferred then return 'pending' end");
+        self.write_line("if type(value) == 'function' then");
+        self.indent();
+        self.write_line("local ok, result = pcall(value)");
+        self.write_line("return ok and 'then' or 'catch', result");
+        self.dedent();
+        self.write_line("end");
+        self.write_line("if type(value) == 'thread' then");
+        self.indent();
+        self.write_line("local ok, result, resumes = true, nil, 0");
+        self.write_line("while ok and coroutine.status(value) == 'suspended' do");
+        self.indent();
+        self.write_line(&format!("if resumes == {} then", AWAIT_MAX_RESUMES));
+        self.indent();
+        self.write_line(&format!(
+            "return 'catch', 'coroutine still running after {} resumes'",
+            AWAIT_MAX_RESUMES
+        ));
+        self.dedent();
+        self.write_line("end");
+        self.write_line("ok, result = coroutine.resume(value)");
+        self.write_line("resumes = resumes + 1");
+        self.dedent();
+        self.write_line("end");
+        self.write_line("return ok and 'then' or 'catch', result");
+        self.dedent();
+        self.write_line("end");
+        self.write_line("return 'then', value");
+        self.dedent();
+        self.write_line("end");
+        self.write_line("");
         self.write_line("local exports = {}");
 
         Ok(())
     }
 
     fn generate_nodes(&mut self, nodes: &[IRNode]) -> Result<()> {
-        for node in nodes {
+        // Snippets are declared before the rest of the block, so they can be
+        // rendered anywhere in it and can render each other
+        let snippets: Vec<&str> = nodes
+            .iter()
+            .filter_map(|node| match node {
+                IRNode::Snippet { name, .. } => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+        if !snippets.is_empty() {
+            self.write_line(&format!("local {}", snippets.join(", ")));
+            for node in nodes.iter().filter(|node| matches!(node, IRNode::Snippet { .. })) {
+                self.generate_node(node)?;
+            }
+        }
+        for node in nodes.iter().filter(|node| !matches!(node, IRNode::Snippet { .. })) {
             self.generate_node(node)?;
         }
         Ok(())
@@ -430,6 +691,26 @@ impl LuaCodeGenerator {
                 empty.as_ref(),
                 *sensitive,
             ),
+            IRNode::AwaitNode {
+                expression,
+                pending,
+                then_id,
+                then_branch,
+                catch_id,
+                catch_branch,
+            } => self.generate_await_node(
+                expression,
+                pending,
+                (then_id.as_deref(), then_branch.as_ref()),
+                (catch_id.as_deref(), catch_branch.as_ref()),
+            ),
+            IRNode::CustomBlock {
+                name,
+                children,
+                span,
+                lua,
+                ..
+            } => self.generate_custom_block(name, children, span, lua.as_deref()),
             IRNode::ElementNode {
                 tag,
                 attributes,
@@ -439,17 +720,39 @@ impl LuaCodeGenerator {
                 name,
                 attributes,
                 children,
+                ..
             } => self.generate_component_node(name, attributes, children.as_ref()),
+            IRNode::DynamicComponent {
+                this,
+                attributes,
+                children,
+            } => self.generate_dynamic_component(this, attributes, children.as_ref()),
             IRNode::LocalConst { name, expression } => {
                 self.generate_local_const(name, expression)
             }
             IRNode::RenderChildren { optional } => self.generate_render_children(*optional),
+            // The local is declared by generate_nodes
+            IRNode::Snippet { name, params, body } => self.write_snippet_function(name, params, body),
+            IRNode::RenderSnippet { callee, args, optional } => {
+                self.generate_render_snippet(callee, args.as_ref(), *optional)
+            }
             IRNode::ScriptAny { content } => {
                 // Process dynamic expressions in script tags
                 let processed_content = content.clone();
-                
+
                 // Look for mustache expressions in the script tag: {expression}
                 let mut offset = 0;
+
+                // Add the CSP nonce unless the opening tag already carries one
+                let open_tag_end = processed_content.find('>').unwrap_or(processed_content.len());
+                if self.mode == OutputMode::Html
+                    && processed_content.starts_with("<script")
+                    && !processed_content[..open_tag_end].contains("nonce=")
+                {
+                    self.write_line("__write(\"<script\")");
+                    self.generate_nonce_attribute();
+                    offset = "<script".len();
+                }
                 while let Some(start) = processed_content[offset..].find('{') {
                     let real_start = offset + start;
                     if let Some(end) = processed_content[real_start..].find('}') {
@@ -463,7 +766,11 @@ impl LuaCodeGenerator {
                                 "__write(\"{}\")",
                                 processed_content[offset..real_start].replace("\\", "\\\\").replace("\"", "\\\"")
                             ));
-                            self.write_line(&format!("__write(smart_tostring({}))", expr));
+                            self.write_line(&format!(
+                                "__write(__raw(smart_tostring({}), 0, \"{}\", \"script\"))",
+                                expr,
+                                escape_lua_string(expr)
+                            ));
                             offset = real_end;
                         } else {
                             offset = real_start + 1; // Skip this { and continue
@@ -510,12 +817,17 @@ impl LuaCodeGenerator {
 
         if escaped {
             self.write_line_with_source(
-                &format!("__write(html_escape(smart_tostring({})))", expr),
+                &format!("__write({})", self.escape_call(&format!("smart_tostring({})", expr))),
                 source_line,
             );
         } else {
             self.write_line_with_source(
-                &format!("__write(smart_tostring({}))", expr),
+                &format!(
+                    "__write(__raw(smart_tostring({}), {}, \"{}\", \"html\"))",
+                    expr,
+                    source_line,
+                    escape_lua_string(expr)
+                ),
                 source_line,
             );
         }
@@ -548,11 +860,34 @@ impl LuaCodeGenerator {
         self.generate_nodes(then_branch)?;
         self.dedent();
 
-        if let Some(else_nodes) = else_branch {
-            self.write_line("else");
-            self.indent();
-            self.generate_nodes(else_nodes)?;
-            self.dedent();
+        // `{:else if}` arrives as an else branch holding only the next if
+        // block; emit the chain as `elseif` instead of nesting it
+        let mut else_branch = else_branch;
+        while let Some(else_nodes) = else_branch {
+            match else_nodes.as_slice() {
+                [IRNode::IfNode {
+                    condition,
+                    then_branch,
+                    else_branch: next,
+                    sensitive: nested_sensitive,
+                }] if *nested_sensitive == sensitive => {
+                    self.write_line_with_source(
+                        &format!("elseif {} then", condition.content.trim()),
+                        condition.span.line,
+                    );
+                    self.indent();
+                    self.generate_nodes(then_branch)?;
+                    self.dedent();
+                    else_branch = next.as_ref();
+                }
+                _ => {
+                    self.write_line("else");
+                    self.indent();
+                    self.generate_nodes(else_nodes)?;
+                    self.dedent();
+                    break;
+                }
+            }
         }
 
         self.write_line("end");
@@ -641,6 +976,91 @@ impl LuaCodeGenerator {
         Ok(())
     }
 
+    /// Renders the branch matching the state `__await` reports for the value.
+    ///
+    /// The `{:then}` and `{:catch}` branches are compiled into functions
+    /// taking the writer and the settled value. For a `ctx.defer()` value,
+    /// they are registered in `runtime.deferred_fragments` and the pending
+    /// branch is wrapped in a `<luat-deferred>` slot, which the engine
+    /// replaces with the branch rendered once the value resolves.
+    fn generate_await_node(
+        &mut self,
+        expression: &Expression,
+        pending: &[IRNode],
+        then: (Option<&str>, Option<&Vec<IRNode>>),
+        catch: (Option<&str>, Option<&Vec<IRNode>>),
+    ) -> Result<()> {
+        self.write_line("do");
+        self.indent();
+        self.write_line_with_source(&format!("local __value = {}", expression.content.trim()), expression.span.line);
+        self.write_line("local __state, __settled = __await(__value)");
+
+        for (state, (binding, branch)) in [("then", then), ("catch", catch)] {
+            let Some(branch) = branch else {
+                self.write_line(&format!("local __{} = nil", state));
+                continue;
+            };
+            self.write_line(&format!("local function __{}(__write, {})", state, binding.unwrap_or("_")));
+            self.indent();
+            self.generate_nodes(branch)?;
+            self.dedent();
+            self.write_line("end");
+            self.write_line(&format!("if __state == \"{0}\" then __{0}(__write, __settled) end", state));
+        }
+
+        if catch.1.is_none() {
+            // Without {:catch}, errors propagate like any other render error
+            self.write_line("if __state == \"catch\" then error(__settled, 0) end");
+        }
+        self.write_line("if __state == \"pending\" then");
+        self.indent();
+        self.write_line("local __fragments = runtime.deferred_fragments");
+        self.write_line("local __slot");
+        self.write_line("if __fragments and type(__value) == 'table' and __value.__luat_deferred then");
+        self.indent();
+        self.write_line("local __id = __value.__luat_deferred");
+        self.write_line("__fragments[__id] = __fragments[__id] or {}");
+        self.write_line("__slot = __id .. '-' .. (#__fragments[__id] + 1)");
+        self.write_line("table.insert(__fragments[__id], { slot = __slot, ['then'] = __then, catch = __catch })");
+        self.write_line("__write('<luat-deferred id=\"' .. __slot .. '\">')");
+        self.dedent();
+        self.write_line("end");
+        self.generate_nodes(pending)?;
+        self.write_line("if __slot then __write('</luat-deferred>') end");
+        self.dedent();
+        self.write_line("end");
+
+        self.dedent();
+        self.write_line("end");
+        Ok(())
+    }
+
+    /// Emits the code generated by the block's directive, after compiling the
+    /// block's children into the local function `__children`.
+    fn generate_custom_block(
+        &mut self,
+        name: &str,
+        children: &[IRNode],
+        span: &Span,
+        lua: Option<&str>,
+    ) -> Result<()> {
+        let lua = lua.ok_or_else(|| crate::directives::unknown_directive(name, span))?;
+
+        self.write_line("do");
+        self.indent();
+        self.write_line("local function __children(__write)");
+        self.indent();
+        self.generate_nodes(children)?;
+        self.dedent();
+        self.write_line("end");
+        for line in lua.lines() {
+            self.write_line_with_source(line, span.line);
+        }
+        self.dedent();
+        self.write_line("end");
+        Ok(())
+    }
+
     fn generate_element_node(
         &mut self,
         tag: &str,
@@ -650,13 +1070,63 @@ impl LuaCodeGenerator {
         // Opening tag
         self.write_line(&format!("__write(\"<{}\")", tag)); // Removed trailing space here
 
-        // Attributes
-        for attr in attributes {
-            self.generate_attribute(attr)?;
+        // Attributes; the `class:` directives and, with a component style,
+        // the scope class join the class attribute
+        let scope = self.scope_class.clone().filter(|_| crate::style::is_scoped_element(tag));
+        let directives: Vec<(&str, &Expression)> = attributes
+            .iter()
+            .filter_map(|attr| match attr {
+                IRAttribute::Named { name, value: IRAttributeValue::Dynamic(expr) } => {
+                    name.strip_prefix("class:").map(|class| (class, expr))
+                }
+                _ => None,
+            })
+            .collect();
+        let merge_class = scope.is_some() || !directives.is_empty();
+        let has_class_attribute = attributes
+            .iter()
+            .any(|attr| matches!(attr, IRAttribute::Named { name, .. } if name == "class"));
+        let mut has_class = false;
+        if attributes.iter().any(|attr| matches!(attr, IRAttribute::Spread(_))) {
+            // With spreads, which attributes end up set is only known at render time
+            self.generate_attribute_list(attributes, &directives, scope.as_deref());
+            has_class = true;
+        } else {
+            for attr in attributes {
+                match attr {
+                    IRAttribute::Named { name, .. } if name.starts_with("class:") => {
+                        if !has_class_attribute && !has_class {
+                            self.generate_class_attribute(None, &directives, scope.as_deref())?;
+                            has_class = true;
+                        }
+                    }
+                    IRAttribute::Named { name, value } if name == "class" && merge_class && !has_class => {
+                        if !self.generate_class_attribute(Some(value), &directives, scope.as_deref())? {
+                            self.generate_attribute(attr)?;
+                        }
+                        has_class = true;
+                    }
+                    _ => self.generate_attribute(attr)?,
+                }
+            }
+        }
+        if let (Some(scope), false) = (&scope, has_class) {
+            self.write_line(&format!("__write(\" class=\\\"{}\\\"\")", scope));
         }
 
-        if children.is_empty() && is_void_element(tag) {
-            // Check for HTML void elements
+        let has_nonce = attributes
+            .iter()
+            .any(|attr| matches!(attr, IRAttribute::Named { name, .. } if name == "nonce"));
+        if self.mode == OutputMode::Html && (tag == "script" || tag == "style") && !has_nonce {
+            self.generate_nonce_attribute();
+        }
+
+        let self_closing = match self.mode {
+            OutputMode::Html => is_void_element(tag),
+            OutputMode::Xml => true,
+            OutputMode::Text => false,
+        };
+        if children.is_empty() && self_closing {
             self.write_line("__write(\" />\")");
         } else {
             self.write_line("__write(\">\")");
@@ -667,6 +1137,157 @@ impl LuaCodeGenerator {
         Ok(())
     }
 
+    /// Wraps a Lua expression in the escaping the output mode calls for.
+    fn escape_call(&self, value: &str) -> String {
+        match self.mode {
+            OutputMode::Html | OutputMode::Xml => format!("html_escape({})", value),
+            OutputMode::Text => value.to_string(),
+        }
+    }
+
+    /// Writes the template's scoped CSS in a `<style>` element, once per
+    /// render however many times the template is rendered in it.
+    fn generate_component_style(&mut self, style: &crate::style::ComponentStyle) {
+        self.write_line("-- Scoped component style, written by the template's first render");
+        self.write_line("runtime.component_styles = runtime.component_styles or {}");
+        self.write_line(&format!("if not runtime.component_styles[\"{}\"] then", style.scope));
+        self.indent();
+        self.write_line(&format!("runtime.component_styles[\"{}\"] = true", style.scope));
+        self.write_line("__write(\"<style\")");
+        self.generate_nonce_attribute();
+        self.write_line(&format!("__write(\">{}</style>\")", escape_lua_string(&style.css)));
+        self.dedent();
+        self.write_line("end");
+    }
+
+    /// Adds the request's CSP nonce (if any) to the tag being opened.
+    fn generate_nonce_attribute(&mut self) {
+        self.write_line(
+            "if runtime.csp_nonce then __write(\" nonce=\\\"\" .. html_escape(runtime.csp_nonce) .. \"\\\"\") end",
+        );
+    }
+
+    /// Writes a `class` attribute from its value, the classes of `class:`
+    /// directives whose condition holds, and the scope class, if any.
+    ///
+    /// Returns false without writing anything for values it does not handle.
+    fn generate_class_attribute(
+        &mut self,
+        value: Option<&IRAttributeValue>,
+        directives: &[(&str, &Expression)],
+        scope: Option<&str>,
+    ) -> Result<bool> {
+        if !directives.is_empty() {
+            self.generate_class_list(value, directives, scope);
+            return Ok(true);
+        }
+
+        let suffix = scope.map(|scope| format!(" {}", scope)).unwrap_or_default();
+        match value.unwrap_or(&IRAttributeValue::BooleanTrue) {
+            IRAttributeValue::Static(val) => {
+                self.write_line(&format!(
+                    "__write(\" class=\\\"{}{}\\\"\")",
+                    escape_lua_string(val),
+                    suffix
+                ));
+            }
+            IRAttributeValue::BooleanTrue => {
+                self.write_line(&format!("__write(\" class=\\\"{}\\\"\")", suffix.trim_start()));
+            }
+            IRAttributeValue::Dynamic(expr) => {
+                self.write_line_with_source(&format!("local __val = {}", expr.content.trim()), expr.span.line);
+                self.write_line("if type(__val) == 'table' then");
+                self.indent();
+                self.write_line("local __classes = {}");
+                self.write_line(&format!(
+                    "for k, v in {} do if v then table.insert(__classes, k) end end",
+                    self.ordered_pairs("__val", &expr.content)
+                ));
+                self.write_line(&format!(
+                    "__write(\" class=\\\"\" .. table.concat(__classes, ' ') .. \"{}\\\"\")",
+                    suffix
+                ));
+                self.dedent();
+                self.write_line("else");
+                self.indent();
+                self.write_line(&format!(
+                    "__write(\" class=\\\"\" .. {} .. \"{}\\\"\")",
+                    self.escape_call("tostring(__val)"),
+                    suffix
+                ));
+                self.dedent();
+                self.write_line("end");
+            }
+            IRAttributeValue::RawHtml(_) => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Writes a `class` attribute assembled at render time, leaving it out
+    /// if no class applies.
+    fn generate_class_list(
+        &mut self,
+        value: Option<&IRAttributeValue>,
+        directives: &[(&str, &Expression)],
+        scope: Option<&str>,
+    ) {
+        self.write_line("do");
+        self.indent();
+        self.write_line("local __classes = {}");
+        match value {
+            Some(IRAttributeValue::Static(val)) if !val.trim().is_empty() => {
+                self.write_line(&format!("table.insert(__classes, \"{}\")", escape_lua_string(val.trim())));
+            }
+            Some(IRAttributeValue::Dynamic(expr)) => {
+                self.write_line_with_source(&format!("local __val = {}", expr.content.trim()), expr.span.line);
+                self.write_line("if type(__val) == 'table' then");
+                self.indent();
+                self.write_line(&format!(
+                    "for k, v in {} do if v then table.insert(__classes, {}) end end",
+                    self.ordered_pairs("__val", &expr.content),
+                    self.escape_call("tostring(k)")
+                ));
+                self.dedent();
+                self.write_line("elseif __val ~= nil then");
+                self.indent();
+                self.write_line(&format!("table.insert(__classes, {})", self.escape_call("tostring(__val)")));
+                self.dedent();
+                self.write_line("end");
+            }
+            Some(IRAttributeValue::RawHtml(expr)) => {
+                let source_line = expr.span.line;
+                self.write_line_with_source(
+                    &format!(
+                        "table.insert(__classes, __raw(tostring({}), {}, \"{}\", \"attribute\"))",
+                        expr.content.trim(),
+                        source_line,
+                        escape_lua_string(expr.content.trim())
+                    ),
+                    source_line,
+                );
+            }
+            _ => {}
+        }
+        for (class, condition) in directives {
+            self.write_line_with_source(
+                &format!(
+                    "if {} then table.insert(__classes, \"{}\") end",
+                    condition.content.trim(),
+                    escape_lua_string(class)
+                ),
+                condition.span.line,
+            );
+        }
+        if let Some(scope) = scope {
+            self.write_line(&format!("table.insert(__classes, \"{}\")", scope));
+        }
+        self.write_line(
+            "if #__classes > 0 then __write(\" class=\\\"\" .. table.concat(__classes, \" \") .. \"\\\"\") end",
+        );
+        self.dedent();
+        self.write_line("end");
+    }
+
     fn generate_attribute(&mut self, attr: &IRAttribute) -> Result<()> {
         match attr {
             IRAttribute::Named { name, value } => match value {
@@ -680,27 +1301,13 @@ impl LuaCodeGenerator {
                 IRAttributeValue::Dynamic(expr) => {
                     let source_line = expr.span.line;
                     if name == "class" {
-                        self.write_line_with_source(
-                            &format!("local __val = {}", expr.content.trim()),
-                            source_line,
-                        );
-                        self.write_line("if type(__val) == 'table' then");
-                        self.indent();
-                        self.write_line("local __classes = {}");
-                        self.write_line("for k, v in pairs(__val) do if v then table.insert(__classes, k) end end");
-                        self.write_line("__write(\" class=\\\"\" .. table.concat(__classes, ' ') .. \"\\\"\")");
-                        self.dedent();
-                        self.write_line("else");
-                        self.indent();
-                        self.write_line("__write(\" class=\\\"\" .. html_escape(tostring(__val)) .. \"\\\"\")");
-                        self.dedent();
-                        self.write_line("end");
+                        self.generate_class_attribute(Some(value), &[], None)?;
                     } else {
                         self.write_line_with_source(
                             &format!(
-                                "__write(\" {}=\\\"\" .. html_escape(tostring({})) .. \"\\\"\")",
+                                "__write(\" {}=\\\"\" .. {} .. \"\\\"\")",
                                 name,
-                                expr.content.trim()
+                                self.escape_call(&format!("tostring({})", expr.content.trim()))
                             ),
                             source_line,
                         );
@@ -710,9 +1317,11 @@ impl LuaCodeGenerator {
                     let source_line = expr.span.line;
                     self.write_line_with_source(
                         &format!(
-                            "__write(\" {}=\\\"\" .. tostring({}) .. \"\\\"\")",
+                            "__write(\" {}=\\\"\" .. __raw(tostring({}), {}, \"{}\", \"attribute\") .. \"\\\"\")",
                             name,
-                            expr.content.trim()
+                            expr.content.trim(),
+                            source_line,
+                            escape_lua_string(expr.content.trim())
                         ),
                         source_line,
                     );
@@ -721,22 +1330,100 @@ impl LuaCodeGenerator {
                     self.write_line(&format!("__write(\" {}\")", name));
                 }
             },
-            IRAttribute::Spread(expr) => {
-                let source_line = expr.span.line;
-                // For spreading into HTML elements, iterate and append attributes
-                self.write_line_with_source(
-                    &format!("for __k, __v in pairs({}) do", expr.content.trim()),
-                    source_line,
-                );
-                self.indent();
-                self.write_line("__write(\" \" .. __k .. \"=\\\"\" .. html_escape(tostring(__v)) .. \"\\\"\")");
-                self.dedent();
-                self.write_line("end");
-            }
+            IRAttribute::Spread(_) => self.generate_attribute_list(std::slice::from_ref(attr), &[], None),
         }
         Ok(())
     }
 
+    /// Writes the attributes of an element with spreads, collected at render
+    /// time so later attributes override earlier ones and `class` and
+    /// `style` values are merged. A spread's keys are set in key order.
+    fn generate_attribute_list(
+        &mut self,
+        attributes: &[IRAttribute],
+        directives: &[(&str, &Expression)],
+        scope: Option<&str>,
+    ) {
+        let escape = match self.mode {
+            OutputMode::Html | OutputMode::Xml => "html_escape",
+            OutputMode::Text => "nil",
+        };
+        self.write_line("do");
+        self.indent();
+        self.write_line("local __attrs = { names = {}, values = {} }");
+        for attr in attributes {
+            match attr {
+                IRAttribute::Named { name, .. } if name.starts_with("class:") => {}
+                IRAttribute::Named { name, value } => match value {
+                    IRAttributeValue::Static(val) => self.write_line(&format!(
+                        "__set_attribute(__attrs, \"{}\", \"{}\")",
+                        name,
+                        escape_lua_string(val)
+                    )),
+                    IRAttributeValue::Dynamic(expr) => self.write_line_with_source(
+                        &format!(
+                            "__set_attribute(__attrs, \"{}\", {}, {})",
+                            name,
+                            expr.content.trim(),
+                            escape
+                        ),
+                        expr.span.line,
+                    ),
+                    IRAttributeValue::RawHtml(expr) => self.write_line_with_source(
+                        &format!(
+                            "__set_attribute(__attrs, \"{}\", __raw(tostring({}), {}, \"{}\", \"attribute\"))",
+                            name,
+                            expr.content.trim(),
+                            expr.span.line,
+                            escape_lua_string(expr.content.trim())
+                        ),
+                        expr.span.line,
+                    ),
+                    IRAttributeValue::BooleanTrue => {
+                        self.write_line(&format!("__set_attribute(__attrs, \"{}\", true)", name))
+                    }
+                },
+                IRAttribute::Spread(expr) => self.write_line_with_source(
+                    &format!(
+                        "for __k, __v in {} do __set_attribute(__attrs, __k, __v, {}) end",
+                        self.ordered_pairs(expr.content.trim(), &expr.content),
+                        escape
+                    ),
+                    expr.span.line,
+                ),
+            }
+        }
+        for (class, condition) in directives {
+            self.write_line_with_source(
+                &format!(
+                    "if {} then __set_attribute(__attrs, \"class\", \"{}\") end",
+                    condition.content.trim(),
+                    escape_lua_string(class)
+                ),
+                condition.span.line,
+            );
+        }
+        if let Some(scope) = scope {
+            self.write_line(&format!("__set_attribute(__attrs, \"class\", \"{}\")", scope));
+        }
+        self.write_line("for _, __k in ipairs(__attrs.names) do");
+        self.indent();
+        self.write_line("local __v = __attrs.values[__k]");
+        self.write_line("if __v == true then");
+        self.indent();
+        self.write_line("__write(\" \" .. __k)");
+        self.dedent();
+        self.write_line("elseif __v ~= false then");
+        self.indent();
+        self.write_line("__write(\" \" .. __k .. \"=\\\"\" .. __v .. \"\\\"\")");
+        self.dedent();
+        self.write_line("end");
+        self.dedent();
+        self.write_line("end");
+        self.dedent();
+        self.write_line("end");
+    }
+
     fn generate_component_node(
         &mut self,
         name: &str,
@@ -780,25 +1467,68 @@ impl LuaCodeGenerator {
             }
         }
 
-        // Add children function if present
+        // Add children function if present; snippets declared directly
+        // inside the component become props of the same name
         if let Some(child_nodes) = children {
-            self.write_line("__component_props.children = function(__write)");
-            self.indent();
-            self.generate_nodes(child_nodes)?;
-            self.dedent();
-            self.write_line("end");
+            let (snippets, content): (Vec<&IRNode>, Vec<&IRNode>) = child_nodes
+                .iter()
+                .partition(|node| matches!(node, IRNode::Snippet { .. }));
+            for snippet in snippets {
+                if let IRNode::Snippet { name, params, body } = snippet {
+                    self.write_snippet_function(&component_prop_setter(name), params, body)?;
+                }
+            }
+            if !content.is_empty() {
+                self.write_line("__component_props.children = function(__write)");
+                self.indent();
+                self.write_capture_start();
+                for node in content {
+                    self.generate_node(node)?;
+                }
+                self.write_capture_end();
+                self.dedent();
+                self.write_line("end");
+            }
         }
 
         // Call component render function
         // self.write_line(&format!("__write({}.render(__component_props))", name));
         self.write_line(&format!(
-            "__write({}.render(__component_props, runtime))",
+            "__write({}.render(__component_props, runtime), true)",
             name
         ));
 
         Ok(())
     }
 
+    fn generate_dynamic_component(
+        &mut self,
+        this: &Expression,
+        attributes: &[IRAttribute],
+        children: Option<&Vec<IRNode>>,
+    ) -> Result<()> {
+        // Like Svelte, a nil or false component renders nothing
+        self.write_line("do");
+        self.indent();
+        self.write_line_with_source(&format!("local __component = {}", this.content.trim()), this.span.line);
+        self.write_line("if __component then");
+        self.indent();
+        self.write_line("if type(__component) ~= 'table' or type(__component.render) ~= 'function' then");
+        self.indent();
+        self.write_line(&format!(
+            "error(\"<luat:component this={{{}}}> expects a component module, got \" .. type(__component), 0)",
+            escape_lua_string(this.content.trim())
+        ));
+        self.dedent();
+        self.write_line("end");
+        self.generate_component_node("__component", attributes, children)?;
+        self.dedent();
+        self.write_line("end");
+        self.dedent();
+        self.write_line("end");
+        Ok(())
+    }
+
     fn generate_render_children(&mut self, optional: bool) -> Result<()> {
         if optional {
             self.write_line("if props.children then");
@@ -820,6 +1550,74 @@ impl LuaCodeGenerator {
         Ok(())
     }
 
+    /// Assigns a snippet function to `target`. Snippets take the writer to
+    /// render into, followed by their parameters, like children functions.
+    fn write_snippet_function(&mut self, target: &str, params: &[String], body: &[IRNode]) -> Result<()> {
+        let mut all_params = vec!["__write".to_string()];
+        all_params.extend(params.iter().cloned());
+        self.write_line(&format!("{} = function({})", target, all_params.join(", ")));
+        self.indent();
+        self.write_capture_start();
+        self.generate_nodes(body)?;
+        self.write_capture_end();
+        self.dedent();
+        self.write_line("end");
+        Ok(())
+    }
+
+    /// Starts the body of a children or snippet function: called without a
+    /// writer, as in `props.children()`, it renders into a buffer that
+    /// [`write_capture_end`](Self::write_capture_end) returns as a string.
+    fn write_capture_start(&mut self) {
+        self.write_line("local __captured");
+        self.write_line("if __write == nil then");
+        self.indent();
+        self.write_line("__captured = {}");
+        self.write_line("__write = function(content) __captured[#__captured + 1] = tostring(content) end");
+        self.dedent();
+        self.write_line("end");
+    }
+
+    fn write_capture_end(&mut self) {
+        self.write_line("if __captured then return table.concat(__captured) end");
+    }
+
+    fn generate_render_snippet(
+        &mut self,
+        callee: &Expression,
+        args: Option<&Expression>,
+        optional: bool,
+    ) -> Result<()> {
+        let source_line = callee.span.line;
+        let args = match args {
+            Some(args) => format!("__write, {}", args.content.trim()),
+            None => "__write".to_string(),
+        };
+        // Snippets write their output; a plain function returning a string,
+        // like one wrapping `props.children()`, has the string written
+        self.write_line("do");
+        self.indent();
+        if optional {
+            self.write_line_with_source(
+                &format!("local __snippet = {}", callee.content.trim()),
+                source_line,
+            );
+            self.write_line(&format!(
+                "local __rendered = __snippet and __snippet({})",
+                args
+            ));
+        } else {
+            self.write_line_with_source(
+                &format!("local __rendered = {}({})", callee.content.trim(), args),
+                source_line,
+            );
+        }
+        self.write_line("if __rendered ~= nil and __rendered ~= false then __write(__rendered) end");
+        self.dedent();
+        self.write_line("end");
+        Ok(())
+    }
+
     fn generate_html_comment(&mut self, children: &[IRNode]) -> Result<()> {
         self.write_line("__write(\"<!--\")");
         self.generate_nodes(children)?;
@@ -833,7 +1631,18 @@ impl LuaCodeGenerator {
         }
         self.output.push_str(line);
         self.output.push('\n');
-        self.current_line += 1;
+        self.current_line += line.matches('\n').count() + 1;
+    }
+
+    /// Writes script content, mapping each of its lines to the template line
+    /// it came from.
+    fn write_script(&mut self, content: &str, start_line: usize) {
+        if start_line > 0 {
+            for offset in 0..content.lines().count() {
+                self.source_map.record(self.current_line + offset, start_line + offset);
+            }
+        }
+        self.write_line(content);
     }
 
     /// Writes a line and records the source mapping.
@@ -856,15 +1665,22 @@ impl LuaCodeGenerator {
     fn generate_with_sourcemap(&mut self, ir: IR) -> Result<(String, LuaSourceMap)> {
         let code = self.generate(ir)?;
 
-        // Prepend source map comment to the code
-        let source_map_comment = self.source_map.to_comment();
-        let final_code = if !source_map_comment.is_empty() {
-            format!("{}\n{}", source_map_comment, code)
-        } else {
-            code
+        if self.source_map.is_empty() {
+            return Ok((code, self.source_map.clone()));
+        }
+
+        // Prepend source map comment to the code, which moves every line down by one
+        let source_map = LuaSourceMap {
+            mappings: self
+                .source_map
+                .mappings
+                .iter()
+                .map(|(lua, src)| (lua + 1, *src))
+                .collect(),
         };
+        let final_code = format!("{}\n{}", source_map.to_comment(), code);
 
-        Ok((final_code, self.source_map.clone()))
+        Ok((final_code, source_map))
     }
 }
 
@@ -916,19 +1732,79 @@ fn is_valid_lua_identifier(name: &str) -> bool {
 }
 
 /// Bundle multiple Lua modules into a single file
-pub fn bundle_sources<F>(sources: Vec<(String, String)>, mut progress: F) -> Result<(String, crate::sourcemap::BundleSourceMap)>
+pub fn bundle_sources<F>(sources: Vec<(String, String)>, progress: F) -> Result<(String, crate::sourcemap::BundleSourceMap)>
+where
+    F: FnMut(usize, usize),
+{
+    let (bundle, _chunks, source_map) = bundle_modules(sources, None, progress)?;
+    Ok((bundle, source_map))
+}
+
+/// A bundle whose modules are stored in separate chunk files.
+///
+/// Produced by [`bundle_sources_chunked`].
+#[derive(Debug, Clone, Default)]
+pub struct ChunkedBundle {
+    /// Entry bundle: the module loader and one lazy loader per module.
+    pub bundle: String,
+    /// Chunk files as `(path, Lua source)`, one per module.
+    pub chunks: Vec<(String, String)>,
+}
+
+/// Bundle multiple Lua modules into an entry bundle plus one chunk per module.
+///
+/// The entry bundle has the same loader as [`bundle_sources`], but each
+/// `__module_loaders` entry only records the chunk path. The chunk is read
+/// through the chunk reader the engine runs the bundle with on the first
+/// `require` of the module, so modules that are never required are never loaded. Chunk paths are
+/// `chunk_dir/<index>-<module>.lua`; errors in a chunk report the module
+/// name and its own line numbers.
+pub fn bundle_sources_chunked<F>(sources: Vec<(String, String)>, chunk_dir: &str, progress: F) -> Result<ChunkedBundle>
+where
+    F: FnMut(usize, usize),
+{
+    let (bundle, chunks, _source_map) = bundle_modules(sources, Some(chunk_dir), progress)?;
+    Ok(ChunkedBundle { bundle, chunks })
+}
+
+/// Returns the chunk path of the `index`th module `name`.
+fn chunk_path(chunk_dir: &str, index: usize, name: &str) -> String {
+    let file: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .collect();
+    let dir = chunk_dir.trim_end_matches('/');
+    if dir.is_empty() {
+        format!("{:04}-{}.lua", index, file)
+    } else {
+        format!("{}/{:04}-{}.lua", dir, index, file)
+    }
+}
+
+/// Writes the bundle; with `chunk_dir`, module sources go to chunks instead.
+fn bundle_modules<F>(
+    sources: Vec<(String, String)>,
+    chunk_dir: Option<&str>,
+    mut progress: F,
+) -> Result<(String, Vec<(String, String)>, crate::sourcemap::BundleSourceMap)>
 where
     F: FnMut(usize, usize),
 {
     let mut bundle = String::new();
+    let mut chunks = Vec::new();
     let mut source_map = crate::sourcemap::BundleSourceMap::new();
 
     bundle.push_str("-- Bundled Lua template modules\n");
-    bundle.push_str("-- Use internal load function preserved by sandbox\n");
-    bundle.push_str("local __load = __luat_internal_load\n");
+    bundle.push_str("-- `load`, the chunk reader, the per-request module names and the module cache,\n");
+    bundle.push_str("-- passed in by the engine out of reach of templates\n");
+    bundle.push_str(&format!(
+        "local __load, __read_chunk, {}, __modules = ...\n",
+        crate::module_state::PER_REQUEST_MODULES
+    ));
     bundle.push_str("local __original_require = require\n");
     bundle.push_str("local __module_loaders = {}\n");
-    bundle.push_str("local __modules = {}\n\n");
+    bundle.push_str(&format!("{0} = {0} or {{}}\n", crate::module_state::PER_REQUEST_MODULES));
+    bundle.push_str("__modules = __modules or {}\n\n");
 
     bundle.push_str("local function __normalize_path(path)\n");
     bundle.push_str("  path = string.gsub(path, \"\\\\\", \"/\")\n");
@@ -1022,6 +1898,8 @@ where
     bundle.push_str("  return nil\n");
     bundle.push_str("end\n\n");
 
+    // Compiled once, so a per-request module runs again without a new load
+    bundle.push_str("local __server_functions = {}\n");
     bundle.push_str("local function __load_server_module(key)\n");
     bundle.push_str("  if package.loaded[key] ~= nil then return package.loaded[key] end\n");
     bundle.push_str("  if not __server_sources then return nil end\n");
@@ -1029,7 +1907,8 @@ where
     bundle.push_str("  if not source then return nil end\n");
     bundle.push_str("  local prev = _G.__luat_current_module\n");
     bundle.push_str("  _G.__luat_current_module = key\n");
-    bundle.push_str("  local fn = __load(source, \"@\" .. key)\n");
+    bundle.push_str("  local fn = __server_functions[key] or __load(source, \"@\" .. key)\n");
+    bundle.push_str("  __server_functions[key] = fn\n");
     bundle.push_str("  local ok, result = pcall(fn)\n");
     bundle.push_str("  _G.__luat_current_module = prev\n");
     bundle.push_str("  if not ok then error(result, 2) end\n");
@@ -1073,16 +1952,58 @@ where
     bundle.push_str("  return module_name .. ': ' .. err\n");
     bundle.push_str("end\n\n");
 
+    if chunk_dir.is_some() {
+        // Chunks are read and compiled once, so a per-request module runs
+        // again without reading its chunk again
+        bundle.push_str("local __chunk_functions = {}\n");
+        bundle.push_str("local function __load_chunk(name, path)\n");
+        bundle.push_str("  local fn = __chunk_functions[name]\n");
+        bundle.push_str("  if fn == nil then\n");
+        bundle.push_str("    if __read_chunk == nil then error(\"cannot load bundle chunk \" .. path .. \": no chunk reader\", 2) end\n");
+        bundle.push_str("    local err\n");
+        bundle.push_str("    fn, err = __load(__read_chunk(path), \"@\" .. name)\n");
+        bundle.push_str("    if not fn then error(__enhance_error(err, name), 2) end\n");
+        bundle.push_str("    __chunk_functions[name] = fn\n");
+        bundle.push_str("  end\n");
+        bundle.push_str("  local __prev = _G.__luat_current_module\n");
+        bundle.push_str("  _G.__luat_current_module = name\n");
+        bundle.push_str("  local __ok, __result = pcall(fn)\n");
+        bundle.push_str("  _G.__luat_current_module = __prev\n");
+        bundle.push_str("  if not __ok then error(__enhance_error(__result, name), 2) end\n");
+        bundle.push_str("  return __result\n");
+        bundle.push_str("end\n\n");
+    }
+
     // Generate all modules
     for (i, (name, source)) in sources.iter().enumerate() {
         progress(i, sources.len());
 
+        let escaped_name = escape_lua_string(name);
+        if crate::module_state::ModuleState::of_source(source) == crate::module_state::ModuleState::PerRequest {
+            bundle.push_str(&format!(
+                "{}[\"{}\"] = true\n",
+                crate::module_state::PER_REQUEST_MODULES,
+                escaped_name
+            ));
+        }
+        if let Some(dir) = chunk_dir {
+            let path = chunk_path(dir, i, name);
+            bundle.push_str(&format!("-- Module: {}\n", name));
+            bundle.push_str(&format!(
+                "__module_loaders[\"{}\"] = function() return __load_chunk(\"{}\", \"{}\") end\n\n",
+                escaped_name,
+                escaped_name,
+                escape_lua_string(&path)
+            ));
+            chunks.push((path, source.clone()));
+            continue;
+        }
+
         // Calculate the line offset where this module's source starts
         // (current bundle lines + 5 wrapper lines: comment, function, prev, current_module, pcall)
         let bundle_lines_so_far = bundle.lines().count();
         let module_source_start_line = bundle_lines_so_far + 6; // 5 wrapper lines + 1 for 1-indexing
 
-        let escaped_name = escape_lua_string(name);
         bundle.push_str(&format!("-- Module: {}\n", name));
         bundle.push_str(&format!("__module_loaders[\"{}\"] = function()\n", escaped_name));
         bundle.push_str("  local __prev = _G.__luat_current_module\n");
@@ -1113,7 +2034,7 @@ where
     bundle.push_str("return __modules\n");
 
     progress(sources.len(), sources.len());
-    Ok((bundle, source_map))
+    Ok((bundle, chunks, source_map))
 }
 
 #[cfg(test)]
@@ -1138,6 +2059,29 @@ mod tests {
         assert!(lua_code.contains("</div>"));
     }
 
+    #[test]
+    fn test_csp_nonce_added_to_script_and_style() {
+        let source = r#"<style>@import url(a.css);</style><script src="/app.js"></script><p>{nonce()}</p>"#;
+        let ast = parse_template(source).unwrap();
+        let ir = transform_ast(ast).unwrap();
+        let lua_code = generate_lua_code(ir, "test").unwrap();
+
+        let lua = mlua::Lua::new();
+        let module: mlua::Table = lua.load(&lua_code).eval().unwrap();
+        let render: mlua::Function = module.get("render").unwrap();
+
+        let runtime = lua.create_table().unwrap();
+        runtime.set("csp_nonce", "abc123").unwrap();
+        let html: String = render.call((lua.create_table().unwrap(), runtime)).unwrap();
+        assert!(html.contains(r#"<style nonce="abc123">"#), "{}", html);
+        assert!(html.contains(r#"<script nonce="abc123" src="/app.js">"#), "{}", html);
+        assert!(html.contains("<p>abc123</p>"), "{}", html);
+
+        // Without a nonce nothing is added
+        let html: String = render.call((lua.create_table().unwrap(), lua.create_table().unwrap())).unwrap();
+        assert!(!html.contains("nonce="), "{}", html);
+    }
+
     #[test]
     fn test_generate_component() {
         let source = r#"        
@@ -1165,6 +2109,19 @@ mod tests {
         assert!(lua_code.contains("end"));
     }
 
+    #[test]
+    fn test_generate_else_if_chain() {
+        let source = r#"{!if a}A{:else if b}B{:else if c}C{:else}D{/if}"#;
+        let ast = parse_template(source).unwrap();
+        let ir = transform_ast(ast).unwrap();
+
+        let lua_code = generate_lua_code(ir, "test").unwrap();
+        assert!(lua_code.contains("if a then"));
+        assert!(lua_code.contains("elseif b then"));
+        assert!(lua_code.contains("elseif c then"));
+        assert_eq!(lua_code.matches("<!-- sensitive -->").count(), 1);
+    }
+
     #[test]
     fn test_generate_each_block() {
         let source = r#"{#each items as item}Hello {item}{/each}"#;
@@ -1269,4 +2226,17 @@ mod tests {
         // The generated code should work
         assert!(lua_code.contains("function render"));
     }
+
+    #[test]
+    fn test_sourcemap_maps_script_lines() {
+        let source = "<script>\nlocal a = 1\nlocal b = broken_call()\n</script>\n<p>{a}</p>\n\n<p>{b}</p>";
+        let ast = parse_template(source).unwrap();
+        let ir = transform_ast(ast).unwrap();
+
+        let (lua_code, source_map) = generate_lua_code_with_sourcemap(ir, "test").unwrap();
+        let line_of = |needle: &str| lua_code.lines().position(|l| l.contains(needle)).unwrap() + 1;
+
+        assert_eq!(source_map.lookup(line_of("broken_call()")), Some(3));
+        assert_eq!(source_map.lookup(line_of("smart_tostring(b)")), Some(7));
+    }
 }
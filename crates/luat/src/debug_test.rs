@@ -277,11 +277,11 @@ fn test_render_children_parsing() {
 
 #[test] 
 fn test_render_children_step_by_step() {
-    // Test render_children rule directly (removed render_expr test)
-    let result1 = LuatParser::parse(Rule::render_children, "{@render children?.()}");
+    // Test render_tag rule directly (removed render_expr test)
+    let result1 = LuatParser::parse(Rule::render_tag, "{@render children?.()}");
     match result1 {
-        Ok(_) => println!("✓ render_children rule works"),
-        Err(e) => println!("✗ render_children rule failed: {}", e),
+        Ok(_) => println!("✓ render_tag rule works"),
+        Err(e) => println!("✗ render_tag rule failed: {}", e),
     }
     
     // Test basic ident
@@ -0,0 +1,268 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Build-time defines.
+//!
+//! Defines are named constants fixed when templates are compiled, so one
+//! codebase can be built per customer or edition:
+//!
+//! ```text
+//! luat build --define FEATURE_X=true --define EDITION=pro
+//!
+//! {#if defines.FEATURE_X}<NewCheckout />{:else}<Checkout />{/if}
+//! ```
+//!
+//! Every `defines.NAME` in a template expression is replaced by the value,
+//! or by `nil` if `NAME` is not defined. `{#if}` blocks whose condition is
+//! then constant keep only the branch taken, so the other branch is not
+//! compiled into the module or the bundle. Values `true` and `false` and
+//! numbers keep their type; anything else is a string.
+//!
+//! Templates whose scripts use a variable named `defines` are left as they
+//! are.
+
+use crate::error::{LuatError, Result};
+use crate::expression::{tokenize, TokenKind};
+use crate::transform::{IRAttribute, IRAttributeValue, IRNode, IR};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+/// Registry of build-time defines.
+///
+/// Clones share the registry, so defines set through one handle apply to
+/// templates compiled through any other.
+#[derive(Debug, Clone, Default)]
+pub struct Defines {
+    /// Lua literal of each define's value, by name.
+    values: Arc<RwLock<BTreeMap<String, String>>>,
+}
+
+impl Defines {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the define `name` to `value`, replacing any earlier value.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LuatError::TransformError`] if `name` is not an identifier.
+    pub fn set(&self, name: &str, value: &str) -> Result<()> {
+        let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(LuatError::TransformError(format!(
+                "Invalid define name `{}`: expected letters, digits and underscores",
+                name
+            )));
+        }
+        self.values
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .insert(name.to_string(), lua_literal(value));
+        Ok(())
+    }
+
+    /// Sets a define written as `NAME=value`; a bare `NAME` sets it to `true`.
+    pub fn parse(&self, definition: &str) -> Result<()> {
+        match definition.split_once('=') {
+            Some((name, value)) => self.set(name.trim(), value.trim()),
+            None => self.set(definition.trim(), "true"),
+        }
+    }
+
+    /// Returns true if no define is set.
+    pub fn is_empty(&self) -> bool {
+        self.values
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .is_empty()
+    }
+
+    /// Replaces `defines.NAME` in every expression of `ir` and folds the
+    /// expressions and `{#if}` blocks that become constant.
+    pub fn apply(&self, ir: &mut IR) {
+        let scripts = [&ir.module_script, &ir.regular_script];
+        let shadowed = scripts.into_iter().flatten().any(|script| match tokenize(&script.content) {
+            Ok(tokens) => tokens
+                .iter()
+                .any(|token| token.kind == TokenKind::Name && token.text == "defines"),
+            Err(_) => true,
+        });
+        if shadowed {
+            return;
+        }
+
+        let values = self.values.read().unwrap_or_else(std::sync::PoisonError::into_inner);
+        if substitute_nodes(&mut ir.body, &values) {
+            crate::fold::fold_constants(ir);
+        }
+    }
+}
+
+/// Returns the Lua literal for a define value. Numbers are written the way
+/// Lua prints them, so `+5` and `05` become `5`.
+fn lua_literal(value: &str) -> String {
+    if let Ok(integer) = value.parse::<i64>() {
+        return integer.to_string();
+    }
+    let is_float = value.bytes().all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'E' | b'+' | b'-'));
+    match value.parse::<f64>() {
+        Ok(float) if is_float && float.is_finite() => format!("{:?}", float),
+        _ if value == "true" || value == "false" => value.to_string(),
+        _ => format!("{:?}", value),
+    }
+}
+
+/// Replaces `defines.NAME` in `expression`; returns true if it changed.
+fn substitute(expression: &mut String, values: &BTreeMap<String, String>) -> bool {
+    if !expression.contains("defines") {
+        return false;
+    }
+    let Ok(tokens) = tokenize(expression) else {
+        return false;
+    };
+
+    let mut out = String::with_capacity(expression.len());
+    let mut last = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        let is_define = token.kind == TokenKind::Name
+            && token.text == "defines"
+            && (i == 0 || !matches!(tokens[i - 1].text, "." | ":"))
+            && tokens.get(i + 1).is_some_and(|dot| dot.kind == TokenKind::Symbol && dot.text == ".");
+        let Some(name) = tokens.get(i + 2).filter(|name| is_define && name.kind == TokenKind::Name) else {
+            continue;
+        };
+        out.push_str(&expression[last..token.offset]);
+        out.push('(');
+        out.push_str(values.get(name.text).map_or("nil", String::as_str));
+        out.push(')');
+        last = name.offset + name.text.len();
+    }
+    if last == 0 {
+        return false;
+    }
+    out.push_str(&expression[last..]);
+    *expression = out;
+    true
+}
+
+fn substitute_attributes(attributes: &mut [IRAttribute], values: &BTreeMap<String, String>) -> bool {
+    let mut changed = false;
+    for attr in attributes {
+        match attr {
+            IRAttribute::Named { value: IRAttributeValue::Dynamic(expr) | IRAttributeValue::RawHtml(expr), .. }
+            | IRAttribute::Spread(expr) => changed |= substitute(&mut expr.content, values),
+            IRAttribute::Named { .. } => {}
+        }
+    }
+    changed
+}
+
+fn substitute_nodes(nodes: &mut [IRNode], values: &BTreeMap<String, String>) -> bool {
+    let mut changed = false;
+    for node in nodes {
+        changed |= match node {
+            IRNode::MustacheNode { expression, .. } | IRNode::LocalConst { expression, .. } => {
+                substitute(&mut expression.content, values)
+            }
+            IRNode::IfNode { condition, then_branch, else_branch, .. } => {
+                substitute(&mut condition.content, values)
+                    | substitute_nodes(then_branch, values)
+                    | else_branch.as_mut().is_some_and(|nodes| substitute_nodes(nodes, values))
+            }
+            IRNode::EachNode { list_expr, body, empty, .. } => {
+                substitute(&mut list_expr.content, values)
+                    | substitute_nodes(body, values)
+                    | empty.as_mut().is_some_and(|nodes| substitute_nodes(nodes, values))
+            }
+            IRNode::AwaitNode { expression, pending, then_branch, catch_branch, .. } => {
+                substitute(&mut expression.content, values)
+                    | substitute_nodes(pending, values)
+                    | then_branch.as_mut().is_some_and(|nodes| substitute_nodes(nodes, values))
+                    | catch_branch.as_mut().is_some_and(|nodes| substitute_nodes(nodes, values))
+            }
+            IRNode::CustomBlock { args, children, .. } => {
+                args.as_mut().is_some_and(|args| substitute(&mut args.content, values))
+                    | substitute_nodes(children, values)
+            }
+            IRNode::ElementNode { attributes, children, .. } => {
+                substitute_attributes(attributes, values) | substitute_nodes(children, values)
+            }
+            IRNode::ComponentNode { attributes, children, .. } => {
+                substitute_attributes(attributes, values)
+                    | children.as_mut().is_some_and(|nodes| substitute_nodes(nodes, values))
+            }
+            IRNode::DynamicComponent { this, attributes, children } => {
+                substitute(&mut this.content, values)
+                    | substitute_attributes(attributes, values)
+                    | children.as_mut().is_some_and(|nodes| substitute_nodes(nodes, values))
+            }
+            IRNode::RenderSnippet { callee, args, .. } => {
+                substitute(&mut callee.content, values)
+                    | args.as_mut().is_some_and(|args| substitute(&mut args.content, values))
+            }
+            IRNode::Snippet { body: children, .. } | IRNode::HtmlComment { children } => {
+                substitute_nodes(children, values)
+            }
+            IRNode::TextNode { .. } | IRNode::RenderChildren { .. } | IRNode::ScriptAny { .. } => false,
+        };
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_lua_code, parse_template, transform_ast};
+
+    fn compile(source: &str, defines: &Defines) -> String {
+        let mut ir = transform_ast(parse_template(source).unwrap()).unwrap();
+        defines.apply(&mut ir);
+        generate_lua_code(ir, "test").unwrap()
+    }
+
+    #[test]
+    fn test_dead_branches_removed() {
+        let defines = Defines::new();
+        defines.parse("FEATURE_X").unwrap();
+        defines.parse("EDITION=pro").unwrap();
+
+        let source = r#"{#if defines.FEATURE_X}<b>new</b>{:else}<i>old</i>{/if}{#if defines.EDITION == "free"}<u>ads</u>{/if}<p>{defines.EDITION}</p>{#if defines.MISSING}<s>gone</s>{/if}"#;
+        let lua_code = compile(source, &defines);
+        assert!(lua_code.contains(r#"__write("new")"#), "{}", lua_code);
+        assert!(lua_code.contains(r#"__write("pro")"#), "{}", lua_code);
+        for stripped in ["old", "ads", "gone"] {
+            assert!(!lua_code.contains(&format!("\"{}\"", stripped)), "{}", lua_code);
+        }
+        assert!(!lua_code.contains("if ("), "{}", lua_code);
+    }
+
+    #[test]
+    fn test_define_values() {
+        assert_eq!(lua_literal("true"), "true");
+        assert_eq!(lua_literal("-3"), "-3");
+        assert_eq!(lua_literal("+5"), "5");
+        assert_eq!(lua_literal("007"), "7");
+        assert_eq!(lua_literal("1.5e3"), "1500.0");
+        assert_eq!(lua_literal("+.5"), "0.5");
+        assert_eq!(lua_literal("inf"), "\"inf\"");
+        assert_eq!(lua_literal("a\"b"), "\"a\\\"b\"");
+        assert!(Defines::new().parse("NOT-VALID=1").is_err());
+
+        let mut expression = "defines.A .. props.defines.B .. defines.B".to_string();
+        let values = BTreeMap::from([("A".to_string(), "1".to_string())]);
+        assert!(substitute(&mut expression, &values));
+        assert_eq!(expression, "(1) .. props.defines.B .. (nil)");
+    }
+
+    #[test]
+    fn test_script_variable_named_defines() {
+        let defines = Defines::new();
+        defines.set("X", "true").unwrap();
+        let lua_code = compile("<script>local defines = { X = false }</script>{#if defines.X}<b>x</b>{/if}", &defines);
+        assert!(lua_code.contains("defines.X"), "{}", lua_code);
+    }
+}
@@ -0,0 +1,260 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Custom block directives.
+//!
+//! Besides `{#if}`, `{#each}` and `{#await}`, templates may use any block
+//! `{#name args}...{/name}`. The host registers a [`BlockDirective`] for the
+//! name, which turns each use into Lua when the template is compiled:
+//!
+//! ```rust,ignore
+//! engine.register_block_directive("feature", |call: &BlockDirectiveCall| {
+//!     let flag = call.args.map(|args| args.content.clone()).unwrap_or_default();
+//!     Ok(format!("if flags.enabled({}) then __children(__write) end", flag))
+//! });
+//! ```
+//!
+//! ```text
+//! {#feature "new-checkout"}<NewCheckout />{/feature}
+//! ```
+//!
+//! The generated code runs where the block appears in the render function.
+//! The block's children are compiled into the local function `__children`,
+//! which takes the writer to render into: `__children(__write)` renders them
+//! in place, while passing another function captures their output. A block
+//! without a registered directive fails to compile.
+//!
+//! Every engine starts with two directives, which can be replaced like any
+//! other: `{#feature}` (see [`flags`](crate::extensions::flags)) and `{#key}`.
+//!
+//! # `{#key}`
+//!
+//! `{#key expr}...{/key}` renders its children between marker comments
+//! carrying the key, so client-side tools (HTMX, morphdom) can tell when the
+//! fragment was re-rendered for a different value:
+//!
+//! ```text
+//! {#key props.user.id}<Profile user={props.user} />{/key}
+//! <!--luat:key 42--><div class="profile">...</div><!--/luat:key-->
+//! ```
+//!
+//! The key is HTML-escaped, with `-` written as `&#45;` so it cannot end the
+//! comment.
+
+use crate::ast::{Expression, Span};
+use crate::error::{LuatError, Result};
+use crate::transform::{IRNode, IR};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// A use of a custom block, handed to its [`BlockDirective`].
+#[derive(Debug, Clone, Copy)]
+pub struct BlockDirectiveCall<'a> {
+    /// Block name, e.g. `feature` for `{#feature ...}`.
+    pub name: &'a str,
+    /// Lua expression text after the name, if any.
+    pub args: Option<&'a Expression>,
+    /// The block's children.
+    pub children: &'a [IRNode],
+    /// Location of the opening tag.
+    pub span: &'a Span,
+}
+
+/// Generates the Lua code for a custom block directive.
+///
+/// Implemented for closures taking a [`BlockDirectiveCall`].
+pub trait BlockDirective: Send + Sync {
+    /// Returns Lua statements rendering the block; see the
+    /// [module documentation](self) for what they can use.
+    fn generate(&self, call: &BlockDirectiveCall<'_>) -> Result<String>;
+}
+
+impl<F> BlockDirective for F
+where
+    F: Fn(&BlockDirectiveCall<'_>) -> Result<String> + Send + Sync,
+{
+    fn generate(&self, call: &BlockDirectiveCall<'_>) -> Result<String> {
+        self(call)
+    }
+}
+
+/// Registry of custom block directives.
+///
+/// Clones share the registry, so directives registered through one handle
+/// apply to templates compiled through any other.
+#[derive(Clone, Default)]
+pub struct BlockDirectives {
+    handlers: Arc<RwLock<HashMap<String, Arc<dyn BlockDirective>>>>,
+}
+
+impl std::fmt::Debug for BlockDirectives {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let handlers = self.handlers.read().unwrap_or_else(std::sync::PoisonError::into_inner);
+        let mut names: Vec<&String> = handlers.keys().collect();
+        names.sort();
+        f.debug_struct("BlockDirectives").field("names", &names).finish()
+    }
+}
+
+impl BlockDirectives {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `directive` for `{#name}` blocks, replacing any earlier one.
+    ///
+    /// Templates compiled before the call keep their generated code.
+    pub fn register(&self, name: impl Into<String>, directive: impl BlockDirective + 'static) {
+        self.handlers
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .insert(name.into(), Arc::new(directive));
+    }
+
+    /// Copies every directive registered in `other` into this registry.
+    pub fn extend(&self, other: &BlockDirectives) {
+        let handlers: Vec<_> = other
+            .handlers
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .iter()
+            .map(|(name, directive)| (name.clone(), directive.clone()))
+            .collect();
+        self.handlers
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .extend(handlers);
+    }
+
+    /// Returns true if a directive is registered for `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.handlers
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .contains_key(name)
+    }
+
+    /// Generates the Lua code of every custom block in `ir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LuatError::CodegenError`] for a block without a registered
+    /// directive, and passes on errors from the directives.
+    pub fn expand(&self, ir: &mut IR) -> Result<()> {
+        self.expand_nodes(&mut ir.body)
+    }
+
+    fn expand_nodes(&self, nodes: &mut [IRNode]) -> Result<()> {
+        for node in nodes {
+            match node {
+                IRNode::CustomBlock { name, args, children, span, lua } => {
+                    self.expand_nodes(children)?;
+                    let directive = self
+                        .handlers
+                        .read()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner)
+                        .get(name.as_str())
+                        .cloned()
+                        .ok_or_else(|| unknown_directive(name, span))?;
+                    let call = BlockDirectiveCall {
+                        name: name.as_str(),
+                        args: args.as_ref(),
+                        children: children.as_slice(),
+                        span: &*span,
+                    };
+                    *lua = Some(directive.generate(&call)?);
+                }
+                IRNode::IfNode { then_branch, else_branch, .. } => {
+                    self.expand_nodes(then_branch)?;
+                    if let Some(else_nodes) = else_branch {
+                        self.expand_nodes(else_nodes)?;
+                    }
+                }
+                IRNode::EachNode { body, empty, .. } => {
+                    self.expand_nodes(body)?;
+                    if let Some(empty_nodes) = empty {
+                        self.expand_nodes(empty_nodes)?;
+                    }
+                }
+                IRNode::AwaitNode { pending, then_branch, catch_branch, .. } => {
+                    self.expand_nodes(pending)?;
+                    for branch in [then_branch, catch_branch].into_iter().flatten() {
+                        self.expand_nodes(branch)?;
+                    }
+                }
+                IRNode::ElementNode { children, .. }
+                | IRNode::HtmlComment { children }
+                | IRNode::Snippet { body: children, .. } => {
+                    self.expand_nodes(children)?;
+                }
+                IRNode::ComponentNode { children: Some(children), .. }
+                | IRNode::DynamicComponent { children: Some(children), .. } => {
+                    self.expand_nodes(children)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Built-in `{#key}` directive: wraps the children in key marker comments.
+pub(crate) fn key_directive(call: &BlockDirectiveCall<'_>) -> Result<String> {
+    let key = call.args.map(|args| args.content.trim()).unwrap_or_default();
+    if key.is_empty() {
+        return Err(LuatError::CodegenError(format!(
+            "{{#key}} requires an expression at line {}, column {}",
+            call.span.line, call.span.column
+        )));
+    }
+    Ok(format!(
+        "local __key = string.gsub(html_escape({}), \"%-\", \"&#45;\")\n\
+         __write(\"<!--luat:key \" .. __key .. \"-->\")\n\
+         __children(__write)\n\
+         __write(\"<!--/luat:key-->\")",
+        key
+    ))
+}
+
+/// Error for a `{#name}` block nothing is registered for.
+pub(crate) fn unknown_directive(name: &str, span: &Span) -> LuatError {
+    LuatError::CodegenError(format!(
+        "Unknown block directive {{#{}}} at line {}, column {}",
+        name, span.line, span.column
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_lua_code, parse_template, transform_ast};
+
+    #[test]
+    fn test_expand_custom_block() {
+        let ast = parse_template(r#"<div>{#upper "x"}<p>Hi</p>{/upper}</div>"#).unwrap();
+        let mut ir = transform_ast(ast).unwrap();
+
+        let directives = BlockDirectives::new();
+        directives.register("upper", |call: &BlockDirectiveCall<'_>| {
+            assert_eq!(call.args.map(|args| args.content.as_str()), Some("\"x\""));
+            assert_eq!(call.children.len(), 1);
+            Ok("local __parts = {}\n__children(function(s) table.insert(__parts, s) end)\n__write(string.upper(table.concat(__parts)))".to_string())
+        });
+        directives.expand(&mut ir).unwrap();
+
+        let lua_code = generate_lua_code(ir, "test").unwrap();
+        assert!(lua_code.contains("local function __children(__write)"));
+        assert!(lua_code.contains("__write(string.upper(table.concat(__parts)))"));
+    }
+
+    #[test]
+    fn test_unknown_directive_fails() {
+        let ast = parse_template("\n  {#cache}<p>Hi</p>{/cache}").unwrap();
+        let mut ir = transform_ast(ast).unwrap();
+
+        let err = BlockDirectives::new().expand(&mut ir).unwrap_err();
+        assert!(err.to_string().contains("Unknown block directive {#cache} at line 2"), "{}", err);
+    }
+}
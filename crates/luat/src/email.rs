@@ -0,0 +1,404 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Email-safe rendering.
+//!
+//! Many mail clients ignore `<style>` blocks and drop scripts, frames and
+//! media. [`inline_email_html`] rewrites rendered HTML so it survives them:
+//!
+//! - rules from `<style>` blocks are copied into the `style` attribute of
+//!   every element they match, before the element's own inline styles
+//! - `<script>`, `<iframe>`, `<object>`, media and form elements are removed
+//!   with their content, `<link>`, `<embed>` and `<base>` tags on their own
+//!
+//! Supported selectors are tag, `.class`, `#id` and `*`, compounds of them
+//! (`td.label`) and descendant combinations (`.footer a`). Rules the inliner
+//! can't apply (`@media`, pseudo-classes, attribute selectors, `>`/`+`/`~`)
+//! are kept in a single `<style>` block in `<head>` for the clients that
+//! support them.
+//!
+//! ```rust,ignore
+//! let html = engine.render_email(&module, &context)?;
+//! ```
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Elements removed together with their content.
+const STRIPPED_ELEMENTS: &[&str] = &[
+    "script", "noscript", "iframe", "object", "video", "audio", "canvas", "form",
+];
+
+/// Tags removed on their own.
+const STRIPPED_TAGS: &[&str] = &["link", "embed", "base"];
+
+/// HTML void elements, which never get an entry on the open-element stack.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// One compound selector such as `td.label#total`.
+#[derive(Debug, Clone, PartialEq)]
+struct Compound {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+}
+
+/// A CSS rule the inliner can apply.
+#[derive(Debug, Clone)]
+struct Rule {
+    /// Compound selectors, outermost ancestor first.
+    selector: Vec<Compound>,
+    /// Specificity as (ids, classes, tags).
+    specificity: (usize, usize, usize),
+    /// Position in the stylesheet, for equal specificity.
+    order: usize,
+    declarations: Vec<(String, String)>,
+}
+
+/// An open element, as far as selector matching is concerned.
+#[derive(Debug, Clone)]
+struct Element {
+    tag: String,
+    id: Option<String>,
+    classes: Vec<String>,
+}
+
+/// Inlines `<style>` rules and removes tags mail clients don't support.
+///
+/// See the [module documentation](self) for what is supported.
+pub fn inline_email_html(html: &str) -> String {
+    static STYLE: OnceLock<Regex> = OnceLock::new();
+    let style_re = STYLE.get_or_init(|| {
+        Regex::new(r"(?is)<style\b[^>]*>(.*?)</style\s*>").expect("style element pattern is valid")
+    });
+    let mut css = String::new();
+    for caps in style_re.captures_iter(html) {
+        css.push_str(&caps[1]);
+        css.push('\n');
+    }
+    let html = style_re.replace_all(html, "");
+
+    let (rules, retained) = parse_stylesheet(&css);
+    let mut output = apply_rules(&html, &rules);
+
+    if !retained.is_empty() {
+        let block = format!("<style>{}</style>", retained.join("\n"));
+        match output.to_ascii_lowercase().find("</head>") {
+            Some(index) => output.insert_str(index, &block),
+            None => output.insert_str(0, &block),
+        }
+    }
+    output
+}
+
+/// Splits a stylesheet into rules the inliner applies and the source text of
+/// those it keeps in a `<style>` block.
+fn parse_stylesheet(css: &str) -> (Vec<Rule>, Vec<String>) {
+    static COMMENT: OnceLock<Regex> = OnceLock::new();
+    let comment_re = COMMENT.get_or_init(|| {
+        Regex::new(r"(?s)/\*.*?\*/").expect("comment pattern is valid")
+    });
+    let css = comment_re.replace_all(css, "");
+
+    let mut rules = Vec::new();
+    let mut retained = Vec::new();
+    let mut rest = css.trim();
+
+    while !rest.is_empty() {
+        let Some(open) = rest.find('{') else { break };
+        let prelude = rest[..open].trim();
+        let Some(close) = matching_brace(rest, open) else {
+            break;
+        };
+        let body = &rest[open + 1..close];
+
+        if prelude.starts_with('@') {
+            retained.push(rest[..=close].trim().to_string());
+        } else {
+            let declarations = parse_declarations(body);
+            for selector in prelude.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                match parse_selector(selector) {
+                    Some(compounds) => rules.push(Rule {
+                        specificity: specificity(&compounds),
+                        selector: compounds,
+                        order: rules.len(),
+                        declarations: declarations.clone(),
+                    }),
+                    None => retained.push(format!("{} {{{}}}", selector, body.trim())),
+                }
+            }
+        }
+        rest = rest[close + 1..].trim_start();
+    }
+
+    (rules, retained)
+}
+
+/// Finds the `}` closing the block opened at `open`, allowing nested blocks.
+fn matching_brace(css: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in css[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_declarations(body: &str) -> Vec<(String, String)> {
+    body.split(';')
+        .filter_map(|declaration| {
+            let (property, value) = declaration.split_once(':')?;
+            let (property, value) = (property.trim(), value.trim());
+            if property.is_empty() || value.is_empty() {
+                return None;
+            }
+            Some((property.to_ascii_lowercase(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Parses a selector made of compounds joined by descendant combinators.
+/// Returns `None` for anything else.
+fn parse_selector(selector: &str) -> Option<Vec<Compound>> {
+    static COMPOUND: OnceLock<Regex> = OnceLock::new();
+    let compound_re = COMPOUND.get_or_init(|| {
+        Regex::new(r"^([a-zA-Z][a-zA-Z0-9-]*|\*)?((?:[.#][a-zA-Z_-][a-zA-Z0-9_-]*)*)$")
+            .expect("compound pattern is valid")
+    });
+    static PART: OnceLock<Regex> = OnceLock::new();
+    let part_re = PART.get_or_init(|| {
+        Regex::new(r"[.#][a-zA-Z0-9_-]+").expect("selector part pattern is valid")
+    });
+
+    selector
+        .split_whitespace()
+        .map(|part| {
+            let caps = compound_re.captures(part)?;
+            let tag = caps
+                .get(1)
+                .map(|m| m.as_str().to_ascii_lowercase())
+                .filter(|tag| tag != "*");
+            let mut compound = Compound { tag, id: None, classes: Vec::new() };
+            for m in part_re.find_iter(&caps[2]) {
+                let (kind, name) = m.as_str().split_at(1);
+                if kind == "#" {
+                    compound.id = Some(name.to_string());
+                } else {
+                    compound.classes.push(name.to_string());
+                }
+            }
+            Some(compound)
+        })
+        .collect()
+}
+
+fn specificity(selector: &[Compound]) -> (usize, usize, usize) {
+    selector.iter().fold((0, 0, 0), |(ids, classes, tags), compound| {
+        (
+            ids + usize::from(compound.id.is_some()),
+            classes + compound.classes.len(),
+            tags + usize::from(compound.tag.is_some()),
+        )
+    })
+}
+
+impl Compound {
+    fn matches(&self, element: &Element) -> bool {
+        self.tag.as_ref().map_or(true, |tag| *tag == element.tag)
+            && self.id.as_ref().map_or(true, |id| element.id.as_ref() == Some(id))
+            && self.classes.iter().all(|class| element.classes.contains(class))
+    }
+}
+
+impl Rule {
+    /// Matches the last compound against `element` and the others, right to
+    /// left, against its ancestors.
+    fn matches(&self, element: &Element, ancestors: &[Element]) -> bool {
+        let Some((last, outer)) = self.selector.split_last() else {
+            return false;
+        };
+        if !last.matches(element) {
+            return false;
+        }
+        let mut ancestors = ancestors.iter().rev();
+        outer
+            .iter()
+            .rev()
+            .all(|compound| ancestors.any(|ancestor| compound.matches(ancestor)))
+    }
+}
+
+/// Rewrites every start tag with the styles of the rules matching it, and
+/// drops the tags in [`STRIPPED_ELEMENTS`] and [`STRIPPED_TAGS`].
+fn apply_rules(html: &str, rules: &[Rule]) -> String {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    let tag_re = TAG.get_or_init(|| {
+        Regex::new(r"<(/?)([a-zA-Z][a-zA-Z0-9:-]*)([^>]*)>").expect("tag pattern is valid")
+    });
+    let mut output = String::with_capacity(html.len());
+    let mut stack: Vec<Element> = Vec::new();
+    let mut position = 0;
+
+    while let Some(caps) = tag_re.captures_at(html, position) {
+        let whole = caps.get(0).unwrap();
+        output.push_str(&html[position..whole.start()]);
+        position = whole.end();
+
+        let closing = !caps[1].is_empty();
+        let tag = caps[2].to_ascii_lowercase();
+        let attributes = &caps[3];
+
+        if closing {
+            if let Some(index) = stack.iter().rposition(|element| element.tag == tag) {
+                stack.truncate(index);
+            }
+            output.push_str(whole.as_str());
+            continue;
+        }
+
+        if STRIPPED_ELEMENTS.contains(&tag.as_str()) {
+            let end_tag = format!("</{}", tag);
+            position = html[position..]
+                .to_ascii_lowercase()
+                .find(&end_tag)
+                .and_then(|offset| {
+                    let end = position + offset;
+                    html[end..].find('>').map(|close| end + close + 1)
+                })
+                .unwrap_or(html.len());
+            continue;
+        }
+        if STRIPPED_TAGS.contains(&tag.as_str()) {
+            continue;
+        }
+
+        let element = Element {
+            tag: tag.clone(),
+            id: attribute_value(attributes, "id"),
+            classes: attribute_value(attributes, "class")
+                .map(|classes| classes.split_whitespace().map(String::from).collect())
+                .unwrap_or_default(),
+        };
+
+        let mut matched: Vec<&Rule> = rules
+            .iter()
+            .filter(|rule| rule.matches(&element, &stack))
+            .collect();
+        matched.sort_by_key(|rule| (rule.specificity, rule.order));
+
+        let self_closing = attributes.trim_end().ends_with('/');
+        if matched.is_empty() {
+            output.push_str(whole.as_str());
+        } else {
+            output.push_str(&styled_tag(&caps[2], attributes, &matched, self_closing));
+        }
+
+        if !self_closing && !VOID_ELEMENTS.contains(&tag.as_str()) {
+            stack.push(element);
+        }
+    }
+
+    output.push_str(&html[position..]);
+    output
+}
+
+/// Builds a start tag whose `style` attribute holds the matched rules'
+/// declarations followed by the tag's own inline style.
+fn styled_tag(tag: &str, attributes: &str, rules: &[&Rule], self_closing: bool) -> String {
+    static STYLE_ATTR: OnceLock<Regex> = OnceLock::new();
+    let style_attr_re = STYLE_ATTR.get_or_init(|| {
+        Regex::new(r#"(?i)\sstyle\s*=\s*("[^"]*"|'[^']*')"#)
+            .expect("style attribute pattern is valid")
+    });
+
+    let mut declarations: Vec<(String, String)> = Vec::new();
+    let inline = attribute_value(attributes, "style").unwrap_or_default();
+    let inline = parse_declarations(&inline);
+    for (property, value) in rules.iter().flat_map(|rule| rule.declarations.iter()).chain(&inline) {
+        match declarations.iter_mut().find(|(existing, _)| existing == property) {
+            Some(declaration) => declaration.1 = value.clone(),
+            None => declarations.push((property.clone(), value.clone())),
+        }
+    }
+
+    let style = declarations
+        .iter()
+        .map(|(property, value)| format!("{}: {}", property, value.replace('"', "'")))
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    let attributes = style_attr_re.replace_all(attributes, "");
+    let attributes = attributes.trim_end();
+    let attributes = attributes.strip_suffix('/').unwrap_or(attributes).trim_end();
+    format!(
+        "<{}{} style=\"{}\"{}>",
+        tag,
+        attributes,
+        style,
+        if self_closing { " /" } else { "" }
+    )
+}
+
+/// Returns the value of a quoted attribute in a start tag's attribute text.
+fn attribute_value(attributes: &str, name: &str) -> Option<String> {
+    static ATTRIBUTE: OnceLock<Regex> = OnceLock::new();
+    let re = ATTRIBUTE.get_or_init(|| {
+        Regex::new(r#"(?:^|\s)([^\s=/>"']+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#)
+            .expect("attribute pattern is valid")
+    });
+    let caps = re.captures_iter(attributes).find(|caps| caps[1].eq_ignore_ascii_case(name))?;
+    caps.get(2).or_else(|| caps.get(3)).map(|m| m.as_str().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inlines_rules_by_specificity() {
+        let html = r#"<html><head><style>
+            p { color: black; margin: 0 }
+            .note { color: gray; }
+            p.note { font-weight: bold }
+            .footer a { color: #555; }
+            a:hover { color: red; }
+            @media (max-width: 600px) { p { font-size: 14px; } }
+        </style></head><body>
+            <p class="note" style="margin: 4px">Hi</p>
+            <div class="footer"><a href="/x">Unsubscribe</a></div>
+            <a href="/y">Home</a>
+        </body></html>"#;
+
+        let output = inline_email_html(html);
+        assert!(
+            output.contains(r#"<p class="note" style="color: gray; margin: 4px; font-weight: bold">Hi</p>"#),
+            "{}",
+            output
+        );
+        assert!(output.contains(r#"<a href="/x" style="color: #555">Unsubscribe</a>"#), "{}", output);
+        assert!(output.contains(r#"<a href="/y">Home</a>"#), "{}", output);
+        assert!(
+            output.contains("<style>a:hover {color: red;}\n@media (max-width: 600px) { p { font-size: 14px; } }</style></head>"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_strips_unsupported_tags() {
+        let html = r#"<link rel="stylesheet" href="/app.css"><p>Text</p><script>track()</script><iframe src="/x"></iframe><img src="/logo.png" />"#;
+        assert_eq!(inline_email_html(html), r#"<p>Text</p><img src="/logo.png" />"#);
+    }
+}
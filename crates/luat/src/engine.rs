@@ -45,13 +45,20 @@
 //! On native builds, the engine uses `Arc<Mutex<...>>` for thread-safe caching.
 //! On WASM builds, it uses `Rc<RefCell<...>>` for single-threaded operation.
 
+use crate::ast::TemplateAST;
 use crate::cache::*;
 use crate::codegen::*;
+use crate::auto_import::ComponentDirs;
+use crate::defines::Defines;
+use crate::passes::CompilePasses;
+use crate::warning::CompileWarning;
+use crate::directives::{BlockDirective, BlockDirectives};
 use crate::error::{LuatError, Result};
+use crate::render_limits::{RenderLimitExceeded, RenderLimits};
+use crate::sandbox::SandboxProfile;
 use crate::parser::parse_template;
 use crate::resolver::*;
 use crate::transform::*;
-use crate::transform::validate_ir;
 use crate::sourcemap::BundleSourceMap;
 use mlua::LuaSerdeExt;
 use mlua::{Lua, Table, Value};
@@ -59,6 +66,63 @@ use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use std::path::Path;
 
+/// Registry key holding raw output sites recorded in escape audit mode.
+const ESCAPE_AUDIT_REGISTRY_KEY: &str = "__luat_escape_audit";
+
+/// Registry key holding the raw output sites of the request being audited.
+const REQUEST_ESCAPE_AUDIT_REGISTRY_KEY: &str = "__luat_request_escape_audit";
+
+/// Registry key holding the function chunked bundles read their chunks with.
+const READ_CHUNK_REGISTRY_KEY: &str = "__luat_read_chunk";
+
+/// Builds the `children` prop of a layout from the render function, props,
+/// runtime and module path of the template it wraps: callable like a
+/// component's children (`{@render children()}`), and the HTML itself where
+/// a string is expected (`{@html props.children}`). The template renders
+/// the first time either asks for it; see [`Engine::layout_chain`].
+const LAYOUT_CHILDREN_LUA: &str = r#"
+local render, props, runtime, path, set_current_module = ...
+local html
+local function rendered()
+  if html == nil then
+    local previous = set_current_module(path)
+    html = render(props, runtime)
+    set_current_module(previous)
+  end
+  return html
+end
+return setmetatable({}, {
+  __call = function(_, write)
+    if write == nil then return rendered() end
+    write(rendered(), true)
+  end,
+  __tostring = function() return rendered() end,
+  __concat = function(a, b) return tostring(a) .. tostring(b) end,
+  __len = function() return #rendered() end,
+})
+"#;
+
+/// Renders an `{#await}` branch registered for a deferred value into a
+/// string, given the branch and the settled value.
+const RENDER_FRAGMENT_LUA: &str = r#"
+local branch, value = ...
+local out = {}
+branch(function(content) out[#out + 1] = tostring(content) end, value)
+return table.concat(out)
+"#;
+
+/// A page inside its layouts, ready to render; see [`Engine::layout_chain`].
+struct LayoutChain {
+    /// The render function of the outermost template
+    render: mlua::Function,
+    /// The props of the outermost template
+    context: Value,
+    /// The runtime all templates of the chain render with
+    runtime: Table,
+    /// The compiled templates of the chain, the outermost first
+    modules: Vec<SharedPtr<Module>>,
+}
+
 /// Helper function to convert absolute path to relative path.
 /// Used in closures where self is not available.
 fn to_relative_path(absolute_path: &str, root_path: &Option<String>) -> String {
@@ -74,6 +138,33 @@ fn to_relative_path(absolute_path: &str, root_path: &Option<String>) -> String {
         .unwrap_or_else(|| absolute_path.to_string())
 }
 
+/// Compiles a URL path pattern for [`Engine::add_route_headers`]: `*`
+/// matches anything but `/`, and `**` any characters, where `/**` also
+/// matches nothing, so `/docs/**` covers `/docs` itself.
+fn path_pattern_regex(pattern: &str) -> regex::Regex {
+    let mut expr = String::from("^");
+    let mut rest = pattern;
+    while let Some(star) = rest.find('*') {
+        let prefix = &rest[..star];
+        if rest[star..].starts_with("**") {
+            let (prefix, any) = match prefix.strip_suffix('/') {
+                Some(prefix) => (prefix, "(?:/.*)?"),
+                None => (prefix, ".*"),
+            };
+            expr.push_str(&regex::escape(prefix));
+            expr.push_str(any);
+            rest = &rest[star + 2..];
+        } else {
+            expr.push_str(&regex::escape(prefix));
+            expr.push_str("[^/]*");
+            rest = &rest[star + 1..];
+        }
+    }
+    expr.push_str(&regex::escape(rest));
+    expr.push('$');
+    regex::Regex::new(&expr).expect("escaped path pattern is a valid regex")
+}
+
 // Conditional imports for thread primitives
 #[cfg(not(target_arch = "wasm32"))]
 use std::sync::{Arc, Mutex};
@@ -111,6 +202,70 @@ pub struct Engine<R: ResourceResolver> {
     lua: Lua,
     /// Root path for computing relative paths in error messages
     root_path: Option<String>,
+    /// Maximum rendered output per template render, in bytes
+    max_output_bytes: Option<usize>,
+    /// Instruction and time limits per render
+    render_limits: RenderLimits,
+    /// Whether raw (unescaped) output sites are recorded during rendering
+    escape_audit: bool,
+    /// Whether time, randomness and table iteration order are frozen
+    deterministic: bool,
+    /// Whether globals set by a request are undone after it
+    isolate_requests: bool,
+    /// Response headers for request paths matching a pattern, in order added
+    route_headers: Vec<(regex::Regex, HashMap<String, String>)>,
+    /// Block directives, defines, whitespace collapsing, component
+    /// directories and warnings of compiles, shared with the module searcher
+    passes: CompilePasses,
+    /// Translation catalogs, which request locales are negotiated against
+    catalogs: std::sync::Arc<crate::extensions::Catalogs>,
+    /// Last compile error per template, with the hash of the failing source
+    #[cfg(not(target_arch = "wasm32"))]
+    compile_diagnostics: Mutex<HashMap<String, (u64, LuatError)>>,
+    #[cfg(target_arch = "wasm32")]
+    compile_diagnostics: RefCell<HashMap<String, (u64, LuatError)>>,
+}
+
+/// A module required by a template, as reported by [`Engine::compile_debug`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugDependency {
+    /// Name passed to `require()`.
+    pub module: String,
+    /// Path the resolver maps it to, or `None` if it cannot be resolved.
+    pub resolved: Option<String>,
+}
+
+/// Every compilation stage of a template, returned by [`Engine::compile_debug`].
+#[derive(Debug, Clone)]
+pub struct CompileDebug {
+    /// Entry name the template was requested with.
+    pub entry: String,
+    /// Path reported by the resolver.
+    pub path: String,
+    /// Template source.
+    pub source: String,
+    /// Parsed template.
+    pub ast: TemplateAST,
+    /// Intermediate representation passed to code generation.
+    pub ir: IR,
+    /// Generated Lua source, including the embedded source map comment.
+    pub lua_code: String,
+    /// Mapping from Lua lines to template lines.
+    pub source_map: LuaSourceMap,
+    /// Modules required by the template, in order of appearance.
+    pub dependencies: Vec<DebugDependency>,
+}
+
+impl CompileDebug {
+    /// Returns a readable dump of the AST.
+    pub fn ast_dump(&self) -> String {
+        format!("{:#?}", self.ast)
+    }
+
+    /// Returns a readable dump of the IR.
+    pub fn ir_dump(&self) -> String {
+        format!("{:#?}", self.ir)
+    }
 }
 
 /// Wrapper for a Lua value to be used as template context.
@@ -147,6 +302,258 @@ impl<R: ResourceResolver> Engine<R> {
         self.root_path = Some(root.as_ref().to_string_lossy().to_string());
     }
 
+    /// Sets the maximum number of bytes a single render may produce.
+    ///
+    /// When the limit is exceeded, rendering aborts with an error naming the
+    /// component path (e.g. `Page > List > Row`) that wrote the most output.
+    /// For [`Engine::respond`] and [`Engine::respond_async`], the limit
+    /// covers the whole request: the page and its layouts share one count,
+    /// in which the page's output passed to a layout as `children` counts
+    /// once. `None` (the default) disables the guard.
+    pub fn set_max_output_bytes(&mut self, limit: Option<usize>) {
+        self.max_output_bytes = limit;
+    }
+
+    /// Sets the longest a single render may run.
+    ///
+    /// For [`Engine::respond`] and [`Engine::respond_async`], the limit
+    /// covers the whole request: load functions, `+server.lua` handlers,
+    /// actions and the templates they render. A render that runs longer,
+    /// e.g. a template stuck in a loop, aborts with a
+    /// [`LuatError::TemplateRuntimeError`] naming the template and line that
+    /// was running. The time is checked every 1000 Lua
+    /// instructions, so time spent in a single Rust call such as an HTTP
+    /// request is only noticed after it returns. `None` (the default)
+    /// disables the limit.
+    pub fn set_render_timeout(&mut self, timeout: Option<std::time::Duration>) {
+        self.render_limits.timeout = timeout;
+    }
+
+    /// Sets the most Lua instructions a single render may run, or a whole
+    /// request handled by [`Engine::respond`].
+    ///
+    /// Unlike [`Engine::set_render_timeout`], the limit doesn't depend on
+    /// the machine or its load, so a template that renders once renders
+    /// every time. It is checked every 1000 instructions. `None` (the
+    /// default) disables the limit.
+    pub fn set_instruction_limit(&mut self, limit: Option<u64>) {
+        self.render_limits.instructions = limit;
+    }
+
+    /// Sets the most memory the engine's Lua state may use, in bytes.
+    ///
+    /// The limit covers everything in the state: loaded modules, cached
+    /// components and whatever a render allocates. A template that goes over
+    /// it fails with [`LuatError::ResourceLimit`] and the engine stays usable
+    /// once the garbage is collected. `None` (the default) removes the limit.
+    pub fn set_memory_limit_bytes(&mut self, limit: Option<usize>) -> Result<()> {
+        self.lua.set_memory_limit(limit.unwrap_or(0))?;
+        Ok(())
+    }
+
+    /// Adds response headers for requests whose path matches `pattern`.
+    ///
+    /// In the pattern, `*` matches within a path segment and `**` across
+    /// segments (e.g. `/docs/**`). Headers apply to page and API responses
+    /// from [`Engine::respond`]; headers exported by the route's server
+    /// modules or returned by its handler take precedence, and among
+    /// matching rules later ones win. `luat dev` and `luat serve` add the
+    /// rules from `luat.toml [routes]`.
+    pub fn add_route_headers(&mut self, pattern: &str, headers: HashMap<String, String>) {
+        self.route_headers.push((path_pattern_regex(pattern), headers));
+    }
+
+    /// Registers a custom block directive for `{#name args}...{/name}`.
+    ///
+    /// The directive generates the Lua code of each such block when a
+    /// template using it is compiled; see [`crate::directives`]. Register
+    /// directives before compiling templates: modules already compiled or
+    /// cached keep their code.
+    pub fn register_block_directive(&self, name: &str, directive: impl BlockDirective + 'static) {
+        self.passes.block_directives.register(name, directive);
+    }
+
+    /// Returns the registry of custom block directives.
+    pub fn block_directives(&self) -> &BlockDirectives {
+        &self.passes.block_directives
+    }
+
+    /// Returns the build-time defines `defines.NAME` in templates resolve to.
+    ///
+    /// Set defines before compiling templates: modules already compiled or
+    /// cached keep their code. See [`crate::defines`].
+    pub fn defines(&self) -> &Defines {
+        &self.passes.defines
+    }
+
+    /// Sets whether every run of whitespace in the static text of HTML
+    /// templates is collapsed to a single space. Text inside `<pre>`,
+    /// `<textarea>`, `<script>` and `<style>` is kept as written.
+    ///
+    /// Set this before compiling templates: modules already compiled or
+    /// cached keep their code. See [`crate::whitespace`].
+    pub fn set_collapse_whitespace(&self, enabled: bool) {
+        self.passes.collapse_whitespace.set(enabled);
+    }
+
+    /// Returns whether whitespace in HTML templates is collapsed.
+    pub fn collapse_whitespace(&self) -> bool {
+        self.passes.collapse_whitespace.get()
+    }
+
+    /// Returns the directories component tags are imported from.
+    ///
+    /// Empty by default; add directories before compiling templates to
+    /// import `<Button>` without a `{#use}` or `require`. See
+    /// [`crate::auto_import`].
+    pub fn component_dirs(&self) -> &ComponentDirs {
+        &self.passes.component_dirs
+    }
+
+    /// Returns the warnings of the templates compiled since the last call,
+    /// those of each template's latest compile, and clears them. See
+    /// [`crate::warning`].
+    pub fn take_warnings(&self) -> Vec<CompileWarning> {
+        self.passes.warnings.take()
+    }
+
+    /// Transforms a parsed template to IR through the engine's compile
+    /// passes; see [`crate::passes`].
+    pub(crate) fn compile_ir(&self, ast: TemplateAST, name: &str, importer: &str) -> Result<IR> {
+        self.passes.compile_ir(ast, name, importer, &self.resolver)
+    }
+
+    /// Makes `require(alias)` load the module `target`.
+    ///
+    /// The alias is registered in `package.preload`, so it takes precedence
+    /// over modules the resolver would find under the same name.
+    pub fn add_module_alias(&self, alias: &str, target: &str) -> Result<()> {
+        let target = target.to_string();
+        let loader = self.lua.create_function(move |lua, _: mlua::MultiValue| {
+            let require: mlua::Function = lua.globals().get("require")?;
+            require.call::<Value>(target.as_str())
+        })?;
+        let package: Table = self.lua.globals().get("package")?;
+        let preload: Table = package.get("preload")?;
+        preload.set(alias, loader)?;
+        Ok(())
+    }
+
+    /// Sets the feature flags read by `flags.enabled()` and `{#feature}`.
+    ///
+    /// Values stored in `overrides` under a flag's name take precedence over
+    /// its definition, so flags can be flipped at runtime without a deploy.
+    pub fn set_feature_flags(
+        &self,
+        flags: crate::extensions::FeatureFlags,
+        overrides: Option<std::sync::Arc<dyn crate::kv::KVStore>>,
+    ) -> Result<()> {
+        crate::extensions::flags::register_flags_module(&self.lua, flags, overrides)?;
+        Ok(())
+    }
+
+    /// Sets the translation catalogs used by `t()` and the `i18n` module.
+    ///
+    /// Request locales are negotiated from `Accept-Language` against the
+    /// catalog locales, falling back to the catalogs' default locale.
+    pub fn set_catalogs(&mut self, catalogs: crate::extensions::Catalogs) -> Result<()> {
+        self.catalogs = std::sync::Arc::new(catalogs);
+        crate::extensions::i18n::register_i18n_module(&self.lua, self.catalogs.clone())?;
+        Ok(())
+    }
+
+    /// Returns the locale of a request: the best catalog locale for its
+    /// `Accept-Language`, or the best locale with formatting data when there
+    /// are no catalogs.
+    fn request_locale(&self, request: &crate::request::LuatRequest) -> String {
+        if self.catalogs.is_empty() {
+            return crate::extensions::fmt::negotiate_locale(request.accept_language()).to_string();
+        }
+        self.catalogs
+            .negotiate(request.accept_language())
+            .unwrap_or(self.catalogs.default_locale())
+            .to_string()
+    }
+
+    /// Sets the asset manifest used by the `asset()` template helper.
+    ///
+    /// Assets listed in the manifest get `integrity` and `crossorigin`
+    /// attributes. `luat build` writes the manifest when `[build] sri` is enabled.
+    pub fn set_asset_manifest(&self, manifest: crate::extensions::AssetManifest) -> Result<()> {
+        crate::extensions::assets::register_asset_helper(&self.lua, manifest)?;
+        Ok(())
+    }
+
+    /// Sets the widths the `img()` template helper generates variants for.
+    pub fn set_image_widths(&self, widths: Vec<u32>) -> Result<()> {
+        crate::extensions::img::register_img_helper(&self.lua, widths)?;
+        Ok(())
+    }
+
+    /// Enables or disables escape audit mode.
+    ///
+    /// While enabled, every `{@html}` block, raw attribute and expression
+    /// interpolated into a `<script>` tag is recorded with its template
+    /// location. Collect the records with [`Engine::take_escape_audit`].
+    pub fn set_escape_audit(&mut self, enabled: bool) {
+        self.escape_audit = enabled;
+    }
+
+    /// Enables or disables deterministic render mode for golden tests.
+    ///
+    /// While enabled, `os.time()`/`os.date()` report a fixed instant in UTC,
+    /// `math.random` is reseeded at the start of every request and standalone
+    /// render, and `pairs` iterates in sorted key order, so the same input
+    /// renders byte-identical output across runs. See
+    /// [`crate::extensions::deterministic`].
+    pub fn set_deterministic(&mut self, enabled: bool) -> Result<()> {
+        crate::extensions::deterministic::set_deterministic(&self.lua, enabled)?;
+        self.deterministic = enabled;
+        Ok(())
+    }
+
+    /// Enables or disables request isolation.
+    ///
+    /// While enabled, [`Engine::respond`] and [`Engine::respond_async`] put
+    /// the Lua globals back as they were before the request, tables reachable
+    /// from them included: keys the request adds are removed and those it
+    /// replaces restored, so one request can't observe another's. Modules
+    /// stay loaded, as without isolation; see [`crate::module_state`] for
+    /// module-level state.
+    pub fn set_request_isolation(&mut self, enabled: bool) {
+        self.isolate_requests = enabled;
+    }
+
+    /// Returns the raw output sites recorded since the last call and clears them.
+    ///
+    /// Returns an empty report when escape audit mode is disabled.
+    pub fn take_escape_audit(&self) -> Result<crate::audit::EscapeAuditReport> {
+        let sites: Table = match self.lua.named_registry_value::<Table>(ESCAPE_AUDIT_REGISTRY_KEY) {
+            Ok(sites) => sites,
+            Err(_) => return Ok(Default::default()),
+        };
+
+        let report = Self::escape_audit_report(&sites)?;
+        self.lua.set_named_registry_value(ESCAPE_AUDIT_REGISTRY_KEY, self.lua.create_table()?)?;
+        Ok(report)
+    }
+
+    /// Converts a table of recorded raw output sites into a report.
+    fn escape_audit_report(sites: &Table) -> Result<crate::audit::EscapeAuditReport> {
+        let mut report = crate::audit::EscapeAuditReport::default();
+        for site in sites.clone().sequence_values::<Table>() {
+            let site = site?;
+            report.sites.push(crate::audit::RawOutputSite {
+                template: site.get::<Option<String>>("template")?.unwrap_or_default(),
+                line: site.get::<Option<usize>>("line")?.unwrap_or(0),
+                expression: site.get::<Option<String>>("expression")?.unwrap_or_default(),
+                kind: site.get::<Option<String>>("kind")?.unwrap_or_default(),
+                value: site.get::<Option<String>>("value")?.unwrap_or_default(),
+            });
+        }
+        Ok(report)
+    }
+
     /// Converts an absolute path to a relative path based on the root.
     ///
     /// If root_path is not set, returns just the filename as a fallback.
@@ -165,54 +572,10 @@ impl<R: ResourceResolver> Engine<R> {
             .unwrap_or_else(|| absolute_path.to_string())
     }
 
-    /// Sandboxes the Lua environment by disabling dangerous functions and libraries.
-    ///
-    /// This removes access to:
-    /// - `io` library (file I/O)
-    /// - `debug` library (introspection)
-    /// - `load`, `loadstring`, `loadfile`, `dofile` (dynamic code execution)
-    /// - Most of `os` library (keeps only `os.date`, `os.time`, `os.clock`, `os.difftime`)
-    fn sandbox_lua(lua: &Lua, globals: &Table) -> Result<()> {
-        // Save load function for internal use before sandboxing
-        // This allows the bundle's module loader to work while preventing user code access
-        let load_fn: mlua::Function = globals.get("load")?;
-        globals.set("__luat_internal_load", load_fn)?;
-
-        // Save safe os functions before removing the library
-        let os_table: Table = globals.get("os")?;
-        let os_date: mlua::Function = os_table.get("date")?;
-        let os_time: mlua::Function = os_table.get("time")?;
-        let os_clock: mlua::Function = os_table.get("clock")?;
-        let os_difftime: mlua::Function = os_table.get("difftime")?;
-
-        // Disable dangerous libraries
-        globals.set("io", mlua::Value::Nil)?;
-        globals.set("debug", mlua::Value::Nil)?;
-
-        // Disable dangerous functions (user code cannot use these)
-        globals.set("load", mlua::Value::Nil)?;
-        globals.set("loadstring", mlua::Value::Nil)?;
-        globals.set("loadfile", mlua::Value::Nil)?;
-        globals.set("dofile", mlua::Value::Nil)?;
-
-        // Create restricted os table with only safe functions
-        let safe_os = lua.create_table()?;
-        safe_os.set("date", os_date)?;
-        safe_os.set("time", os_time)?;
-        safe_os.set("clock", os_clock)?;
-        safe_os.set("difftime", os_difftime)?;
-
-        // Replace os with restricted version
-        globals.set("os", safe_os)?;
-
-        Ok(())
-    }
-
     /// Creates a new engine with the given resolver and cache.
     ///
-    /// This is the low-level constructor. For convenience, prefer
-    /// [`with_memory_cache`](Self::with_memory_cache) or
-    /// [`with_filesystem_cache`](Self::with_filesystem_cache).
+    /// Equivalent to `Engine::builder(resolver).cache(cache).build()`; use
+    /// [`Engine::builder`] for further configuration.
     ///
     /// # Arguments
     ///
@@ -223,12 +586,20 @@ impl<R: ResourceResolver> Engine<R> {
     ///
     /// Returns an error if the Lua runtime fails to initialize.
     pub fn new(resolver: R, cache: Box<dyn Cache>) -> Result<Self> {
+        Self::builder(resolver).cache(cache).build()
+    }
+
+    /// Creates a new engine whose Lua state is sandboxed by `sandbox`.
+    ///
+    /// Use [`EngineBuilder::sandbox_profile`](crate::EngineBuilder::sandbox_profile)
+    /// to pick the profile.
+    pub(crate) fn with_sandbox(resolver: R, cache: Box<dyn Cache>, sandbox: &SandboxProfile) -> Result<Self> {
         let lua = Lua::new();
         let globals = lua.globals();
 
-        // Security: Sandbox the Lua environment
-        // Disable dangerous libraries and functions while keeping safe ones
-        Self::sandbox_lua(&lua, &globals)?;
+        // Security: remove the libraries and functions the profile doesn't allow
+        let sandbox = sandbox.options();
+        crate::sandbox::restrict_globals(&lua, &sandbox)?;
 
         globals.set(
             "createContextHelpers",
@@ -292,18 +663,50 @@ impl<R: ResourceResolver> Engine<R> {
         // lua.load(write_stream_module)
         //     .set_name("raisin:write_stream")?
         //     .eval()?;
+        let content_resolver = resolver.clone_box();
         // Create the engine instance
         let mut engine = Self {
             resolver,
             cache,
             lua,
             root_path: None,
+            max_output_bytes: None,
+            render_limits: RenderLimits::default(),
+            escape_audit: false,
+            deterministic: false,
+            isolate_requests: false,
+            route_headers: Vec::new(),
+            passes: CompilePasses::default(),
+            catalogs: Default::default(),
+            compile_diagnostics: Default::default(),
         };
 
         // Setup the custom module searcher to resolve Lua modules through our resolver
         engine.setup_custom_searcher()?;
         // Register the json module using the shared implementation
         crate::extensions::json::register_json_module(&engine.lua)?;
+        crate::extensions::assets::register_asset_helper(&engine.lua, Default::default())?;
+        crate::extensions::img::register_img_helper(&engine.lua, crate::extensions::img::DEFAULT_IMAGE_WIDTHS.to_vec())?;
+        crate::extensions::fmt::register_fmt_module(&engine.lua)?;
+        crate::extensions::i18n::register_i18n_module(&engine.lua, engine.catalogs.clone())?;
+        crate::extensions::paginate::register_paginate_module(&engine.lua)?;
+        crate::extensions::url::register_url_module(&engine.lua)?;
+        crate::extensions::seo::register_seo_module(&engine.lua)?;
+        crate::extensions::feed::register_feed_module(&engine.lua)?;
+        crate::extensions::render::register_render_module(&engine.lua)?;
+        crate::extensions::content::register_content_module(&engine.lua, content_resolver)?;
+        #[cfg(feature = "markdown")]
+        crate::extensions::markdown::register_markdown_module(&engine.lua)?;
+        crate::extensions::flags::register_flags_module(&engine.lua, Default::default(), None)?;
+        engine
+            .passes
+            .block_directives
+            .register("feature", crate::extensions::flags::feature_directive);
+        engine
+            .passes
+            .block_directives
+            .register("key", crate::directives::key_directive);
+        crate::sandbox::restrict_require(&engine.lua, &sandbox)?;
 
         Ok(engine)
     }
@@ -336,8 +739,14 @@ impl<R: ResourceResolver> Engine<R> {
         #[cfg(target_arch = "wasm32")]
         let cache_clone2 = Rc::clone(&cache_clone);
 
+        #[cfg(not(target_arch = "wasm32"))]
+        let resolver_for_chunks = Arc::clone(&resolver_clone);
+        #[cfg(target_arch = "wasm32")]
+        let resolver_for_chunks = Rc::clone(&resolver_clone);
+
         // Clone root_path for use in closures (for relative path display in errors)
         let root_path_for_searcher = self.root_path.clone();
+        let passes_for_searcher = self.passes.clone();
 
         // 1. SEARCHER 1: CACHE-BASED SEARCHER
         // This searcher checks if the module is already in the cache
@@ -356,7 +765,7 @@ impl<R: ResourceResolver> Engine<R> {
             let cache_key = format!("module:{}", module_path);
 
             #[cfg(not(target_arch = "wasm32"))]
-            let cache = cache_clone.lock().unwrap();
+            let cache = cache_clone.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
             #[cfg(target_arch = "wasm32")]
             let cache = cache_clone.borrow();
 
@@ -364,6 +773,11 @@ impl<R: ResourceResolver> Engine<R> {
             if let Ok(Some(module)) = cache.get(&cache_key) {
                 //println!("DEBUG: Found module in cache with exact key: {}", cache_key);
                 // Found in cache, create loader function
+                if crate::module_state::ModuleState::of_source(&module.lua_code)
+                    == crate::module_state::ModuleState::PerRequest
+                {
+                    crate::module_state::mark_per_request(lua, &module_name)?;
+                }
                 match lua.load(&module.lua_code).into_function() {
                     Ok(loader) => {
                         // Return the loader function and the module path
@@ -400,9 +814,9 @@ impl<R: ResourceResolver> Engine<R> {
 
             // Try to resolve the module through our resolver
             #[cfg(not(target_arch = "wasm32"))]
-            let resolver = resolver_clone.lock().unwrap();
+            let resolver = resolver_clone.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
             #[cfg(not(target_arch = "wasm32"))]
-            let cache = cache_clone2.lock().unwrap();
+            let cache = cache_clone2.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
 
             #[cfg(target_arch = "wasm32")]
             let resolver = resolver_clone.borrow();
@@ -426,7 +840,13 @@ impl<R: ResourceResolver> Engine<R> {
                                 ast.path = Some(resolved.path.clone());
 
                                 // Transform to IR
-                                match transform_ast(ast) {
+                                let ir = passes_for_searcher.compile_ir(
+                                    ast,
+                                    &resolved.path,
+                                    &resolved.path,
+                                    &**resolver,
+                                );
+                                match ir {
                                     Ok(ir) => {
                                         // Extract module name for codegen
                                         let module_name = std::path::Path::new(&resolved.path)
@@ -437,6 +857,10 @@ impl<R: ResourceResolver> Engine<R> {
 
                                         let components: Vec<String> =
                                             ir.components.clone().into_iter().collect();
+                                        let metadata = crate::metadata::ComponentMetadata::from_ir(
+                                            &ir,
+                                            &resolved.source,
+                                        );
                                         match generate_lua_code(ir, &module_name) {
                                             Ok(lua_code) => {
                                                 // Calculate hash of source code
@@ -447,18 +871,16 @@ impl<R: ResourceResolver> Engine<R> {
                                                 let hash = hasher.finish();
 
                                                 // Create a module and cache it
-                                                #[cfg(not(target_arch = "wasm32"))]
-                                                let module = Arc::new(Module::new(
+                                                let mut module = Module::new(
                                                     module_name.clone(),
                                                     lua_code.clone(),
                                                     components,
-                                                ));
+                                                );
+                                                module.metadata = metadata;
+                                                #[cfg(not(target_arch = "wasm32"))]
+                                                let module = Arc::new(module);
                                                 #[cfg(target_arch = "wasm32")]
-                                                let module = Rc::new(Module::new(
-                                                    module_name.clone(),
-                                                    lua_code.clone(),
-                                                    components,
-                                                ));
+                                                let module = Rc::new(module);
 
                                                 // Use the canonical path as cache key
                                                 let cache_key = format!("module:{}", resolved.path);
@@ -507,6 +929,12 @@ impl<R: ResourceResolver> Engine<R> {
                         (resolved.source, None)
                     };
 
+                    if crate::module_state::ModuleState::of_source(&content)
+                        == crate::module_state::ModuleState::PerRequest
+                    {
+                        crate::module_state::mark_per_request(lua, &original_module_name)?;
+                    }
+
                     // Create a loader function for the module
                     // Set the chunk name to relative path for readable error messages
                     // The @ prefix tells Lua this is a file path
@@ -574,6 +1002,22 @@ impl<R: ResourceResolver> Engine<R> {
             }
         })?;
 
+        // Chunked bundles read their module chunks through the resolver,
+        // unless `set_chunk_root` points them to a directory
+        let chunk_resolver = resolver_for_chunks;
+        let read_chunk = self.lua.create_function(move |_, path: String| {
+            #[cfg(not(target_arch = "wasm32"))]
+            let resolver = chunk_resolver.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+            #[cfg(target_arch = "wasm32")]
+            let resolver = chunk_resolver.borrow();
+
+            resolver
+                .resolve("", &path)
+                .map(|resolved| resolved.source)
+                .map_err(|e| mlua::Error::RuntimeError(format!("cannot load bundle chunk {}: {}", path, e)))
+        })?;
+        self.lua.set_named_registry_value(READ_CHUNK_REGISTRY_KEY, read_chunk)?;
+
         // Get the length of the existing searchers table
         let searchers_len = searchers.raw_len();
         // println!("DEBUG: Current searchers length: {}", searchers_len);
@@ -604,8 +1048,7 @@ impl<R: ResourceResolver> Engine<R> {
     /// let engine = Engine::with_memory_cache(resolver, 100)?;
     /// ```
     pub fn with_memory_cache(resolver: R, cache_size: usize) -> Result<Self> {
-        let cache = Box::new(MemoryCache::new(cache_size));
-        Self::new(resolver, cache)
+        Self::builder(resolver).memory_cache(cache_size)?.build()
     }
 
     /// Creates a new engine with a filesystem-backed cache.
@@ -624,8 +1067,7 @@ impl<R: ResourceResolver> Engine<R> {
         cache_dir: P,
         memory_size: usize,
     ) -> Result<Self> {
-        let cache = Box::new(FileSystemCache::new(cache_dir, memory_size)?);
-        Self::new(resolver, cache)
+        Self::builder(resolver).filesystem_cache(cache_dir, memory_size)?.build()
     }
 
     /// Compiles a template entry point and returns the compiled module.
@@ -710,23 +1152,7 @@ impl<R: ResourceResolver> Engine<R> {
 
                 // Now compile the template with actual file content
                 // Pass the resolved path so it can be used for relative import resolution
-                let module = match self.compile_template_string_with_path(
-                    &module_path,
-                    &resolved.source,
-                    Some(resolved.path),
-                ) {
-                    Ok(m) => {
-                        //println!("DEBUG: Successfully compiled module {}", module_path);
-                        //if !pending.is_empty() {
-                        //    println!("DEBUG: New pending modules: {:?}", pending);
-                        //}
-                        m
-                    }
-                    Err(e) => {
-                        //println!("DEBUG: Error compiling module {}: {:?}", module_path, e);
-                        return Err(e);
-                    }
-                };
+                let module = self.compile_source_cached(&module_path, resolved)?;
 
                 compiled_modules.insert(module_path, module);
             }
@@ -763,6 +1189,91 @@ impl<R: ResourceResolver> Engine<R> {
         }
     }
 
+    /// Compiles a resolved template, reusing the result of an earlier compile
+    /// of the same source.
+    ///
+    /// Compiled modules are cached under the source hash, and the last compile
+    /// error of each template is kept alongside, so requests to an unchanged
+    /// broken template report the same diagnostics without recompiling.
+    fn compile_source_cached(&self, module_path: &str, resolved: ResolvedResource) -> Result<SharedPtr<Module>> {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        resolved.source.hash(&mut hasher);
+        resolved.path.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let source_key = format!("source:{}:{}", module_path, hash);
+        if let Ok(Some(module)) = self.cache.get(&source_key) {
+            return Ok(module);
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut diagnostics = self
+            .compile_diagnostics
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        #[cfg(target_arch = "wasm32")]
+        let mut diagnostics = self.compile_diagnostics.borrow_mut();
+
+        if let Some((failed_hash, error)) = diagnostics.get(module_path) {
+            if *failed_hash == hash {
+                return Err(error.clone());
+            }
+        }
+
+        match self.compile_template_string_with_path(module_path, &resolved.source, Some(resolved.path)) {
+            Ok(module) => {
+                diagnostics.remove(module_path);
+                let _ = self.cache.set(&source_key, module.clone());
+                Ok(module)
+            }
+            Err(e) => {
+                diagnostics.insert(module_path.to_string(), (hash, e.clone()));
+                Err(e)
+            }
+        }
+    }
+
+    /// Compiles a template and returns every stage of the compilation.
+    ///
+    /// Unlike [`compile_entry`](Self::compile_entry), nothing is cached and
+    /// dependencies are resolved but not compiled. Used by `luat inspect` to
+    /// debug code generation.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let debug = engine.compile_debug("pages/index.luat")?;
+    /// println!("{}", debug.lua_code);
+    /// ```
+    pub fn compile_debug(&self, entry: &str) -> Result<CompileDebug> {
+        let resolved = self.resolver.resolve("", entry)?;
+        let ast = crate::enhanced_parser::parse_template_with_context(&resolved.source, Some(entry))?;
+        let ir = self.compile_ir(ast.clone(), entry, &resolved.path)?;
+        let (lua_code, source_map) = generate_lua_code_with_sourcemap(ir.clone(), entry)?;
+
+        let mut dependencies: Vec<DebugDependency> = Vec::new();
+        for module in &ast.imports {
+            if dependencies.iter().any(|d| &d.module == module) {
+                continue;
+            }
+            dependencies.push(DebugDependency {
+                module: module.clone(),
+                resolved: self.resolver.get_resolved_path(&resolved.path, module).ok(),
+            });
+        }
+
+        Ok(CompileDebug {
+            entry: entry.to_string(),
+            path: resolved.path,
+            source: resolved.source,
+            ast,
+            ir,
+            lua_code,
+            source_map,
+            dependencies,
+        })
+    }
+
     /// Renders a compiled template with the given context data.
     ///
     /// This method executes the template's Lua code with the provided context,
@@ -793,6 +1304,175 @@ impl<R: ResourceResolver> Engine<R> {
     /// assert!(html.contains("Hello, World"));
     /// ```
     pub fn render(&self, module: &Module, context: &Value) -> Result<String> {
+        let output = self.render_output(module, context)?;
+        let html = output.to_str().map_err(|_| {
+            LuatError::InvalidTemplate(format!(
+                "Output of '{}' is not valid UTF-8; use render_bytes for binary output",
+                module.path.as_deref().unwrap_or(&module.name)
+            ))
+        })?;
+        Ok(html.to_string())
+    }
+
+    /// Renders a template and returns its output as raw bytes.
+    ///
+    /// Unlike [`Engine::render`], output that isn't valid UTF-8 (e.g. bytes
+    /// read with `kv:get(key, "arrayBuffer")` and written with `{@html}`) is
+    /// returned unchanged.
+    pub fn render_bytes(&self, module: &Module, context: &Value) -> Result<Vec<u8>> {
+        Ok(self.render_output(module, context)?.as_bytes().to_vec())
+    }
+
+    /// Renders a template into `writer`, in chunks as the template produces
+    /// them.
+    ///
+    /// Unlike [`Engine::render`], the page isn't buffered whole: the top-level
+    /// template hands its output over about every 8 KiB, so large pages start
+    /// flushing early and memory stays bounded by the largest component.
+    /// Output written before an error has already reached `writer`.
+    ///
+    /// For an async body, use [`Engine::render_to_async_writer`].
+    ///
+    /// ```rust,ignore
+    /// let module = engine.compile_entry("report.luat")?;
+    /// let mut out = std::io::BufWriter::new(std::fs::File::create("report.html")?);
+    /// engine.render_to_writer(&module, &context, &mut out)?;
+    /// ```
+    pub fn render_to_writer<W: std::io::Write>(&self, module: &Module, context: &Value, writer: &mut W) -> Result<()> {
+        let mut write_error = None;
+        let rendered = self.lua.scope(|scope| {
+            let sink = scope.create_function_mut(|_, chunk: mlua::String| {
+                writer.write_all(&chunk.as_bytes()).map_err(|e| {
+                    let message = e.to_string();
+                    write_error = Some(e);
+                    mlua::Error::RuntimeError(message)
+                })
+            })?;
+            Ok(self.render_output_to(module, context, Some(sink)))
+        })?;
+        let rest = match rendered {
+            Ok(rest) => rest,
+            Err(e) => return Err(write_error.map(LuatError::IoError).unwrap_or(e)),
+        };
+        // Templates compiled before streaming return all of their output here
+        writer.write_all(&rest.as_bytes())?;
+        Ok(())
+    }
+
+    /// Renders a template into an async `writer`, in chunks as the template
+    /// produces them.
+    ///
+    /// Like [`Engine::render_to_writer`], but the render waits for each chunk
+    /// to be written before producing the next, so a slow client holds back
+    /// the render rather than growing a buffer.
+    ///
+    /// Only available when the `async-lua` feature is enabled.
+    ///
+    /// ```rust,ignore
+    /// let (mut client, server) = tokio::io::duplex(64 * 1024);
+    /// engine.render_to_async_writer(&module, &context, &mut client).await?;
+    /// ```
+    #[cfg(feature = "async-lua")]
+    pub async fn render_to_async_writer<W: tokio::io::AsyncWrite + Unpin>(
+        &self,
+        module: &Module,
+        context: &Value,
+        writer: &mut W,
+    ) -> Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        // One chunk in flight: the sink waits until the writer took the last
+        let (sender, mut receiver) = tokio::sync::mpsc::channel::<Vec<u8>>(1);
+        let sender = std::sync::Arc::new(std::sync::Mutex::new(Some(sender)));
+        let sink_sender = sender.clone();
+        let sink = self.lua.create_async_function(move |_, chunk: mlua::String| {
+            let sender = sink_sender.lock().ok().and_then(|sender| sender.clone());
+            let chunk = chunk.as_bytes().to_vec();
+            async move {
+                let closed = || mlua::Error::RuntimeError("Output writer closed".to_string());
+                sender.ok_or_else(closed)?.send(chunk).await.map_err(|_| closed())
+            }
+        })?;
+
+        let render = async {
+            let rendered = self.render_output_to_async(module, context, sink).await;
+            // Ends the write loop once the last chunk is taken
+            if let Ok(mut sender) = sender.lock() {
+                sender.take();
+            }
+            rendered
+        };
+        let write = async move {
+            while let Some(chunk) = receiver.recv().await {
+                if let Err(e) = writer.write_all(&chunk).await {
+                    return (writer, Some(e));
+                }
+            }
+            (writer, None)
+        };
+        let (rendered, (writer, write_error)) = tokio::join!(render, write);
+
+        let rest = match (rendered, write_error) {
+            (_, Some(e)) => return Err(LuatError::IoError(e)),
+            (Ok(rest), None) => rest,
+            (Err(e), None) => return Err(e),
+        };
+        // Templates compiled before streaming return all of their output here
+        writer.write_all(&rest.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// Renders a template for sending as an email.
+    ///
+    /// The output of [`Engine::render`] goes through
+    /// [`inline_email_html`](crate::email::inline_email_html): `<style>` rules
+    /// are inlined into `style` attributes and tags mail clients don't
+    /// support are removed.
+    pub fn render_email(&self, module: &Module, context: &Value) -> Result<String> {
+        let html = self.render(module, context)?;
+        Ok(crate::email::inline_email_html(&html))
+    }
+
+    /// Loads `module` with its dependencies and runs its `render` function.
+    fn render_output(&self, module: &Module, context: &Value) -> Result<mlua::String> {
+        self.render_output_to(module, context, None)
+    }
+
+    /// Loads `module` with its dependencies and runs its `render` function,
+    /// which passes its output to `stream` in chunks when given and returns
+    /// the output not yet passed.
+    fn render_output_to(&self, module: &Module, context: &Value, stream: Option<mlua::Function>) -> Result<mlua::String> {
+        let _limits = self.render_limits.start(&self.lua);
+        let render_func = self.module_render_function(module)?;
+
+        let runtime = self.render_runtime()?;
+        // Taken by the template that renders first, so components keep buffering
+        runtime.set("stream", stream)?;
+
+        // Call render function with both context and runtime
+        let result = render_func.call((self.lua.to_value(context)?, &runtime));
+        runtime.set("stream", mlua::Value::Nil)?;
+        result.map_err(|e| self.render_error(&[module], e))
+    }
+
+    /// Async version of [`Engine::render_output_to`], whose `stream` may
+    /// yield.
+    #[cfg(feature = "async-lua")]
+    async fn render_output_to_async(&self, module: &Module, context: &Value, stream: mlua::Function) -> Result<mlua::String> {
+        let _limits = self.render_limits.start(&self.lua);
+        let render_func = self.module_render_function(module)?;
+
+        let runtime = self.render_runtime()?;
+        runtime.set("stream", stream)?;
+
+        let result = render_func.call_async((self.lua.to_value(context)?, &runtime)).await;
+        runtime.set("stream", mlua::Value::Nil)?;
+        result.map_err(|e| self.render_error(&[module], e))
+    }
+
+    /// Loads `module` with its dependencies and returns its `render`
+    /// function.
+    fn module_render_function(&self, module: &Module) -> Result<mlua::Function> {
         // First, ensure all dependencies are loaded recursively
         //println!("DEBUG: Loading dependencies for module: {}", module.name);
         if !module.dependencies.is_empty() {
@@ -813,8 +1493,7 @@ impl<R: ResourceResolver> Engine<R> {
                             let compiled = if dep.ends_with(".luat") {
                                 // Parse and compile the template
                                 let ast = parse_template(&resolved.source)?;
-                                let ir = transform_ast(ast)?;
-                                validate_ir(&ir)?;
+                                let ir = self.compile_ir(ast, dep, &resolved.path)?;
 
                                 let module_name = std::path::Path::new(dep)
                                     .file_stem()
@@ -872,25 +1551,7 @@ impl<R: ResourceResolver> Engine<R> {
 
         let chunk = self.lua.load(&module.lua_code);
         let chunk = chunk.set_name(format!("@{}", self.make_relative_path(&module_path)));
-        let lua_func = match chunk.eval::<Table>() {
-            Ok(f) => f,
-            Err(e) => {
-                // Translate error line numbers using source map if available
-                if let Some(source_map) = &module.source_map {
-                    let original_msg = e.to_string();
-                    let translated_msg = source_map.translate_error(&original_msg);
-                    if translated_msg != original_msg {
-                        return Err(LuatError::TemplateRuntimeError {
-                            template: module.path.clone().unwrap_or_else(|| module.name.clone()),
-                            message: translated_msg,
-                            lua_traceback: None,
-                            source_context: None,
-                        });
-                    }
-                }
-                return Err(LuatError::LuaError(e));
-            }
-        };
+        let lua_func = chunk.eval::<Table>().map_err(|e| self.render_error(&[module], e))?;
 
         // Check if the module has a render function
         if !lua_func.contains_key("render")? {
@@ -900,14 +1561,185 @@ impl<R: ResourceResolver> Engine<R> {
             ));
         }
 
-        let render_func = lua_func.get::<mlua::Function>("render")?;
+        Ok(lua_func.get::<mlua::Function>("render")?)
+    }
+
+    /// Converts an error of rendering `modules`, a template followed by the
+    /// templates it renders such as a layout's children. Render limit errors
+    /// are reported at the template line, and other errors have their line
+    /// numbers translated with the source map of the template they name.
+    fn render_error(&self, modules: &[&Module], e: mlua::Error) -> LuatError {
+        if crate::error::is_memory_error(&e) {
+            return e.into();
+        }
+        let relative_path = |module: &Module| self.make_relative_path(module.path.as_deref().unwrap_or(&module.name));
+        if let Some(exceeded) = RenderLimitExceeded::find(&e) {
+            let module = modules.iter().find(|module| relative_path(module) == exceeded.template).or(modules.first());
+            if let Some(module) = module {
+                return self.render_limit_error(module, exceeded);
+            }
+        }
+        // Translate error line numbers using source map if available
+        let original_msg = e.to_string();
+        let module = modules
+            .iter()
+            .find(|module| original_msg.contains(&format!("{}:", relative_path(module))))
+            .or(modules.first());
+        if let Some(module) = module {
+            if let Some(source_map) = &module.source_map {
+                let translated_msg = source_map.translate_error(&original_msg);
+                if translated_msg != original_msg {
+                    return LuatError::TemplateRuntimeError {
+                        template: module.path.clone().unwrap_or_else(|| module.name.clone()),
+                        message: translated_msg,
+                        lua_traceback: None,
+                        source_context: None,
+                    };
+                }
+            }
+        }
+        LuatError::LuaError(e)
+    }
 
-        // Get the shared runtime from registry (initialized by handle_page_route)
-        // This preserves the context_stack across all renders in a request
+    /// Reports a render limit error at the template line it happened on.
+    ///
+    /// Lines of the rendered template are mapped to its source; lines of
+    /// components it uses stay lines of their generated code.
+    fn render_limit_error(&self, module: &Module, exceeded: &RenderLimitExceeded) -> LuatError {
+        let module_path = module.path.clone().unwrap_or_else(|| module.name.clone());
+        let line = match &module.source_map {
+            Some(map) if exceeded.template == self.make_relative_path(&module_path) => {
+                map.lookup(exceeded.line).unwrap_or(exceeded.line)
+            }
+            _ => exceeded.line,
+        };
+        LuatError::TemplateRuntimeError {
+            template: exceeded.template.clone(),
+            message: format!("{}:{}: {}", exceeded.template, line, exceeded.reason),
+            lua_traceback: None,
+            source_context: None,
+        }
+    }
+
+    /// Prepares a page for rendering inside its layouts, given from the
+    /// outermost to the innermost as in [`Route::layouts`](crate::router::Route).
+    ///
+    /// Each layout gets the merged load props, and as `children` the
+    /// template it wraps, callable like the children of a component:
+    /// `{@render children()}` writes it, and calling it without a writer,
+    /// as in `{@html props.children()}`, returns its HTML, as does
+    /// `{@html props.children}`. The page renders when its layout first
+    /// renders `children`, so whatever a layout writes
+    /// before that comes out before the page.
+    ///
+    /// With `from_bundle`, templates that can't be compiled from source are
+    /// taken from the preloaded bundle.
+    fn layout_chain(
+        &self,
+        page_path: &str,
+        layouts: &[String],
+        props: &serde_json::Map<String, serde_json::Value>,
+        from_bundle: bool,
+    ) -> Result<LayoutChain> {
+        let runtime = self.render_runtime()?;
+        let set_current_module = self.lua.create_function(|lua, path: Option<String>| {
+            let previous: Option<String> = lua.named_registry_value("__luat_current_module")?;
+            lua.set_named_registry_value("__luat_current_module", path)?;
+            Ok(previous)
+        })?;
+
+        let mut modules = Vec::new();
+        let (mut render, mut path) = self.template_render_function(page_path, from_bundle, &mut modules)?;
+        let mut context = self.to_value(serde_json::Value::Object(props.clone()))?;
+        for layout_path in layouts.iter().rev() {
+            let children: Table = self.lua.load(LAYOUT_CHILDREN_LUA).call((
+                render,
+                context,
+                &runtime,
+                path,
+                set_current_module.clone(),
+            ))?;
+            let (layout_render, layout_module_path) =
+                self.template_render_function(layout_path, from_bundle, &mut modules)?;
+            context = self.to_value(serde_json::Value::Object(props.clone()))?;
+            if let Value::Table(table) = &context {
+                table.set("children", children)?;
+            }
+            render = layout_render;
+            path = layout_module_path;
+        }
+        modules.reverse();
+
+        Ok(LayoutChain {
+            render,
+            context,
+            runtime,
+            modules,
+        })
+    }
+
+    /// Returns the render function of the template at `path` and the path
+    /// its `require` calls resolve from, adding its module to `modules`.
+    fn template_render_function(
+        &self,
+        path: &str,
+        from_bundle: bool,
+        modules: &mut Vec<SharedPtr<Module>>,
+    ) -> Result<(mlua::Function, String)> {
+        match self.compile_entry(path) {
+            Ok(module) => {
+                let render = self.module_render_function(&module)?;
+                let module_path = module.path.clone().unwrap_or_else(|| module.name.clone());
+                modules.push(module);
+                Ok((render, module_path))
+            }
+            Err(err) if from_bundle && self.is_not_found_error(&err) => {
+                Ok((self.bundle_render_function(path)?, path.to_string()))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Renders a page prepared with [`Engine::layout_chain`].
+    fn render_layout_chain(&self, chain: LayoutChain) -> Result<String> {
+        let result = chain.render.call::<mlua::String>((chain.context, &chain.runtime));
+        self.layout_chain_output(&chain.modules, result)
+    }
+
+    /// Renders a page prepared with [`Engine::layout_chain`] asynchronously.
+    #[cfg(feature = "async-lua")]
+    async fn render_layout_chain_async(&self, chain: LayoutChain) -> Result<String> {
+        let result = chain.render.call_async::<mlua::String>((chain.context, &chain.runtime)).await;
+        self.layout_chain_output(&chain.modules, result)
+    }
+
+    fn layout_chain_output(&self, modules: &[SharedPtr<Module>], result: mlua::Result<mlua::String>) -> Result<String> {
+        let modules: Vec<&Module> = modules.iter().map(|module| &**module).collect();
+        let html = result.map_err(|e| self.render_error(&modules, e))?;
+        let html = html.to_str().map_err(|_| {
+            LuatError::InvalidTemplate("Rendered page is not valid UTF-8".to_string())
+        })?;
+        Ok(html.to_string())
+    }
+
+    /// Returns the runtime table passed to a template's `render` function.
+    ///
+    /// Uses the shared request runtime from the registry (initialized by
+    /// handle_page_route) so the context_stack and the output guard are
+    /// shared by all renders in a request. A standalone render gets a fresh
+    /// runtime, and with it a fresh output guard.
+    fn render_runtime(&self) -> Result<Table> {
         let runtime: Table = match self.lua.named_registry_value::<Table>("__luat_request_runtime") {
-            Ok(existing) => existing,
+            Ok(existing) => {
+                // The page and its layouts count against one limit
+                if self.max_output_bytes.is_some() && existing.contains_key("output_guard")? {
+                    return self.with_escape_audit(existing);
+                }
+                existing
+            }
             Err(_) => {
                 // Fallback: create a temporary runtime for standalone renders
+                self.reseed_if_deterministic()?;
                 let runtime = self.lua.create_table()?;
                 let stack: Table = self.lua.create_sequence_from::<Table>(vec![])?;
                 runtime.set("context_stack", stack)?;
@@ -915,29 +1747,54 @@ impl<R: ResourceResolver> Engine<R> {
             }
         };
 
-        // Call render function with both context and runtime
-        let result: String = match render_func.call((self.lua.to_value(context)?, &runtime)) {
-            Ok(r) => r,
-            Err(e) => {
-                // Translate error line numbers using source map if available
-                if let Some(source_map) = &module.source_map {
-                    let original_msg = e.to_string();
-                    let translated_msg = source_map.translate_error(&original_msg);
-                    if translated_msg != original_msg {
-                        // Return custom error with translated line numbers
-                        return Err(LuatError::TemplateRuntimeError {
-                            template: module.path.clone().unwrap_or_else(|| module.name.clone()),
-                            message: translated_msg,
-                            lua_traceback: None,
-                            source_context: None,
-                        });
-                    }
-                }
-                return Err(LuatError::LuaError(e));
+        match self.max_output_bytes {
+            Some(limit) => {
+                let guard = self.lua.create_table()?;
+                guard.set("limit", limit)?;
+                guard.set("bytes", 0)?;
+                guard.set("by_path", self.lua.create_table()?)?;
+                guard.set("path", self.lua.create_table()?)?;
+                guard.set("current", "")?;
+                runtime.set("output_guard", guard)?;
             }
-        };
+            None => runtime.set("output_guard", mlua::Value::Nil)?,
+        }
 
-        Ok(result)
+        self.with_escape_audit(runtime)
+    }
+
+    /// Sets the escape audit table of a render runtime.
+    ///
+    /// A request runtime started by [`Engine::respond_audited`] keeps its own
+    /// table; other renders record into the list [`Engine::take_escape_audit`]
+    /// drains.
+    fn with_escape_audit(&self, runtime: Table) -> Result<Table> {
+        if self.escape_audit {
+            if runtime.contains_key("escape_audit")? {
+                return Ok(runtime);
+            }
+            let sites = match self.lua.named_registry_value::<Table>(ESCAPE_AUDIT_REGISTRY_KEY) {
+                Ok(sites) => sites,
+                Err(_) => {
+                    let sites = self.lua.create_table()?;
+                    self.lua.set_named_registry_value(ESCAPE_AUDIT_REGISTRY_KEY, &sites)?;
+                    sites
+                }
+            };
+            runtime.set("escape_audit", sites)?;
+        } else {
+            runtime.set("escape_audit", mlua::Value::Nil)?;
+        }
+
+        Ok(runtime)
+    }
+
+    /// Restarts `math.random` from the fixed seed in deterministic mode.
+    fn reseed_if_deterministic(&self) -> Result<()> {
+        if self.deterministic {
+            crate::extensions::deterministic::reseed(&self.lua)?;
+        }
+        Ok(())
     }
 
     /// Load a dependency module and make it available to Lua
@@ -945,12 +1802,10 @@ impl<R: ResourceResolver> Engine<R> {
     fn load_dependency(&self, module_path: &str) -> Result<()> {
         // If it's already in the cache, use the cached version
         let cache_key = format!("module:{}", module_path);
-        let module = if let Some(cached) = self.cache.get(&cache_key)? {
-            cached
-        } else {
+        let module = crate::cache::read_through(self.cache.as_ref(), &cache_key, || {
             // Otherwise, compile it
-            self.compile_template_string(module_path, module_path)?
-        };
+            self.compile_template_string(module_path, module_path)
+        })?;
 
         // Extract module name (remove extension)
         let module_name = std::path::Path::new(module_path)
@@ -993,8 +1848,7 @@ impl<R: ResourceResolver> Engine<R> {
         let ast = parse_template(source)?;
 
         // Transform to IR
-        let ir = transform_ast(ast)?;
-        validate_ir(&ir)?;
+        let ir = self.compile_ir(ast, "source_template", "")?;
 
         // Generate Lua code with a consistent module name
         let module_name = "source_template"; // Use a consistent module name
@@ -1094,6 +1948,16 @@ impl<R: ResourceResolver> Engine<R> {
     #[cfg(feature = "async-lua")]
     pub async fn render_from_bundle(&self, module_name: &str, context: &Value) -> Result<String> {
         // println!("DEBUG: Rendering from bundle module: {}", module_name);
+        let render_func = self.bundle_render_function(module_name)?;
+
+        let runtime = self.render_runtime()?;
+
+        let result: String = render_func.call_async((context, &runtime)).await?;
+        Ok(result)
+    }
+
+    /// Returns the `render` function of a module of the preloaded bundle.
+    fn bundle_render_function(&self, module_name: &str) -> Result<mlua::Function> {
         let require: mlua::Function = self.lua.globals().get("require")?;
         // println!("DEBUG: Calling require for module: {}", module_name);
         let module: Table = require.call(module_name)?;
@@ -1104,24 +1968,7 @@ impl<R: ResourceResolver> Engine<R> {
                 module_name
             )));
         }
-
-        let render_func: mlua::Function = module.get("render")?;
-
-        // Get the shared runtime from registry (initialized by handle_page_route)
-        // This preserves the context_stack across all renders in a request
-        let runtime: Table = match self.lua.named_registry_value::<Table>("__luat_request_runtime") {
-            Ok(existing) => existing,
-            Err(_) => {
-                // Fallback: create a temporary runtime for standalone renders
-                let runtime = self.lua.create_table()?;
-                let stack: Table = self.lua.create_sequence_from::<Table>(vec![])?;
-                runtime.set("context_stack", stack)?;
-                runtime
-            }
-        };
-
-        let result: String = render_func.call_async((context, &runtime)).await?;
-        Ok(result)
+        Ok(module.get("render")?)
     }
 
     /// Loads Lua code directly into the engine's runtime.
@@ -1129,7 +1976,10 @@ impl<R: ResourceResolver> Engine<R> {
     /// The code is executed immediately, making any defined modules
     /// or functions available for subsequent `require()` calls.
     pub fn preload_bundle_code(&self, lua_code: &str) -> Result<()> {
-        self.lua.load(lua_code).set_name("@luat_bundle").exec()?;
+        self.lua
+            .load(lua_code)
+            .set_name("@luat_bundle")
+            .call::<()>(self.bundle_loader_args()?)?;
         Ok(())
     }
 
@@ -1138,10 +1988,99 @@ impl<R: ResourceResolver> Engine<R> {
     /// Use with bytecode produced by [`compile_bundle`](Self::compile_bundle).
     pub fn preload_bundle_code_from_binary(&self, bytecode: &[u8]) -> Result<()> {
         let func = self.lua.load(bytecode).into_function()?;
-        let _: () = func.call(())?;
+        func.call::<()>(self.bundle_loader_args()?)?;
+        Ok(())
+    }
+
+    /// Returns the arguments a bundle is run with: `load` for its module
+    /// loader, the function its lazy module loaders read chunks with, the
+    /// table it marks its per-request modules in and its module cache.
+    /// Passing them in keeps them out of reach of templates, which could
+    /// otherwise load code under a sandbox without `load`, read any
+    /// module's source or change which modules are reloaded per request.
+    fn bundle_loader_args(&self) -> Result<(mlua::Function, mlua::Function, Table, Table)> {
+        let load = self
+            .lua
+            .named_registry_value::<mlua::Function>(crate::sandbox::INTERNAL_LOAD_REGISTRY_KEY)?;
+        let read_chunk = self.lua.create_function(|lua, path: String| {
+            lua.named_registry_value::<mlua::Function>(READ_CHUNK_REGISTRY_KEY)?
+                .call::<mlua::String>(path)
+        })?;
+        Ok((
+            load,
+            read_chunk,
+            crate::module_state::per_request_modules(&self.lua)?,
+            crate::module_state::new_bundle_modules(&self.lua)?,
+        ))
+    }
+
+    /// Makes chunked bundles read their chunks from files under `root`
+    /// instead of through the resolver.
+    ///
+    /// Chunk paths are relative to `root`: `luat build` with `[build] chunked`
+    /// writes them to `dist/chunks/`, so `luat serve` passes `dist`. Paths
+    /// that would leave `root` are refused.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_chunk_root(&self, root: impl Into<std::path::PathBuf>) -> Result<()> {
+        let root = root.into();
+        let read_chunk = self.lua.create_function(move |_, path: String| {
+            let relative = Path::new(&path);
+            if !relative.components().all(|c| matches!(c, std::path::Component::Normal(_))) {
+                return Err(mlua::Error::RuntimeError(format!("cannot load bundle chunk {}: invalid path", path)));
+            }
+            std::fs::read_to_string(root.join(relative))
+                .map_err(|e| mlua::Error::RuntimeError(format!("cannot load bundle chunk {}: {}", path, e)))
+        })?;
+        self.lua.set_named_registry_value(READ_CHUNK_REGISTRY_KEY, read_chunk)?;
         Ok(())
     }
 
+    /// Returns the build stamp of the preloaded bundle, if it has one.
+    ///
+    /// `luat build` stamps bundles with [`BundleInfo::stamp`](crate::BundleInfo::stamp);
+    /// bundles built before stamping was added return `None`.
+    pub fn bundle_info(&self) -> Result<Option<crate::bundle_info::BundleInfo>> {
+        match self.lua.globals().get::<Value>(crate::bundle_info::BUNDLE_INFO_GLOBAL)? {
+            Value::Nil => Ok(None),
+            value => Ok(Some(self.lua.from_value(value)?)),
+        }
+    }
+
+    /// Returns the aggregated scoped CSS of the given template sources.
+    ///
+    /// Collects the component style (see [`style`](crate::style)) of every
+    /// `.luat` source, each under a comment naming its path, in the order
+    /// given. Templates with identical CSS share a scope and appear once.
+    ///
+    /// # Arguments
+    ///
+    /// * `sources` - (path, source_code) tuples, as for [`bundle_sources`](Self::bundle_sources)
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let css = engine.stylesheet(&sources)?;
+    /// std::fs::write("dist/public/luat.css", css)?;
+    /// ```
+    pub fn stylesheet(&self, sources: &[(String, String)]) -> Result<String> {
+        let mut css = String::new();
+        let mut scopes = std::collections::HashSet::new();
+        for (name, source) in sources {
+            if !name.ends_with(".luat") {
+                continue;
+            }
+            let mut ast = parse_template(source)?;
+            ast.path = Some(name.clone());
+            let Some(style) = transform_ast(ast)?.style else {
+                continue;
+            };
+            if scopes.insert(style.scope.clone()) {
+                css.push_str(&format!("/* {} */\n{}\n\n", name, style.css));
+            }
+        }
+        Ok(css)
+    }
+
     /// Bundles multiple template sources into a single Lua file.
     ///
     /// Creates a self-contained bundle with all templates and their
@@ -1160,6 +2099,41 @@ impl<R: ResourceResolver> Engine<R> {
         sources: Vec<(String, String)>,
         mut progress: F,
     ) -> Result<(String, BundleSourceMap)>
+    where
+        F: FnMut(usize, usize),
+    {
+        let ordered_sources = self.compile_bundle_sources(&sources, &mut progress)?;
+        bundle_sources(ordered_sources, progress)
+    }
+
+    /// Bundles sources into an entry bundle plus one chunk file per module.
+    ///
+    /// Like [`bundle_sources`](Self::bundle_sources), but module code is kept
+    /// out of the entry bundle so large sites do not hold every module in
+    /// memory. Write each chunk under its path where the resolver of the
+    /// serving engine finds it, or below the directory given to
+    /// [`set_chunk_root`](Self::set_chunk_root); a module's chunk is read and
+    /// loaded the first time the module is required. See
+    /// [`codegen::bundle_sources_chunked`](crate::codegen::bundle_sources_chunked).
+    pub fn bundle_sources_chunked<F>(
+        &self,
+        sources: Vec<(String, String)>,
+        chunk_dir: &str,
+        mut progress: F,
+    ) -> Result<ChunkedBundle>
+    where
+        F: FnMut(usize, usize),
+    {
+        let ordered_sources = self.compile_bundle_sources(&sources, &mut progress)?;
+        bundle_sources_chunked(ordered_sources, chunk_dir, progress)
+    }
+
+    /// Compiles bundle sources to Lua and orders them by dependency.
+    fn compile_bundle_sources<F>(
+        &self,
+        sources: &[(String, String)],
+        progress: &mut F,
+    ) -> Result<Vec<(String, String)>>
     where
         F: FnMut(usize, usize),
     {
@@ -1171,8 +2145,7 @@ impl<R: ResourceResolver> Engine<R> {
 
             // Parse and compile the template
             let ast = parse_template(source)?;
-            let ir = transform_ast(ast)?;
-            validate_ir(&ir)?;
+            let ir = self.compile_ir(ast, name, name)?;
 
             let lua_code = if name.ends_with(".luat") {
                 generate_lua_code(ir, name)?
@@ -1187,18 +2160,9 @@ impl<R: ResourceResolver> Engine<R> {
         }
 
         // Order sources based on their dependencies
-        let ordered_sources = match crate::dependencies::order_sources(compiled_sources) {
-            Ok(sources) => sources,
-            Err(err) => {
-                return Err(LuatError::InvalidTemplate(format!(
-                    "Failed to order sources by dependency: {}",
-                    err
-                )))
-            }
-        };
-
-        // Bundle the ordered sources
-        bundle_sources(ordered_sources, progress)
+        crate::dependencies::order_sources(compiled_sources).map_err(|err| {
+            LuatError::InvalidTemplate(format!("Failed to order sources by dependency: {}", err))
+        })
     }
 
     /// Bundles multiple sources with source map for debugging.
@@ -1279,7 +2243,11 @@ _G.require = __enhanced_require
         // Bundle the ordered sources
         bundle.push_str("\n-- BUNDLED MODULES\n");
         bundle.push_str("local __module_loaders = {}\n");
-        bundle.push_str("local __modules = {}\n");
+        // The per-request module names and the module cache, passed in by the engine
+        bundle.push_str(&format!(
+            "local {0}, __modules = select(3, ...)\n{0} = {0} or {{}}\n__modules = __modules or {{}}\n",
+            crate::module_state::PER_REQUEST_MODULES
+        ));
         bundle.push_str("local __original_require = require\n\n");
 
         bundle.push_str("local function __normalize_path(path)\n");
@@ -1405,6 +2373,15 @@ _G.require = __enhanced_require
 
             // Add the module loader
             let escaped_name = escape_lua_string(name);
+            if crate::module_state::ModuleState::of_source(lua_code)
+                == crate::module_state::ModuleState::PerRequest
+            {
+                bundle.push_str(&format!(
+                    "{}['{}'] = true\n",
+                    crate::module_state::PER_REQUEST_MODULES,
+                    escaped_name
+                ));
+            }
             bundle.push_str(&format!("__module_loaders['{}'] = function()\n", escaped_name));
             bundle.push_str("  local __prev = _G.__luat_current_module\n");
             bundle.push_str(&format!("  _G.__luat_current_module = '{}'\n", escaped_name));
@@ -1554,7 +2531,7 @@ _G.__bundle_debug = {
         let source_map = BundleSourceMap::new();
         
         // Execute the bundle code
-        self.lua.load(lua_code).exec()?;
+        self.lua.load(lua_code).call::<()>(self.bundle_loader_args()?)?;
         
         // TODO: Extract sourcemap from Lua if needed
         
@@ -1631,8 +2608,15 @@ _G.__bundle_debug = {
         self.cache.contains_key(module_path)
     }
 
-    /// Clears all cached compiled modules.
+    /// Clears all cached compiled modules and compile diagnostics.
     pub fn clear_cache(&self) -> Result<()> {
+        #[cfg(not(target_arch = "wasm32"))]
+        self.compile_diagnostics
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clear();
+        #[cfg(target_arch = "wasm32")]
+        self.compile_diagnostics.borrow_mut().clear();
         self.cache.clear()
     }
 
@@ -1651,7 +2635,9 @@ _G.__bundle_debug = {
                 json = true, _G = true, package = true,
                 string = true, table = true, math = true,
                 io = true, os = true, debug = true,
-                coroutine = true, utf8 = true, kv = true
+                coroutine = true, utf8 = true, kv = true,
+                url = true, feed = true, content = true,
+                markdown = true
             }
             function require(name)
                 -- Always clear from cache before loading (except builtins)
@@ -1687,7 +2673,8 @@ _G.__bundle_debug = {
             if key != "json" && key != "_G" && key != "package"
                && key != "string" && key != "table" && key != "math"
                && key != "io" && key != "os" && key != "debug"
-               && key != "coroutine" && key != "utf8" {
+               && key != "coroutine" && key != "utf8" && key != "url"
+               && key != "feed" && key != "content" && key != "markdown" {
                 keys_to_remove.push(key);
             }
         }
@@ -1746,7 +2733,8 @@ _G.__bundle_debug = {
         if route.page_server.is_none() {
             return false;
         }
-        if !request.method.eq_ignore_ascii_case("GET") {
+        // HEAD renders the page like GET; the server drops the body
+        if !request.method.eq_ignore_ascii_case("GET") && !request.method.eq_ignore_ascii_case("HEAD") {
             return true;
         }
         request.action_name().is_some()
@@ -1965,19 +2953,23 @@ _G.__bundle_debug = {
     ) -> Result<crate::response::LuatResponse> {
         use crate::runtime::Runtime;
 
+        // Load functions, handlers and actions run under the render limits too
+        let _limits = self.render_limits.start(&self.lua);
+        crate::module_state::reset(&self.lua)?;
+        let snapshot = self.isolation_snapshot()?;
         let runtime = Runtime::new(&self.lua);
 
         // For API-only routes (+server.lua without +page.luat)
-        if route.is_api_route() {
-            return self.handle_api_route(&runtime, route, request);
-        }
-
-        if self.is_action_request(route, request) {
-            return self.handle_action_request_sync(route, request);
-        }
-
-        // For page routes, run load functions and render
-        self.handle_page_route(&runtime, route, request)
+        let response = if route.is_api_route() {
+            self.handle_api_route(&runtime, route, request)
+        } else if self.is_action_request(route, request) {
+            self.handle_action_request_sync(route, request)
+        } else {
+            // For page routes, run load functions and render
+            self.handle_page_route(&runtime, route, request)
+        };
+        self.end_isolation(snapshot)?;
+        Ok(self.with_route_headers(&request.path, response?))
     }
 
     /// Async request handler that can fall back to bundle rendering.
@@ -1989,17 +2981,341 @@ _G.__bundle_debug = {
     ) -> Result<crate::response::LuatResponse> {
         use crate::runtime::Runtime;
 
+        // Load functions, handlers and actions run under the render limits too
+        let _limits = self.render_limits.start(&self.lua);
+        crate::module_state::reset(&self.lua)?;
+        let snapshot = self.isolation_snapshot()?;
         let runtime = Runtime::new(&self.lua);
 
-        if route.is_api_route() {
-            return self.handle_api_route(&runtime, route, request);
+        let response = if route.is_api_route() {
+            self.handle_api_route(&runtime, route, request)
+        } else if self.is_action_request(route, request) {
+            self.handle_action_request_async(route, request).await
+        } else {
+            self.handle_page_route_async(&runtime, route, request).await
+        };
+        self.end_isolation(snapshot)?;
+        Ok(self.with_route_headers(&request.path, response?))
+    }
+
+    /// Handles a request like [`Engine::respond`] and returns the raw output
+    /// sites it reached.
+    ///
+    /// The report only holds this request's sites, leaving the list
+    /// [`Engine::take_escape_audit`] drains alone. It is empty when escape
+    /// audit mode is disabled.
+    pub fn respond_audited(
+        &self,
+        route: &crate::router::Route,
+        request: &crate::request::LuatRequest,
+    ) -> Result<(crate::response::LuatResponse, crate::audit::EscapeAuditReport)> {
+        let sites = self.begin_request_audit()?;
+        let response = self.respond(route, request);
+        self.end_request_audit(response, sites)
+    }
+
+    /// Async version of [`Engine::respond_audited`].
+    #[cfg(feature = "async-lua")]
+    pub async fn respond_async_audited(
+        &self,
+        route: &crate::router::Route,
+        request: &crate::request::LuatRequest,
+    ) -> Result<(crate::response::LuatResponse, crate::audit::EscapeAuditReport)> {
+        let sites = self.begin_request_audit()?;
+        let response = self.respond_async(route, request).await;
+        self.end_request_audit(response, sites)
+    }
+
+    /// Installs a fresh audit table the next request runtime picks up.
+    ///
+    /// The request runtime is created before the first await, so a concurrent
+    /// request replacing the table afterwards doesn't change where this
+    /// request records.
+    fn begin_request_audit(&self) -> Result<Option<Table>> {
+        if !self.escape_audit {
+            return Ok(None);
+        }
+        let sites = self.lua.create_table()?;
+        self.lua.set_named_registry_value(REQUEST_ESCAPE_AUDIT_REGISTRY_KEY, &sites)?;
+        Ok(Some(sites))
+    }
+
+    /// Builds the report of a request started with [`Engine::begin_request_audit`].
+    fn end_request_audit(
+        &self,
+        response: Result<crate::response::LuatResponse>,
+        sites: Option<Table>,
+    ) -> Result<(crate::response::LuatResponse, crate::audit::EscapeAuditReport)> {
+        let Some(sites) = sites else {
+            return Ok((response?, Default::default()));
+        };
+        // A concurrent request may have installed its own table since
+        if self.lua.named_registry_value::<Option<Table>>(REQUEST_ESCAPE_AUDIT_REGISTRY_KEY)?.as_ref() == Some(&sites) {
+            self.lua.unset_named_registry_value(REQUEST_ESCAPE_AUDIT_REGISTRY_KEY)?;
+        }
+        Ok((response?, Self::escape_audit_report(&sites)?))
+    }
+
+    /// Copies the globals before a request when request isolation is on.
+    fn isolation_snapshot(&self) -> Result<Option<crate::module_state::GlobalsSnapshot>> {
+        if !self.isolate_requests {
+            return Ok(None);
+        }
+        Ok(Some(crate::module_state::snapshot_globals(&self.lua)?))
+    }
+
+    /// Undoes the globals a request set, see [`Engine::set_request_isolation`].
+    fn end_isolation(&self, snapshot: Option<crate::module_state::GlobalsSnapshot>) -> Result<()> {
+        if let Some(snapshot) = snapshot {
+            crate::module_state::restore_globals(snapshot)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the headers of the [`Engine::add_route_headers`] rules matching
+    /// `path`, which are known before the route runs.
+    pub fn route_headers(&self, path: &str) -> HashMap<String, String> {
+        let mut configured = HashMap::new();
+        for (pattern, rule_headers) in &self.route_headers {
+            if pattern.is_match(path) {
+                for (name, value) in rule_headers {
+                    crate::response::set_header(&mut configured, name.clone(), value.clone());
+                }
+            }
+        }
+        configured
+    }
+
+    /// Adds the headers of matching [`Engine::add_route_headers`] rules that
+    /// the response doesn't set itself.
+    fn with_route_headers(
+        &self,
+        path: &str,
+        mut response: crate::response::LuatResponse,
+    ) -> crate::response::LuatResponse {
+        use crate::response::LuatResponse;
+
+        let headers = match &mut response {
+            LuatResponse::Html { headers, .. }
+            | LuatResponse::Json { headers, .. }
+            | LuatResponse::Bytes { headers, .. }
+            | LuatResponse::Stream { headers, .. } => headers,
+            LuatResponse::Redirect { .. } | LuatResponse::Error { .. } => return response,
+        };
+        for (name, value) in self.route_headers(path) {
+            if !crate::response::has_header(headers, &name) {
+                headers.insert(name, value);
+            }
+        }
+        response
+    }
+
+    /// Renders the error page for `status`, e.g. after a failed render in production.
+    ///
+    /// The first template found among `<status>.luat`, the route's
+    /// `+error.luat` and the root `+error.luat` is rendered with
+    /// `props.status`, `props.message` and `props.request_id` (the request's
+    /// `X-Request-Id`). Without one, or if it fails to render, a minimal
+    /// built-in page is returned instead, so this never fails.
+    pub fn respond_error(
+        &self,
+        route: Option<&crate::router::Route>,
+        request: &crate::request::LuatRequest,
+        status: u16,
+        message: &str,
+    ) -> crate::response::LuatResponse {
+        let candidates = Self::error_template_candidates(route.and_then(|route| route.error.clone()), status);
+        self.render_error_page(candidates, request, status, message)
+    }
+
+    /// Async version of [`Engine::respond_error`] that also finds error
+    /// templates in a preloaded bundle.
+    #[cfg(feature = "async-lua")]
+    pub async fn respond_error_async(
+        &self,
+        route: Option<&crate::router::Route>,
+        request: &crate::request::LuatRequest,
+        status: u16,
+        message: &str,
+    ) -> crate::response::LuatResponse {
+        let candidates = Self::error_template_candidates(route.and_then(|route| route.error.clone()), status);
+        self.render_error_page_async(candidates, request, status, message).await
+    }
+
+    /// Renders the 404 page for a request that matched no route.
+    ///
+    /// Like [`Engine::respond_error`], but instead of a route's `+error.luat`
+    /// the nearest one along the request path is used: for `/blog/2024/x`,
+    /// `blog/2024/x/+error.luat`, `blog/2024/+error.luat` and
+    /// `blog/+error.luat` are tried after `404.luat`, before the root
+    /// `+error.luat`.
+    pub fn respond_not_found(&self, request: &crate::request::LuatRequest) -> crate::response::LuatResponse {
+        let candidates = Self::error_template_candidates(Self::nearest_error_templates(&request.path), 404);
+        self.render_error_page(candidates, request, 404, crate::response::reason_phrase(404))
+    }
+
+    /// Async version of [`Engine::respond_not_found`].
+    #[cfg(feature = "async-lua")]
+    pub async fn respond_not_found_async(
+        &self,
+        request: &crate::request::LuatRequest,
+    ) -> crate::response::LuatResponse {
+        let candidates = Self::error_template_candidates(Self::nearest_error_templates(&request.path), 404);
+        self.render_error_page_async(candidates, request, 404, crate::response::reason_phrase(404))
+            .await
+    }
+
+    /// Renders the first error template found among `candidates`.
+    fn render_error_page(
+        &self,
+        candidates: Vec<String>,
+        request: &crate::request::LuatRequest,
+        status: u16,
+        message: &str,
+    ) -> crate::response::LuatResponse {
+        let rendered = self.begin_error_page(request, status, message).and_then(|(runtime, context)| {
+            for candidate in candidates.iter().filter(|candidate| !self.resolves_elsewhere(candidate)) {
+                match self.compile_entry(candidate).and_then(|module| self.render(&module, &context)) {
+                    Ok(body) => return Ok(Some((body, self.extract_page_head(&runtime)?))),
+                    Err(err) if self.is_not_found_error(&err) => continue,
+                    Err(err) => return Err(err),
+                }
+            }
+            Ok(None)
+        });
+        self.finish_error_page(rendered, request, status, message)
+    }
+
+    /// Async version of [`Engine::render_error_page`].
+    #[cfg(feature = "async-lua")]
+    async fn render_error_page_async(
+        &self,
+        candidates: Vec<String>,
+        request: &crate::request::LuatRequest,
+        status: u16,
+        message: &str,
+    ) -> crate::response::LuatResponse {
+        let rendered = match self.begin_error_page(request, status, message) {
+            Ok((runtime, context)) => {
+                let mut rendered = Ok(None);
+                for candidate in candidates.iter().filter(|candidate| !self.resolves_elsewhere(candidate)) {
+                    match self.render_template_async(candidate, &context).await {
+                        Ok(body) => {
+                            rendered = self.extract_page_head(&runtime).map(|head| Some((body, head)));
+                            break;
+                        }
+                        Err(err) if self.is_not_found_error(&err) => continue,
+                        Err(err) => {
+                            rendered = Err(err);
+                            break;
+                        }
+                    }
+                }
+                rendered
+            }
+            Err(err) => Err(err),
+        };
+        self.finish_error_page(rendered, request, status, message)
+    }
+
+    /// Whether the resolver maps `candidate` to some other file, e.g. through
+    /// its basename fallback, which would pick the root `+error.luat` for a
+    /// missing nested one.
+    fn resolves_elsewhere(&self, candidate: &str) -> bool {
+        self.resolver
+            .get_resolved_path("", candidate)
+            .is_ok_and(|resolved| !std::path::Path::new(&resolved).ends_with(candidate))
+    }
+
+    /// `+error.luat` paths along a request path, nearest first.
+    fn nearest_error_templates(path: &str) -> Vec<String> {
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty() && *s != "." && *s != "..").collect();
+        (1..=segments.len())
+            .rev()
+            .map(|depth| format!("{}/+error.luat", segments[..depth].join("/")))
+            .collect()
+    }
+
+    /// Error templates to try for `status`, most specific first.
+    fn error_template_candidates(error_templates: impl IntoIterator<Item = String>, status: u16) -> Vec<String> {
+        let mut candidates = vec![format!("{}.luat", status)];
+        candidates.extend(error_templates);
+        candidates.push("+error.luat".to_string());
+        candidates.dedup();
+        candidates
+    }
+
+    /// Sets up the request runtime for an error template and builds its props.
+    fn begin_error_page(
+        &self,
+        request: &crate::request::LuatRequest,
+        status: u16,
+        message: &str,
+    ) -> Result<(Table, Value)> {
+        let request_runtime: Table = self.lua.create_table()?;
+        request_runtime.set("context_stack", self.lua.create_table()?)?;
+        request_runtime.set("page_context", self.lua.create_table()?)?;
+        request_runtime.set("csp_nonce", request.csp_nonce.as_deref())?;
+        request_runtime.set("cookies", request.cookies.clone())?;
+        request_runtime.set("escape_audit", self.lua.named_registry_value::<Value>(REQUEST_ESCAPE_AUDIT_REGISTRY_KEY)?)?;
+        self.lua.set_named_registry_value("__luat_request_runtime", request_runtime.clone())?;
+
+        let mut props = serde_json::Map::new();
+        props.insert("status".to_string(), status.into());
+        props.insert("message".to_string(), message.into());
+        if let Some(request_id) = request.request_id() {
+            props.insert("request_id".to_string(), request_id.into());
         }
+        let context = self.to_value(serde_json::Value::Object(props))?;
+        Ok((request_runtime, context))
+    }
+
+    /// Wraps a rendered error template, or the built-in page, in a response.
+    fn finish_error_page(
+        &self,
+        rendered: Result<Option<(String, crate::extensions::seo::PageHead)>>,
+        request: &crate::request::LuatRequest,
+        status: u16,
+        message: &str,
+    ) -> crate::response::LuatResponse {
+        let _ = self.lua.unset_named_registry_value("__luat_request_runtime");
 
-        if self.is_action_request(route, request) {
-            return self.handle_action_request_async(route, request).await;
+        let (body, mut head) = match rendered {
+            Ok(Some(page)) => page,
+            Ok(None) => (Self::builtin_error_page(request, status, message), Default::default()),
+            Err(err) => {
+                tracing::warn!("Error template for status {} failed: {}", status, err);
+                (Self::builtin_error_page(request, status, message), Default::default())
+            }
+        };
+        if head.title.is_none() {
+            head.title = Some(format!("{} {}", status, crate::response::reason_phrase(status)));
+        }
+        crate::response::LuatResponse::Html {
+            status,
+            headers: HashMap::new(),
+            body,
+            head,
         }
+    }
 
-        self.handle_page_route_async(&runtime, route, request).await
+    fn builtin_error_page(request: &crate::request::LuatRequest, status: u16, message: &str) -> String {
+        let escape = |s: &str| {
+            s.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+        };
+        let request_id = request
+            .request_id()
+            .map(|id| format!("\n    <p><small>Request ID: {}</small></p>", escape(id)))
+            .unwrap_or_default();
+        format!(
+            "<main class=\"luat-error\">\n    <h1>{}</h1>\n    <p>{}</p>{}\n</main>",
+            status,
+            escape(message),
+            request_id
+        )
     }
 
     fn handle_action_request_sync(
@@ -2106,6 +3422,22 @@ _G.__bundle_debug = {
         Ok(self.action_response_to_luat(response, rendered))
     }
 
+    /// Returns the handlers and `spec` table a route's `+server.lua` exports,
+    /// or `None` if the route has no `+server.lua`.
+    ///
+    /// Runs the module's top-level code but none of its handlers; `luat
+    /// openapi` uses this to document API routes.
+    pub fn api_spec(&self, route: &crate::router::Route) -> Result<Option<crate::runtime::ApiSpec>> {
+        let Some(api_path) = route.api.as_ref() else {
+            return Ok(None);
+        };
+        let source = self.resolve_server_source(api_path)?;
+        let spec = crate::runtime::Runtime::new(&self.lua)
+            .api_spec(&source, api_path)
+            .map_err(LuatError::LuaError)?;
+        Ok(Some(spec))
+    }
+
     /// Handles an API-only route (+server.lua).
     fn handle_api_route(
         &self,
@@ -2135,6 +3467,10 @@ _G.__bundle_debug = {
             ));
         }
 
+        if let Some(bytes) = api_result.bytes {
+            return Ok(LuatResponse::bytes_with_headers(api_result.status, bytes, api_result.headers));
+        }
+
         // Return JSON response
         Ok(LuatResponse::json_with_headers(
             api_result.status,
@@ -2159,9 +3495,19 @@ _G.__bundle_debug = {
         let page_context: Table = self.lua.create_table()?;  // Non-scoped page context for view_title etc.
         request_runtime.set("context_stack", context_stack)?;
         request_runtime.set("page_context", page_context)?;
+        request_runtime.set("csp_nonce", request.csp_nonce.as_deref())?;
+        request_runtime.set("cookies", request.cookies.clone())?;
+        request_runtime.set("escape_audit", self.lua.named_registry_value::<Value>(REQUEST_ESCAPE_AUDIT_REGISTRY_KEY)?)?;
+        request_runtime.set("locale", self.request_locale(request))?;
+        // `{#await}` branches rendered once their deferred value resolves
+        request_runtime.set("deferred_fragments", crate::runtime::deferred_fragments_table(&self.lua)?)?;
         self.lua.set_named_registry_value("__luat_request_runtime", request_runtime.clone())?;
+        self.reseed_if_deterministic()?;
 
         let mut merged_props = serde_json::Map::new();
+        let mut deferred = Vec::new();
+        // Headers exported by server modules; the page's override its layouts'
+        let mut headers = HashMap::new();
 
         // 1. Run layout server load functions (from root to current)
         for layout_server_path in &route.layout_servers {
@@ -2170,9 +3516,16 @@ _G.__bundle_debug = {
             // Check for redirect
             if let Some(redirect) = load_result.redirect {
                 let status = load_result.status.unwrap_or(302);
+                deferred.extend(load_result.deferred);
+                self.discard_deferred(&deferred)?;
                 return Ok(LuatResponse::redirect_with_status(status, redirect));
             }
 
+            deferred.extend(load_result.deferred);
+            for (name, value) in load_result.headers {
+                crate::response::set_header(&mut headers, name, value);
+            }
+
             // Merge props
             if let JsonValue::Object(props) = load_result.props {
                 for (k, v) in props {
@@ -2188,9 +3541,16 @@ _G.__bundle_debug = {
             // Check for redirect
             if let Some(redirect) = load_result.redirect {
                 let status = load_result.status.unwrap_or(302);
+                deferred.extend(load_result.deferred);
+                self.discard_deferred(&deferred)?;
                 return Ok(LuatResponse::redirect_with_status(status, redirect));
             }
 
+            deferred.extend(load_result.deferred);
+            for (name, value) in load_result.headers {
+                crate::response::set_header(&mut headers, name, value);
+            }
+
             // Merge props
             if let JsonValue::Object(props) = load_result.props {
                 for (k, v) in props {
@@ -2204,47 +3564,47 @@ _G.__bundle_debug = {
             LuatError::InvalidTemplate("Page route has no +page.luat".to_string())
         })?;
 
-        // Compile the page template
-        let module = self.compile_entry(page_path)?;
+        // 4. Render it inside its layouts, which render it as their children
+        let chain = self.layout_chain(page_path, &route.layouts, &merged_props, false)?;
+        let body_html = self.render_layout_chain(chain)?;
 
-        // Convert merged props to Lua value
-        let context = self.to_value(JsonValue::Object(merged_props.clone()))?;
-
-        // Render the page
-        let mut body_html = self.render(&module, &context)?;
-
-        // 4. Wrap in layouts (from innermost to outermost)
-        for layout_path in route.layouts.iter().rev() {
-            // Create layout props with children
-            let mut layout_props = merged_props.clone();
-            layout_props.insert("children".to_string(), JsonValue::String(body_html.clone()));
-
-            let layout_context = self.to_value(JsonValue::Object(layout_props))?;
-
-            // Compile and render the layout
-            let layout_module = self.compile_entry(layout_path)?;
-            body_html = self.render(&layout_module, &layout_context)?;
+        // Collect the document head set with seo.set() or setPageContext("view_title")
+        let head = self.extract_page_head(&request_runtime)?;
+        if let Some(variants) = crate::extensions::flags::variants_header(&request_runtime)? {
+            headers.insert(crate::extensions::flags::VARIANTS_HEADER.to_string(), variants);
         }
 
-        // Extract view_title from context_stack if set by any template
-        let view_title = self.extract_view_title_from_context(&request_runtime)?;
-
         // Clean up request runtime from registry
         let _ = self.lua.unset_named_registry_value("__luat_request_runtime");
 
-        // Build response with optional view_title header
-        let mut headers = std::collections::HashMap::new();
-        if let Some(title) = view_title {
-            headers.insert("x-luat-title".to_string(), title);
+        if !deferred.is_empty() {
+            return Ok(LuatResponse::Stream {
+                status: 200,
+                headers,
+                body: body_html,
+                deferred,
+                head,
+            });
         }
 
         Ok(LuatResponse::Html {
             status: 200,
             headers,
             body: body_html,
+            head,
         })
     }
 
+    /// Builds the page head from `seo.set()` values, using `view_title` as
+    /// the title when `seo` did not set one.
+    fn extract_page_head(&self, runtime: &Table) -> Result<crate::extensions::seo::PageHead> {
+        let mut head = crate::extensions::seo::PageHead::from_runtime(runtime)?;
+        if head.title.is_none() {
+            head.title = self.extract_view_title_from_context(runtime)?;
+        }
+        Ok(head)
+    }
+
     /// Extracts view_title from page_context (preferred) or context_stack (fallback).
     fn extract_view_title_from_context(&self, runtime: &Table) -> Result<Option<String>> {
         // First check page_context (non-scoped, takes precedence)
@@ -2293,18 +3653,35 @@ _G.__bundle_debug = {
         let page_context: Table = self.lua.create_table()?;  // Non-scoped page context for view_title etc.
         request_runtime.set("context_stack", context_stack)?;
         request_runtime.set("page_context", page_context)?;
+        request_runtime.set("csp_nonce", request.csp_nonce.as_deref())?;
+        request_runtime.set("cookies", request.cookies.clone())?;
+        request_runtime.set("escape_audit", self.lua.named_registry_value::<Value>(REQUEST_ESCAPE_AUDIT_REGISTRY_KEY)?)?;
+        request_runtime.set("locale", self.request_locale(request))?;
+        // `{#await}` branches rendered once their deferred value resolves
+        request_runtime.set("deferred_fragments", crate::runtime::deferred_fragments_table(&self.lua)?)?;
         self.lua.set_named_registry_value("__luat_request_runtime", request_runtime.clone())?;
+        self.reseed_if_deterministic()?;
 
         let mut merged_props = serde_json::Map::new();
+        let mut deferred = Vec::new();
+        // Headers exported by server modules; the page's override its layouts'
+        let mut headers = HashMap::new();
 
         for layout_server_path in &route.layout_servers {
             let load_result = self.run_load_file(runtime, layout_server_path, request, &route.params)?;
 
             if let Some(redirect) = load_result.redirect {
                 let status = load_result.status.unwrap_or(302);
+                deferred.extend(load_result.deferred);
+                self.discard_deferred(&deferred)?;
                 return Ok(LuatResponse::redirect_with_status(status, redirect));
             }
 
+            deferred.extend(load_result.deferred);
+            for (name, value) in load_result.headers {
+                crate::response::set_header(&mut headers, name, value);
+            }
+
             if let JsonValue::Object(props) = load_result.props {
                 for (k, v) in props {
                     merged_props.insert(k, v);
@@ -2317,9 +3694,16 @@ _G.__bundle_debug = {
 
             if let Some(redirect) = load_result.redirect {
                 let status = load_result.status.unwrap_or(302);
+                deferred.extend(load_result.deferred);
+                self.discard_deferred(&deferred)?;
                 return Ok(LuatResponse::redirect_with_status(status, redirect));
             }
 
+            deferred.extend(load_result.deferred);
+            for (name, value) in load_result.headers {
+                crate::response::set_header(&mut headers, name, value);
+            }
+
             if let JsonValue::Object(props) = load_result.props {
                 for (k, v) in props {
                     merged_props.insert(k, v);
@@ -2331,36 +3715,115 @@ _G.__bundle_debug = {
             LuatError::InvalidTemplate("Page route has no +page.luat".to_string())
         })?;
 
-        let context = self.to_value(JsonValue::Object(merged_props.clone()))?;
-        let mut body_html = self.render_template_async(page_path, &context).await?;
+        let chain = self.layout_chain(page_path, &route.layouts, &merged_props, true)?;
+        let body_html = self.render_layout_chain_async(chain).await?;
 
-        for layout_path in route.layouts.iter().rev() {
-            let mut layout_props = merged_props.clone();
-            layout_props.insert("children".to_string(), JsonValue::String(body_html.clone()));
-
-            let layout_context = self.to_value(JsonValue::Object(layout_props))?;
-            body_html = self.render_template_async(layout_path, &layout_context).await?;
+        // Collect the document head set with seo.set() or setPageContext("view_title")
+        let head = self.extract_page_head(&request_runtime)?;
+        if let Some(variants) = crate::extensions::flags::variants_header(&request_runtime)? {
+            headers.insert(crate::extensions::flags::VARIANTS_HEADER.to_string(), variants);
         }
 
-        // Extract view_title from context_stack if set by any template
-        let view_title = self.extract_view_title_from_context(&request_runtime)?;
-
         // Clean up request runtime from registry
         let _ = self.lua.unset_named_registry_value("__luat_request_runtime");
 
-        // Build response with optional view_title header
-        let mut headers = std::collections::HashMap::new();
-        if let Some(title) = view_title {
-            headers.insert("x-luat-title".to_string(), title);
+        if !deferred.is_empty() {
+            return Ok(LuatResponse::Stream {
+                status: 200,
+                headers,
+                body: body_html,
+                deferred,
+                head,
+            });
         }
 
         Ok(LuatResponse::Html {
             status: 200,
             headers,
             body: body_html,
+            head,
         })
     }
 
+    /// Resolves a deferred load value and returns the HTML chunk to stream after the shell.
+    ///
+    /// The chunk is a JSON `<script>` payload tagged with `data-luat-deferred`
+    /// followed by an inline script that stores the value in
+    /// `window.__luat_deferred` and dispatches a `luat:deferred` event on
+    /// `document`. Lua errors raised while resolving are reported in the
+    /// payload as `{ "error": "..." }` rather than failing the stream.
+    /// Pass the request's CSP nonce so the inline scripts are allowed to run.
+    ///
+    /// Each `{#await}` block that rendered its pending branch for the value
+    /// has its `{:then}` branch (or `{:catch}` on error) rendered with it and
+    /// streamed in a `<template>`, followed by a script that puts it in place
+    /// of the pending content.
+    pub fn resolve_deferred(&self, id: &str, nonce: Option<&str>) -> Result<String> {
+        use crate::runtime::Runtime;
+
+        let runtime = Runtime::new(&self.lua);
+        let resolved = runtime.resolve_deferred_value(id);
+        let fragments = runtime.take_deferred_fragments(id)?;
+        let mut payload = match &resolved {
+            Ok(value) => match runtime.lua_to_json(value) {
+                Ok(value) => serde_json::json!({ "value": value }),
+                Err(err) => serde_json::json!({ "error": err.to_string() }),
+            },
+            Err(err) => serde_json::json!({ "error": err.to_string() }),
+        };
+
+        let mut rendered = Vec::new();
+        for fragment in fragments {
+            let slot: String = fragment.get("slot")?;
+            let (branch, value) = match &resolved {
+                Ok(value) => (fragment.get::<Option<mlua::Function>>("then")?, value.clone()),
+                Err(err) => (
+                    fragment.get::<Option<mlua::Function>>("catch")?,
+                    Value::String(self.lua.create_string(err.to_string())?),
+                ),
+            };
+            // Without a matching branch the pending content stays
+            let Some(branch) = branch else { continue };
+            let _limits = self.render_limits.start(&self.lua);
+            match self.lua.load(RENDER_FRAGMENT_LUA).call::<String>((branch, value)) {
+                Ok(html) => rendered.push((slot, html)),
+                Err(err) => payload = serde_json::json!({ "error": err.to_string() }),
+            }
+        }
+
+        // "</" must not appear inside a script element
+        let payload = serde_json::to_string(&payload)
+            .map_err(|e| LuatError::InvalidTemplate(format!("Deferred value '{}': {}", id, e)))?
+            .replace("</", "<\\/");
+        let id = id.replace('"', "");
+        let nonce_attr = nonce
+            .map(|n| format!(" nonce=\"{}\"", n.replace('"', "")))
+            .unwrap_or_default();
+
+        let mut chunk = format!(
+            "<script type=\"application/json\" data-luat-deferred=\"{id}\">{payload}</script>\
+             <script{nonce_attr}>(function(id){{var el=document.querySelector('script[data-luat-deferred=\"'+id+'\"]');\
+             var d=JSON.parse(el.textContent);(window.__luat_deferred=window.__luat_deferred||{{}})[id]=d;\
+             document.dispatchEvent(new CustomEvent('luat:deferred',{{detail:{{id:id,value:d.value,error:d.error}}}}));}})(\"{id}\")</script>\n",
+        );
+        for (slot, html) in rendered {
+            let slot = slot.replace(['"', '\''], "");
+            chunk.push_str(&format!(
+                "<template data-luat-slot=\"{slot}\">{html}</template>\
+                 <script{nonce_attr}>(function(s){{var t=document.querySelector('template[data-luat-slot=\"'+s+'\"]'),\
+                 p=document.getElementById(s);if(t&&p){{p.replaceWith(t.content);}}if(t){{t.remove();}}}})(\"{slot}\")</script>\n",
+            ));
+        }
+        Ok(chunk)
+    }
+
+    /// Drops deferred values that will not be resolved (e.g. the client disconnected).
+    pub fn discard_deferred(&self, ids: &[String]) -> Result<()> {
+        crate::runtime::Runtime::new(&self.lua)
+            .discard_deferred(ids)
+            .map_err(LuatError::LuaError)
+    }
+
     /// Runs a load file and returns the result.
     fn run_load_file(
         &self,
@@ -8,8 +8,8 @@ use crate::error::Result;
 use crate::resolver::ResourceResolver;
 use crate::cache::SharedPtr;
 use crate::Module;
-use crate::transform::{transform_ast, validate_ir};
 use crate::codegen::generate_lua_code_with_sourcemap;
+use crate::metadata::ComponentMetadata;
 
 #[cfg(not(target_arch = "wasm32"))]
 use std::sync::Arc;
@@ -34,30 +34,22 @@ impl<R: ResourceResolver> Engine<R> {
         let ast = parse_template_with_context(source, Some(name))?;
 
         // Transform to IR
-        let ir = transform_ast(ast)?;
-        validate_ir(&ir)?;
+        let ir = self.compile_ir(ast, name, path.as_deref().unwrap_or(name))?;
+
+        let metadata = ComponentMetadata::from_ir(&ir, source);
 
         // Generate Lua code with source map for error line translation
         let (lua_code, source_map) = generate_lua_code_with_sourcemap(ir, name)?;
 
         // Create the module with source map for error translation
+        let mut module = Module::with_source_map(name.to_string(), lua_code, Vec::new(), path, source_map);
+        module.metadata = metadata;
+
         #[cfg(not(target_arch = "wasm32"))]
-        let module = Arc::new(Module::with_source_map(
-            name.to_string(),
-            lua_code,
-            Vec::new(),
-            path,
-            source_map,
-        ));
+        let module = Arc::new(module);
 
         #[cfg(target_arch = "wasm32")]
-        let module = Rc::new(Module::with_source_map(
-            name.to_string(),
-            lua_code,
-            Vec::new(),
-            path,
-            source_map,
-        ));
+        let module = Rc::new(module);
 
         Ok(module)
     }
@@ -15,6 +15,7 @@
 //! - **Lua errors**: Runtime execution failures
 //! - **Resolution errors**: Template file not found
 //! - **Cache errors**: Caching operation failures
+//! - **Resource limits**: A template used too much memory
 //!
 //! # Source Context
 //!
@@ -153,7 +154,11 @@ pub enum LuatError {
 
     /// Lua runtime execution error.
     #[error("Lua execution error: {0}")]
-    LuaError(#[from] mlua::Error),
+    LuaError(#[source] mlua::Error),
+
+    /// A resource limit, such as the Lua memory limit, was exceeded.
+    #[error("Resource limit exceeded: {0}")]
+    ResourceLimit(String),
 
     /// File I/O error.
     #[error("IO error: {0}")]
@@ -212,5 +217,67 @@ pub enum LuatError {
     },
 }
 
+impl Clone for LuatError {
+    /// Clones the error. `std::io::Error` isn't `Clone`, so I/O errors are
+    /// recreated from their kind and message.
+    fn clone(&self) -> Self {
+        match self {
+            Self::ParseError { message, line, column, file, source_context } => Self::ParseError {
+                message: message.clone(),
+                line: *line,
+                column: *column,
+                file: file.clone(),
+                source_context: source_context.clone(),
+            },
+            Self::TransformError(message) => Self::TransformError(message.clone()),
+            Self::CodegenError(message) => Self::CodegenError(message.clone()),
+            Self::LuaError(e) => Self::LuaError(e.clone()),
+            Self::ResourceLimit(message) => Self::ResourceLimit(message.clone()),
+            Self::IoError(e) => Self::IoError(std::io::Error::new(e.kind(), e.to_string())),
+            Self::ResolutionError(message) => Self::ResolutionError(message.clone()),
+            Self::CacheError(message) => Self::CacheError(message.clone()),
+            Self::ModuleNotFound(message) => Self::ModuleNotFound(message.clone()),
+            Self::InvalidTemplate(message) => Self::InvalidTemplate(message.clone()),
+            Self::MultipleModuleScripts => Self::MultipleModuleScripts,
+            Self::MultipleRegularScripts => Self::MultipleRegularScripts,
+            Self::ModuleScriptNotFirst => Self::ModuleScriptNotFirst,
+            Self::TemplateRuntimeError { template, message, lua_traceback, source_context } => {
+                Self::TemplateRuntimeError {
+                    template: template.clone(),
+                    message: message.clone(),
+                    lua_traceback: lua_traceback.clone(),
+                    source_context: source_context.clone(),
+                }
+            }
+            Self::BundleModuleError { module, message, original_error } => Self::BundleModuleError {
+                module: module.clone(),
+                message: message.clone(),
+                original_error: original_error.clone(),
+            },
+        }
+    }
+}
+
+impl From<mlua::Error> for LuatError {
+    /// Wraps a Lua error; running out of the engine's memory limit becomes
+    /// [`LuatError::ResourceLimit`].
+    fn from(error: mlua::Error) -> Self {
+        if is_memory_error(&error) {
+            Self::ResourceLimit(format!("Lua memory limit exceeded ({})", error))
+        } else {
+            Self::LuaError(error)
+        }
+    }
+}
+
+/// True if `error` is, or was caused by, Lua running out of memory.
+pub(crate) fn is_memory_error(error: &mlua::Error) -> bool {
+    match error {
+        mlua::Error::MemoryError(_) => true,
+        mlua::Error::CallbackError { cause, .. } | mlua::Error::WithContext { cause, .. } => is_memory_error(cause),
+        _ => false,
+    }
+}
+
 /// Convenience type alias for Results with [`LuatError`].
 pub type Result<T> = std::result::Result<T, LuatError>;
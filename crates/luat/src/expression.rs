@@ -0,0 +1,566 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Restricted Lua expression grammar for template expressions.
+//!
+//! Mustache content (`{...}`), block conditions, `{@local}` values and
+//! attribute values are pasted into the generated Lua, so a statement such as
+//! `{x = 1}` or `{local y}` used to surface as a confusing Lua syntax error
+//! (or, worse, compile and mutate state). [`validate_expression`] checks that
+//! the text is exactly one Lua expression:
+//!
+//! ```text
+//! exp      ::= unop* simple (binop unop* simple)*
+//! simple   ::= nil | true | false | Number | String | '...'
+//!            | function funcbody | table | suffixed
+//! suffixed ::= primary { '.' Name | '[' exp ']' | ':' Name args | args }
+//! primary  ::= Name | '$' Name | '(' exp ')'
+//! ```
+//!
+//! Function literals may contain statements in their body; only the
+//! expression itself is restricted.
+
+use std::fmt;
+
+/// An expression that is not a single Lua expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpressionError {
+    /// What is wrong.
+    pub message: String,
+    /// Byte offset into the expression text.
+    pub offset: usize,
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ExpressionError {}
+
+/// Statement keywords that can never start or continue an expression.
+const STATEMENT_KEYWORDS: &[&str] = &["local", "return", "if", "for", "while", "do", "repeat", "goto", "break"];
+
+const KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in", "local",
+    "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+const BINARY_OPERATORS: &[&str] = &[
+    "+", "-", "*", "/", "//", "%", "^", "..", "==", "~=", "<", "<=", ">", ">=", "and", "or", "&", "|", "~",
+    "<<", ">>",
+];
+
+/// Symbols, longest first so `...` wins over `..` and `.`.
+const SYMBOLS: &[&str] = &[
+    "...", "..", "==", "~=", "<=", ">=", "//", "<<", ">>", "::", "+", "-", "*", "/", "%", "^", "#", "&", "~",
+    "|", "<", ">", "=", "(", ")", "{", "}", "[", "]", ";", ":", ",", ".", "$",
+];
+
+/// Checks that `source` is a single Lua expression without statements or
+/// assignments.
+pub fn validate_expression(source: &str) -> Result<(), ExpressionError> {
+    let tokens = tokenize(source)?;
+    let mut parser = ExpressionParser { tokens, pos: 0 };
+    parser.expression()?;
+
+    let token = parser.peek();
+    match token.kind {
+        TokenKind::Eof => Ok(()),
+        TokenKind::Symbol if token.text == "=" => Err(parser.error_at(
+            token,
+            "assignments are not allowed in template expressions; use {@local} or a <script> block",
+        )),
+        TokenKind::Symbol if token.text == ";" => Err(parser.error_at(
+            token,
+            "statements are not allowed in template expressions; use a <script> block",
+        )),
+        _ => Err(parser.error_at(token, &format!("unexpected `{}` after the expression", token.text))),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    Name,
+    Keyword,
+    Number,
+    String,
+    Symbol,
+    Eof,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Token<'a> {
+    pub(crate) kind: TokenKind,
+    pub(crate) text: &'a str,
+    pub(crate) offset: usize,
+}
+
+pub(crate) fn tokenize(source: &str) -> Result<Vec<Token<'_>>, ExpressionError> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        let start = i;
+
+        if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if source[i..].starts_with("--") {
+            i += 2;
+            if let Some(level) = long_bracket_level(&source[i..]) {
+                i = skip_long_bracket(source, i, level, "comment")?;
+            } else {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            continue;
+        }
+
+        let kind = if c.is_ascii_alphabetic() || c == b'_' {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            if KEYWORDS.contains(&&source[start..i]) {
+                TokenKind::Keyword
+            } else {
+                TokenKind::Name
+            }
+        } else if c.is_ascii_digit() || (c == b'.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit)) {
+            while i < bytes.len() {
+                let b = bytes[i];
+                let exponent = matches!(b, b'e' | b'E' | b'p' | b'P');
+                if exponent && matches!(bytes.get(i + 1), Some(b'+') | Some(b'-')) {
+                    i += 2;
+                } else if b.is_ascii_alphanumeric() || b == b'.' || b == b'_' {
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            TokenKind::Number
+        } else if c == b'"' || c == b'\'' {
+            i += 1;
+            loop {
+                match bytes.get(i) {
+                    None | Some(b'\n') => {
+                        return Err(ExpressionError {
+                            message: "unfinished string".to_string(),
+                            offset: start,
+                        })
+                    }
+                    Some(b'\\') => i += 2,
+                    Some(&b) if b == c => {
+                        i += 1;
+                        break;
+                    }
+                    Some(_) => i += 1,
+                }
+            }
+            TokenKind::String
+        } else if let Some(level) = long_bracket_level(&source[i..]) {
+            i = skip_long_bracket(source, i, level, "string")?;
+            TokenKind::String
+        } else if let Some(symbol) = SYMBOLS.iter().find(|s| source[i..].starts_with(**s)) {
+            i += symbol.len();
+            TokenKind::Symbol
+        } else {
+            let ch = source[i..].chars().next().unwrap_or_default();
+            return Err(ExpressionError {
+                message: format!("unexpected character `{}`", ch),
+                offset: start,
+            });
+        };
+
+        tokens.push(Token {
+            kind,
+            text: &source[start..i],
+            offset: start,
+        });
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::Eof,
+        text: "end of expression",
+        offset: source.len(),
+    });
+    Ok(tokens)
+}
+
+/// Returns the keys of the table constructor `expression`, in the order
+/// they are written, as Lua expressions: `"name"` for `name = value` and the
+/// literal for `["key"] = value`. Returns `None` if `expression` is not a
+/// table constructor.
+pub(crate) fn table_constructor_keys(expression: &str) -> Option<Vec<String>> {
+    let tokens = tokenize(expression).ok()?;
+    let (keys, close) = constructor_keys(&tokens, 0)?;
+    (tokens[close + 1].kind == TokenKind::Eof).then_some(keys)
+}
+
+/// Returns the keys of the tables `script` assigns to locals, by local name,
+/// for `local name = { ... }`; see [`table_constructor_keys`].
+pub(crate) fn local_table_keys(script: &str) -> std::collections::HashMap<String, Vec<String>> {
+    let mut tables = std::collections::HashMap::new();
+    let Ok(tokens) = tokenize(script) else {
+        return tables;
+    };
+    for (i, window) in tokens.windows(4).enumerate() {
+        let is_local_table = window[0].text == "local"
+            && window[1].kind == TokenKind::Name
+            && window[2].text == "="
+            && window[3].text == "{";
+        if let Some((keys, _)) = constructor_keys(&tokens, i + 3).filter(|_| is_local_table) {
+            tables.insert(window[1].text.to_string(), keys);
+        }
+    }
+    tables
+}
+
+/// Returns the keys of the table constructor opening at `tokens[open]` and
+/// the index of its closing brace. Keys that are neither names nor string
+/// or number literals are left out.
+fn constructor_keys(tokens: &[Token<'_>], open: usize) -> Option<(Vec<String>, usize)> {
+    if tokens.get(open)?.text != "{" {
+        return None;
+    }
+    let mut keys = Vec::new();
+    let mut depth = 0;
+    let mut field_start = true;
+    let mut i = open;
+    while let Some(token) = tokens.get(i) {
+        if token.kind == TokenKind::Eof {
+            return None;
+        }
+        let text_at = |n: usize| tokens.get(i + n).map(|token| token.text);
+        if depth == 1 && field_start {
+            field_start = false;
+            if token.kind == TokenKind::Name && text_at(1) == Some("=") {
+                keys.push(format!("\"{}\"", token.text));
+            } else if token.text == "["
+                && tokens.get(i + 1).is_some_and(|key| matches!(key.kind, TokenKind::String | TokenKind::Number))
+                && text_at(2) == Some("]")
+                && text_at(3) == Some("=")
+            {
+                keys.push(tokens[i + 1].text.to_string());
+                i += 3;
+                continue;
+            }
+        }
+        if token.kind == TokenKind::Symbol {
+            match token.text {
+                "{" | "(" | "[" => depth += 1,
+                "}" | ")" | "]" => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some((keys, i));
+                    }
+                }
+                "," | ";" if depth == 1 => field_start = true,
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Returns the level of a long bracket (`[[` is 0, `[==[` is 2) at the start of `s`.
+fn long_bracket_level(s: &str) -> Option<usize> {
+    let rest = s.strip_prefix('[')?;
+    let level = rest.bytes().take_while(|&b| b == b'=').count();
+    rest[level..].starts_with('[').then_some(level)
+}
+
+/// Skips a long bracket starting at `start`, returning the offset after it.
+fn skip_long_bracket(source: &str, start: usize, level: usize, what: &str) -> Result<usize, ExpressionError> {
+    let open = level + 2;
+    let close = format!("]{}]", "=".repeat(level));
+    match source[start + open..].find(&close) {
+        Some(end) => Ok(start + open + end + close.len()),
+        None => Err(ExpressionError {
+            message: format!("unfinished long {}", what),
+            offset: start,
+        }),
+    }
+}
+
+struct ExpressionParser<'a> {
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+}
+
+impl<'a> ExpressionParser<'a> {
+    fn peek(&self) -> &Token<'a> {
+        &self.tokens[self.pos]
+    }
+
+    fn next(&mut self) -> Token<'a> {
+        let token = self.tokens[self.pos].clone();
+        if token.kind != TokenKind::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn is(&self, text: &str) -> bool {
+        let token = self.peek();
+        matches!(token.kind, TokenKind::Symbol | TokenKind::Keyword) && token.text == text
+    }
+
+    fn eat(&mut self, text: &str) -> bool {
+        if self.is(text) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), ExpressionError> {
+        if self.eat(text) {
+            Ok(())
+        } else {
+            let token = self.peek().clone();
+            Err(self.error_at(&token, &format!("expected `{}` but found `{}`", text, token.text)))
+        }
+    }
+
+    fn error_at(&self, token: &Token<'_>, message: &str) -> ExpressionError {
+        ExpressionError {
+            message: message.to_string(),
+            offset: token.offset,
+        }
+    }
+
+    fn name(&mut self) -> Result<(), ExpressionError> {
+        let token = self.next();
+        if token.kind == TokenKind::Name {
+            Ok(())
+        } else {
+            Err(self.error_at(&token, &format!("expected a name but found `{}`", token.text)))
+        }
+    }
+
+    fn expression(&mut self) -> Result<(), ExpressionError> {
+        loop {
+            while self.eat("not") || self.eat("-") || self.eat("#") || self.eat("~") {}
+            self.simple_expression()?;
+
+            let token = self.peek();
+            let is_binary = matches!(token.kind, TokenKind::Symbol | TokenKind::Keyword)
+                && BINARY_OPERATORS.contains(&token.text);
+            if !is_binary {
+                return Ok(());
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn simple_expression(&mut self) -> Result<(), ExpressionError> {
+        let token = self.peek().clone();
+        match token.kind {
+            TokenKind::Number | TokenKind::String => {
+                self.pos += 1;
+                Ok(())
+            }
+            TokenKind::Keyword => match token.text {
+                "nil" | "true" | "false" => {
+                    self.pos += 1;
+                    Ok(())
+                }
+                "function" => {
+                    self.pos += 1;
+                    self.function_body()
+                }
+                keyword if STATEMENT_KEYWORDS.contains(&keyword) => Err(self.error_at(
+                    &token,
+                    &format!(
+                        "`{}` statements are not allowed in template expressions; use a <script> block",
+                        keyword
+                    ),
+                )),
+                _ => Err(self.error_at(&token, &format!("unexpected `{}`", token.text))),
+            },
+            TokenKind::Symbol if token.text == "..." => {
+                self.pos += 1;
+                Ok(())
+            }
+            TokenKind::Symbol if token.text == "{" => self.table(),
+            TokenKind::Eof => Err(self.error_at(&token, "expected an expression")),
+            _ => self.suffixed_expression(),
+        }
+    }
+
+    fn suffixed_expression(&mut self) -> Result<(), ExpressionError> {
+        let token = self.next();
+        match (token.kind, token.text) {
+            (TokenKind::Name, _) => {}
+            (TokenKind::Symbol, "$") => self.name()?,
+            (TokenKind::Symbol, "(") => {
+                self.expression()?;
+                self.expect(")")?;
+            }
+            _ => return Err(self.error_at(&token, &format!("unexpected `{}`", token.text))),
+        }
+
+        loop {
+            if self.eat(".") {
+                self.name()?;
+            } else if self.eat("[") {
+                self.expression()?;
+                self.expect("]")?;
+            } else if self.eat(":") {
+                self.name()?;
+                self.call_arguments()?;
+            } else if self.is("(") || self.is("{") || self.peek().kind == TokenKind::String {
+                self.call_arguments()?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn call_arguments(&mut self) -> Result<(), ExpressionError> {
+        if self.peek().kind == TokenKind::String {
+            self.pos += 1;
+            return Ok(());
+        }
+        if self.is("{") {
+            return self.table();
+        }
+        self.expect("(")?;
+        if !self.eat(")") {
+            self.expression()?;
+            while self.eat(",") {
+                self.expression()?;
+            }
+            self.expect(")")?;
+        }
+        Ok(())
+    }
+
+    fn table(&mut self) -> Result<(), ExpressionError> {
+        self.expect("{")?;
+        while !self.eat("}") {
+            if self.eat("[") {
+                self.expression()?;
+                self.expect("]")?;
+                self.expect("=")?;
+            } else if self.peek().kind == TokenKind::Name && self.tokens[self.pos + 1].text == "=" {
+                self.pos += 2;
+            }
+            self.expression()?;
+            if !self.eat(",") && !self.eat(";") {
+                self.expect("}")?;
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Skips a function literal's parameters and body up to its `end`.
+    fn function_body(&mut self) -> Result<(), ExpressionError> {
+        self.expect("(")?;
+        while !self.eat(")") {
+            let token = self.next();
+            if token.kind == TokenKind::Eof {
+                return Err(self.error_at(&token, "unfinished function parameters"));
+            }
+        }
+
+        let mut depth = 1;
+        loop {
+            let token = self.next();
+            match (token.kind, token.text) {
+                (TokenKind::Eof, _) => return Err(self.error_at(&token, "expected `end` to close the function")),
+                (TokenKind::Keyword, "function" | "if" | "do" | "repeat") => depth += 1,
+                (TokenKind::Keyword, "end" | "until") => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_expressions() {
+        for source in [
+            "name",
+            "props.user.name",
+            "items[1].title:upper()",
+            "a and b or c",
+            "not x == -y",
+            "#list > 0",
+            "\"a\" .. tostring(n) .. 'b'",
+            "[[long]] .. [==[string]==]",
+            "format(\"%d\", 0x1F + 1.5e-3)",
+            "{ a = 1, [\"b\"] = 2; 3 }",
+            "(f or g)(x)",
+            "require 'mod'",
+            "t { x = 1 }",
+            "map(items, function(i) local v = i * 2 if v > 1 then return v end return 0 end)",
+            "x -- trailing comment",
+            "$state(0)",
+            "a // b << 2 ~ c",
+        ] {
+            assert_eq!(validate_expression(source), Ok(()), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_rejects_statements() {
+        let err = validate_expression("x = 1").unwrap_err();
+        assert!(err.message.contains("assignments are not allowed"));
+        assert_eq!(err.offset, 2);
+
+        let err = validate_expression("local y = 2").unwrap_err();
+        assert!(err.message.contains("`local` statements are not allowed"));
+
+        let err = validate_expression("f(); g()").unwrap_err();
+        assert!(err.message.contains("statements are not allowed"));
+
+        let err = validate_expression("return x").unwrap_err();
+        assert!(err.message.contains("`return` statements"));
+    }
+
+    #[test]
+    fn test_rejects_malformed_expressions() {
+        assert!(validate_expression("").is_err());
+        assert!(validate_expression("os.time() and").is_err());
+        assert!(validate_expression("a b").is_err());
+        assert!(validate_expression("a, b").is_err());
+        assert!(validate_expression("f(").is_err());
+        assert!(validate_expression("'open").is_err());
+        assert!(validate_expression("x @ y").is_err());
+        assert!(validate_expression("function() return 1").is_err());
+    }
+
+    #[test]
+    fn test_table_keys_in_written_order() {
+        assert_eq!(
+            table_constructor_keys("{ title = 'x', [\"data-id\"] = 1, f(a, b), nested = { z = 1 }, [k] = 2 }"),
+            Some(vec!["\"title\"".to_string(), "\"data-id\"".to_string(), "\"nested\"".to_string()])
+        );
+        assert_eq!(table_constructor_keys("{ a = 1 }.a"), None);
+        assert_eq!(table_constructor_keys("attrs"), None);
+
+        let tables = local_table_keys("local attrs = { role = 'button', id = 'go' }\nlocal n = 1");
+        assert_eq!(tables["attrs"], ["\"role\"", "\"id\""]);
+        assert!(!tables.contains_key("n"));
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Asset helper and Subresource Integrity manifest.
+//!
+//! Registers a global `asset(path)` function that returns the attributes for
+//! a stylesheet or script, ready to spread onto an element:
+//!
+//! ```html
+//! <link rel="stylesheet" {...asset("css/app.css")}>
+//! <script {...asset("js/app.js")} defer></script>
+//! ```
+//!
+//! Paths without a leading `/` are resolved under `/public/`. When the asset
+//! is listed in the [`AssetManifest`], `integrity` and `crossorigin`
+//! attributes are included.
+
+use base64::Engine as _;
+use mlua::{Lua, Result as LuaResult};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha384};
+use std::collections::BTreeMap;
+
+/// File name of the asset manifest written by `luat build`.
+pub const ASSET_MANIFEST_FILE: &str = "assets.json";
+
+/// Integrity metadata for a single asset.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetEntry {
+    /// Subresource Integrity value (e.g. `sha384-...`).
+    pub integrity: String,
+}
+
+/// Maps asset URLs (e.g. `/public/css/app.css`) to their integrity metadata.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetManifest {
+    /// Assets keyed by URL.
+    pub assets: BTreeMap<String, AssetEntry>,
+}
+
+impl AssetManifest {
+    /// Records an asset's content under the given URL.
+    pub fn insert(&mut self, url: impl Into<String>, content: &[u8]) {
+        self.assets.insert(
+            url.into(),
+            AssetEntry {
+                integrity: sri_hash(content),
+            },
+        );
+    }
+
+    /// Returns the integrity value for an asset URL, if known.
+    pub fn integrity(&self, url: &str) -> Option<&str> {
+        self.assets.get(url).map(|entry| entry.integrity.as_str())
+    }
+}
+
+/// Computes a `sha384-` Subresource Integrity value for the given content.
+pub fn sri_hash(content: &[u8]) -> String {
+    let digest = Sha384::digest(content);
+    format!(
+        "sha384-{}",
+        base64::engine::general_purpose::STANDARD.encode(digest)
+    )
+}
+
+/// Resolves an `asset()` path to its URL.
+pub fn asset_url(path: &str) -> String {
+    if path.starts_with('/') || path.contains("://") {
+        path.to_string()
+    } else {
+        format!("/public/{}", path)
+    }
+}
+
+/// Registers the global `asset(path)` helper backed by the given manifest.
+///
+/// Calling this again replaces the previous manifest.
+pub fn register_asset_helper(lua: &Lua, manifest: AssetManifest) -> LuaResult<()> {
+    let asset = lua.create_function(move |lua, path: String| {
+        let url = asset_url(&path);
+        let attrs = lua.create_table()?;
+        // Cache busters and fragments stay in the URL but aren't in the manifest
+        let file = url.split(['?', '#']).next().unwrap_or_default();
+        let url_attr = if file.ends_with(".js") { "src" } else { "href" };
+        if let Some(integrity) = manifest.integrity(file) {
+            attrs.set("integrity", integrity)?;
+            attrs.set("crossorigin", "anonymous")?;
+        }
+        attrs.set(url_attr, url)?;
+        Ok(attrs)
+    })?;
+
+    lua.globals().set("asset", asset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mlua::Table;
+
+    #[test]
+    fn test_sri_hash() {
+        // Known value for an empty input
+        assert_eq!(
+            sri_hash(b""),
+            "sha384-OLBgp1GsljhM2TJ+sbHjaiH9txEUvgdDTAzHv2P24donTt6/529l+9Ua0vFImLlb"
+        );
+    }
+
+    #[test]
+    fn test_asset_helper_attributes() {
+        let lua = Lua::new();
+        let mut manifest = AssetManifest::default();
+        manifest.insert("/public/js/app.js", b"console.log(1)");
+        register_asset_helper(&lua, manifest.clone()).unwrap();
+
+        let js: Table = lua.load(r#"asset("js/app.js")"#).eval().unwrap();
+        assert_eq!(js.get::<String>("src").unwrap(), "/public/js/app.js");
+        assert_eq!(
+            js.get::<String>("integrity").unwrap(),
+            manifest.integrity("/public/js/app.js").unwrap()
+        );
+        assert_eq!(js.get::<String>("crossorigin").unwrap(), "anonymous");
+
+        let css: Table = lua.load(r#"asset("/static/site.css")"#).eval().unwrap();
+        assert_eq!(css.get::<String>("href").unwrap(), "/static/site.css");
+        assert!(css.get::<Option<String>>("integrity").unwrap().is_none());
+
+        let busted: Table = lua.load(r#"asset("js/app.js?v=3#main")"#).eval().unwrap();
+        assert_eq!(busted.get::<String>("src").unwrap(), "/public/js/app.js?v=3#main");
+        assert_eq!(
+            busted.get::<String>("integrity").unwrap(),
+            manifest.integrity("/public/js/app.js").unwrap()
+        );
+    }
+}
@@ -0,0 +1,557 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Content collections (`content`).
+//!
+//! A collection is a directory in the content directory (the resolver's
+//! `$content` alias, `src/content` in a luat project) holding Markdown,
+//! JSON or YAML entries:
+//!
+//! ```text
+//! src/content/posts/_schema.json
+//! src/content/posts/hello.md
+//! src/content/posts/2024/recap.md
+//! ```
+//!
+//! ```lua
+//! local posts = content.query("posts", { sort = "-date", limit = 10 })
+//! for _, post in ipairs(posts) do
+//!     print(post.slug, post.data.title, post.body)
+//! end
+//! local post = content.get("posts", "2024/recap")
+//! ```
+//!
+//! Each entry has a `slug` (its path in the collection, without extension),
+//! `collection`, `path`, `data` and, for Markdown, `body`. `data` is the
+//! frontmatter of a `.md` file (a YAML block between `---` lines), or the
+//! object in a `.json`, `.yaml` or `.yml` file. YAML is read in a subset:
+//! `key: value` pairs with strings, numbers, booleans and null, `[a, b]`
+//! lists, and nested `- item` lists or `key: value` maps one level deep.
+//! Deeper nesting is an error rather than being flattened.
+//!
+//! `content.query` options: `sort` (a data field, `-field` for descending;
+//! entries without the field come last), `filter` (a function returning
+//! whether to keep an entry) and `limit`.
+//!
+//! An optional `_schema.json` in the collection maps data fields to a type,
+//! `string`, `number`, `integer`, `boolean`, `date` (ISO 8601), `array` or
+//! `object`, with a `?` suffix for optional fields. An entry that doesn't
+//! match raises an error naming the file and field. Files starting with `_`
+//! or `.` are not entries.
+//!
+//! Parsed collections are cached and read again through the resolver when
+//! one of their files is added, removed or modified, so edits show up in
+//! `luat dev` without a restart. Also available as `require("content")`.
+
+use super::feed::parse_iso_date;
+use crate::resolver::ResourceResolver;
+use mlua::{Function, Lua, LuaSerdeExt, Result as LuaResult, SerializeOptions, Table, Value};
+use serde_json::{Map, Value as JsonValue};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Resolver alias of the content directory.
+const CONTENT_ALIAS: &str = "$content";
+
+/// Schema file of a collection.
+const SCHEMA_FILE: &str = "_schema.json";
+
+/// An entry of a collection.
+#[derive(Debug, Clone, PartialEq)]
+struct ContentEntry {
+    slug: String,
+    path: String,
+    data: Map<String, JsonValue>,
+    body: Option<String>,
+}
+
+/// Parsed collections by name, with the modification times of the files
+/// they were read from.
+type CollectionCache = Arc<Mutex<HashMap<String, (Vec<(String, SystemTime)>, Vec<ContentEntry>)>>>;
+
+/// Returns the resolver directory of `collection`.
+fn collection_dir(collection: &str) -> Result<String, String> {
+    if collection.is_empty() || collection.split('/').any(|part| part.is_empty() || part == "." || part == "..") {
+        return Err(format!("invalid collection name '{}'", collection));
+    }
+    Ok(format!("{}/{}", CONTENT_ALIAS, collection))
+}
+
+/// Reads and validates the entries of `collection`, sorted by path, reusing
+/// the entries in `cache` while no file of the collection was added, removed
+/// or modified. Collections with a file of unknown modification time are
+/// always read again.
+fn load_collection(
+    resolver: &dyn ResourceResolver,
+    cache: &CollectionCache,
+    collection: &str,
+) -> Result<Vec<ContentEntry>, String> {
+    let dir = collection_dir(collection)?;
+    let paths = resolver.list_resources(&dir).map_err(|e| e.to_string())?;
+    let stamp: Option<Vec<(String, SystemTime)>> =
+        paths.iter().map(|path| resolver.resource_modified(path).map(|modified| (path.clone(), modified))).collect();
+
+    if let Some(stamp) = &stamp {
+        if let Some((cached_stamp, entries)) = cache.lock().unwrap().get(collection) {
+            if cached_stamp == stamp {
+                return Ok(entries.clone());
+            }
+        }
+    }
+    let entries = read_entries(resolver, &dir, paths)?;
+    if let Some(stamp) = stamp {
+        cache.lock().unwrap().insert(collection.to_string(), (stamp, entries.clone()));
+    }
+    Ok(entries)
+}
+
+/// Reads and validates the entries at `paths`, listed from `dir`.
+fn read_entries(resolver: &dyn ResourceResolver, dir: &str, paths: Vec<String>) -> Result<Vec<ContentEntry>, String> {
+    let mut schema = None;
+    let mut entries = Vec::new();
+    for path in paths {
+        let relative = &path[dir.len() + 1..];
+        let file_name = relative.rsplit('/').next().unwrap_or(relative);
+        if relative == SCHEMA_FILE {
+            let source = resolver.read_resource(&path).map_err(|e| e.to_string())?;
+            schema = Some(parse_schema(&source).map_err(|e| format!("{}: {}", path, e))?);
+            continue;
+        }
+        if file_name.starts_with('_') || file_name.starts_with('.') {
+            continue;
+        }
+        let Some((slug, extension)) = relative.rsplit_once('.') else {
+            continue;
+        };
+        if !matches!(extension, "md" | "json" | "yaml" | "yml") {
+            continue;
+        }
+        let source = resolver.read_resource(&path).map_err(|e| e.to_string())?;
+        let (data, body) = parse_entry(&source, extension).map_err(|e| format!("{}: {}", path, e))?;
+        entries.push(ContentEntry { slug: slug.to_string(), path, data, body });
+    }
+
+    if let Some(schema) = &schema {
+        for entry in &entries {
+            validate(&entry.data, schema).map_err(|e| format!("{}: {}", entry.path, e))?;
+        }
+    }
+    Ok(entries)
+}
+
+/// Splits an entry into its data and Markdown body.
+fn parse_entry(source: &str, extension: &str) -> Result<(Map<String, JsonValue>, Option<String>), String> {
+    match extension {
+        "md" => {
+            let (frontmatter, body) = split_frontmatter(source);
+            let data = match frontmatter {
+                Some(frontmatter) => parse_yaml(frontmatter)?,
+                None => Map::new(),
+            };
+            Ok((data, Some(body.to_string())))
+        }
+        "json" => match serde_json::from_str(source).map_err(|e| e.to_string())? {
+            JsonValue::Object(data) => Ok((data, None)),
+            _ => Err("expected a JSON object".to_string()),
+        },
+        _ => Ok((parse_yaml(source)?, None)),
+    }
+}
+
+/// Splits `---` delimited frontmatter from a Markdown source.
+fn split_frontmatter(source: &str) -> (Option<&str>, &str) {
+    let Some(rest) = source.strip_prefix("---\n").or_else(|| source.strip_prefix("---\r\n")) else {
+        return (None, source);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            let body = &rest[offset + line.len()..];
+            return (Some(&rest[..offset]), body.strip_prefix('\n').or_else(|| body.strip_prefix("\r\n")).unwrap_or(body));
+        }
+        offset += line.len();
+    }
+    (None, source)
+}
+
+/// Parses the YAML subset described in the module docs.
+fn parse_yaml(source: &str) -> Result<Map<String, JsonValue>, String> {
+    let lines: Vec<(usize, &str)> = source
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .collect();
+    let mut map = Map::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let (number, line) = lines[i];
+        if line.starts_with([' ', '\t']) {
+            return Err(format!("line {}: unexpected indentation", number + 1));
+        }
+        let (key, value) = split_key(line).ok_or_else(|| format!("line {}: expected `key: value`", number + 1))?;
+        i += 1;
+        if !value.is_empty() {
+            map.insert(key.to_string(), parse_scalar(value));
+            continue;
+        }
+        // A nested list or map in the indented lines that follow, all at the
+        // indentation of the first
+        let nested: Vec<(usize, &str)> =
+            lines[i..].iter().take_while(|(_, line)| line.starts_with([' ', '\t'])).copied().collect();
+        i += nested.len();
+        let indent = |line: &str| line.len() - line.trim_start().len();
+        if let Some(&(number, _)) = nested.iter().find(|(_, line)| indent(line) != indent(nested[0].1)) {
+            return Err(format!("line {}: nesting deeper than one level is not supported", number + 1));
+        }
+        let nested: Vec<(usize, &str)> = nested.into_iter().map(|(n, line)| (n, line.trim())).collect();
+        let value = if nested.is_empty() {
+            JsonValue::Null
+        } else if nested.iter().all(|(_, line)| line.starts_with('-')) {
+            JsonValue::Array(nested.iter().map(|(_, line)| parse_scalar(line[1..].trim())).collect())
+        } else {
+            let mut object = Map::new();
+            for (number, line) in nested {
+                let (key, value) =
+                    split_key(line).ok_or_else(|| format!("line {}: expected `key: value`", number + 1))?;
+                object.insert(key.to_string(), parse_scalar(value));
+            }
+            JsonValue::Object(object)
+        };
+        map.insert(key.to_string(), value);
+    }
+    Ok(map)
+}
+
+/// Splits `key: value`, returning the trimmed value.
+fn split_key(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once(':')?;
+    let key = key.trim().trim_matches(|c| c == '"' || c == '\'');
+    (!key.is_empty()).then(|| (key, value.trim()))
+}
+
+fn parse_scalar(value: &str) -> JsonValue {
+    let quoted = |q: char| value.len() >= 2 && value.starts_with(q) && value.ends_with(q);
+    if quoted('"') {
+        return serde_json::from_str(value).unwrap_or_else(|_| JsonValue::String(value[1..value.len() - 1].to_string()));
+    }
+    if quoted('\'') {
+        return JsonValue::String(value[1..value.len() - 1].replace("''", "'"));
+    }
+    if let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        if items.trim().is_empty() {
+            return JsonValue::Array(Vec::new());
+        }
+        return JsonValue::Array(items.split(',').map(|item| parse_scalar(item.trim())).collect());
+    }
+    match value {
+        "" | "~" | "null" => JsonValue::Null,
+        "true" => JsonValue::Bool(true),
+        "false" => JsonValue::Bool(false),
+        _ => {
+            if let Ok(i) = value.parse::<i64>() {
+                JsonValue::from(i)
+            } else if let Some(n) = value.parse::<f64>().ok().filter(|n| n.is_finite()) {
+                JsonValue::from(n)
+            } else {
+                JsonValue::String(value.to_string())
+            }
+        }
+    }
+}
+
+/// A field of a collection schema.
+#[derive(Debug)]
+struct SchemaField {
+    name: String,
+    kind: String,
+    optional: bool,
+}
+
+fn parse_schema(source: &str) -> Result<Vec<SchemaField>, String> {
+    let JsonValue::Object(fields) = serde_json::from_str(source).map_err(|e| e.to_string())? else {
+        return Err("expected an object of field types".to_string());
+    };
+    fields
+        .into_iter()
+        .map(|(name, kind)| {
+            let kind = kind.as_str().ok_or_else(|| format!("type of '{}' must be a string", name))?;
+            let (kind, optional) = match kind.strip_suffix('?') {
+                Some(kind) => (kind, true),
+                None => (kind, false),
+            };
+            if !matches!(kind, "string" | "number" | "integer" | "boolean" | "date" | "array" | "object") {
+                return Err(format!("unknown type '{}' for '{}'", kind, name));
+            }
+            Ok(SchemaField { name, kind: kind.to_string(), optional })
+        })
+        .collect()
+}
+
+fn validate(data: &Map<String, JsonValue>, schema: &[SchemaField]) -> Result<(), String> {
+    for field in schema {
+        let value = match data.get(&field.name) {
+            None | Some(JsonValue::Null) if field.optional => continue,
+            None | Some(JsonValue::Null) => return Err(format!("missing required field '{}'", field.name)),
+            Some(value) => value,
+        };
+        let valid = match field.kind.as_str() {
+            "string" => value.is_string(),
+            "number" => value.is_number(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "boolean" => value.is_boolean(),
+            "date" => value.as_str().and_then(parse_iso_date).is_some(),
+            "array" => value.is_array(),
+            _ => value.is_object(),
+        };
+        if !valid {
+            return Err(format!("field '{}' must be {} {}", field.name, article(&field.kind), field.kind));
+        }
+    }
+    Ok(())
+}
+
+fn article(kind: &str) -> &'static str {
+    if kind.starts_with(['a', 'e', 'i', 'o', 'u']) {
+        "an"
+    } else {
+        "a"
+    }
+}
+
+/// Orders entries by a data field; entries without it come last.
+fn sort_entries(entries: &mut [ContentEntry], sort: &str) {
+    let (field, descending) = match sort.strip_prefix('-') {
+        Some(field) => (field, true),
+        None => (sort, false),
+    };
+    entries.sort_by(|a, b| match (a.data.get(field), b.data.get(field)) {
+        (Some(x), Some(y)) if !x.is_null() && !y.is_null() => {
+            let ordering = compare_values(x, y);
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        }
+        (Some(x), _) if !x.is_null() => Ordering::Less,
+        (_, Some(y)) if !y.is_null() => Ordering::Greater,
+        _ => Ordering::Equal,
+    });
+}
+
+fn compare_values(a: &JsonValue, b: &JsonValue) -> Ordering {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(x), Some(y)) => x.total_cmp(&y),
+        _ => match (a.as_str(), b.as_str()) {
+            (Some(x), Some(y)) => x.cmp(y),
+            _ => a.to_string().cmp(&b.to_string()),
+        },
+    }
+}
+
+fn entry_to_lua(lua: &Lua, collection: &str, entry: ContentEntry) -> LuaResult<Table> {
+    let options = SerializeOptions::new()
+        .set_array_metatable(false)
+        .serialize_none_to_null(false)
+        .serialize_unit_to_null(false);
+    let table = lua.create_table()?;
+    table.set("slug", entry.slug)?;
+    table.set("collection", collection)?;
+    table.set("path", entry.path)?;
+    table.set("data", lua.to_value_with(&entry.data, options)?)?;
+    table.set("body", entry.body)?;
+    Ok(table)
+}
+
+/// Registers the global `content` module and `require("content")`, reading
+/// collections through `resolver`.
+pub fn register_content_module(lua: &Lua, resolver: Box<dyn ResourceResolver>) -> LuaResult<()> {
+    let content = lua.create_table()?;
+    let cache = CollectionCache::default();
+
+    let query_resolver = resolver.clone_box();
+    let query_cache = cache.clone();
+    content.set(
+        "query",
+        lua.create_function(move |lua, (collection, options): (String, Option<Table>)| {
+            let mut entries = load_collection(query_resolver.as_ref(), &query_cache, &collection)
+                .map_err(mlua::Error::runtime)?;
+            let (filter, limit) = match &options {
+                Some(options) => {
+                    if let Some(sort) = options.get::<Option<String>>("sort")? {
+                        sort_entries(&mut entries, &sort);
+                    }
+                    (options.get::<Option<Function>>("filter")?, options.get::<Option<usize>>("limit")?)
+                }
+                None => (None, None),
+            };
+
+            let results = lua.create_table()?;
+            for entry in entries {
+                if limit.is_some_and(|limit| results.raw_len() >= limit) {
+                    break;
+                }
+                let entry = entry_to_lua(lua, &collection, entry)?;
+                if let Some(filter) = &filter {
+                    if !filter.call::<bool>(entry.clone())? {
+                        continue;
+                    }
+                }
+                results.raw_push(entry)?;
+            }
+            Ok(results)
+        })?,
+    )?;
+
+    content.set(
+        "get",
+        lua.create_function(move |lua, (collection, slug): (String, String)| {
+            let entries = load_collection(resolver.as_ref(), &cache, &collection).map_err(mlua::Error::runtime)?;
+            match entries.into_iter().find(|entry| entry.slug == slug) {
+                Some(entry) => Ok(Value::Table(entry_to_lua(lua, &collection, entry)?)),
+                None => Ok(Value::Nil),
+            }
+        })?,
+    )?;
+
+    let globals = lua.globals();
+    globals.set("content", content)?;
+
+    let package: Table = globals.get("package")?;
+    let preload: Table = package.get("preload")?;
+    preload.set(
+        "content",
+        lua.create_function(|lua, _: ()| lua.globals().get::<Table>("content"))?,
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::MemoryResourceResolver;
+
+    fn resolver() -> MemoryResourceResolver {
+        let mut resolver = MemoryResourceResolver::new();
+        resolver.resources.insert(
+            "$content/posts/_schema.json".to_string(),
+            r#"{"title": "string", "date": "date", "tags": "array?"}"#.to_string(),
+        );
+        resolver.resources.insert(
+            "$content/posts/hello.md".to_string(),
+            "---\ntitle: Hello\ndate: 2024-01-15\ntags: [intro, lua]\n---\n# Hello\n".to_string(),
+        );
+        resolver.resources.insert(
+            "$content/posts/2024/recap.md".to_string(),
+            "---\ntitle: \"Recap: 2024\"\ndate: 2024-12-31\ntags:\n  - news\n---\nThe year.\n".to_string(),
+        );
+        resolver.resources.insert(
+            "$content/authors/ada.json".to_string(),
+            r#"{"name": "Ada", "posts": 3}"#.to_string(),
+        );
+        resolver
+    }
+
+    #[test]
+    fn test_parse_yaml() {
+        let data = parse_yaml(
+            "title: 'It''s here'\ncount: 3\nratio: 0.5\ndraft: false\nempty: ~\n# comment\nauthor:\n  name: Ada\n  url: \"https://ada.dev\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            JsonValue::Object(data),
+            serde_json::json!({
+                "title": "It's here",
+                "count": 3,
+                "ratio": 0.5,
+                "draft": false,
+                "empty": null,
+                "author": { "name": "Ada", "url": "https://ada.dev" },
+            })
+        );
+        assert!(parse_yaml("  indented: true").is_err());
+        assert!(parse_yaml("author:\n  name: Ada\n    url: x\n").is_err());
+        assert!(parse_yaml("author:\n  links:\n    - a\n").is_err());
+        assert!(parse_yaml("no separator").is_err());
+    }
+
+    #[test]
+    fn test_split_frontmatter() {
+        assert_eq!(split_frontmatter("---\na: 1\n---\nBody"), (Some("a: 1\n"), "Body"));
+        assert_eq!(split_frontmatter("# No frontmatter"), (None, "# No frontmatter"));
+    }
+
+    #[test]
+    fn test_content_query() {
+        let lua = Lua::new();
+        register_content_module(&lua, Box::new(resolver())).unwrap();
+        let (slugs, title, body, tag, missing): (String, String, String, String, bool) = lua
+            .load(
+                r#"
+                local slugs = {}
+                for _, post in ipairs(content.query("posts", { sort = "-date" })) do
+                    slugs[#slugs + 1] = post.slug
+                end
+                local recap = content.get("posts", "2024/recap")
+                local tagged = content.query("posts", {
+                    filter = function(post) return post.data.tags[1] == "intro" end,
+                })
+                return table.concat(slugs, ","), recap.data.title, recap.body,
+                    tagged[1].slug, content.get("posts", "nope") == nil
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!(slugs, "2024/recap,hello");
+        assert_eq!(title, "Recap: 2024");
+        assert_eq!(body, "The year.\n");
+        assert_eq!(tag, "hello");
+        assert!(missing);
+
+        let limited: usize = lua.load(r#"return #content.query("posts", { limit = 1 })"#).eval().unwrap();
+        assert_eq!(limited, 1);
+        let name: String = lua.load(r#"return content.query("authors")[1].data.name"#).eval().unwrap();
+        assert_eq!(name, "Ada");
+    }
+
+    #[test]
+    fn test_content_schema_errors() {
+        let mut resolver = resolver();
+        resolver.resources.insert(
+            "$content/posts/broken.md".to_string(),
+            "---\ntitle: Broken\ndate: soon\n---\n".to_string(),
+        );
+        let cache = CollectionCache::default();
+        let err = load_collection(&resolver, &cache, "posts").unwrap_err();
+        assert_eq!(err, "$content/posts/broken.md: field 'date' must be a date");
+        assert!(load_collection(&resolver, &cache, "../secrets").is_err());
+    }
+
+    #[cfg(feature = "filesystem")]
+    #[test]
+    fn test_content_cache_reloads_changed_files() {
+        use crate::resolver::FileSystemResolver;
+        use std::fs;
+        use std::time::{Duration, SystemTime};
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let posts = temp_dir.path().join("posts");
+        fs::create_dir_all(&posts).unwrap();
+        fs::write(posts.join("hello.md"), "---\ntitle: Hello\n---\n").unwrap();
+        let resolver = FileSystemResolver::new(temp_dir.path()).with_content_dir(temp_dir.path());
+        let cache = CollectionCache::default();
+
+        let title = |cache: &CollectionCache| load_collection(&resolver, cache, "posts").unwrap()[0].data["title"].clone();
+        assert_eq!(title(&cache), "Hello");
+        assert_eq!(cache.lock().unwrap().len(), 1);
+
+        fs::write(posts.join("hello.md"), "---\ntitle: Edited\n---\n").unwrap();
+        let file = fs::File::options().write(true).open(posts.join("hello.md")).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(5)).unwrap();
+        assert_eq!(title(&cache), "Edited");
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Deterministic render mode for golden tests and prerendered output.
+//!
+//! While enabled:
+//!
+//! - `os.time()` returns [`FROZEN_TIME`] (2000-01-01 00:00:00 UTC) and
+//!   `os.date()` formats that time, always in UTC
+//! - `os.clock()` returns `0`
+//! - `math.random` is reseeded with [`RANDOM_SEED`] via [`reseed`], which the
+//!   engine calls at the start of every request and standalone render
+//! - `pairs` visits keys in sorted order (numbers, then strings, then other
+//!   types), so class tables, attribute spreads and serialized tables come
+//!   out in the same order on every run
+//!
+//! `os.time(table)` still converts through the local time zone.
+
+use mlua::{Lua, Result as LuaResult, Table};
+
+/// Unix timestamp returned by `os.time()` in deterministic mode.
+pub const FROZEN_TIME: i64 = 946_684_800;
+
+/// Seed applied to `math.random` in deterministic mode.
+pub const RANDOM_SEED: i64 = 42;
+
+/// Registry key holding the original `pairs` and `os` functions.
+const ORIGINALS_REGISTRY_KEY: &str = "__luat_nondeterministic";
+
+const INSTALL: &str = r#"
+local originals, frozen = ...
+local time, date, raw_pairs = originals.time, originals.date, originals.pairs
+
+os.time = function(t)
+    if t == nil then return frozen end
+    return time(t)
+end
+
+os.date = function(format, t)
+    format = format or "%c"
+    if string.sub(format, 1, 1) ~= "!" then format = "!" .. format end
+    return date(format, t or frozen)
+end
+
+os.clock = function() return 0 end
+
+local rank = { number = 1, string = 2, boolean = 3 }
+local function before(a, b)
+    local ta, tb = type(a), type(b)
+    if ta ~= tb then
+        local ra, rb = rank[ta] or 4, rank[tb] or 4
+        if ra ~= rb then return ra < rb end
+        return ta < tb
+    end
+    if ta == "number" or ta == "string" then return a < b end
+    return tostring(a) < tostring(b)
+end
+
+pairs = function(t)
+    local mt = getmetatable(t)
+    if type(t) ~= "table" or (type(mt) == "table" and mt.__pairs) then
+        return raw_pairs(t)
+    end
+    local keys = {}
+    for k in next, t do keys[#keys + 1] = k end
+    table.sort(keys, before)
+    local i = 0
+    return function()
+        i = i + 1
+        local k = keys[i]
+        if k ~= nil then return k, t[k] end
+    end, t, nil
+end
+"#;
+
+/// Enables or disables deterministic mode in a Lua state.
+///
+/// Disabling restores the original `pairs`, `os.time`, `os.date` and
+/// `os.clock`.
+pub fn set_deterministic(lua: &Lua, enabled: bool) -> LuaResult<()> {
+    let globals = lua.globals();
+    let os: Table = globals.get("os")?;
+
+    let originals = match lua.named_registry_value::<Table>(ORIGINALS_REGISTRY_KEY) {
+        Ok(originals) => originals,
+        Err(_) => {
+            let originals = lua.create_table()?;
+            originals.set("pairs", globals.get::<mlua::Function>("pairs")?)?;
+            originals.set("time", os.get::<mlua::Function>("time")?)?;
+            originals.set("date", os.get::<mlua::Function>("date")?)?;
+            originals.set("clock", os.get::<mlua::Function>("clock")?)?;
+            lua.set_named_registry_value(ORIGINALS_REGISTRY_KEY, &originals)?;
+            originals
+        }
+    };
+
+    if enabled {
+        lua.load(INSTALL)
+            .set_name("luat:deterministic")
+            .call::<()>((originals, FROZEN_TIME))?;
+        reseed(lua)
+    } else {
+        globals.set("pairs", originals.get::<mlua::Function>("pairs")?)?;
+        for name in ["time", "date", "clock"] {
+            os.set(name, originals.get::<mlua::Function>(name)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reseeds `math.random` with [`RANDOM_SEED`].
+pub fn reseed(lua: &Lua) -> LuaResult<()> {
+    let math: Table = lua.globals().get("math")?;
+    math.get::<mlua::Function>("randomseed")?.call::<()>(RANDOM_SEED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frozen_time_and_sorted_pairs() {
+        let lua = Lua::new();
+        set_deterministic(&lua, true).unwrap();
+
+        let (time, date, clock): (i64, String, f64) = lua
+            .load(r#"return os.time(), os.date("%Y-%m-%d %H:%M:%S"), os.clock()"#)
+            .eval()
+            .unwrap();
+        assert_eq!(time, FROZEN_TIME);
+        assert_eq!(date, "2000-01-01 00:00:00");
+        assert_eq!(clock, 0.0);
+
+        let keys: String = lua
+            .load(
+                r#"
+                local out = {}
+                for k in pairs({ zeta = 1, alpha = 2, [2] = 3, [1] = 4, mid = 5 }) do
+                    out[#out + 1] = tostring(k)
+                end
+                return table.concat(out, ",")
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!(keys, "1,2,alpha,mid,zeta");
+    }
+
+    #[test]
+    fn test_random_is_reseeded() {
+        let lua = Lua::new();
+        set_deterministic(&lua, true).unwrap();
+        let draw = || lua.load("return math.random(1, 1000000)").eval::<i64>().unwrap();
+
+        let first = draw();
+        reseed(&lua).unwrap();
+        assert_eq!(draw(), first);
+    }
+
+    #[test]
+    fn test_disable_restores_originals() {
+        let lua = Lua::new();
+        set_deterministic(&lua, true).unwrap();
+        set_deterministic(&lua, false).unwrap();
+
+        let time: i64 = lua.load("return os.time()").eval().unwrap();
+        assert!(time > FROZEN_TIME);
+    }
+}
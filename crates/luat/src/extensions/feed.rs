@@ -0,0 +1,505 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! RSS 2.0 and Atom feed generation (`feed`).
+//!
+//! `feed.rss(spec)` and `feed.atom(spec)` return a response table that a
+//! `+server.lua` handler can return directly; `feed.rss_xml(spec)` and
+//! `feed.atom_xml(spec)` return just the XML.
+//!
+//! ```lua
+//! function GET(ctx)
+//!     return feed.atom {
+//!         title = "My Blog",
+//!         link = "https://example.com/blog",
+//!         feed_url = "https://example.com/blog/feed.xml",
+//!         description = "Posts about Lua",
+//!         entries = {
+//!             { title = "Hello", link = "https://example.com/blog/hello",
+//!               date = "2024-01-15", summary = "First post", author = "Ada" },
+//!         },
+//!     }
+//! end
+//! ```
+//!
+//! Entry fields: `title`, `link`, `id` (defaults to `link`), `summary`,
+//! `content` (HTML), `date`, `updated`, `author` and `categories`. Dates are
+//! Unix timestamps or ISO 8601 strings (`2024-01-15`,
+//! `2024-01-15T10:30:00+02:00`). Feed fields: `title`, `link`,
+//! `description`, `feed_url`, `id`, `language`, `author` and `updated`
+//! (defaults to the newest entry, then the current time).
+//!
+//! Also available as `require("feed")`.
+
+use super::fmt::{civil_date, lua_now};
+use mlua::{Lua, Result as LuaResult, Table, Value};
+use std::fmt::Write;
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+/// Escapes text for XML element content and attribute values, dropping
+/// control characters XML 1.0 does not allow.
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if (c as u32) < 0x20 || c == '\u{FFFE}' || c == '\u{FFFF}' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Days since 1970-01-01 for a calendar date (inverse of `civil_date`).
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = i64::from(month);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Parses `YYYY-MM-DD` with an optional `THH:MM[:SS[.fff]]` time and a `Z`
+/// or `±HH:MM` offset (UTC when omitted) into a Unix timestamp.
+pub(crate) fn parse_iso_date(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let year = s.get(0..4).filter(|y| y.bytes().all(|b| b.is_ascii_digit()))?.parse().ok()?;
+    let month = number_at(s, 5)? as u32;
+    let day = number_at(s, 8)? as u32;
+    if s.get(4..5)? != "-" || s.get(7..8)? != "-" || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let mut timestamp = days_from_civil(year, month, day) * 86_400;
+
+    let time = &s[10..];
+    if time.is_empty() {
+        return Some(timestamp);
+    }
+    if !time.starts_with('T') && !time.starts_with(' ') {
+        return None;
+    }
+    let clock = &time[1..];
+    let hour = number_at(clock, 0)?;
+    let minute = number_at(clock, 3)?;
+    if clock.get(2..3)? != ":" {
+        return None;
+    }
+    let mut rest = &clock[5..];
+    let mut second = 0;
+    if let Some(after) = rest.strip_prefix(':') {
+        second = number_at(after, 0)?;
+        rest = &after[2..];
+        if let Some(fraction) = rest.strip_prefix('.') {
+            let digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+            rest = &fraction[digits..];
+        }
+    }
+    timestamp += hour * 3_600 + minute * 60 + second;
+
+    match rest {
+        "" | "Z" | "z" => Some(timestamp),
+        offset => {
+            let sign = match offset.get(0..1)? {
+                "+" => 1,
+                "-" => -1,
+                _ => return None,
+            };
+            let offset = &offset[1..];
+            let hours = number_at(offset, 0)?;
+            let minutes = match offset.get(2..) {
+                Some("") => 0,
+                Some(m) => number_at(m.strip_prefix(':').unwrap_or(m), 0)?,
+                None => return None,
+            };
+            Some(timestamp - sign * (hours * 3_600 + minutes * 60))
+        }
+    }
+}
+
+/// Reads a two-digit number at `at`.
+fn number_at(s: &str, at: usize) -> Option<i64> {
+    let part = s.get(at..at + 2)?;
+    if part.bytes().all(|b| b.is_ascii_digit()) {
+        part.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// RFC 822 date as used by RSS, e.g. `Mon, 15 Jan 2024 10:30:00 +0000`.
+fn rfc822(timestamp: i64) -> String {
+    let date = civil_date(timestamp);
+    let seconds = timestamp.rem_euclid(86_400);
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} +0000",
+        WEEKDAYS[date.weekday],
+        date.day,
+        MONTHS[date.month as usize - 1],
+        date.year,
+        seconds / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60
+    )
+}
+
+/// RFC 3339 date as used by Atom, e.g. `2024-01-15T10:30:00Z`.
+fn rfc3339(timestamp: i64) -> String {
+    let date = civil_date(timestamp);
+    let seconds = timestamp.rem_euclid(86_400);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        date.year,
+        date.month,
+        date.day,
+        seconds / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60
+    )
+}
+
+/// Reads an optional date field as a Unix timestamp.
+fn date_field(table: &Table, key: &str) -> LuaResult<Option<i64>> {
+    match table.get::<Value>(key)? {
+        Value::Nil => Ok(None),
+        Value::Integer(n) => Ok(Some(n)),
+        Value::Number(n) => Ok(Some(n as i64)),
+        Value::String(s) => {
+            let s = s.to_str()?;
+            parse_iso_date(&s)
+                .map(Some)
+                .ok_or_else(|| mlua::Error::runtime(format!("feed: cannot parse `{}` date \"{}\"", key, &*s)))
+        }
+        other => Err(mlua::Error::runtime(format!(
+            "feed: `{}` must be a timestamp or ISO 8601 string, got {}",
+            key,
+            other.type_name()
+        ))),
+    }
+}
+
+/// A feed entry read from Lua.
+#[derive(Debug, Default)]
+struct Entry {
+    title: String,
+    link: Option<String>,
+    id: Option<String>,
+    summary: Option<String>,
+    content: Option<String>,
+    published: Option<i64>,
+    updated: Option<i64>,
+    author: Option<String>,
+    categories: Vec<String>,
+}
+
+impl Entry {
+    fn from_table(table: &Table) -> LuaResult<Self> {
+        let categories = match table.get::<Value>("categories")? {
+            Value::Table(list) => list.sequence_values::<String>().collect::<LuaResult<_>>()?,
+            Value::String(s) => vec![s.to_str()?.to_string()],
+            _ => Vec::new(),
+        };
+        Ok(Self {
+            title: table.get::<Option<String>>("title")?.unwrap_or_default(),
+            link: table.get("link")?,
+            id: table.get("id")?,
+            summary: table.get("summary")?,
+            content: table.get("content")?,
+            published: date_field(table, "date")?,
+            updated: date_field(table, "updated")?,
+            author: table.get("author")?,
+            categories,
+        })
+    }
+
+    fn id(&self) -> Option<&str> {
+        self.id.as_deref().or(self.link.as_deref())
+    }
+
+    fn last_modified(&self) -> Option<i64> {
+        self.updated.or(self.published)
+    }
+}
+
+/// A feed read from Lua.
+#[derive(Debug, Default)]
+struct Feed {
+    title: String,
+    link: String,
+    description: Option<String>,
+    feed_url: Option<String>,
+    id: Option<String>,
+    language: Option<String>,
+    author: Option<String>,
+    updated: i64,
+    entries: Vec<Entry>,
+}
+
+impl Feed {
+    fn from_table(lua: &Lua, table: &Table) -> LuaResult<Self> {
+        let mut entries = Vec::new();
+        if let Some(list) = table.get::<Option<Table>>("entries")? {
+            for entry in list.sequence_values::<Table>() {
+                entries.push(Entry::from_table(&entry?)?);
+            }
+        }
+        let updated = match date_field(table, "updated")? {
+            Some(updated) => updated,
+            None => match entries.iter().filter_map(Entry::last_modified).max() {
+                Some(newest) => newest,
+                None => lua_now(lua)?,
+            },
+        };
+        Ok(Self {
+            title: table.get::<Option<String>>("title")?.unwrap_or_default(),
+            link: table.get::<Option<String>>("link")?.unwrap_or_default(),
+            description: table.get("description")?,
+            feed_url: table.get("feed_url")?,
+            id: table.get("id")?,
+            language: table.get("language")?,
+            author: table.get("author")?,
+            updated,
+            entries,
+        })
+    }
+
+    fn to_rss(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n<channel>\n");
+        let _ = writeln!(xml, "<title>{}</title>", xml_escape(&self.title));
+        let _ = writeln!(xml, "<link>{}</link>", xml_escape(&self.link));
+        let _ = writeln!(
+            xml,
+            "<description>{}</description>",
+            xml_escape(self.description.as_deref().unwrap_or(&self.title))
+        );
+        if let Some(feed_url) = &self.feed_url {
+            let _ = writeln!(
+                xml,
+                "<atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\"/>",
+                xml_escape(feed_url)
+            );
+        }
+        if let Some(language) = &self.language {
+            let _ = writeln!(xml, "<language>{}</language>", xml_escape(language));
+        }
+        let _ = writeln!(xml, "<lastBuildDate>{}</lastBuildDate>", rfc822(self.updated));
+
+        for entry in &self.entries {
+            xml.push_str("<item>\n");
+            let _ = writeln!(xml, "<title>{}</title>", xml_escape(&entry.title));
+            if let Some(link) = &entry.link {
+                let _ = writeln!(xml, "<link>{}</link>", xml_escape(link));
+            }
+            if let Some(id) = entry.id() {
+                let permalink = entry.id.is_none() || entry.id == entry.link;
+                let _ = writeln!(
+                    xml,
+                    "<guid isPermaLink=\"{}\">{}</guid>",
+                    permalink,
+                    xml_escape(id)
+                );
+            }
+            if let Some(description) = entry.content.as_deref().or(entry.summary.as_deref()) {
+                let _ = writeln!(xml, "<description>{}</description>", xml_escape(description));
+            }
+            if let Some(author) = &entry.author {
+                let _ = writeln!(xml, "<dc:creator>{}</dc:creator>", xml_escape(author));
+            }
+            for category in &entry.categories {
+                let _ = writeln!(xml, "<category>{}</category>", xml_escape(category));
+            }
+            if let Some(date) = entry.published.or(entry.updated) {
+                let _ = writeln!(xml, "<pubDate>{}</pubDate>", rfc822(date));
+            }
+            xml.push_str("</item>\n");
+        }
+
+        xml.push_str("</channel>\n</rss>\n");
+        xml
+    }
+
+    fn to_atom(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        match &self.language {
+            Some(language) => {
+                let _ = writeln!(
+                    xml,
+                    "<feed xmlns=\"http://www.w3.org/2005/Atom\" xml:lang=\"{}\">",
+                    xml_escape(language)
+                );
+            }
+            None => xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n"),
+        }
+        let _ = writeln!(xml, "<title>{}</title>", xml_escape(&self.title));
+        if let Some(description) = &self.description {
+            let _ = writeln!(xml, "<subtitle>{}</subtitle>", xml_escape(description));
+        }
+        let id = self.id.as_deref().or(self.feed_url.as_deref()).unwrap_or(&self.link);
+        let _ = writeln!(xml, "<id>{}</id>", xml_escape(id));
+        let _ = writeln!(xml, "<link href=\"{}\"/>", xml_escape(&self.link));
+        if let Some(feed_url) = &self.feed_url {
+            let _ = writeln!(xml, "<link rel=\"self\" href=\"{}\"/>", xml_escape(feed_url));
+        }
+        let _ = writeln!(xml, "<updated>{}</updated>", rfc3339(self.updated));
+        if let Some(author) = &self.author {
+            let _ = writeln!(xml, "<author><name>{}</name></author>", xml_escape(author));
+        }
+
+        for entry in &self.entries {
+            xml.push_str("<entry>\n");
+            let _ = writeln!(xml, "<title>{}</title>", xml_escape(&entry.title));
+            if let Some(id) = entry.id() {
+                let _ = writeln!(xml, "<id>{}</id>", xml_escape(id));
+            }
+            if let Some(link) = &entry.link {
+                let _ = writeln!(xml, "<link href=\"{}\"/>", xml_escape(link));
+            }
+            let updated = entry.last_modified().unwrap_or(self.updated);
+            let _ = writeln!(xml, "<updated>{}</updated>", rfc3339(updated));
+            if let Some(published) = entry.published {
+                let _ = writeln!(xml, "<published>{}</published>", rfc3339(published));
+            }
+            if let Some(author) = &entry.author {
+                let _ = writeln!(xml, "<author><name>{}</name></author>", xml_escape(author));
+            }
+            for category in &entry.categories {
+                let _ = writeln!(xml, "<category term=\"{}\"/>", xml_escape(category));
+            }
+            if let Some(summary) = &entry.summary {
+                let _ = writeln!(xml, "<summary>{}</summary>", xml_escape(summary));
+            }
+            if let Some(content) = &entry.content {
+                let _ = writeln!(xml, "<content type=\"html\">{}</content>", xml_escape(content));
+            }
+            xml.push_str("</entry>\n");
+        }
+
+        xml.push_str("</feed>\n");
+        xml
+    }
+}
+
+/// Wraps feed XML in a `+server.lua` response table.
+fn feed_response(lua: &Lua, xml: String, content_type: &str) -> LuaResult<Table> {
+    let headers = lua.create_table()?;
+    headers.set("Content-Type", content_type)?;
+    let response = lua.create_table()?;
+    response.set("status", 200)?;
+    response.set("headers", headers)?;
+    response.set("body", xml)?;
+    Ok(response)
+}
+
+/// Registers the global `feed` module and `require("feed")`.
+pub fn register_feed_module(lua: &Lua) -> LuaResult<()> {
+    let feed = lua.create_table()?;
+
+    feed.set(
+        "rss_xml",
+        lua.create_function(|lua, spec: Table| Ok(Feed::from_table(lua, &spec)?.to_rss()))?,
+    )?;
+    feed.set(
+        "atom_xml",
+        lua.create_function(|lua, spec: Table| Ok(Feed::from_table(lua, &spec)?.to_atom()))?,
+    )?;
+    feed.set(
+        "rss",
+        lua.create_function(|lua, spec: Table| {
+            let xml = Feed::from_table(lua, &spec)?.to_rss();
+            feed_response(lua, xml, "application/rss+xml; charset=utf-8")
+        })?,
+    )?;
+    feed.set(
+        "atom",
+        lua.create_function(|lua, spec: Table| {
+            let xml = Feed::from_table(lua, &spec)?.to_atom();
+            feed_response(lua, xml, "application/atom+xml; charset=utf-8")
+        })?,
+    )?;
+
+    let globals = lua.globals();
+    globals.set("feed", feed)?;
+
+    let package: Table = globals.get("package")?;
+    let preload: Table = package.get("preload")?;
+    preload.set(
+        "feed",
+        lua.create_function(|lua, _: ()| lua.globals().get::<Table>("feed"))?,
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_iso_date() {
+        assert_eq!(parse_iso_date("1970-01-01"), Some(0));
+        assert_eq!(parse_iso_date("2024-01-15"), Some(1_705_276_800));
+        assert_eq!(parse_iso_date("2024-01-15T10:30:00Z"), Some(1_705_314_600));
+        assert_eq!(parse_iso_date("2024-01-15T12:30:00.250+02:00"), Some(1_705_314_600));
+        assert_eq!(parse_iso_date("2024-01-15 10:30"), Some(1_705_314_600));
+        assert_eq!(parse_iso_date("15/01/2024"), None);
+        assert_eq!(parse_iso_date("2024-13-01"), None);
+    }
+
+    #[test]
+    fn test_date_formats() {
+        assert_eq!(rfc822(1_705_314_600), "Mon, 15 Jan 2024 10:30:00 +0000");
+        assert_eq!(rfc3339(1_705_314_600), "2024-01-15T10:30:00Z");
+    }
+
+    #[test]
+    fn test_rss_and_atom() {
+        let lua = Lua::new();
+        register_feed_module(&lua).unwrap();
+
+        let (rss, atom, content_type): (String, String, String) = lua
+            .load(
+                r#"
+                local spec = {
+                    title = "Fish & Chips",
+                    link = "https://example.com/",
+                    feed_url = "https://example.com/feed.xml",
+                    entries = {
+                        { title = "<Hello>", link = "https://example.com/a?x=1&y=2",
+                          date = "2024-01-15", content = "<p>Hi</p>\0", categories = { "news" } },
+                    },
+                }
+                local response = require("feed").rss(spec)
+                return response.body, feed.atom_xml(spec), response.headers["Content-Type"]
+                "#,
+            )
+            .eval()
+            .unwrap();
+
+        assert_eq!(content_type, "application/rss+xml; charset=utf-8");
+        assert!(rss.contains("<title>Fish &amp; Chips</title>"));
+        assert!(rss.contains("<title>&lt;Hello&gt;</title>"));
+        assert!(rss.contains("<link>https://example.com/a?x=1&amp;y=2</link>"));
+        assert!(rss.contains("<description>&lt;p&gt;Hi&lt;/p&gt;</description>"));
+        assert!(rss.contains("<pubDate>Mon, 15 Jan 2024 00:00:00 +0000</pubDate>"));
+        assert!(rss.contains("<lastBuildDate>Mon, 15 Jan 2024 00:00:00 +0000</lastBuildDate>"));
+        assert!(rss.contains("<category>news</category>"));
+
+        assert!(atom.contains("<id>https://example.com/feed.xml</id>"));
+        assert!(atom.contains("<link rel=\"self\" href=\"https://example.com/feed.xml\"/>"));
+        assert!(atom.contains("<updated>2024-01-15T00:00:00Z</updated>"));
+        assert!(atom.contains("<content type=\"html\">&lt;p&gt;Hi&lt;/p&gt;</content>"));
+        assert!(atom.contains("<category term=\"news\"/>"));
+    }
+}
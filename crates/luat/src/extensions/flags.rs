@@ -0,0 +1,455 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Feature flags (`flags`) and the `{#feature}` block.
+//!
+//! Registers a global `flags` table, available to server code and templates:
+//!
+//! ```lua
+//! flags.enabled("new-checkout")                  -- true / false
+//! flags.enabled("new-checkout", { user = id })   -- evaluate for a given user
+//! flags.identify(user.id)                        -- user for the rest of the request
+//! flags.user()                                   -- current user, or nil
+//! flags.variants()                               -- experiments assigned so far
+//! ```
+//!
+//! Templates can gate markup with the `{#feature}` block, which takes the
+//! same arguments as `flags.enabled`:
+//!
+//! ```text
+//! {#feature "new-checkout"}<NewCheckout />{/feature}
+//! ```
+//!
+//! A flag is on when any of its rules matches, checked in order: the user is
+//! listed in `users`, the request carries the flag's `cookie` (with a value
+//! other than `0`, `false` or `off`), `enabled` is set, or the user falls
+//! into the `rollout` percentage. Rollout buckets are derived from the flag
+//! name and the user, so a user keeps the same answer across requests. The
+//! user is `opts.user`, then the one passed to `flags.identify`, then the
+//! value of [`FeatureFlags::user_cookie`]. Since the client controls that
+//! cookie, it only places anonymous visitors in rollouts and experiments and
+//! never matches `users`. Without a user, partial rollouts are off. Unknown
+//! flags are off.
+//!
+//! When an override store is set, a value stored under the flag's name
+//! replaces its definition: `on`/`off` (or `true`/`false`, `1`/`0`) force the
+//! flag, while a JSON object is read as a [`FeatureFlag`].
+//!
+//! # Experiments
+//!
+//! `variant(name, variants)` assigns the user to one of the variants of an
+//! A/B test:
+//!
+//! ```lua
+//! local layout = variant("checkout-layout", { "control", "one-page" })
+//! ```
+//!
+//! The assignment is derived from the experiment name and the same user as
+//! flags, so it is stable across requests. Without a user, or while a flag
+//! of the same name is defined and off, the first variant is returned and
+//! nothing is recorded. Assignments made during a page request are sent in
+//! the [`VARIANTS_HEADER`] response header (`checkout-layout=one-page`) for
+//! analytics, and `flags.variants()` returns them to templates.
+
+use crate::directives::BlockDirectiveCall;
+use crate::error::{LuatError, Result};
+use crate::kv::KVStore;
+use mlua::{Lua, Result as LuaResult, Table, Value};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// Registry key of the per-request runtime table, which carries `cookies`.
+const REQUEST_RUNTIME_REGISTRY_KEY: &str = "__luat_request_runtime";
+
+/// Key in the request runtime holding the user set by `flags.identify`.
+const RUNTIME_USER_KEY: &str = "flag_user";
+
+/// Key in the request runtime holding the experiment assignments.
+const RUNTIME_VARIANTS_KEY: &str = "flag_variants";
+
+/// Response header listing the experiment assignments of a page request.
+pub const VARIANTS_HEADER: &str = "x-luat-variants";
+
+/// Definition of a single feature flag.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeatureFlag {
+    /// Turns the flag on for everyone.
+    pub enabled: bool,
+    /// Percentage of users (0-100) the flag is on for.
+    pub rollout: Option<f64>,
+    /// Users the flag is always on for.
+    pub users: Vec<String>,
+    /// Cookie that turns the flag on for the requests carrying it.
+    pub cookie: Option<String>,
+}
+
+/// Feature flag definitions, keyed by flag name.
+///
+/// Deserializes from a table of flags next to the settings, as in `luat.toml`:
+///
+/// ```toml
+/// [flags]
+/// user_cookie = "session_id"
+///
+/// [flags.new-checkout]
+/// rollout = 25
+/// users = ["alice"]
+/// cookie = "beta"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FeatureFlags {
+    /// Cookie whose value buckets anonymous visitors into rollouts and
+    /// experiments; it never matches a flag's `users`.
+    #[serde(default)]
+    pub user_cookie: Option<String>,
+    /// Flag definitions.
+    #[serde(flatten)]
+    pub flags: BTreeMap<String, FeatureFlag>,
+}
+
+impl FeatureFlags {
+    /// Adds or replaces a flag definition.
+    pub fn insert(&mut self, name: impl Into<String>, flag: FeatureFlag) {
+        self.flags.insert(name.into(), flag);
+    }
+}
+
+/// The user a flag is evaluated for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FlagUser {
+    /// A user identified by the server, through `opts.user` or `flags.identify`.
+    Identified(String),
+    /// The value of [`FeatureFlags::user_cookie`], set by the client.
+    Cookie(String),
+}
+
+impl FlagUser {
+    /// Returns the user id used for rollout buckets.
+    pub fn id(&self) -> &str {
+        match self {
+            FlagUser::Identified(id) | FlagUser::Cookie(id) => id,
+        }
+    }
+}
+
+impl FeatureFlag {
+    /// Evaluates the flag for a request.
+    ///
+    /// Only an identified user can match `users`.
+    pub fn evaluate(&self, name: &str, user: Option<&FlagUser>, cookies: &HashMap<String, String>) -> bool {
+        if let Some(FlagUser::Identified(user)) = user {
+            if self.users.iter().any(|u| u == user) {
+                return true;
+            }
+        }
+        if let Some(cookie) = &self.cookie {
+            if cookies.get(cookie).is_some_and(|value| is_truthy(value)) {
+                return true;
+            }
+        }
+        if self.enabled {
+            return true;
+        }
+        match (self.rollout, user) {
+            (Some(rollout), _) if rollout >= 100.0 => true,
+            (Some(rollout), Some(user)) => f64::from(bucket(name, user.id())) < rollout * 100.0,
+            _ => false,
+        }
+    }
+}
+
+/// Returns the stable bucket (0..10000) of `user` for the given key.
+pub fn bucket(key: &str, user: &str) -> u32 {
+    let digest = Sha256::digest(format!("{}:{}", key, user).as_bytes());
+    u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]) % 10_000
+}
+
+fn is_truthy(value: &str) -> bool {
+    !matches!(value.trim().to_ascii_lowercase().as_str(), "" | "0" | "false" | "off")
+}
+
+/// Reads the definition override for `name` from the store, if any.
+fn read_override(store: &dyn KVStore, name: &str) -> Option<FeatureFlag> {
+    // A failing store must not take pages down; the configured flag applies.
+    let value = store.get(name).ok()??;
+    let value = String::from_utf8(value).ok()?;
+    match value.trim().to_ascii_lowercase().as_str() {
+        "on" | "true" | "1" => Some(FeatureFlag { enabled: true, ..Default::default() }),
+        "off" | "false" | "0" => Some(FeatureFlag::default()),
+        _ => serde_json::from_str(&value).ok(),
+    }
+}
+
+/// Returns the user flags are evaluated for, see the module documentation.
+pub(crate) fn current_user(lua: &Lua, user_cookie: Option<&str>, opts: Option<&Table>) -> LuaResult<Option<FlagUser>> {
+    if let Some(user) = opts.map(|o| o.get::<Option<String>>("user")).transpose()?.flatten() {
+        return Ok(Some(FlagUser::Identified(user)));
+    }
+    let Some(runtime) = lua.named_registry_value::<Option<Table>>(REQUEST_RUNTIME_REGISTRY_KEY)? else {
+        return Ok(None);
+    };
+    if let Some(user) = runtime.get::<Option<String>>(RUNTIME_USER_KEY)? {
+        return Ok(Some(FlagUser::Identified(user)));
+    }
+    match user_cookie {
+        Some(cookie) => Ok(request_cookies(lua)?
+            .remove(cookie)
+            .filter(|value| !value.is_empty())
+            .map(FlagUser::Cookie)),
+        None => Ok(None),
+    }
+}
+
+/// Returns the cookies of the current request.
+pub(crate) fn request_cookies(lua: &Lua) -> LuaResult<HashMap<String, String>> {
+    match lua.named_registry_value::<Option<Table>>(REQUEST_RUNTIME_REGISTRY_KEY)? {
+        Some(runtime) => Ok(runtime.get::<Option<HashMap<String, String>>>("cookies")?.unwrap_or_default()),
+        None => Ok(HashMap::new()),
+    }
+}
+
+/// Returns the value of the [`VARIANTS_HEADER`] for a request runtime, if
+/// any experiment was assigned.
+pub(crate) fn variants_header(runtime: &Table) -> LuaResult<Option<String>> {
+    let Some(variants) = runtime.get::<Option<BTreeMap<String, String>>>(RUNTIME_VARIANTS_KEY)? else {
+        return Ok(None);
+    };
+    if variants.is_empty() {
+        return Ok(None);
+    }
+    let pairs: Vec<String> = variants
+        .iter()
+        .map(|(name, variant)| format!("{}={}", name, variant))
+        .collect();
+    Ok(Some(pairs.join(", ")))
+}
+
+/// Flag definitions and overrides shared by the `flags` functions.
+struct FlagState {
+    flags: FeatureFlags,
+    overrides: Option<Arc<dyn KVStore>>,
+}
+
+impl FlagState {
+    /// Returns the override or configured definition of `name`.
+    fn definition(&self, name: &str) -> Option<FeatureFlag> {
+        self.overrides
+            .as_deref()
+            .and_then(|store| read_override(store, name))
+            .or_else(|| self.flags.flags.get(name).cloned())
+    }
+
+    fn enabled(&self, lua: &Lua, name: &str, user: Option<&FlagUser>) -> LuaResult<bool> {
+        match self.definition(name) {
+            Some(flag) => Ok(flag.evaluate(name, user, &request_cookies(lua)?)),
+            None => Ok(false),
+        }
+    }
+
+    fn variant(&self, lua: &Lua, name: &str, variants: &[Value], opts: Option<&Table>) -> LuaResult<Value> {
+        let Some(control) = variants.first() else {
+            return Err(mlua::Error::runtime(format!(
+                "variant(\"{}\") needs at least one variant",
+                name
+            )));
+        };
+        let Some(user) = current_user(lua, self.flags.user_cookie.as_deref(), opts)? else {
+            return Ok(control.clone());
+        };
+        if let Some(flag) = self.definition(name) {
+            if !flag.evaluate(name, Some(&user), &request_cookies(lua)?) {
+                return Ok(control.clone());
+            }
+        }
+
+        let index = bucket(name, user.id()) as usize * variants.len() / 10_000;
+        let chosen = variants[index].clone();
+        if let Some(runtime) = lua.named_registry_value::<Option<Table>>(REQUEST_RUNTIME_REGISTRY_KEY)? {
+            let assigned = match runtime.get::<Option<Table>>(RUNTIME_VARIANTS_KEY)? {
+                Some(assigned) => assigned,
+                None => {
+                    let assigned = lua.create_table()?;
+                    runtime.set(RUNTIME_VARIANTS_KEY, &assigned)?;
+                    assigned
+                }
+            };
+            assigned.set(name, lua.coerce_string(chosen.clone())?)?;
+        }
+        Ok(chosen)
+    }
+}
+
+/// Registers the global `flags` table and `variant` function with the given
+/// definitions.
+///
+/// Values in `overrides` take precedence over `flags`, see the module
+/// documentation. Calling this again replaces the previous definitions.
+pub fn register_flags_module(
+    lua: &Lua,
+    flags: FeatureFlags,
+    overrides: Option<Arc<dyn KVStore>>,
+) -> LuaResult<()> {
+    let state = Arc::new(FlagState { flags, overrides });
+    let table = lua.create_table()?;
+
+    let enabled_state = state.clone();
+    table.set(
+        "enabled",
+        lua.create_function(move |lua, (name, opts): (String, Option<Table>)| {
+            let user = current_user(lua, enabled_state.flags.user_cookie.as_deref(), opts.as_ref())?;
+            enabled_state.enabled(lua, &name, user.as_ref())
+        })?,
+    )?;
+
+    let variant_state = state.clone();
+    let variant = lua.create_function(move |lua, (name, variants, opts): (String, Vec<Value>, Option<Table>)| {
+        variant_state.variant(lua, &name, &variants, opts.as_ref())
+    })?;
+    table.set("variant", &variant)?;
+
+    table.set(
+        "variants",
+        lua.create_function(|lua, ()| {
+            let runtime = lua.named_registry_value::<Option<Table>>(REQUEST_RUNTIME_REGISTRY_KEY)?;
+            match runtime.map(|r| r.get::<Option<Table>>(RUNTIME_VARIANTS_KEY)).transpose()?.flatten() {
+                Some(assigned) => Ok(assigned),
+                None => lua.create_table(),
+            }
+        })?,
+    )?;
+
+    table.set(
+        "identify",
+        lua.create_function(|lua, user: Option<String>| {
+            if let Some(runtime) = lua.named_registry_value::<Option<Table>>(REQUEST_RUNTIME_REGISTRY_KEY)? {
+                runtime.set(RUNTIME_USER_KEY, user)?;
+            }
+            Ok(())
+        })?,
+    )?;
+
+    let user_cookie = state.flags.user_cookie.clone();
+    table.set(
+        "user",
+        lua.create_function(move |lua, ()| {
+            Ok(current_user(lua, user_cookie.as_deref(), None)?.map(|user| user.id().to_string()))
+        })?,
+    )?;
+
+    let globals = lua.globals();
+    globals.set("flags", table)?;
+    globals.set("variant", variant)
+}
+
+/// Built-in `{#feature}` directive: renders its children when
+/// `flags.enabled(args)` is true.
+pub(crate) fn feature_directive(call: &BlockDirectiveCall<'_>) -> Result<String> {
+    let args = call.args.map(|args| args.content.trim()).unwrap_or_default();
+    if args.is_empty() {
+        return Err(LuatError::CodegenError(format!(
+            "{{#feature}} requires a flag name at line {}, column {}",
+            call.span.line, call.span.column
+        )));
+    }
+    Ok(format!("if flags.enabled({}) then\n__children(__write)\nend", args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv::{MemoryKVStore, PutOptions};
+
+    fn runtime_with_cookies(lua: &Lua, cookies: &[(&str, &str)]) {
+        let runtime = lua.create_table().unwrap();
+        let map: HashMap<String, String> =
+            cookies.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        runtime.set("cookies", map).unwrap();
+        lua.set_named_registry_value(REQUEST_RUNTIME_REGISTRY_KEY, runtime).unwrap();
+    }
+
+    #[test]
+    fn test_flag_rules() {
+        let lua = Lua::new();
+        let mut flags = FeatureFlags { user_cookie: Some("uid".to_string()), ..Default::default() };
+        flags.insert("on", FeatureFlag { enabled: true, ..Default::default() });
+        flags.insert("beta", FeatureFlag { cookie: Some("beta".to_string()), ..Default::default() });
+        flags.insert("staff", FeatureFlag { users: vec!["alice".to_string()], ..Default::default() });
+        flags.insert("half", FeatureFlag { rollout: Some(50.0), ..Default::default() });
+        register_flags_module(&lua, flags, None).unwrap();
+
+        runtime_with_cookies(&lua, &[("beta", "1"), ("uid", "alice")]);
+        let result: (bool, bool, bool, bool, bool) = lua
+            .load(r#"return flags.enabled("on"), flags.enabled("beta"), flags.enabled("staff", { user = "alice" }),
+                flags.enabled("staff", { user = "bob" }), flags.enabled("missing")"#)
+            .eval()
+            .unwrap();
+        assert_eq!(result, (true, true, true, false, false));
+
+        // The user cookie is client-controlled and never matches `users`
+        let spoofed: (bool, String) = lua.load(r#"return flags.enabled("staff"), flags.user()"#).eval().unwrap();
+        assert_eq!(spoofed, (false, "alice".to_string()));
+        let identified: bool = lua.load(r#"flags.identify("alice"); return flags.enabled("staff")"#).eval().unwrap();
+        assert!(identified);
+
+        // Rollout is stable per user and off without one
+        let first: bool = lua.load(r#"return flags.enabled("half", { user = "u1" })"#).eval().unwrap();
+        let again: bool = lua.load(r#"flags.identify("u1"); return flags.enabled("half")"#).eval().unwrap();
+        assert_eq!(first, again);
+        assert_eq!(first, f64::from(bucket("half", "u1")) < 5000.0);
+        lua.unset_named_registry_value(REQUEST_RUNTIME_REGISTRY_KEY).unwrap();
+        let anonymous: bool = lua.load(r#"return flags.enabled("half")"#).eval().unwrap();
+        assert!(!anonymous);
+    }
+
+    #[test]
+    fn test_variant_assignment() {
+        let lua = Lua::new();
+        let mut flags = FeatureFlags { user_cookie: Some("sid".to_string()), ..Default::default() };
+        flags.insert("paused", FeatureFlag::default());
+        register_flags_module(&lua, flags, None).unwrap();
+
+        // Anonymous visitors get the first variant
+        runtime_with_cookies(&lua, &[]);
+        let anonymous: String = lua.load(r#"return variant("layout", { "a", "b", "c" })"#).eval().unwrap();
+        assert_eq!(anonymous, "a");
+
+        runtime_with_cookies(&lua, &[("sid", "s-42")]);
+        let chosen: String = lua.load(r#"return variant("layout", { "a", "b", "c" })"#).eval().unwrap();
+        let expected = ["a", "b", "c"][bucket("layout", "s-42") as usize * 3 / 10_000];
+        assert_eq!(chosen, expected);
+        let paused: String = lua.load(r#"return variant("paused", { "a", "b" })"#).eval().unwrap();
+        assert_eq!(paused, "a");
+
+        let runtime: Table = lua.named_registry_value(REQUEST_RUNTIME_REGISTRY_KEY).unwrap();
+        assert_eq!(variants_header(&runtime).unwrap(), Some(format!("layout={}", expected)));
+        let recorded: String = lua.load("return flags.variants().layout").eval().unwrap();
+        assert_eq!(recorded, expected);
+
+        assert!(lua.load(r#"return variant("empty", {})"#).exec().is_err());
+    }
+
+    #[test]
+    fn test_kv_overrides() {
+        let lua = Lua::new();
+        let store = Arc::new(MemoryKVStore::new());
+        store.put("off-by-default", b"on", PutOptions::default()).unwrap();
+        store.put("on-by-default", b"off", PutOptions::default()).unwrap();
+        store.put("json", br#"{"users": ["carol"]}"#, PutOptions::default()).unwrap();
+
+        let mut flags = FeatureFlags::default();
+        flags.insert("off-by-default", FeatureFlag::default());
+        flags.insert("on-by-default", FeatureFlag { enabled: true, ..Default::default() });
+        register_flags_module(&lua, flags, Some(store)).unwrap();
+
+        let result: (bool, bool, bool, bool) = lua
+            .load(r#"return flags.enabled("off-by-default"), flags.enabled("on-by-default"),
+                flags.enabled("json", { user = "carol" }), flags.enabled("json", { user = "dave" })"#)
+            .eval()
+            .unwrap();
+        assert_eq!(result, (true, false, true, false));
+    }
+}
@@ -0,0 +1,756 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Locale-aware number, currency and date formatting.
+//!
+//! Registers a global `fmt` table:
+//!
+//! ```lua
+//! fmt.number(1234.5)                        -- "1,234.5"
+//! fmt.number(0.256, { style = "percent" })  -- "26%"
+//! fmt.currency(1234.5, "EUR")               -- "€1,234.50"
+//! fmt.date(ts, "long")                      -- "January 15, 2024"
+//! fmt.date(ts, "{yyyy}-{MM}-{dd}")          -- custom pattern
+//! fmt.locale()                              -- "en"
+//! ```
+//!
+//! Two shorthand globals cover the other things every app reimplements:
+//!
+//! ```lua
+//! plural(3, "item", "items")                -- "3 items"
+//! plural(1, "{n} item left", "{n} items left") -- "1 item left"
+//! time_ago(ts)                              -- "5 minutes ago", "in 2 days"
+//! ```
+//!
+//! The locale comes from `opts.locale`, then the request locale (negotiated
+//! from `Accept-Language`, see [`negotiate_locale`]), then `en`. Locale data
+//! is built in for the locales in [`SUPPORTED_LOCALES`]; other tags fall back
+//! to their language, then to `en`.
+//!
+//! Dates take a Unix timestamp in seconds (default: `os.time()`) and are
+//! formatted in UTC unless `opts.utc_offset` (minutes) is given.
+
+use mlua::{Lua, Result as LuaResult, Table, Value};
+
+/// Registry key of the per-request runtime table, which carries `locale`.
+const REQUEST_RUNTIME_REGISTRY_KEY: &str = "__luat_request_runtime";
+
+/// Locale used when neither the call nor the request specifies one.
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Locales with built-in formatting data.
+pub const SUPPORTED_LOCALES: &[&str] = &["en", "en-GB", "de", "de-CH", "fr", "es", "it", "nl", "pt"];
+
+/// Where the currency symbol goes relative to the amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CurrencyPosition {
+    /// `€1,234.50`
+    Prefix,
+    /// `€ 1.234,50`
+    PrefixSpace,
+    /// `1.234,50 €`
+    SuffixSpace,
+}
+
+struct LocaleData {
+    tag: &'static str,
+    decimal: &'static str,
+    group: &'static str,
+    currency: CurrencyPosition,
+    percent: &'static str,
+    months: [&'static str; 12],
+    months_short: [&'static str; 12],
+    /// Sunday first.
+    weekdays: [&'static str; 7],
+    /// `short`, `medium`, `long` and `full` date patterns.
+    dates: [&'static str; 4],
+}
+
+const NBSP: &str = "\u{a0}";
+
+const EN_MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November",
+    "December",
+];
+const EN_MONTHS_SHORT: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+const EN_WEEKDAYS: [&str; 7] = ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+
+const DE_MONTHS: [&str; 12] = [
+    "Januar", "Februar", "März", "April", "Mai", "Juni", "Juli", "August", "September", "Oktober", "November",
+    "Dezember",
+];
+const DE_MONTHS_SHORT: [&str; 12] = [
+    "Jan.", "Feb.", "März", "Apr.", "Mai", "Juni", "Juli", "Aug.", "Sept.", "Okt.", "Nov.", "Dez.",
+];
+const DE_WEEKDAYS: [&str; 7] = ["Sonntag", "Montag", "Dienstag", "Mittwoch", "Donnerstag", "Freitag", "Samstag"];
+const DE_DATES: [&str; 4] = [
+    "{dd}.{MM}.{yy}",
+    "{dd}.{MM}.{yyyy}",
+    "{d}. {MMMM} {yyyy}",
+    "{EEEE}, {d}. {MMMM} {yyyy}",
+];
+
+const LOCALES: &[LocaleData] = &[
+    LocaleData {
+        tag: "en",
+        decimal: ".",
+        group: ",",
+        currency: CurrencyPosition::Prefix,
+        percent: "%",
+        months: EN_MONTHS,
+        months_short: EN_MONTHS_SHORT,
+        weekdays: EN_WEEKDAYS,
+        dates: ["{M}/{d}/{yy}", "{MMM} {d}, {yyyy}", "{MMMM} {d}, {yyyy}", "{EEEE}, {MMMM} {d}, {yyyy}"],
+    },
+    LocaleData {
+        tag: "en-GB",
+        decimal: ".",
+        group: ",",
+        currency: CurrencyPosition::Prefix,
+        percent: "%",
+        months: EN_MONTHS,
+        months_short: EN_MONTHS_SHORT,
+        weekdays: EN_WEEKDAYS,
+        dates: ["{dd}/{MM}/{yyyy}", "{d} {MMM} {yyyy}", "{d} {MMMM} {yyyy}", "{EEEE} {d} {MMMM} {yyyy}"],
+    },
+    LocaleData {
+        tag: "de",
+        decimal: ",",
+        group: ".",
+        currency: CurrencyPosition::SuffixSpace,
+        percent: "\u{a0}%",
+        months: DE_MONTHS,
+        months_short: DE_MONTHS_SHORT,
+        weekdays: DE_WEEKDAYS,
+        dates: DE_DATES,
+    },
+    LocaleData {
+        tag: "de-CH",
+        decimal: ".",
+        group: "’",
+        currency: CurrencyPosition::PrefixSpace,
+        percent: "%",
+        months: DE_MONTHS,
+        months_short: DE_MONTHS_SHORT,
+        weekdays: DE_WEEKDAYS,
+        dates: DE_DATES,
+    },
+    LocaleData {
+        tag: "fr",
+        decimal: ",",
+        group: "\u{202f}",
+        currency: CurrencyPosition::SuffixSpace,
+        percent: "\u{202f}%",
+        months: [
+            "janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août", "septembre", "octobre",
+            "novembre", "décembre",
+        ],
+        months_short: [
+            "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.", "nov.", "déc.",
+        ],
+        weekdays: ["dimanche", "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi"],
+        dates: ["{dd}/{MM}/{yyyy}", "{d} {MMM} {yyyy}", "{d} {MMMM} {yyyy}", "{EEEE} {d} {MMMM} {yyyy}"],
+    },
+    LocaleData {
+        tag: "es",
+        decimal: ",",
+        group: ".",
+        currency: CurrencyPosition::SuffixSpace,
+        percent: "\u{a0}%",
+        months: [
+            "enero", "febrero", "marzo", "abril", "mayo", "junio", "julio", "agosto", "septiembre", "octubre",
+            "noviembre", "diciembre",
+        ],
+        months_short: ["ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sept", "oct", "nov", "dic"],
+        weekdays: ["domingo", "lunes", "martes", "miércoles", "jueves", "viernes", "sábado"],
+        dates: [
+            "{d}/{M}/{yy}",
+            "{d} {MMM} {yyyy}",
+            "{d} de {MMMM} de {yyyy}",
+            "{EEEE}, {d} de {MMMM} de {yyyy}",
+        ],
+    },
+    LocaleData {
+        tag: "it",
+        decimal: ",",
+        group: ".",
+        currency: CurrencyPosition::SuffixSpace,
+        percent: "%",
+        months: [
+            "gennaio", "febbraio", "marzo", "aprile", "maggio", "giugno", "luglio", "agosto", "settembre", "ottobre",
+            "novembre", "dicembre",
+        ],
+        months_short: ["gen", "feb", "mar", "apr", "mag", "giu", "lug", "ago", "set", "ott", "nov", "dic"],
+        weekdays: ["domenica", "lunedì", "martedì", "mercoledì", "giovedì", "venerdì", "sabato"],
+        dates: ["{dd}/{MM}/{yy}", "{d} {MMM} {yyyy}", "{d} {MMMM} {yyyy}", "{EEEE} {d} {MMMM} {yyyy}"],
+    },
+    LocaleData {
+        tag: "nl",
+        decimal: ",",
+        group: ".",
+        currency: CurrencyPosition::PrefixSpace,
+        percent: "%",
+        months: [
+            "januari", "februari", "maart", "april", "mei", "juni", "juli", "augustus", "september", "oktober",
+            "november", "december",
+        ],
+        months_short: ["jan", "feb", "mrt", "apr", "mei", "jun", "jul", "aug", "sep", "okt", "nov", "dec"],
+        weekdays: ["zondag", "maandag", "dinsdag", "woensdag", "donderdag", "vrijdag", "zaterdag"],
+        dates: ["{dd}-{MM}-{yyyy}", "{d} {MMM} {yyyy}", "{d} {MMMM} {yyyy}", "{EEEE} {d} {MMMM} {yyyy}"],
+    },
+    LocaleData {
+        tag: "pt",
+        decimal: ",",
+        group: ".",
+        currency: CurrencyPosition::PrefixSpace,
+        percent: "%",
+        months: [
+            "janeiro", "fevereiro", "março", "abril", "maio", "junho", "julho", "agosto", "setembro", "outubro",
+            "novembro", "dezembro",
+        ],
+        months_short: [
+            "jan.", "fev.", "mar.", "abr.", "mai.", "jun.", "jul.", "ago.", "set.", "out.", "nov.", "dez.",
+        ],
+        weekdays: ["domingo", "segunda-feira", "terça-feira", "quarta-feira", "quinta-feira", "sexta-feira", "sábado"],
+        dates: [
+            "{dd}/{MM}/{yyyy}",
+            "{d} de {MMM} de {yyyy}",
+            "{d} de {MMMM} de {yyyy}",
+            "{EEEE}, {d} de {MMMM} de {yyyy}",
+        ],
+    },
+];
+
+/// Finds built-in data for a locale tag, falling back to its language, then `en`.
+fn locale_data(tag: &str) -> &'static LocaleData {
+    let tag = tag.replace('_', "-");
+    LOCALES
+        .iter()
+        .find(|l| l.tag.eq_ignore_ascii_case(&tag))
+        .or_else(|| {
+            let language = tag.split('-').next().unwrap_or_default();
+            LOCALES.iter().find(|l| l.tag.eq_ignore_ascii_case(language))
+        })
+        .unwrap_or(&LOCALES[0])
+}
+
+/// Picks the best supported locale for an `Accept-Language` header value.
+///
+/// Ranges are tried in order of their `q` weight; a region-specific range
+/// (`de-AT`) matches its language (`de`) when the region has no data.
+pub fn negotiate_locale(accept_language: Option<&str>) -> &'static str {
+    let Some(header) = accept_language else {
+        return DEFAULT_LOCALE;
+    };
+
+    language_ranges(header)
+        .iter()
+        .find_map(|range| {
+            let language = range.split(['-', '_']).next().unwrap_or_default();
+            let data = locale_data(range);
+            data.tag
+                .split('-')
+                .next()
+                .filter(|l| l.eq_ignore_ascii_case(language))
+                .map(|_| data.tag)
+        })
+        .unwrap_or(DEFAULT_LOCALE)
+}
+
+/// Returns the language ranges of an `Accept-Language` header value, most
+/// preferred first; `*` and ranges with `q=0` are left out.
+pub(crate) fn language_ranges(header: &str) -> Vec<&str> {
+    let mut ranges: Vec<(&str, f32)> = header
+        .split(',')
+        .filter_map(|part| {
+            let mut pieces = part.split(';');
+            let range = pieces.next()?.trim();
+            let q = pieces
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .and_then(|q| q.parse().ok())
+                .unwrap_or(1.0);
+            (!range.is_empty() && range != "*" && q > 0.0).then_some((range, q))
+        })
+        .collect();
+    ranges.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    ranges.into_iter().map(|(range, _)| range).collect()
+}
+
+/// Options shared by `fmt.number` and `fmt.currency`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct NumberOptions {
+    min_fraction: usize,
+    max_fraction: usize,
+    grouping: bool,
+}
+
+/// Formats `n` with the locale's separators.
+fn format_decimal(n: f64, data: &LocaleData, options: NumberOptions) -> String {
+    if !n.is_finite() {
+        return n.to_string();
+    }
+
+    // Round half away from zero, as ICU does, before formatting
+    let factor = 10f64.powi(options.max_fraction as i32);
+    let fixed = format!("{:.*}", options.max_fraction, (n.abs() * factor).round() / factor);
+    let (integer, fraction) = fixed.split_once('.').unwrap_or((&fixed, ""));
+    let mut fraction = fraction.trim_end_matches('0').to_string();
+    while fraction.len() < options.min_fraction {
+        fraction.push('0');
+    }
+
+    let mut out = String::new();
+    if n < 0.0 && (integer.bytes().any(|b| b != b'0') || !fraction.bytes().all(|b| b == b'0')) {
+        out.push('-');
+    }
+    for (i, digit) in integer.chars().enumerate() {
+        if options.grouping && i > 0 && (integer.len() - i) % 3 == 0 {
+            out.push_str(data.group);
+        }
+        out.push(digit);
+    }
+    if !fraction.is_empty() {
+        out.push_str(data.decimal);
+        out.push_str(&fraction);
+    }
+    out
+}
+
+/// Currency symbol and default fraction digits for an ISO 4217 code.
+fn currency_info(code: &str) -> (String, usize) {
+    let code = code.to_ascii_uppercase();
+    match code.as_str() {
+        "EUR" => ("€".to_string(), 2),
+        "USD" => ("$".to_string(), 2),
+        "GBP" => ("£".to_string(), 2),
+        "JPY" => ("¥".to_string(), 0),
+        "BRL" => ("R$".to_string(), 2),
+        _ => (code, 2),
+    }
+}
+
+fn format_currency(n: f64, code: &str, data: &LocaleData, decimals: Option<usize>) -> String {
+    let (symbol, default_digits) = currency_info(code);
+    let digits = decimals.unwrap_or(default_digits);
+    let amount = format_decimal(
+        n.abs(),
+        data,
+        NumberOptions {
+            min_fraction: digits,
+            max_fraction: digits,
+            grouping: true,
+        },
+    );
+    let sign = if n < 0.0 && amount.bytes().any(|b| b.is_ascii_digit() && b != b'0') {
+        "-"
+    } else {
+        ""
+    };
+
+    match data.currency {
+        CurrencyPosition::Prefix => format!("{}{}{}", sign, symbol, amount),
+        CurrencyPosition::PrefixSpace => format!("{}{}{}{}", sign, symbol, NBSP, amount),
+        CurrencyPosition::SuffixSpace => format!("{}{}{}{}", sign, amount, NBSP, symbol),
+    }
+}
+
+/// A calendar date and weekday in UTC (or a fixed offset).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CivilDate {
+    pub(crate) year: i64,
+    pub(crate) month: u32,
+    pub(crate) day: u32,
+    /// 0 = Sunday.
+    pub(crate) weekday: usize,
+}
+
+/// Converts a Unix timestamp (seconds) to a calendar date.
+pub(crate) fn civil_date(timestamp: i64) -> CivilDate {
+    // Howard Hinnant's days-to-civil algorithm
+    let days = timestamp.div_euclid(86_400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    CivilDate {
+        year,
+        month,
+        day,
+        weekday: (days + 4).rem_euclid(7) as usize,
+    }
+}
+
+/// Expands `{d}`, `{dd}`, `{M}`, `{MM}`, `{MMM}`, `{MMMM}`, `{yy}`, `{yyyy}`
+/// and `{EEEE}` in a date pattern.
+fn format_date(date: CivilDate, pattern: &str, data: &LocaleData) -> String {
+    let month = date.month as usize - 1;
+    let mut out = String::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let token = &rest[start + 1..start + end];
+        match token {
+            "d" => out.push_str(&date.day.to_string()),
+            "dd" => out.push_str(&format!("{:02}", date.day)),
+            "M" => out.push_str(&date.month.to_string()),
+            "MM" => out.push_str(&format!("{:02}", date.month)),
+            "MMM" => out.push_str(data.months_short[month]),
+            "MMMM" => out.push_str(data.months[month]),
+            "yy" => out.push_str(&format!("{:02}", date.year.rem_euclid(100))),
+            "yyyy" => out.push_str(&date.year.to_string()),
+            "EEEE" => out.push_str(data.weekdays[date.weekday]),
+            _ => out.push_str(&rest[start..=start + end]),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Picks the singular or plural form for `n` and inserts the count.
+///
+/// The count replaces `{n}` in the chosen form, or is prepended when the
+/// form has no placeholder. `plural` defaults to `singular` + `s`.
+fn pluralize(n: f64, count: &str, singular: &str, plural: Option<&str>) -> String {
+    let form = if n == 1.0 {
+        singular.to_string()
+    } else {
+        plural.map(str::to_string).unwrap_or_else(|| format!("{}s", singular))
+    };
+    if form.contains("{n}") {
+        form.replace("{n}", count)
+    } else {
+        format!("{} {}", count, form)
+    }
+}
+
+/// Describes the distance between `ts` and `now` in words.
+fn relative_time(ts: i64, now: i64) -> String {
+    const UNITS: &[(i64, &str)] = &[
+        (365 * 86_400, "year"),
+        (30 * 86_400, "month"),
+        (7 * 86_400, "week"),
+        (86_400, "day"),
+        (3_600, "hour"),
+        (60, "minute"),
+    ];
+
+    let delta = now - ts;
+    let seconds = delta.abs();
+    if seconds < 45 {
+        return "just now".to_string();
+    }
+
+    let (size, unit) = UNITS
+        .iter()
+        .find(|(size, _)| seconds >= *size)
+        .copied()
+        .unwrap_or((60, "minute"));
+    let count = ((seconds as f64) / (size as f64)).round().max(1.0) as i64;
+    let amount = if count == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", count, unit)
+    };
+
+    if delta >= 0 {
+        format!("{} ago", amount)
+    } else {
+        format!("in {}", amount)
+    }
+}
+
+/// Current time through Lua, so deterministic mode's frozen clock applies.
+pub(crate) fn lua_now(lua: &Lua) -> LuaResult<i64> {
+    lua.globals()
+        .get::<Table>("os")?
+        .get::<mlua::Function>("time")?
+        .call::<i64>(())
+}
+
+/// Resolves the locale for a call: `opts.locale`, then the request locale.
+fn current_locale(lua: &Lua, opts: Option<&Table>) -> LuaResult<String> {
+    if let Some(locale) = opts.map(|o| o.get::<Option<String>>("locale")).transpose()?.flatten() {
+        return Ok(locale);
+    }
+    let request_locale = match lua.named_registry_value::<Table>(REQUEST_RUNTIME_REGISTRY_KEY) {
+        Ok(runtime) => runtime.get::<Option<String>>("locale")?,
+        Err(_) => None,
+    };
+    Ok(request_locale.unwrap_or_else(|| DEFAULT_LOCALE.to_string()))
+}
+
+fn number_options(opts: Option<&Table>, min: usize, max: usize) -> LuaResult<NumberOptions> {
+    let get = |key: &str| -> LuaResult<Option<usize>> {
+        match opts {
+            Some(opts) => opts.get(key),
+            None => Ok(None),
+        }
+    };
+    let decimals = get("decimals")?;
+    let min_fraction = decimals.or(get("minimum_fraction_digits")?).unwrap_or(min);
+    let max_fraction = decimals
+        .or(get("maximum_fraction_digits")?)
+        .unwrap_or(max)
+        .max(min_fraction);
+    let grouping = match opts {
+        Some(opts) => opts.get::<Option<bool>>("grouping")?.unwrap_or(true),
+        None => true,
+    };
+    Ok(NumberOptions {
+        min_fraction,
+        max_fraction,
+        grouping,
+    })
+}
+
+/// Registers the global `fmt` table.
+pub fn register_fmt_module(lua: &Lua) -> LuaResult<()> {
+    let fmt = lua.create_table()?;
+
+    fmt.set(
+        "number",
+        lua.create_function(|lua, (n, opts): (f64, Option<Table>)| {
+            let data = locale_data(&current_locale(lua, opts.as_ref())?);
+            let percent = match &opts {
+                Some(opts) => opts.get::<Option<String>>("style")?.as_deref() == Some("percent"),
+                None => false,
+            };
+            if percent {
+                let options = number_options(opts.as_ref(), 0, 0)?;
+                Ok(format!("{}{}", format_decimal(n * 100.0, data, options), data.percent))
+            } else {
+                let options = number_options(opts.as_ref(), 0, 3)?;
+                Ok(format_decimal(n, data, options))
+            }
+        })?,
+    )?;
+
+    fmt.set(
+        "currency",
+        lua.create_function(|lua, (n, code, opts): (f64, String, Option<Table>)| {
+            let data = locale_data(&current_locale(lua, opts.as_ref())?);
+            let decimals = match &opts {
+                Some(opts) => opts.get::<Option<usize>>("decimals")?,
+                None => None,
+            };
+            Ok(format_currency(n, &code, data, decimals))
+        })?,
+    )?;
+
+    fmt.set(
+        "date",
+        lua.create_function(|lua, (ts, style, opts): (Option<i64>, Option<String>, Option<Table>)| {
+            let data = locale_data(&current_locale(lua, opts.as_ref())?);
+            let ts = match ts {
+                Some(ts) => ts,
+                None => lua_now(lua)?,
+            };
+            let offset = match &opts {
+                Some(opts) => opts.get::<Option<i64>>("utc_offset")?.unwrap_or(0),
+                None => 0,
+            };
+
+            let style = style.unwrap_or_else(|| "medium".to_string());
+            let pattern = match style.as_str() {
+                "short" => data.dates[0],
+                "medium" => data.dates[1],
+                "long" => data.dates[2],
+                "full" => data.dates[3],
+                "iso" => "{yyyy}-{MM}-{dd}",
+                custom => custom,
+            };
+            Ok(format_date(civil_date(ts + offset * 60), pattern, data))
+        })?,
+    )?;
+
+    fmt.set(
+        "locale",
+        lua.create_function(|lua, ()| Ok(locale_data(&current_locale(lua, None)?).tag))?,
+    )?;
+
+    let globals = lua.globals();
+    globals.set("fmt", Value::Table(fmt))?;
+
+    globals.set(
+        "plural",
+        lua.create_function(|lua, (n, singular, plural): (f64, String, Option<String>)| {
+            let data = locale_data(&current_locale(lua, None)?);
+            let count = format_decimal(
+                n,
+                data,
+                NumberOptions {
+                    min_fraction: 0,
+                    max_fraction: 3,
+                    grouping: true,
+                },
+            );
+            Ok(pluralize(n, &count, &singular, plural.as_deref()))
+        })?,
+    )?;
+
+    globals.set(
+        "time_ago",
+        lua.create_function(|lua, (ts, now): (i64, Option<i64>)| {
+            let now = match now {
+                Some(now) => now,
+                None => lua_now(lua)?,
+            };
+            Ok(relative_time(ts, now))
+        })?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(lua: &Lua, code: &str) -> String {
+        lua.load(code).eval::<String>().unwrap()
+    }
+
+    #[test]
+    fn test_number_formatting() {
+        let lua = Lua::new();
+        register_fmt_module(&lua).unwrap();
+
+        assert_eq!(eval(&lua, "return fmt.number(1234567.891)"), "1,234,567.891");
+        assert_eq!(eval(&lua, "return fmt.number(1234.5, { locale = 'de' })"), "1.234,5");
+        assert_eq!(eval(&lua, "return fmt.number(1234.5, { locale = 'de-CH' })"), "1’234.5");
+        assert_eq!(eval(&lua, "return fmt.number(-0.0001)"), "0");
+        assert_eq!(eval(&lua, "return fmt.number(2, { decimals = 2 })"), "2.00");
+        assert_eq!(eval(&lua, "return fmt.number(12345, { grouping = false })"), "12345");
+        assert_eq!(eval(&lua, "return fmt.number(0.256, { style = 'percent' })"), "26%");
+        assert_eq!(
+            eval(&lua, "return fmt.number(0.256, { style = 'percent', locale = 'de' })"),
+            "26\u{a0}%"
+        );
+    }
+
+    #[test]
+    fn test_currency_formatting() {
+        let lua = Lua::new();
+        register_fmt_module(&lua).unwrap();
+
+        assert_eq!(eval(&lua, "return fmt.currency(1234.5, 'EUR')"), "€1,234.50");
+        assert_eq!(
+            eval(&lua, "return fmt.currency(-1234.5, 'EUR', { locale = 'de' })"),
+            "-1.234,50\u{a0}€"
+        );
+        assert_eq!(
+            eval(&lua, "return fmt.currency(1234.5, 'CHF', { locale = 'de-CH' })"),
+            "CHF\u{a0}1’234.50"
+        );
+        assert_eq!(eval(&lua, "return fmt.currency(1234.5, 'JPY')"), "¥1,235");
+    }
+
+    #[test]
+    fn test_date_formatting() {
+        let lua = Lua::new();
+        register_fmt_module(&lua).unwrap();
+
+        // 2024-01-15 (a Monday) 12:00 UTC
+        let ts = 1_705_320_000;
+        let date = |style: &str, locale: &str| {
+            eval(&lua, &format!("return fmt.date({}, '{}', {{ locale = '{}' }})", ts, style, locale))
+        };
+        assert_eq!(date("short", "en"), "1/15/24");
+        assert_eq!(date("medium", "en"), "Jan 15, 2024");
+        assert_eq!(date("long", "en"), "January 15, 2024");
+        assert_eq!(date("full", "en"), "Monday, January 15, 2024");
+        assert_eq!(date("long", "de"), "15. Januar 2024");
+        assert_eq!(date("full", "fr"), "lundi 15 janvier 2024");
+        assert_eq!(date("long", "es"), "15 de enero de 2024");
+        assert_eq!(date("iso", "en"), "2024-01-15");
+        assert_eq!(date("{dd}.{MM}.", "de"), "15.01.");
+
+        // Crossing midnight with an offset
+        assert_eq!(
+            eval(&lua, &format!("return fmt.date({}, 'iso', {{ utc_offset = 720 }})", ts)),
+            "2024-01-16"
+        );
+    }
+
+    #[test]
+    fn test_plural() {
+        let lua = Lua::new();
+        register_fmt_module(&lua).unwrap();
+
+        assert_eq!(eval(&lua, "return plural(1, 'item', 'items')"), "1 item");
+        assert_eq!(eval(&lua, "return plural(0, 'item', 'items')"), "0 items");
+        assert_eq!(eval(&lua, "return plural(1200, 'visit')"), "1,200 visits");
+        assert_eq!(eval(&lua, "return plural(2, 'person', 'people')"), "2 people");
+        assert_eq!(
+            eval(&lua, "return plural(1, '{n} item left', '{n} items left')"),
+            "1 item left"
+        );
+    }
+
+    #[test]
+    fn test_time_ago() {
+        let now = 1_705_320_000;
+        assert_eq!(relative_time(now - 10, now), "just now");
+        assert_eq!(relative_time(now - 60, now), "1 minute ago");
+        assert_eq!(relative_time(now - 5 * 60, now), "5 minutes ago");
+        assert_eq!(relative_time(now - 3 * 3_600, now), "3 hours ago");
+        assert_eq!(relative_time(now - 86_400, now), "1 day ago");
+        assert_eq!(relative_time(now - 14 * 86_400, now), "2 weeks ago");
+        assert_eq!(relative_time(now - 400 * 86_400, now), "1 year ago");
+        assert_eq!(relative_time(now + 2 * 86_400, now), "in 2 days");
+
+        let lua = Lua::new();
+        register_fmt_module(&lua).unwrap();
+        assert_eq!(
+            eval(&lua, &format!("return time_ago({}, {})", now - 120, now)),
+            "2 minutes ago"
+        );
+    }
+
+    #[test]
+    fn test_civil_date() {
+        assert_eq!(
+            civil_date(0),
+            CivilDate { year: 1970, month: 1, day: 1, weekday: 4 }
+        );
+        assert_eq!(
+            civil_date(951_782_400),
+            CivilDate { year: 2000, month: 2, day: 29, weekday: 2 }
+        );
+        assert_eq!(
+            civil_date(-86_400),
+            CivilDate { year: 1969, month: 12, day: 31, weekday: 3 }
+        );
+    }
+
+    #[test]
+    fn test_negotiate_locale() {
+        assert_eq!(negotiate_locale(None), "en");
+        assert_eq!(negotiate_locale(Some("de-DE,de;q=0.9,en;q=0.8")), "de");
+        assert_eq!(negotiate_locale(Some("de-CH")), "de-CH");
+        assert_eq!(negotiate_locale(Some("ja, fr;q=0.5")), "fr");
+        assert_eq!(negotiate_locale(Some("en;q=0.1, nl;q=0.9")), "nl");
+        assert_eq!(negotiate_locale(Some("ja")), "en");
+    }
+
+    #[test]
+    fn test_request_locale() {
+        let lua = Lua::new();
+        register_fmt_module(&lua).unwrap();
+        let runtime = lua.create_table().unwrap();
+        runtime.set("locale", "fr").unwrap();
+        lua.set_named_registry_value(REQUEST_RUNTIME_REGISTRY_KEY, runtime).unwrap();
+
+        assert_eq!(eval(&lua, "return fmt.locale()"), "fr");
+        assert_eq!(eval(&lua, "return fmt.number(1234.5)"), "1\u{202f}234,5");
+    }
+}
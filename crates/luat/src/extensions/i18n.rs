@@ -0,0 +1,538 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Translations from locale catalogs (`i18n`).
+//!
+//! A catalog maps message keys to strings for one locale. Nested objects
+//! are addressed with dotted keys, and an object keyed by plural categories
+//! (`zero`, `one`, `two`, `few`, `many`, `other`) is a plural message:
+//!
+//! ```json
+//! {
+//!     "nav": { "home": "Home" },
+//!     "greeting": "Hello, {name}!",
+//!     "cart": { "items": { "zero": "Your cart is empty", "one": "{count} item", "other": "{count} items" } }
+//! }
+//! ```
+//!
+//! Templates and server code translate with the global `t`:
+//!
+//! ```html
+//! <a href="/">{t("nav.home")}</a>
+//! <p>{t("greeting", { name = user.name })}</p>
+//! <p>{t("cart.items", { count = #cart })}</p>
+//! ```
+//!
+//! `{name}` placeholders are replaced with the matching variable. `count`
+//! picks the plural form with the rules of the locale (see
+//! [`plural_category`]); `zero` is used for a count of 0 when the message has
+//! it. A key missing from the locale's catalog is looked up in its language
+//! (`de` for `de-CH`), then in the default locale; a key found nowhere is
+//! returned as is.
+//!
+//! The locale is the request locale, negotiated from `Accept-Language`
+//! against the catalogs (see [`Catalogs::negotiate`]), which `fmt` formats
+//! with too. Server code can change it for the rest of the request:
+//!
+//! ```lua
+//! i18n.set_locale(ctx.params.lang)
+//! i18n.locale()                   -- "de"
+//! i18n.locales()                  -- { "de", "en" }
+//! i18n.has("nav.home")            -- true
+//! i18n.t("nav.home", nil, "en")   -- translate for another locale
+//! ```
+//!
+//! Also available as `require("i18n")`.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use mlua::{Lua, Result as LuaResult, Table, Value};
+use serde_json::Value as JsonValue;
+
+/// Registry key of the per-request runtime table, which carries `locale`.
+const REQUEST_RUNTIME_REGISTRY_KEY: &str = "__luat_request_runtime";
+
+/// Plural categories, as used for the keys of plural messages.
+const PLURAL_CATEGORIES: [&str; 6] = ["zero", "one", "two", "few", "many", "other"];
+
+/// A translated message.
+#[derive(Debug, Clone, PartialEq)]
+enum Message {
+    Text(String),
+    /// Forms keyed by plural category; always has `other`.
+    Plural(BTreeMap<String, String>),
+}
+
+/// Message catalogs, keyed by locale.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Catalogs {
+    default_locale: String,
+    catalogs: BTreeMap<String, BTreeMap<String, Message>>,
+}
+
+impl Default for Catalogs {
+    fn default() -> Self {
+        Self::new(crate::extensions::fmt::DEFAULT_LOCALE)
+    }
+}
+
+impl Catalogs {
+    /// Creates empty catalogs that fall back to `default_locale`.
+    pub fn new(default_locale: impl Into<String>) -> Self {
+        Self {
+            default_locale: default_locale.into(),
+            catalogs: BTreeMap::new(),
+        }
+    }
+
+    /// Adds the messages of a JSON object to the catalog of `locale`,
+    /// replacing messages with the same key.
+    pub fn insert(&mut self, locale: &str, messages: &JsonValue) {
+        let catalog = self.catalogs.entry(locale.to_string()).or_default();
+        flatten(catalog, "", messages);
+    }
+
+    /// Parses a JSON catalog and adds it to `locale`.
+    pub fn insert_json(&mut self, locale: &str, source: &str) -> serde_json::Result<()> {
+        let messages: JsonValue = serde_json::from_str(source)?;
+        self.insert(locale, &messages);
+        Ok(())
+    }
+
+    /// Locale used when a key or a request locale has no catalog.
+    pub fn default_locale(&self) -> &str {
+        &self.default_locale
+    }
+
+    /// Locales that have a catalog, sorted.
+    pub fn locales(&self) -> Vec<&str> {
+        self.catalogs.keys().map(String::as_str).collect()
+    }
+
+    /// Returns `true` if there are no catalogs.
+    pub fn is_empty(&self) -> bool {
+        self.catalogs.is_empty()
+    }
+
+    /// Picks the best locale with a catalog for an `Accept-Language` header
+    /// value, or `None` if no range matches one.
+    ///
+    /// Ranges are tried in order of their `q` weight. A range matches a
+    /// catalog of the same tag, then one of its language (`de` for `de-AT`),
+    /// then one of the same language in another region (`de-CH` for `de`).
+    pub fn negotiate(&self, accept_language: Option<&str>) -> Option<&str> {
+        let ranges = crate::extensions::fmt::language_ranges(accept_language?);
+        ranges.iter().find_map(|range| {
+            let language = language_of(range);
+            self.find_catalog(range)
+                .or_else(|| self.find_catalog(language))
+                .or_else(|| self.catalogs.keys().find(|l| language_of(l).eq_ignore_ascii_case(language)))
+                .map(String::as_str)
+        })
+    }
+
+    /// Returns the message for `key` in `locale`, with the plural form for
+    /// `count`, before interpolation.
+    pub fn message(&self, locale: &str, key: &str, count: Option<f64>) -> Option<&str> {
+        let (catalog_locale, message) = self
+            .fallback_chain(locale)
+            .into_iter()
+            .find_map(|(catalog_locale, catalog)| Some((catalog_locale, catalog.get(key)?)))?;
+        match message {
+            Message::Text(text) => Some(text),
+            Message::Plural(forms) => {
+                let form = match count {
+                    Some(count) if count == 0.0 && forms.contains_key("zero") => "zero",
+                    Some(count) => plural_category(catalog_locale, count),
+                    None => "other",
+                };
+                forms.get(form).or_else(|| forms.get("other")).map(String::as_str)
+            }
+        }
+    }
+
+    /// Translates `key` in `locale`, replacing `{name}` placeholders with the
+    /// values `vars` returns. Returns the key itself when it isn't found.
+    pub fn translate(
+        &self,
+        locale: &str,
+        key: &str,
+        count: Option<f64>,
+        vars: impl Fn(&str) -> Option<String>,
+    ) -> String {
+        match self.message(locale, key, count) {
+            Some(message) => interpolate(message, vars),
+            None => key.to_string(),
+        }
+    }
+
+    fn find_catalog(&self, tag: &str) -> Option<&String> {
+        self.catalogs.keys().find(|l| l.eq_ignore_ascii_case(tag))
+    }
+
+    /// Catalogs to look a key up in, with their locales: the locale, its
+    /// language, then the default locale and its language.
+    fn fallback_chain(&self, locale: &str) -> Vec<(&str, &BTreeMap<String, Message>)> {
+        let default_locale = self.default_locale.as_str();
+        let mut chain: Vec<(&str, &BTreeMap<String, Message>)> = Vec::new();
+        for tag in [locale, language_of(locale), default_locale, language_of(default_locale)] {
+            if let Some((name, catalog)) = self.catalogs.iter().find(|(l, _)| l.eq_ignore_ascii_case(tag)) {
+                if !chain.iter().any(|(l, _)| *l == name.as_str()) {
+                    chain.push((name.as_str(), catalog));
+                }
+            }
+        }
+        chain
+    }
+}
+
+fn language_of(tag: &str) -> &str {
+    tag.split(['-', '_']).next().unwrap_or_default()
+}
+
+/// Adds the messages of `value` under `prefix` to `catalog`.
+fn flatten(catalog: &mut BTreeMap<String, Message>, prefix: &str, value: &JsonValue) {
+    let key = |name: &str| match prefix {
+        "" => name.to_string(),
+        _ => format!("{}.{}", prefix, name),
+    };
+    match value {
+        JsonValue::Object(map) if is_plural(map) => {
+            let forms = map
+                .iter()
+                .filter_map(|(category, form)| Some((category.clone(), scalar(form)?)))
+                .collect();
+            catalog.insert(prefix.to_string(), Message::Plural(forms));
+        }
+        JsonValue::Object(map) => {
+            for (name, value) in map {
+                flatten(catalog, &key(name), value);
+            }
+        }
+        value => {
+            if let Some(text) = scalar(value) {
+                catalog.insert(prefix.to_string(), Message::Text(text));
+            }
+        }
+    }
+}
+
+fn is_plural(map: &serde_json::Map<String, JsonValue>) -> bool {
+    map.contains_key("other") && map.keys().all(|key| PLURAL_CATEGORIES.contains(&key.as_str()))
+}
+
+fn scalar(value: &JsonValue) -> Option<String> {
+    match value {
+        JsonValue::String(s) => Some(s.clone()),
+        JsonValue::Number(n) => Some(n.to_string()),
+        JsonValue::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Replaces `{name}` placeholders in `message`; placeholders without a value
+/// are kept.
+fn interpolate(message: &str, vars: impl Fn(&str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(message.len());
+    let mut rest = message;
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let name = after.find('}').map(|end| &after[..end]);
+        match name.filter(|n| !n.is_empty() && n.chars().all(|c| c.is_alphanumeric() || c == '_')) {
+            Some(name) => {
+                match vars(name) {
+                    Some(value) => output.push_str(&value),
+                    None => {
+                        output.push('{');
+                        output.push_str(name);
+                        output.push('}');
+                    }
+                }
+                rest = &after[name.len() + 1..];
+            }
+            None => {
+                output.push('{');
+                rest = after;
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Returns the CLDR plural category of `n` in `locale`: `zero`, `one`,
+/// `two`, `few`, `many` or `other`.
+///
+/// Rules are built in for the common languages; other languages use the
+/// English rule (`one` for 1, `other` otherwise).
+pub fn plural_category(locale: &str, n: f64) -> &'static str {
+    let language = language_of(locale).to_ascii_lowercase();
+    let n = n.abs();
+    let integer = n.fract() == 0.0;
+    let i = n.trunc() as u64;
+    let (i10, i100) = (i % 10, i % 100);
+    match language.as_str() {
+        "ja" | "zh" | "ko" | "vi" | "th" | "id" | "ms" | "lo" | "my" => "other",
+        "fr" | "pt" => {
+            if i <= 1 {
+                "one"
+            } else {
+                "other"
+            }
+        }
+        "ru" | "uk" | "be" => {
+            if !integer {
+                "other"
+            } else if i10 == 1 && i100 != 11 {
+                "one"
+            } else if (2..=4).contains(&i10) && !(12..=14).contains(&i100) {
+                "few"
+            } else {
+                "many"
+            }
+        }
+        "pl" => {
+            if !integer {
+                "other"
+            } else if i == 1 {
+                "one"
+            } else if (2..=4).contains(&i10) && !(12..=14).contains(&i100) {
+                "few"
+            } else {
+                "many"
+            }
+        }
+        "cs" | "sk" => {
+            if !integer {
+                "many"
+            } else if i == 1 {
+                "one"
+            } else if (2..=4).contains(&i) {
+                "few"
+            } else {
+                "other"
+            }
+        }
+        "ar" => {
+            if !integer {
+                "other"
+            } else if i == 0 {
+                "zero"
+            } else if i == 1 {
+                "one"
+            } else if i == 2 {
+                "two"
+            } else if (3..=10).contains(&i100) {
+                "few"
+            } else if (11..=99).contains(&i100) {
+                "many"
+            } else {
+                "other"
+            }
+        }
+        "he" => match (integer, i) {
+            (true, 1) => "one",
+            (true, 2) => "two",
+            _ => "other",
+        },
+        _ => {
+            if integer && i == 1 {
+                "one"
+            } else {
+                "other"
+            }
+        }
+    }
+}
+
+/// Resolves the locale for a call: `locale` if given, then the request
+/// locale, then the default locale.
+fn current_locale(lua: &Lua, catalogs: &Catalogs, locale: Option<String>) -> LuaResult<String> {
+    if let Some(locale) = locale {
+        return Ok(locale);
+    }
+    let request_locale = match lua.named_registry_value::<Option<Table>>(REQUEST_RUNTIME_REGISTRY_KEY)? {
+        Some(runtime) => runtime.get::<Option<String>>("locale")?,
+        None => None,
+    };
+    Ok(request_locale.unwrap_or_else(|| catalogs.default_locale.clone()))
+}
+
+/// Translates a key with the variables of a Lua table.
+fn translate(lua: &Lua, catalogs: &Catalogs, key: &str, vars: Option<&Table>, locale: Option<String>) -> LuaResult<String> {
+    let locale = current_locale(lua, catalogs, locale)?;
+    let count = match vars {
+        Some(vars) => vars.get::<Option<f64>>("count")?,
+        None => None,
+    };
+    Ok(catalogs.translate(&locale, key, count, |name| {
+        let value = vars?.get::<Value>(name).ok()?;
+        match value {
+            Value::Nil => None,
+            Value::Boolean(b) => Some(b.to_string()),
+            value => lua.coerce_string(value).ok().flatten().map(|s| s.to_string_lossy()),
+        }
+    }))
+}
+
+/// Registers the global `i18n` module, the global `t` and `require("i18n")`.
+pub fn register_i18n_module(lua: &Lua, catalogs: Arc<Catalogs>) -> LuaResult<()> {
+    let i18n = lua.create_table()?;
+
+    let t_catalogs = catalogs.clone();
+    let t = lua.create_function(move |lua, (key, vars, locale): (String, Option<Table>, Option<String>)| {
+        translate(lua, &t_catalogs, &key, vars.as_ref(), locale)
+    })?;
+    i18n.set("t", &t)?;
+
+    let locale_catalogs = catalogs.clone();
+    i18n.set(
+        "locale",
+        lua.create_function(move |lua, ()| current_locale(lua, &locale_catalogs, None))?,
+    )?;
+
+    i18n.set(
+        "set_locale",
+        lua.create_function(|lua, locale: String| {
+            if let Some(runtime) = lua.named_registry_value::<Option<Table>>(REQUEST_RUNTIME_REGISTRY_KEY)? {
+                runtime.set("locale", locale)?;
+            }
+            Ok(())
+        })?,
+    )?;
+
+    let locales_catalogs = catalogs.clone();
+    i18n.set(
+        "locales",
+        lua.create_function(move |lua, ()| lua.create_sequence_from(locales_catalogs.locales()))?,
+    )?;
+
+    let has_catalogs = catalogs;
+    i18n.set(
+        "has",
+        lua.create_function(move |lua, (key, locale): (String, Option<String>)| {
+            let locale = current_locale(lua, &has_catalogs, locale)?;
+            Ok(has_catalogs.message(&locale, &key, None).is_some())
+        })?,
+    )?;
+
+    let globals = lua.globals();
+    globals.set("i18n", i18n)?;
+    globals.set("t", t)?;
+
+    let package: Table = globals.get("package")?;
+    let preload: Table = package.get("preload")?;
+    preload.set(
+        "i18n",
+        lua.create_function(|lua, _: ()| lua.globals().get::<Table>("i18n"))?,
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn catalogs() -> Catalogs {
+        let mut catalogs = Catalogs::new("en");
+        catalogs.insert(
+            "en",
+            &json!({
+                "nav": { "home": "Home", "about": "About" },
+                "greeting": "Hello, {name}!",
+                "cart": { "items": { "zero": "Your cart is empty", "one": "{count} item", "other": "{count} items" } },
+            }),
+        );
+        catalogs.insert(
+            "de",
+            &json!({
+                "nav": { "home": "Startseite" },
+                "cart": { "items": { "one": "{count} Artikel", "other": "{count} Artikel" } },
+            }),
+        );
+        catalogs.insert("ru", &json!({ "apples": { "one": "{count} яблоко", "few": "{count} яблока", "many": "{count} яблок", "other": "{count} яблока" } }));
+        catalogs
+    }
+
+    fn vars<'a>(pairs: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| pairs.iter().find(|(k, _)| *k == name).map(|(_, v)| v.to_string())
+    }
+
+    #[test]
+    fn test_translate() {
+        let catalogs = catalogs();
+        assert_eq!(catalogs.translate("en", "nav.home", None, vars(&[])), "Home");
+        assert_eq!(catalogs.translate("de", "nav.home", None, vars(&[])), "Startseite");
+        assert_eq!(catalogs.translate("de-CH", "nav.home", None, vars(&[])), "Startseite");
+        assert_eq!(catalogs.translate("de", "nav.about", None, vars(&[])), "About");
+        assert_eq!(catalogs.translate("en", "greeting", None, vars(&[("name", "Ada")])), "Hello, Ada!");
+        assert_eq!(catalogs.translate("en", "greeting", None, vars(&[])), "Hello, {name}!");
+        assert_eq!(catalogs.translate("en", "missing.key", None, vars(&[])), "missing.key");
+    }
+
+    #[test]
+    fn test_translate_plurals() {
+        let catalogs = catalogs();
+        let count = |n: &'static str| vec![("count", n)];
+        assert_eq!(catalogs.translate("en", "cart.items", Some(0.0), vars(&count("0"))), "Your cart is empty");
+        assert_eq!(catalogs.translate("en", "cart.items", Some(1.0), vars(&count("1"))), "1 item");
+        assert_eq!(catalogs.translate("en", "cart.items", Some(2.0), vars(&count("2"))), "2 items");
+        assert_eq!(catalogs.translate("de", "cart.items", Some(0.0), vars(&count("0"))), "0 Artikel");
+        assert_eq!(catalogs.translate("ru", "apples", Some(21.0), vars(&count("21"))), "21 яблоко");
+        assert_eq!(catalogs.translate("ru", "apples", Some(3.0), vars(&count("3"))), "3 яблока");
+        assert_eq!(catalogs.translate("ru", "apples", Some(11.0), vars(&count("11"))), "11 яблок");
+    }
+
+    #[test]
+    fn test_plural_category() {
+        assert_eq!(plural_category("en", 1.0), "one");
+        assert_eq!(plural_category("en", 1.5), "other");
+        assert_eq!(plural_category("fr", 0.0), "one");
+        assert_eq!(plural_category("ja", 1.0), "other");
+        assert_eq!(plural_category("pl", 22.0), "few");
+        assert_eq!(plural_category("pl", 25.0), "many");
+        assert_eq!(plural_category("cs", 3.0), "few");
+        assert_eq!(plural_category("ar", 0.0), "zero");
+        assert_eq!(plural_category("ar", 105.0), "few");
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let catalogs = catalogs();
+        assert_eq!(catalogs.negotiate(Some("de-AT,de;q=0.9,en;q=0.8")), Some("de"));
+        assert_eq!(catalogs.negotiate(Some("fr,en;q=0.5")), Some("en"));
+        assert_eq!(catalogs.negotiate(Some("fr")), None);
+        assert_eq!(catalogs.negotiate(None), None);
+        assert_eq!(catalogs.locales(), ["de", "en", "ru"]);
+    }
+
+    #[test]
+    fn test_i18n_module() {
+        let lua = Lua::new();
+        register_i18n_module(&lua, Arc::new(catalogs())).unwrap();
+        let runtime = lua.create_table().unwrap();
+        runtime.set("locale", "de").unwrap();
+        lua.set_named_registry_value(REQUEST_RUNTIME_REGISTRY_KEY, runtime).unwrap();
+
+        let result: String = lua
+            .load(
+                r#"
+                local parts = { t("nav.home"), t("cart.items", { count = 2 }), i18n.t("nav.home", nil, "en") }
+                i18n.set_locale("en")
+                table.insert(parts, t("cart.items", { count = 1 }))
+                table.insert(parts, i18n.locale())
+                table.insert(parts, tostring(i18n.has("nav.about")))
+                table.insert(parts, tostring(require("i18n") == i18n))
+                return table.concat(parts, "|")
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!(result, "Startseite|2 Artikel|Home|1 item|en|true|true");
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Responsive image helper (`img`).
+//!
+//! Registers a global `img(src, opts)` function that returns the attributes
+//! of an `<img>` with a `srcset` of resized variants, ready to spread onto
+//! the element:
+//!
+//! ```html
+//! <img {...img("/static/hero.jpg", { alt = "Hero", sizes = "(min-width: 800px) 50vw, 100vw" })} />
+//! ```
+//!
+//! Each variant is served by the image endpoint of `luat dev` and
+//! `luat serve` ([`IMAGE_ENDPOINT`]), which resizes the source on first
+//! request and caches the result. Paths are resolved like `asset()` paths.
+//!
+//! Options: `widths` (default: the configured widths), `format` (`webp`,
+//! `jpeg`, `png`; default: negotiated from the `Accept` header), `quality`
+//! (1-100, JPEG only) and `sizes` (default `100vw`). Every other option is
+//! passed through as an attribute; `loading="lazy"` and `decoding="async"`
+//! are set unless given. External URLs are returned without a `srcset`.
+
+use mlua::{Lua, Result as LuaResult, Table, Value};
+
+use super::assets::asset_url;
+
+/// URL prefix of the image endpoint; the source path follows it.
+pub const IMAGE_ENDPOINT: &str = "/__img";
+
+/// Widths `img()` generates variants for, unless configured otherwise.
+pub const DEFAULT_IMAGE_WIDTHS: [u32; 5] = [320, 640, 960, 1280, 1920];
+
+/// Options that shape the variants rather than becoming attributes.
+const VARIANT_OPTIONS: [&str; 3] = ["widths", "format", "quality"];
+
+/// Returns the endpoint URL of `url` resized to `width`.
+pub fn image_url(url: &str, width: u32, format: Option<&str>, quality: Option<u32>) -> String {
+    let mut out = format!("{}{}?w={}", IMAGE_ENDPOINT, url, width);
+    if let Some(format) = format {
+        out.push_str("&format=");
+        out.push_str(format);
+    }
+    if let Some(quality) = quality {
+        out.push_str(&format!("&q={}", quality));
+    }
+    out
+}
+
+/// Returns `true` for URLs the image endpoint can't serve.
+fn is_external(url: &str) -> bool {
+    url.contains("://") || url.starts_with("//") || url.starts_with("data:")
+}
+
+/// Registers the global `img(src, opts)` helper, with variants in `widths`
+/// by default.
+///
+/// Calling this again replaces the previous widths.
+pub fn register_img_helper(lua: &Lua, widths: Vec<u32>) -> LuaResult<()> {
+    let img = lua.create_function(move |lua, (src, opts): (String, Option<Table>)| {
+        let url = asset_url(&src);
+        let attrs = lua.create_table()?;
+        attrs.set("loading", "lazy")?;
+        attrs.set("decoding", "async")?;
+
+        let (mut widths, format, quality) = match &opts {
+            Some(opts) => (
+                opts.get::<Option<Vec<u32>>>("widths")?.unwrap_or_else(|| widths.clone()),
+                opts.get::<Option<String>>("format")?,
+                opts.get::<Option<u32>>("quality")?,
+            ),
+            None => (widths.clone(), None, None),
+        };
+        if let Some(opts) = &opts {
+            for pair in opts.pairs::<String, Value>() {
+                let (key, value) = pair?;
+                if !VARIANT_OPTIONS.contains(&key.as_str()) {
+                    attrs.set(key, value)?;
+                }
+            }
+        }
+
+        widths.sort_unstable();
+        widths.dedup();
+        match widths.last() {
+            Some(&largest) if !is_external(&url) => {
+                let srcset: Vec<String> = widths
+                    .iter()
+                    .map(|&w| format!("{} {}w", image_url(&url, w, format.as_deref(), quality), w))
+                    .collect();
+                attrs.set("src", image_url(&url, largest, format.as_deref(), quality))?;
+                attrs.set("srcset", srcset.join(", "))?;
+                if !attrs.contains_key("sizes")? {
+                    attrs.set("sizes", "100vw")?;
+                }
+            }
+            _ => attrs.set("src", url)?,
+        }
+        Ok(attrs)
+    })?;
+
+    lua.globals().set("img", img)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_img_helper_attributes() {
+        let lua = Lua::new();
+        register_img_helper(&lua, vec![640, 320]).unwrap();
+
+        let attrs: Table = lua.load(r#"img("/static/hero.jpg", { alt = "Hero" })"#).eval().unwrap();
+        assert_eq!(attrs.get::<String>("src").unwrap(), "/__img/static/hero.jpg?w=640");
+        assert_eq!(
+            attrs.get::<String>("srcset").unwrap(),
+            "/__img/static/hero.jpg?w=320 320w, /__img/static/hero.jpg?w=640 640w"
+        );
+        assert_eq!(attrs.get::<String>("sizes").unwrap(), "100vw");
+        assert_eq!(attrs.get::<String>("alt").unwrap(), "Hero");
+        assert_eq!(attrs.get::<String>("loading").unwrap(), "lazy");
+
+        let attrs: Table = lua
+            .load(r#"img("img/a.png", { widths = { 100 }, format = "webp", quality = 70, loading = "eager" })"#)
+            .eval()
+            .unwrap();
+        assert_eq!(attrs.get::<String>("src").unwrap(), "/__img/public/img/a.png?w=100&format=webp&q=70");
+        assert_eq!(attrs.get::<String>("loading").unwrap(), "eager");
+        assert!(attrs.get::<Option<String>>("format").unwrap().is_none());
+
+        let attrs: Table = lua.load(r#"img("https://cdn.example.com/a.jpg")"#).eval().unwrap();
+        assert_eq!(attrs.get::<String>("src").unwrap(), "https://cdn.example.com/a.jpg");
+        assert!(attrs.get::<Option<String>>("srcset").unwrap().is_none());
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Markdown rendering (`markdown`, `markdown` feature).
+//!
+//! `markdown.render(text)` renders CommonMark with tables, strikethrough,
+//! task lists and footnotes to HTML. Templates use it through
+//! `{@markdown expr}`:
+//!
+//! ```html
+//! <article>{@markdown post.body}</article>
+//! ```
+//!
+//! The output is safe to embed: HTML in the Markdown is escaped rather than
+//! passed through, and link and image URLs with a scheme other than `http`,
+//! `https`, `mailto` or `tel` (e.g. `javascript:`) are dropped. `nil`
+//! renders as an empty string.
+//!
+//! Also available as `require("markdown")`.
+
+use mlua::{Lua, Result as LuaResult, Table};
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+
+/// URL schemes links and images may use.
+const ALLOWED_SCHEMES: [&str; 4] = ["http", "https", "mailto", "tel"];
+
+/// Renders Markdown to sanitized HTML.
+pub fn render_markdown(text: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+    let events = Parser::new_ext(text, options).map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        Event::Start(Tag::Link { link_type, dest_url, title, id }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image { link_type, dest_url, title, id }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        event => event,
+    });
+    let mut output = String::with_capacity(text.len() * 3 / 2);
+    html::push_html(&mut output, events);
+    output
+}
+
+/// Returns `url`, or an empty URL if it has a scheme that isn't allowed.
+fn safe_url(url: CowStr<'_>) -> CowStr<'_> {
+    // Browsers ignore whitespace and control characters inside a scheme
+    let cleaned: String = url.chars().filter(|c| !c.is_whitespace() && !c.is_control()).collect();
+    let scheme_end = cleaned.find([':', '/', '?', '#']);
+    match scheme_end {
+        Some(end) if cleaned[end..].starts_with(':') => {
+            let scheme = cleaned[..end].to_ascii_lowercase();
+            if ALLOWED_SCHEMES.contains(&scheme.as_str()) {
+                url
+            } else {
+                CowStr::Borrowed("")
+            }
+        }
+        _ => url,
+    }
+}
+
+/// Registers the global `markdown` module and `require("markdown")`.
+pub fn register_markdown_module(lua: &Lua) -> LuaResult<()> {
+    let markdown = lua.create_table()?;
+    markdown.set(
+        "render",
+        lua.create_function(|_, text: Option<String>| Ok(render_markdown(text.as_deref().unwrap_or_default())))?,
+    )?;
+
+    let globals = lua.globals();
+    globals.set("markdown", markdown.clone())?;
+
+    // Templates require the module itself, so reassigning the global
+    // doesn't change what `{@markdown}` renders with
+    let package: Table = globals.get("package")?;
+    let preload: Table = package.get("preload")?;
+    preload.set("markdown", lua.create_function(move |_, _: ()| Ok(markdown.clone()))?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_markdown() {
+        assert_eq!(
+            render_markdown("# Title\n\nSome *text* and a [link](https://example.com)."),
+            "<h1>Title</h1>\n<p>Some <em>text</em> and a <a href=\"https://example.com\">link</a>.</p>\n"
+        );
+        let table = render_markdown("| a |\n|---|\n| 1 |");
+        assert!(table.contains("<th>a</th>") && table.contains("<td>1</td>"), "{}", table);
+        assert_eq!(render_markdown(""), "");
+    }
+
+    #[test]
+    fn test_render_markdown_sanitizes() {
+        let block = render_markdown("<script>alert(1)</script>");
+        assert!(!block.contains("<script") && block.contains("&lt;script&gt;"), "{}", block);
+        let inline = render_markdown("Hi <b onclick=x>there</b>");
+        assert!(!inline.contains("<b") && inline.contains("&lt;b onclick=x&gt;"), "{}", inline);
+
+        assert!(render_markdown("[x](javascript:alert(1))").contains("href=\"\""));
+        assert!(render_markdown("[x](JavaScript:alert(1))").contains("href=\"\""));
+        assert!(render_markdown("![i](data:image/svg+xml,x)").contains("src=\"\""));
+        assert!(render_markdown("[x](/a:b)").contains("href=\"/a:b\""));
+        assert!(render_markdown("[x](mailto:a@b.c)").contains("href=\"mailto:a@b.c\""));
+    }
+}
@@ -2,9 +2,58 @@
 // SPDX-License-Identifier: Apache-2.0
 // SPDX-License-Identifier: MIT
 
+/// Asset helper and Subresource Integrity manifest.
+pub mod assets;
+/// Content collections from Markdown, JSON and YAML files (`content`).
+pub mod content;
+/// Deterministic render mode (frozen time, seeded randomness, sorted `pairs`).
+pub mod deterministic;
+/// RSS and Atom feed generation (`feed`).
+pub mod feed;
+/// Feature flags (`flags`) and the `{#feature}` block.
+pub mod flags;
+/// Locale-aware number, currency and date formatting (`fmt`).
+pub mod fmt;
+/// Responsive image helper (`img()`).
+pub mod img;
+/// Translations from locale catalogs (`i18n`, `t()`).
+pub mod i18n;
 /// JSON module for Lua.
 pub mod json;
+/// Markdown rendering (`markdown`) and `{@markdown}`.
+#[cfg(feature = "markdown")]
+pub mod markdown;
 /// Lua extensions.
 pub mod lua;
+/// Pagination helper (`paginate`) and built-in `<Pagination>` component.
+pub mod paginate;
+/// Rendering templates from Lua (`luat.render`).
+pub mod render;
+/// Page title, meta and OpenGraph tags (`seo`).
+pub mod seo;
+/// URL parsing and query string building (`url`).
+pub mod url;
 
-pub use json::register_json_module;
\ No newline at end of file
+pub use assets::{register_asset_helper, AssetManifest};
+pub use content::register_content_module;
+pub use feed::register_feed_module;
+pub use flags::{register_flags_module, FeatureFlag, FeatureFlags, FlagUser};
+pub use fmt::register_fmt_module;
+pub use i18n::{register_i18n_module, Catalogs};
+pub use img::{register_img_helper, IMAGE_ENDPOINT};
+pub use json::register_json_module;
+#[cfg(feature = "markdown")]
+pub use markdown::{register_markdown_module, render_markdown};
+pub use paginate::register_paginate_module;
+pub use render::register_render_module;
+pub use seo::{register_seo_module, PageHead};
+pub use url::register_url_module;
+
+/// Escapes text for HTML element content and attribute values.
+pub(crate) fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
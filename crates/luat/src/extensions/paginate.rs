@@ -0,0 +1,341 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Pagination helper and built-in `<Pagination>` component.
+//!
+//! `paginate(total, page, per_page, opts)` clamps the page, computes the
+//! slice to load and a window of page links:
+//!
+//! ```lua
+//! local p = paginate(count, tonumber(ctx.query.page) or 1, 20, {
+//!     path = "/blog", query = ctx.query,
+//! })
+//! local posts = store:list({ offset = p.offset, limit = p.limit })
+//! return { props = { posts = posts, pagination = p } }
+//! ```
+//!
+//! The result has `page`, `per_page`, `total`, `total_pages`, `offset`,
+//! `limit`, `has_prev`, `has_next`, `prev`, `next`, `pages` (entries with
+//! `number`, `href` and `current`, or `gap = true`) and `url_for(n)`.
+//!
+//! Options: `path` (default `""`, i.e. the current page), `param` (query
+//! parameter, default `page`), `query` (parameters to keep in links),
+//! `window` (pages shown on each side of the current one, default 2) and
+//! `url_for` (a function building the link to page `n`, for routes such as
+//! `/blog/page/[n]`; replaces `path`, `param` and `query`).
+//!
+//! The global `Pagination` component renders the links through the result's
+//! `url_for`:
+//!
+//! ```html
+//! <Pagination pagination={props.pagination} />
+//! <Pagination total={120} page={3} per_page={20} path="/blog" />
+//! <Pagination total={120} page={3} url_for={function(n) return "/blog/page/" .. n end} />
+//! ```
+
+use super::html_escape;
+use mlua::{Function, Lua, Result as LuaResult, Table, Value};
+use std::collections::BTreeMap;
+
+/// Default number of pages shown on each side of the current page.
+const DEFAULT_WINDOW: usize = 2;
+
+/// An entry in the page window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PageLink {
+    Page(usize),
+    Gap,
+}
+
+/// Computes the pages to link: first, last and `window` pages around `page`.
+///
+/// Gaps of a single page show the page instead of an ellipsis.
+fn page_window(page: usize, total_pages: usize, window: usize) -> Vec<PageLink> {
+    let start = page.saturating_sub(window).max(1);
+    let end = (page + window).min(total_pages);
+
+    let mut numbers = vec![1];
+    numbers.extend(start..=end);
+    numbers.push(total_pages);
+    numbers.sort_unstable();
+    numbers.dedup();
+
+    let mut links = Vec::new();
+    let mut previous = 0;
+    for number in numbers {
+        match number - previous {
+            1 => {}
+            2 => links.push(PageLink::Page(number - 1)),
+            _ => links.push(PageLink::Gap),
+        }
+        links.push(PageLink::Page(number));
+        previous = number;
+    }
+    links
+}
+
+/// Builds a link to page `n`, keeping the other query parameters.
+fn page_url(path: &str, param: &str, query: &BTreeMap<String, String>, n: usize) -> String {
+    let mut serializer = form_urlencoded::Serializer::new(String::new());
+    for (key, value) in query {
+        if key != param {
+            serializer.append_pair(key, value);
+        }
+    }
+    serializer.append_pair(param, &n.to_string());
+    format!("{}?{}", path, serializer.finish())
+}
+
+fn paginate(
+    lua: &Lua,
+    (total, page, per_page, opts): (i64, Option<i64>, Option<i64>, Option<Table>),
+) -> LuaResult<Table> {
+    let per_page = per_page.unwrap_or(20);
+    if per_page <= 0 {
+        return Err(mlua::Error::runtime("paginate: per_page must be positive"));
+    }
+    let total = total.max(0) as usize;
+    let per_page = per_page as usize;
+    let total_pages = total.div_ceil(per_page).max(1);
+    let page = (page.unwrap_or(1).max(1) as usize).min(total_pages);
+
+    let mut path = String::new();
+    let mut param = "page".to_string();
+    let mut window = DEFAULT_WINDOW;
+    let mut query = BTreeMap::new();
+    let mut custom_url_for = None;
+    if let Some(opts) = &opts {
+        custom_url_for = opts.get::<Option<Function>>("url_for")?;
+        path = opts.get::<Option<String>>("path")?.unwrap_or_default();
+        param = opts.get::<Option<String>>("param")?.unwrap_or(param);
+        window = opts.get::<Option<usize>>("window")?.unwrap_or(window);
+        if let Some(params) = opts.get::<Option<Table>>("query")? {
+            for pair in params.pairs::<String, Value>() {
+                let (key, value) = pair?;
+                if let Some(value) = lua.coerce_string(value)? {
+                    query.insert(key, value.to_str()?.to_string());
+                }
+            }
+        }
+    }
+
+    let result = lua.create_table()?;
+    result.set("total", total)?;
+    result.set("page", page)?;
+    result.set("per_page", per_page)?;
+    result.set("total_pages", total_pages)?;
+    result.set("offset", (page - 1) * per_page)?;
+    result.set("limit", per_page)?;
+    result.set("has_prev", page > 1)?;
+    result.set("has_next", page < total_pages)?;
+    if page > 1 {
+        result.set("prev", page - 1)?;
+    }
+    if page < total_pages {
+        result.set("next", page + 1)?;
+    }
+
+    let url_for = match custom_url_for {
+        Some(url_for) => url_for,
+        None => lua.create_function(move |_, n: usize| Ok(page_url(&path, &param, &query, n)))?,
+    };
+
+    let pages = lua.create_table()?;
+    for link in page_window(page, total_pages, window) {
+        let entry = lua.create_table()?;
+        match link {
+            PageLink::Page(number) => {
+                entry.set("number", number)?;
+                entry.set("href", url_for.call::<String>(number)?)?;
+                entry.set("current", number == page)?;
+            }
+            PageLink::Gap => entry.set("gap", true)?,
+        }
+        pages.push(entry)?;
+    }
+    result.set("pages", pages)?;
+    result.set("url_for", url_for)?;
+
+    Ok(result)
+}
+
+/// Renders the `<Pagination>` component.
+///
+/// Props: `pagination` (a `paginate` result) or `total`/`page`/`per_page`
+/// plus the `paginate` options; `label`, `prev_label`, `next_label` and
+/// `class` customize the markup. Links come from the result's `url_for`.
+fn render_pagination(lua: &Lua, props: Table) -> LuaResult<String> {
+    let pagination = match props.get::<Option<Table>>("pagination")? {
+        Some(pagination) => pagination,
+        None => paginate(
+            lua,
+            (
+                props.get::<Option<i64>>("total")?.unwrap_or(0),
+                props.get("page")?,
+                props.get("per_page")?,
+                Some(props.clone()),
+            ),
+        )?,
+    };
+
+    let total_pages: usize = pagination.get("total_pages")?;
+    if total_pages <= 1 {
+        return Ok(String::new());
+    }
+
+    let text = |key: &str, default: &str| -> LuaResult<String> {
+        Ok(html_escape(
+            &props
+                .get::<Option<String>>(key)?
+                .unwrap_or_else(|| default.to_string()),
+        ))
+    };
+    let url_for: Function = pagination.get("url_for")?;
+    let link = |rel: &str, page: Option<usize>, label: &str| -> LuaResult<String> {
+        Ok(match page {
+            Some(page) => format!(
+                "<a href=\"{}\" rel=\"{}\">{}</a>",
+                html_escape(&url_for.call::<String>(page)?),
+                rel,
+                label
+            ),
+            None => format!("<span aria-disabled=\"true\">{}</span>", label),
+        })
+    };
+
+    let mut html = format!(
+        "<nav class=\"{}\" aria-label=\"{}\">",
+        text("class", "pagination")?,
+        text("label", "Pagination")?
+    );
+    html.push_str(&link(
+        "prev",
+        pagination.get("prev")?,
+        &text("prev_label", "Previous")?,
+    )?);
+
+    let pages: Table = pagination.get("pages")?;
+    for entry in pages.sequence_values::<Table>() {
+        let entry = entry?;
+        if entry.get::<Option<bool>>("gap")?.unwrap_or(false) {
+            html.push_str("<span class=\"pagination-gap\">…</span>");
+        } else if entry.get::<bool>("current")? {
+            html.push_str(&format!(
+                "<span aria-current=\"page\">{}</span>",
+                entry.get::<usize>("number")?
+            ));
+        } else {
+            let number = entry.get::<usize>("number")?;
+            html.push_str(&format!(
+                "<a href=\"{}\">{}</a>",
+                html_escape(&url_for.call::<String>(number)?),
+                number
+            ));
+        }
+    }
+
+    html.push_str(&link(
+        "next",
+        pagination.get("next")?,
+        &text("next_label", "Next")?,
+    )?);
+    html.push_str("</nav>");
+    Ok(html)
+}
+
+/// Registers the global `paginate` function and `Pagination` component.
+pub fn register_paginate_module(lua: &Lua) -> LuaResult<()> {
+    let globals = lua.globals();
+    globals.set("paginate", lua.create_function(paginate)?)?;
+
+    let component = lua.create_table()?;
+    component.set(
+        "render",
+        lua.create_function(|lua, (props, _runtime): (Option<Table>, Value)| {
+            let props = match props {
+                Some(props) => props,
+                None => lua.create_table()?,
+            };
+            render_pagination(lua, props)
+        })?,
+    )?;
+    globals.set("Pagination", component)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use PageLink::{Gap, Page};
+
+    #[test]
+    fn test_page_window() {
+        assert_eq!(page_window(1, 1, 2), vec![Page(1)]);
+        assert_eq!(
+            page_window(1, 10, 2),
+            vec![Page(1), Page(2), Page(3), Gap, Page(10)]
+        );
+        assert_eq!(
+            page_window(6, 12, 1),
+            vec![Page(1), Gap, Page(5), Page(6), Page(7), Gap, Page(12)]
+        );
+        // A single skipped page is shown instead of a gap
+        assert_eq!(
+            page_window(4, 6, 1),
+            vec![Page(1), Page(2), Page(3), Page(4), Page(5), Page(6)]
+        );
+    }
+
+    #[test]
+    fn test_paginate() {
+        let lua = Lua::new();
+        register_paginate_module(&lua).unwrap();
+
+        let (offset, total_pages, page, next, href): (usize, usize, usize, usize, String) = lua
+            .load(
+                r#"
+                local p = paginate(95, 2, 20, { path = "/blog", query = { tag = "rust & lua", page = "2" } })
+                return p.offset, p.total_pages, p.page, p.next, p.url_for(3)
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!((offset, total_pages, page, next), (20, 5, 2, 3));
+        assert_eq!(href, "/blog?tag=rust+%26+lua&page=3");
+
+        // Out-of-range pages are clamped
+        let page: usize = lua.load("return paginate(10, 99, 5).page").eval().unwrap();
+        assert_eq!(page, 2);
+        assert!(lua.load("return paginate(10, 1, 0)").exec().is_err());
+    }
+
+    #[test]
+    fn test_pagination_component() {
+        let lua = Lua::new();
+        register_paginate_module(&lua).unwrap();
+
+        let html: String = lua
+            .load(r#"return Pagination.render({ total = 100, page = 1, per_page = 10, path = "/posts" })"#)
+            .eval()
+            .unwrap();
+        assert!(html.starts_with("<nav class=\"pagination\" aria-label=\"Pagination\">"));
+        assert!(html.contains("<span aria-disabled=\"true\">Previous</span>"));
+        assert!(html.contains("<span aria-current=\"page\">1</span>"));
+        assert!(html.contains("<a href=\"/posts?page=2\">2</a>"));
+        assert!(html.contains("<span class=\"pagination-gap\">…</span>"));
+        assert!(html.contains("<a href=\"/posts?page=2\" rel=\"next\">Next</a>"));
+
+        let html: String = lua
+            .load(r#"return Pagination.render({ total = 30, page = 2, per_page = 10, url_for = function(n) return "/blog/page/" .. n end })"#)
+            .eval()
+            .unwrap();
+        assert!(html.contains("<a href=\"/blog/page/1\" rel=\"prev\">Previous</a>"), "{}", html);
+        assert!(html.contains("<a href=\"/blog/page/3\">3</a>"), "{}", html);
+
+        let empty: String = lua
+            .load("return Pagination.render({ total = 5, page = 1 })")
+            .eval()
+            .unwrap();
+        assert_eq!(empty, "");
+    }
+}
@@ -0,0 +1,145 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Rendering templates from Lua (`luat.render`).
+//!
+//! ```lua
+//! function POST(ctx)
+//!     local html = luat.render("emails/Invoice.luat", { order = order })
+//!     mail.send({ to = order.email, html = html })
+//!     return { status = 202 }
+//! end
+//! ```
+//!
+//! The template is loaded through `require`, so it resolves and caches like
+//! a component. It renders with its own context stack: `getContext` inside
+//! it doesn't see contexts set by the page or handler that called it. Called
+//! during a page request, it shares the request's CSP nonce, cookies,
+//! locale, escape audit and output limit.
+//!
+//! Pass `{ email = true }` as a third argument to inline the template's
+//! `<style>` rules and strip tags mail clients don't support (see
+//! [`crate::email`]).
+
+use mlua::{Function, Lua, Result as LuaResult, Table, Value};
+
+/// Request runtime fields a template rendered with `luat.render` shares.
+const SHARED_RUNTIME_KEYS: [&str; 5] = ["csp_nonce", "cookies", "locale", "escape_audit", "output_guard"];
+
+/// Returns the runtime `luat.render` passes to a template: a fresh context
+/// stack, with the request-wide fields of the current request runtime.
+fn render_runtime(lua: &Lua) -> LuaResult<Table> {
+    let runtime = lua.create_table()?;
+    runtime.set("context_stack", lua.create_table()?)?;
+    if let Some(request) = lua.named_registry_value::<Option<Table>>("__luat_request_runtime")? {
+        for key in SHARED_RUNTIME_KEYS {
+            runtime.set(key, request.get::<Value>(key)?)?;
+        }
+    }
+    Ok(runtime)
+}
+
+/// Registers the `luat` global with `luat.render(path, props, options)`.
+pub fn register_render_module(lua: &Lua) -> LuaResult<()> {
+    let luat = lua.create_table()?;
+
+    luat.set(
+        "render",
+        lua.create_function(|lua, (path, props, options): (String, Option<Table>, Option<Table>)| {
+            let require: Function = lua.globals().get("require")?;
+            let module: Value = require.call(path.as_str())?;
+            let render = match module {
+                Value::Table(module) => module.get::<Option<Function>>("render")?,
+                _ => None,
+            };
+            let render = render.ok_or_else(|| {
+                mlua::Error::runtime(format!("luat.render: '{}' is not a template", path))
+            })?;
+
+            let runtime = render_runtime(lua)?;
+            let props = match props {
+                Some(props) => props,
+                None => lua.create_table()?,
+            };
+            let output = render.call::<mlua::String>((props, runtime))?;
+
+            let email = match options {
+                Some(options) => options.get::<Option<bool>>("email")?.unwrap_or(false),
+                None => false,
+            };
+            if !email {
+                return Ok(output);
+            }
+            let html = output.to_str()?;
+            lua.create_string(crate::email::inline_email_html(&html))
+        })?,
+    )?;
+
+    lua.globals().set("luat", luat)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_from_lua() {
+        let lua = Lua::new();
+        register_render_module(&lua).unwrap();
+        lua.load(
+            r#"
+            package.preload["Greeting.luat"] = function()
+                return { render = function(props, runtime) return "<p>Hi " .. props.name .. "</p>" end }
+            end
+            package.preload["util"] = function() return {} end
+            "#,
+        )
+        .exec()
+        .unwrap();
+
+        let html: String = lua.load(r#"luat.render("Greeting.luat", { name = "Ada" })"#).eval().unwrap();
+        assert_eq!(html, "<p>Hi Ada</p>");
+
+        lua.load(
+            r#"
+            package.preload["Email.luat"] = function()
+                return { render = function() return "<style>p { color: red }</style><p>Hi</p><script>x()</script>" end }
+            end
+            "#,
+        )
+        .exec()
+        .unwrap();
+        let html: String = lua.load(r#"luat.render("Email.luat", {}, { email = true })"#).eval().unwrap();
+        assert_eq!(html, r#"<p style="color: red">Hi</p>"#);
+
+        let err = lua.load(r#"luat.render("util")"#).exec().unwrap_err();
+        assert!(err.to_string().contains("'util' is not a template"), "{}", err);
+    }
+
+    #[test]
+    fn test_render_shares_request_runtime() {
+        let lua = Lua::new();
+        register_render_module(&lua).unwrap();
+        lua.load(
+            r#"
+            package.preload["Nonce.luat"] = function()
+                return { render = function(props, runtime)
+                    return runtime.csp_nonce .. " " .. #runtime.context_stack
+                end }
+            end
+            "#,
+        )
+        .exec()
+        .unwrap();
+
+        let request = lua.create_table().unwrap();
+        request.set("csp_nonce", "abc").unwrap();
+        request.set("context_stack", lua.create_sequence_from([lua.create_table().unwrap()]).unwrap()).unwrap();
+        lua.set_named_registry_value("__luat_request_runtime", request).unwrap();
+
+        let html: String = lua.load(r#"luat.render("Nonce.luat")"#).eval().unwrap();
+        assert_eq!(html, "abc 0");
+    }
+}
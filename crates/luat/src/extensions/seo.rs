@@ -0,0 +1,337 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Page title, meta, robots and OpenGraph tags (`seo`).
+//!
+//! Load functions, server code and templates describe the page head through
+//! the global `seo` table instead of writing tags by hand:
+//!
+//! ```lua
+//! seo.set {
+//!     title = post.title,
+//!     description = post.summary,
+//!     canonical = "https://example.com/blog/" .. post.slug,
+//!     og = { type = "article", image = post.cover },
+//!     twitter = { card = "summary_large_image" },
+//! }
+//! ```
+//!
+//! `seo.set` overrides earlier values key by key (including inside `og`,
+//! `twitter` and `meta`); `seo.defaults` only fills keys nobody has set yet,
+//! which suits layouts that render after the page. `seo.get()` returns the
+//! values collected so far.
+//!
+//! The engine collects the values into a [`PageHead`] attached to page
+//! responses. Adapters put [`PageHead::title`] into `%luat.title%` and
+//! [`PageHead::to_html`] into `%luat.head%`. Outside a page request the
+//! values are discarded.
+
+use super::html_escape;
+use mlua::{Lua, Result as LuaResult, Table, Value};
+use std::collections::BTreeMap;
+
+/// Key of the SEO table inside the request runtime.
+const RUNTIME_KEY: &str = "seo";
+
+/// Head metadata collected while handling a page request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageHead {
+    /// Document title.
+    pub title: Option<String>,
+    /// `<meta name="description">`.
+    pub description: Option<String>,
+    /// `<link rel="canonical">`.
+    pub canonical: Option<String>,
+    /// `<meta name="robots">`, e.g. `noindex, nofollow`.
+    pub robots: Option<String>,
+    /// OpenGraph properties without the `og:` prefix.
+    pub og: BTreeMap<String, String>,
+    /// Twitter card properties without the `twitter:` prefix.
+    pub twitter: BTreeMap<String, String>,
+    /// Additional `<meta name=... content=...>` tags.
+    pub meta: BTreeMap<String, String>,
+}
+
+impl PageHead {
+    /// Returns true when nothing was set.
+    pub fn is_empty(&self) -> bool {
+        *self == PageHead::default()
+    }
+
+    /// Renders the meta and link tags (not the title), one per line.
+    ///
+    /// When any OpenGraph property is set, `og:title`, `og:description` and
+    /// `og:url` default to the title, description and canonical URL.
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        let mut tag = |line: String| {
+            html.push_str("    ");
+            html.push_str(&line);
+            html.push('\n');
+        };
+
+        if let Some(description) = &self.description {
+            tag(meta_tag("name", "description", description));
+        }
+        if let Some(robots) = &self.robots {
+            tag(meta_tag("name", "robots", robots));
+        }
+        if let Some(canonical) = &self.canonical {
+            tag(format!("<link rel=\"canonical\" href=\"{}\">", html_escape(canonical)));
+        }
+
+        if !self.og.is_empty() {
+            let mut og = self.og.clone();
+            for (key, fallback) in [
+                ("title", &self.title),
+                ("description", &self.description),
+                ("url", &self.canonical),
+            ] {
+                if let Some(value) = fallback {
+                    og.entry(key.to_string()).or_insert_with(|| value.clone());
+                }
+            }
+            for (key, value) in &og {
+                tag(meta_tag("property", &format!("og:{}", key), value));
+            }
+        }
+        for (key, value) in &self.twitter {
+            tag(meta_tag("name", &format!("twitter:{}", key), value));
+        }
+        for (name, value) in &self.meta {
+            tag(meta_tag("name", name, value));
+        }
+
+        html
+    }
+
+    /// Reads the values collected by `seo.set` in a request runtime table.
+    pub fn from_runtime(runtime: &Table) -> LuaResult<Self> {
+        let seo = match runtime.get::<Option<Table>>(RUNTIME_KEY)? {
+            Some(seo) => seo,
+            None => return Ok(Self::default()),
+        };
+
+        Ok(Self {
+            title: seo.get("title")?,
+            description: seo.get("description")?,
+            canonical: seo.get("canonical")?,
+            robots: seo.get("robots")?,
+            og: string_map(&seo, "og")?,
+            twitter: string_map(&seo, "twitter")?,
+            meta: string_map(&seo, "meta")?,
+        })
+    }
+}
+
+fn meta_tag(attribute: &str, key: &str, content: &str) -> String {
+    format!(
+        "<meta {}=\"{}\" content=\"{}\">",
+        attribute,
+        html_escape(key),
+        html_escape(content)
+    )
+}
+
+fn string_map(seo: &Table, key: &str) -> LuaResult<BTreeMap<String, String>> {
+    let mut map = BTreeMap::new();
+    if let Some(table) = seo.get::<Option<Table>>(key)? {
+        for pair in table.pairs::<String, String>() {
+            let (key, value) = pair?;
+            map.insert(key, value);
+        }
+    }
+    Ok(map)
+}
+
+/// Keys holding nested tables that are merged key by key.
+const NESTED_KEYS: [&str; 3] = ["og", "twitter", "meta"];
+
+/// Keys accepted at the top level of `seo.set`.
+const KEYS: [&str; 7] = ["title", "description", "canonical", "robots", "og", "twitter", "meta"];
+
+/// Converts a value to a string for the head; `false` clears it.
+fn head_value(lua: &Lua, key: &str, value: Value) -> LuaResult<Value> {
+    match value {
+        Value::Nil | Value::Boolean(false) => Ok(Value::Nil),
+        Value::Table(list) if key == "robots" => {
+            let items: Vec<String> = list.sequence_values::<String>().collect::<LuaResult<_>>()?;
+            Ok(Value::String(lua.create_string(items.join(", "))?))
+        }
+        other => match lua.coerce_string(other.clone())? {
+            Some(s) => Ok(Value::String(s)),
+            None => Err(mlua::Error::runtime(format!(
+                "seo: `{}` must be a string, got {}",
+                key,
+                other.type_name()
+            ))),
+        },
+    }
+}
+
+/// Merges `values` into `target`; with `overwrite` false only unset keys are filled.
+fn merge(lua: &Lua, target: &Table, values: Table, overwrite: bool) -> LuaResult<()> {
+    for pair in values.pairs::<String, Value>() {
+        let (key, value) = pair?;
+        if NESTED_KEYS.contains(&key.as_str()) {
+            let Value::Table(nested) = value else {
+                return Err(mlua::Error::runtime(format!("seo: `{}` must be a table", key)));
+            };
+            let existing = match target.get::<Option<Table>>(key.as_str())? {
+                Some(existing) => existing,
+                None => {
+                    let table = lua.create_table()?;
+                    target.set(key.as_str(), &table)?;
+                    table
+                }
+            };
+            merge(lua, &existing, nested, overwrite)?;
+            continue;
+        }
+
+        if !overwrite && !target.get::<Value>(key.as_str())?.is_nil() {
+            continue;
+        }
+        let value = head_value(lua, &key, value)?;
+        target.set(key, value)?;
+    }
+    Ok(())
+}
+
+/// Rejects misspelled top-level keys before anything is merged.
+fn check_keys(values: &Table) -> LuaResult<()> {
+    for pair in values.pairs::<String, Value>() {
+        let (key, _) = pair?;
+        if !KEYS.contains(&key.as_str()) {
+            return Err(mlua::Error::runtime(format!(
+                "seo: unknown key `{}` (expected one of {})",
+                key,
+                KEYS.join(", ")
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the SEO table of the current request, if any.
+fn request_seo(lua: &Lua) -> LuaResult<Option<Table>> {
+    let runtime = match lua.named_registry_value::<Option<Table>>("__luat_request_runtime")? {
+        Some(runtime) => runtime,
+        None => return Ok(None),
+    };
+    match runtime.get::<Option<Table>>(RUNTIME_KEY)? {
+        Some(seo) => Ok(Some(seo)),
+        None => {
+            let seo = lua.create_table()?;
+            runtime.set(RUNTIME_KEY, &seo)?;
+            Ok(Some(seo))
+        }
+    }
+}
+
+/// Registers the global `seo` table.
+pub fn register_seo_module(lua: &Lua) -> LuaResult<()> {
+    let seo = lua.create_table()?;
+
+    seo.set(
+        "set",
+        lua.create_function(|lua, values: Table| {
+            check_keys(&values)?;
+            match request_seo(lua)? {
+                Some(target) => merge(lua, &target, values, true),
+                None => Ok(()),
+            }
+        })?,
+    )?;
+
+    seo.set(
+        "defaults",
+        lua.create_function(|lua, values: Table| {
+            check_keys(&values)?;
+            match request_seo(lua)? {
+                Some(target) => merge(lua, &target, values, false),
+                None => Ok(()),
+            }
+        })?,
+    )?;
+
+    seo.set(
+        "get",
+        lua.create_function(|lua, ()| match request_seo(lua)? {
+            Some(seo) => Ok(seo),
+            None => lua.create_table(),
+        })?,
+    )?;
+
+    lua.globals().set("seo", seo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_request(lua: &Lua) -> Table {
+        let runtime = lua.create_table().unwrap();
+        lua.set_named_registry_value("__luat_request_runtime", &runtime).unwrap();
+        runtime
+    }
+
+    #[test]
+    fn test_set_and_defaults() {
+        let lua = Lua::new();
+        register_seo_module(&lua).unwrap();
+        let runtime = with_request(&lua);
+
+        lua.load(
+            r#"
+            seo.set { title = "Post", og = { type = "article" }, robots = { "noindex", "nofollow" } }
+            seo.defaults { title = "Site", description = "All posts", og = { type = "website", site_name = "Blog" } }
+            seo.set { og = { image = "/cover.png" } }
+            "#,
+        )
+        .exec()
+        .unwrap();
+
+        let head = PageHead::from_runtime(&runtime).unwrap();
+        assert_eq!(head.title.as_deref(), Some("Post"));
+        assert_eq!(head.description.as_deref(), Some("All posts"));
+        assert_eq!(head.robots.as_deref(), Some("noindex, nofollow"));
+        assert_eq!(head.og.get("type").map(String::as_str), Some("article"));
+        assert_eq!(head.og.get("site_name").map(String::as_str), Some("Blog"));
+        assert_eq!(head.og.get("image").map(String::as_str), Some("/cover.png"));
+    }
+
+    #[test]
+    fn test_to_html() {
+        let mut head = PageHead {
+            title: Some("Fish & Chips".to_string()),
+            description: Some("A \"classic\"".to_string()),
+            canonical: Some("https://example.com/fish".to_string()),
+            ..Default::default()
+        };
+        head.og.insert("type".to_string(), "article".to_string());
+        head.twitter.insert("card".to_string(), "summary".to_string());
+
+        let html = head.to_html();
+        assert!(html.contains(r#"<meta name="description" content="A &quot;classic&quot;">"#));
+        assert!(html.contains(r#"<link rel="canonical" href="https://example.com/fish">"#));
+        assert!(html.contains(r#"<meta property="og:title" content="Fish &amp; Chips">"#));
+        assert!(html.contains(r#"<meta property="og:url" content="https://example.com/fish">"#));
+        assert!(html.contains(r#"<meta name="twitter:card" content="summary">"#));
+        assert!(!html.contains("<title>"));
+    }
+
+    #[test]
+    fn test_without_request_is_discarded() {
+        let lua = Lua::new();
+        register_seo_module(&lua).unwrap();
+
+        let title: Option<String> = lua
+            .load(r#"seo.set { title = "Ignored" } return seo.get().title"#)
+            .eval()
+            .unwrap();
+        assert_eq!(title, None);
+        assert!(lua.load(r#"seo.set { titel = "Typo" }"#).exec().is_err());
+    }
+}
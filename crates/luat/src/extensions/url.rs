@@ -0,0 +1,353 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! URL and query string helpers (`url`).
+//!
+//! ```lua
+//! url.build("/search", { q = "rust & lua", tags = { "a", "b" } })
+//! --> "/search?q=rust+%26+lua&tags=a&tags=b"
+//!
+//! url.with_query("/posts?tag=lua&page=1", { page = 2 })
+//! --> "/posts?tag=lua&page=2"
+//!
+//! url.with_query(ctx, { page = false })   -- ctx.url + ctx.query, without page
+//!
+//! local u = url.parse("https://example.com:8080/a?x=1&x=2#top")
+//! -- u.scheme, u.host, u.port, u.path, u.query.x == { "1", "2" }, u.fragment
+//! ```
+//!
+//! Parameter values may be strings, numbers, booleans or lists (repeated
+//! keys). `false` drops a parameter, which is how `with_query` removes one.
+//! Keys from tables are emitted in sorted order; keys already present in a
+//! URL keep their position.
+//!
+//! Also available as `require("url")`.
+
+use mlua::{Lua, Result as LuaResult, Table, Value};
+
+/// A URL split into its parts.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ParsedUrl {
+    /// Scheme without `:`, e.g. `https`.
+    pub scheme: Option<String>,
+    /// Host name without port.
+    pub host: Option<String>,
+    /// Port, when given explicitly.
+    pub port: Option<u16>,
+    /// Path, possibly empty for relative query-only URLs.
+    pub path: String,
+    /// Decoded query parameters in order of appearance.
+    pub query: Vec<(String, String)>,
+    /// Fragment without `#`.
+    pub fragment: Option<String>,
+}
+
+impl ParsedUrl {
+    /// Parses an absolute or relative URL.
+    pub fn parse(input: &str) -> Self {
+        let mut url = ParsedUrl::default();
+        let mut rest = input;
+
+        if let Some((before, fragment)) = rest.split_once('#') {
+            url.fragment = Some(fragment.to_string());
+            rest = before;
+        }
+        if let Some((before, query)) = rest.split_once('?') {
+            url.query = parse_query(query);
+            rest = before;
+        }
+
+        if let Some(colon) = rest.find("://") {
+            let scheme = &rest[..colon];
+            if !scheme.is_empty() && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c)) {
+                url.scheme = Some(scheme.to_ascii_lowercase());
+                rest = &rest[colon + 3..];
+                let (authority, path) = match rest.find('/') {
+                    Some(slash) => rest.split_at(slash),
+                    None => (rest, ""),
+                };
+                let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+                match authority.rsplit_once(':') {
+                    Some((host, port)) if !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => {
+                        url.host = Some(host.to_string());
+                        url.port = port.parse().ok();
+                    }
+                    _ => url.host = Some(authority.to_string()),
+                }
+                rest = path;
+            }
+        }
+
+        url.path = rest.to_string();
+        url
+    }
+
+    /// Replaces parameters named in `params`, keeping the others in place.
+    ///
+    /// A `None` value removes the parameter; several values repeat the key.
+    pub fn set_params(&mut self, params: Vec<(String, Option<Vec<String>>)>) {
+        for (key, values) in params {
+            let position = self.query.iter().position(|(k, _)| *k == key);
+            self.query.retain(|(k, _)| *k != key);
+            let values = values.unwrap_or_default();
+            let at = position.unwrap_or(self.query.len());
+            for (i, value) in values.into_iter().enumerate() {
+                self.query.insert(at + i, (key.clone(), value));
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ParsedUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(scheme) = &self.scheme {
+            write!(f, "{}://", scheme)?;
+        }
+        if let Some(host) = &self.host {
+            f.write_str(host)?;
+            if let Some(port) = self.port {
+                write!(f, ":{}", port)?;
+            }
+        }
+        f.write_str(&self.path)?;
+        if !self.query.is_empty() {
+            write!(f, "?{}", encode_query(&self.query))?;
+        }
+        if let Some(fragment) = &self.fragment {
+            write!(f, "#{}", fragment)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decodes an `application/x-www-form-urlencoded` query string.
+pub fn parse_query(query: &str) -> Vec<(String, String)> {
+    form_urlencoded::parse(query.as_bytes()).into_owned().collect()
+}
+
+/// Encodes parameters as a query string (without the leading `?`).
+pub fn encode_query(params: &[(String, String)]) -> String {
+    let mut serializer = form_urlencoded::Serializer::new(String::new());
+    for (key, value) in params {
+        serializer.append_pair(key, value);
+    }
+    serializer.finish()
+}
+
+/// Converts a Lua parameter value; `None` means the parameter is dropped.
+fn param_values(lua: &Lua, key: &str, value: Value) -> LuaResult<Option<Vec<String>>> {
+    match value {
+        Value::Nil | Value::Boolean(false) => Ok(None),
+        Value::Table(list) => {
+            let mut values = Vec::new();
+            for item in list.sequence_values::<Value>() {
+                if let Some(values_for_item) = param_values(lua, key, item?)? {
+                    values.extend(values_for_item);
+                }
+            }
+            Ok(Some(values))
+        }
+        other => match lua.coerce_string(other.clone())? {
+            Some(s) => Ok(Some(vec![s.to_str()?.to_string()])),
+            None if matches!(other, Value::Boolean(true)) => Ok(Some(vec!["true".to_string()])),
+            None => Err(mlua::Error::runtime(format!(
+                "url: cannot encode a {} value for parameter `{}`",
+                other.type_name(),
+                key
+            ))),
+        },
+    }
+}
+
+/// Reads a parameter table, sorted by key.
+fn table_params(lua: &Lua, params: Option<Table>) -> LuaResult<Vec<(String, Option<Vec<String>>)>> {
+    let mut result = Vec::new();
+    if let Some(params) = params {
+        for pair in params.pairs::<Value, Value>() {
+            let (key, value) = pair?;
+            let key = match lua.coerce_string(key)? {
+                Some(key) => key.to_str()?.to_string(),
+                None => continue,
+            };
+            let values = param_values(lua, &key, value)?;
+            result.push((key, values));
+        }
+    }
+    result.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(result)
+}
+
+/// Reads the `current` argument of `with_query`: a URL string or a table
+/// with `path` (or `url`, as on the request context) and `query`, such as
+/// a `url.parse` result.
+fn current_url(lua: &Lua, current: Value) -> LuaResult<ParsedUrl> {
+    match current {
+        Value::Table(table) => {
+            let path = match table.get::<Option<String>>("path")? {
+                Some(path) => path,
+                None => table.get::<Option<String>>("url")?.unwrap_or_default(),
+            };
+            let mut url = ParsedUrl::parse(&path);
+            url.scheme = table.get::<Option<String>>("scheme")?.or(url.scheme);
+            url.host = table.get::<Option<String>>("host")?.or(url.host);
+            url.port = table.get::<Option<u16>>("port")?.or(url.port);
+            url.fragment = table.get::<Option<String>>("fragment")?.or(url.fragment);
+            url.set_params(table_params(lua, table.get("query")?)?);
+            Ok(url)
+        }
+        Value::Nil => Ok(ParsedUrl::default()),
+        other => match lua.coerce_string(other)? {
+            Some(s) => Ok(ParsedUrl::parse(&s.to_str()?)),
+            None => Err(mlua::Error::runtime("url.with_query: expected a URL string or table")),
+        },
+    }
+}
+
+fn url_to_table(lua: &Lua, url: &ParsedUrl) -> LuaResult<Table> {
+    let table = lua.create_table()?;
+    table.set("scheme", url.scheme.as_deref())?;
+    table.set("host", url.host.as_deref())?;
+    table.set("port", url.port)?;
+    table.set("path", url.path.as_str())?;
+    table.set("fragment", url.fragment.as_deref())?;
+
+    let query = lua.create_table()?;
+    for (key, value) in &url.query {
+        match query.get::<Value>(key.as_str())? {
+            Value::Nil => query.set(key.as_str(), value.as_str())?,
+            Value::Table(list) => list.push(value.as_str())?,
+            first => query.set(key.as_str(), lua.create_sequence_from([first, Value::String(lua.create_string(value)?)])?)?,
+        }
+    }
+    table.set("query", query)?;
+    table.set("query_string", encode_query(&url.query))?;
+    Ok(table)
+}
+
+/// Registers the global `url` module and `require("url")`.
+pub fn register_url_module(lua: &Lua) -> LuaResult<()> {
+    let url = lua.create_table()?;
+
+    url.set(
+        "parse",
+        lua.create_function(|lua, input: String| url_to_table(lua, &ParsedUrl::parse(&input)))?,
+    )?;
+
+    url.set(
+        "build",
+        lua.create_function(|lua, (path, params): (String, Option<Table>)| {
+            let mut url = ParsedUrl::parse(&path);
+            url.set_params(table_params(lua, params)?);
+            Ok(url.to_string())
+        })?,
+    )?;
+
+    url.set(
+        "with_query",
+        lua.create_function(|lua, (current, params): (Value, Option<Table>)| {
+            let mut url = current_url(lua, current)?;
+            url.set_params(table_params(lua, params)?);
+            Ok(url.to_string())
+        })?,
+    )?;
+
+    url.set(
+        "encode",
+        lua.create_function(|_, s: mlua::String| {
+            Ok(form_urlencoded::byte_serialize(&s.as_bytes()[..]).collect::<String>())
+        })?,
+    )?;
+
+    url.set(
+        "decode",
+        lua.create_function(|_, s: String| {
+            Ok(form_urlencoded::parse(format!("={}", s).as_bytes())
+                .next()
+                .map(|(_, value)| value.into_owned())
+                .unwrap_or_default())
+        })?,
+    )?;
+
+    let globals = lua.globals();
+    globals.set("url", url)?;
+
+    let package: Table = globals.get("package")?;
+    let preload: Table = package.get("preload")?;
+    preload.set(
+        "url",
+        lua.create_function(|lua, _: ()| lua.globals().get::<Table>("url"))?,
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_absolute_url() {
+        let url = ParsedUrl::parse("https://user@example.com:8080/a/b?x=1&y=two+words&x=3#top");
+        assert_eq!(url.scheme.as_deref(), Some("https"));
+        assert_eq!(url.host.as_deref(), Some("example.com"));
+        assert_eq!(url.port, Some(8080));
+        assert_eq!(url.path, "/a/b");
+        assert_eq!(url.fragment.as_deref(), Some("top"));
+        assert_eq!(
+            url.query,
+            vec![
+                ("x".to_string(), "1".to_string()),
+                ("y".to_string(), "two words".to_string()),
+                ("x".to_string(), "3".to_string()),
+            ]
+        );
+
+        let relative = ParsedUrl::parse("/posts?page=2");
+        assert_eq!(relative.host, None);
+        assert_eq!(relative.to_string(), "/posts?page=2");
+    }
+
+    #[test]
+    fn test_set_params_keeps_position() {
+        let mut url = ParsedUrl::parse("/posts?tag=lua&page=1&sort=new");
+        url.set_params(vec![
+            ("page".to_string(), Some(vec!["2".to_string()])),
+            ("sort".to_string(), None),
+            ("q".to_string(), Some(vec!["a&b".to_string()])),
+        ]);
+        assert_eq!(url.to_string(), "/posts?tag=lua&page=2&q=a%26b");
+    }
+
+    #[test]
+    fn test_lua_api() {
+        let lua = Lua::new();
+        register_url_module(&lua).unwrap();
+
+        let (built, with_query, from_ctx, port, repeated, encoded): (String, String, String, u16, String, String) = lua
+            .load(
+                r#"
+                local u = require("url")
+                local parsed = u.parse("http://localhost:3000/x?a=1&a=2")
+                return u.build("/search", { q = "rust & lua", tags = { "a", "b" }, draft = false }),
+                    u.with_query("/posts?tag=lua&page=1#list", { page = 2 }),
+                    u.with_query({ url = "/posts", query = { page = "3", tag = "lua" } }, { page = false }),
+                    parsed.port,
+                    table.concat(parsed.query.a, ","),
+                    u.encode("a b/c")
+                "#,
+            )
+            .eval()
+            .unwrap();
+
+        assert_eq!(built, "/search?q=rust+%26+lua&tags=a&tags=b");
+        assert_eq!(with_query, "/posts?tag=lua&page=2#list");
+        assert_eq!(from_ctx, "/posts?tag=lua");
+        assert_eq!(port, 3000);
+        assert_eq!(repeated, "1,2");
+        assert_eq!(encoded, "a+b%2Fc");
+
+        let decoded: String = lua.load(r#"return url.decode("a+b%2Fc")"#).eval().unwrap();
+        assert_eq!(decoded, "a b/c");
+    }
+}
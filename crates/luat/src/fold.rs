@@ -0,0 +1,676 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Compile-time constant folding.
+//!
+//! Expressions made only of literals, operators and a whitelist of pure
+//! library functions are evaluated while the template is transformed, so
+//! they are emitted as static text instead of Lua:
+//!
+//! ```text
+//! <h1>{string.upper("menu")}</h1>      →  <h1>MENU</h1>
+//! <div data-cols={12 // 4}>            →  <div data-cols="3">
+//! ```
+//!
+//! An `{#if}` block whose condition is constant is replaced by the branch
+//! taken; `{!if}` blocks are kept.
+//!
+//! Folding follows Lua 5.4 semantics and is conservative: anything it cannot
+//! evaluate exactly as Lua would (variables, floats in the output, string to
+//! number coercion, runtime errors such as division by zero) is left to the
+//! generated code.
+//!
+//! Pure functions: `string.upper`, `string.lower`, `string.len`,
+//! `string.rep`, `string.reverse`, `string.sub`, `math.abs`, `math.floor`,
+//! `math.ceil`, `math.max`, `math.min` and `tostring`. Calls are not folded
+//! in a template whose scripts use `string`, `math` or `tostring` other than
+//! to call them, or whose `{#each}`, `{#await}`, `{@const}` or `{#snippet}`
+//! blocks bind one of those names, since they may rebind the name.
+
+use crate::ast::{Expression, OutputMode};
+use crate::expression::{tokenize, Token, TokenKind};
+use crate::transform::{IRAttribute, IRAttributeValue, IRNode, IR};
+
+/// Longest string a folded expression may produce.
+const MAX_FOLDED_LEN: usize = 4096;
+
+/// Value of a constant expression.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Constant {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl Constant {
+    fn truthy(&self) -> bool {
+        !matches!(self, Constant::Nil | Constant::Bool(false))
+    }
+
+    /// Returns what `tostring` gives for the value, or `None` for floats,
+    /// whose formatting is left to Lua.
+    pub(crate) fn to_lua_string(&self) -> Option<String> {
+        match self {
+            Constant::Nil => Some("nil".to_string()),
+            Constant::Bool(b) => Some(b.to_string()),
+            Constant::Int(i) => Some(i.to_string()),
+            Constant::Float(_) => None,
+            Constant::Str(s) => Some(s.clone()),
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Constant::Int(i) => Some(*i as f64),
+            Constant::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    /// String operand of a library function; numbers are converted like Lua does.
+    fn as_str(&self) -> Option<String> {
+        match self {
+            Constant::Str(s) => Some(s.clone()),
+            Constant::Int(i) => Some(i.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// Evaluates `source` if it is a constant expression; `calls` allows calls
+/// to the pure functions.
+pub(crate) fn evaluate(source: &str, calls: bool) -> Option<Constant> {
+    let tokens = tokenize(source).ok()?;
+    let mut folder = Folder { tokens, pos: 0, calls };
+    let value = folder.expression(0)?;
+    let value = (folder.peek().kind == TokenKind::Eof).then_some(value)?;
+    match &value {
+        Constant::Str(s) if s.len() > MAX_FOLDED_LEN => None,
+        _ => Some(value),
+    }
+}
+
+/// Folds the constant mustache expressions and element attribute values of
+/// `ir` into static text.
+pub(crate) fn fold_constants(ir: &mut IR) {
+    let scripts = [&ir.module_script, &ir.regular_script];
+    let calls = !scripts
+        .into_iter()
+        .flatten()
+        .any(|script| may_rebind_pure_functions(&script.content))
+        && !binds_pure_function(&ir.body);
+    fold_nodes(&mut ir.body, ir.mode, calls);
+}
+
+/// Returns true if a template block in `nodes` declares a variable named
+/// like a pure function's table or name.
+fn binds_pure_function(nodes: &[IRNode]) -> bool {
+    let is_pure_name = |binding: &str| {
+        tokenize(binding).map_or(true, |tokens| {
+            tokens
+                .iter()
+                .any(|t| t.kind == TokenKind::Name && matches!(t.text, "string" | "math" | "tostring"))
+        })
+    };
+    nodes.iter().any(|node| match node {
+        IRNode::LocalConst { name, .. } => is_pure_name(name),
+        IRNode::EachNode { item_id, index_id, body, empty, .. } => {
+            is_pure_name(item_id)
+                || index_id.as_deref().is_some_and(is_pure_name)
+                || binds_pure_function(body)
+                || empty.as_deref().is_some_and(binds_pure_function)
+        }
+        IRNode::AwaitNode { pending, then_id, then_branch, catch_id, catch_branch, .. } => {
+            [then_id, catch_id].into_iter().flatten().any(|id| is_pure_name(id))
+                || binds_pure_function(pending)
+                || [then_branch, catch_branch].into_iter().flatten().any(|b| binds_pure_function(b))
+        }
+        IRNode::Snippet { name, params, body } => {
+            is_pure_name(name) || params.iter().any(|p| is_pure_name(p)) || binds_pure_function(body)
+        }
+        IRNode::IfNode { then_branch, else_branch, .. } => {
+            binds_pure_function(then_branch) || else_branch.as_deref().is_some_and(binds_pure_function)
+        }
+        IRNode::ElementNode { children, .. }
+        | IRNode::CustomBlock { children, .. }
+        | IRNode::HtmlComment { children }
+        | IRNode::ComponentNode { children: Some(children), .. }
+        | IRNode::DynamicComponent { children: Some(children), .. } => binds_pure_function(children),
+        _ => false,
+    })
+}
+
+/// Returns true if `script` uses a pure function's table or name other than
+/// as `name.field` or `name(...)`.
+fn may_rebind_pure_functions(script: &str) -> bool {
+    let Ok(tokens) = tokenize(script) else {
+        return true;
+    };
+    tokens.windows(2).any(|pair| {
+        pair[0].kind == TokenKind::Name
+            && matches!(pair[0].text, "string" | "math" | "tostring")
+            && !matches!(pair[1].text, "." | "(")
+    })
+}
+
+fn fold_nodes(nodes: &mut Vec<IRNode>, mode: OutputMode, calls: bool) {
+    for node in nodes.iter_mut() {
+        match node {
+            IRNode::MustacheNode { expression, escaped: true } => {
+                if let Some(text) = fold_text(expression, calls) {
+                    *node = IRNode::TextNode { content: escape(&text, mode) };
+                }
+            }
+            IRNode::ElementNode { attributes, children, .. } => {
+                for attr in attributes.iter_mut() {
+                    if let IRAttribute::Named { name, value } = attr {
+                        let folded = match value {
+                            // `class:` directive conditions stay expressions
+                            IRAttributeValue::Dynamic(_) if name.starts_with("class:") => None,
+                            IRAttributeValue::Dynamic(expr) => {
+                                evaluate(&expr.content, calls).and_then(|v| v.to_lua_string())
+                            }
+                            _ => None,
+                        };
+                        if let Some(text) = folded {
+                            *value = IRAttributeValue::Static(escape(&text, mode));
+                        }
+                    }
+                }
+                fold_nodes(children, mode, calls);
+            }
+            IRNode::IfNode { then_branch, else_branch, .. } => {
+                fold_nodes(then_branch, mode, calls);
+                if let Some(else_nodes) = else_branch {
+                    fold_nodes(else_nodes, mode, calls);
+                }
+            }
+            IRNode::EachNode { body, empty, .. } => {
+                fold_nodes(body, mode, calls);
+                if let Some(empty_nodes) = empty {
+                    fold_nodes(empty_nodes, mode, calls);
+                }
+            }
+            IRNode::AwaitNode { pending, then_branch, catch_branch, .. } => {
+                fold_nodes(pending, mode, calls);
+                for branch in [then_branch, catch_branch].into_iter().flatten() {
+                    fold_nodes(branch, mode, calls);
+                }
+            }
+            IRNode::CustomBlock { children, .. }
+            | IRNode::HtmlComment { children }
+            | IRNode::Snippet { body: children, .. } => fold_nodes(children, mode, calls),
+            IRNode::ComponentNode { children: Some(children), .. }
+            | IRNode::DynamicComponent { children: Some(children), .. } => fold_nodes(children, mode, calls),
+            _ => {}
+        }
+    }
+
+    let mut kept = Vec::with_capacity(nodes.len());
+    for node in std::mem::take(nodes) {
+        match node {
+            // Expressions folded to nothing (nil, "") leave no output
+            IRNode::TextNode { content } if content.is_empty() => {}
+            // {#if} blocks with a constant condition keep only the branch taken
+            IRNode::IfNode { condition, then_branch, else_branch, sensitive: false } => {
+                match evaluate(&condition.content, calls) {
+                    Some(value) => {
+                        let branch = if value.truthy() { then_branch } else { else_branch.unwrap_or_default() };
+                        if branch.iter().any(|node| matches!(node, IRNode::LocalConst { .. } | IRNode::Snippet { .. })) {
+                            // Keep a block around declarations so they stay local to it
+                            kept.push(IRNode::IfNode {
+                                condition: Expression::new("true", condition.span),
+                                then_branch: branch,
+                                else_branch: None,
+                                sensitive: false,
+                            });
+                        } else {
+                            kept.extend(branch);
+                        }
+                    }
+                    None => kept.push(IRNode::IfNode { condition, then_branch, else_branch, sensitive: false }),
+                }
+            }
+            node => kept.push(node),
+        }
+    }
+    *nodes = kept;
+}
+
+/// Text a mustache tag renders for a constant expression (`smart_tostring`).
+fn fold_text(expression: &Expression, calls: bool) -> Option<String> {
+    match evaluate(&expression.content, calls)? {
+        Constant::Nil => Some(String::new()),
+        value => value.to_lua_string(),
+    }
+}
+
+fn escape(text: &str, mode: OutputMode) -> String {
+    match mode {
+        OutputMode::Html | OutputMode::Xml => text
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+            .replace('\'', "&#39;"),
+        OutputMode::Text => text.to_string(),
+    }
+}
+
+/// Left and right priorities of Lua's binary operators that can be folded.
+fn binary_priority(op: &str) -> Option<(u8, u8)> {
+    Some(match op {
+        "or" => (1, 1),
+        "and" => (2, 2),
+        "<" | ">" | "<=" | ">=" | "~=" | "==" => (3, 3),
+        ".." => (9, 8),
+        "+" | "-" => (10, 10),
+        "*" | "/" | "//" | "%" => (11, 11),
+        "^" => (14, 13),
+        _ => return None,
+    })
+}
+
+/// Priority of unary operators.
+const UNARY_PRIORITY: u8 = 12;
+
+struct Folder<'a> {
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+    calls: bool,
+}
+
+impl<'a> Folder<'a> {
+    fn peek(&self) -> &Token<'a> {
+        &self.tokens[self.pos]
+    }
+
+    fn is(&self, text: &str) -> bool {
+        let token = self.peek();
+        matches!(token.kind, TokenKind::Symbol | TokenKind::Keyword) && token.text == text
+    }
+
+    fn eat(&mut self, text: &str) -> bool {
+        let found = self.is(text);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expression(&mut self, limit: u8) -> Option<Constant> {
+        let mut left = if self.is("not") || self.is("-") || self.is("#") {
+            let op = self.peek().text;
+            self.pos += 1;
+            unary(op, self.expression(UNARY_PRIORITY)?)?
+        } else {
+            self.simple()?
+        };
+
+        loop {
+            let token = self.peek();
+            if !matches!(token.kind, TokenKind::Symbol | TokenKind::Keyword) {
+                return Some(left);
+            }
+            let op = token.text;
+            let Some((left_priority, right_priority)) = binary_priority(op) else {
+                return Some(left);
+            };
+            if left_priority <= limit {
+                return Some(left);
+            }
+            self.pos += 1;
+            let right = self.expression(right_priority)?;
+            left = binary(op, left, right)?;
+        }
+    }
+
+    fn simple(&mut self) -> Option<Constant> {
+        let token = self.peek().clone();
+        self.pos += 1;
+        match (token.kind, token.text) {
+            (TokenKind::Number, text) => parse_number(text),
+            (TokenKind::String, text) => parse_string(text).map(Constant::Str),
+            (TokenKind::Keyword, "nil") => Some(Constant::Nil),
+            (TokenKind::Keyword, "true") => Some(Constant::Bool(true)),
+            (TokenKind::Keyword, "false") => Some(Constant::Bool(false)),
+            (TokenKind::Symbol, "(") => {
+                let value = self.expression(0)?;
+                self.eat(")").then_some(value)
+            }
+            (TokenKind::Name, name) if self.calls => {
+                let mut function = name.to_string();
+                if self.eat(".") {
+                    let field = self.peek().clone();
+                    if field.kind != TokenKind::Name {
+                        return None;
+                    }
+                    self.pos += 1;
+                    function = format!("{}.{}", function, field.text);
+                }
+                let args = self.arguments()?;
+                call(&function, &args)
+            }
+            _ => None,
+        }
+    }
+
+    fn arguments(&mut self) -> Option<Vec<Constant>> {
+        if !self.eat("(") {
+            return None;
+        }
+        let mut args = Vec::new();
+        if self.eat(")") {
+            return Some(args);
+        }
+        loop {
+            args.push(self.expression(0)?);
+            if self.eat(")") {
+                return Some(args);
+            }
+            if !self.eat(",") {
+                return None;
+            }
+        }
+    }
+}
+
+fn parse_number(text: &str) -> Option<Constant> {
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        return i64::from_str_radix(hex, 16).ok().map(Constant::Int);
+    }
+    if text.bytes().all(|b| b.is_ascii_digit()) {
+        // Decimal integers that overflow become floats in Lua
+        return Some(match text.parse::<i64>() {
+            Ok(i) => Constant::Int(i),
+            Err(_) => Constant::Float(text.parse().ok()?),
+        });
+    }
+    text.parse::<f64>().ok().map(Constant::Float)
+}
+
+/// Parses a string literal, giving up on escapes other than the common ones.
+fn parse_string(text: &str) -> Option<String> {
+    if text.starts_with('[') {
+        let level = text[1..].bytes().take_while(|&b| b == b'=').count();
+        let body = &text[level + 2..text.len() - level - 2];
+        // A newline right after the opening bracket is skipped
+        let body = body.strip_prefix("\r\n").or_else(|| body.strip_prefix('\n')).unwrap_or(body);
+        return Some(body.to_string());
+    }
+
+    let body = &text[1..text.len() - 1];
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        out.push(match chars.next()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '\\' => '\\',
+            '"' => '"',
+            '\'' => '\'',
+            _ => return None,
+        });
+    }
+    Some(out)
+}
+
+fn unary(op: &str, value: Constant) -> Option<Constant> {
+    match (op, value) {
+        ("not", value) => Some(Constant::Bool(!value.truthy())),
+        ("-", Constant::Int(i)) => Some(Constant::Int(i.wrapping_neg())),
+        ("-", Constant::Float(f)) => Some(Constant::Float(-f)),
+        ("#", Constant::Str(s)) => Some(Constant::Int(s.len() as i64)),
+        _ => None,
+    }
+}
+
+fn binary(op: &str, left: Constant, right: Constant) -> Option<Constant> {
+    use Constant::{Bool, Float, Int, Str};
+
+    match op {
+        "and" => return Some(if left.truthy() { right } else { left }),
+        "or" => return Some(if left.truthy() { left } else { right }),
+        "==" => return Some(Bool(equals(&left, &right))),
+        "~=" => return Some(Bool(!equals(&left, &right))),
+        _ => {}
+    }
+
+    match (op, &left, &right) {
+        ("..", _, _) => {
+            let joined = left.as_str()? + &right.as_str()?;
+            (joined.len() <= MAX_FOLDED_LEN).then_some(Str(joined))
+        }
+        ("<" | "<=" | ">" | ">=", Str(a), Str(b)) => Some(Bool(compare(op, a.cmp(b)))),
+        ("<" | "<=" | ">" | ">=", _, _) => {
+            let ordering = left.as_f64()?.partial_cmp(&right.as_f64()?)?;
+            Some(Bool(compare(op, ordering)))
+        }
+        ("+", Int(a), Int(b)) => Some(Int(a.wrapping_add(*b))),
+        ("-", Int(a), Int(b)) => Some(Int(a.wrapping_sub(*b))),
+        ("*", Int(a), Int(b)) => Some(Int(a.wrapping_mul(*b))),
+        ("//", Int(_), Int(0)) | ("%", Int(_), Int(0)) => None,
+        ("//", Int(a), Int(b)) => {
+            let quotient = a.wrapping_div(*b);
+            let adjust = a.wrapping_rem(*b) != 0 && ((*a < 0) != (*b < 0));
+            Some(Int(if adjust { quotient - 1 } else { quotient }))
+        }
+        ("%", Int(a), Int(b)) => {
+            let remainder = a.wrapping_rem(*b);
+            let adjust = remainder != 0 && ((remainder < 0) != (*b < 0));
+            Some(Int(if adjust { remainder + b } else { remainder }))
+        }
+        _ => {
+            let (a, b) = (left.as_f64()?, right.as_f64()?);
+            Some(Float(match op {
+                "+" => a + b,
+                "-" => a - b,
+                "*" => a * b,
+                "/" => a / b,
+                "//" => (a / b).floor(),
+                "%" => {
+                    let remainder = a % b;
+                    if remainder != 0.0 && (remainder < 0.0) != (b < 0.0) {
+                        remainder + b
+                    } else {
+                        remainder
+                    }
+                }
+                "^" => a.powf(b),
+                _ => return None,
+            }))
+        }
+    }
+}
+
+fn equals(left: &Constant, right: &Constant) -> bool {
+    match (left.as_f64(), right.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => left == right,
+    }
+}
+
+fn compare(op: &str, ordering: std::cmp::Ordering) -> bool {
+    use std::cmp::Ordering::{Greater, Less};
+    match op {
+        "<" => ordering == Less,
+        "<=" => ordering != Greater,
+        ">" => ordering == Greater,
+        _ => ordering != Less,
+    }
+}
+
+/// Calls a whitelisted pure function.
+fn call(function: &str, args: &[Constant]) -> Option<Constant> {
+    use Constant::{Float, Int, Str};
+
+    let string_arg = |i: usize| args.get(i).and_then(Constant::as_str);
+    let int_arg = |i: usize| match args.get(i) {
+        Some(Int(n)) => Some(*n),
+        _ => None,
+    };
+
+    match function {
+        "string.upper" => Some(Str(string_arg(0)?.to_ascii_uppercase())),
+        "string.lower" => Some(Str(string_arg(0)?.to_ascii_lowercase())),
+        "string.len" => Some(Int(string_arg(0)?.len() as i64)),
+        "string.reverse" => {
+            let s = string_arg(0)?;
+            s.is_ascii().then(|| Str(s.chars().rev().collect()))
+        }
+        "string.rep" => {
+            let (s, n) = (string_arg(0)?, int_arg(1)?);
+            let sep = match args.get(2) {
+                Some(_) => string_arg(2)?,
+                None => String::new(),
+            };
+            if n <= 0 || (s.is_empty() && sep.is_empty()) {
+                return Some(Str(String::new()));
+            }
+            // Every repetition but the last adds at least one byte
+            if n > MAX_FOLDED_LEN as i64 + 1 {
+                return None;
+            }
+            let len = (s.len() + sep.len()).checked_mul(n as usize)?;
+            if len > MAX_FOLDED_LEN + sep.len() {
+                return None;
+            }
+            Some(Str(vec![s; n as usize].join(&sep)))
+        }
+        "string.sub" => {
+            let s = string_arg(0)?;
+            if !s.is_ascii() {
+                return None;
+            }
+            let len = s.len() as i64;
+            let start = match int_arg(1)? {
+                i if i < 0 => (len + i + 1).max(1),
+                0 => 1,
+                i => i,
+            };
+            let end = match args.get(2) {
+                None => len,
+                Some(_) => match int_arg(2)? {
+                    j if j < 0 => len + j + 1,
+                    j => j.min(len),
+                },
+            };
+            if start > end {
+                return Some(Str(String::new()));
+            }
+            Some(Str(s[(start - 1) as usize..end as usize].to_string()))
+        }
+        "math.abs" => match args.first()? {
+            Int(n) => Some(Int(n.wrapping_abs())),
+            Float(f) => Some(Float(f.abs())),
+            _ => None,
+        },
+        "math.floor" | "math.ceil" => match args.first()? {
+            Int(n) => Some(Int(*n)),
+            Float(f) => {
+                let rounded = if function == "math.floor" { f.floor() } else { f.ceil() };
+                // Lua returns an integer when the result fits
+                if rounded >= i64::MIN as f64 && rounded < i64::MAX as f64 {
+                    Some(Int(rounded as i64))
+                } else {
+                    Some(Float(rounded))
+                }
+            }
+            _ => None,
+        },
+        "math.max" | "math.min" => {
+            let mut best = args.first()?.clone();
+            best.as_f64()?; // numbers only
+            for arg in &args[1..] {
+                let (value, current) = (arg.as_f64()?, best.as_f64()?);
+                let better = if function == "math.max" { value > current } else { value < current };
+                if better {
+                    best = arg.clone();
+                }
+            }
+            Some(best)
+        }
+        "tostring" => args.first()?.to_lua_string().map(Str),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(source: &str) -> Option<String> {
+        evaluate(source, true).and_then(|value| value.to_lua_string())
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(text(r#"string.upper("menu") .. "!""#).as_deref(), Some("MENU!"));
+        assert_eq!(text("1 + 2 * 3"), Some("7".to_string()));
+        assert_eq!(text("2 ^ 3 ^ 0 .. ''"), None); // float result
+        assert_eq!(text("-7 // 2"), Some("-4".to_string()));
+        assert_eq!(text("-7 % 3"), Some("2".to_string()));
+        assert_eq!(text("math.floor(10 / 4)"), Some("2".to_string()));
+        assert_eq!(text("math.max(3, 9, 4)"), Some("9".to_string()));
+        assert_eq!(text(r#"string.rep("ab", 3, "-")"#).as_deref(), Some("ab-ab-ab"));
+        assert_eq!(text(r#"string.rep("", 999999999999)"#).as_deref(), Some(""));
+        assert_eq!(text(r#"string.sub("hello", -3)"#).as_deref(), Some("llo"));
+        assert_eq!(text(r#"#"abc" == 3 and "yes" or "no""#).as_deref(), Some("yes"));
+        assert_eq!(text("not nil"), Some("true".to_string()));
+        assert_eq!(text("[[\nlong]] .. 1"), Some("long1".to_string()));
+    }
+
+    #[test]
+    fn test_not_folded() {
+        for source in [
+            "props.title",
+            "string.upper(name)",
+            "os.time()",
+            "1 // 0",
+            r#""10" + 1"#,
+            "1 < 'x'",
+            r#"string.rep("x", 100000)"#,
+            r#"string.rep("", 100000, "-")"#,
+            r#""\65""#,
+            "1 | 2",
+        ] {
+            assert_eq!(text(source), None, "{}", source);
+        }
+        assert_eq!(evaluate("tostring(1)", false), None);
+    }
+
+    #[test]
+    fn test_fold_nodes() {
+        let ast = crate::parse_template(r#"<p title={"a" .. "&b"}>{string.lower("HI")}{nil}{props.x}</p>"#).unwrap();
+        let ir = crate::transform_ast(ast).unwrap();
+        let IRNode::ElementNode { attributes, children, .. } = &ir.body[0] else {
+            panic!("expected an element");
+        };
+        assert!(matches!(
+            &attributes[0],
+            IRAttribute::Named { value: IRAttributeValue::Static(v), .. } if v == "a&amp;b"
+        ));
+        assert_eq!(children.len(), 2);
+        assert!(matches!(&children[0], IRNode::TextNode { content } if content == "hi"));
+        assert!(matches!(&children[1], IRNode::MustacheNode { .. }));
+
+        let shadowed = r#"<script>local string = { upper = function() return "?" end }</script>{string.upper("a")}"#;
+        let ir = crate::transform_ast(crate::parse_template(shadowed).unwrap()).unwrap();
+        assert!(matches!(&ir.body[..], [IRNode::MustacheNode { .. }]));
+
+        let each = r#"{#each props.items as string}{string.upper("a")}{/each}"#;
+        let ir = crate::transform_ast(crate::parse_template(each).unwrap()).unwrap();
+        let IRNode::EachNode { body, .. } = &ir.body[0] else {
+            panic!("expected an each block");
+        };
+        assert!(matches!(&body[..], [IRNode::MustacheNode { .. }]));
+    }
+}
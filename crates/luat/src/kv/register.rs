@@ -100,7 +100,7 @@ fn create_namespace_table(lua: &Lua, store: Arc<dyn KVStore>) -> LuaResult<Table
                             }
                             Some(other) => {
                                 return Err(mlua::Error::runtime(format!(
-                                    "Unknown type hint: {}",
+                                    "Unknown type hint: {}. Expected 'text', 'json', or 'arrayBuffer'",
                                     other
                                 )))
                             }
@@ -404,6 +404,27 @@ mod tests {
         assert_eq!(age, 30);
     }
 
+    #[test]
+    fn test_binary_values() {
+        let lua = create_test_lua();
+
+        lua.load(
+            r#"
+            local kv = KV.namespace("test")
+            kv:put("image", "\137PNG\0\255\254")
+            result = kv:get("image", "arrayBuffer")
+            with_meta = kv:getWithMetadata("image", "arrayBuffer")
+        "#,
+        )
+        .exec()
+        .unwrap();
+
+        let result: mlua::String = lua.globals().get("result").unwrap();
+        assert_eq!(result.as_bytes().as_ref(), b"\x89PNG\0\xff\xfe");
+        let with_meta: mlua::String = lua.globals().get("with_meta").unwrap();
+        assert_eq!(with_meta.as_bytes().as_ref(), b"\x89PNG\0\xff\xfe");
+    }
+
     #[test]
     fn test_delete() {
         let lua = create_test_lua();
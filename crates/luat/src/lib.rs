@@ -43,18 +43,44 @@ pub mod ast;
 pub mod parser;
 /// AST to IR transformation.
 pub mod transform;
+/// Restricted expression grammar for template expressions.
+pub mod expression;
+/// Compile-time constant folding.
+mod fold;
+/// Build-time defines resolved when templates are compiled.
+pub mod defines;
+/// Whitespace collapsing for HTML templates.
+pub mod whitespace;
+/// Automatic component imports from configured directories.
+pub mod auto_import;
+/// Compile passes shared by every way a template is compiled.
+mod passes;
 /// Lua code generation.
 pub mod codegen;
+/// Scoped component styles.
+pub mod style;
+/// Compile warnings.
+pub mod warning;
+/// Accessibility lint rules.
+pub mod a11y;
+/// Custom block directives registered by the host.
+pub mod directives;
 /// Dependency graph analysis.
 pub mod dependencies;
 /// Main template engine.
 pub mod engine;
+/// Fluent engine construction.
+pub mod builder;
 /// Resource resolution (filesystem, memory).
 pub mod resolver;
 /// Error types and reporting.
 pub mod error;
 /// Compiled module caching.
 pub mod cache;
+/// Module lifecycle between requests.
+pub mod module_state;
+/// Component metadata of compiled templates.
+pub mod metadata;
 /// Lua runtime extensions.
 pub mod extensions;
 /// Script block processing.
@@ -75,16 +101,32 @@ pub mod kv;
 pub mod request;
 /// HTTP response abstraction for the engine.
 pub mod response;
+/// Escape audit records for raw output sites.
+pub mod audit;
+/// Email-safe rendering (CSS inlining, unsupported tag removal).
+pub mod email;
+/// Version and content stamp of production bundles.
+pub mod bundle_info;
 /// Shared request body parsing helpers.
 mod body;
 /// File-based routing for the engine.
 pub mod router;
 /// Runtime execution for server-side Lua code.
 pub mod runtime;
+/// Instruction and time limits for renders.
+mod render_limits;
+/// Sandbox profiles for the Lua state.
+pub mod sandbox;
+/// Pool of engines for rendering on several threads.
+#[cfg(all(not(target_arch = "wasm32"), feature = "send"))]
+pub mod pool;
 
 /// WASM bindings for browser usage.
 #[cfg(target_arch = "wasm32")]
 pub mod wasm;
+/// Origin Private File System cache for browser builds.
+#[cfg(all(target_arch = "wasm32", feature = "opfs"))]
+pub mod opfs_cache;
 
 #[cfg(test)]
 mod test_parsing;
@@ -97,17 +139,32 @@ pub use parser::*;
 pub use parser::Rule; // Explicitly export Rule enum
 pub use parser::LuatParser; // Explicitly export LuatParser
 pub use transform::*;
+pub use expression::{validate_expression, ExpressionError};
 pub use codegen::*;
+pub use directives::{BlockDirective, BlockDirectiveCall, BlockDirectives};
+pub use defines::Defines;
+pub use auto_import::ComponentDirs;
+pub use warning::CompileWarning;
 pub use dependencies::*;
 pub use engine::*;
+pub use builder::EngineBuilder;
 pub use resolver::*;
 pub use error::*;
 pub use cache::*;
+pub use module_state::ModuleState;
+pub use metadata::{referenced_props, ComponentImport, ComponentMetadata};
 pub use request::LuatRequest;
-pub use response::LuatResponse;
+pub use response::{encode_json_body, reason_phrase, LuatResponse};
+pub use audit::{EscapeAuditReport, RawOutputSite};
+pub use email::inline_email_html;
+pub use bundle_info::BundleInfo;
 pub use router::{Route, Router};
-pub use runtime::{ApiResult, LoadResult, Runtime};
+pub use sandbox::{SandboxOptions, SandboxProfile};
+pub use runtime::{ApiResult, ApiSpec, LoadResult, Runtime};
+#[cfg(all(not(target_arch = "wasm32"), feature = "send"))]
+pub use pool::{EnginePool, OwnedPooledEngine, PooledEngine};
 pub use extensions::register_json_module;
+pub use extensions::seo::PageHead;
 
 // Re-export mlua value
 pub use mlua::Value;
@@ -115,6 +172,8 @@ pub use mlua::Value;
 // Re-export WASM bindings when targeting WebAssembly
 #[cfg(target_arch = "wasm32")]
 pub use wasm::*;
+#[cfg(all(target_arch = "wasm32", feature = "opfs"))]
+pub use opfs_cache::{OpfsCache, DEFAULT_OPFS_DIRECTORY};
 
 #[cfg(test)]
 mod tests;
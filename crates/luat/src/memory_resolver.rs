@@ -352,6 +352,15 @@ impl ResourceResolver for MemoryResourceResolver {
     fn clone_box(&self) -> Box<dyn ResourceResolver> {
         Box::new(self.clone())
     }
+
+    fn list_resources(&self, dir: &str) -> Result<Vec<String>> {
+        let prefix = format!("{}/", dir.trim_end_matches('/'));
+        let mut paths: Vec<String> = self.with_templates_mut(|templates| {
+            templates.keys().filter(|path| path.starts_with(&prefix)).cloned().collect()
+        });
+        paths.sort();
+        Ok(paths)
+    }
 }
 
 #[cfg(test)]
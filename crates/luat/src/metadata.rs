@@ -0,0 +1,175 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Component metadata of compiled templates.
+//!
+//! Every [`Module`](crate::Module) compiled from a template carries the
+//! props it reads, the components it imports and the slots it renders, so
+//! documentation generators and editors don't have to parse it again:
+//!
+//! ```rust,ignore
+//! let module = engine.compile_entry("lib/components/Card.luat")?;
+//! for prop in &module.metadata.props {
+//!     println!("prop {}", prop);
+//! }
+//! ```
+
+use crate::transform::{IRNode, IR};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::OnceLock;
+
+/// What a template exposes to, and takes from, other templates.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComponentMetadata {
+    /// Props read through `props.<name>` or declared with `export let`,
+    /// other than slots, sorted.
+    pub props: Vec<String>,
+    /// Components imported with `{#use}` or `local Name = require(...)`
+    /// and used as tags, sorted by name.
+    pub imports: Vec<ComponentImport>,
+    /// Slots rendered with `{@render children()}` (as `children`) or
+    /// `{@render props.<name>()}`, sorted.
+    pub slots: Vec<String>,
+}
+
+/// A component a template imports.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ComponentImport {
+    /// Name the component is used under, e.g. `Button`.
+    pub name: String,
+    /// Module path it is imported from, e.g. `$lib/components/Button`.
+    pub path: String,
+}
+
+impl ComponentMetadata {
+    /// Collects the metadata of a template from its IR and source.
+    pub fn from_ir(ir: &IR, source: &str) -> Self {
+        static REQUIRE: OnceLock<Regex> = OnceLock::new();
+        let require = REQUIRE.get_or_init(|| {
+            Regex::new(r#"\blocal\s+([A-Za-z_][A-Za-z0-9_]*)\s*=\s*require\s*\(?\s*["']([^"']+)["']"#)
+                .expect("require pattern is valid")
+        });
+
+        let mut slots = BTreeSet::new();
+        collect_slots(&ir.body, &mut slots);
+
+        let props = referenced_props(source).into_iter().filter(|name| !slots.contains(name));
+
+        let mut imports: BTreeSet<ComponentImport> = ir
+            .uses
+            .iter()
+            .map(|import| ComponentImport { name: import.name.clone(), path: import.path.clone() })
+            .collect();
+        let scripts = [&ir.module_script, &ir.regular_script];
+        for script in scripts.into_iter().flatten() {
+            for captures in require.captures_iter(&script.content) {
+                if ir.components.contains(&captures[1]) {
+                    imports.insert(ComponentImport { name: captures[1].to_string(), path: captures[2].to_string() });
+                }
+            }
+        }
+
+        Self {
+            props: props.collect(),
+            imports: imports.into_iter().collect(),
+            slots: slots.into_iter().collect(),
+        }
+    }
+}
+
+/// Returns the prop names a template reads through `props.<name>` or
+/// declares with `export let <name>`, sorted.
+pub fn referenced_props(source: &str) -> Vec<String> {
+    static PROP: OnceLock<Regex> = OnceLock::new();
+    let prop = PROP.get_or_init(|| {
+        Regex::new(r"(?m)\bprops\.([A-Za-z_][A-Za-z0-9_]*)|^[ \t]*export[ \t]+let[ \t]+([A-Za-z_][A-Za-z0-9_]*)")
+            .expect("prop pattern is valid")
+    });
+    prop.captures_iter(source)
+        .filter_map(|c| c.get(1).or_else(|| c.get(2)).map(|m| m.as_str().to_string()))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+fn collect_slots(nodes: &[IRNode], slots: &mut BTreeSet<String>) {
+    for node in nodes {
+        match node {
+            IRNode::RenderChildren { .. } => {
+                slots.insert("children".to_string());
+            }
+            IRNode::RenderSnippet { callee, .. } => {
+                let name = callee.content.trim().strip_prefix("props.").unwrap_or_default();
+                if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    slots.insert(name.to_string());
+                }
+            }
+            IRNode::IfNode { then_branch, else_branch, .. } => {
+                collect_slots(then_branch, slots);
+                if let Some(else_nodes) = else_branch {
+                    collect_slots(else_nodes, slots);
+                }
+            }
+            IRNode::EachNode { body, empty, .. } => {
+                collect_slots(body, slots);
+                if let Some(empty_nodes) = empty {
+                    collect_slots(empty_nodes, slots);
+                }
+            }
+            IRNode::AwaitNode { pending, then_branch, catch_branch, .. } => {
+                collect_slots(pending, slots);
+                for branch in [then_branch, catch_branch].into_iter().flatten() {
+                    collect_slots(branch, slots);
+                }
+            }
+            IRNode::ElementNode { children, .. }
+            | IRNode::CustomBlock { children, .. }
+            | IRNode::Snippet { body: children, .. }
+            | IRNode::ComponentNode { children: Some(children), .. }
+            | IRNode::DynamicComponent { children: Some(children), .. } => collect_slots(children, slots),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_template, transform_ast};
+
+    #[test]
+    fn test_referenced_props() {
+        let source = r#"<div class={props.class}><h2>{props.title}</h2>{props.title}</div>"#;
+        assert_eq!(referenced_props(source), vec!["class", "title"]);
+    }
+
+    #[test]
+    fn test_component_metadata() {
+        let source = r#"{#use Icon from "$lib/icons/Icon"}
+<script>
+local Button = require("./Button.luat")
+local util = require("util")
+export let size = "md"
+</script>
+<article class={props.class}>
+  <header>{@render props.header?.()}</header>
+  <Icon name="x" />{@render children?.()}
+  {#if props.footer}<footer>{@render props.footer()}</footer>{/if}
+  <Button label={props.label} />
+</article>"#;
+        let ir = transform_ast(parse_template(source).unwrap()).unwrap();
+        let metadata = ComponentMetadata::from_ir(&ir, source);
+        assert_eq!(metadata.props, ["class", "label", "size"]);
+        assert_eq!(metadata.slots, ["children", "footer", "header"]);
+        assert_eq!(
+            metadata.imports,
+            [
+                ComponentImport { name: "Button".to_string(), path: "./Button.luat".to_string() },
+                ComponentImport { name: "Icon".to_string(), path: "$lib/icons/Icon".to_string() },
+            ]
+        );
+    }
+}
@@ -0,0 +1,249 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Module lifecycle between requests.
+//!
+//! A module is loaded once and its result kept in `package.loaded`, so
+//! tables it builds at load time are shared by every request the engine
+//! serves. A module whose load-time state must not leak from one request
+//! into the next says so in a comment:
+//!
+//! ```lua
+//! -- module_state = "per-request"
+//! local M = { visits = {} }
+//! return M
+//! ```
+//!
+//! Such a module is dropped from `package.loaded` (and the bundle's module
+//! cache) before each request, so the next `require` runs it again. Every
+//! other module, and one annotated `module_state = "shared"`, stays loaded.
+//! A bundle reads and compiles each module once: running a per-request
+//! module again reuses its compiled chunk.
+//!
+//! Which modules are per request, and the bundle's module cache, are kept
+//! in the Lua registry, out of reach of templates.
+//!
+//! With request isolation ([`crate::Engine::set_request_isolation`]), the
+//! globals are also put back as they were before each request, along with
+//! every table reachable from them (such as `string` or a `config` table):
+//! keys a request adds are removed and those it replaces restored. Only
+//! `package.loaded` is left alone.
+
+use mlua::{Lua, Table, Value};
+use regex::Regex;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+/// Registry key of the table whose keys are the module names loaded per
+/// request. Bundles get the table from the engine as a local of this name
+/// and mark their per-request modules in it.
+pub const PER_REQUEST_MODULES: &str = "__luat_per_request_modules";
+
+/// Registry key of the module cache of the preloaded bundle.
+pub(crate) const BUNDLE_MODULES: &str = "__luat_bundle_modules";
+
+/// How long a loaded module's state lives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ModuleState {
+    /// Loaded once and shared by all requests (the default).
+    #[default]
+    Shared,
+    /// Loaded again for every request that requires it.
+    PerRequest,
+}
+
+impl ModuleState {
+    /// Returns the state a module's source declares with a
+    /// `-- module_state = "per-request"|"shared"` comment, or
+    /// [`ModuleState::Shared`] without one.
+    pub fn of_source(source: &str) -> Self {
+        static ANNOTATION: OnceLock<Regex> = OnceLock::new();
+        let annotation = ANNOTATION.get_or_init(|| {
+            Regex::new(r#"(?m)^[ \t]*--[ \t]*module_state[ \t]*=[ \t]*["'](per-request|shared)["']"#)
+                .expect("module_state pattern is valid")
+        });
+        match annotation.captures(source) {
+            Some(captures) if &captures[1] == "per-request" => ModuleState::PerRequest,
+            _ => ModuleState::Shared,
+        }
+    }
+
+    /// Returns the annotation value, `"shared"` or `"per-request"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModuleState::Shared => "shared",
+            ModuleState::PerRequest => "per-request",
+        }
+    }
+}
+
+/// Returns the table of the module names loaded per request, creating it
+/// on first use.
+pub(crate) fn per_request_modules(lua: &Lua) -> mlua::Result<Table> {
+    if let Some(modules) = lua.named_registry_value::<Option<Table>>(PER_REQUEST_MODULES)? {
+        return Ok(modules);
+    }
+    let modules = lua.create_table()?;
+    lua.set_named_registry_value(PER_REQUEST_MODULES, &modules)?;
+    Ok(modules)
+}
+
+/// Returns a new module cache for a bundle about to be preloaded, replacing
+/// that of any earlier bundle.
+pub(crate) fn new_bundle_modules(lua: &Lua) -> mlua::Result<Table> {
+    let modules = lua.create_table()?;
+    lua.set_named_registry_value(BUNDLE_MODULES, &modules)?;
+    Ok(modules)
+}
+
+/// Records that the module `require`d as `name` is loaded per request.
+pub(crate) fn mark_per_request(lua: &Lua, name: &str) -> mlua::Result<()> {
+    per_request_modules(lua)?.raw_set(name, true)
+}
+
+/// Unloads the per-request modules, so the next `require` of one runs it
+/// again. Shared modules stay loaded.
+pub(crate) fn reset(lua: &Lua) -> mlua::Result<()> {
+    let Some(modules) = lua.named_registry_value::<Option<Table>>(PER_REQUEST_MODULES)? else {
+        return Ok(());
+    };
+    let loaded: Table = lua.globals().get::<Table>("package")?.get("loaded")?;
+    let bundle_modules = lua.named_registry_value::<Option<Table>>(BUNDLE_MODULES)?;
+    for pair in modules.pairs::<String, Value>() {
+        let (name, _) = pair?;
+        loaded.raw_set(name.as_str(), Value::Nil)?;
+        if let Some(bundle_modules) = &bundle_modules {
+            bundle_modules.raw_set(name.as_str(), Value::Nil)?;
+        }
+    }
+    Ok(())
+}
+
+/// The globals as they were before a request, see [`restore_globals`]: every
+/// table reachable from them with a copy of its contents, globals first.
+pub(crate) struct GlobalsSnapshot(Vec<(Table, Table)>);
+
+/// Copies the globals and every table reachable from them, except
+/// `package.loaded`.
+pub(crate) fn snapshot_globals(lua: &Lua) -> mlua::Result<GlobalsSnapshot> {
+    let globals = lua.globals();
+    let mut seen = HashSet::new();
+    let loaded = globals
+        .raw_get::<Option<Table>>("package")?
+        .map(|package| package.raw_get::<Option<Table>>("loaded"))
+        .transpose()?
+        .flatten();
+    if let Some(loaded) = loaded {
+        seen.insert(loaded.to_pointer());
+    }
+
+    seen.insert(globals.to_pointer());
+    let mut pending = vec![globals];
+    let mut saved = Vec::new();
+    while let Some(table) = pending.pop() {
+        let copy = lua.create_table()?;
+        for pair in table.pairs::<Value, Value>() {
+            let (key, value) = pair?;
+            if let Value::Table(child) = &value {
+                if seen.insert(child.to_pointer()) {
+                    pending.push(child.clone());
+                }
+            }
+            copy.raw_set(key, value)?;
+        }
+        saved.push((table, copy));
+    }
+    Ok(GlobalsSnapshot(saved))
+}
+
+/// Removes the keys added since `snapshot` and restores those replaced, in
+/// the globals and every table reachable from them at the time.
+pub(crate) fn restore_globals(snapshot: GlobalsSnapshot) -> mlua::Result<()> {
+    for (table, copy) in snapshot.0 {
+        let mut added = Vec::new();
+        for pair in table.pairs::<Value, Value>() {
+            let (key, _) = pair?;
+            if copy.raw_get::<Value>(key.clone())?.is_nil() {
+                added.push(key);
+            }
+        }
+        for key in added {
+            table.raw_set(key, Value::Nil)?;
+        }
+        for pair in copy.pairs::<Value, Value>() {
+            let (key, value) = pair?;
+            table.raw_set(key, value)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_state_annotation() {
+        assert_eq!(ModuleState::of_source("return {}"), ModuleState::Shared);
+        assert_eq!(
+            ModuleState::of_source("-- module_state = \"per-request\"\nreturn {}"),
+            ModuleState::PerRequest
+        );
+        assert_eq!(
+            ModuleState::of_source("local x = 1\n  --module_state='per-request'\nreturn x"),
+            ModuleState::PerRequest
+        );
+        assert_eq!(ModuleState::of_source("-- module_state = \"shared\""), ModuleState::Shared);
+        assert_eq!(
+            ModuleState::of_source("local s = '-- module_state = \"per-request\"'"),
+            ModuleState::Shared
+        );
+    }
+
+    #[test]
+    fn test_reset_unloads_per_request_modules() {
+        let lua = Lua::new();
+        lua.load("package.loaded.counter = {}; package.loaded.config = {}").exec().unwrap();
+        mark_per_request(&lua, "counter").unwrap();
+        // Templates can't reach the marks
+        lua.load("__luat_per_request_modules = { config = true }").exec().unwrap();
+        reset(&lua).unwrap();
+        let (counter, config): (bool, bool) = lua
+            .load("return package.loaded.counter == nil, package.loaded.config ~= nil")
+            .eval()
+            .unwrap();
+        assert!(counter && config);
+    }
+
+    #[test]
+    fn test_restore_globals() {
+        let lua = Lua::new();
+        lua.load("site = 'docs'; config = { x = 0 }; __luat_current_module = 'a'").exec().unwrap();
+        let snapshot = snapshot_globals(&lua).unwrap();
+        lua.load(
+            r#"site = 'blog'; user = 'ada'; print = nil; __luat_current_module = 'b'
+            config.x = 1; string.foo = function() end
+            package.loaded.fresh = {}; __luat_per_request_modules = { fresh = true }"#,
+        )
+        .exec()
+        .unwrap();
+        restore_globals(snapshot).unwrap();
+
+        let (site, user, print, current): (String, Value, bool, String) = lua
+            .load("return site, user, print ~= nil, __luat_current_module")
+            .eval()
+            .unwrap();
+        assert_eq!(site, "docs");
+        assert!(user.is_nil());
+        assert!(print);
+        assert_eq!(current, "a");
+        let (x, foo, fresh, marks): (i64, Value, bool, Value) = lua
+            .load("return config.x, ('').foo, package.loaded.fresh ~= nil, __luat_per_request_modules")
+            .eval()
+            .unwrap();
+        assert_eq!(x, 0);
+        assert!(foo.is_nil());
+        assert!(fresh && marks.is_nil());
+    }
+}
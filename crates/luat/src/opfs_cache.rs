@@ -0,0 +1,359 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Origin Private File System cache for browser builds.
+//!
+//! [`OpfsCache`] persists compiled modules in the browser's Origin Private
+//! File System, so playgrounds that compile many templates don't recompile
+//! them on every visit. Modules are stored with [`Module::store`]'s
+//! versioned format, one file per cache key.
+//!
+//! Only the content-hashed `source:` entries are persisted: they stay valid
+//! as long as the source does. Path-keyed `module:` entries would serve a
+//! stale module after the template changes between visits, so they live in
+//! the memory layer only.
+//!
+//! OPFS is asynchronous while [`Cache`] is not: the cache keeps an in-memory
+//! LRU layer that answers synchronous lookups, [`OpfsCache::preload`] fills it
+//! from disk, and synchronous writes are persisted in the background.
+//! [`AsyncCache`] operations go to OPFS directly.
+//!
+//! Only available on `wasm32` builds with the `opfs` feature.
+//!
+//! # Example (JavaScript)
+//!
+//! ```javascript
+//! import { WasmEngine } from 'luat';
+//!
+//! // Modules compiled in earlier sessions are loaded from OPFS
+//! const engine = await WasmEngine.withOpfsCache(200);
+//! ```
+
+#![cfg(all(target_arch = "wasm32", feature = "opfs"))]
+
+use crate::cache::{AsyncCache, Cache, CacheFuture, MemoryCache, Module, SharedPtr};
+use crate::error::{LuatError, Result};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{FileSystemDirectoryHandle, FileSystemFileHandle, FileSystemWritableFileStream};
+
+/// Directory used by [`OpfsCache::open_default`].
+pub const DEFAULT_OPFS_DIRECTORY: &str = "luat-cache";
+
+/// Prefix of the content-hashed cache keys, the only ones persisted.
+const PERSISTED_KEY_PREFIX: &str = "source:";
+
+fn is_persisted(key: &str) -> bool {
+    key.starts_with(PERSISTED_KEY_PREFIX)
+}
+
+/// A cache file: the module plus the key it was stored under, which the
+/// sanitized file name can't be turned back into.
+#[derive(serde::Deserialize)]
+struct Entry {
+    key: String,
+    module: Module,
+}
+
+/// Persistent cache backed by the Origin Private File System.
+///
+/// Stores compiled modules in an OPFS directory, with an in-memory LRU
+/// layer for synchronous access.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use luat::OpfsCache;
+///
+/// let cache = OpfsCache::open("luat-cache", 200).await?;
+/// cache.preload().await?;
+/// let engine = Engine::new(resolver, Box::new(cache))?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct OpfsCache {
+    directory: FileSystemDirectoryHandle,
+    memory_cache: MemoryCache,
+}
+
+impl OpfsCache {
+    /// Opens (creating if needed) the OPFS directory `directory`.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - Name of the directory in the origin's file system
+    /// * `memory_capacity` - Size of in-memory LRU layer
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if OPFS is not available (e.g. outside a secure
+    /// context) or the directory cannot be created.
+    pub async fn open(directory: &str, memory_capacity: usize) -> Result<Self> {
+        let root: FileSystemDirectoryHandle = await_promise(storage_manager()?.get_directory(), "open OPFS")
+            .await?
+            .unchecked_into();
+
+        let options = web_sys::FileSystemGetDirectoryOptions::new();
+        options.set_create(true);
+        let directory = await_promise(
+            root.get_directory_handle_with_options(directory, &options),
+            "create cache directory",
+        )
+        .await?
+        .unchecked_into();
+
+        Ok(Self {
+            directory,
+            memory_cache: MemoryCache::try_new(memory_capacity)?,
+        })
+    }
+
+    /// Opens the cache in [`DEFAULT_OPFS_DIRECTORY`].
+    pub async fn open_default(memory_capacity: usize) -> Result<Self> {
+        Self::open(DEFAULT_OPFS_DIRECTORY, memory_capacity).await
+    }
+
+    /// Loads persisted modules into the memory layer, up to its capacity,
+    /// and returns how many were loaded.
+    ///
+    /// Files that can't be read, or were written by an incompatible luat
+    /// version, are removed.
+    pub async fn preload(&self) -> Result<usize> {
+        let mut loaded = 0;
+        for name in self.file_names().await? {
+            match self.read_file(&name).await {
+                Ok(Some(entry)) if is_persisted(&entry.key) => {
+                    self.memory_cache.set(&entry.key, SharedPtr::new(entry.module))?;
+                    loaded += 1;
+                }
+                // Left by versions that persisted path-keyed entries
+                Ok(Some(_)) => {
+                    let _ = self.delete_file(&name).await;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::warn!("Discarding unreadable cache file '{}': {}", name, e);
+                    let _ = self.delete_file(&name).await;
+                }
+            }
+        }
+        Ok(loaded)
+    }
+
+    fn file_name(key: &str) -> String {
+        let safe_key = key.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_");
+        format!("{}.json", safe_key)
+    }
+
+    async fn file_names(&self) -> Result<Vec<String>> {
+        let keys = self.directory.keys();
+        let mut names = Vec::new();
+        loop {
+            let next = keys.next().map_err(|e| js_error("list cache directory", e))?;
+            let next: js_sys::IteratorNext = await_promise(next, "list cache directory").await?.unchecked_into();
+            if next.done() {
+                break;
+            }
+            if let Some(name) = next.value().as_string() {
+                if name.ends_with(".json") {
+                    names.push(name);
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    async fn read_file(&self, name: &str) -> Result<Option<Entry>> {
+        let handle = match JsFuture::from(self.directory.get_file_handle(name)).await {
+            Ok(handle) => handle.unchecked_into::<FileSystemFileHandle>(),
+            Err(e) if is_not_found(&e) => return Ok(None),
+            Err(e) => return Err(js_error("open cache file", e)),
+        };
+        let file: web_sys::File = await_promise(handle.get_file(), "read cache file").await?.unchecked_into();
+        let text = await_promise(file.text(), "read cache file")
+            .await?
+            .as_string()
+            .unwrap_or_default();
+        serde_json::from_str(&text)
+            .map(Some)
+            .map_err(|e| LuatError::CacheError(format!("Failed to load module: {}", e)))
+    }
+
+    async fn write_file(&self, key: &str, module: &Module) -> Result<()> {
+        let json = serde_json::to_string(&EntryRef { key, module })
+            .map_err(|e| LuatError::CacheError(format!("Failed to store module: {}", e)))?;
+
+        let options = web_sys::FileSystemGetFileOptions::new();
+        options.set_create(true);
+        let handle: FileSystemFileHandle = await_promise(
+            self.directory.get_file_handle_with_options(&Self::file_name(key), &options),
+            "create cache file",
+        )
+        .await?
+        .unchecked_into();
+        let stream: FileSystemWritableFileStream = await_promise(handle.create_writable(), "write cache file")
+            .await?
+            .unchecked_into();
+        let written = stream.write_with_str(&json).map_err(|e| js_error("write cache file", e))?;
+        await_promise(written, "write cache file").await?;
+        // The file is only replaced once the stream is closed
+        await_promise(stream.close(), "write cache file").await?;
+        Ok(())
+    }
+
+    async fn delete_file(&self, name: &str) -> Result<()> {
+        match JsFuture::from(self.directory.remove_entry(name)).await {
+            Ok(_) => Ok(()),
+            Err(e) if is_not_found(&e) => Ok(()),
+            Err(e) => Err(js_error("remove cache file", e)),
+        }
+    }
+
+    async fn clear_files(&self) -> Result<()> {
+        for name in self.file_names().await? {
+            self.delete_file(&name).await?;
+        }
+        Ok(())
+    }
+
+    /// Runs `task` in the background, logging failures.
+    fn persist<F>(task: F)
+    where
+        F: std::future::Future<Output = Result<()>> + 'static,
+    {
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Err(e) = task.await {
+                tracing::warn!("Failed to update OPFS cache: {}", e);
+            }
+        });
+    }
+}
+
+/// Borrowed form of [`Entry`] for writing.
+#[derive(serde::Serialize)]
+struct EntryRef<'a> {
+    key: &'a str,
+    module: &'a Module,
+}
+
+// Synchronous access goes through the memory layer; writes reach OPFS in the background
+impl Cache for OpfsCache {
+    fn get(&self, key: &str) -> Result<Option<SharedPtr<Module>>> {
+        self.memory_cache.get(key)
+    }
+
+    fn set(&self, key: &str, module: SharedPtr<Module>) -> Result<()> {
+        self.memory_cache.set(key, module.clone())?;
+        if !is_persisted(key) {
+            return Ok(());
+        }
+        let cache = self.clone();
+        let key = key.to_string();
+        Self::persist(async move { cache.write_file(&key, &module).await });
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<()> {
+        self.memory_cache.remove(key)?;
+        let cache = self.clone();
+        let name = Self::file_name(key);
+        Self::persist(async move { cache.delete_file(&name).await });
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.memory_cache.clear()?;
+        let cache = self.clone();
+        Self::persist(async move { cache.clear_files().await });
+        Ok(())
+    }
+
+    fn contains_key(&self, key: &str) -> bool {
+        self.memory_cache.contains_key(key)
+    }
+
+    fn clone_box(&self) -> Box<dyn Cache> {
+        Box::new(self.clone())
+    }
+}
+
+impl AsyncCache for OpfsCache {
+    fn get_async<'a>(&'a self, key: &'a str) -> CacheFuture<'a, Option<SharedPtr<Module>>> {
+        Box::pin(async move {
+            if let Some(module) = self.memory_cache.get(key)? {
+                return Ok(Some(module));
+            }
+            if !is_persisted(key) {
+                return Ok(None);
+            }
+            match self.read_file(&Self::file_name(key)).await? {
+                // Sanitized names can collide; the stored key decides
+                Some(entry) if entry.key == key => {
+                    let module = SharedPtr::new(entry.module);
+                    self.memory_cache.set(key, module.clone())?;
+                    Ok(Some(module))
+                }
+                _ => Ok(None),
+            }
+        })
+    }
+
+    fn set_async<'a>(&'a self, key: &'a str, module: SharedPtr<Module>) -> CacheFuture<'a, ()> {
+        Box::pin(async move {
+            self.memory_cache.set(key, module.clone())?;
+            if !is_persisted(key) {
+                return Ok(());
+            }
+            self.write_file(key, &module).await
+        })
+    }
+
+    fn remove_async<'a>(&'a self, key: &'a str) -> CacheFuture<'a, ()> {
+        Box::pin(async move {
+            self.memory_cache.remove(key)?;
+            self.delete_file(&Self::file_name(key)).await
+        })
+    }
+
+    fn clear_async(&self) -> CacheFuture<'_, ()> {
+        Box::pin(async move {
+            self.memory_cache.clear()?;
+            self.clear_files().await
+        })
+    }
+}
+
+/// Returns the storage manager of the window or worker we run in.
+fn storage_manager() -> Result<web_sys::StorageManager> {
+    let global = js_sys::global();
+    let navigator = js_sys::Reflect::get(&global, &JsValue::from_str("navigator")).unwrap_or(JsValue::UNDEFINED);
+    let storage = js_sys::Reflect::get(&navigator, &JsValue::from_str("storage")).unwrap_or(JsValue::UNDEFINED);
+    let supported = !storage.is_undefined()
+        && js_sys::Reflect::has(&storage, &JsValue::from_str("getDirectory")).unwrap_or(false);
+    if !supported {
+        return Err(LuatError::CacheError(
+            "Origin Private File System is not available in this context".to_string(),
+        ));
+    }
+    Ok(storage.unchecked_into())
+}
+
+async fn await_promise(promise: js_sys::Promise, action: &str) -> Result<JsValue> {
+    JsFuture::from(promise).await.map_err(|e| js_error(action, e))
+}
+
+fn is_not_found(error: &JsValue) -> bool {
+    js_sys::Reflect::get(error, &JsValue::from_str("name"))
+        .ok()
+        .and_then(|name| name.as_string())
+        .is_some_and(|name| name == "NotFoundError")
+}
+
+fn js_error(action: &str, error: JsValue) -> LuatError {
+    let message = error
+        .dyn_ref::<js_sys::Error>()
+        .map(|e| String::from(e.message()))
+        .or_else(|| error.as_string())
+        .unwrap_or_else(|| format!("{:?}", error));
+    LuatError::CacheError(format!("Failed to {}: {}", action, message))
+}
@@ -23,7 +23,8 @@
 //! - Components: `<Button>`, `<Card>` (capitalized names)
 //! - Mustache expressions: `{expression}`
 //! - Control flow: `{#if}`, `{#each}`, `{:else}`, etc.
-//! - Directives: `{@html}`, `{@local}`, `{@render}`
+//! - Directives: `{@html}`, `{@const}` / `{@local}`, `{@render}`
+//! - Snippets: `{#snippet name(params)}...{/snippet}`, rendered with `{@render name(args)}`
 //! - Scripts: `<script>` and `<script context="module">`
 
 use crate::ast::*;
@@ -108,6 +109,8 @@ fn extract_lua_dependencies(script_content: &str) -> Vec<String> {
 /// assert_eq!(ast.body.len(), 1);
 /// ```
 pub fn parse_template(source: &str) -> Result<TemplateAST> {
+    // A byte order mark left by some editors is not template content
+    let source = source.strip_prefix('\u{feff}').unwrap_or(source);
     // Keep original signature for now, path is set in engine
     let pairs = LuatParser::parse(Rule::template, source).map_err(|e| {
         let (line, col) = match e.line_col {
@@ -260,6 +263,29 @@ pub fn parse_template(source: &str) -> Result<TemplateAST> {
                                         ast.regular_script = Some(script_block);
                                         has_regular_script = true;
                                     }
+                                    Rule::use_directive => {
+                                        let (line, column) = content_pair.as_span().start_pos().line_col();
+                                        let markup_before = ast.body.iter().any(|node| {
+                                            !matches!(node, Node::TextNode { content } if content.trim().is_empty())
+                                        });
+                                        if markup_before {
+                                            return Err(create_parse_error(
+                                                "`{#use}` must come before the template markup",
+                                                line,
+                                                column,
+                                            ));
+                                        }
+                                        let import = parse_use_directive(content_pair);
+                                        if ast.uses.iter().any(|u| u.name == import.name) {
+                                            return Err(create_parse_error(
+                                                format!("`{}` is imported twice with `{{#use}}`", import.name),
+                                                line,
+                                                column,
+                                            ));
+                                        }
+                                        ast.imports.push(import.path.clone());
+                                        ast.uses.push(import);
+                                    }
                                     Rule::template_node => {
                                         let node = parse_template_node(content_pair)?;
                                         ast.body.push(node);
@@ -301,37 +327,92 @@ pub fn parse_template(source: &str) -> Result<TemplateAST> {
         }
     }
 
+    apply_mode_directive(&mut ast, source)?;
+
     Ok(ast)
 }
 
+/// Takes a leading `<!-- luat:mode xml -->` comment out of the body and
+/// records the output mode it names.
+///
+/// Whitespace after the directive is dropped too, so an XML declaration that
+/// follows it still starts the output.
+fn apply_mode_directive(ast: &mut TemplateAST, source: &str) -> Result<()> {
+    let Some(index) = ast
+        .body
+        .iter()
+        .position(|node| !matches!(node, Node::TextNode { content } if content.trim().is_empty()))
+    else {
+        return Ok(());
+    };
+    let name = match &ast.body[index] {
+        Node::HtmlComment { children } => match children.as_slice() {
+            [Node::TextNode { content }] => match content.trim().strip_prefix("luat:mode") {
+                Some(name) => name.trim().to_string(),
+                None => return Ok(()),
+            },
+            _ => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+
+    let mode = OutputMode::from_name(&name).ok_or_else(|| {
+        let offset = source.find("luat:mode").unwrap_or(0);
+        let line = source[..offset].matches('\n').count() + 1;
+        let column = offset - source[..offset].rfind('\n').map_or(0, |i| i + 1) + 1;
+        create_parse_error(
+            format!("Unknown output mode '{}' (expected html, xml or text)", name),
+            line,
+            column,
+        )
+    })?;
+
+    ast.body.drain(..=index);
+    if let Some(Node::TextNode { content }) = ast.body.first_mut() {
+        let trimmed = content.trim_start().to_string();
+        if trimmed.is_empty() {
+            ast.body.remove(0);
+        } else {
+            *content = trimmed;
+        }
+    }
+    ast.mode = Some(mode);
+    Ok(())
+}
+
 fn parse_script_block(
     pair: pest::iterators::Pair<Rule>,
     script_type: ScriptType,
 ) -> Result<ScriptBlock> {
     let span = pair_to_span(&pair);
-    let content = extract_script_content(pair)?;
+    let (content, content_line) = extract_script_content(pair)?;
 
     Ok(ScriptBlock {
         script_type,
         content,
         span,
+        content_line,
     })
 }
 
-fn extract_script_content(script_pair: pest::iterators::Pair<Rule>) -> Result<String> {
+/// Returns the trimmed script content and the line it starts on.
+fn extract_script_content(script_pair: pest::iterators::Pair<Rule>) -> Result<(String, usize)> {
     let span = script_pair.as_span(); // Capture span before moving script_pair
 
     for pair in script_pair.into_inner() {
         if pair.as_rule() == Rule::script_content {
             // Extract the raw content between the script tags
-            let content = pair.as_str().trim().to_string();
+            let raw = pair.as_str();
+            let content = raw.trim().to_string();
+            let skipped = &raw[..raw.len() - raw.trim_start().len()];
+            let content_line = pair.as_span().start_pos().line_col().0 + skipped.matches('\n').count();
 
             // Process the script content using AST-based parsing
             let processed_content = parse_lua_script_with_magic(&content)?;
 
             // We don't parse the content here - we trust that the Lua runtime will handle it
             // This allows string literals containing </script> to work correctly
-            return Ok(processed_content);
+            return Ok((processed_content, content_line));
         }
     }
 
@@ -510,21 +591,37 @@ fn parse_node(pair: pest::iterators::Pair<Rule>) -> Result<Node> {
             let content = pair.into_inner().as_str().to_string();
             Ok(Node::ScriptAny { tag, content })
         }
+        Rule::style_block => parse_style_block(pair),
+        // `<?xml ... ?>` and other processing instructions are output as-is
+        Rule::processing_instruction => Ok(Node::TextNode {
+            content: pair.as_str().to_string(),
+        }),
         Rule::luat_text => Ok(Node::TextNode {
             // Unescape \{ and \} to literal { and }
             content: pair.as_str().replace("\\{", "{").replace("\\}", "}"),
         }),
+        // `{#raw}...{/raw}` content is output as written
+        Rule::raw_block => Ok(Node::TextNode {
+            content: pair.into_inner().next().map(|p| p.as_str().to_string()).unwrap_or_default(),
+        }),
         Rule::mustache => parse_mustache(pair),
         Rule::raw_html => parse_raw_html(pair),
+        Rule::markdown_tag => parse_markdown_tag(pair),
         Rule::local_const => parse_local_const(pair),
-        Rule::render_children => parse_render_children(pair),
+        Rule::render_tag => parse_render_tag(pair),
+        Rule::render_block => parse_render_block(pair),
+        Rule::match_block => parse_match_block(pair),
         Rule::html_comment => parse_html_comment(pair),
         Rule::luat_comment => Ok(Node::LuatComment),
         Rule::luat_line_comment => Ok(Node::LuatComment),
+        Rule::server_comment => Ok(Node::LuatComment),
         Rule::if_block => parse_if_block(pair, false),
         Rule::sensitive_if_block => parse_if_block(pair, true),
         Rule::each_block => parse_each_block(pair, false),
         Rule::sensitive_each_block => parse_each_block(pair, true),
+        Rule::await_block => parse_await_block(pair),
+        Rule::snippet_block => parse_snippet_block(pair),
+        Rule::custom_block => parse_custom_block(pair),
         Rule::element_or_component_node => parse_element_or_component_node(pair),
         _ => Err(LuatError::ParseError {
             message: format!("Unexpected rule: {:?}", pair.as_rule()),
@@ -536,6 +633,25 @@ fn parse_node(pair: pest::iterators::Pair<Rule>) -> Result<Node> {
     }
 }
 
+fn parse_style_block(pair: pest::iterators::Pair<Rule>) -> Result<Node> {
+    let mut attributes = Vec::new();
+    let mut content = String::new();
+    for inner_pair in pair.into_inner() {
+        match inner_pair.as_rule() {
+            Rule::attributes => {
+                for attr_pair in inner_pair.into_inner() {
+                    if let Rule::attribute = attr_pair.as_rule() {
+                        attributes.push(parse_attribute(attr_pair)?);
+                    }
+                }
+            }
+            Rule::style_content => content = inner_pair.as_str().to_string(),
+            _ => {}
+        }
+    }
+    Ok(Node::StyleBlock { attributes, content })
+}
+
 fn parse_mustache(pair: pest::iterators::Pair<Rule>) -> Result<Node> {
     let span = pair.as_span();
     for inner_pair in pair.into_inner() {
@@ -572,12 +688,44 @@ fn parse_raw_html(pair: pest::iterators::Pair<Rule>) -> Result<Node> {
     })
 }
 
+/// Parses `{@markdown expr}` into `{@html __markdown.render(expr)}`, where
+/// `__markdown` is the module captured when the template loads, so a
+/// template's own `markdown` doesn't shadow it.
+fn parse_markdown_tag(pair: pest::iterators::Pair<Rule>) -> Result<Node> {
+    let span = pair.as_span();
+    if cfg!(not(feature = "markdown")) {
+        return Err(LuatError::ParseError {
+            message: "{@markdown} needs luat built with the `markdown` feature".to_string(),
+            line: span.start_pos().line_col().0,
+            column: span.start_pos().line_col().1,
+            file: None,
+            source_context: None,
+        });
+    }
+    match pair.into_inner().find(|p| p.as_rule() == Rule::expr) {
+        Some(expr) => Ok(Node::RawHtml {
+            expression: Expression::new(format!("__markdown.render({})", expr.as_str().trim()), pair_to_span(&expr)),
+        }),
+        None => Err(LuatError::ParseError {
+            message: "Invalid {@markdown} expression".to_string(),
+            line: span.start_pos().line_col().0,
+            column: span.start_pos().line_col().1,
+            file: None,
+            source_context: None,
+        }),
+    }
+}
+
 fn parse_local_const(pair: pest::iterators::Pair<Rule>) -> Result<Node> {
     let span = pair.as_span();
+    let mut tag = "local".to_string();
     let mut name = None;
     let mut expr = None;
     for inner_pair in pair.into_inner() {
         match inner_pair.as_rule() {
+            Rule::local_const_tag => {
+                tag = inner_pair.as_str().to_string();
+            }
             Rule::ident => {
                 name = Some(inner_pair.as_str().to_string());
             }
@@ -588,10 +736,10 @@ fn parse_local_const(pair: pest::iterators::Pair<Rule>) -> Result<Node> {
         }
     }
     if let (Some(name), Some(expression)) = (name, expr) {
-        Ok(Node::LocalConst { name, expression })
+        Ok(Node::LocalConst { name, expression, tag })
     } else {
         Err(LuatError::ParseError {
-            message: "Invalid {@local} syntax".to_string(),
+            message: format!("Invalid {{@{}}} syntax", tag),
             line: span.start_pos().line_col().0,
             column: span.start_pos().line_col().1,
             file: None,
@@ -600,9 +748,257 @@ fn parse_local_const(pair: pest::iterators::Pair<Rule>) -> Result<Node> {
     }
 }
 
-fn parse_render_children(pair: pest::iterators::Pair<Rule>) -> Result<Node> {
-    let optional = pair.as_str().contains("?");
-    Ok(Node::RenderChildren { optional })
+fn parse_render_tag(pair: pest::iterators::Pair<Rule>) -> Result<Node> {
+    let (line, column) = pair.as_span().start_pos().line_col();
+    let invalid = || LuatError::ParseError {
+        message: "Invalid {@render} syntax, expected {@render name(args)}".to_string(),
+        line,
+        column,
+        file: None,
+        source_context: None,
+    };
+
+    let call_pair = pair.into_inner().next().ok_or_else(invalid)?;
+    let span = pair_to_span(&call_pair);
+    let call = call_pair.as_str().trim();
+    let open = call_open_paren(call).ok_or_else(invalid)?;
+
+    let mut callee = call[..open].trim_end();
+    let mut optional = false;
+    if let Some(stripped) = callee.strip_suffix("?.").or_else(|| callee.strip_suffix('?')) {
+        callee = stripped.trim_end();
+        optional = true;
+    }
+    if callee.is_empty() {
+        return Err(invalid());
+    }
+    let args = call[open + 1..call.len() - 1].trim();
+
+    // `{@render children()}` renders the children passed to the component
+    if args.is_empty() && (callee == "children" || callee == "props.children") {
+        return Ok(Node::RenderChildren { optional });
+    }
+
+    Ok(Node::RenderSnippet {
+        callee: Expression::new(callee, span.clone()),
+        args: (!args.is_empty()).then(|| Expression::new(args, span)),
+        optional,
+    })
+}
+
+/// Parses `{@render call}{:fallback}...{/render}` into an `{#if}` that
+/// renders the fallback when the snippet is nil.
+fn parse_render_block(pair: pest::iterators::Pair<Rule>) -> Result<Node> {
+    let span = pair_to_span(&pair);
+    let mut render = None;
+    let mut fallback = Vec::new();
+    for inner_pair in pair.into_inner() {
+        match inner_pair.as_rule() {
+            Rule::render_tag => render = Some(parse_render_tag(inner_pair)?),
+            Rule::template_node => fallback.push(parse_node(inner_pair)?),
+            _ => {}
+        }
+    }
+    let (condition, render) = match render {
+        Some(Node::RenderChildren { .. }) => (
+            Expression::new("props.children", span),
+            Node::RenderChildren { optional: true },
+        ),
+        Some(Node::RenderSnippet { callee, args, .. }) => (
+            callee.clone(),
+            Node::RenderSnippet { callee, args, optional: true },
+        ),
+        _ => unreachable!("render_block starts with a render_tag"),
+    };
+    Ok(Node::IfBlock {
+        condition,
+        then_branch: vec![render],
+        else_branch: Some(fallback),
+    })
+}
+
+/// Local the `{#match}` value is bound to, so it is evaluated once.
+const MATCH_VALUE: &str = "__luat_match";
+
+/// Parses `{#match value}{:case a, b}...{:default}...{/match}` into an
+/// `{#if}` chain comparing the value, bound to a local, with each case.
+fn parse_match_block(pair: pest::iterators::Pair<Rule>) -> Result<Node> {
+    let span = pair_to_span(&pair);
+    let mut subject = None;
+    let mut cases: Vec<(Expression, Vec<Node>)> = Vec::new();
+    let mut default: Option<Vec<Node>> = None;
+    for inner_pair in pair.into_inner() {
+        match inner_pair.as_rule() {
+            Rule::match_start | Rule::match_case => {
+                let is_start = inner_pair.as_rule() == Rule::match_start;
+                let Some(expr_pair) = inner_pair.into_inner().find(|p| p.as_rule() == Rule::expr) else {
+                    continue;
+                };
+                let expr = Expression::new(expr_pair.as_str().trim(), pair_to_span(&expr_pair));
+                if is_start {
+                    subject = Some(expr);
+                } else {
+                    let condition = split_top_level_commas(&expr.content)
+                        .into_iter()
+                        .map(|value| format!("{} == ({})", MATCH_VALUE, value))
+                        .collect::<Vec<_>>()
+                        .join(" or ");
+                    cases.push((Expression::new(condition, expr.span), Vec::new()));
+                }
+            }
+            Rule::match_default => default = Some(Vec::new()),
+            Rule::match_end => break,
+            _ => {
+                let node_span = pair_to_span(&inner_pair);
+                let node = parse_node(inner_pair)?;
+                match (&mut default, cases.last_mut()) {
+                    (Some(nodes), _) | (None, Some((_, nodes))) => nodes.push(node),
+                    (None, None) => match node {
+                        Node::TextNode { content } if content.trim().is_empty() => {}
+                        Node::LuatComment => {}
+                        _ => {
+                            return Err(LuatError::ParseError {
+                                message: "Content before the first {:case} in {#match} block".to_string(),
+                                line: node_span.line,
+                                column: node_span.column,
+                                file: None,
+                                source_context: None,
+                            })
+                        }
+                    },
+                }
+            }
+        }
+    }
+    let Some(subject) = subject else {
+        return Err(LuatError::ParseError {
+            message: "Missing value in {#match} block".to_string(),
+            line: span.line,
+            column: span.column,
+            file: None,
+            source_context: None,
+        });
+    };
+
+    let mut chain = default;
+    for (condition, then_branch) in cases.into_iter().rev() {
+        chain = Some(vec![Node::IfBlock { condition, then_branch, else_branch: chain }]);
+    }
+    // `if true` gives the local a scope of its own
+    Ok(Node::IfBlock {
+        condition: Expression::new("true", span.clone()),
+        then_branch: std::iter::once(Node::LocalConst {
+            name: MATCH_VALUE.to_string(),
+            expression: subject,
+            tag: "local".to_string(),
+        })
+        .chain(chain.unwrap_or_default())
+        .collect(),
+        else_branch: None,
+    })
+}
+
+/// Splits `text` at commas outside strings and brackets.
+fn split_top_level_commas(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(text[start..].trim());
+    parts
+}
+
+/// Returns the position of the `(` matching the `)` that ends `call`.
+///
+/// Parentheses inside string literals are ignored.
+fn call_open_paren(call: &str) -> Option<usize> {
+    if !call.ends_with(')') {
+        return None;
+    }
+    let mut opens = Vec::new();
+    let mut quote = None;
+    let mut escaped = false;
+    let mut matched = None;
+    for (i, c) in call.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '(' => opens.push(i),
+            ')' => matched = Some(opens.pop()?),
+            _ => {}
+        }
+    }
+    if opens.is_empty() {
+        matched
+    } else {
+        None
+    }
+}
+
+fn parse_snippet_block(pair: pest::iterators::Pair<Rule>) -> Result<Node> {
+    let span = pair.as_span();
+    let mut name = None;
+    let mut params = Vec::new();
+    let mut body = Vec::new();
+    for inner_pair in pair.into_inner() {
+        match inner_pair.as_rule() {
+            Rule::snippet_start => {
+                for part in inner_pair.into_inner() {
+                    match part.as_rule() {
+                        Rule::ident => name = Some(part.as_str().to_string()),
+                        Rule::snippet_params => {
+                            params = part.into_inner().map(|param| param.as_str().to_string()).collect();
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Rule::template_node => body.push(parse_node(inner_pair)?),
+            _ => {}
+        }
+    }
+    match name {
+        Some(name) => Ok(Node::Snippet { name, params, body }),
+        None => Err(LuatError::ParseError {
+            message: "Invalid {#snippet} syntax, expected {#snippet name(params)}".to_string(),
+            line: span.start_pos().line_col().0,
+            column: span.start_pos().line_col().1,
+            file: None,
+            source_context: None,
+        }),
+    }
 }
 
 fn parse_html_comment(pair: pest::iterators::Pair<Rule>) -> Result<Node> {
@@ -809,6 +1205,90 @@ fn parse_each_block(pair: pest::iterators::Pair<Rule>, sensitive: bool) -> Resul
     }
 }
 
+fn parse_await_block(pair: pest::iterators::Pair<Rule>) -> Result<Node> {
+    let span = pair.as_span();
+    let mut expression = None;
+    let mut pending = Vec::new();
+    let mut then_id = None;
+    let mut then_branch: Option<Vec<Node>> = None;
+    let mut catch_id = None;
+    let mut catch_branch: Option<Vec<Node>> = None;
+
+    for inner_pair in pair.into_inner() {
+        match inner_pair.as_rule() {
+            Rule::await_start => {
+                if let Some(expr_pair) = inner_pair.into_inner().find(|p| p.as_rule() == Rule::expr) {
+                    expression = Some(Expression::new(expr_pair.as_str().trim(), pair_to_span(&expr_pair)));
+                }
+            }
+            Rule::await_then => {
+                then_id = inner_pair.into_inner().next().map(|p| p.as_str().to_string());
+                then_branch = Some(Vec::new());
+            }
+            Rule::await_catch => {
+                catch_id = inner_pair.into_inner().next().map(|p| p.as_str().to_string());
+                catch_branch = Some(Vec::new());
+            }
+            Rule::await_end => break,
+            _ => {
+                let node = parse_node(inner_pair)?;
+                if let Some(branch) = catch_branch.as_mut() {
+                    branch.push(node);
+                } else if let Some(branch) = then_branch.as_mut() {
+                    branch.push(node);
+                } else {
+                    pending.push(node);
+                }
+            }
+        }
+    }
+
+    let expression = expression.ok_or_else(|| LuatError::ParseError {
+        message: "Missing expression in await block".to_string(),
+        line: span.start_pos().line_col().0,
+        column: span.start_pos().line_col().1,
+        file: None,
+        source_context: None,
+    })?;
+
+    Ok(Node::AwaitBlock {
+        expression,
+        pending,
+        then_id,
+        then_branch,
+        catch_id,
+        catch_branch,
+    })
+}
+
+fn parse_custom_block(pair: pest::iterators::Pair<Rule>) -> Result<Node> {
+    let mut name = String::new();
+    let mut args = None;
+    let mut span = pair_to_span(&pair);
+    let mut children = Vec::new();
+
+    for inner_pair in pair.into_inner() {
+        match inner_pair.as_rule() {
+            Rule::custom_block_start => {
+                span = pair_to_span(&inner_pair);
+                for part in inner_pair.into_inner() {
+                    match part.as_rule() {
+                        Rule::ident => name = part.as_str().to_string(),
+                        Rule::expr => {
+                            args = Some(Expression::new(part.as_str().trim(), pair_to_span(&part)));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Rule::custom_block_end => break,
+            _ => children.push(parse_node(inner_pair)?),
+        }
+    }
+
+    Ok(Node::CustomBlock { name, args, children, span })
+}
+
 /// Parse a LUAT magic function like $state(value) or $state(value, default)
 #[allow(dead_code)]
 fn parse_luat_magic_function(pair: pest::iterators::Pair<Rule>) -> Result<LuatMagicFunction> {
@@ -847,6 +1327,7 @@ fn parse_luat_magic_function(pair: pest::iterators::Pair<Rule>) -> Result<LuatMa
 
 fn parse_element_or_component_node(pair: pest::iterators::Pair<Rule>) -> Result<Node> {
     let span = pair.as_span();
+    let element_span = pair_to_span(&pair);
     // println!("Parsing element or component: {:?}", pair.as_rule());
 
     // Check which rule matched
@@ -910,6 +1391,7 @@ fn parse_element_or_component_node(pair: pest::iterators::Pair<Rule>) -> Result<
                 name,
                 attributes,
                 children,
+                span: element_span,
             })
         }
 
@@ -939,6 +1421,7 @@ fn parse_element_or_component_node(pair: pest::iterators::Pair<Rule>) -> Result<
                 tag,
                 attributes,
                 children: Vec::new(), // Void elements never have children
+                span: element_span,
             })
         }
 
@@ -986,6 +1469,7 @@ fn parse_element_or_component_node(pair: pest::iterators::Pair<Rule>) -> Result<
                 tag,
                 attributes,
                 children,
+                span: element_span,
             })
         }
 
@@ -1070,12 +1554,14 @@ fn parse_element_or_component_node(pair: pest::iterators::Pair<Rule>) -> Result<
                     name: tag_or_name,
                     attributes,
                     children,
+                    span: element_span,
                 })
             } else if !tag_or_name.is_empty() {
                 Ok(Node::ElementNode {
                     tag: tag_or_name,
                     attributes,
                     children,
+                    span: element_span,
                 })
             } else {
                 Err(LuatError::ParseError {
@@ -1190,7 +1676,30 @@ fn parse_attribute(pair: pest::iterators::Pair<Rule>) -> Result<Attribute> {
             }
         }
         Rule::boolean_attr => {
-            let name = pair.into_inner().next().unwrap().as_str().to_string();
+            let name_pair = pair.into_inner().next().unwrap();
+            let name = name_pair.as_str().to_string();
+            // `class:name` is shorthand for `class:name={name}`
+            if let Some(class) = name.strip_prefix("class:") {
+                let is_ident = class.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                    && class.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                if !is_ident {
+                    let (line, column) = span.start_pos().line_col();
+                    return Err(LuatError::ParseError {
+                        message: format!("`{}` needs a condition: {}={{condition}}", name, name),
+                        line,
+                        column,
+                        file: None,
+                        source_context: None,
+                    });
+                }
+                let mut condition_span = pair_to_span(&name_pair);
+                condition_span.start += "class:".len();
+                condition_span.column += "class:".len();
+                return Ok(Attribute::Named {
+                    name: name.clone(),
+                    value: AttributeValue::Shorthand(Expression::new(class.to_string(), condition_span)),
+                });
+            }
             return Ok(Attribute::Named {
                 name,
                 value: AttributeValue::BooleanTrue,
@@ -1218,6 +1727,18 @@ fn parse_attribute(pair: pest::iterators::Pair<Rule>) -> Result<Attribute> {
     })
 }
 
+/// Parses `{#use Name from "path"}`.
+fn parse_use_directive(pair: pest::iterators::Pair<Rule>) -> UseImport {
+    let span = pair_to_span(&pair);
+    let mut inner = pair.into_inner();
+    let name = inner.next().map(|p| p.as_str().to_string()).unwrap_or_default();
+    let path = inner
+        .next()
+        .map(|p| p.as_str()[1..p.as_str().len() - 1].to_string())
+        .unwrap_or_default();
+    UseImport { name, path, span }
+}
+
 fn pair_to_span(pair: &pest::iterators::Pair<Rule>) -> Span {
     let span = pair.as_span();
     let start_pos = span.start_pos().line_col();
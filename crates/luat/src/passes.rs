@@ -0,0 +1,60 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The compile passes between parsing and code generation.
+//!
+//! Every way an engine compiles a template, from `require` in the module
+//! searcher to [`Engine::compile_bundle`](crate::Engine::compile_bundle),
+//! turns the parsed template into IR with [`CompilePasses::compile_ir`], so
+//! a pass added here applies to all of them.
+
+use crate::ast::TemplateAST;
+use crate::auto_import::ComponentDirs;
+use crate::defines::Defines;
+use crate::directives::BlockDirectives;
+use crate::error::Result;
+use crate::resolver::ResourceResolver;
+use crate::transform::{transform_ast, validate_ir, IR};
+use crate::warning::CompileWarnings;
+use crate::whitespace::CollapseWhitespace;
+
+/// The settings of an engine's compile passes.
+///
+/// Clones share the settings, so the module searcher follows the engine's.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CompilePasses {
+    /// Custom `{#name}` block directives
+    pub(crate) block_directives: BlockDirectives,
+    /// Build-time defines
+    pub(crate) defines: Defines,
+    /// Compile warnings not yet taken
+    pub(crate) warnings: CompileWarnings,
+    /// Whether static text whitespace is collapsed
+    pub(crate) collapse_whitespace: CollapseWhitespace,
+    /// Directories component tags are imported from
+    pub(crate) component_dirs: ComponentDirs,
+}
+
+impl CompilePasses {
+    /// Transforms the template `name` to IR and runs the passes on it: its
+    /// warnings are recorded, defines applied, whitespace collapsed,
+    /// components imported relative to `importer` and block directives
+    /// expanded, then the result is validated.
+    pub(crate) fn compile_ir(
+        &self,
+        ast: TemplateAST,
+        name: &str,
+        importer: &str,
+        resolver: &dyn ResourceResolver,
+    ) -> Result<IR> {
+        let mut ir = transform_ast(ast)?;
+        self.warnings.record(name, &ir.warnings);
+        self.defines.apply(&mut ir);
+        self.collapse_whitespace.apply(&mut ir);
+        self.component_dirs.apply(&mut ir, importer, resolver)?;
+        self.block_directives.expand(&mut ir)?;
+        validate_ir(&ir)?;
+        Ok(ir)
+    }
+}
@@ -0,0 +1,348 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! A pool of engines for rendering on several threads at once.
+//!
+//! An [`Engine`] owns a single Lua state, which only one thread can use at a
+//! time. [`EnginePool`] keeps a fixed number of engines, each with its own
+//! isolated Lua state; a request checks one out, renders with it and returns
+//! it when the guard is dropped. Checkouts block while every engine is busy;
+//! async servers use [`EnginePool::checkout_owned`], which waits without
+//! blocking the thread and returns a guard a spawned task can keep.
+//!
+//! ```rust,ignore
+//! use luat::{EnginePool, FileSystemResolver, MemoryCache};
+//!
+//! let pool = EnginePool::shared(8, FileSystemResolver::new("./templates"), Box::new(MemoryCache::new(200)))?;
+//!
+//! // On any thread:
+//! let engine = pool.checkout();
+//! let module = engine.compile_entry("index.luat")?;
+//! let html = engine.render(&module, &engine.to_value(props)?)?;
+//! ```
+//!
+//! Values such as `mlua::Value` belong to the Lua state that created them,
+//! so create contexts with the checked-out engine, not another one.
+
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Poll, Waker};
+use std::time::{Duration, Instant};
+
+use crate::cache::Cache;
+use crate::engine::Engine;
+use crate::error::Result;
+use crate::resolver::ResourceResolver;
+
+/// A fixed set of engines checked out one request at a time.
+pub struct EnginePool<R: ResourceResolver> {
+    idle: Mutex<Idle<R>>,
+    returned: Condvar,
+    size: usize,
+}
+
+/// The engines not checked out, and the async checkouts waiting for one.
+struct Idle<R: ResourceResolver> {
+    engines: Vec<Engine<R>>,
+    waiting: Vec<Waker>,
+}
+
+impl<R: ResourceResolver> EnginePool<R> {
+    /// Creates a pool of `size` engines made by `factory`.
+    ///
+    /// The factory runs once per engine, so every engine gets the same
+    /// globals, extensions and settings.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error `factory` returns.
+    pub fn new<F>(size: usize, mut factory: F) -> Result<Self>
+    where
+        F: FnMut() -> Result<Engine<R>>,
+    {
+        assert!(size > 0, "engine pool needs at least one engine");
+        let engines = (0..size).map(|_| factory()).collect::<Result<Vec<_>>>()?;
+        Ok(Self::from_engines(engines))
+    }
+
+    /// Creates a pool of engines that were set up already.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `engines` is empty.
+    pub fn from_engines(engines: Vec<Engine<R>>) -> Self {
+        assert!(!engines.is_empty(), "engine pool needs at least one engine");
+        Self {
+            size: engines.len(),
+            idle: Mutex::new(Idle { engines, waiting: Vec::new() }),
+            returned: Condvar::new(),
+        }
+    }
+
+    /// Creates a pool of `size` engines that share `resolver` and `cache`.
+    ///
+    /// A template compiled by one engine is in the cache for the others.
+    /// Each engine gets a clone of the resolver and a
+    /// [`clone_box`](Cache::clone_box) of the cache, so the built-in caches
+    /// and resolvers share their storage.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a Lua state fails to initialize.
+    pub fn shared(size: usize, resolver: R, cache: Box<dyn Cache>) -> Result<Self>
+    where
+        R: Clone,
+    {
+        Self::new(size, || Engine::new(resolver.clone(), cache.clone_box()))
+    }
+
+    /// Number of engines in the pool.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Number of engines not checked out.
+    pub fn idle(&self) -> usize {
+        self.lock().engines.len()
+    }
+
+    /// Checks out an engine, waiting until one is idle.
+    pub fn checkout(&self) -> PooledEngine<'_, R> {
+        let mut idle = self.lock();
+        loop {
+            if let Some(engine) = idle.engines.pop() {
+                return self.guard(engine);
+            }
+            idle = self.returned.wait(idle).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Checks out an engine, waiting asynchronously until one is idle.
+    ///
+    /// The guard holds on to the pool, so it can be moved into a spawned
+    /// task, e.g. one streaming values that live in the engine's Lua state.
+    pub async fn checkout_owned(self: Arc<Self>) -> OwnedPooledEngine<R> {
+        let engine = std::future::poll_fn(|cx| {
+            let mut idle = self.lock();
+            match idle.engines.pop() {
+                Some(engine) => Poll::Ready(engine),
+                None => {
+                    idle.waiting.push(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+        .await;
+        OwnedPooledEngine {
+            pool: self,
+            engine: Some(engine),
+        }
+    }
+
+    /// Checks out an engine if one is idle, without waiting.
+    pub fn try_checkout(&self) -> Option<PooledEngine<'_, R>> {
+        self.lock().engines.pop().map(|engine| self.guard(engine))
+    }
+
+    /// Checks out an engine, waiting at most `timeout` for one to be idle.
+    pub fn checkout_timeout(&self, timeout: Duration) -> Option<PooledEngine<'_, R>> {
+        let deadline = Instant::now() + timeout;
+        let mut idle = self.lock();
+        loop {
+            if let Some(engine) = idle.engines.pop() {
+                return Some(self.guard(engine));
+            }
+            let remaining = deadline.checked_duration_since(Instant::now())?;
+            idle = self
+                .returned
+                .wait_timeout(idle, remaining)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    /// Runs `f` on every engine, e.g. to change a setting on all of them.
+    ///
+    /// Waits until every engine is idle and keeps them checked out while
+    /// `f` runs. Stops at the first error.
+    pub fn for_each<F>(&self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&mut Engine<R>) -> Result<()>,
+    {
+        let mut idle = self.lock();
+        while idle.engines.len() < self.size {
+            idle = self.returned.wait(idle).unwrap_or_else(|e| e.into_inner());
+        }
+        idle.engines.iter_mut().try_for_each(&mut f)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Idle<R>> {
+        // An engine is only pushed or popped under the lock, so the list is
+        // intact even if a holder panicked.
+        self.idle.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Puts a checked-out engine back and wakes the checkouts waiting.
+    fn give_back(&self, engine: Engine<R>) {
+        let waiting = {
+            let mut idle = self.lock();
+            idle.engines.push(engine);
+            std::mem::take(&mut idle.waiting)
+        };
+        self.returned.notify_all();
+        waiting.into_iter().for_each(Waker::wake);
+    }
+
+    fn guard(&self, engine: Engine<R>) -> PooledEngine<'_, R> {
+        PooledEngine {
+            pool: self,
+            engine: Some(engine),
+        }
+    }
+}
+
+/// An engine checked out of an [`EnginePool`], returned to it when dropped.
+pub struct PooledEngine<'a, R: ResourceResolver> {
+    pool: &'a EnginePool<R>,
+    engine: Option<Engine<R>>,
+}
+
+impl<R: ResourceResolver> Deref for PooledEngine<'_, R> {
+    type Target = Engine<R>;
+
+    fn deref(&self) -> &Self::Target {
+        self.engine.as_ref().expect("engine is present until drop")
+    }
+}
+
+impl<R: ResourceResolver> DerefMut for PooledEngine<'_, R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.engine.as_mut().expect("engine is present until drop")
+    }
+}
+
+impl<R: ResourceResolver> Drop for PooledEngine<'_, R> {
+    fn drop(&mut self) {
+        if let Some(engine) = self.engine.take() {
+            self.pool.give_back(engine);
+        }
+    }
+}
+
+/// An engine checked out with [`EnginePool::checkout_owned`], returned to
+/// the pool when dropped.
+pub struct OwnedPooledEngine<R: ResourceResolver> {
+    pool: Arc<EnginePool<R>>,
+    engine: Option<Engine<R>>,
+}
+
+impl<R: ResourceResolver> Deref for OwnedPooledEngine<R> {
+    type Target = Engine<R>;
+
+    fn deref(&self) -> &Self::Target {
+        self.engine.as_ref().expect("engine is present until drop")
+    }
+}
+
+impl<R: ResourceResolver> DerefMut for OwnedPooledEngine<R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.engine.as_mut().expect("engine is present until drop")
+    }
+}
+
+impl<R: ResourceResolver> Drop for OwnedPooledEngine<R> {
+    fn drop(&mut self) {
+        if let Some(engine) = self.engine.take() {
+            self.pool.give_back(engine);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryCache, MemoryResourceResolver};
+
+    fn pool(size: usize) -> (EnginePool<MemoryResourceResolver>, MemoryCache) {
+        let resolver = MemoryResourceResolver::new();
+        resolver.add_template("hello.luat", "<p>Hello {props.name}</p>".to_string());
+        let cache = MemoryCache::new(10);
+        let pool = EnginePool::shared(size, resolver, Box::new(cache.clone())).unwrap();
+        (pool, cache)
+    }
+
+    #[test]
+    fn test_checkout_returns_engines() {
+        let (pool, _) = pool(2);
+        let first = pool.checkout();
+        let second = pool.try_checkout().unwrap();
+        assert_eq!(pool.idle(), 0);
+        assert!(pool.try_checkout().is_none());
+        assert!(pool.checkout_timeout(Duration::from_millis(10)).is_none());
+
+        drop(first);
+        assert_eq!(pool.idle(), 1);
+        drop(second);
+        assert_eq!(pool.idle(), 2);
+    }
+
+    #[test]
+    fn test_renders_on_many_threads() {
+        let (pool, cache) = pool(2);
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let pool = &pool;
+                scope.spawn(move || {
+                    let engine = pool.checkout();
+                    let module = engine.compile_entry("hello.luat").unwrap();
+                    let context = engine.to_value(serde_json::json!({ "name": i })).unwrap();
+                    assert_eq!(engine.render(&module, &context).unwrap(), format!("<p>Hello {}</p>", i));
+                });
+            }
+        });
+        assert_eq!(pool.idle(), 2);
+        assert!(cache.contains_key("module:hello.luat"));
+    }
+
+    #[tokio::test]
+    async fn test_checkout_owned_waits_for_an_engine() {
+        let (pool, _) = pool(1);
+        let pool = Arc::new(pool);
+        let first = pool.clone().checkout_owned().await;
+        let waiting = tokio::spawn(pool.clone().checkout_owned());
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+
+        drop(first);
+        let second = waiting.await.unwrap();
+        assert!(second.compile_entry("hello.luat").is_ok());
+        assert_eq!(pool.idle(), 0);
+        drop(second);
+        assert_eq!(pool.idle(), 1);
+    }
+
+    #[test]
+    fn test_for_each_configures_every_engine() {
+        let (pool, _) = pool(3);
+        let mut seen = 0;
+        pool.for_each(|engine| {
+            engine.lua().globals().set("SITE", "Docs")?;
+            seen += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(seen, 3);
+
+        let engine = pool.checkout();
+        assert_eq!(engine.lua().globals().get::<String>("SITE").unwrap(), "Docs");
+    }
+}
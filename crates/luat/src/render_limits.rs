@@ -0,0 +1,123 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Instruction and wall-clock limits for renders.
+//!
+//! With a limit set, a Lua hook runs every [`CHECK_INTERVAL`] instructions
+//! while a template renders and aborts the render once it has run too many
+//! instructions or for too long, so a template stuck in a loop can't hold an
+//! engine forever. The hook raises [`RenderLimitExceeded`] with the template
+//! and line that was running; the engine reports it as a
+//! [`crate::LuatError::TemplateRuntimeError`].
+//!
+//! The limits count from the start of a render, or of a request handled by
+//! [`crate::Engine::respond`], whose load functions, handlers and actions
+//! are covered too. A render started while another is running, e.g. by a
+//! helper that renders a template, counts against the outer render's limits.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use mlua::{HookTriggers, Lua, VmState};
+
+/// Lua instructions between two limit checks.
+const CHECK_INTERVAL: u32 = 1000;
+
+/// The limits a render runs under.
+#[derive(Debug, Default)]
+pub(crate) struct RenderLimits {
+    /// Longest a render may run.
+    pub(crate) timeout: Option<Duration>,
+    /// Most Lua instructions a render may run.
+    pub(crate) instructions: Option<u64>,
+    /// Whether a render holds the hook
+    active: AtomicBool,
+}
+
+impl RenderLimits {
+    /// Installs the limit hook for a render, until the returned guard drops.
+    ///
+    /// Returns `None` without limits, or while another render holds the hook.
+    pub(crate) fn start<'a>(&'a self, lua: &'a Lua) -> Option<RenderLimitGuard<'a>> {
+        if self.timeout.is_none() && self.instructions.is_none() {
+            return None;
+        }
+        if self.active.swap(true, Ordering::AcqRel) {
+            return None;
+        }
+
+        let timer = self.timeout.map(|timeout| (Instant::now(), timeout));
+        let instructions = self.instructions;
+        let executed = AtomicU64::new(0);
+        lua.set_hook(
+            HookTriggers::new().every_nth_instruction(CHECK_INTERVAL),
+            move |_, debug| {
+                let executed = executed.fetch_add(u64::from(CHECK_INTERVAL), Ordering::Relaxed) + u64::from(CHECK_INTERVAL);
+                let reason = match (instructions, timer) {
+                    (Some(limit), _) if executed > limit => format!("instruction limit of {} exceeded", limit),
+                    (_, Some((started, timeout))) if started.elapsed() > timeout => {
+                        format!("render time limit of {}ms exceeded", timeout.as_millis())
+                    }
+                    _ => return Ok(VmState::Continue),
+                };
+                let template = debug
+                    .source()
+                    .source
+                    .map(|source| source.trim_start_matches('@').to_string())
+                    .unwrap_or_else(|| "?".to_string());
+                Err(mlua::Error::external(RenderLimitExceeded {
+                    template,
+                    line: usize::try_from(debug.curr_line()).unwrap_or(0),
+                    reason,
+                }))
+            },
+        );
+        Some(RenderLimitGuard { lua, active: &self.active })
+    }
+}
+
+/// Removes the limit hook when dropped.
+pub(crate) struct RenderLimitGuard<'a> {
+    lua: &'a Lua,
+    active: &'a AtomicBool,
+}
+
+impl Drop for RenderLimitGuard<'_> {
+    fn drop(&mut self) {
+        self.lua.remove_hook();
+        self.active.store(false, Ordering::Release);
+    }
+}
+
+/// Raised by the limit hook when a render goes over a limit.
+#[derive(Debug, Clone)]
+pub(crate) struct RenderLimitExceeded {
+    /// Chunk name of the template that was running.
+    pub(crate) template: String,
+    /// Line of the generated Lua code that was running.
+    pub(crate) line: usize,
+    /// Which limit was exceeded.
+    pub(crate) reason: String,
+}
+
+impl RenderLimitExceeded {
+    /// Finds the limit error in `error`, which wraps it once per Rust
+    /// callback it passed through.
+    pub(crate) fn find(error: &mlua::Error) -> Option<&Self> {
+        match error {
+            mlua::Error::ExternalError(e) => e.downcast_ref(),
+            mlua::Error::CallbackError { cause, .. } | mlua::Error::WithContext { cause, .. } => Self::find(cause),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RenderLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.template, self.line, self.reason)
+    }
+}
+
+impl std::error::Error for RenderLimitExceeded {}
@@ -43,6 +43,10 @@ pub struct LuatRequest {
 
     /// Cookies
     pub cookies: HashMap<String, String>,
+
+    /// Per-request CSP nonce, exposed to templates via `nonce()` and added to
+    /// emitted `<script>`/`<style>` tags
+    pub csp_nonce: Option<String>,
 }
 
 impl LuatRequest {
@@ -55,6 +59,7 @@ impl LuatRequest {
             body: None,
             query: HashMap::new(),
             cookies: HashMap::new(),
+            csp_nonce: None,
         }
     }
 
@@ -82,6 +87,12 @@ impl LuatRequest {
         self
     }
 
+    /// Sets the CSP nonce for this request.
+    pub fn with_csp_nonce(mut self, nonce: impl Into<String>) -> Self {
+        self.csp_nonce = Some(nonce.into());
+        self
+    }
+
     /// Returns the body as a string, if present and valid UTF-8.
     pub fn body_str(&self) -> Option<&str> {
         self.body.as_ref().and_then(|b| std::str::from_utf8(b).ok())
@@ -99,6 +110,18 @@ impl LuatRequest {
             .or_else(|| self.headers.get("Content-Type").map(|s| s.as_str()))
     }
 
+    /// Returns the Accept-Language header, if present.
+    pub fn accept_language(&self) -> Option<&str> {
+        self.headers.get("accept-language").map(|s| s.as_str())
+            .or_else(|| self.headers.get("Accept-Language").map(|s| s.as_str()))
+    }
+
+    /// Returns the X-Request-Id header, if present.
+    pub fn request_id(&self) -> Option<&str> {
+        self.headers.get("x-request-id").map(|s| s.as_str())
+            .or_else(|| self.headers.get("X-Request-Id").map(|s| s.as_str()))
+    }
+
     /// Checks if this is a form submission (POST with form content type).
     pub fn is_form_submission(&self) -> bool {
         self.method.eq_ignore_ascii_case("POST")
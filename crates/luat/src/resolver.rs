@@ -25,6 +25,7 @@
 //! Implement [`ResourceResolver`] for custom loading strategies (network, database, etc.).
 
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use crate::error::{Result, LuatError};
 
 #[cfg(all(not(target_arch = "wasm32"), feature = "filesystem"))]
@@ -81,6 +82,28 @@ pub trait ResourceResolver: Send + Sync + 'static {
 
     /// Creates a boxed clone (for use in closures).
     fn clone_box(&self) -> Box<dyn ResourceResolver>;
+
+    /// Lists the resources under the directory `dir`, at any depth, as
+    /// sorted names [`read_resource`](Self::read_resource) accepts (`dir`
+    /// joined with the relative path). Resolvers that can't enumerate their
+    /// resources list none.
+    fn list_resources(&self, dir: &str) -> Result<Vec<String>> {
+        let _ = dir;
+        Ok(Vec::new())
+    }
+
+    /// Reads a resource listed by [`list_resources`](Self::list_resources),
+    /// of any file type.
+    fn read_resource(&self, path: &str) -> Result<String> {
+        self.resolve("", path).map(|resource| resource.source)
+    }
+
+    /// Returns when a listed resource was last modified, or `None` if the
+    /// resolver can't tell (callers then treat it as changed).
+    fn resource_modified(&self, path: &str) -> Option<SystemTime> {
+        let _ = path;
+        None
+    }
 }
 
 /// Trait for resolving and loading template resources (WASM variant).
@@ -92,6 +115,20 @@ pub trait ResourceResolver: 'static {
     fn get_resolved_path(&self, importer_path: &str, module_name: &str) -> Result<String>;
     /// Creates a boxed clone (for use in closures).
     fn clone_box(&self) -> Box<dyn ResourceResolver>;
+    /// Lists the resources under the directory `dir`, at any depth.
+    fn list_resources(&self, dir: &str) -> Result<Vec<String>> {
+        let _ = dir;
+        Ok(Vec::new())
+    }
+    /// Reads a resource listed by `list_resources`.
+    fn read_resource(&self, path: &str) -> Result<String> {
+        self.resolve("", path).map(|resource| resource.source)
+    }
+    /// Returns when a listed resource was last modified, if known.
+    fn resource_modified(&self, path: &str) -> Option<SystemTime> {
+        let _ = path;
+        None
+    }
 }
 
 impl Clone for Box<dyn ResourceResolver> {
@@ -125,6 +162,12 @@ pub struct FileSystemResolver {
     pub root_dir: String,
     /// The lib directory for $lib alias resolution.
     pub lib_dir: Option<String>,
+    /// Directory holding installed component packages (e.g. `luat_modules`).
+    pub modules_dir: Option<String>,
+    /// Additional source roots searched for bare module names.
+    pub source_roots: Vec<String>,
+    /// The content directory for `$content` alias resolution.
+    pub content_dir: Option<String>,
 }
 
 #[cfg(all(not(target_arch = "wasm32"), feature = "filesystem"))]
@@ -134,6 +177,9 @@ impl FileSystemResolver {
         Self {
             root_dir: path_to_string(root_dir.as_ref()),
             lib_dir: None,
+            modules_dir: None,
+            source_roots: Vec::new(),
+            content_dir: None,
         }
     }
 
@@ -154,9 +200,109 @@ impl FileSystemResolver {
         self
     }
 
+    /// Sets the directory holding installed component packages.
+    ///
+    /// Bare module names that are not found in the root or lib directory are
+    /// looked up in this directory, so `require("ui/Button")` resolves to
+    /// `luat_modules/ui/Button.luat`. `$lib/ui/Button` falls back to the
+    /// installed package as well.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let resolver = FileSystemResolver::new("./src/routes")
+    ///     .with_lib_dir("./src/lib")
+    ///     .with_modules_dir("./luat_modules");
+    /// ```
+    pub fn with_modules_dir<P: AsRef<Path>>(mut self, modules_dir: P) -> Self {
+        self.modules_dir = Some(path_to_string(modules_dir.as_ref()));
+        self
+    }
+
+    /// Adds a shared source root, e.g. a components directory in a monorepo.
+    ///
+    /// Bare module names that are not found in the root or lib directory are
+    /// looked up in each source root in the order they were added.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let resolver = FileSystemResolver::new("./src/routes")
+    ///     .with_source_root("../shared/components");
+    /// // require("Button") resolves to ../shared/components/Button.luat
+    /// ```
+    pub fn with_source_root<P: AsRef<Path>>(mut self, root: P) -> Self {
+        self.source_roots.push(path_to_string(root.as_ref()));
+        self
+    }
+
+    /// Sets the content directory for `$content` alias resolution.
+    ///
+    /// Content collections (see [`crate::extensions::content`]) are loaded
+    /// from this directory.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let resolver = FileSystemResolver::new("./src/routes")
+    ///     .with_content_dir("./src/content");
+    /// // resolver.resolve("", "$content/posts/hello.md") reads ./src/content/posts/hello.md
+    /// ```
+    pub fn with_content_dir<P: AsRef<Path>>(mut self, content_dir: P) -> Self {
+        self.content_dir = Some(path_to_string(content_dir.as_ref()));
+        self
+    }
+
+    /// Looks up a bare module name in the additional source roots.
+    fn resolve_in_source_roots(&self, module_name: &str) -> Option<PathBuf> {
+        if module_name.starts_with("./") || module_name.starts_with("../") || Path::new(module_name).is_absolute() {
+            return None;
+        }
+        self.source_roots.iter().find_map(|root| {
+            let path = Path::new(root).join(module_name);
+            if path.extension().is_some() && path.is_file() {
+                return Some(path);
+            }
+            ["luat", "lua"]
+                .iter()
+                .map(|ext| path.with_extension(ext))
+                .find(|candidate| candidate.is_file())
+        })
+    }
+
+    /// Looks up a module in the installed packages directory.
+    ///
+    /// Tries `<modules_dir>/<name>` as-is, with `.luat`/`.lua` appended, and
+    /// finally the package's `index` module for names like `ui`.
+    fn resolve_package(&self, module_name: &str) -> Option<PathBuf> {
+        let modules_dir = Path::new(self.modules_dir.as_ref()?);
+        let name = module_name
+            .strip_prefix("$lib/")
+            .or_else(|| module_name.strip_prefix("lib/"))
+            .unwrap_or(module_name);
+        if name.starts_with("./") || name.starts_with("../") || Path::new(name).is_absolute() {
+            return None;
+        }
+
+        let package_path = modules_dir.join(name);
+        if package_path.extension().is_some() && package_path.is_file() {
+            return Some(package_path);
+        }
+        ["luat", "lua"]
+            .iter()
+            .map(|ext| package_path.with_extension(ext))
+            .chain(["index.luat", "index.lua"].iter().map(|index| package_path.join(index)))
+            .find(|candidate| candidate.is_file())
+    }
+
     /// Expands path aliases like `$lib/...` to their actual paths.
     /// Returns (expanded_path, is_alias_absolute).
     fn expand_aliases(&self, module_name: &str) -> (String, bool) {
+        if let Some(ref content_dir) = self.content_dir {
+            if let Some(suffix) = module_name.strip_prefix("$content/") {
+                return (format!("{}/{}", content_dir, suffix), true);
+            }
+        }
         if let Some(ref lib_dir) = self.lib_dir {
             if let Some(suffix) = module_name.strip_prefix("$lib/") {
                 // Replace $lib/ with the lib directory path
@@ -194,6 +340,7 @@ impl FileSystemResolver {
     }
     
     fn resolve_internal(&self, importer_path: &str, module_name: &str) -> Result<(PathBuf, String)> {
+        let requested_name = module_name;
         let (expanded_module_name, alias_absolute) = self.expand_aliases(module_name);
         let module_name = expanded_module_name.as_str();
 
@@ -272,6 +419,13 @@ impl FileSystemResolver {
                 }
             }
             
+            // Source roots and installed packages take precedence over the basename fallback
+            if resolved_path_option.is_none() {
+                resolved_path_option = self
+                    .resolve_in_source_roots(requested_name)
+                    .or_else(|| self.resolve_package(requested_name));
+            }
+
             // If still not found, check if it's a component name only
             if resolved_path_option.is_none() {
                 // Try component name only from both root and base paths
@@ -330,7 +484,7 @@ impl FileSystemResolver {
                     format!("Failed to canonicalize path '{}': {}", resolved_path.to_string_lossy(), e)
                 ))?;
 
-                // Security: Verify the resolved path is within root_dir OR lib_dir
+                // Security: Verify the resolved path is within root_dir, lib_dir, modules_dir or a source root
                 // This prevents symlink attacks and path traversal while allowing lib imports
                 let canonical_root = fs::canonicalize(&self.root_dir).map_err(|e| LuatError::ResolutionError(
                     format!("Failed to canonicalize root '{}': {}", self.root_dir, e)
@@ -347,7 +501,25 @@ impl FileSystemResolver {
                     false
                 };
 
-                if !in_root && !in_lib {
+                let in_modules = self
+                    .modules_dir
+                    .as_ref()
+                    .and_then(|dir| fs::canonicalize(dir).ok())
+                    .is_some_and(|dir| canonical_path.starts_with(dir));
+
+                let in_source_root = self
+                    .source_roots
+                    .iter()
+                    .filter_map(|dir| fs::canonicalize(dir).ok())
+                    .any(|dir| canonical_path.starts_with(dir));
+
+                let in_content = self
+                    .content_dir
+                    .as_ref()
+                    .and_then(|dir| fs::canonicalize(dir).ok())
+                    .is_some_and(|dir| canonical_path.starts_with(dir));
+
+                if !in_root && !in_lib && !in_modules && !in_source_root && !in_content {
                     return Err(LuatError::ResolutionError(
                         format!("Security: Path '{}' escapes allowed directories", module_name)
                     ));
@@ -400,6 +572,59 @@ impl ResourceResolver for FileSystemResolver {
     fn clone_box(&self) -> Box<dyn ResourceResolver> {
         Box::new(self.clone())
     }
+
+    fn list_resources(&self, dir: &str) -> Result<Vec<String>> {
+        let dir_path = self.listing_path(dir);
+        let mut files = Vec::new();
+        if dir_path.is_dir() {
+            collect_files(&dir_path, &dir_path, &mut files)?;
+        }
+        files.sort();
+        let prefix = dir.trim_end_matches('/');
+        Ok(files.into_iter().map(|file| format!("{}/{}", prefix, file)).collect())
+    }
+
+    fn resource_modified(&self, path: &str) -> Option<SystemTime> {
+        fs::metadata(self.listing_path(path)).and_then(|metadata| metadata.modified()).ok()
+    }
+}
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "filesystem"))]
+impl FileSystemResolver {
+    /// Maps a name used with [`ResourceResolver::list_resources`] to its
+    /// filesystem path.
+    fn listing_path(&self, name: &str) -> PathBuf {
+        let (expanded, alias) = self.expand_aliases(name);
+        if alias {
+            PathBuf::from(expanded)
+        } else {
+            Path::new(&self.root_dir).join(expanded.trim_start_matches('/'))
+        }
+    }
+}
+
+/// Collects the paths of the files under `dir`, relative to `root` and with
+/// `/` separators. Hidden files and directories are skipped, and so are
+/// symlinked directories, which could loop back to an ancestor.
+#[cfg(all(not(target_arch = "wasm32"), feature = "filesystem"))]
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<String>) -> Result<()> {
+    for entry in fs::read_dir(dir).map_err(LuatError::IoError)? {
+        let entry = entry.map_err(LuatError::IoError)?;
+        let path = entry.path();
+        if path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with('.')) {
+            continue;
+        }
+        let file_type = entry.file_type().map_err(LuatError::IoError)?;
+        if file_type.is_symlink() && path.is_dir() {
+            continue;
+        }
+        if file_type.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            files.push(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+    Ok(())
 }
 
 /// Simple in-memory resource resolver for testing.
@@ -513,6 +738,25 @@ impl ResourceResolver for MemoryResourceResolver {
     fn clone_box(&self) -> Box<dyn ResourceResolver> {
         Box::new(self.clone())
     }
+
+    fn list_resources(&self, dir: &str) -> Result<Vec<String>> {
+        let prefix = format!("{}/", dir.trim_end_matches('/'));
+        let mut paths: Vec<String> = self.resources.keys().filter(|path| path.starts_with(&prefix)).cloned().collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    fn read_resource(&self, path: &str) -> Result<String> {
+        self.resources.get(path).cloned().ok_or_else(|| {
+            LuatError::ResolutionError(format!("Resource '{}' not found in memory resources", path))
+        })
+    }
+
+    fn resource_modified(&self, path: &str) -> Option<SystemTime> {
+        // Resources only change through `&mut self`, so a listed resource of
+        // a (cloned) resolver never changes
+        self.resources.contains_key(path).then_some(SystemTime::UNIX_EPOCH)
+    }
 }
 
 #[cfg(test)]
@@ -697,6 +941,93 @@ mod tests {
         assert!(result.is_err());
     }
     
+    #[cfg(feature = "filesystem")]
+    #[test]
+    fn test_filesystem_resolver_installed_packages() {
+        let temp_dir = TempDir::new().unwrap();
+        let routes = temp_dir.path().join("routes");
+        let lib = temp_dir.path().join("lib");
+        let modules = temp_dir.path().join("luat_modules");
+        fs::create_dir_all(&routes).unwrap();
+        fs::create_dir_all(&lib).unwrap();
+        fs::create_dir_all(modules.join("ui")).unwrap();
+
+        let button = r#"<button>{props.label}</button>"#;
+        fs::write(modules.join("ui/Button.luat"), button).unwrap();
+        fs::write(modules.join("ui/index.lua"), "return {}").unwrap();
+
+        let resolver = FileSystemResolver::new(&routes)
+            .with_lib_dir(&lib)
+            .with_modules_dir(&modules);
+
+        assert_eq!(resolver.resolve("", "ui/Button").unwrap().source, button);
+        assert_eq!(resolver.resolve("", "$lib/ui/Button").unwrap().source, button);
+        assert_eq!(resolver.resolve("", "ui").unwrap().source, "return {}");
+        assert!(resolver.resolve("", "./ui/Button").is_err());
+
+        // Without a modules dir, packages are not visible
+        let resolver = FileSystemResolver::new(&routes).with_lib_dir(&lib);
+        assert!(resolver.resolve("", "ui/Button").is_err());
+    }
+
+    #[cfg(feature = "filesystem")]
+    #[test]
+    fn test_filesystem_resolver_content_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let routes = temp_dir.path().join("routes");
+        let content = temp_dir.path().join("content");
+        fs::create_dir_all(&routes).unwrap();
+        fs::create_dir_all(content.join("posts/2024")).unwrap();
+        fs::write(content.join("posts/hello.md"), "# Hello").unwrap();
+        fs::write(content.join("posts/2024/recap.md"), "# Recap").unwrap();
+        fs::write(content.join("posts/.draft.md"), "").unwrap();
+
+        let resolver = FileSystemResolver::new(&routes).with_content_dir(&content);
+        assert_eq!(
+            resolver.list_resources("$content/posts").unwrap(),
+            ["$content/posts/2024/recap.md", "$content/posts/hello.md"]
+        );
+        assert_eq!(resolver.resolve("", "$content/posts/hello.md").unwrap().source, "# Hello");
+        assert!(resolver.list_resources("$content/missing").unwrap().is_empty());
+        assert!(resolver.resource_modified("$content/posts/hello.md").is_some());
+        assert!(resolver.resource_modified("$content/posts/missing.md").is_none());
+
+        // A symlink back to an ancestor isn't followed
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&content, content.join("posts/2024/loop")).unwrap();
+            assert_eq!(resolver.list_resources("$content/posts").unwrap().len(), 2);
+        }
+
+        // Without a content dir, the alias is not resolved
+        let resolver = FileSystemResolver::new(&routes);
+        assert!(resolver.resolve("", "$content/posts/hello.md").is_err());
+    }
+
+    #[cfg(feature = "filesystem")]
+    #[test]
+    fn test_filesystem_resolver_source_roots() {
+        let temp_dir = TempDir::new().unwrap();
+        let routes = temp_dir.path().join("app/routes");
+        let shared = temp_dir.path().join("shared/components");
+        fs::create_dir_all(&routes).unwrap();
+        fs::create_dir_all(shared.join("forms")).unwrap();
+
+        fs::write(shared.join("forms/Input.luat"), "<input>").unwrap();
+        fs::write(shared.join("Icon.luat"), "<i></i>").unwrap();
+        let outside = temp_dir.path().join("secret.luat");
+        fs::write(&outside, "secret").unwrap();
+
+        let resolver = FileSystemResolver::new(&routes).with_source_root(&shared);
+
+        let input = resolver.resolve("", "forms/Input").unwrap();
+        assert_eq!(input.source, "<input>");
+        // Relative imports between files in a source root keep working
+        assert_eq!(resolver.resolve(&input.path, "../Icon").unwrap().source, "<i></i>");
+        // Paths outside every root are still rejected
+        assert!(resolver.resolve(&input.path, "../../../secret").is_err());
+    }
+
     #[test]
     fn test_memory_resolver() {
         let mut resolver = MemoryResourceResolver::new();
@@ -10,6 +10,7 @@
 
 use std::collections::HashMap;
 use serde_json::Value as JsonValue;
+use crate::extensions::seo::PageHead;
 
 /// A platform-agnostic HTTP response from the Luat engine.
 ///
@@ -31,8 +32,10 @@ use serde_json::Value as JsonValue;
 /// let redirect = LuatResponse::redirect("/login");
 /// ```
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum LuatResponse {
     /// HTML response (from template rendering)
+    #[non_exhaustive]
     Html {
         /// HTTP status code
         status: u16,
@@ -40,6 +43,9 @@ pub enum LuatResponse {
         headers: HashMap<String, String>,
         /// HTML body
         body: String,
+        /// Title and meta tags collected with `seo.set` for the document
+        /// head; read it with [`LuatResponse::head`]
+        head: PageHead,
     },
 
     /// JSON response (from API handlers)
@@ -52,6 +58,34 @@ pub enum LuatResponse {
         body: JsonValue,
     },
 
+    /// Binary response (from API handlers returning bytes that aren't valid
+    /// UTF-8, or the empty answer to `OPTIONS`)
+    Bytes {
+        /// HTTP status code
+        status: u16,
+        /// HTTP headers
+        headers: HashMap<String, String>,
+        /// Raw body
+        body: Vec<u8>,
+    },
+
+    /// Streamed HTML response: the shell is sent first, then each deferred
+    /// value is resolved with `Engine::resolve_deferred` and appended in order.
+    #[non_exhaustive]
+    Stream {
+        /// HTTP status code
+        status: u16,
+        /// HTTP headers
+        headers: HashMap<String, String>,
+        /// HTML shell rendered with placeholders for deferred values
+        body: String,
+        /// IDs of deferred values still to resolve
+        deferred: Vec<String>,
+        /// Title and meta tags collected with `seo.set` for the document
+        /// head; read it with [`LuatResponse::head`]
+        head: PageHead,
+    },
+
     /// Redirect response
     Redirect {
         /// HTTP status code (301, 302, 303, 307, 308)
@@ -76,6 +110,7 @@ impl LuatResponse {
             status,
             headers: HashMap::new(),
             body: body.into(),
+            head: PageHead::default(),
         }
     }
 
@@ -89,6 +124,16 @@ impl LuatResponse {
             status,
             headers,
             body: body.into(),
+            head: PageHead::default(),
+        }
+    }
+
+    /// Returns the title and meta tags collected with `seo.set` for the
+    /// document head of an HTML or streamed response.
+    pub fn head(&self) -> Option<&PageHead> {
+        match self {
+            Self::Html { head, .. } | Self::Stream { head, .. } => Some(head),
+            _ => None,
         }
     }
 
@@ -114,6 +159,22 @@ impl LuatResponse {
         }
     }
 
+    /// Creates a binary response with headers.
+    ///
+    /// Adapters should send `application/octet-stream` when `headers` has no
+    /// `content-type`.
+    pub fn bytes_with_headers(
+        status: u16,
+        body: impl Into<Vec<u8>>,
+        headers: HashMap<String, String>,
+    ) -> Self {
+        Self::Bytes {
+            status,
+            headers,
+            body: body.into(),
+        }
+    }
+
     /// Creates a redirect response (HTTP 302 by default).
     pub fn redirect(location: impl Into<String>) -> Self {
         Self::Redirect {
@@ -158,6 +219,8 @@ impl LuatResponse {
         match self {
             Self::Html { status, .. } => *status,
             Self::Json { status, .. } => *status,
+            Self::Bytes { status, .. } => *status,
+            Self::Stream { status, .. } => *status,
             Self::Redirect { status, .. } => *status,
             Self::Error { status, .. } => *status,
         }
@@ -180,10 +243,13 @@ impl LuatResponse {
         (300..400).contains(&status)
     }
 
-    /// Adds a header to the response (only for Html and Json variants).
+    /// Adds a header to the response (only for Html, Json, Bytes and Stream variants).
     pub fn with_header(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
         match &mut self {
-            Self::Html { headers, .. } | Self::Json { headers, .. } => {
+            Self::Html { headers, .. }
+            | Self::Json { headers, .. }
+            | Self::Bytes { headers, .. }
+            | Self::Stream { headers, .. } => {
                 headers.insert(key.into(), value.into());
             }
             _ => {}
@@ -192,6 +258,60 @@ impl LuatResponse {
     }
 }
 
+/// Returns true if `headers` contains `name`, compared case-insensitively.
+pub(crate) fn has_header(headers: &HashMap<String, String>, name: &str) -> bool {
+    headers.keys().any(|key| key.eq_ignore_ascii_case(name))
+}
+
+/// Sets a header, replacing any existing one whose name differs only in case.
+pub(crate) fn set_header(headers: &mut HashMap<String, String>, name: String, value: String) {
+    headers.retain(|key, _| !key.eq_ignore_ascii_case(&name));
+    headers.insert(name, value);
+}
+
+/// Returns the reason phrase for common HTTP status codes, e.g. `Not Found`.
+pub fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        410 => "Gone",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ if (400..500).contains(&status) => "Client Error",
+        _ => "Server Error",
+    }
+}
+
+/// Serializes the body of a [`LuatResponse::Json`] for the wire.
+///
+/// A string body sent with a non-JSON `content-type` header (an RSS feed from
+/// `feed.rss`, CSV, plain text) is written verbatim; anything else is encoded
+/// as JSON.
+pub fn encode_json_body(body: &JsonValue, headers: &HashMap<String, String>) -> String {
+    let content_type = headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| value.to_ascii_lowercase());
+
+    match (body, content_type) {
+        (JsonValue::String(text), Some(content_type)) if !content_type.contains("json") => {
+            text.clone()
+        }
+        _ => serde_json::to_string(body).unwrap_or_default(),
+    }
+}
+
 impl Default for LuatResponse {
     fn default() -> Self {
         Self::html(200, "")
@@ -215,6 +335,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_encode_json_body() {
+        let mut headers = HashMap::new();
+        let body = JsonValue::String("<rss/>".to_string());
+        assert_eq!(encode_json_body(&body, &headers), "\"<rss/>\"");
+
+        headers.insert("Content-Type".to_string(), "application/rss+xml".to_string());
+        assert_eq!(encode_json_body(&body, &headers), "<rss/>");
+
+        let object = serde_json::json!({ "ok": true });
+        assert_eq!(encode_json_body(&object, &headers), r#"{"ok":true}"#);
+    }
+
     #[test]
     fn test_json_response() {
         let resp = LuatResponse::json(200, serde_json::json!({"success": true}));
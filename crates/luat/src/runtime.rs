@@ -29,6 +29,12 @@ pub struct LoadResult {
 
     /// Optional HTTP status code
     pub status: Option<u16>,
+
+    /// IDs of deferred values created with `ctx.defer()`, in creation order
+    pub deferred: Vec<String>,
+
+    /// Response headers exported by the module as a `headers` table
+    pub headers: HashMap<String, String>,
 }
 
 impl Default for LoadResult {
@@ -37,10 +43,75 @@ impl Default for LoadResult {
             props: JsonValue::Object(serde_json::Map::new()),
             redirect: None,
             status: None,
+            deferred: Vec::new(),
+            headers: HashMap::new(),
         }
     }
 }
 
+/// Registry key of the table holding pending deferred values (id -> function/thread).
+const DEFERRED_REGISTRY_KEY: &str = "__luat_deferred";
+
+/// Registry key of the table holding the `{#await}` fragments of pending
+/// deferred values (id -> list of fragments).
+const DEFERRED_FRAGMENTS_REGISTRY_KEY: &str = "__luat_deferred_fragments";
+
+/// Registry key of the counter used to generate unique deferred IDs.
+const DEFERRED_SEQ_REGISTRY_KEY: &str = "__luat_deferred_seq";
+
+/// Key used in props to mark a value that will be resolved after the shell is sent.
+pub const DEFERRED_MARKER_KEY: &str = "__luat_deferred";
+
+/// Returns the registry table of pending deferred values, creating it if needed.
+fn deferred_table(lua: &Lua) -> LuaResult<Table> {
+    registry_table(lua, DEFERRED_REGISTRY_KEY)
+}
+
+/// Returns the table of `{#await}` fragments waiting for deferred values
+/// (id -> list of `{ slot, then, catch }`), creating it if needed.
+///
+/// Page renders get it as `runtime.deferred_fragments`; see
+/// [`Engine::resolve_deferred`](crate::Engine::resolve_deferred).
+pub fn deferred_fragments_table(lua: &Lua) -> LuaResult<Table> {
+    registry_table(lua, DEFERRED_FRAGMENTS_REGISTRY_KEY)
+}
+
+/// Returns the table stored in the registry under `key`, creating it if needed.
+fn registry_table(lua: &Lua, key: &str) -> LuaResult<Table> {
+    match lua.named_registry_value::<Option<Table>>(key)? {
+        Some(table) => Ok(table),
+        None => {
+            let table = lua.create_table()?;
+            lua.set_named_registry_value(key, table.clone())?;
+            Ok(table)
+        }
+    }
+}
+
+/// Collects deferred IDs from placeholder objects nested anywhere in `value`.
+fn collect_deferred_ids(value: &JsonValue, ids: &mut Vec<String>) {
+    match value {
+        JsonValue::Object(map) => {
+            if let Some(JsonValue::String(id)) = map.get(DEFERRED_MARKER_KEY) {
+                ids.push(id.clone());
+                return;
+            }
+            for v in map.values() {
+                collect_deferred_ids(v, ids);
+            }
+        }
+        JsonValue::Array(arr) => {
+            for v in arr {
+                collect_deferred_ids(v, ids);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// HTTP methods a `+server.lua` handler can export.
+const API_METHODS: [&str; 7] = ["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"];
+
 /// Result of running an API handler.
 #[derive(Debug, Clone)]
 pub struct ApiResult {
@@ -52,6 +123,10 @@ pub struct ApiResult {
 
     /// Response headers
     pub headers: HashMap<String, String>,
+
+    /// Raw body, used instead of `body` when the handler returns a string
+    /// body that isn't valid UTF-8 (e.g. image bytes)
+    pub bytes: Option<Vec<u8>>,
 }
 
 impl Default for ApiResult {
@@ -60,6 +135,7 @@ impl Default for ApiResult {
             status: 200,
             body: JsonValue::Null,
             headers: HashMap::new(),
+            bytes: None,
         }
     }
 }
@@ -78,10 +154,32 @@ impl ApiResult {
                 map
             }),
             headers: HashMap::new(),
+            bytes: None,
+        }
+    }
+
+    /// Creates the answer to an `OPTIONS` request the handler doesn't export,
+    /// with an empty body.
+    pub fn options(allow: String) -> Self {
+        Self {
+            status: 204,
+            body: JsonValue::Null,
+            headers: HashMap::from([("Allow".to_string(), allow)]),
+            bytes: Some(Vec::new()),
         }
     }
 }
 
+/// What a `+server.lua` module declares about its API, for documentation.
+#[derive(Debug, Clone, Default)]
+pub struct ApiSpec {
+    /// Methods the module exports a handler for, e.g. `["GET", "POST"]`
+    pub methods: Vec<String>,
+
+    /// The exported `spec` table, or `null` if there is none
+    pub spec: JsonValue,
+}
+
 /// Runtime executor for Lua code.
 ///
 /// This struct provides methods to execute server-side Lua code
@@ -139,12 +237,17 @@ impl<'lua> Runtime<'lua> {
             .set_environment(env.clone())
             .exec()?;
 
+        let headers = Self::exported_headers(&env);
+
         // Now check for load function in our env (not inherited from globals)
         let load_fn: Option<Function> = env.raw_get("load").ok();
 
         let Some(load_fn) = load_fn else {
             // No load function defined in this source
-            return Ok(LoadResult::default());
+            return Ok(LoadResult {
+                headers,
+                ..LoadResult::default()
+            });
         };
 
         // Create context table for Lua
@@ -154,7 +257,9 @@ impl<'lua> Runtime<'lua> {
         let result: Value = load_fn.call(ctx_table)?;
 
         // Parse the result
-        self.parse_load_result(result)
+        let mut result = self.parse_load_result(result)?;
+        result.headers = headers;
+        Ok(result)
     }
 
     /// Runs an API handler (GET, POST, etc.) from Lua source code.
@@ -182,28 +287,23 @@ impl<'lua> Runtime<'lua> {
         let globals = self.lua.globals();
         let _ = globals.set("__luat_current_module", name);
 
-        // Create an environment table that inherits from globals
-        let globals = self.lua.globals();
-        let env = self.lua.create_table()?;
-
-        // Set metatable so env inherits from globals
-        let mt = self.lua.create_table()?;
-        mt.set("__index", globals.clone())?;
-        env.set_metatable(Some(mt));
-
-        // Execute the source in our custom environment
-        self.lua
-            .load(source)
-            .set_name(name)
-            .set_environment(env.clone())
-            .exec()?;
+        let env = self.load_module_env(source, name)?;
 
-        // Get the handler function based on method
+        // Get the handler function based on method; HEAD falls back to GET
         let method = &request.method;
-        let handler_fn: Option<Function> = env.raw_get(method.as_str()).ok();
+        let handler_fn: Option<Function> = env
+            .raw_get(method.as_str())
+            .ok()
+            .or_else(|| method.eq_ignore_ascii_case("HEAD").then(|| env.raw_get("GET").ok()).flatten());
 
         let Some(handler_fn) = handler_fn else {
-            return Ok(ApiResult::method_not_allowed(method));
+            let allow = Self::allowed_methods(&env).join(", ");
+            if method.eq_ignore_ascii_case("OPTIONS") {
+                return Ok(ApiResult::options(allow));
+            }
+            let mut result = ApiResult::method_not_allowed(method);
+            result.headers.insert("Allow".to_string(), allow);
+            return Ok(result);
         };
 
         // Create context table for Lua
@@ -212,8 +312,80 @@ impl<'lua> Runtime<'lua> {
         // Call the handler function
         let result: Value = handler_fn.call(ctx_table)?;
 
-        // Parse the result
-        self.parse_api_result(result)
+        // Parse the result; headers the handler returns win over exported ones
+        let mut result = self.parse_api_result(result)?;
+        for (name, value) in Self::exported_headers(&env) {
+            if !crate::response::has_header(&result.headers, &name) {
+                result.headers.insert(name, value);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Reads the handlers and `spec` table a `+server.lua` module exports,
+    /// without calling any handler.
+    ///
+    /// ```lua
+    /// spec = {
+    ///     tags = { "greetings" },
+    ///     GET = { summary = "Say hello" },
+    /// }
+    ///
+    /// function GET(ctx) ... end
+    /// ```
+    pub fn api_spec(&self, source: &str, name: &str) -> LuaResult<ApiSpec> {
+        self.lua.set_named_registry_value("__luat_current_module", name)?;
+        let _ = self.lua.globals().set("__luat_current_module", name);
+        let env = self.load_module_env(source, name)?;
+
+        let methods = API_METHODS
+            .into_iter()
+            .filter(|method| env.raw_get::<Function>(*method).is_ok())
+            .map(str::to_string)
+            .collect();
+        let spec = match env.raw_get::<Value>("spec")? {
+            value @ Value::Table(_) => self.lua_to_json(&value)?,
+            _ => JsonValue::Null,
+        };
+        Ok(ApiSpec { methods, spec })
+    }
+
+    /// Runs a server module in its own environment that inherits from
+    /// globals, and returns the environment with what the module defined.
+    fn load_module_env(&self, source: &str, name: &str) -> LuaResult<Table> {
+        let env = self.lua.create_table()?;
+        let mt = self.lua.create_table()?;
+        mt.set("__index", self.lua.globals())?;
+        env.set_metatable(Some(mt));
+
+        self.lua
+            .load(source)
+            .set_name(name)
+            .set_environment(env.clone())
+            .exec()?;
+        Ok(env)
+    }
+
+    /// Reads the `headers` table a server module exports, e.g.
+    /// `headers = { ["Cache-Control"] = "public, max-age=300" }`.
+    fn exported_headers(env: &Table) -> HashMap<String, String> {
+        match env.raw_get::<Table>("headers") {
+            Ok(headers) => headers.pairs::<String, String>().flatten().collect(),
+            Err(_) => HashMap::new(),
+        }
+    }
+
+    /// Methods an API handler answers, for the `Allow` header.
+    fn allowed_methods(env: &Table) -> Vec<&'static str> {
+        let defines = |method: &str| env.raw_get::<Function>(method).is_ok();
+        API_METHODS
+            .into_iter()
+            .filter(|method| match *method {
+                "HEAD" => defines("HEAD") || defines("GET"),
+                "OPTIONS" => true,
+                method => defines(method),
+            })
+            .collect()
     }
 
     /// Creates a Lua context table from a request.
@@ -345,9 +517,96 @@ impl<'lua> Runtime<'lua> {
         ctx.set("setPageContext", set_page_context)?;
         ctx.set("getPageContext", get_page_context)?;
 
+        // Add defer() for values resolved after the page shell has been sent.
+        // Accepts a function or a coroutine; returns a placeholder table that
+        // ends up in props as { __luat_deferred = "<id>" }.
+        let defer = self.lua.create_function(|lua, pending: Value| {
+            if !matches!(pending, Value::Function(_) | Value::Thread(_)) {
+                return Err(mlua::Error::runtime(
+                    "defer() expects a function or a coroutine",
+                ));
+            }
+
+            let seq = lua
+                .named_registry_value::<Option<i64>>(DEFERRED_SEQ_REGISTRY_KEY)?
+                .unwrap_or(0)
+                + 1;
+            lua.set_named_registry_value(DEFERRED_SEQ_REGISTRY_KEY, seq)?;
+
+            let id = format!("luat-d-{}", seq);
+            deferred_table(lua)?.set(id.as_str(), pending)?;
+
+            let placeholder = lua.create_table()?;
+            placeholder.set(DEFERRED_MARKER_KEY, id)?;
+            Ok(placeholder)
+        })?;
+
+        ctx.set("defer", defer)?;
+
         Ok(ctx)
     }
 
+    /// Resolves a deferred value created with `ctx.defer()` during a load function.
+    ///
+    /// Functions are called once; coroutines are resumed until they finish,
+    /// failing once they have been resumed as often as `{#await}` allows
+    /// (10000 times). The pending entry is removed whether or not resolution
+    /// succeeds.
+    pub fn resolve_deferred(&self, id: &str) -> LuaResult<JsonValue> {
+        let value = self.resolve_deferred_value(id)?;
+        self.lua_to_json(&value)
+    }
+
+    /// Like [`Runtime::resolve_deferred`], but returns the Lua value.
+    pub fn resolve_deferred_value(&self, id: &str) -> LuaResult<Value> {
+        let pending_table = deferred_table(self.lua)?;
+        let pending: Value = pending_table.get(id)?;
+        pending_table.set(id, Value::Nil)?;
+
+        match pending {
+            Value::Function(func) => func.call::<Value>(()),
+            Value::Thread(thread) => {
+                let mut last = Value::Nil;
+                let mut resumes = 0;
+                while thread.status() == mlua::ThreadStatus::Resumable {
+                    if resumes == crate::codegen::AWAIT_MAX_RESUMES {
+                        return Err(mlua::Error::runtime(format!(
+                            "Deferred value '{}' still running after {} resumes",
+                            id,
+                            crate::codegen::AWAIT_MAX_RESUMES
+                        )));
+                    }
+                    last = thread.resume::<Value>(())?;
+                    resumes += 1;
+                }
+                Ok(last)
+            }
+            _ => Err(mlua::Error::runtime(format!(
+                "No pending deferred value with id '{}'",
+                id
+            ))),
+        }
+    }
+
+    /// Removes and returns the `{#await}` fragments waiting for `id`.
+    pub fn take_deferred_fragments(&self, id: &str) -> LuaResult<Vec<Table>> {
+        let fragments = deferred_fragments_table(self.lua)?;
+        let waiting: Option<Vec<Table>> = fragments.get(id)?;
+        fragments.set(id, Value::Nil)?;
+        Ok(waiting.unwrap_or_default())
+    }
+
+    /// Drops pending deferred values without resolving them.
+    pub fn discard_deferred(&self, ids: &[String]) -> LuaResult<()> {
+        let pending_table = deferred_table(self.lua)?;
+        let fragments = deferred_fragments_table(self.lua)?;
+        for id in ids {
+            pending_table.set(id.as_str(), Value::Nil)?;
+            fragments.set(id.as_str(), Value::Nil)?;
+        }
+        Ok(())
+    }
+
     /// Parses a Lua value into LoadResult.
     fn parse_load_result(&self, value: Value) -> LuaResult<LoadResult> {
         let mut result = LoadResult::default();
@@ -365,6 +624,7 @@ impl<'lua> Runtime<'lua> {
 
                 // Convert to JSON props (excluding special keys)
                 result.props = self.table_to_json_excluding(&table, &["redirect", "status"])?;
+                collect_deferred_ids(&result.props, &mut result.deferred);
             }
             Value::Nil => {
                 // Return empty props
@@ -389,9 +649,14 @@ impl<'lua> Runtime<'lua> {
                     result.status = status;
                 }
 
-                // Check for body
+                // Check for body; strings that aren't UTF-8 are sent as raw bytes
                 if let Ok(body) = table.get::<Value>("body") {
-                    result.body = self.lua_to_json(&body)?;
+                    match body {
+                        Value::String(ref bytes) if bytes.to_str().is_err() => {
+                            result.bytes = Some(bytes.as_bytes().to_vec());
+                        }
+                        body => result.body = self.lua_to_json(&body)?,
+                    }
                 } else {
                     // If no body key, the whole table is the body
                     result.body = self.table_to_json_excluding(&table, &["status", "headers"])?;
@@ -457,7 +722,7 @@ impl<'lua> Runtime<'lua> {
 
     /// Converts a Lua value to JSON.
     #[allow(clippy::only_used_in_recursion)]
-    fn lua_to_json(&self, value: &Value) -> LuaResult<JsonValue> {
+    pub(crate) fn lua_to_json(&self, value: &Value) -> LuaResult<JsonValue> {
         Ok(match value {
             Value::Nil => JsonValue::Null,
             Value::Boolean(b) => JsonValue::Bool(*b),
@@ -599,6 +864,66 @@ mod tests {
         assert_eq!(result.redirect, Some("/login".to_string()));
     }
 
+    #[test]
+    fn test_run_load_with_deferred() {
+        let lua = Lua::new();
+        let runtime = Runtime::new(&lua);
+
+        let source = r#"
+            function load(ctx)
+                return {
+                    title = "Post",
+                    comments = ctx.defer(function() return { "first", "second" } end),
+                    related = ctx.defer(coroutine.create(function()
+                        coroutine.yield("partial")
+                        return "done"
+                    end)),
+                }
+            end
+        "#;
+
+        let request = LuatRequest::new("/post", "GET");
+        let params = HashMap::new();
+
+        let result = runtime.run_load(source, "test", &request, &params).unwrap();
+        assert_eq!(result.props["title"], "Post");
+        assert_eq!(result.deferred.len(), 2);
+
+        let comments_id = result.props["comments"][DEFERRED_MARKER_KEY].as_str().unwrap();
+        let comments = runtime.resolve_deferred(comments_id).unwrap();
+        assert_eq!(comments, serde_json::json!(["first", "second"]));
+
+        let related_id = result.props["related"][DEFERRED_MARKER_KEY].as_str().unwrap();
+        assert_eq!(runtime.resolve_deferred(related_id).unwrap(), "done");
+
+        // Deferred values resolve only once
+        assert!(runtime.resolve_deferred(comments_id).is_err());
+    }
+
+    #[test]
+    fn test_resolve_deferred_caps_resumes() {
+        let lua = Lua::new();
+        let runtime = Runtime::new(&lua);
+
+        let source = r#"
+            function load(ctx)
+                return {
+                    forever = ctx.defer(coroutine.create(function()
+                        while true do coroutine.yield() end
+                    end)),
+                }
+            end
+        "#;
+
+        let request = LuatRequest::new("/post", "GET");
+        let params = HashMap::new();
+
+        let result = runtime.run_load(source, "test", &request, &params).unwrap();
+        let id = result.props["forever"][DEFERRED_MARKER_KEY].as_str().unwrap();
+        let err = runtime.resolve_deferred(id).unwrap_err().to_string();
+        assert!(err.contains("still running after"), "{}", err);
+    }
+
     #[test]
     fn test_run_load_no_function() {
         let lua = Lua::new();
@@ -655,6 +980,27 @@ mod tests {
         assert!(result.body["error"].as_str().unwrap().contains("POST"));
     }
 
+    #[test]
+    fn test_api_spec() {
+        let lua = Lua::new();
+        let runtime = Runtime::new(&lua);
+
+        let source = r#"
+            spec = { tags = { "items" }, POST = { summary = "Create an item" } }
+            function GET(ctx) return {} end
+            function POST(ctx) return {} end
+        "#;
+
+        let api = runtime.api_spec(source, "test").unwrap();
+        assert_eq!(api.methods, ["GET", "POST"]);
+        assert_eq!(api.spec["tags"][0], "items");
+        assert_eq!(api.spec["POST"]["summary"], "Create an item");
+
+        let api = runtime.api_spec("function DELETE(ctx) end", "test").unwrap();
+        assert_eq!(api.methods, ["DELETE"]);
+        assert!(api.spec.is_null());
+    }
+
     #[test]
     fn test_lua_to_json_array() {
         let lua = Lua::new();
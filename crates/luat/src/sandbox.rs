@@ -0,0 +1,213 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Sandbox profiles for the engine's Lua state.
+//!
+//! A [`SandboxProfile`] decides how much of the Lua standard library
+//! templates and server modules can use:
+//!
+//! | Profile    | `io`, `debug`, `load` | `os`                    | `require` of Lua modules | `string.rep`  |
+//! |------------|-----------------------|-------------------------|--------------------------|---------------|
+//! | `Trusted`  | yes                   | all                     | yes                      | unlimited     |
+//! | `Standard` | no                    | date, time, clock, diff | yes                      | unlimited     |
+//! | `Strict`   | no                    | date, time, clock, diff | no                       | 1 MiB results |
+//!
+//! `Standard` is the default. `Strict` suits engines rendering templates
+//! written by users: templates can still use components and the built-in
+//! modules such as `json`, but not load Lua modules. `Custom` picks each
+//! setting through [`SandboxOptions`].
+//!
+//! ```rust,ignore
+//! use luat::{Engine, SandboxProfile};
+//!
+//! let engine = Engine::builder(resolver)
+//!     .sandbox_profile(SandboxProfile::Strict)
+//!     .build()?;
+//! ```
+
+use mlua::{Function, Lua, Table, Value};
+
+use crate::error::Result;
+
+/// Longest string `string.rep` builds under [`SandboxProfile::Strict`].
+pub const STRICT_MAX_STRING_REP: usize = 1024 * 1024;
+
+/// How much of the Lua standard library the engine exposes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SandboxProfile {
+    /// For user-authored templates: [`SandboxProfile::Standard`] without
+    /// `require` of Lua modules, and `string.rep` limited to
+    /// [`STRICT_MAX_STRING_REP`] bytes.
+    Strict,
+    /// Removes `io`, `debug`, `load`, `loadstring`, `loadfile`, `dofile`
+    /// and all of `os` but `os.date`, `os.time`, `os.clock` and
+    /// `os.difftime`.
+    #[default]
+    Standard,
+    /// No sandbox: for trusted templates and server code only, which can
+    /// then read and write files and run arbitrary Lua.
+    Trusted,
+    /// Each setting picked separately.
+    Custom(SandboxOptions),
+}
+
+impl SandboxProfile {
+    /// Returns the settings of the profile.
+    pub fn options(&self) -> SandboxOptions {
+        match self {
+            Self::Strict => SandboxOptions {
+                require_modules: false,
+                max_string_rep: Some(STRICT_MAX_STRING_REP),
+                ..SandboxOptions::default()
+            },
+            Self::Standard => SandboxOptions::default(),
+            Self::Trusted => SandboxOptions {
+                io: true,
+                os: true,
+                debug: true,
+                load: true,
+                require_modules: true,
+                max_string_rep: None,
+            },
+            Self::Custom(options) => options.clone(),
+        }
+    }
+}
+
+/// Settings of a [`SandboxProfile::Custom`] sandbox.
+///
+/// The default is the [`SandboxProfile::Standard`] sandbox.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SandboxOptions {
+    /// Keeps the `io` library.
+    pub io: bool,
+    /// Keeps all of `os`; otherwise only `os.date`, `os.time`, `os.clock`
+    /// and `os.difftime`.
+    pub os: bool,
+    /// Keeps the `debug` library.
+    pub debug: bool,
+    /// Keeps `load`, `loadstring`, `loadfile` and `dofile`.
+    pub load: bool,
+    /// Lets `require` load Lua modules; otherwise it only loads `.luat`
+    /// components and the built-in modules.
+    pub require_modules: bool,
+    /// Longest string `string.rep` may build, in bytes.
+    pub max_string_rep: Option<usize>,
+}
+
+impl Default for SandboxOptions {
+    fn default() -> Self {
+        Self {
+            io: false,
+            os: false,
+            debug: false,
+            load: false,
+            require_modules: true,
+            max_string_rep: None,
+        }
+    }
+}
+
+/// Registry key holding `load` for the bundle's module loader.
+pub(crate) const INTERNAL_LOAD_REGISTRY_KEY: &str = "__luat_internal_load";
+
+/// Removes the globals `options` doesn't allow.
+///
+/// Runs before the engine registers its modules.
+pub(crate) fn restrict_globals(lua: &Lua, options: &SandboxOptions) -> Result<()> {
+    let globals = lua.globals();
+
+    // The bundle's module loader needs `load` even when user code can't use
+    // it; the engine passes it to the bundle, so it's no global
+    let load_fn: Function = globals.get("load")?;
+    lua.set_named_registry_value(INTERNAL_LOAD_REGISTRY_KEY, load_fn)?;
+
+    if !options.io {
+        globals.set("io", Value::Nil)?;
+    }
+    if !options.debug {
+        globals.set("debug", Value::Nil)?;
+    }
+    if !options.load {
+        for name in ["load", "loadstring", "loadfile", "dofile"] {
+            globals.set(name, Value::Nil)?;
+        }
+    }
+    if !options.os {
+        let os: Table = globals.get("os")?;
+        let safe_os = lua.create_table()?;
+        for name in ["date", "time", "clock", "difftime"] {
+            safe_os.set(name, os.get::<Function>(name)?)?;
+        }
+        globals.set("os", safe_os)?;
+    }
+    if let Some(max) = options.max_string_rep {
+        limit_string_rep(lua, max)?;
+    }
+    Ok(())
+}
+
+/// Limits `require` to components and built-in modules unless `options`
+/// allows Lua modules.
+///
+/// Runs after the engine has installed its module searchers.
+pub(crate) fn restrict_require(lua: &Lua, options: &SandboxOptions) -> Result<()> {
+    if options.require_modules {
+        return Ok(());
+    }
+    // Searchers after the first (`package.preload`) may only return `.luat`
+    // components; anything else reads as "not found"
+    let package: Table = lua.globals().get("package")?;
+    let searchers: Table = package.get("searchers")?;
+    for i in 2..=searchers.raw_len() {
+        let searcher: Function = searchers.raw_get(i)?;
+        let guarded = lua.create_function(move |lua, name: String| {
+            let (loader, path): (Value, Value) = searcher.call(name.as_str())?;
+            let Value::Function(function) = &loader else {
+                return Ok((loader, path));
+            };
+            let path_is_component = match &path {
+                Value::String(path) => path.to_str().is_ok_and(|path| path.ends_with(".luat")),
+                _ => false,
+            };
+            let chunk_is_component = function
+                .info()
+                .source
+                .is_some_and(|source| source.ends_with(".luat"));
+            if path_is_component || chunk_is_component {
+                return Ok((loader, path));
+            }
+            let message = format!("\n\tLua module '{}' is not allowed by the sandbox", name);
+            Ok((Value::String(lua.create_string(message)?), Value::Nil))
+        })?;
+        searchers.raw_set(i, guarded)?;
+    }
+    Ok(())
+}
+
+/// Makes `string.rep` fail instead of building a string over `max` bytes.
+fn limit_string_rep(lua: &Lua, max: usize) -> Result<()> {
+    let string: Table = lua.globals().get("string")?;
+    let rep: Function = string.get("rep")?;
+    string.set(
+        "rep",
+        lua.create_function(move |_, (s, n, sep): (mlua::String, i64, Option<mlua::String>)| {
+            let count = usize::try_from(n).unwrap_or(0);
+            let sep_len = sep.as_ref().map_or(0, |sep| sep.as_bytes().len());
+            let len = s
+                .as_bytes()
+                .len()
+                .saturating_mul(count)
+                .saturating_add(sep_len.saturating_mul(count.saturating_sub(1)));
+            if len > max {
+                return Err(mlua::Error::RuntimeError(format!(
+                    "string.rep: result of {} bytes exceeds the sandbox limit of {} bytes",
+                    len, max
+                )));
+            }
+            rep.call::<mlua::String>((s, n, sep))
+        })?,
+    )?;
+    Ok(())
+}
@@ -2,7 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0
 // SPDX-License-Identifier: MIT
 
-//! Script content processor for LUAT magic functions.
+//! Script content processor for LUAT magic functions and prop declarations.
+
+use regex::Regex;
+use std::sync::OnceLock;
 
 /// Processes script content to transform LUAT magic functions like `$state()` and `$derived()`.
 ///
@@ -118,6 +121,205 @@ pub fn process_script_content(content: &str) -> String {
     output
 }
 
+/// Expands `export let` prop declarations of a component script into
+/// locals bound to the props:
+///
+/// ```lua
+/// export let title = "Untitled"
+/// -- becomes
+/// local title = props.title; if title == nil then title = "Untitled"; props.title = title end
+/// ```
+///
+/// The default is used when the prop is nil, so `false` and `0` are kept,
+/// and is written back so `props.title` sees it too. The script then works
+/// on a shallow copy of the props, so the caller's table isn't changed.
+/// Without a default the local is bound to the prop as is. A default may
+/// span several lines; declarations in comments and strings are left alone
+/// and script line numbers don't change.
+pub fn expand_prop_declarations(content: &str) -> String {
+    static DECLARATION: OnceLock<Regex> = OnceLock::new();
+    let declaration = DECLARATION.get_or_init(|| {
+        Regex::new(r"^([ \t]*)export[ \t]+let[ \t]+([A-Za-z_][A-Za-z0-9_]*)[ \t]*(=)?")
+            .expect("prop declaration pattern is valid")
+    });
+
+    let lexemes = lexemes(content);
+    let mut output = String::new();
+    let mut copies_props = false;
+    let mut pos = 0;
+    while pos < content.len() {
+        let line_end = content[pos..].find('\n').map_or(content.len(), |i| pos + i);
+        let captures = (lexemes[pos] == Lexeme::Code).then(|| declaration.captures(&content[pos..line_end])).flatten();
+        let Some(captures) = captures else {
+            output.push_str(&content[pos..line_end]);
+            pos = line_end;
+            if pos < content.len() {
+                output.push('\n');
+                pos += 1;
+            }
+            continue;
+        };
+
+        let (indent, name) = (&captures[1], &captures[2]);
+        let after = pos + captures[0].len();
+        let (default_end, statement_end) = match captures.get(3) {
+            Some(assign) => default_span(content, &lexemes, pos + assign.end()),
+            None => (after, line_end),
+        };
+        let default = content[after..default_end].trim();
+        if default.is_empty() {
+            output.push_str(&format!("{indent}local {name} = props.{name}"));
+        } else {
+            copies_props = true;
+            output.push_str(&format!(
+                "{indent}local {name} = props.{name}; if {name} == nil then {name} = {default}; props.{name} = {name} end"
+            ));
+        }
+        let rest = &content[default_end..statement_end];
+        output.push_str(rest.trim_start().strip_prefix(';').unwrap_or(rest));
+        pos = statement_end;
+    }
+
+    if copies_props {
+        output.insert_str(
+            0,
+            "do local __props = {} for k, v in pairs(props) do __props[k] = v end props = __props end ",
+        );
+    }
+    output
+}
+
+/// Returns the end of the default expression starting at `start` and of
+/// its statement: the expression runs to a `;` or the end of the first line
+/// on which its brackets and blocks are closed and which doesn't end in an
+/// operator, without a trailing comment.
+fn default_span(content: &str, lexemes: &[Lexeme], start: usize) -> (usize, usize) {
+    let bytes = content.as_bytes();
+    let mut depth = 0i32;
+    let mut end = start;
+    let mut i = start;
+    while i < bytes.len() {
+        match lexemes[i] {
+            Lexeme::Code => {}
+            Lexeme::String => {
+                end = i + 1;
+                i += 1;
+                continue;
+            }
+            Lexeme::Comment => {
+                i += 1;
+                continue;
+            }
+        }
+        let c = bytes[i];
+        if c == b'\n' {
+            if depth <= 0 && end > start && !ends_in_operator(&content[start..end]) {
+                return (end, i);
+            }
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            let word_end = content[i..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .map_or(bytes.len(), |n| i + n);
+            match &content[i..word_end] {
+                "function" | "if" | "do" | "repeat" => depth += 1,
+                "end" | "until" => depth -= 1,
+                _ => {}
+            }
+            end = word_end;
+            i = word_end;
+            continue;
+        } else if c == b';' && depth <= 0 {
+            return (end, i + 1);
+        } else if !c.is_ascii_whitespace() {
+            match c {
+                b'(' | b'{' | b'[' => depth += 1,
+                b')' | b'}' | b']' => depth -= 1,
+                _ => {}
+            }
+            end = i + 1;
+        }
+        i += 1;
+    }
+    (end, bytes.len())
+}
+
+/// Whether an expression is continued on the next line: it ends in a
+/// binary operator, a `,` or `=`.
+fn ends_in_operator(expression: &str) -> bool {
+    let expression = expression.trim_end();
+    let word = expression
+        .rsplit(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .next()
+        .unwrap_or_default();
+    matches!(word, "and" | "or" | "not")
+        || expression.ends_with(['+', '-', '*', '/', '%', '^', '<', '>', '=', '~', '.', ',', '&', '|'])
+}
+
+/// What a byte of a Lua chunk is part of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lexeme {
+    Code,
+    String,
+    Comment,
+}
+
+/// Classifies each byte of a Lua chunk. Line breaks ending a line comment
+/// are code.
+fn lexemes(content: &str) -> Vec<Lexeme> {
+    let bytes = content.as_bytes();
+    let mut lexemes = vec![Lexeme::Code; bytes.len()];
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let lexeme = match bytes[i] {
+            b'"' | b'\'' => {
+                let quote = bytes[i];
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote && bytes[i] != b'\n' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i = (i + 1).min(bytes.len());
+                Lexeme::String
+            }
+            b'[' if long_bracket(&bytes[i..]).is_some() => {
+                i = long_bracket_end(bytes, i);
+                Lexeme::String
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                if long_bracket(&bytes[i + 2..]).is_some() {
+                    i = long_bracket_end(bytes, i + 2);
+                } else {
+                    i = content[i..].find('\n').map_or(bytes.len(), |n| i + n);
+                }
+                Lexeme::Comment
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        lexemes[start..i].fill(lexeme);
+    }
+    lexemes
+}
+
+/// Returns the level of the long bracket `[[`, `[=[`, ... `bytes` starts with.
+fn long_bracket(bytes: &[u8]) -> Option<usize> {
+    let level = bytes.iter().skip(1).take_while(|&&b| b == b'=').count();
+    (bytes.first() == Some(&b'[') && bytes.get(level + 1) == Some(&b'[')).then_some(level)
+}
+
+/// Returns the end of the long string or comment opened at `start`.
+fn long_bracket_end(bytes: &[u8], start: usize) -> usize {
+    let level = long_bracket(&bytes[start..]).unwrap_or_default();
+    let close = format!("]{}]", "=".repeat(level));
+    let body = start + level + 2;
+    bytes[body..]
+        .windows(close.len())
+        .position(|w| w == close.as_bytes())
+        .map_or(bytes.len(), |n| body + n + close.len())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -149,4 +351,48 @@ mod tests {
         let expected = "-- LUAT magic function $init will be implemented in future\nlocal something = nil";
         assert_eq!(process_script_content(input), expected);
     }
+
+    #[test]
+    fn test_expand_prop_declarations() {
+        let copy = "do local __props = {} for k, v in pairs(props) do __props[k] = v end props = __props end ";
+        let input = "  export let title = \"Untitled\" -- shown in the header\nexport let count = 0;\nexport let items\nlocal x = 1";
+        let expected = format!(
+            "{copy}  local title = props.title; if title == nil then title = \"Untitled\"; props.title = title end -- shown in the header\n\
+            local count = props.count; if count == nil then count = 0; props.count = count end\n\
+            local items = props.items\n\
+            local x = 1"
+        );
+        assert_eq!(expand_prop_declarations(input), expected);
+        assert_eq!(
+            expand_prop_declarations("export let s = \"a -- b\""),
+            format!("{copy}local s = props.s; if s == nil then s = \"a -- b\"; props.s = s end")
+        );
+        assert_eq!(expand_prop_declarations("export let items;"), "local items = props.items");
+    }
+
+    #[test]
+    fn test_expand_multiline_prop_defaults() {
+        let input = "export let tags = {\n  \"a\",\n  \"b\",\n}\nexport let format = function(value)\n  if value then return value end\nend\nexport let label = \"x\" ..\n  \"y\"\nlocal z = 1";
+        let output = expand_prop_declarations(input);
+        assert_eq!(output.lines().count(), input.lines().count());
+        assert!(output.contains("tags = {\n  \"a\",\n  \"b\",\n}; props.tags = tags end\n"), "{}", output);
+        assert!(output.contains("format = function(value)\n  if value then return value end\nend; props.format = format end\n"), "{}", output);
+        assert!(output.contains("label = \"x\" ..\n  \"y\"; props.label = label end\nlocal z = 1"), "{}", output);
+    }
+
+    #[test]
+    fn test_expand_skips_comments_and_strings() {
+        let input = "--[[\nexport let hidden = 1\n]]\nlocal s = [[\nexport let text\n]]\n-- export let note = 2";
+        assert_eq!(expand_prop_declarations(input), input);
+    }
+
+    #[test]
+    fn test_prop_defaults_leave_caller_props_alone() {
+        let lua = mlua::Lua::new();
+        let script = expand_prop_declarations("export let size = \"md\"\nreturn props.size");
+        let render = lua.load(format!("local props = ... {script}")).into_function().unwrap();
+        let props = lua.create_table().unwrap();
+        assert_eq!(render.call::<String>(props.clone()).unwrap(), "md");
+        assert!(props.get::<Option<String>>("size").unwrap().is_none());
+    }
 }
@@ -0,0 +1,329 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Scoped component styles.
+//!
+//! A `<style>` block at the top level of a template styles that template
+//! only. Every element of the template gets a scope class derived from a
+//! hash of the CSS, and every selector is rewritten to require it:
+//!
+//! ```text
+//! <style>.card h2 { margin: 0 }</style>     .card.luat-1a2b3c4d h2.luat-1a2b3c4d { margin: 0 }
+//! <div class="card"><h2>..</h2></div>       <div class="card luat-1a2b3c4d"><h2 class="luat-1a2b3c4d">..</h2></div>
+//! ```
+//!
+//! The scoped CSS is rendered in a `<style>` element ahead of the
+//! template's first render in a page, so it needs no stylesheet link. The
+//! scoped CSS of all templates is also aggregated with
+//! [`Engine::stylesheet`](crate::Engine::stylesheet); `luat build` writes it
+//! to `public/luat.css`.
+//!
+//! `:global(selector)` leaves part of a selector unscoped, and
+//! `<style global>` (or a `<style>` nested in an element) is rendered in
+//! place unchanged. Rules inside `@media`, `@supports`, `@container` and
+//! `@layer` are scoped; other at-rules such as `@keyframes` and
+//! `@font-face` are copied as they are.
+
+use crate::error::{LuatError, Result};
+use sha2::{Digest, Sha256};
+
+/// Scoped CSS of a template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentStyle {
+    /// Class added to the template's elements, e.g. `luat-1a2b3c4d`.
+    pub scope: String,
+    /// The template's CSS with selectors rewritten to require the scope class.
+    pub css: String,
+}
+
+impl ComponentStyle {
+    /// Scopes the CSS of a template's `<style>` blocks.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LuatError::TransformError`] for CSS with unbalanced braces.
+    pub fn new(css: &str) -> Result<Self> {
+        let scope = scope_class(css);
+        let css = scope_css(css, &scope)?;
+        Ok(Self { scope, css })
+    }
+}
+
+/// Returns the scope class for a template with the given CSS.
+pub fn scope_class(css: &str) -> String {
+    let digest = Sha256::digest(css.trim().as_bytes());
+    let hash: String = digest[..4].iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("luat-{}", hash)
+}
+
+/// Returns true if elements with `tag` get the scope class.
+///
+/// Elements that are never rendered as boxes are left alone.
+pub(crate) fn is_scoped_element(tag: &str) -> bool {
+    !matches!(
+        tag,
+        "head" | "meta" | "link" | "title" | "base" | "script" | "style" | "template"
+    )
+}
+
+/// At-rules whose block holds rules to scope.
+const NESTING_AT_RULES: &[&str] = &["media", "supports", "container", "layer", "document"];
+
+/// Rewrites every selector in `css` to require the class `scope`.
+///
+/// # Errors
+///
+/// Returns [`LuatError::TransformError`] for CSS with unbalanced braces.
+pub fn scope_css(css: &str, scope: &str) -> Result<String> {
+    let mut out = String::with_capacity(css.len() + css.len() / 4);
+    scope_rules(css, scope, &mut out)?;
+    Ok(out.trim().to_string())
+}
+
+fn scope_rules(css: &str, scope: &str, out: &mut String) -> Result<()> {
+    let mut rest = css;
+    loop {
+        let Some((prelude, end, terminator)) = split_at_top_level(rest, &['{', ';', '}']) else {
+            if !strip_comments(rest).trim().is_empty() {
+                return Err(invalid_css("expected `{` after a selector"));
+            }
+            return Ok(());
+        };
+        let prelude = strip_comments(prelude);
+        let prelude = prelude.trim();
+        match terminator {
+            ';' => {
+                // Statement at-rules such as @import and @charset
+                out.push_str(prelude);
+                out.push_str(";\n");
+                rest = &rest[end + 1..];
+            }
+            '}' => return Err(invalid_css("unexpected `}`")),
+            _ => {
+                let body_start = end + 1;
+                let body_end = body_start + closing_brace(&rest[body_start..])?;
+                let body = &rest[body_start..body_end];
+                if let Some(at_rule) = prelude.strip_prefix('@') {
+                    let name = at_rule.split(|c: char| c.is_whitespace() || c == '(').next().unwrap_or_default();
+                    out.push_str(prelude);
+                    if NESTING_AT_RULES.contains(&name.to_ascii_lowercase().as_str()) {
+                        out.push_str(" {\n");
+                        scope_rules(body, scope, out)?;
+                        out.push_str("}\n");
+                    } else {
+                        out.push_str(" {");
+                        out.push_str(body);
+                        out.push_str("}\n");
+                    }
+                } else {
+                    let selectors: Vec<String> = split_top_level(prelude, ',')
+                        .into_iter()
+                        .map(|selector| scope_selector(selector.trim(), scope))
+                        .collect();
+                    out.push_str(&selectors.join(", "));
+                    out.push_str(" {");
+                    out.push_str(body);
+                    out.push_str("}\n");
+                }
+                rest = &rest[body_end + 1..];
+            }
+        }
+    }
+}
+
+/// Scopes each compound selector of a complex selector.
+fn scope_selector(selector: &str, scope: &str) -> String {
+    let mut out = String::new();
+    let mut compound = String::new();
+    let mut combinator: Option<char> = None;
+    let mut depth = 0usize;
+    let mut quote: Option<char> = None;
+
+    let flush = |compound: &mut String, combinator: &mut Option<char>, out: &mut String| {
+        if compound.is_empty() {
+            return;
+        }
+        if !out.is_empty() {
+            match combinator.take() {
+                Some(c) => {
+                    out.push(' ');
+                    out.push(c);
+                    out.push(' ');
+                }
+                None => out.push(' '),
+            }
+        }
+        out.push_str(&scope_compound(compound, scope));
+        compound.clear();
+    };
+
+    for c in selector.chars() {
+        if let Some(q) = quote {
+            compound.push(c);
+            if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => {
+                quote = Some(c);
+                compound.push(c);
+            }
+            '(' | '[' => {
+                depth += 1;
+                compound.push(c);
+            }
+            ')' | ']' => {
+                depth = depth.saturating_sub(1);
+                compound.push(c);
+            }
+            c if depth == 0 && c.is_whitespace() => flush(&mut compound, &mut combinator, &mut out),
+            '>' | '+' | '~' if depth == 0 => {
+                flush(&mut compound, &mut combinator, &mut out);
+                combinator = Some(c);
+            }
+            c => compound.push(c),
+        }
+    }
+    flush(&mut compound, &mut combinator, &mut out);
+    out
+}
+
+fn scope_compound(compound: &str, scope: &str) -> String {
+    if let Some(inner) = compound.strip_prefix(":global(").and_then(|c| c.strip_suffix(')')) {
+        return inner.trim().to_string();
+    }
+    if compound == ":root" {
+        return compound.to_string();
+    }
+    // The class goes before a pseudo-element, which must come last
+    let at = compound.find("::").unwrap_or(compound.len());
+    format!("{}.{}{}", &compound[..at], scope, &compound[at..])
+}
+
+/// Finds the first of `terminators` outside strings, comments and brackets.
+///
+/// Returns the text before it, its offset and the terminator.
+fn split_at_top_level<'a>(css: &'a str, terminators: &[char]) -> Option<(&'a str, usize, char)> {
+    let bytes = css.as_bytes();
+    let mut i = 0;
+    let mut depth = 0usize;
+    while i < bytes.len() {
+        let c = bytes[i] as char;
+        match c {
+            '/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = css[i + 2..].find("*/").map(|end| i + 2 + end + 2).unwrap_or(bytes.len());
+                continue;
+            }
+            '"' | '\'' => i = skip_string(bytes, i),
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            c if depth == 0 && terminators.contains(&c) => return Some((&css[..i], i, c)),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Returns the offset of the `}` closing a block whose body starts `css`.
+fn closing_brace(css: &str) -> Result<usize> {
+    let bytes = css.as_bytes();
+    let mut i = 0;
+    let mut depth = 0usize;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = css[i + 2..].find("*/").map(|end| i + 2 + end + 2).unwrap_or(bytes.len());
+                continue;
+            }
+            b'"' | b'\'' => i = skip_string(bytes, i),
+            b'{' => depth += 1,
+            b'}' if depth == 0 => return Ok(i),
+            b'}' => depth -= 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    Err(invalid_css("missing `}`"))
+}
+
+/// Returns the offset of the quote closing the string opened at `start`.
+fn skip_string(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() && bytes[i] != quote {
+        if bytes[i] == b'\\' {
+            i += 1;
+        }
+        i += 1;
+    }
+    i
+}
+
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while let Some((part, end, _)) = split_at_top_level(rest, &[separator]) {
+        parts.push(part);
+        rest = &rest[end + 1..];
+    }
+    parts.push(rest);
+    parts
+}
+
+fn strip_comments(css: &str) -> String {
+    let mut out = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        out.push_str(&rest[..start]);
+        rest = rest[start + 2..].find("*/").map(|end| &rest[start + 2 + end + 2..]).unwrap_or("");
+    }
+    out.push_str(rest);
+    out
+}
+
+fn invalid_css(message: &str) -> LuatError {
+    LuatError::TransformError(format!("Invalid CSS in <style>: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_css() {
+        let css = r#"
+            /* cards */
+            .card h2, .card > p::first-line { margin: 0; }
+            a:hover, input[type="text" i] + label { color: red }
+            :global(body) .card, .menu :global(.open) { display: block }
+            @media (max-width: 600px) { .card { padding: 0 } }
+            @keyframes fade { from { opacity: 0 } to { opacity: 1 } }
+            @import url("theme.css");
+        "#;
+        let scoped = scope_css(css, "s").unwrap();
+        assert!(scoped.contains(".card.s h2.s, .card.s > p.s::first-line { margin: 0; }"), "{}", scoped);
+        assert!(scoped.contains(r#"a:hover.s, input[type="text" i].s + label.s { color: red }"#), "{}", scoped);
+        assert!(scoped.contains("body .card.s, .menu.s .open { display: block }"), "{}", scoped);
+        assert!(scoped.contains("@media (max-width: 600px) {\n.card.s { padding: 0 }\n}"), "{}", scoped);
+        assert!(scoped.contains("@keyframes fade { from { opacity: 0 } to { opacity: 1 } }"), "{}", scoped);
+        assert!(scoped.contains(r#"@import url("theme.css");"#), "{}", scoped);
+    }
+
+    #[test]
+    fn test_invalid_css() {
+        assert!(scope_css(".a { color: red", "s").is_err());
+        assert!(scope_css(".a { } }", "s").is_err());
+        assert!(scope_css(".a", "s").is_err());
+    }
+
+    #[test]
+    fn test_scope_class_is_stable() {
+        let style = ComponentStyle::new(".a { color: red }").unwrap();
+        assert_eq!(style.scope, scope_class("\n.a { color: red }\n"));
+        assert_eq!(style.scope.len(), "luat-".len() + 8);
+    }
+}
@@ -219,6 +219,24 @@ mod local_expression_tests {
         assert!(lua_code.contains("smart_tostring(area)"));
     }
 
+    #[test]
+    fn test_const_tag_scoped_to_block() {
+        let source = r#"{#each props.boxes as box}{@const area = box.w * box.h}<p>{area}</p>{/each}{#if props.show}{@const label = "on"}<b>{label}</b>{:else}{@const label = "off"}<i>{label}</i>{/if}<span>{area}{label}</span>"#;
+
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_template_string("test", source).unwrap();
+        let context = engine
+            .to_value(serde_json::json!({ "boxes": [{ "w": 2, "h": 3 }, { "w": 4, "h": 5 }], "show": false }))
+            .unwrap();
+        let result = engine.render(&module, &context).unwrap();
+        assert_eq!(result, "<p>6</p><p>20</p><i>off</i><span></span>");
+
+        let ast = parse_template("<div>{@const x = 1}</div>").unwrap();
+        let err = transform_ast(ast).unwrap_err();
+        assert!(err.to_string().contains("{@const} is only allowed as an immediate child of a block"));
+    }
+
     #[test]
     fn test_local_tag_illegal_usage_outside_block() {
         let source = r#"
@@ -231,6 +249,24 @@ mod local_expression_tests {
             .to_string()
             .contains("{@local} is only allowed as an immediate child of a block"));
     }
+    #[test]
+    fn test_statement_in_mustache_is_rejected() {
+        let source = "<p>\n  {count = count + 1}\n</p>";
+        let ast = parse_template(source).unwrap();
+        let err = transform_ast(ast).unwrap_err().to_string();
+
+        assert!(err.contains("Invalid expression `count = count + 1` at line 2, column 10"));
+        assert!(err.contains("assignments are not allowed"));
+
+        let ast = parse_template("{#if local x}<p/>{/if}").unwrap();
+        let err = transform_ast(ast).unwrap_err().to_string();
+        assert!(err.contains("`local` statements are not allowed"));
+
+        let ast = parse_template("{#feature flag = 1}<p/>{/feature}").unwrap();
+        let err = transform_ast(ast).unwrap_err().to_string();
+        assert!(err.contains("assignments are not allowed"), "{}", err);
+    }
+
     #[test]
     fn test_multiple_locals_in_each_block() {
         let source = r#"
@@ -375,6 +411,22 @@ mod integration_tests {
         // The result should contain the rendered component
         assert!(result.contains("card"));
         assert!(result.contains("Welcome"));
+        assert!(result.contains("<p>This is the card content</p>"));
+    }
+
+    #[test]
+    fn test_control_flow_else_if_chain() {
+        let source = r#"{#if props.n > 10}<p>big</p>{:else if props.n > 5}<p>medium</p>{:else if props.n > 0}<p>small</p>{:else}<p>none</p>{/if}"#;
+
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine(temp_dir.path()).unwrap();
+
+        for (n, expected) in [(20, "<p>big</p>"), (7, "<p>medium</p>"), (1, "<p>small</p>"), (0, "<p>none</p>")] {
+            let mut context = HashMap::new();
+            context.insert("n".to_string(), Value::Integer(n));
+            let result = engine.render_source(source, &context).unwrap();
+            assert_eq!(result.trim(), expected, "n = {}", n);
+        }
     }
 
     #[test]
@@ -684,6 +736,19 @@ None of this should be parsed
         assert!(!result.contains("Lua-style comment"));
     }
 
+    #[test]
+    fn test_server_comment() {
+        let source = r#"{!-- admin password rotates monthly --}<div><!-- public {!-- {props.secret} --}note -->Content</div>"#;
+
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine(temp_dir.path()).unwrap();
+
+        let context = HashMap::new();
+        let result = engine.render_source(source, &context).unwrap();
+        assert!(result.contains("<!-- public note -->Content</div>"), "{}", result);
+        assert!(!result.contains("admin") && !result.contains("secret"));
+    }
+
     #[test]
     fn test_comment_as_first_line_before_script() {
         // Test that comments can appear before script blocks
@@ -869,6 +934,49 @@ props name: {props.name}
         assert!(bundle.contains("local function __require"));
     }
 
+    #[test]
+    fn test_chunked_bundle_loads_lazily() {
+        let builder = Engine::with_memory_cache(MemoryResourceResolver::new(), 10).unwrap();
+        let sources = vec![
+            ("lib/math.lua".to_string(), "return { answer = 42 }".to_string()),
+            ("lib/broken.lua".to_string(), "error(\"should not load\")".to_string()),
+        ];
+        let chunked = builder.bundle_sources_chunked(sources, "chunks", |_, _| {}).unwrap();
+        assert_eq!(chunked.chunks.len(), 2);
+        assert!(!chunked.bundle.contains("answer = 42"));
+        assert!(chunked.bundle.contains("__load_chunk(\"lib/math.lua\", \"chunks/"));
+
+        let mut resolver = MemoryResourceResolver::new();
+        for (path, code) in &chunked.chunks {
+            assert!(path.starts_with("chunks/"), "{}", path);
+            resolver.add_resource(path, code);
+        }
+        let engine = Engine::with_memory_cache(resolver, 10).unwrap();
+        engine.preload_bundle_code(&chunked.bundle).unwrap();
+
+        let answer: i64 = engine.lua().load("return require(\"lib/math\").answer").eval().unwrap();
+        assert_eq!(answer, 42);
+
+        let err = engine.lua().load("return require(\"lib/broken\")").exec().unwrap_err();
+        assert!(err.to_string().contains("lib/broken.lua:1: should not load"), "{}", err);
+
+        // Templates can't reach the chunk reader to read module sources
+        let reader: Value = engine.lua().load("return __luat_read_chunk").eval().unwrap();
+        assert!(reader.is_nil());
+
+        // Chunks can also be read from a directory, as `luat serve` does
+        let temp_dir = TempDir::new().unwrap();
+        for (path, code) in &chunked.chunks {
+            fs::create_dir_all(temp_dir.path().join(path).parent().unwrap()).unwrap();
+            fs::write(temp_dir.path().join(path), code).unwrap();
+        }
+        let engine = Engine::with_memory_cache(MemoryResourceResolver::new(), 10).unwrap();
+        engine.set_chunk_root(temp_dir.path()).unwrap();
+        engine.preload_bundle_code(&chunked.bundle).unwrap();
+        let answer: i64 = engine.lua().load("return require(\"lib/math\").answer").eval().unwrap();
+        assert_eq!(answer, 42);
+    }
+
     #[test]
     fn test_error_handling() {
         // Test parse error
@@ -898,6 +1006,1527 @@ props name: {props.name}
         let result = parse_template(multiple_modules);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_output_size_guard_names_component() {
+        let temp_dir = TempDir::new().unwrap();
+
+        fs::write(
+            temp_dir.path().join("Row.luat"),
+            r#"<li>{props.value}</li>"#,
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("main.luat"),
+            r#"
+<script>
+    local Row = require("Row.luat")
+</script>
+<ul>
+{#each props.items as item}
+    <Row value={item} />
+{/each}
+</ul>
+"#,
+        )
+        .unwrap();
+
+        let mut engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("main.luat").unwrap();
+        let items: Vec<String> = (0..200).map(|i| format!("item-{}", i)).collect();
+        let context = engine
+            .to_value(serde_json::json!({ "items": items }))
+            .unwrap();
+
+        // No limit by default
+        assert!(engine.render(&module, &context).is_ok());
+
+        engine.set_max_output_bytes(Some(1000));
+        let err = engine.render(&module, &context).unwrap_err().to_string();
+        assert!(err.contains("limit 1000"), "{}", err);
+        assert!(err.contains("main.luat > Row"), "{}", err);
+
+        // A generous limit renders normally
+        engine.set_max_output_bytes(Some(1_000_000));
+        let html = engine.render(&module, &context).unwrap();
+        assert!(html.contains("<li>item-199</li>"));
+    }
+
+    #[test]
+    fn test_memory_limit_stops_render() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("main.luat"),
+            r#"
+<script>
+    local rows = {}
+    for i = 1, props.count do
+        rows[i] = string.rep("x", 100) .. i
+    end
+    local count = #rows
+</script>
+<p>{count}</p>
+"#,
+        )
+        .unwrap();
+
+        let mut engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("main.luat").unwrap();
+        let limit = engine.lua().used_memory() + 4 * 1024 * 1024;
+        engine.set_memory_limit_bytes(Some(limit)).unwrap();
+
+        let small = engine.to_value(serde_json::json!({ "count": 100 })).unwrap();
+        assert!(engine.render(&module, &small).unwrap().contains("<p>100</p>"));
+
+        let large = engine.to_value(serde_json::json!({ "count": 1_000_000 })).unwrap();
+        let err = engine.render(&module, &large).unwrap_err();
+        assert!(matches!(err, LuatError::ResourceLimit(_)), "{:?}", err);
+
+        // The engine recovers once the garbage is collected
+        engine.lua().gc_collect().unwrap();
+        assert!(engine.render(&module, &small).is_ok());
+
+        engine.set_memory_limit_bytes(None).unwrap();
+        assert!(engine.render(&module, &large).is_ok());
+    }
+
+    #[test]
+    fn test_render_limits_stop_runaway_templates() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("main.luat"),
+            r#"
+<script>
+    local n = 0
+    while props.spin do n = n + 1 end
+</script>
+<p>done</p>
+"#,
+        )
+        .unwrap();
+
+        let mut engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("main.luat").unwrap();
+        let spin = engine.to_value(serde_json::json!({ "spin": true })).unwrap();
+        let finish = engine.to_value(serde_json::json!({ "spin": false })).unwrap();
+
+        engine.set_instruction_limit(Some(100_000));
+        match engine.render(&module, &spin).unwrap_err() {
+            LuatError::TemplateRuntimeError { template, message, .. } => {
+                assert_eq!(template, "main.luat");
+                assert!(message.starts_with("main.luat:"), "{}", message);
+                assert!(message.contains("instruction limit of 100000 exceeded"), "{}", message);
+            }
+            other => panic!("Expected TemplateRuntimeError, got {:?}", other),
+        }
+        // The hook is gone after the render, so the engine keeps working
+        assert!(engine.render(&module, &finish).unwrap().contains("<p>done</p>"));
+
+        engine.set_instruction_limit(None);
+        engine.set_render_timeout(Some(std::time::Duration::from_millis(50)));
+        let err = engine.render(&module, &spin).unwrap_err().to_string();
+        assert!(err.contains("render time limit of 50ms exceeded"), "{}", err);
+        assert!(engine.render(&module, &finish).is_ok());
+    }
+
+    #[test]
+    fn test_render_limits_cover_load_functions() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("+page.server.lua"), "function load(ctx) while true do end end").unwrap();
+        fs::write(temp_dir.path().join("+page.luat"), "<p>never</p>").unwrap();
+
+        let mut engine = create_engine(temp_dir.path()).unwrap();
+        engine.set_instruction_limit(Some(100_000));
+        let router = crate::Router::from_paths(["+page.server.lua", "+page.luat"].into_iter());
+        let route = router.match_url("/").unwrap();
+        let err = engine.respond(&route, &crate::LuatRequest::new("/", "GET")).unwrap_err().to_string();
+        assert!(err.contains("instruction limit of 100000 exceeded"), "{}", err);
+    }
+
+    #[test]
+    fn test_output_limit_covers_page_and_layouts() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("+layout.luat"), "<main>{string.rep('y', 600)}{@render children()}</main>").unwrap();
+        fs::write(temp_dir.path().join("+page.luat"), "<p>{props.pad}</p>").unwrap();
+        fs::write(
+            temp_dir.path().join("+page.server.lua"),
+            "function load() return { pad = string.rep('x', 600) } end",
+        )
+        .unwrap();
+
+        let mut engine = create_engine(temp_dir.path()).unwrap();
+        let router = crate::Router::from_paths(["+layout.luat", "+page.server.lua", "+page.luat"].into_iter());
+        let route = router.match_url("/").unwrap();
+        let request = crate::LuatRequest::new("/", "GET");
+
+        // The page and the layout each stay under the limit, together they don't
+        engine.set_max_output_bytes(Some(1000));
+        let err = engine.respond(&route, &request).unwrap_err().to_string();
+        assert!(err.contains("limit 1000"), "{}", err);
+
+        // The page's output counts once, not again inside the layout
+        engine.set_max_output_bytes(Some(1500));
+        assert!(engine.respond(&route, &request).is_ok());
+    }
+
+    #[test]
+    fn test_render_to_writer_streams_chunks() {
+        struct Chunks(Vec<Vec<u8>>);
+        impl std::io::Write for Chunks {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.push(buf.to_vec());
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        struct Closed;
+        impl std::io::Write for Closed {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::BrokenPipe.into())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("Row.luat"), "<li>{props.value}</li>").unwrap();
+        fs::write(
+            temp_dir.path().join("main.luat"),
+            r#"<script>
+    local Row = require("Row.luat")
+</script>
+<ul>{#each props.items as item}<Row value={item} />{/each}</ul>"#,
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("main.luat").unwrap();
+        let items: Vec<String> = (0..2000).map(|i| format!("item-{}", i)).collect();
+        let context = engine.to_value(serde_json::json!({ "items": items })).unwrap();
+
+        let mut chunks = Chunks(Vec::new());
+        engine.render_to_writer(&module, &context, &mut chunks).unwrap();
+        assert!(chunks.0.len() > 1, "{} chunks", chunks.0.len());
+        assert_eq!(chunks.0.concat(), engine.render(&module, &context).unwrap().into_bytes());
+
+        let err = engine.render_to_writer(&module, &context, &mut Closed).unwrap_err();
+        assert!(matches!(err, LuatError::IoError(ref e) if e.kind() == std::io::ErrorKind::BrokenPipe), "{}", err);
+    }
+
+    #[cfg(feature = "async-lua")]
+    #[tokio::test]
+    async fn test_render_to_async_writer() {
+        use tokio::io::AsyncReadExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("main.luat"),
+            "<ul>{#each props.items as item}<li>{item}</li>{/each}</ul>",
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("main.luat").unwrap();
+        let items: Vec<String> = (0..2000).map(|i| format!("item-{}", i)).collect();
+        let context = engine.to_value(serde_json::json!({ "items": items })).unwrap();
+        let expected = engine.render(&module, &context).unwrap();
+
+        // A pipe smaller than the page only fits it if the render waits for the reader
+        let (mut client, mut server) = tokio::io::duplex(4096);
+        let read = tokio::spawn(async move {
+            let mut html = String::new();
+            server.read_to_string(&mut html).await.unwrap();
+            html
+        });
+        engine.render_to_async_writer(&module, &context, &mut client).await.unwrap();
+        drop(client);
+        assert_eq!(read.await.unwrap(), expected);
+
+        let (mut client, server) = tokio::io::duplex(4096);
+        drop(server);
+        let err = engine.render_to_async_writer(&module, &context, &mut client).await.unwrap_err();
+        assert!(matches!(err, LuatError::IoError(ref e) if e.kind() == std::io::ErrorKind::BrokenPipe), "{}", err);
+    }
+
+    #[test]
+    fn test_escape_audit_records_raw_output() {
+        let temp_dir = TempDir::new().unwrap();
+
+        fs::write(
+            temp_dir.path().join("main.luat"),
+            r#"<p>{props.safe}</p>
+<div>{@html props.body}</div>
+"#,
+        )
+        .unwrap();
+
+        let mut engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("main.luat").unwrap();
+        let context = engine
+            .to_value(serde_json::json!({ "safe": "<b>", "body": "<em>hi</em>" }))
+            .unwrap();
+
+        // Nothing is recorded while auditing is off
+        engine.render(&module, &context).unwrap();
+        assert!(engine.take_escape_audit().unwrap().is_empty());
+
+        engine.set_escape_audit(true);
+        engine.render(&module, &context).unwrap();
+        engine.render(&module, &context).unwrap();
+
+        let report = engine.take_escape_audit().unwrap();
+        assert_eq!(report.sites.len(), 2);
+        let sites = report.unique_sites();
+        assert_eq!(sites.len(), 1);
+        assert_eq!(sites[0].template, "main.luat");
+        assert_eq!(sites[0].line, 2);
+        assert_eq!(sites[0].expression, "props.body");
+        assert_eq!(sites[0].kind, "html");
+        assert_eq!(sites[0].value, "<em>hi</em>");
+        assert!(report.to_string().contains("main.luat:2 [html] props.body"));
+
+        // Taking the report clears it
+        assert!(engine.take_escape_audit().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_escape_audit_report_per_request() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("+page.luat"), "<div>{@html '<em>hi</em>'}</div>").unwrap();
+        fs::write(temp_dir.path().join("main.luat"), "<div>{@html '<b>'}</div>").unwrap();
+
+        let mut engine = create_engine(temp_dir.path()).unwrap();
+        engine.set_escape_audit(true);
+        let router = crate::Router::from_paths(["+page.luat"].into_iter());
+        let route = router.match_url("/").unwrap();
+        let request = crate::LuatRequest::new("/", "GET");
+
+        // A standalone render records into the shared list
+        let module = engine.compile_entry("main.luat").unwrap();
+        engine.render(&module, &engine.to_value(serde_json::json!({})).unwrap()).unwrap();
+
+        let (_, first) = engine.respond_audited(&route, &request).unwrap();
+        let (_, second) = engine.respond_audited(&route, &request).unwrap();
+        assert_eq!(first.sites.len(), 1);
+        assert_eq!(first.sites[0].value, "<em>hi</em>");
+        assert_eq!(second.sites.len(), 1);
+
+        let shared = engine.take_escape_audit().unwrap();
+        assert_eq!(shared.sites.len(), 1);
+        assert_eq!(shared.sites[0].template, "main.luat");
+    }
+
+    #[test]
+    fn test_deterministic_render_is_byte_stable() {
+        let temp_dir = TempDir::new().unwrap();
+
+        fs::write(
+            temp_dir.path().join("main.luat"),
+            r#"<script>
+local stamp = os.date("%Y-%m-%d", os.time())
+local roll = math.random(1, 1000000)
+</script>
+<p class={{ c = true, a = true, b = true, d = true }}>{stamp} {roll}</p>
+"#,
+        )
+        .unwrap();
+
+        let mut engine = create_engine(temp_dir.path()).unwrap();
+        engine.set_deterministic(true).unwrap();
+        let module = engine.compile_entry("main.luat").unwrap();
+        let context = engine.to_value(serde_json::json!({})).unwrap();
+
+        let first = engine.render(&module, &context).unwrap();
+        assert!(first.contains(r#"class="a b c d""#), "{}", first);
+        assert!(first.contains("2000-01-01"), "{}", first);
+        for _ in 0..5 {
+            assert_eq!(engine.render(&module, &context).unwrap(), first);
+        }
+
+        engine.set_deterministic(false).unwrap();
+        let html = engine.render(&module, &context).unwrap();
+        assert!(!html.contains("2000-01-01"));
+    }
+
+    #[test]
+    fn test_fmt_helpers_in_templates() {
+        let temp_dir = TempDir::new().unwrap();
+
+        fs::write(
+            temp_dir.path().join("main.luat"),
+            r#"<p>{fmt.currency(props.total, "EUR", { locale = "de" })} / {fmt.date(0, "long")}</p>"#,
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("main.luat").unwrap();
+        let context = engine.to_value(serde_json::json!({ "total": 1234.5 })).unwrap();
+
+        let html = engine.render(&module, &context).unwrap();
+        assert!(html.contains("1.234,50\u{a0}€ / January 1, 1970"), "{}", html);
+    }
+
+    #[test]
+    fn test_pagination_component_in_templates() {
+        let temp_dir = TempDir::new().unwrap();
+
+        fs::write(
+            temp_dir.path().join("main.luat"),
+            r#"<Pagination pagination={paginate(props.total, props.page, 10, { path = "/posts" })} />"#,
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("main.luat").unwrap();
+        let context = engine.to_value(serde_json::json!({ "total": 45, "page": 5 })).unwrap();
+
+        let html = engine.render(&module, &context).unwrap();
+        assert!(html.contains(r#"<a href="/posts?page=4" rel="prev">Previous</a>"#), "{}", html);
+        assert!(html.contains(r#"<span aria-current="page">5</span>"#), "{}", html);
+        assert!(html.contains(r#"<span aria-disabled="true">Next</span>"#), "{}", html);
+    }
+
+    #[test]
+    fn test_url_helpers_in_templates() {
+        let temp_dir = TempDir::new().unwrap();
+
+        fs::write(
+            temp_dir.path().join("main.luat"),
+            r#"<a href={url.with_query(props.current, { page = 2 })}>{url.build("/search", { q = props.q })}</a>"#,
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("main.luat").unwrap();
+        let context = engine
+            .to_value(serde_json::json!({ "current": "/posts?tag=a&b&page=1", "q": "<x>" }))
+            .unwrap();
+
+        let html = engine.render(&module, &context).unwrap();
+        assert_eq!(
+            html.trim(),
+            r#"<a href="/posts?tag=a&amp;b=&amp;page=2">/search?q=%3Cx%3E</a>"#
+        );
+    }
+
+    #[test]
+    fn test_seo_values_are_collected_into_page_head() {
+        let temp_dir = TempDir::new().unwrap();
+
+        fs::write(
+            temp_dir.path().join("+layout.luat"),
+            r#"<script>seo.defaults { title = "Site", og = { site_name = "Blog" } }</script>{@html props.children}"#,
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("+page.server.lua"),
+            r#"function load(ctx) seo.set { description = "Loaded" } return {} end"#,
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("+page.luat"),
+            r#"<script>seo.set { title = "Post", og = { type = "article" } }</script><p>Body</p>"#,
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let router = crate::Router::from_paths(["+layout.luat", "+page.server.lua", "+page.luat"].into_iter());
+        let route = router.match_url("/").unwrap();
+        let request = crate::LuatRequest::new("/", "GET");
+
+        match engine.respond(&route, &request).unwrap() {
+            crate::LuatResponse::Html { head, headers, .. } => {
+                assert_eq!(head.title.as_deref(), Some("Post"));
+                assert_eq!(head.description.as_deref(), Some("Loaded"));
+                assert_eq!(head.og.get("site_name").map(String::as_str), Some("Blog"));
+                assert!(head.to_html().contains(r#"<meta property="og:title" content="Post">"#));
+                assert!(!headers.contains_key("x-luat-title"));
+            }
+            other => panic!("Expected Html response, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_request_isolation() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("+page.server.lua"),
+            "function load(ctx) _G.visits = (_G.visits or 0) + 1 return { visits = _G.visits } end",
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("+page.luat"), "<p>{props.visits}</p>").unwrap();
+
+        let mut engine = create_engine(temp_dir.path()).unwrap();
+        let router = crate::Router::from_paths(["+page.server.lua", "+page.luat"].into_iter());
+        let route = router.match_url("/").unwrap();
+        let request = crate::LuatRequest::new("/", "GET");
+        let body = |engine: &Engine<FileSystemResolver>| match engine.respond(&route, &request).unwrap() {
+            crate::LuatResponse::Html { body, .. } => body.trim().to_string(),
+            other => panic!("Expected Html response, got {:?}", other),
+        };
+
+        assert_eq!(body(&engine), "<p>1</p>");
+        assert_eq!(body(&engine), "<p>2</p>");
+
+        engine.set_request_isolation(true);
+        assert_eq!(body(&engine), "<p>3</p>");
+        assert_eq!(body(&engine), "<p>3</p>");
+        assert_eq!(engine.lua().globals().get::<i64>("visits").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_per_request_modules_reload_between_requests() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("counter.lua"), "-- module_state = \"per-request\"\nreturn { hits = 0 }").unwrap();
+        fs::write(temp_dir.path().join("tally.lua"), "return { hits = 0 }").unwrap();
+        fs::write(
+            temp_dir.path().join("+page.server.lua"),
+            r#"function load(ctx)
+    local counter, tally = require("counter"), require("tally")
+    counter.hits = counter.hits + 1
+    tally.hits = tally.hits + 1
+    return { counter = counter.hits, tally = tally.hits }
+end"#,
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("+page.luat"), "<p>{props.counter} {props.tally}</p>").unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let router = crate::Router::from_paths(["+page.server.lua", "+page.luat"].into_iter());
+        let route = router.match_url("/").unwrap();
+        let request = crate::LuatRequest::new("/", "GET");
+        let body = || match engine.respond(&route, &request).unwrap() {
+            crate::LuatResponse::Html { body, .. } => body.trim().to_string(),
+            other => panic!("Expected Html response, got {:?}", other),
+        };
+
+        assert_eq!(body(), "<p>1 1</p>");
+        assert_eq!(body(), "<p>1 2</p>");
+    }
+
+    #[test]
+    fn test_bundle_per_request_modules_read_once() {
+        let builder = Engine::with_memory_cache(MemoryResourceResolver::new(), 10).unwrap();
+        let sources = vec![(
+            "lib/counter.lua".to_string(),
+            "-- module_state = \"per-request\"\nreturn { hits = 0 }".to_string(),
+        )];
+        let chunked = builder.bundle_sources_chunked(sources, "chunks", |_, _| {}).unwrap();
+
+        let temp_dir = TempDir::new().unwrap();
+        for (path, code) in &chunked.chunks {
+            fs::create_dir_all(temp_dir.path().join(path).parent().unwrap()).unwrap();
+            fs::write(temp_dir.path().join(path), code).unwrap();
+        }
+        let engine = Engine::with_memory_cache(MemoryResourceResolver::new(), 10).unwrap();
+        engine.set_chunk_root(temp_dir.path()).unwrap();
+        engine.preload_bundle_code(&chunked.bundle).unwrap();
+
+        let hit = "local counter = require(\"lib/counter\") counter.hits = counter.hits + 1 return counter.hits";
+        assert_eq!(engine.lua().load(hit).eval::<i64>().unwrap(), 1);
+        assert_eq!(engine.lua().load(hit).eval::<i64>().unwrap(), 2);
+
+        // The next request runs the module again, without reading its chunk
+        fs::remove_dir_all(temp_dir.path().join("chunks")).unwrap();
+        crate::module_state::reset(engine.lua()).unwrap();
+        assert_eq!(engine.lua().load(hit).eval::<i64>().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_layout_children() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("blog")).unwrap();
+        fs::write(temp_dir.path().join("+layout.luat"), "<main>{@render children()}</main>").unwrap();
+        fs::write(temp_dir.path().join("blog/+layout.luat"), "<section>{@html props.children}</section>").unwrap();
+        fs::write(temp_dir.path().join("blog/+page.luat"), "<p>{props.title or \"a & b\"}</p>").unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let router = crate::Router::from_paths(["+layout.luat", "blog/+layout.luat", "blog/+page.luat"].into_iter());
+        let route = router.match_url("/blog").unwrap();
+        let request = crate::LuatRequest::new("/blog", "GET");
+
+        match engine.respond(&route, &request).unwrap() {
+            crate::LuatResponse::Html { body, .. } => {
+                assert_eq!(body.trim(), "<main><section><p>a &amp; b</p></section></main>");
+            }
+            other => panic!("Expected Html response, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_feed_from_server_route() {
+        let temp_dir = TempDir::new().unwrap();
+
+        fs::write(
+            temp_dir.path().join("+server.lua"),
+            r#"
+            function GET(ctx)
+                return feed.rss {
+                    title = "Blog",
+                    link = "https://example.com",
+                    entries = { { title = "A & B", link = "https://example.com/a", date = 0 } },
+                }
+            end
+            "#,
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let router = crate::Router::from_paths(["+server.lua"].into_iter());
+        let route = router.match_url("/").unwrap();
+        let request = crate::LuatRequest::new("/", "GET");
+
+        match engine.respond(&route, &request).unwrap() {
+            crate::LuatResponse::Json { headers, body, .. } => {
+                assert_eq!(headers["Content-Type"], "application/rss+xml; charset=utf-8");
+                let xml = crate::encode_json_body(&body, &headers);
+                assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"), "{}", xml);
+                assert!(xml.contains("<title>A &amp; B</title>"), "{}", xml);
+                assert!(xml.contains("<pubDate>Thu, 01 Jan 1970 00:00:00 +0000</pubDate>"), "{}", xml);
+            }
+            other => panic!("Expected Json response, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_compile_debug_reports_every_stage() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("Card.luat"), "<div>{props.title}</div>").unwrap();
+        fs::write(
+            temp_dir.path().join("page.luat"),
+            "<script>\nlocal Card = require(\"Card.luat\")\nlocal Missing = require(\"Missing\")\n</script>\n<Card title=\"Hi\" />\n<p>{props.name}</p>",
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let debug = engine.compile_debug("page.luat").unwrap();
+
+        assert!(debug.path.ends_with("page.luat"));
+        assert!(debug.source.contains("<Card"));
+        assert!(debug.ast_dump().contains("ComponentNode"));
+        assert!(debug.ir_dump().contains("Card"));
+        assert!(debug.lua_code.contains("function render"));
+        assert_eq!(debug.source_map.lookup(debug.lua_code.lines().count()), Some(6));
+
+        let deps: Vec<(&str, bool)> = debug
+            .dependencies
+            .iter()
+            .map(|d| (d.module.as_str(), d.resolved.is_some()))
+            .collect();
+        assert_eq!(deps, vec![("Card.luat", true), ("Missing", false)]);
+    }
+
+    #[test]
+    fn test_respond_error_uses_error_templates() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("blog")).unwrap();
+        fs::write(temp_dir.path().join("blog/+page.luat"), "<p>Blog</p>").unwrap();
+        fs::write(
+            temp_dir.path().join("blog/+error.luat"),
+            "<p>Blog error {props.status}: {props.message} ({props.request_id})</p>",
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("404.luat"), "<p>Nothing at this address</p>").unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let router = crate::Router::from_paths(["blog/+page.luat", "blog/+error.luat"].into_iter());
+        let route = router.match_url("/blog").unwrap();
+        let mut request = crate::LuatRequest::new("/blog", "GET");
+        request.headers.insert("x-request-id".to_string(), "req-1".to_string());
+
+        let body = |response: crate::LuatResponse| match response {
+            crate::LuatResponse::Html { status, body, head, .. } => (status, body, head.title),
+            other => panic!("Expected Html response, got {:?}", other),
+        };
+
+        // Status-specific template wins over the route's error template
+        let (status, html, _) = body(engine.respond_error(Some(&route), &request, 404, "Not Found"));
+        assert_eq!(status, 404);
+        assert_eq!(html, "<p>Nothing at this address</p>");
+
+        let (status, html, title) = body(engine.respond_error(Some(&route), &request, 500, "Internal Server Error"));
+        assert_eq!(status, 500);
+        assert_eq!(html, "<p>Blog error 500: Internal Server Error (req-1)</p>");
+        assert_eq!(title.as_deref(), Some("500 Internal Server Error"));
+
+        // No template applies: built-in page
+        let (_, html, _) = body(engine.respond_error(None, &request, 503, "<down>"));
+        assert!(html.contains("<h1>503</h1>"));
+        assert!(html.contains("&lt;down&gt;"));
+        assert!(html.contains("Request ID: req-1"));
+    }
+
+    #[test]
+    fn test_respond_not_found_uses_nearest_error_template() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("blog/2024")).unwrap();
+        fs::write(temp_dir.path().join("blog/+error.luat"), "<p>No such post ({props.status})</p>").unwrap();
+        fs::write(temp_dir.path().join("+error.luat"), "<p>Lost? {props.message}</p>").unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let body = |response: crate::LuatResponse| match response {
+            crate::LuatResponse::Html { status, body, .. } => (status, body),
+            other => panic!("Expected Html response, got {:?}", other),
+        };
+
+        let request = crate::LuatRequest::new("/blog/2024/missing", "GET");
+        assert_eq!(body(engine.respond_not_found(&request)), (404, "<p>No such post (404)</p>".to_string()));
+
+        let request = crate::LuatRequest::new("/shop/missing", "GET");
+        assert_eq!(body(engine.respond_not_found(&request)), (404, "<p>Lost? Not Found</p>".to_string()));
+
+        // A status-specific template still wins
+        fs::write(temp_dir.path().join("404.luat"), "<p>Nothing at this address</p>").unwrap();
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let request = crate::LuatRequest::new("/blog/2024/missing", "GET");
+        assert_eq!(body(engine.respond_not_found(&request)).1, "<p>Nothing at this address</p>");
+    }
+
+    #[test]
+    fn test_head_and_options_requests() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("api")).unwrap();
+        fs::write(
+            temp_dir.path().join("api/+server.lua"),
+            "function GET(ctx) return { body = { method = ctx.method } } end\nfunction POST(ctx) return { status = 201 } end",
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("+page.luat"), "<p>{props.greeting}</p>").unwrap();
+        fs::write(
+            temp_dir.path().join("+page.server.lua"),
+            "function load(ctx) return { greeting = \"Hello\" } end",
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let router = crate::Router::from_paths(["api/+server.lua", "+page.luat", "+page.server.lua"].into_iter());
+
+        // HEAD renders the page like GET rather than running an action
+        let page = router.match_url("/").unwrap();
+        match engine.respond(&page, &crate::LuatRequest::new("/", "HEAD")).unwrap() {
+            crate::LuatResponse::Html { status, body, .. } => {
+                assert_eq!(status, 200);
+                assert!(body.contains("<p>Hello</p>"), "{}", body);
+            }
+            other => panic!("Expected Html response, got {:?}", other),
+        }
+
+        // HEAD falls back to the GET handler
+        let api = router.match_url("/api").unwrap();
+        match engine.respond(&api, &crate::LuatRequest::new("/api", "HEAD")).unwrap() {
+            crate::LuatResponse::Json { status, body, .. } => {
+                assert_eq!(status, 200);
+                assert_eq!(body["method"], "HEAD");
+            }
+            other => panic!("Expected Json response, got {:?}", other),
+        }
+
+        // OPTIONS and unsupported methods list the exported handlers
+        match engine.respond(&api, &crate::LuatRequest::new("/api", "OPTIONS")).unwrap() {
+            crate::LuatResponse::Bytes { status, headers, body } => {
+                assert_eq!(status, 204);
+                assert_eq!(headers["Allow"], "GET, HEAD, POST, OPTIONS");
+                assert!(body.is_empty());
+            }
+            other => panic!("Expected empty response, got {:?}", other),
+        }
+        match engine.respond(&api, &crate::LuatRequest::new("/api", "DELETE")).unwrap() {
+            crate::LuatResponse::Json { status, headers, .. } => {
+                assert_eq!(status, 405);
+                assert_eq!(headers["Allow"], "GET, HEAD, POST, OPTIONS");
+            }
+            other => panic!("Expected Json response, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_route_response_headers() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("docs/api")).unwrap();
+        fs::write(temp_dir.path().join("docs/+page.luat"), "<p>Docs</p>").unwrap();
+        fs::write(
+            temp_dir.path().join("docs/+page.server.lua"),
+            "headers = { [\"Cache-Control\"] = \"public, max-age=300\" }",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("docs/api/+server.lua"),
+            r#"
+            headers = { ["Cache-Control"] = "no-store", ["X-Api"] = "1" }
+            function GET(ctx) return { body = {}, headers = { ["X-Api"] = "2" } } end
+            "#,
+        )
+        .unwrap();
+
+        let mut engine = create_engine(temp_dir.path()).unwrap();
+        engine.add_route_headers(
+            "/docs/**",
+            HashMap::from([
+                ("cache-control".to_string(), "no-cache".to_string()),
+                ("X-Frame-Options".to_string(), "DENY".to_string()),
+            ]),
+        );
+        engine.add_route_headers("/other/*", HashMap::from([("X-Other".to_string(), "1".to_string())]));
+        let router = crate::Router::from_paths(
+            ["docs/+page.luat", "docs/+page.server.lua", "docs/api/+server.lua"].into_iter(),
+        );
+
+        let headers = |path: &str| {
+            let route = router.match_url(path).unwrap();
+            match engine.respond(&route, &crate::LuatRequest::new(path, "GET")).unwrap() {
+                crate::LuatResponse::Html { headers, .. } | crate::LuatResponse::Json { headers, .. } => headers,
+                other => panic!("Unexpected response {:?}", other),
+            }
+        };
+
+        // Exported headers win over configured ones, which fill in the rest
+        let page = headers("/docs");
+        assert_eq!(page["Cache-Control"], "public, max-age=300");
+        assert!(!page.contains_key("cache-control"));
+        assert_eq!(page["X-Frame-Options"], "DENY");
+        assert!(!page.contains_key("X-Other"));
+
+        // Handler-returned headers win over exported ones
+        let api = headers("/docs/api");
+        assert_eq!(api["Cache-Control"], "no-store");
+        assert_eq!(api["X-Api"], "2");
+        assert_eq!(api["X-Frame-Options"], "DENY");
+    }
+
+    #[test]
+    fn test_binary_safe_output() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("pixel.luat"),
+            "<script>\n    local data = \"\\137PNG\\0\\255\"\n</script>{@html data}",
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("bom.luat"), "\u{feff}<p>Hi</p>").unwrap();
+        fs::create_dir_all(temp_dir.path().join("image")).unwrap();
+        fs::write(
+            temp_dir.path().join("image/+server.lua"),
+            "function GET(ctx) return { headers = { [\"Content-Type\"] = \"image/png\" }, body = \"\\137PNG\\0\\255\" } end",
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let context = engine.to_value(HashMap::<String, String>::new()).unwrap();
+
+        // Binary output is only available as bytes
+        let module = engine.compile_entry("pixel.luat").unwrap();
+        assert_eq!(engine.render_bytes(&module, &context).unwrap(), b"\x89PNG\0\xff");
+        assert!(engine.render(&module, &context).is_err());
+
+        // A leading byte-order mark is not emitted
+        let module = engine.compile_entry("bom.luat").unwrap();
+        assert_eq!(engine.render(&module, &context).unwrap().trim(), "<p>Hi</p>");
+
+        let router = crate::Router::from_paths(["image/+server.lua"].into_iter());
+        let route = router.match_url("/image").unwrap();
+        match engine.respond(&route, &crate::LuatRequest::new("/image", "GET")).unwrap() {
+            crate::LuatResponse::Bytes { status, headers, body } => {
+                assert_eq!(status, 200);
+                assert_eq!(headers["Content-Type"], "image/png");
+                assert_eq!(body, b"\x89PNG\0\xff");
+            }
+            other => panic!("Expected Bytes response, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_compile_results_cached_by_source() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("broken.luat");
+        fs::write(&path, "{#if props.open}\n<p>Open</p>").unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+
+        // An unchanged broken template reports the same diagnostics
+        let first = engine.compile_entry("broken.luat").unwrap_err().to_string();
+        let second = engine.compile_entry("broken.luat").unwrap_err().to_string();
+        assert_eq!(first, second);
+
+        // Editing the source invalidates the cached error
+        fs::write(&path, "{#if props.open}\n<p>Open</p>\n{/if}").unwrap();
+        let module = engine.compile_entry("broken.luat").unwrap();
+        let again = engine.compile_entry("broken.luat").unwrap();
+        assert!(std::sync::Arc::ptr_eq(&module, &again));
+    }
+
+    #[test]
+    fn test_luat_render_from_api_route() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("emails")).unwrap();
+        fs::create_dir_all(temp_dir.path().join("api/orders")).unwrap();
+        fs::write(
+            temp_dir.path().join("emails/Invoice.luat"),
+            "<h1>Invoice {props.number}</h1>",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("api/orders/+server.lua"),
+            "function POST(ctx)\n  local html = luat.render(\"emails/Invoice.luat\", { number = 42 })\n  return { body = { html = html } }\nend",
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let router = crate::Router::from_paths(["api/orders/+server.lua"].into_iter());
+        let route = router.match_url("/api/orders").unwrap();
+        match engine.respond(&route, &crate::LuatRequest::new("/api/orders", "POST")).unwrap() {
+            crate::LuatResponse::Json { status, body, .. } => {
+                assert_eq!(status, 200);
+                assert!(body["html"].as_str().unwrap().contains("<h1>Invoice 42</h1>"), "{}", body);
+            }
+            other => panic!("Expected Json response, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_await_block_states() {
+        let temp_dir = TempDir::new().unwrap();
+        let template = r#"<script>
+    local loaders = {
+        value = "plain",
+        func = function() return "from function" end,
+        thread = coroutine.create(function()
+            coroutine.yield("partial")
+            return "from coroutine"
+        end),
+        failing = function() error("boom", 0) end,
+        deferred = { __luat_deferred = "luat-d-1" },
+        endless = coroutine.create(function()
+            while true do coroutine.yield() end
+        end),
+    }
+</script>
+{#await loaders[props.kind]}<p>Loading</p>{:then value}<p>{value}</p>{:catch err}<p>Error: {err}</p>{/await}"#;
+        fs::write(temp_dir.path().join("await.luat"), template).unwrap();
+        fs::write(
+            temp_dir.path().join("uncaught.luat"),
+            "{#await function() error(\"boom\", 0) end}<p>Loading</p>{:then value}<p>{value}</p>{/await}",
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("await.luat").unwrap();
+        for (kind, expected) in [
+            ("value", "<p>plain</p>"),
+            ("func", "<p>from function</p>"),
+            ("thread", "<p>from coroutine</p>"),
+            ("failing", "<p>Error: boom</p>"),
+            ("deferred", "<p>Loading</p>"),
+            ("endless", "<p>Error: coroutine still running after 10000 resumes</p>"),
+        ] {
+            let context = engine.to_value(serde_json::json!({ "kind": kind })).unwrap();
+            let html = engine.render(&module, &context).unwrap();
+            assert_eq!(html.trim(), expected, "kind {}", kind);
+        }
+
+        // Without {:catch} the error fails the render
+        let module = engine.compile_entry("uncaught.luat").unwrap();
+        let context = engine.to_value(serde_json::json!({})).unwrap();
+        let err = engine.render(&module, &context).unwrap_err();
+        assert!(err.to_string().contains("boom"), "{}", err);
+    }
+
+    #[test]
+    fn test_await_deferred_streams_then_branch() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("+page.server.lua"),
+            "function load(ctx) return { slow = ctx.defer(function() return \"done\" end) } end",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("+page.luat"),
+            "{#await props.slow}<p>Loading</p>{:then value}<p>{value}</p>{/await}",
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let router = crate::Router::from_paths(["+page.server.lua", "+page.luat"].into_iter());
+        let route = router.match_url("/").unwrap();
+
+        match engine.respond(&route, &crate::LuatRequest::new("/", "GET")).unwrap() {
+            crate::LuatResponse::Stream { body, deferred, .. } => {
+                let slot = format!("{}-1", deferred[0]);
+                assert!(
+                    body.contains(&format!("<luat-deferred id=\"{}\"><p>Loading</p></luat-deferred>", slot)),
+                    "{}",
+                    body
+                );
+                let chunk = engine.resolve_deferred(&deferred[0], None).unwrap();
+                assert!(
+                    chunk.contains(&format!("<template data-luat-slot=\"{}\"><p>done</p></template>", slot)),
+                    "{}",
+                    chunk
+                );
+            }
+            other => panic!("Expected Stream response, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_custom_block_directive() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("page.luat"),
+            "<ul>{#repeat props.times}<li>{props.label}</li>{/repeat}</ul>",
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("unknown.luat"), "{#cache}<p>x</p>{/cache}").unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        engine.register_block_directive("repeat", |call: &BlockDirectiveCall<'_>| {
+            let times = call.args.map(|args| args.content.clone()).unwrap_or_else(|| "1".to_string());
+            Ok(format!("for _ = 1, {} do __children(__write) end", times))
+        });
+
+        let module = engine.compile_entry("page.luat").unwrap();
+        let context = engine.to_value(serde_json::json!({ "times": 3, "label": "a" })).unwrap();
+        let html = engine.render(&module, &context).unwrap();
+        assert_eq!(html, "<ul><li>a</li><li>a</li><li>a</li></ul>");
+
+        let err = engine.compile_entry("unknown.luat").unwrap_err();
+        assert!(err.to_string().contains("Unknown block directive {#cache}"), "{}", err);
+    }
+
+    #[test]
+    fn test_feature_block() {
+        use crate::extensions::{FeatureFlag, FeatureFlags};
+
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("page.luat"),
+            r#"{#feature "new-nav"}<nav>new</nav>{/feature}{#feature "old"}<p>old</p>{/feature}{#if flags.enabled("new-nav")}<b>on</b>{/if}"#,
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("bare.luat"), "{#feature}<p>x</p>{/feature}").unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let mut flags = FeatureFlags::default();
+        flags.insert("new-nav", FeatureFlag { enabled: true, ..Default::default() });
+        engine.set_feature_flags(flags, None).unwrap();
+
+        let module = engine.compile_entry("page.luat").unwrap();
+        let context = engine.to_value(serde_json::json!({})).unwrap();
+        let html = engine.render(&module, &context).unwrap();
+        assert_eq!(html, "<nav>new</nav><b>on</b>");
+
+        let err = engine.compile_entry("bare.luat").unwrap_err();
+        assert!(err.to_string().contains("{#feature} requires a flag name"), "{}", err);
+    }
+
+    #[test]
+    fn test_translations() {
+        use crate::extensions::Catalogs;
+
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("page.luat"),
+            r#"<h1>{t("title", {name = props.name})}</h1><p>{t("cart.items", {count = props.count})}</p><p>{t("missing")}</p>"#,
+        )
+        .unwrap();
+
+        let mut engine = create_engine(temp_dir.path()).unwrap();
+        let mut catalogs = Catalogs::new("en");
+        catalogs
+            .insert_json("en", r#"{"title": "Hi {name}", "cart": {"items": {"one": "{count} item", "other": "{count} items"}}}"#)
+            .unwrap();
+        engine.set_catalogs(catalogs).unwrap();
+
+        let module = engine.compile_entry("page.luat").unwrap();
+        let context = engine.to_value(serde_json::json!({ "name": "<Ada>", "count": 2 })).unwrap();
+        let html = engine.render(&module, &context).unwrap();
+        assert_eq!(html, "<h1>Hi &lt;Ada&gt;</h1><p>2 items</p><p>missing</p>");
+    }
+
+    #[test]
+    fn test_key_block() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("page.luat"),
+            "<ul>{#key props.id}<li>{props.name}</li>{/key}</ul>",
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("page.luat").unwrap();
+        let context = engine.to_value(serde_json::json!({ "id": "a-->b", "name": "Ada" })).unwrap();
+        let html = engine.render(&module, &context).unwrap();
+        assert_eq!(html, "<ul><!--luat:key a&#45;&#45;&gt;b--><li>Ada</li><!--/luat:key--></ul>");
+    }
+
+    #[test]
+    fn test_snippets() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("List.luat"),
+            "<ul>{#each props.items as item}<li>{@render props.row(item)}</li>{/each}</ul>{@render props.footer?.()}",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("page.luat"),
+            r#"<script>
+    local List = require("List.luat")
+</script>
+{@render badge("new", 1)}{@render badge("hot", 2)}
+{#snippet badge(label, n)}<b>{label}:{n}</b>{/snippet}
+{#snippet link(item)}<a href="/{item}">{item}</a>{/snippet}
+<List items={props.items} row={link}>
+    {#snippet footer()}<p>{#each props.items as item}{@render badge(item, #props.items)}{/each}</p>{/snippet}
+</List>
+<List items={props.items}>
+    {#snippet row(item)}<i>{item}</i>{/snippet}
+</List>"#,
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("page.luat").unwrap();
+        let context = engine.to_value(serde_json::json!({ "items": ["a", "b"] })).unwrap();
+        let html = engine.render(&module, &context).unwrap();
+        assert_eq!(
+            html,
+            "<b>new:1</b><b>hot:2</b>\
+             <ul><li><a href=\"/a\">a</a></li><li><a href=\"/b\">b</a></li></ul><p><b>a:2</b><b>b:2</b></p>\
+             <ul><li><i>a</i></li><li><i>b</i></li></ul>"
+        );
+    }
+
+    #[test]
+    fn test_dynamic_component() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("Hero.luat"), "<h1>{props.title}</h1>").unwrap();
+        fs::write(
+            temp_dir.path().join("Quote.luat"),
+            "<blockquote>{@render props.children?.()}</blockquote>",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("page.luat"),
+            r#"<script>
+    local blocks = {
+        { component = require("Hero.luat"), props = { title = "Hi" } },
+        { component = require("Quote.luat"), props = {} },
+        { component = nil, props = {} },
+    }
+</script>
+{#each blocks as block}<luat:component this={block.component} {...block.props}><i>{props.who}</i></luat:component>{/each}"#,
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("page.luat").unwrap();
+        let context = engine.to_value(serde_json::json!({ "who": "Ada" })).unwrap();
+        let html = engine.render(&module, &context).unwrap();
+        assert_eq!(html, "<h1>Hi</h1><blockquote><i>Ada</i></blockquote>");
+
+        let err = parse_template("<luat:component title=\"x\" />")
+            .and_then(transform_ast)
+            .unwrap_err();
+        assert!(err.to_string().contains("requires a `this={expression}` attribute"), "{}", err);
+    }
+
+    #[test]
+    fn test_scoped_styles() {
+        let temp_dir = TempDir::new().unwrap();
+        let card = r#"<style>
+    .card { padding: 0 }
+    p:hover { color: red }
+</style>
+<div class="card"><p>{props.text}</p><b class={props.kind}>!</b></div>
+<div><style>a { color: blue }</style></div>"#;
+        fs::write(temp_dir.path().join("Card.luat"), card).unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("Card.luat").unwrap();
+        let context = engine.to_value(serde_json::json!({ "text": "Hi", "kind": "note" })).unwrap();
+        let html = engine.render(&module, &context).unwrap();
+
+        let style = crate::style::ComponentStyle::new("\n    .card { padding: 0 }\n    p:hover { color: red }\n").unwrap();
+        let scope = style.scope.clone();
+        assert_eq!(
+            html,
+            format!(
+                r#"<style>{1}</style><div class="card {0}"><p class="{0}">Hi</p><b class="note {0}">!</b></div><div class="{0}"><style>a {{ color: blue }}</style></div>"#,
+                scope, style.css
+            )
+        );
+
+        // A template rendered twice in a page writes its style once
+        fs::write(
+            temp_dir.path().join("Page.luat"),
+            "<script>\n    local Card = require(\"Card.luat\")\n</script><Card text=\"a\" /><Card text=\"b\" />",
+        )
+        .unwrap();
+        let module = engine.compile_entry("Page.luat").unwrap();
+        let html = engine.render(&module, &engine.to_value(serde_json::json!({})).unwrap()).unwrap();
+        assert_eq!(html.matches(&format!("<style>{}</style>", style.css)).count(), 1, "{}", html);
+
+        let css = engine.stylesheet(&[("Card.luat".to_string(), card.to_string())]).unwrap();
+        assert!(css.starts_with("/* Card.luat */\n"), "{}", css);
+        assert!(css.contains(&format!(".card.{0} {{ padding: 0 }}\np:hover.{0} {{ color: red }}", scope)), "{}", css);
+    }
+
+    #[test]
+    fn test_class_directives() {
+        let temp_dir = TempDir::new().unwrap();
+        let template = r#"<script>local error = props.error</script>
+<li class="item" class:active={props.active} class:error>a</li><li class:active={not props.active}>b</li><li class={props.kind} class:error>c</li>"#;
+        fs::write(temp_dir.path().join("Item.luat"), template).unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("Item.luat").unwrap();
+        let context = engine
+            .to_value(serde_json::json!({ "active": true, "error": true, "kind": "a&b" }))
+            .unwrap();
+        let html = engine.render(&module, &context).unwrap();
+        assert_eq!(
+            html.trim(),
+            r#"<li class="item active error">a</li><li>b</li><li class="a&amp;b error">c</li>"#
+        );
+
+        fs::write(temp_dir.path().join("Static.luat"), r#"<li class:active="yes">x</li>"#).unwrap();
+        assert!(engine.compile_entry("Static.luat").is_err());
+        fs::write(temp_dir.path().join("Dashed.luat"), "<li class:is-open>x</li>").unwrap();
+        assert!(engine.compile_entry("Dashed.luat").is_err());
+    }
+
+    #[test]
+    fn test_compile_warnings() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("Logo.luat"), "<a href=\"/\">\n  <img src=\"logo.png\">\n</a>").unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        engine.compile_entry("Logo.luat").unwrap();
+        let warnings = engine.take_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, "a11y-missing-alt");
+        assert!(warnings[0].to_string().ends_with("Logo.luat:2:3: <img> should have an alt attribute (a11y-missing-alt)"), "{}", warnings[0]);
+        assert!(engine.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_template_strings_collapse_whitespace() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine(temp_dir.path()).unwrap();
+        engine.set_collapse_whitespace(true);
+
+        let module = engine.compile_template_string("Spaced", "<p>a   b</p>\n\n<pre>  x</pre>").unwrap();
+        let html = engine.render(&module, &engine.to_value(serde_json::json!({})).unwrap()).unwrap();
+        assert_eq!(html, "<p>a b</p> <pre>  x</pre>");
+    }
+
+    #[test]
+    fn test_named_slots() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("Card.luat"),
+            "<article><header>{@render props.header?.()}</header>{@render children?()}<footer>{@render props.footer?.()}</footer></article>",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("Page.luat"),
+            r#"<script>local Card = require("Card.luat")</script>
+<Card><h1 slot="header" class="title">Title</h1><p>Body</p><fragment slot="footer">Page {props.page}</fragment></Card>"#,
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("Page.luat").unwrap();
+        let html = engine.render(&module, &engine.to_value(serde_json::json!({ "page": 2 })).unwrap()).unwrap();
+        assert_eq!(
+            html.trim(),
+            r#"<article><header><h1 class="title">Title</h1></header><p>Body</p><footer>Page 2</footer></article>"#
+        );
+
+        fs::write(
+            temp_dir.path().join("Twice.luat"),
+            r#"<script>local Card = require("Card.luat")</script><Card><p slot="footer">a</p><p slot="footer">b</p></Card>"#,
+        )
+        .unwrap();
+        assert!(engine.compile_entry("Twice.luat").is_err());
+    }
+
+    #[test]
+    fn test_render_fallback() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("Panel.luat"),
+            "<section>{@render props.children?.()}{:fallback}<p>No content</p>{/render}<footer>{@render props.footer()}{:fallback}-{/render}</footer></section>",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("Page.luat"),
+            r#"<script>local Panel = require("Panel.luat")</script><Panel /><Panel><b>Hi</b><i slot="footer">end</i></Panel>"#,
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("Page.luat").unwrap();
+        let html = engine.render(&module, &engine.to_value(serde_json::json!({})).unwrap()).unwrap();
+        assert_eq!(
+            html.trim(),
+            "<section><p>No content</p><footer>-</footer></section><section><b>Hi</b><footer><i>end</i></footer></section>"
+        );
+    }
+
+    #[test]
+    fn test_match_block() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("Blocks.luat"),
+            r#"{#each props.blocks as block}{#match block.type}
+  {:case "hero"}<h1>{block.title}</h1>
+  {:case "text", "quote"}<p>{block.body}</p>
+  {:default}<hr>
+{/match}{/each}"#,
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("Blocks.luat").unwrap();
+        let props = serde_json::json!({
+            "blocks": [
+                { "type": "hero", "title": "Welcome" },
+                { "type": "quote", "body": "Hi" },
+                { "type": "video" },
+            ]
+        });
+        let html = engine.render(&module, &engine.to_value(props).unwrap()).unwrap();
+        assert_eq!(html.split_whitespace().collect::<String>(), "<h1>Welcome</h1><p>Hi</p><hr>");
+    }
+
+    #[test]
+    fn test_match_block_rejects_content_before_first_case() {
+        assert!(parse_template("{#match x} {-- note --}\n  {:case 1}a{/match}").is_ok());
+
+        let err = parse_template("{#match x}\n<p>lost</p>{:case 1}a{/match}").unwrap_err();
+        assert!(err.to_string().contains("Content before the first {:case}"), "{}", err);
+    }
+
+    #[test]
+    fn test_raw_block() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("Counter.luat"),
+            r#"<h2>{props.title}</h2>
+{#raw}<div x-data="{ count: 0 }"><button @click="count++">{{ count }}</button>{#if}</div>{/raw}"#,
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("Counter.luat").unwrap();
+        let props = serde_json::json!({ "title": "Clicks" });
+        let html = engine.render(&module, &engine.to_value(props).unwrap()).unwrap();
+        assert!(html.starts_with("<h2>Clicks</h2>"), "{}", html);
+        assert!(
+            html.ends_with(r#"<div x-data="{ count: 0 }"><button @click="count++">{{ count }}</button>{#if}</div>"#),
+            "{}",
+            html
+        );
+    }
+
+    #[cfg(feature = "markdown")]
+    #[test]
+    fn test_markdown_tag() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("Post.luat"), "<article>{@markdown props.body}</article>").unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("Post.luat").unwrap();
+        let props = serde_json::json!({ "body": "# Hi\n\n<i>raw</i> **bold**" });
+        let html = engine.render(&module, &engine.to_value(props).unwrap()).unwrap();
+        assert_eq!(
+            html.trim(),
+            "<article><h1>Hi</h1>\n<p>&lt;i&gt;raw&lt;/i&gt; <strong>bold</strong></p>\n</article>"
+        );
+    }
+
+    #[cfg(feature = "markdown")]
+    #[test]
+    fn test_markdown_tag_ignores_shadowed_markdown() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("Post.luat"),
+            "<script>local markdown = props.markdown</script><article>{@markdown props.body}</article>",
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        engine.lua().load("markdown = nil").exec().unwrap();
+        let module = engine.compile_entry("Post.luat").unwrap();
+        let props = serde_json::json!({ "body": "*hi*", "markdown": "x" });
+        let html = engine.render(&module, &engine.to_value(props).unwrap()).unwrap();
+        assert_eq!(html.trim(), "<article><p><em>hi</em></p>\n</article>");
+    }
+
+    #[test]
+    fn test_export_let_props() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("Badge.luat"),
+            "<script>\nexport let label = \"New\"\nexport let count = 1 -- shown after the label\nexport let tone\n</script><span>{label} {props.count} {tone or \"plain\"}</span>",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("Page.luat"),
+            r#"<script>local Badge = require("Badge.luat")</script><Badge /><Badge label="Sale" count={0} tone="red" />"#,
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("Page.luat").unwrap();
+        let html = engine.render(&module, &engine.to_value(serde_json::json!({})).unwrap()).unwrap();
+        assert_eq!(html.trim(), "<span>New 1 plain</span><span>Sale 0 red</span>");
+    }
+
+    #[test]
+    fn test_use_directive() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("Button.luat"), "<button>{props.label}</button>").unwrap();
+        fs::write(
+            temp_dir.path().join("Toolbar.luat"),
+            "{#use Button from \"Button.luat\"}\n<script context=\"module\">local LABEL = \"Save\"</script>\n<nav><Button label={LABEL} /></nav>",
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("Toolbar.luat").unwrap();
+        let html = engine.render(&module, &engine.to_value(serde_json::json!({})).unwrap()).unwrap();
+        assert_eq!(html.trim(), "<nav><button>Save</button></nav>");
+
+        fs::write(temp_dir.path().join("Late.luat"), "<nav></nav>{#use Button from \"Button.luat\"}").unwrap();
+        assert!(engine.compile_entry("Late.luat").is_err());
+    }
+
+    #[test]
+    fn test_variant_header() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("shop")).unwrap();
+        fs::write(
+            temp_dir.path().join("shop/+page.luat"),
+            r#"<p>{variant("cta", { "buy", "order" })}</p>"#,
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let flags = crate::extensions::FeatureFlags {
+            user_cookie: Some("sid".to_string()),
+            ..Default::default()
+        };
+        engine.set_feature_flags(flags, None).unwrap();
+        let router = crate::Router::from_paths(["shop/+page.luat"].into_iter());
+        let route = router.match_url("/shop").unwrap();
+
+        let respond = |request: crate::LuatRequest| match engine.respond(&route, &request).unwrap() {
+            crate::LuatResponse::Html { headers, body, .. } => (headers, body),
+            other => panic!("Unexpected response {:?}", other),
+        };
+
+        let (headers, body) = respond(crate::LuatRequest::new("/shop", "GET"));
+        assert_eq!(body, "<p>buy</p>");
+        assert!(!headers.contains_key(crate::extensions::flags::VARIANTS_HEADER));
+
+        let cookies = HashMap::from([("sid".to_string(), "visitor-7".to_string())]);
+        let (headers, body) = respond(crate::LuatRequest::new("/shop", "GET").with_cookies(cookies));
+        let expected = ["buy", "order"][crate::extensions::flags::bucket("cta", "visitor-7") as usize * 2 / 10_000];
+        assert_eq!(body, format!("<p>{}</p>", expected));
+        assert_eq!(headers[crate::extensions::flags::VARIANTS_HEADER], format!("cta={}", expected));
+    }
+
+    #[test]
+    fn test_output_modes() {
+        let temp_dir = TempDir::new().unwrap();
+        let feed = r#"<!-- luat:mode xml -->
+<?xml version="1.0" encoding="UTF-8"?>
+<rss><channel><link>{props.url}</link><atom:link href="{props.url}" /><title>{props.title}</title><description></description></channel></rss>"#;
+        fs::write(temp_dir.path().join("feed.luat"), feed).unwrap();
+        fs::write(
+            temp_dir.path().join("email.luat.txt"),
+            "Hello {props.title},\nsee {props.url}",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("unknown.luat"),
+            "<!-- luat:mode pdf -->\n<p>x</p>",
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let context = engine
+            .to_value(serde_json::json!({ "url": "https://example.com/?a=1&b=2", "title": "Tom & Jerry" }))
+            .unwrap();
+
+        let module = engine.compile_entry("feed.luat").unwrap();
+        let xml = engine.render(&module, &context).unwrap();
+        assert_eq!(
+            xml,
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss><channel><link>https://example.com/?a=1&amp;b=2</link><atom:link href=\"https://example.com/?a=1&amp;b=2\" /><title>Tom &amp; Jerry</title><description /></channel></rss>"
+        );
+
+        let module = engine.compile_entry("email.luat.txt").unwrap();
+        let text = engine.render(&module, &context).unwrap();
+        assert_eq!(text, "Hello Tom & Jerry,\nsee https://example.com/?a=1&b=2");
+
+        let err = engine.compile_entry("unknown.luat").unwrap_err();
+        assert!(err.to_string().contains("Unknown output mode 'pdf'"), "{}", err);
+    }
+
+    #[test]
+    fn test_spread_attributes_merge_class_and_style() {
+        let temp_dir = TempDir::new().unwrap();
+
+        fs::write(
+            temp_dir.path().join("main.luat"),
+            r#"<script>
+    local attrs = { class = "btn", id = "a", disabled = true, hidden = false, style = "color: red;", title = 'say "hi"' }
+</script>
+<button class="base" {...attrs} id="b" style="margin: 0" class:active={true}>x</button>"#,
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("main.luat").unwrap();
+        let context = engine.to_value(serde_json::json!({})).unwrap();
+
+        let html = engine.render(&module, &context).unwrap();
+        assert_eq!(
+            html.trim(),
+            r#"<button class="base btn active" id="b" disabled style="color: red; margin: 0" title="say &quot;hi&quot;">x</button>"#
+        );
+    }
+
+    #[test]
+    fn test_asset_helper_adds_integrity() {
+        let temp_dir = TempDir::new().unwrap();
+
+        fs::write(
+            temp_dir.path().join("main.luat"),
+            r#"<script {...asset("js/app.js")}></script>"#,
+        )
+        .unwrap();
+
+        let engine = create_engine(temp_dir.path()).unwrap();
+        let module = engine.compile_entry("main.luat").unwrap();
+        let context = engine.to_value(serde_json::json!({})).unwrap();
+
+        let html = engine.render(&module, &context).unwrap();
+        assert_eq!(html.trim(), r#"<script src="/public/js/app.js"></script>"#);
+
+        let mut manifest = crate::extensions::AssetManifest::default();
+        manifest.insert("/public/js/app.js", b"alert(1)");
+        let integrity = manifest.integrity("/public/js/app.js").unwrap().to_string();
+        engine.set_asset_manifest(manifest).unwrap();
+
+        let html = engine.render(&module, &context).unwrap();
+        assert!(html.contains(&format!("integrity=\"{}\"", integrity)), "{}", html);
+        assert!(html.contains(r#"crossorigin="anonymous""#), "{}", html);
+    }
 }
 
 #[cfg(test)]
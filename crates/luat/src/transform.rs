@@ -13,6 +13,13 @@
 //! 2. **Component collection**: All used components are tracked
 //! 3. **Control flow flattening**: Nested blocks are preserved with metadata
 //! 4. **Attribute processing**: Dynamic vs static attributes are distinguished
+//! 5. **Expression checks**: Every expression must be a single Lua expression
+//!    (see [`crate::expression`]); statements and assignments are rejected
+//! 6. **Accessibility checks**: HTML templates are checked against the
+//!    [`crate::a11y`] rules; problems become [`IR::warnings`]
+//!
+//! [`lint_html`] is a separate, opt-in pass that reports markup browsers
+//! would restructure, such as `<div>` inside `<p>`.
 //!
 //! # Usage
 //!
@@ -26,6 +33,8 @@
 
 use crate::ast::*;
 use crate::error::Result;
+use crate::style::ComponentStyle;
+use crate::warning::CompileWarning;
 use std::collections::HashSet;
 
 /// Intermediate Representation of a transformed template.
@@ -34,6 +43,8 @@ use std::collections::HashSet;
 /// It tracks script blocks, template body, and component dependencies.
 #[derive(Debug, Clone)]
 pub struct IR {
+    /// `{#use}` imports, bound before the module script.
+    pub uses: Vec<UseImport>,
     /// Module-level script (runs once when loaded).
     pub module_script: Option<ScriptBlock>,
     /// Component script (runs on each render).
@@ -42,6 +53,12 @@ pub struct IR {
     pub body: Vec<IRNode>,
     /// Set of component names used in this template.
     pub components: HashSet<String>,
+    /// Output mode the template is rendered in.
+    pub mode: OutputMode,
+    /// Scoped CSS of the template's top-level `<style>` blocks, if any.
+    pub style: Option<ComponentStyle>,
+    /// Problems that do not stop compilation, such as accessibility issues.
+    pub warnings: Vec<CompileWarning>,
 }
 
 /// A node in the transformed intermediate representation.
@@ -85,7 +102,37 @@ pub enum IRNode {
         /// If true, preserve whitespace.
         sensitive: bool,
     },
-    /// Local constant declaration `{@local}`.
+    /// An async value block `{#await}`.
+    AwaitNode {
+        /// Expression producing the awaited value.
+        expression: Expression,
+        /// Nodes to render while pending.
+        pending: Vec<IRNode>,
+        /// Variable name for the resolved value.
+        then_id: Option<String>,
+        /// Nodes to render when resolved.
+        then_branch: Option<Vec<IRNode>>,
+        /// Variable name for the error.
+        catch_id: Option<String>,
+        /// Nodes to render when resolving fails.
+        catch_branch: Option<Vec<IRNode>>,
+    },
+    /// A custom block `{#name args}...{/name}`.
+    CustomBlock {
+        /// The block name.
+        name: String,
+        /// Expression text after the name, if any.
+        args: Option<Expression>,
+        /// Nodes inside the block.
+        children: Vec<IRNode>,
+        /// Location of the opening tag.
+        span: Span,
+        /// Lua code from the registered directive, set by
+        /// [`BlockDirectives::expand`](crate::directives::BlockDirectives::expand).
+        lua: Option<String>,
+    },
+    /// Local constant declaration `{@const}` / `{@local}`, scoped to the
+    /// enclosing block.
     LocalConst {
         /// The variable name.
         name: String,
@@ -109,6 +156,37 @@ pub enum IRNode {
         attributes: Vec<IRAttribute>,
         /// Children to pass (None if no children).
         children: Option<Vec<IRNode>>,
+        /// Location of the opening tag.
+        span: Span,
+    },
+    /// Dynamic component `<luat:component this={expr}>`, whose module is the
+    /// value of `this` at render time.
+    DynamicComponent {
+        /// Expression evaluating to the component module.
+        this: Expression,
+        /// Props passed to the component.
+        attributes: Vec<IRAttribute>,
+        /// Children to pass (None if no children).
+        children: Option<Vec<IRNode>>,
+    },
+    /// Snippet declaration, rendered through a function taking the writer
+    /// followed by the parameters.
+    Snippet {
+        /// The snippet name.
+        name: String,
+        /// Parameter names.
+        params: Vec<String>,
+        /// Snippet body.
+        body: Vec<IRNode>,
+    },
+    /// Snippet render tag.
+    RenderSnippet {
+        /// Expression evaluating to the snippet.
+        callee: Expression,
+        /// Argument list text, if any.
+        args: Option<Expression>,
+        /// If true, no error when the snippet is nil.
+        optional: bool,
     },
     /// Children slot render directive.
     RenderChildren {
@@ -161,17 +239,166 @@ pub enum IRAttributeValue {
 ///
 /// # Errors
 ///
-/// Returns an error if transformation fails (e.g., invalid `{@local}` placement).
+/// Returns an error if transformation fails (e.g., invalid `{@local}` placement,
+/// or a statement such as `{x = 1}` where an expression is expected).
 pub fn transform_ast(ast: TemplateAST) -> Result<IR> {
+    check_expressions(&ast.body, ast.path.as_deref())?;
+
+    // An explicit directive wins over the file extension
+    let mode = ast
+        .mode
+        .or_else(|| ast.path.as_deref().and_then(OutputMode::from_path))
+        .unwrap_or_default();
+
+    let warnings = match mode {
+        OutputMode::Html => crate::a11y::check(&ast.body),
+        _ => Vec::new(),
+    };
+    let (style, body) = extract_component_style(ast.body, mode)?;
     let mut components = HashSet::new();
-    let body = transform_nodes(ast.body, &mut components, false)?;
+    let body = transform_nodes(body, &mut components, false)?;
 
-    Ok(IR {
+    let mut ir = IR {
+        uses: ast.uses,
         module_script: ast.module_script,
-        regular_script: ast.regular_script,
+        regular_script: ast.regular_script.map(|mut script| {
+            script.content = crate::script_processor::expand_prop_declarations(&script.content);
+            script
+        }),
         body,
         components,
-    })
+        mode,
+        style,
+        warnings,
+    };
+    crate::fold::fold_constants(&mut ir);
+    Ok(ir)
+}
+
+/// Takes the component styles out of the top-level nodes of an HTML template.
+///
+/// Returns the scoped CSS of every top-level `<style>` block without the
+/// `global` attribute, and the remaining nodes.
+fn extract_component_style(nodes: Vec<Node>, mode: OutputMode) -> Result<(Option<ComponentStyle>, Vec<Node>)> {
+    if mode != OutputMode::Html {
+        return Ok((None, nodes));
+    }
+
+    let mut css = Vec::new();
+    let mut body = Vec::new();
+    for node in nodes {
+        match node {
+            Node::StyleBlock { attributes, content } if !has_global_attribute(&attributes) => css.push(content),
+            node => body.push(node),
+        }
+    }
+
+    let css = css.join("\n");
+    let style = if css.trim().is_empty() {
+        None
+    } else {
+        Some(ComponentStyle::new(&css)?)
+    };
+    Ok((style, body))
+}
+
+fn has_global_attribute(attributes: &[Attribute]) -> bool {
+    attributes
+        .iter()
+        .any(|attr| matches!(attr, Attribute::Named { name, .. } if name == "global"))
+}
+
+/// Checks that every template expression is a single Lua expression.
+fn check_expressions(nodes: &[Node], path: Option<&str>) -> Result<()> {
+    for node in nodes {
+        match node {
+            Node::MustacheNode { expression }
+            | Node::RawHtml { expression }
+            | Node::LocalConst { expression, .. } => check_expression(expression, path)?,
+            Node::IfBlock { condition, then_branch, else_branch }
+            | Node::SensitiveIfBlock { condition, then_branch, else_branch } => {
+                check_expression(condition, path)?;
+                check_expressions(then_branch, path)?;
+                if let Some(else_nodes) = else_branch {
+                    check_expressions(else_nodes, path)?;
+                }
+            }
+            Node::EachBlock { list_expr, body, empty, .. }
+            | Node::SensitiveEachBlock { list_expr, body, empty, .. } => {
+                check_expression(list_expr, path)?;
+                check_expressions(body, path)?;
+                if let Some(empty_nodes) = empty {
+                    check_expressions(empty_nodes, path)?;
+                }
+            }
+            Node::AwaitBlock { expression, pending, then_branch, catch_branch, .. } => {
+                check_expression(expression, path)?;
+                check_expressions(pending, path)?;
+                for branch in [then_branch, catch_branch].into_iter().flatten() {
+                    check_expressions(branch, path)?;
+                }
+            }
+            Node::CustomBlock { args, children, .. } => {
+                if let Some(args) = args {
+                    check_expression(args, path)?;
+                }
+                check_expressions(children, path)?;
+            }
+            Node::Snippet { body, .. } => check_expressions(body, path)?,
+            Node::RenderSnippet { callee, .. } => check_expression(callee, path)?,
+            Node::ElementNode { attributes, children, .. }
+            | Node::ComponentNode { attributes, children, .. } => {
+                check_attribute_expressions(attributes, path)?;
+                check_expressions(children, path)?;
+            }
+            Node::StyleBlock { attributes, .. } => check_attribute_expressions(attributes, path)?,
+            Node::HtmlComment { children } => check_expressions(children, path)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn check_attribute_expressions(attributes: &[Attribute], path: Option<&str>) -> Result<()> {
+    for attr in attributes {
+        match attr {
+            Attribute::Named { value, .. } => match value {
+                AttributeValue::Dynamic(expr)
+                | AttributeValue::RawHtml(expr)
+                | AttributeValue::Shorthand(expr) => check_expression(expr, path)?,
+                AttributeValue::Static(_) | AttributeValue::BooleanTrue => {}
+            },
+            Attribute::Spread(expr) => check_expression(expr, path)?,
+        }
+    }
+    Ok(())
+}
+
+fn check_expression(expr: &Expression, path: Option<&str>) -> Result<()> {
+    let Err(err) = crate::expression::validate_expression(&expr.content) else {
+        return Ok(());
+    };
+
+    // Position of the offending token within the template
+    let before = &expr.content[..err.offset.min(expr.content.len())];
+    let (line, column) = match before.rfind('\n') {
+        Some(newline) => (
+            expr.span.line + before.matches('\n').count(),
+            before.len() - newline,
+        ),
+        None => (expr.span.line, expr.span.column + before.len()),
+    };
+    let location = match path {
+        Some(path) => format!("{}:{}:{}", path, line, column),
+        None => format!("line {}, column {}", line, column),
+    };
+
+    Err(crate::error::LuatError::TransformError(format!(
+        "Invalid expression `{}` at {}: {}",
+        expr.content.trim(),
+        location,
+        err
+    )))
 }
 
 fn transform_nodes(
@@ -219,11 +446,12 @@ fn transform_node(
             }))
         }
 
-        Node::LocalConst { name, expression } => {
+        Node::LocalConst { name, expression, tag } => {
             if !in_block {
-                return Err(crate::error::LuatError::TransformError(
-                    "{@local} is only allowed as an immediate child of a block".to_string(),
-                ));
+                return Err(crate::error::LuatError::TransformError(format!(
+                    "{{@{}}} is only allowed as an immediate child of a block",
+                    tag
+                )));
             }
             Ok(Some(IRNode::LocalConst { name, expression }))
         }
@@ -292,8 +520,39 @@ fn transform_node(
             }))
         }
         
-        Node::ElementNode { tag, attributes, children } => {
+        Node::AwaitBlock { expression, pending, then_id, then_branch, catch_id, catch_branch } => {
+            let pending_ir = transform_nodes(pending, components, true)?;
+            let then_ir = match then_branch {
+                Some(then_nodes) => Some(transform_nodes(then_nodes, components, true)?),
+                None => None,
+            };
+            let catch_ir = match catch_branch {
+                Some(catch_nodes) => Some(transform_nodes(catch_nodes, components, true)?),
+                None => None,
+            };
+
+            Ok(Some(IRNode::AwaitNode {
+                expression,
+                pending: pending_ir,
+                then_id,
+                then_branch: then_ir,
+                catch_id,
+                catch_branch: catch_ir,
+            }))
+        }
+
+        Node::CustomBlock { name, args, children, span } => {
+            let children_ir = transform_nodes(children, components, true)?;
+            Ok(Some(IRNode::CustomBlock { name, args, children: children_ir, span, lua: None }))
+        }
+
+        Node::ElementNode { tag, attributes, children, .. } if tag == DYNAMIC_COMPONENT_TAG => {
+            transform_dynamic_component(attributes, children, components).map(Some)
+        }
+
+        Node::ElementNode { tag, attributes, children, .. } => {
             let ir_attributes = transform_attributes(attributes)?;
+            check_class_directives(&tag, &ir_attributes)?;
             let ir_children = transform_nodes(children, components, false)?;
             
             Ok(Some(IRNode::ElementNode {
@@ -303,21 +562,18 @@ fn transform_node(
             }))
         }
         
-        Node::ComponentNode { name, attributes, children } => {
+        Node::ComponentNode { name, attributes, children, span } => {
             // Insert the full component name to preserve path information
             components.insert(name.clone());
 
             let ir_attributes = transform_attributes(attributes)?;
-            let ir_children = if children.is_empty() {
-                None
-            } else {
-                Some(transform_nodes(children, components, true)?)
-            };
-            
+            let ir_children = transform_component_children(children, components)?;
+
             Ok(Some(IRNode::ComponentNode {
                 name,
                 attributes: ir_attributes,
                 children: ir_children,
+                span,
             }))
         }
 
@@ -334,13 +590,162 @@ fn transform_node(
         Node::RenderChildren { optional } => {
             Ok(Some(IRNode::RenderChildren { optional }))
         }
+
+        Node::Snippet { name, params, body } => {
+            let body_ir = transform_nodes(body, components, true)?;
+            Ok(Some(IRNode::Snippet { name, params, body: body_ir }))
+        }
+
+        Node::RenderSnippet { callee, args, optional } => {
+            Ok(Some(IRNode::RenderSnippet { callee, args, optional }))
+        }
         
+        // Styles that are not component styles are rendered in place
+        Node::StyleBlock { attributes, content } => {
+            let attributes = attributes
+                .into_iter()
+                .filter(|attr| !matches!(attr, Attribute::Named { name, .. } if name == "global"))
+                .collect();
+            Ok(Some(IRNode::ElementNode {
+                tag: "style".to_string(),
+                attributes: transform_attributes(attributes)?,
+                children: vec![IRNode::TextNode { content }],
+            }))
+        }
+
         Node::ScriptAny { tag: _, content } => {
             Ok(Some(IRNode::ScriptAny { content }))
         },
     }
 }
 
+/// Checks that every `class:name` directive of an element has a condition.
+fn check_class_directives(tag: &str, attributes: &[IRAttribute]) -> Result<()> {
+    for attr in attributes {
+        let IRAttribute::Named { name, value } = attr else {
+            continue;
+        };
+        let Some(class) = name.strip_prefix("class:") else {
+            continue;
+        };
+        if class.is_empty() {
+            return Err(crate::error::LuatError::TransformError(format!(
+                "Missing class name in `class:` directive on <{}>",
+                tag
+            )));
+        }
+        if !matches!(value, IRAttributeValue::Dynamic(_)) {
+            return Err(crate::error::LuatError::TransformError(format!(
+                "`{}` on <{}> takes a condition in braces: {}={{condition}}",
+                name, tag, name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Tag of the dynamic component element.
+const DYNAMIC_COMPONENT_TAG: &str = "luat:component";
+
+fn transform_dynamic_component(
+    attributes: Vec<Attribute>,
+    children: Vec<Node>,
+    components: &mut HashSet<String>,
+) -> Result<IRNode> {
+    let mut this = None;
+    let mut props = Vec::new();
+    for attr in attributes {
+        match attr {
+            Attribute::Named { name, value } if name == "this" => match value {
+                AttributeValue::Dynamic(expr) | AttributeValue::Shorthand(expr) => this = Some(expr),
+                _ => {
+                    return Err(crate::error::LuatError::TransformError(format!(
+                        "<{}> expects `this={{expression}}` evaluating to a component module",
+                        DYNAMIC_COMPONENT_TAG
+                    )))
+                }
+            },
+            attr => props.push(attr),
+        }
+    }
+    let this = this.ok_or_else(|| {
+        crate::error::LuatError::TransformError(format!(
+            "<{}> requires a `this={{expression}}` attribute",
+            DYNAMIC_COMPONENT_TAG
+        ))
+    })?;
+
+    let ir_children = transform_component_children(children, components)?;
+    Ok(IRNode::DynamicComponent {
+        this,
+        attributes: transform_attributes(props)?,
+        children: ir_children,
+    })
+}
+
+/// Transforms the children of a component tag, or returns `None` if it has
+/// none.
+///
+/// Direct children with a `slot="name"` attribute fill a named slot: they
+/// become a snippet passed as the prop `name`, which the component renders
+/// with `{@render props.name?.()}`. A `<fragment slot="name">` passes just
+/// its content; any other element is passed whole, without the attribute.
+fn transform_component_children(
+    children: Vec<Node>,
+    components: &mut HashSet<String>,
+) -> Result<Option<Vec<IRNode>>> {
+    if children.is_empty() {
+        return Ok(None);
+    }
+
+    let mut slots: Vec<String> = Vec::new();
+    let mut nodes = Vec::with_capacity(children.len());
+    for child in children {
+        let Node::ElementNode { tag, mut attributes, children, span } = child else {
+            nodes.push(child);
+            continue;
+        };
+        let slot_index = attributes
+            .iter()
+            .position(|attr| matches!(attr, Attribute::Named { name, .. } if name == "slot"));
+        let Some(slot_index) = slot_index else {
+            nodes.push(Node::ElementNode { tag, attributes, children, span });
+            continue;
+        };
+
+        let name = match attributes.remove(slot_index) {
+            Attribute::Named { value: AttributeValue::Static(name), .. } if is_slot_name(&name) => name,
+            _ => {
+                return Err(crate::error::LuatError::TransformError(format!(
+                    "<{}> at line {}: `slot` expects a static name made of letters, digits and underscores, e.g. slot=\"footer\"",
+                    tag, span.line
+                )))
+            }
+        };
+        if slots.contains(&name) {
+            return Err(crate::error::LuatError::TransformError(format!(
+                "Slot `{}` is filled twice (line {})",
+                name, span.line
+            )));
+        }
+        slots.push(name.clone());
+
+        let body = if tag == "fragment" {
+            children
+        } else {
+            vec![Node::ElementNode { tag, attributes, children, span }]
+        };
+        nodes.push(Node::Snippet { name, params: Vec::new(), body });
+    }
+
+    Ok(Some(transform_nodes(nodes, components, true)?))
+}
+
+fn is_slot_name(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
 fn transform_attributes(attributes: Vec<Attribute>) -> Result<Vec<IRAttribute>> {
     let mut ir_attributes = Vec::new();
 
@@ -392,10 +797,19 @@ fn validate_ir_nodes(nodes: &[IRNode]) -> Result<()> {
                     validate_ir_nodes(empty_nodes)?;
                 }
             }
-            IRNode::ElementNode { children, .. } => {
+            IRNode::AwaitNode { pending, then_branch, catch_branch, .. } => {
+                validate_ir_nodes(pending)?;
+                for branch in [then_branch, catch_branch].into_iter().flatten() {
+                    validate_ir_nodes(branch)?;
+                }
+            }
+            IRNode::ElementNode { children, .. }
+            | IRNode::CustomBlock { children, .. }
+            | IRNode::Snippet { body: children, .. } => {
                 validate_ir_nodes(children)?;
             }
-            IRNode::ComponentNode { children: Some(child_nodes), .. } => {
+            IRNode::ComponentNode { children: Some(child_nodes), .. }
+            | IRNode::DynamicComponent { children: Some(child_nodes), .. } => {
                 validate_ir_nodes(child_nodes)?;
             }
             IRNode::ComponentNode { children: None, .. } => {}
@@ -406,6 +820,168 @@ fn validate_ir_nodes(nodes: &[IRNode]) -> Result<()> {
     Ok(())
 }
 
+/// An HTML validity problem found by [`lint_html`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HtmlIssue {
+    /// Tag of the offending element.
+    pub tag: String,
+    /// What is wrong, e.g. `<div> cannot be inside <p>`.
+    pub message: String,
+}
+
+impl std::fmt::Display for HtmlIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Elements that close an open `<p>`, so they cannot be inside one.
+const CLOSES_PARAGRAPH: &[&str] = &[
+    "address", "article", "aside", "blockquote", "details", "dialog", "div", "dl", "fieldset",
+    "figcaption", "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "header",
+    "hgroup", "hr", "main", "menu", "nav", "ol", "p", "pre", "section", "table", "ul",
+];
+
+/// Interactive elements, which cannot be inside `<a>` or `<button>`.
+const INTERACTIVE: &[&str] = &["a", "button", "details", "embed", "iframe", "label", "select", "textarea"];
+
+/// Elements that may only appear directly inside one of the given parents.
+const REQUIRED_PARENTS: &[(&str, &[&str])] = &[
+    ("li", &["ul", "ol", "menu"]),
+    ("dt", &["dl", "div"]),
+    ("dd", &["dl", "div"]),
+    ("tr", &["table", "thead", "tbody", "tfoot"]),
+    ("td", &["tr"]),
+    ("th", &["tr"]),
+    ("thead", &["table"]),
+    ("tbody", &["table"]),
+    ("tfoot", &["table"]),
+    ("caption", &["table"]),
+    ("colgroup", &["table"]),
+    ("option", &["select", "datalist", "optgroup"]),
+    ("optgroup", &["select"]),
+    ("summary", &["details"]),
+];
+
+/// Elements whose children must be of the given tags (or `<script>` and
+/// `<template>`, which are allowed anywhere).
+const ALLOWED_CHILDREN: &[(&str, &[&str])] = &[
+    ("ul", &["li"]),
+    ("ol", &["li"]),
+    ("table", &["caption", "colgroup", "thead", "tbody", "tfoot", "tr"]),
+    ("thead", &["tr"]),
+    ("tbody", &["tr"]),
+    ("tfoot", &["tr"]),
+    ("tr", &["td", "th"]),
+    ("select", &["option", "optgroup", "hr"]),
+];
+
+/// Checks the element structure of a template against the HTML content
+/// model.
+///
+/// This is an opt-in lint: markup such as `<p><div></div></p>` or `<a>` in
+/// `<a>` compiles, but browsers repair it into a different DOM than the
+/// template shows. Also reported are elements outside their required parent
+/// (`<li>` outside a list) and attributes given twice on one element.
+///
+/// Elements at the top of a template or directly inside a component or
+/// snippet may end up in any parent, so their parent is not checked.
+/// Templates not in HTML mode have no issues.
+pub fn lint_html(ir: &IR) -> Vec<HtmlIssue> {
+    let mut issues = Vec::new();
+    if ir.mode == OutputMode::Html {
+        lint_html_nodes(&ir.body, &mut Vec::new(), None, &mut issues);
+    }
+    issues
+}
+
+/// Lints `nodes`, rendered inside the elements `ancestors` (outermost
+/// first); `parent` is the enclosing element, if known.
+fn lint_html_nodes<'a>(
+    nodes: &'a [IRNode],
+    ancestors: &mut Vec<&'a str>,
+    parent: Option<&'a str>,
+    issues: &mut Vec<HtmlIssue>,
+) {
+    for node in nodes {
+        match node {
+            IRNode::ElementNode { tag, attributes, children, .. } => {
+                lint_html_element(tag, attributes, ancestors, parent, issues);
+                ancestors.push(tag);
+                lint_html_nodes(children, ancestors, Some(tag), issues);
+                ancestors.pop();
+            }
+            IRNode::IfNode { then_branch, else_branch, .. } => {
+                lint_html_nodes(then_branch, ancestors, parent, issues);
+                if let Some(else_nodes) = else_branch {
+                    lint_html_nodes(else_nodes, ancestors, parent, issues);
+                }
+            }
+            IRNode::EachNode { body, empty, .. } => {
+                lint_html_nodes(body, ancestors, parent, issues);
+                if let Some(empty_nodes) = empty {
+                    lint_html_nodes(empty_nodes, ancestors, parent, issues);
+                }
+            }
+            IRNode::AwaitNode { pending, then_branch, catch_branch, .. } => {
+                lint_html_nodes(pending, ancestors, parent, issues);
+                for branch in [then_branch, catch_branch].into_iter().flatten() {
+                    lint_html_nodes(branch, ancestors, parent, issues);
+                }
+            }
+            IRNode::CustomBlock { children, .. } => lint_html_nodes(children, ancestors, parent, issues),
+            IRNode::ComponentNode { children: Some(children), .. }
+            | IRNode::DynamicComponent { children: Some(children), .. } => {
+                lint_html_nodes(children, ancestors, None, issues);
+            }
+            IRNode::Snippet { body, .. } => lint_html_nodes(body, &mut Vec::new(), None, issues),
+            _ => {}
+        }
+    }
+}
+
+fn lint_html_element(
+    tag: &str,
+    attributes: &[IRAttribute],
+    ancestors: &[&str],
+    parent: Option<&str>,
+    issues: &mut Vec<HtmlIssue>,
+) {
+    let mut issue = |message: String| issues.push(HtmlIssue { tag: tag.to_string(), message });
+
+    if CLOSES_PARAGRAPH.contains(&tag) && ancestors.contains(&"p") {
+        issue(format!("<{}> cannot be inside <p>", tag));
+    }
+    if INTERACTIVE.contains(&tag) {
+        if let Some(outer) = ancestors.iter().rev().find(|outer| matches!(**outer, "a" | "button")) {
+            issue(format!("<{}> cannot be inside <{}>", tag, outer));
+        }
+    }
+    if matches!(tag, "form" | "label") && ancestors.contains(&tag) {
+        issue(format!("<{}> cannot be inside <{}>", tag, tag));
+    }
+    if let Some(parent) = parent {
+        if let Some((_, parents)) = REQUIRED_PARENTS.iter().find(|(child, _)| *child == tag) {
+            if !parents.contains(&parent) {
+                issue(format!("<{}> cannot be a child of <{}>", tag, parent));
+            }
+        } else if let Some((_, allowed)) = ALLOWED_CHILDREN.iter().find(|(element, _)| *element == parent) {
+            if !allowed.contains(&tag) && !matches!(tag, "script" | "template") {
+                issue(format!("<{}> cannot be a child of <{}>", tag, parent));
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for attr in attributes {
+        if let IRAttribute::Named { name, .. } = attr {
+            if !seen.insert(name.to_ascii_lowercase()) {
+                issue(format!("<{}> has the attribute `{}` more than once", tag, name));
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -477,4 +1053,49 @@ mod tests {
             _ => panic!("Expected IfNode"),
         }
     }
+
+    #[test]
+    fn test_transform_await_block() {
+        let source = r#"{#await props.user}Loading{:then user}<p>{user.name}</p>{:catch err}Failed{/await}"#;
+        let ast = parse_template(source).unwrap();
+
+        let ir = transform_ast(ast).unwrap();
+        match &ir.body[0] {
+            IRNode::AwaitNode { expression, pending, then_id, then_branch, catch_id, catch_branch } => {
+                assert_eq!(expression.content, "props.user");
+                assert_eq!(pending.len(), 1);
+                assert_eq!(then_id.as_deref(), Some("user"));
+                assert_eq!(then_branch.as_ref().map(Vec::len), Some(1));
+                assert_eq!(catch_id.as_deref(), Some("err"));
+                assert_eq!(catch_branch.as_ref().map(Vec::len), Some(1));
+            }
+            _ => panic!("Expected AwaitNode"),
+        }
+    }
+
+    #[test]
+    fn test_lint_html() {
+        let source = r#"<p>{#if props.x}<div>a</div>{/if}</p>
+<a href="/"><button>b</button></a>
+<ul><li>c</li><div>d</div></ul>
+<table><tr><span>e</span></tr></table>
+<li>top level</li>
+<Card><li>slot</li></Card>
+<img src="a.png" alt="" src="b.png" />"#;
+        let ir = transform_ast(parse_template(source).unwrap()).unwrap();
+        let messages: Vec<String> = lint_html(&ir).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "<div> cannot be inside <p>",
+                "<button> cannot be inside <a>",
+                "<div> cannot be a child of <ul>",
+                "<span> cannot be a child of <tr>",
+                "<img> has the attribute `src` more than once",
+            ]
+        );
+
+        let valid = r#"<ul>{#each props.items as item}<li><a href={item.url}>{item.name}</a></li>{/each}</ul>"#;
+        assert!(lint_html(&transform_ast(parse_template(valid).unwrap()).unwrap()).is_empty());
+    }
 }
@@ -0,0 +1,136 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Compile warnings.
+//!
+//! Some problems in a template do not stop it from compiling but deserve
+//! attention, such as the [accessibility rules](crate::a11y). The transform
+//! stage collects them in [`IR::warnings`](crate::IR::warnings). The engine
+//! logs the warnings of every template it compiles and keeps them until
+//! they are collected with [`Engine::take_warnings`](crate::Engine::take_warnings):
+//!
+//! ```rust,ignore
+//! let module = engine.compile_entry("pages/index.luat")?;
+//! for warning in engine.take_warnings() {
+//!     eprintln!("warning: {}", warning);
+//! }
+//! ```
+//!
+//! Warnings are produced when a template is compiled, so a template served
+//! from the cache reports nothing. A template compiled again replaces the
+//! warnings of its earlier compile, and an engine keeps at most
+//! [`MAX_WARNINGS`], dropping the oldest, so warnings nobody takes don't
+//! pile up in a long-running server.
+
+use crate::ast::Span;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// A problem found while compiling a template.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompileWarning {
+    /// Identifier of the rule, e.g. `a11y-missing-alt`.
+    pub code: String,
+    /// Description of the problem.
+    pub message: String,
+    /// Template the warning is about, if known.
+    pub file: Option<String>,
+    /// Location in the template.
+    pub span: Span,
+}
+
+impl CompileWarning {
+    /// Creates a warning without a file.
+    pub fn new(code: impl Into<String>, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            code: code.into(),
+            message: message.into(),
+            file: None,
+            span,
+        }
+    }
+}
+
+impl fmt::Display for CompileWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        write!(
+            f,
+            "{}:{}: {} ({})",
+            self.span.line, self.span.column, self.message, self.code
+        )
+    }
+}
+
+/// Most warnings an engine keeps until they are taken.
+pub const MAX_WARNINGS: usize = 1000;
+
+/// Warnings recorded by an engine and not yet taken.
+///
+/// Clones share the list, so the module searcher records into the engine's.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CompileWarnings {
+    warnings: Arc<Mutex<Vec<CompileWarning>>>,
+}
+
+impl CompileWarnings {
+    /// Logs and records the warnings of the template `file`, replacing those
+    /// of its earlier compile.
+    pub(crate) fn record(&self, file: &str, warnings: &[CompileWarning]) {
+        let mut recorded = self.warnings.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        recorded.retain(|warning| warning.file.as_deref() != Some(file));
+        for warning in warnings {
+            let warning = CompileWarning {
+                file: Some(file.to_string()),
+                ..warning.clone()
+            };
+            tracing::warn!("{}", warning);
+            recorded.push(warning);
+        }
+        let excess = recorded.len().saturating_sub(MAX_WARNINGS);
+        recorded.drain(..excess);
+    }
+
+    /// Removes and returns every recorded warning.
+    pub(crate) fn take(&self) -> Vec<CompileWarning> {
+        std::mem::take(&mut *self.warnings.lock().unwrap_or_else(std::sync::PoisonError::into_inner))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warning(code: &str) -> CompileWarning {
+        CompileWarning::new(code, "message", Span::new(0, 0, 1, 1))
+    }
+
+    #[test]
+    fn test_recompile_replaces_warnings() {
+        let warnings = CompileWarnings::default();
+        warnings.record("a.luat", &[warning("one"), warning("two")]);
+        warnings.record("b.luat", &[warning("three")]);
+        warnings.record("a.luat", &[]);
+
+        let taken = warnings.take();
+        assert_eq!(taken.len(), 1);
+        assert_eq!(taken[0].file.as_deref(), Some("b.luat"));
+        assert!(warnings.take().is_empty());
+    }
+
+    #[test]
+    fn test_oldest_warnings_dropped() {
+        let warnings = CompileWarnings::default();
+        for i in 0..=MAX_WARNINGS {
+            warnings.record(&format!("{}.luat", i), &[warning("code")]);
+        }
+
+        let taken = warnings.take();
+        assert_eq!(taken.len(), MAX_WARNINGS);
+        assert_eq!(taken[0].file.as_deref(), Some("1.luat"));
+    }
+}
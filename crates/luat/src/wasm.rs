@@ -48,9 +48,28 @@ impl WasmEngine {
     #[wasm_bindgen(constructor)]
     pub fn new(cache_size: Option<usize>) -> Result<WasmEngine, JsValue> {
         let cache_size = cache_size.unwrap_or(100);
-        let resolver = MemoryResourceResolver::new();
-        let cache = Box::new(MemoryCache::new(cache_size));
+        Self::with_cache(Box::new(MemoryCache::new(cache_size)))
+    }
 
+    /// Create a WASM engine whose compiled modules persist in the Origin
+    /// Private File System across sessions
+    #[cfg(feature = "opfs")]
+    #[wasm_bindgen(js_name = withOpfsCache)]
+    pub async fn with_opfs_cache(cache_size: Option<usize>, directory: Option<String>) -> Result<WasmEngine, JsValue> {
+        let cache_size = cache_size.unwrap_or(100);
+        let directory = directory.unwrap_or_else(|| crate::opfs_cache::DEFAULT_OPFS_DIRECTORY.to_string());
+        let cache = crate::opfs_cache::OpfsCache::open(&directory, cache_size)
+            .await
+            .map_err(|e| JsValue::from_str(&format!("Failed to open OPFS cache: {}", e)))?;
+        cache
+            .preload()
+            .await
+            .map_err(|e| JsValue::from_str(&format!("Failed to load OPFS cache: {}", e)))?;
+        Self::with_cache(Box::new(cache))
+    }
+
+    fn with_cache(cache: Box<dyn crate::cache::Cache>) -> Result<WasmEngine, JsValue> {
+        let resolver = MemoryResourceResolver::new();
         let engine = Engine::new(resolver.clone(), cache)
             .map_err(|e| JsValue::from_str(&format!("Failed to create engine: {}", e)))?;
 
@@ -140,6 +159,21 @@ impl WasmEngine {
         Ok(html)
     }
 
+    /// Render a template and return the raw output bytes
+    ///
+    /// Use this instead of `render` when the output may be binary or
+    /// not valid UTF-8. Returns a `Uint8Array` on the JavaScript side.
+    #[wasm_bindgen(js_name = renderBytes)]
+    pub fn render_bytes(&self, entry: &str, context: JsValue) -> Result<Vec<u8>, JsValue> {
+        let module = self.engine.compile_entry(entry)
+            .map_err(|e| JsValue::from_str(&format!("Compilation error: {}", e)))?;
+
+        let lua_context = self.js_to_lua_value(&context)?;
+
+        self.engine.render_bytes(&module, &lua_context)
+            .map_err(|e| JsValue::from_str(&format!("Render error: {}", e)))
+    }
+
     /// Render a template synchronously (alias for render)
     #[wasm_bindgen(js_name = renderSync)]
     pub fn render_sync(&self, entry: &str, context: JsValue) -> Result<String, JsValue> {
@@ -0,0 +1,161 @@
+// Copyright 2019-2026 Maravilla Labs, operated by SOLUTAS GmbH, Switzerland
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Whitespace collapsing.
+//!
+//! With [`Engine::set_collapse_whitespace`](crate::Engine::set_collapse_whitespace),
+//! every run of whitespace in the static text of an HTML template becomes a
+//! single space, which browsers render the same way. Text inside `<pre>`,
+//! `<textarea>`, `<script>` and `<style>` keeps its whitespace, as does the
+//! output of expressions.
+//!
+//! For control over single spots, tags take trim markers: `{-` removes the
+//! whitespace before a tag and `-}` the whitespace after it:
+//!
+//! ```text
+//! <ul>
+//!   {-#each items as item -}
+//!     <li>{item}</li>
+//!   {-/each -}
+//! </ul>
+//! ```
+
+use crate::codegen::OutputMode;
+use crate::transform::{IRNode, IR};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Elements whose text is rendered with its whitespace.
+const PRESERVING_ELEMENTS: &[&str] = &["pre", "textarea", "script", "style"];
+
+/// Whether an engine collapses whitespace.
+///
+/// Clones share the setting, so the module searcher follows the engine's.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CollapseWhitespace {
+    enabled: Arc<AtomicBool>,
+}
+
+impl CollapseWhitespace {
+    pub(crate) fn set(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub(crate) fn get(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Collapses the whitespace of `ir` if enabled.
+    pub(crate) fn apply(&self, ir: &mut IR) {
+        if self.get() {
+            collapse_whitespace(ir);
+        }
+    }
+}
+
+/// Collapses the whitespace in the static text of an HTML template.
+pub(crate) fn collapse_whitespace(ir: &mut IR) {
+    if ir.mode == OutputMode::Html {
+        collapse_nodes(&mut ir.body);
+    }
+}
+
+fn collapse_nodes(nodes: &mut [IRNode]) {
+    for node in nodes {
+        match node {
+            IRNode::TextNode { content } => *content = collapse(content),
+            IRNode::ElementNode { tag, children, .. } => {
+                if !PRESERVING_ELEMENTS.contains(&tag.as_str()) {
+                    collapse_nodes(children);
+                }
+            }
+            IRNode::IfNode { then_branch, else_branch, .. } => {
+                collapse_nodes(then_branch);
+                if let Some(else_nodes) = else_branch {
+                    collapse_nodes(else_nodes);
+                }
+            }
+            IRNode::EachNode { body, empty, .. } => {
+                collapse_nodes(body);
+                if let Some(empty_nodes) = empty {
+                    collapse_nodes(empty_nodes);
+                }
+            }
+            IRNode::AwaitNode { pending, then_branch, catch_branch, .. } => {
+                collapse_nodes(pending);
+                for branch in [then_branch, catch_branch].into_iter().flatten() {
+                    collapse_nodes(branch);
+                }
+            }
+            IRNode::CustomBlock { children, .. }
+            | IRNode::HtmlComment { children }
+            | IRNode::Snippet { body: children, .. } => collapse_nodes(children),
+            IRNode::ComponentNode { children: Some(children), .. }
+            | IRNode::DynamicComponent { children: Some(children), .. } => collapse_nodes(children),
+            _ => {}
+        }
+    }
+}
+
+/// Replaces every run of whitespace in `text` with a single space.
+fn collapse(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_whitespace = false;
+    for c in text.chars() {
+        if c.is_ascii_whitespace() {
+            if !in_whitespace {
+                out.push(' ');
+            }
+            in_whitespace = true;
+        } else {
+            out.push(c);
+            in_whitespace = false;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Node;
+    use crate::{generate_lua_code, parse_template, transform_ast};
+
+    fn compile(source: &str) -> String {
+        let mut ir = transform_ast(parse_template(source).unwrap()).unwrap();
+        collapse_whitespace(&mut ir);
+        generate_lua_code(ir, "test").unwrap()
+    }
+
+    #[test]
+    fn test_collapse_whitespace() {
+        let lua_code = compile("<div>\n    <p>a   b</p>\n    <pre>  x\n  y</pre>\n</div>");
+        assert!(lua_code.contains(r#"__write(" ")"#), "{}", lua_code);
+        assert!(lua_code.contains(r#"__write("a b")"#), "{}", lua_code);
+        assert!(lua_code.contains(r#"__write("  x\n  y")"#), "{}", lua_code);
+    }
+
+    #[test]
+    fn test_trim_markers() {
+        let ast = parse_template("<ul>\n  {-#each props.items as item -}\n    <li>{item}</li>\n  {-/each -}\n</ul>").unwrap();
+        let Node::ElementNode { children, .. } = &ast.body[0] else {
+            panic!("expected <ul>");
+        };
+        assert!(matches!(children.as_slice(), [Node::EachBlock { .. }]), "{:?}", children);
+
+        let ast = parse_template("<p>a \n {-@html props.raw -}  b</p>").unwrap();
+        let Node::ElementNode { children, .. } = &ast.body[0] else {
+            panic!("expected <p>");
+        };
+        assert!(
+            matches!(children.as_slice(), [Node::TextNode { content: a }, Node::RawHtml { .. }, Node::TextNode { content: b }] if a == "a" && b == "b"),
+            "{:?}",
+            children
+        );
+
+        // `{-x}` is still a negative number
+        let ast = parse_template("<p> {-1} </p>").unwrap();
+        assert_eq!(ast.body.len(), 1);
+    }
+}
@@ -1,6 +1,13 @@
 -- API endpoint: /api/hello
 -- Returns JSON instead of HTML
 
+-- Documentation for `luat openapi`
+spec = {
+    tags = { "examples" },
+    GET = { summary = "Say hello" },
+    POST = { summary = "Say hello to a name from the form" },
+}
+
 function GET(ctx)
     return {
         status = 200,